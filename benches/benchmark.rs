@@ -2,8 +2,10 @@ use std::path::{Path, PathBuf};
 use std::time::Duration;
 
 use criterion::black_box;
-use criterion::{criterion_group, criterion_main, Criterion, ParameterizedBenchmark};
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
 use image::{DynamicImage, GenericImageView, GrayImage, Luma};
+use seamcarving::testimg::{self, Blob};
+use seamcarving::Pos;
 
 fn open_image() -> DynamicImage {
     let path: PathBuf = [Path::new(file!()).parent().unwrap(), Path::new("input.png")]
@@ -36,24 +38,28 @@ fn criterion_benchmark(c: &mut Criterion) {
     });
 
     let (w, h) = (160, 90);
-    c.bench(
-        &format!("{w}x{h} to ({w}-i)x{h}", w = w, h = h),
-        ParameterizedBenchmark::new(
-            "seamcarving",
-            move |b, &i| {
-                let gray_img = black_box(gray_bench_image(w, h));
-                b.iter(|| seamcarving::resize(&gray_img, w - i, h))
-            },
-            vec![w / 16, w / 8, w / 6, w / 4, w / 2, 2 * w / 3],
-        ).with_function(
-            "imageproc",
-            move |b, &i| {
-                let gray_img = black_box(gray_bench_image(w, h));
-                b.iter(||
-                    imageproc::seam_carving::shrink_width(&gray_img, w - i))
-            },
-        ),
-    );
+    let mut group = c.benchmark_group(format!("{w}x{h} to ({w}-i)x{h}", w = w, h = h));
+    for &i in &[w / 16, w / 8, w / 6, w / 4, w / 2, 2 * w / 3] {
+        group.bench_with_input(BenchmarkId::new("seamcarving", i), &i, |b, &i| {
+            let gray_img = black_box(gray_bench_image(w, h));
+            b.iter(|| seamcarving::resize(&gray_img, w - i, h))
+        });
+        group.bench_with_input(BenchmarkId::new("imageproc", i), &i, |b, &i| {
+            let gray_img = black_box(gray_bench_image(w, h));
+            b.iter(||
+                imageproc::seam_carving::shrink_width(&gray_img, w - i))
+        });
+    }
+    group.finish();
+
+    c.bench_function("salient blobs 160x90 to 120x90", |b| {
+        let blobs = [
+            Blob { center: Pos(40, 45), radius: 20 },
+            Blob { center: Pos(120, 30), radius: 12 },
+        ];
+        let img = black_box(testimg::salient_blobs(160, 90, &blobs, 1));
+        b.iter(|| seamcarving::resize(&img, 120, 90))
+    });
 }
 
 criterion_group! {
@@ -0,0 +1,290 @@
+//! Paints masks over an image and shows the carved image as it is updated.
+//!
+//! ```text
+//! cargo run --release --features examples-gui --example mask_painter -- examples/butterfly.png
+//! ```
+//!
+//! Pixels painted with the protect brush are kept, the ones painted with the remove brush
+//! are carved out first, then seams are removed until the image has the chosen width.
+//! Seams are removed a few at a time from a [Carvable], which is only rebuilt
+//! when the masks change or when the image gets wider.
+use std::env;
+use std::error::Error;
+use std::time::{Duration, Instant};
+
+use eframe::egui;
+use image::{GenericImageView, GrayImage, Luma, Rgba, RgbaImage};
+use seamcarving::{image_view_to_buffer, Carvable};
+
+/// Time spent removing seams on each frame, so that painting stays responsive
+const CARVING_TIME_PER_FRAME: Duration = Duration::from_millis(15);
+
+/// Colors mixed with the pixels of the painted masks
+const PROTECT_TINT: [u8; 3] = [0, 200, 0];
+const REMOVE_TINT: [u8; 3] = [230, 0, 0];
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Brush {
+    Protect,
+    Remove,
+    Erase,
+}
+
+struct MaskPainter {
+    image: &'static RgbaImage,
+    protect: GrayImage,
+    remove: GrayImage,
+    brush: Brush,
+    radius: u32,
+    width: u32,
+    /// The image being carved, or None when the masks changed since it was created
+    carvable: Option<Carvable<'static, RgbaImage>>,
+    /// Width of the carvable image once the remove mask was carved out
+    uncarved_width: u32,
+    /// Whether the masks and the carved image changed since they were last shown
+    masks_changed: bool,
+    carved_changed: bool,
+    error: Option<String>,
+    painted: Option<egui::TextureHandle>,
+    carved: Option<egui::TextureHandle>,
+}
+
+impl MaskPainter {
+    fn new(image: RgbaImage) -> MaskPainter {
+        let (width, height) = image.dimensions();
+        MaskPainter {
+            image: Box::leak(Box::new(image)),
+            protect: GrayImage::new(width, height),
+            remove: GrayImage::new(width, height),
+            brush: Brush::Protect,
+            radius: 8,
+            width,
+            carvable: None,
+            uncarved_width: width,
+            masks_changed: true,
+            carved_changed: true,
+            error: None,
+            painted: None,
+            carved: None,
+        }
+    }
+
+    /// A carvable image with the current masks, from which the remove mask was carved out
+    fn start_carving(&self) -> Result<Carvable<'static, RgbaImage>, String> {
+        let mut carvable = Carvable::new(self.image);
+        if self.protect.pixels().any(|p| p.0[0] != 0) {
+            carvable.protect(&self.protect);
+        }
+        let widest = self
+            .remove
+            .rows()
+            .map(|row| row.filter(|p| p.0[0] != 0).count())
+            .max()
+            .unwrap_or(0);
+        if widest > 0 {
+            // Like relocate_object: seams only go through the removed pixels, until none is left
+            carvable.set_valid_region(&self.remove);
+            for _ in 0..widest {
+                carvable.try_remove_seam().map_err(|e| e.to_string())?;
+            }
+            let (width, height) = carvable.result().dimensions();
+            carvable.set_valid_region(&GrayImage::from_pixel(width, height, Luma([255])));
+        }
+        Ok(carvable)
+    }
+
+    /// Removes seams until the carved image has the chosen width, or time is up
+    fn carve(&mut self) {
+        let current = |carvable: &Carvable<RgbaImage>| carvable.result().width();
+        if let Some(carvable) = &self.carvable {
+            if current(carvable) < self.width.min(self.uncarved_width) {
+                // Seams cannot be put back
+                self.carvable = None;
+            }
+        }
+        if self.carvable.is_none() {
+            if self.error.is_some() {
+                // Until the masks change
+                return;
+            }
+            match self.start_carving() {
+                Ok(carvable) => {
+                    self.uncarved_width = current(&carvable);
+                    self.carvable = Some(carvable);
+                }
+                Err(error) => {
+                    self.error = Some(error);
+                    return;
+                }
+            }
+            self.carved_changed = true;
+        }
+        if self.error.is_some() {
+            return;
+        }
+        let carvable = self
+            .carvable
+            .as_mut()
+            .expect("the carvable image was just created");
+        let start = Instant::now();
+        while current(carvable) > self.width && start.elapsed() < CARVING_TIME_PER_FRAME {
+            if let Err(error) = carvable.try_remove_seam() {
+                self.error = Some(error.to_string());
+                self.width = current(carvable);
+                break;
+            }
+            self.carved_changed = true;
+        }
+    }
+
+    fn is_carving(&self) -> bool {
+        let carved = self.carvable.as_ref().map(|c| c.result().width());
+        self.error.is_none() && carved.is_none_or(|carved| carved > self.width)
+    }
+
+    /// Paints a disk of the brush around the given pixel of the image
+    fn paint(&mut self, (cx, cy): (i64, i64)) {
+        let r = i64::from(self.radius);
+        let (width, height) = self.image.dimensions();
+        for y in (cy - r).max(0)..(cy + r + 1).min(i64::from(height)) {
+            for x in (cx - r).max(0)..(cx + r + 1).min(i64::from(width)) {
+                if (x - cx).pow(2) + (y - cy).pow(2) > r * r {
+                    continue;
+                }
+                let (x, y) = (x as u32, y as u32);
+                let (protect, remove) = match self.brush {
+                    Brush::Protect => (255, 0),
+                    Brush::Remove => (0, 255),
+                    Brush::Erase => (0, 0),
+                };
+                self.protect.put_pixel(x, y, Luma([protect]));
+                self.remove.put_pixel(x, y, Luma([remove]));
+            }
+        }
+        self.masks_changed();
+    }
+
+    fn masks_changed(&mut self) {
+        self.carvable = None;
+        self.masks_changed = true;
+        self.error = None;
+    }
+
+    /// The image with the masks painted over it
+    fn painted_image(&self) -> RgbaImage {
+        let mut painted = self.image.clone();
+        for (x, y, pixel) in painted.enumerate_pixels_mut() {
+            let tint = if self.protect.get_pixel(x, y).0[0] != 0 {
+                PROTECT_TINT
+            } else if self.remove.get_pixel(x, y).0[0] != 0 {
+                REMOVE_TINT
+            } else {
+                continue;
+            };
+            let Rgba([r, g, b, a]) = *pixel;
+            let mix = |c: u8, t: u8| ((u16::from(c) + u16::from(t)) / 2) as u8;
+            *pixel = Rgba([mix(r, tint[0]), mix(g, tint[1]), mix(b, tint[2]), a]);
+        }
+        painted
+    }
+
+    fn controls(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.selectable_value(&mut self.brush, Brush::Protect, "Protect");
+            ui.selectable_value(&mut self.brush, Brush::Remove, "Remove");
+            ui.selectable_value(&mut self.brush, Brush::Erase, "Erase");
+            ui.add(egui::Slider::new(&mut self.radius, 1..=64).text("brush radius"));
+            let max_width = self.image.width();
+            ui.add(egui::Slider::new(&mut self.width, 1..=max_width).text("width"));
+            if ui.button("Clear masks").clicked() {
+                let (width, height) = self.image.dimensions();
+                self.protect = GrayImage::new(width, height);
+                self.remove = GrayImage::new(width, height);
+                self.masks_changed();
+            }
+            if self.is_carving() {
+                ui.spinner();
+            }
+            if let Some(error) = &self.error {
+                ui.colored_label(egui::Color32::RED, error);
+            }
+        });
+    }
+}
+
+impl eframe::App for MaskPainter {
+    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        egui::TopBottomPanel::top("controls").show(ctx, |ui| self.controls(ui));
+        egui::CentralPanel::default().show(ctx, |ui| {
+            let (width, height) = self.image.dimensions();
+            let scale = (ui.available_width() / 2. / width as f32)
+                .min(ui.available_height() / height as f32)
+                .min(1.);
+            let size = egui::vec2(width as f32, height as f32) * scale;
+            if self.masks_changed {
+                let painted = self.painted_image();
+                texture(ctx, &mut self.painted, "painted", &painted);
+                self.masks_changed = false;
+            }
+            let painted = self.painted.as_ref().map(|painted| painted.id());
+            ui.horizontal_top(|ui| {
+                let Some(painted) = painted else { return };
+                let image = egui::Image::new((painted, size)).sense(egui::Sense::drag());
+                let response = ui.add(image);
+                if let Some(pointer) = response.interact_pointer_pos() {
+                    let pixel = (pointer - response.rect.min) / scale;
+                    self.paint((pixel.x as i64, pixel.y as i64));
+                }
+                if let Some(carved) = &self.carved {
+                    let [carved_width, carved_height] = carved.size();
+                    let carved_size = egui::vec2(carved_width as f32, carved_height as f32);
+                    ui.add(egui::Image::new((carved.id(), carved_size * scale)));
+                }
+            });
+        });
+        self.carve();
+        if self.carved_changed {
+            if let Some(carvable) = &self.carvable {
+                let carved = image_view_to_buffer(carvable.result());
+                texture(ctx, &mut self.carved, "carved", &carved);
+                self.carved_changed = false;
+            }
+        }
+        if self.is_carving() {
+            ctx.request_repaint();
+        }
+    }
+}
+
+/// Uploads an image to the texture, creating it if needed
+fn texture<'t>(
+    ctx: &egui::Context,
+    texture: &'t mut Option<egui::TextureHandle>,
+    name: &str,
+    img: &RgbaImage,
+) -> &'t egui::TextureHandle {
+    let size = [img.width() as usize, img.height() as usize];
+    let image = egui::ColorImage::from_rgba_unmultiplied(size, img.as_raw());
+    let options = egui::TextureOptions::NEAREST;
+    match texture {
+        Some(texture) => {
+            texture.set(image, options);
+            texture
+        }
+        None => texture.insert(ctx.load_texture(name, image, options)),
+    }
+}
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let path = env::args()
+        .nth(1)
+        .ok_or("Usage: mask_painter /path/to/image.png")?;
+    let image = seamcarving::open_image(&path)?.to_rgba8();
+    let painter = MaskPainter::new(image);
+    eframe::run_native(
+        "seamcarving mask painter",
+        eframe::NativeOptions::default(),
+        Box::new(|_creation_context| Ok(Box::new(painter))),
+    )?;
+    Ok(())
+}
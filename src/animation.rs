@@ -0,0 +1,154 @@
+use std::time::Duration;
+
+use image::imageops::replace;
+use image::{GenericImageView, ImageBuffer, Pixel, Rgba, RgbaImage};
+
+use crate::{image_view_to_buffer, Carver, SeamError};
+
+/// An image in the middle of its carving, see [record_animation]
+#[derive(Clone, Debug)]
+#[non_exhaustive]
+pub struct Frame<P: Pixel> {
+    /// The image once `seams` seams were removed from it
+    pub image: ImageBuffer<P, Vec<P::Subpixel>>,
+    /// Number of vertical and horizontal seams removed so far
+    pub seams: u32,
+}
+
+/// Resizes an image with the given carver, and returns the image as it was
+/// every `every_n_seams` removed seams: the first frame is the original image,
+/// and the last one the resized image.
+///
+/// When the image is [carved in stripes](Carver::max_memory), only these two frames are
+/// returned. The frames can be written as an animation with [encode_animation].
+/// [Carver::resize_with_snapshots] hands them over one at a time instead of keeping them all.
+///
+/// ```
+/// let img = image::RgbaImage::from_fn(20, 10, |x, y| image::Rgba([(x * y) as u8, 0, 0, 255]));
+/// let carver = seamcarving::Carver::new().width(14);
+/// let frames = seamcarving::record_animation(&carver, &img, 2).unwrap();
+/// let seams: Vec<u32> = frames.iter().map(|frame| frame.seams).collect();
+/// assert_eq!(seams, vec![0, 2, 4, 6]);
+/// assert_eq!(frames[3].image.dimensions(), (14, 10));
+/// ```
+pub fn record_animation<IMG: GenericImageView>(
+    carver: &Carver,
+    img: &IMG,
+    every_n_seams: u32,
+) -> Result<Vec<Frame<IMG::Pixel>>, SeamError>
+where
+    <IMG as GenericImageView>::Pixel: 'static,
+{
+    let mut frames = vec![Frame {
+        image: image_view_to_buffer(img),
+        seams: 0,
+    }];
+    let (resized, report) =
+        carver.resize_with_snapshots(img, every_n_seams, |frame| frames.push(frame))?;
+    let seams = report.seams.0 + report.seams.1;
+    // The resized image may also have been scaled, or blended differently
+    if frames.last().map(|frame| frame.seams) == Some(seams) && seams > 0 {
+        frames.pop();
+    }
+    frames.push(Frame {
+        image: resized,
+        seams,
+    });
+    Ok(frames)
+}
+
+/// Receives the frames of an animation, to encode them in an animated format such as APNG
+/// with the encoder of the application's choice, see [encode_animation]
+pub trait AnimationEncoder {
+    /// The error returned when a frame cannot be encoded
+    type Error;
+
+    /// Called once before the frames, with the dimensions of the animation
+    /// and its number of frames
+    fn start(&mut self, dimensions: (u32, u32), frames: u32) -> Result<(), Self::Error>;
+
+    /// Called for each frame, in order, with how long it is shown
+    fn add_frame(&mut self, frame: &RgbaImage, delay: Duration) -> Result<(), Self::Error>;
+
+    /// Called once after the last frame
+    fn finish(&mut self) -> Result<(), Self::Error>;
+}
+
+/// Gives frames recorded by [record_animation] to an encoder,
+/// each one shown for `delay`, except the last one which is shown twice as long.
+///
+/// All the frames of an APNG have the dimensions of the first one, so each frame is drawn
+/// in the top left corner of a transparent image of the dimensions of the original image.
+pub fn encode_animation<E: AnimationEncoder>(
+    frames: &[Frame<Rgba<u8>>],
+    delay: Duration,
+    encoder: &mut E,
+) -> Result<(), E::Error> {
+    let (width, height) = frames.iter().fold((0, 0), |(width, height), frame| {
+        let (w, h) = frame.image.dimensions();
+        (width.max(w), height.max(h))
+    });
+    encoder.start((width, height), frames.len() as u32)?;
+    for (i, frame) in frames.iter().enumerate() {
+        let mut canvas = RgbaImage::new(width, height);
+        replace(&mut canvas, &frame.image, 0, 0);
+        let last = i + 1 == frames.len();
+        encoder.add_frame(&canvas, if last { delay * 2 } else { delay })?;
+    }
+    encoder.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Keeps the frames, like an encoder writing them to a file would
+    #[derive(Default)]
+    struct Recorder {
+        dimensions: Option<(u32, u32)>,
+        frames: Vec<(RgbaImage, Duration)>,
+        finished: bool,
+    }
+
+    impl AnimationEncoder for Recorder {
+        type Error = ();
+
+        fn start(&mut self, dimensions: (u32, u32), _frames: u32) -> Result<(), ()> {
+            self.dimensions = Some(dimensions);
+            Ok(())
+        }
+
+        fn add_frame(&mut self, frame: &RgbaImage, delay: Duration) -> Result<(), ()> {
+            self.frames.push((frame.clone(), delay));
+            Ok(())
+        }
+
+        fn finish(&mut self) -> Result<(), ()> {
+            self.finished = true;
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn encodes_frames_of_the_same_dimensions() {
+        let img = RgbaImage::from_fn(16, 12, |x, y| {
+            Rgba([(x * 15) as u8, (y * 20) as u8, 0, 255])
+        });
+        let carver = Carver::new().width(10).height(9);
+        let frames = record_animation(&carver, &img, 4).unwrap();
+        let seams: Vec<u32> = frames.iter().map(|frame| frame.seams).collect();
+        assert_eq!(seams, vec![0, 4, 8, 9]);
+        assert_eq!(frames[3].image, carver.resize(&img).unwrap());
+        let mut recorder = Recorder::default();
+        encode_animation(&frames, Duration::from_millis(100), &mut recorder).unwrap();
+        assert_eq!(recorder.dimensions, Some((16, 12)));
+        assert!(recorder.finished);
+        assert_eq!(recorder.frames.len(), 4);
+        let (last, delay) = &recorder.frames[3];
+        assert_eq!(last.dimensions(), (16, 12));
+        assert_eq!(*delay, Duration::from_millis(200));
+        // Outside of the resized image, the frame is transparent
+        assert_eq!(last.get_pixel(12, 10), &Rgba([0, 0, 0, 0]));
+        assert_eq!(last.get_pixel(0, 0), frames[3].image.get_pixel(0, 0));
+    }
+}
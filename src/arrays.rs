@@ -0,0 +1,118 @@
+use std::convert::TryFrom;
+
+use image::{GenericImageView, ImageBuffer, Luma, Pixel, Primitive};
+use ndarray::{Array2, Array3, AsArray, Ix2, Ix3};
+
+use crate::SeamError;
+
+/// Converts an array of shape `(height, width, channels)`, the layout used by numpy,
+/// into an image that can be carved.
+///
+/// The array can have any memory layout, such as the transposed views numpy often returns.
+/// Fails with [SeamError::ChannelMismatch] if the number of channels
+/// is not the one of the pixel type.
+///
+/// ```
+/// use image::Rgb;
+/// let array = ndarray::Array3::<u8>::zeros((4, 6, 3));
+/// let img = seamcarving::from_ndarray::<Rgb<u8>, _>(&array).unwrap();
+/// assert_eq!(img.dimensions(), (6, 4));
+/// ```
+pub fn from_ndarray<'a, P, A>(array: A) -> Result<ImageBuffer<P, Vec<P::Subpixel>>, SeamError>
+where
+    P: Pixel + 'static,
+    P::Subpixel: 'a,
+    A: AsArray<'a, P::Subpixel, Ix3>,
+{
+    let array = array.into();
+    let (height, width, channels) = array.dim();
+    if channels != usize::from(P::CHANNEL_COUNT) {
+        return Err(SeamError::ChannelMismatch);
+    }
+    let (width, height) = image_size(width, height)?;
+    let subpixels = array.iter().copied().collect();
+    ImageBuffer::from_raw(width, height, subpixels).ok_or(SeamError::SizeMismatch)
+}
+
+/// Converts an array of shape `(height, width)` into a grayscale image,
+/// see [from_ndarray]
+pub fn from_gray_ndarray<'a, T, A>(array: A) -> Result<ImageBuffer<Luma<T>, Vec<T>>, SeamError>
+where
+    T: Primitive + 'static,
+    A: AsArray<'a, T, Ix2>,
+{
+    let array = array.into();
+    let (height, width) = array.dim();
+    let (width, height) = image_size(width, height)?;
+    let subpixels = array.iter().copied().collect();
+    ImageBuffer::from_raw(width, height, subpixels).ok_or(SeamError::SizeMismatch)
+}
+
+/// Converts an image, such as the result of [resize](crate::resize),
+/// into an array of shape `(height, width, channels)`
+///
+/// ```
+/// let img = image::RgbaImage::new(6, 4);
+/// let resized = seamcarving::resize(&img, 5, 4);
+/// assert_eq!(seamcarving::to_ndarray(&resized).dim(), (4, 5, 4));
+/// ```
+pub fn to_ndarray<IMG: GenericImageView>(img: &IMG) -> Array3<<IMG::Pixel as Pixel>::Subpixel> {
+    let (width, height) = img.dimensions();
+    let channels = usize::from(<IMG::Pixel as Pixel>::CHANNEL_COUNT);
+    Array3::from_shape_fn((height as usize, width as usize, channels), |(y, x, c)| {
+        img.get_pixel(x as u32, y as u32).channels()[c]
+    })
+}
+
+/// Converts a grayscale image into an array of shape `(height, width)`, see [to_ndarray]
+pub fn to_gray_ndarray<T, IMG>(img: &IMG) -> Array2<T>
+where
+    T: Primitive + 'static,
+    IMG: GenericImageView<Pixel = Luma<T>>,
+{
+    let (width, height) = img.dimensions();
+    Array2::from_shape_fn((height as usize, width as usize), |(y, x)| {
+        img.get_pixel(x as u32, y as u32).0[0]
+    })
+}
+
+fn image_size(width: usize, height: usize) -> Result<(u32, u32), SeamError> {
+    let width = u32::try_from(width).map_err(|_| SeamError::SizeMismatch)?;
+    let height = u32::try_from(height).map_err(|_| SeamError::SizeMismatch)?;
+    Ok((width, height))
+}
+
+#[cfg(test)]
+mod tests {
+    use image::{GrayImage, Rgb, RgbImage};
+    use ndarray::{Array, Array3, ShapeBuilder};
+
+    use super::*;
+
+    #[test]
+    fn converts_arrays_of_any_layout() {
+        let img = RgbImage::from_fn(5, 3, |x, y| Rgb([x as u8, y as u8, (x * y) as u8]));
+        let array = to_ndarray(&img);
+        assert_eq!(array.dim(), (3, 5, 3));
+        assert_eq!(array[[2, 4, 2]], 8);
+        let fortran = Array3::from_shape_vec((3, 5, 3).f(), array.t().iter().copied().collect());
+        let fortran = fortran.unwrap();
+        assert_eq!(fortran, array);
+        assert_eq!(from_ndarray::<Rgb<u8>, _>(&fortran).unwrap(), img);
+        assert_eq!(
+            from_ndarray::<Luma<u8>, _>(&array),
+            Err(SeamError::ChannelMismatch)
+        );
+    }
+
+    #[test]
+    fn converts_gray_arrays() {
+        let array = Array::from_shape_fn((4, 7), |(y, x)| (x * 10 + y) as u16);
+        let img = from_gray_ndarray(array.view()).unwrap();
+        assert_eq!(img.dimensions(), (7, 4));
+        assert_eq!(img.get_pixel(6, 3).0, [63]);
+        assert_eq!(to_gray_ndarray(&img), array);
+        let resized = crate::resize(&GrayImage::new(7, 4), 5, 4);
+        assert_eq!(to_gray_ndarray(&resized).dim(), (4, 5));
+    }
+}
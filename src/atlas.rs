@@ -0,0 +1,214 @@
+use image::{GenericImageView, GrayImage, Luma, Pixel};
+use num_traits::Zero;
+
+use crate::blend::carved_to_buffer;
+use crate::mask::Rect;
+use crate::pos::Pos;
+use crate::{image_view_to_buffer, Carvable, Carver, PixelBuffer, Rotated, SeamError};
+
+/// Shrinks a texture atlas, given the rectangles of its sub-textures,
+/// and returns the new atlas with the new rectangles of the sub-textures, in the same order.
+///
+/// Without a carver, the sub-textures are kept intact, and only the space between them
+/// is carved away. With a carver, each sub-texture is first resized with it,
+/// [width](Carver::width) and [height](Carver::height) being relative to the sub-texture,
+/// and the space it frees is carved away too.
+/// A margin of `padding` pixels is kept around each sub-texture, to avoid texture bleeding.
+///
+/// Sub-textures must not overlap. Fails with [SeamError::OutOfBounds] if one of them
+/// is not entirely inside the atlas.
+///
+/// ```
+/// use seamcarving::{retarget_atlas, Rect};
+/// let atlas = image::RgbaImage::new(64, 32);
+/// let sprites = [Rect { x: 4, y: 4, width: 16, height: 16 }, Rect { x: 40, y: 8, width: 8, height: 8 }];
+/// let (atlas, sprites) = retarget_atlas(&atlas, &sprites, None, 1).unwrap();
+/// assert_eq!(sprites[0].width, 16);
+/// assert!(atlas.width() < 64);
+/// ```
+pub fn retarget_atlas<IMG: GenericImageView>(
+    atlas: &IMG,
+    regions: &[Rect],
+    carver: Option<&Carver>,
+    padding: u32,
+) -> Result<(PixelBuffer<IMG::Pixel>, Vec<Rect>), SeamError>
+where
+    IMG::Pixel: 'static,
+{
+    let (width, height) = atlas.dimensions();
+    let mut regions = regions.to_vec();
+    if let Some(r) = regions
+        .iter()
+        .find(|r| r.x + r.width > width || r.y + r.height > height)
+    {
+        let (x, y) = (r.x + r.width, r.y + r.height);
+        return Err(SeamError::OutOfBounds { x, y });
+    }
+    let mut atlas = image_view_to_buffer(atlas);
+    if let Some(carver) = carver {
+        for region in &mut regions {
+            *region = resize_region(&mut atlas, *region, carver)?;
+        }
+    }
+    let wide = carve_gutters(&atlas, &mut regions, padding)?;
+    regions.iter_mut().for_each(|r| *r = transposed(*r));
+    let carved = carve_gutters(&Rotated(&wide), &mut regions, padding)?;
+    regions.iter_mut().for_each(|r| *r = transposed(*r));
+    Ok((image_view_to_buffer(&Rotated(&carved)), regions))
+}
+
+/// Resizes a sub-texture where it is, and clears the pixels it does not cover anymore
+fn resize_region<P: Pixel + 'static>(
+    atlas: &mut PixelBuffer<P>,
+    region: Rect,
+    carver: &Carver,
+) -> Result<Rect, SeamError> {
+    let sub_texture = atlas.view(region.x, region.y, region.width, region.height);
+    let resized = carver.resize(&sub_texture)?;
+    let cleared = P::from_slice(&vec![P::Subpixel::zero(); P::CHANNEL_COUNT as usize]).to_owned();
+    for y in 0..region.height {
+        for x in 0..region.width {
+            let pixel = if x < resized.width() && y < resized.height() {
+                *resized.get_pixel(x, y)
+            } else {
+                cleared
+            };
+            atlas.put_pixel(region.x + x, region.y + y, pixel);
+        }
+    }
+    Ok(Rect {
+        width: resized.width(),
+        height: resized.height(),
+        ..region
+    })
+}
+
+/// Removes vertical seams until every seam goes through a sub-texture or its padding,
+/// and moves the sub-textures accordingly
+fn carve_gutters<IMG: GenericImageView>(
+    img: &IMG,
+    regions: &mut [Rect],
+    padding: u32,
+) -> Result<PixelBuffer<IMG::Pixel>, SeamError>
+where
+    IMG::Pixel: 'static,
+{
+    let size = img.dimensions();
+    let protected: Vec<Rect> = regions.iter().map(|r| r.grow(padding, size)).collect();
+    let mask = GrayImage::from_fn(size.0, size.1, |x, y| {
+        Luma([if protected.iter().any(|r| r.contains(x, y)) {
+            255
+        } else {
+            0
+        }])
+    });
+    let mut carvable = Carvable::new(img);
+    carvable.forbid(&mask);
+    // An empty atlas is carved down to a single column
+    for _ in 1..size.0 {
+        let seam = match carvable.remove_next_seam() {
+            Ok(seam) => seam,
+            Err(SeamError::Blocked) => break,
+            Err(e) => return Err(e),
+        };
+        for region in regions.iter_mut() {
+            // The seam passes entirely on one side of each sub-texture
+            let on_the_left = seam
+                .iter()
+                .find(|p| p.1 == region.y)
+                .is_some_and(|&Pos(x, _)| x < region.x);
+            if on_the_left {
+                region.x -= 1;
+            }
+        }
+    }
+    Ok(carved_to_buffer(&carvable.carved, false))
+}
+
+fn transposed(r: Rect) -> Rect {
+    Rect {
+        x: r.y,
+        y: r.x,
+        width: r.height,
+        height: r.width,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use image::{GenericImage, GenericImageView, Rgba, RgbaImage};
+
+    use super::*;
+    use crate::Dimension;
+
+    fn sprite(seed: u32) -> RgbaImage {
+        RgbaImage::from_fn(8, 8, |x, y| {
+            Rgba([
+                (x * 31 + seed) as u8,
+                (y * 17) as u8,
+                (x * y + seed) as u8,
+                255,
+            ])
+        })
+    }
+
+    fn atlas() -> (RgbaImage, [Rect; 2]) {
+        let mut atlas = RgbaImage::from_fn(40, 24, |x, y| Rgba([0, 0, (x ^ y) as u8, 255]));
+        let regions = [
+            Rect {
+                x: 3,
+                y: 2,
+                width: 8,
+                height: 8,
+            },
+            Rect {
+                x: 24,
+                y: 12,
+                width: 8,
+                height: 8,
+            },
+        ];
+        for (i, r) in regions.iter().enumerate() {
+            atlas.copy_from(&sprite(i as u32 * 50), r.x, r.y).unwrap();
+        }
+        (atlas, regions)
+    }
+
+    #[test]
+    fn removes_gutters_only() {
+        let (atlas, regions) = atlas();
+        let (carved, moved) = retarget_atlas(&atlas, &regions, None, 2).unwrap();
+        assert!(carved.width() < 40 && carved.height() < 24);
+        for (i, r) in moved.iter().enumerate() {
+            assert_eq!((r.width, r.height), (8, 8));
+            let view = carved.view(r.x, r.y, r.width, r.height).to_image();
+            assert_eq!(view, sprite(i as u32 * 50));
+        }
+        // The padding is kept between the sub-textures and the borders
+        assert!(moved[0].x >= 2 && moved[0].y >= 2);
+        assert!(moved[1].x + 8 + 2 <= carved.width());
+        let out_of_bounds = Rect {
+            x: 36,
+            y: 0,
+            width: 8,
+            height: 8,
+        };
+        assert!(retarget_atlas(&atlas, &[out_of_bounds], None, 0).is_err());
+    }
+
+    #[test]
+    fn resizes_sub_textures() {
+        let (atlas, regions) = atlas();
+        let carver = Carver::new().width(Dimension::Percent(50));
+        let (carved, moved) = retarget_atlas(&atlas, &regions, Some(&carver), 1).unwrap();
+        let (gutters_only, _) = retarget_atlas(&atlas, &regions, None, 1).unwrap();
+        assert!(moved.iter().all(|r| (r.width, r.height) == (4, 8)));
+        assert!(carved.width() < gutters_only.width());
+        let expected = carver.resize(&sprite(0)).unwrap();
+        let r = moved[0];
+        assert_eq!(
+            carved.view(r.x, r.y, r.width, r.height).to_image(),
+            expected
+        );
+    }
+}
@@ -0,0 +1,165 @@
+use std::cmp::Reverse;
+
+use image::GenericImageView;
+
+use crate::energy::energy_fn;
+use crate::pos::Pos;
+use crate::{aspect_dimensions, max_pos};
+
+/// The size to reach, when the dimensions are chosen by [choose_axes]
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+#[non_exhaustive]
+pub enum Goal {
+    /// At most this number of pixels, with any aspect ratio
+    Pixels(u64),
+    /// The given `(width, height)` ratio, like [retarget_aspect](crate::retarget_aspect)
+    Aspect(u32, u32),
+}
+
+/// The dimensions chosen by [choose_axes]
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+#[non_exhaustive]
+pub struct AxisChoice {
+    pub width: u32,
+    pub height: u32,
+    /// Number of vertical and horizontal seams to remove
+    pub seams: (u32, u32),
+    /// Estimation of the total energy of the removed seams, comparable between choices
+    pub estimated_energy: u64,
+}
+
+/// Chooses how many vertical and horizontal seams to remove from an image to reach a goal,
+/// by estimating how much energy carving each axis would remove:
+/// a landscape with a flat sky loses rows, a row of people loses the space between them.
+///
+/// The energy of the `k` cheapest seams of an axis is estimated from the energy of the image,
+/// as the mean of the `k` cheapest pixels of each line, that no seam can beat,
+/// and of the `k` cheapest straight lines, that are seams. Only [Goal::Pixels] lets
+/// both axes be carved: an aspect ratio is reached by carving the axis that is too long.
+/// Images of width or height 0 are kept as they are.
+///
+/// ```
+/// use seamcarving::{choose_axes, Carver, Goal};
+/// // A flat sky above a busy ground
+/// let img = image::GrayImage::from_fn(30, 20, |x, y| {
+///     image::Luma([if y < 10 { 0 } else { (x * y * 37) as u8 }])
+/// });
+/// let choice = choose_axes(&img, Goal::Pixels(400));
+/// assert_eq!((choice.width, choice.height), (30, 13));
+/// let resized = Carver::new().width(choice.width).height(choice.height).resize(&img);
+/// ```
+pub fn choose_axes<IMG: GenericImageView>(img: &IMG, goal: Goal) -> AxisChoice {
+    let (width, height) = img.dimensions();
+    if width == 0 || height == 0 {
+        return AxisChoice {
+            width,
+            height,
+            seams: (0, 0),
+            estimated_energy: 0,
+        };
+    }
+    let energies: Vec<u64> = Pos::iter_in_rect(Pos(0, 0), max_pos(img))
+        .map(|pos| u64::from(energy_fn(img, pos)))
+        .collect();
+    let transposed: Vec<u64> = (0..width as usize)
+        .flat_map(|x| energies.iter().skip(x).step_by(width as usize).copied())
+        .collect();
+    let vertical = seam_costs(&energies, width as usize);
+    let horizontal = seam_costs(&transposed, height as usize);
+    let choice = |w: u32, h: u32| {
+        let seams = (width - w, height - h);
+        // Horizontal seams are removed once the width is carved, so they are shorter
+        let horizontal = horizontal[seams.1 as usize] * u64::from(w) / u64::from(width);
+        AxisChoice {
+            width: w,
+            height: h,
+            seams,
+            estimated_energy: vertical[seams.0 as usize] + horizontal,
+        }
+    };
+    match goal {
+        Goal::Aspect(rw, rh) => {
+            let (w, h) = aspect_dimensions((width, height), (rw, rh));
+            choice(w, h)
+        }
+        Goal::Pixels(pixels) => (1..=width)
+            .filter_map(|w| {
+                let h = (pixels / u64::from(w)).min(u64::from(height)) as u32;
+                Some(choice(w, h)).filter(|_| h > 0)
+            })
+            // Removing fewer pixels for the same energy
+            .min_by_key(|choice| {
+                (
+                    choice.estimated_energy,
+                    Reverse(choice.width * choice.height),
+                )
+            })
+            .unwrap_or_else(|| choice(width.min(1), height.min(1))),
+    }
+}
+
+/// Estimation of the energy of the `k` cheapest vertical seams of an image, for every `k`,
+/// from the energies of its pixels, row by row
+fn seam_costs(energies: &[u64], width: usize) -> Vec<u64> {
+    let mut cheapest_pixels = vec![0; width];
+    let mut columns = vec![0; width];
+    for row in energies.chunks_exact(width.max(1)) {
+        let mut sorted = row.to_vec();
+        sorted.sort_unstable();
+        for x in 0..width {
+            cheapest_pixels[x] += sorted[x];
+            columns[x] += row[x];
+        }
+    }
+    columns.sort_unstable();
+    let mut costs = vec![0; width + 1];
+    let (mut pixels, mut lines) = (0, 0);
+    for k in 0..width {
+        pixels += cheapest_pixels[k];
+        lines += columns[k];
+        costs[k + 1] = (pixels + lines) / 2;
+    }
+    costs
+}
+
+#[cfg(test)]
+mod tests {
+    use image::{GrayImage, Luma};
+
+    use super::*;
+
+    #[test]
+    fn carves_the_cheapest_axis() {
+        // Flat columns on the left, and a checkerboard on the right
+        let img = GrayImage::from_fn(20, 20, |x, y| {
+            Luma([if x < 12 || (x + y) % 2 == 0 { 0 } else { 200 }])
+        });
+        let choice = choose_axes(&img, Goal::Pixels(200));
+        assert_eq!(
+            (choice.width, choice.height, choice.seams),
+            (10, 20, (10, 0))
+        );
+        assert_eq!(choice.estimated_energy, 0);
+        let transposed = GrayImage::from_fn(20, 20, |x, y| *img.get_pixel(y, x));
+        let choice = choose_axes(&transposed, Goal::Pixels(200));
+        assert_eq!((choice.width, choice.height), (20, 10));
+        let choice = choose_axes(&img, Goal::Aspect(2, 1));
+        assert_eq!((choice.width, choice.height), (20, 10));
+        assert!(choice.estimated_energy > 0);
+        let kept = choose_axes(&img, Goal::Pixels(1000));
+        assert_eq!((kept.seams, kept.estimated_energy), ((0, 0), 0));
+        assert_eq!(choose_axes(&img, Goal::Pixels(0)).seams, (19, 19));
+    }
+
+    #[test]
+    fn keeps_empty_images() {
+        for (width, height) in [(0, 0), (0, 4), (4, 0)] {
+            let img = GrayImage::new(width, height);
+            for goal in [Goal::Pixels(0), Goal::Pixels(10), Goal::Aspect(2, 1)] {
+                let choice = choose_axes(&img, goal);
+                assert_eq!((choice.width, choice.height), (width, height));
+                assert_eq!((choice.seams, choice.estimated_energy), ((0, 0), 0));
+            }
+        }
+    }
+}
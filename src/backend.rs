@@ -0,0 +1,111 @@
+#[cfg(feature = "simd")]
+use crate::kernels::is_vectorized;
+#[cfg(feature = "rayon")]
+use crate::threads::Threads;
+#[cfg(feature = "gpu")]
+use crate::gpu::Gpu;
+
+/// Narrowest image whose seams [Backend::Auto] computes on several threads:
+/// narrower rows are not worth splitting
+#[cfg(feature = "rayon")]
+const AUTO_PARALLEL_MIN_COLUMNS: u32 = 1024;
+
+/// Smallest image whose seams [Backend::Auto] computes on several threads
+#[cfg(feature = "rayon")]
+const AUTO_PARALLEL_MIN_PIXELS: u64 = 1 << 20;
+
+/// How the seams of an image are computed, see
+/// [resize_buffer_with_backend](crate::resize_buffer_with_backend).
+///
+/// The backends only change the speed of the computations, never their results.
+/// [Backend::Auto] never chooses the graphics card, which has to be opened beforehand.
+///
+/// ```
+/// use seamcarving::Backend;
+/// // Nothing to compute when no seam is removed
+/// assert!(matches!(Backend::Auto.resolve((640, 480), 0), Backend::Scalar));
+/// ```
+#[derive(Clone, Debug, Default)]
+#[non_exhaustive]
+pub enum Backend {
+    /// One of the other backends, chosen from the size of the image,
+    /// the number of seams to remove, the processor and the threads of the current rayon pool
+    #[default]
+    Auto,
+    /// One position at a time, on the calling thread
+    Scalar,
+    /// Runs of positions with AVX-512 or NEON instructions, on the calling thread.
+    /// Processors that support neither compute one position at a time.
+    #[cfg(feature = "simd")]
+    Simd,
+    /// Wide rows split between the given threads
+    #[cfg(feature = "rayon")]
+    Threads(Threads),
+    /// The energies of the whole image computed on the given graphics card,
+    /// and cumulated on the calling thread as the rows arrive, the energies around
+    /// each removed seam being computed again on the calling thread
+    #[cfg(feature = "gpu")]
+    Gpu(Gpu),
+}
+
+impl Backend {
+    /// The backend used for an image of the given width and height
+    /// from which `seams` seams are removed: this backend itself, unless it is [Backend::Auto].
+    ///
+    /// Large images are split between threads when the current rayon thread pool has several,
+    /// and the others use vector instructions when the processor supports them.
+    #[cfg_attr(not(feature = "rayon"), allow(unused_variables))]
+    pub fn resolve(&self, (width, height): (u32, u32), seams: u32) -> Backend {
+        if !matches!(self, Backend::Auto) {
+            return self.clone();
+        }
+        if seams == 0 {
+            return Backend::Scalar;
+        }
+        #[cfg(feature = "rayon")]
+        {
+            let pixels = u64::from(width) * u64::from(height);
+            if rayon::current_num_threads() > 1
+                && width >= AUTO_PARALLEL_MIN_COLUMNS
+                && pixels >= AUTO_PARALLEL_MIN_PIXELS
+            {
+                return Backend::Threads(Threads::Current);
+            }
+        }
+        #[cfg(feature = "simd")]
+        {
+            if is_vectorized() {
+                return Backend::Simd;
+            }
+        }
+        Backend::Scalar
+    }
+
+    /// Whether the host can run this backend as described, rather than falling back
+    /// to computing one position at a time: false for [Backend::Simd] on processors
+    /// that support neither AVX-512 nor NEON
+    pub fn is_supported(&self) -> bool {
+        match self {
+            #[cfg(feature = "simd")]
+            Backend::Simd => is_vectorized(),
+            _ => true,
+        }
+    }
+}
+
+#[cfg(all(test, feature = "rayon"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn splits_large_images_between_threads() {
+        let pool = rayon::ThreadPoolBuilder::new().num_threads(4).build().unwrap();
+        let resolve = |size, seams| pool.install(|| Backend::Auto.resolve(size, seams));
+        assert!(matches!(resolve((2048, 1024), 100), Backend::Threads(Threads::Current)));
+        assert!(!matches!(resolve((512, 4096), 100), Backend::Threads(_)));
+        assert!(matches!(resolve((2048, 1024), 0), Backend::Scalar));
+        let single = rayon::ThreadPoolBuilder::new().num_threads(1).build().unwrap();
+        let resolved = single.install(|| Backend::Auto.resolve((2048, 1024), 100));
+        assert!(!matches!(resolved, Backend::Threads(_)));
+    }
+}
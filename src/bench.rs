@@ -0,0 +1,121 @@
+//! Measurement of the speed of this crate on the current machine,
+//! to check that a deployment uses the backends it was built for.
+//!
+//! This module is only available when the `std` feature is enabled.
+//!
+//! ```no_run
+//! for throughput in seamcarving::bench::self_test() {
+//!     let (size, speed) = (throughput.size, throughput.megapixels_per_second);
+//!     println!("{:?} {:?}: {:.1} Mpx/s", size, throughput.backend, speed);
+//! }
+//! println!("vector instructions: {}", seamcarving::bench::vectorized());
+//! ```
+use std::time::Instant;
+
+use crate::backend::Backend;
+use crate::buffer::resize_buffer_with_backend;
+#[cfg(feature = "rayon")]
+use crate::threads::Threads;
+
+/// Sizes of the synthetic images carved by [self_test]
+const SIZES: [(u32, u32); 3] = [(320, 240), (1280, 720), (1920, 1080)];
+
+/// Number of vertical and of horizontal seams removed from each synthetic image
+const SEAMS: u32 = 8;
+
+/// How fast a backend resized a synthetic image
+#[derive(Clone, Debug)]
+#[non_exhaustive]
+pub struct Throughput {
+    /// The backend that resized the image
+    pub backend: Backend,
+    /// Width and height of the image
+    pub size: (u32, u32),
+    /// Millions of pixels of the image resized per second
+    pub megapixels_per_second: f64,
+}
+
+/// Carves synthetic images of several sizes with every backend this crate was built with,
+/// and returns their speed, from the smallest image to the largest.
+///
+/// This takes a few seconds.
+pub fn self_test() -> Vec<Throughput> {
+    measure(&SIZES, SEAMS)
+}
+
+/// Whether the processor supports the vector instructions of [Backend::Simd].
+/// This is always false without the `simd` feature.
+pub fn vectorized() -> bool {
+    #[cfg(feature = "simd")]
+    let vectorized = crate::kernels::is_vectorized();
+    #[cfg(not(feature = "simd"))]
+    let vectorized = false;
+    vectorized
+}
+
+/// Removes `seams` seams in both directions from images of the given sizes, with each backend
+fn measure(sizes: &[(u32, u32)], seams: u32) -> Vec<Throughput> {
+    let mut throughputs = Vec::new();
+    for &size in sizes {
+        let pixels = synthetic_image(size);
+        let target = (size.0.saturating_sub(seams), size.1.saturating_sub(seams));
+        for backend in backends() {
+            let start = Instant::now();
+            resize_buffer_with_backend(&pixels, size, target, &backend)
+                .expect("the synthetic image has one pixel per position");
+            let seconds = start.elapsed().as_secs_f64().max(f64::MIN_POSITIVE);
+            let megapixels = f64::from(size.0) * f64::from(size.1) / 1e6;
+            throughputs.push(Throughput {
+                backend,
+                size,
+                megapixels_per_second: megapixels / seconds,
+            });
+        }
+    }
+    throughputs
+}
+
+/// Every backend this crate was built with, except [Backend::Auto]
+fn backends() -> Vec<Backend> {
+    vec![
+        Backend::Scalar,
+        #[cfg(feature = "simd")]
+        Backend::Simd,
+        #[cfg(feature = "rayon")]
+        Backend::Threads(Threads::Current),
+    ]
+}
+
+/// Gradients with some noise, so that seams are neither all tied nor all identical
+fn synthetic_image((width, height): (u32, u32)) -> Vec<[u8; 3]> {
+    let mut state = 0x2545_f491u32;
+    (0..width * height)
+        .map(|i| {
+            state ^= state << 13;
+            state ^= state >> 17;
+            state ^= state << 5;
+            let (x, y) = (i % width, i / width);
+            let noise = (state % 32) as u8;
+            [
+                (x % 256) as u8 ^ noise,
+                (y % 256) as u8,
+                ((x + y) % 256) as u8,
+            ]
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn measures_every_backend() {
+        let throughputs = measure(&[(40, 30), (60, 20)], 4);
+        assert_eq!(throughputs.len(), 2 * backends().len());
+        assert_eq!(throughputs[0].size, (40, 30));
+        assert!(throughputs
+            .iter()
+            .all(|throughput| throughput.megapixels_per_second > 0.));
+    }
+}
@@ -0,0 +1,230 @@
+use alloc::vec::Vec;
+
+use crate::error::SeamError;
+use crate::pos::Pos;
+use crate::seam_finder::{cumulate, SeamFinder, SeamTable, FORBIDDEN};
+
+/// Finds the vertical seams with the lowest energy like a [SeamFinder], but fills
+/// the table from the top and the bottom of the image at once, meeting in the middle row.
+///
+/// The top half of the image is cumulated downwards, and the bottom half upwards,
+/// so each table is half as tall as the one of a [SeamFinder]: the chain of rows that
+/// depend on each other is halved. With the `rayon` feature,
+/// [par_extract_seam_with_energy](BidirectionalSeamFinder::par_extract_seam_with_energy)
+/// fills both halves on two threads, for a lower latency on tall images.
+/// Each half is cached between seams like the table of a [SeamFinder].
+///
+/// The seams have the lowest energy, like the ones of a [SeamFinder],
+/// but may be another one of the seams of this energy.
+///
+/// ```
+/// use seamcarving::{BidirectionalSeamFinder, Pos, SeamFinder};
+/// let energy = |Pos(x, y): Pos| (x * 7 + y * 13) % 10;
+/// let mut finder = BidirectionalSeamFinder::new(Pos(8, 6));
+/// let (seam, total) = finder.extract_seam_with_energy(energy).unwrap();
+/// assert_eq!(seam.len(), 6);
+/// let expected = SeamFinder::new(Pos(8, 6)).extract_seam_with_energy(energy).unwrap();
+/// assert_eq!(total, expected.1);
+/// ```
+#[derive(Clone, Debug)]
+pub struct BidirectionalSeamFinder {
+    size: Pos,
+    // Rows above the middle one
+    top: SeamFinder,
+    // The other rows, from the bottom of the image
+    bottom: SeamFinder,
+    wrap: bool,
+}
+
+impl BidirectionalSeamFinder {
+    /// A seam finder for an image of the given width and height
+    pub fn new(size: Pos) -> Self {
+        let middle = size.1 / 2;
+        BidirectionalSeamFinder {
+            size,
+            top: SeamFinder::new(Pos(size.0, middle)),
+            bottom: SeamFinder::new(Pos(size.0, size.1 - middle)),
+            wrap: false,
+        }
+    }
+
+    /// Lets seams cross the left and right borders, see [SeamFinder::set_wrapping]
+    pub fn set_wrapping(&mut self, wrap: bool) {
+        self.wrap = wrap;
+        self.top.set_wrapping(wrap);
+        self.bottom.set_wrapping(wrap);
+    }
+
+    /// Width and height of the image with the extracted seams removed
+    pub fn size(&self) -> Pos {
+        self.size
+    }
+
+    /// Extracts the seam with the lowest energy, see [SeamFinder::extract_seam]
+    pub fn extract_seam<F: FnMut(Pos) -> u32>(&mut self, energy: F) -> Option<Vec<Pos>> {
+        self.extract_seam_with_energy(energy).ok().map(|(seam, _energy)| seam)
+    }
+
+    /// Extracts the seam with the lowest energy, and returns its total energy along with it,
+    /// see [SeamFinder::extract_seam_with_energy]
+    pub fn extract_seam_with_energy<F: FnMut(Pos) -> u32>(
+        &mut self,
+        mut energy: F,
+    ) -> Result<(Vec<Pos>, u32), SeamError> {
+        if self.size.0 == 0 {
+            return Err(SeamError::Empty);
+        }
+        let flip = self.flip();
+        self.top.fill(&mut energy);
+        self.bottom.fill(|pos| energy(flip(pos)));
+        self.join()
+    }
+
+    /// Extracts the seam with the lowest energy
+    /// like [extract_seam_with_energy](BidirectionalSeamFinder::extract_seam_with_energy),
+    /// filling the top and the bottom halves of the table concurrently,
+    /// on the threads of the current rayon thread pool.
+    ///
+    /// The seams are exactly the same as with
+    /// [extract_seam_with_energy](BidirectionalSeamFinder::extract_seam_with_energy).
+    #[cfg(feature = "rayon")]
+    pub fn par_extract_seam_with_energy<F: Fn(Pos) -> u32 + Sync>(
+        &mut self,
+        energy: F,
+    ) -> Result<(Vec<Pos>, u32), SeamError> {
+        if self.size.0 == 0 {
+            return Err(SeamError::Empty);
+        }
+        let flip = self.flip();
+        let (top, bottom) = (&mut self.top, &mut self.bottom);
+        rayon::join(
+            || top.fill(&energy),
+            || bottom.fill(|pos| energy(flip(pos))),
+        );
+        self.join()
+    }
+
+    /// Maps the positions of the bottom half of the table to the ones of the image
+    fn flip(&self) -> impl Fn(Pos) -> Pos + Copy + Send + Sync {
+        let height = self.size.1;
+        move |Pos(x, y)| Pos(x, height - 1 - y)
+    }
+
+    /// Finds the cheapest seam through the halves of the filled table,
+    /// whose last rows are next to each other in the image, and removes it
+    fn join(&mut self) -> Result<(Vec<Pos>, u32), SeamError> {
+        let Pos(width, height) = self.size;
+        let Some(bottom_last) = self.bottom.size().1.checked_sub(1) else {
+            // An image without rows has a seam without positions
+            self.size.0 -= 1;
+            self.top.remove(&[]);
+            self.bottom.remove(&[]);
+            return Ok((Vec::new(), 0));
+        };
+        let top_last = self.top.size().1.checked_sub(1);
+        let top = self.top.table(|_| unreachable!("the top half is filled"));
+        let bottom = self.bottom.table(|_| unreachable!("the bottom half is filled"));
+        // The cheapest pair of neighbours of the middle rows, the leftmost ones winning ties
+        let mut best: Option<(Option<u32>, u32, u32)> = None;
+        for x in 0..width {
+            let below = bottom.cumulative_energy(Pos(x, bottom_last));
+            let candidates = match top_last {
+                None => [None, Some((x, 0)), None],
+                Some(y) => {
+                    let above = Pos(x, y + 1).predecessors_in(Pos(width, y + 2), self.wrap);
+                    let mut candidates = [None; 3];
+                    for (candidate, Pos(x, y)) in candidates.iter_mut().zip(above) {
+                        *candidate = Some((x, top.cumulative_energy(Pos(x, y))));
+                    }
+                    candidates
+                }
+            };
+            for (above, energy) in candidates.iter().flatten() {
+                let total = match (*energy, below) {
+                    (FORBIDDEN, _) | (_, FORBIDDEN) => FORBIDDEN,
+                    (energy, below) => cumulate(energy, below),
+                };
+                if best.is_none_or(|(_, _, lowest)| total < lowest) {
+                    best = Some((top_last.map(|_| *above), x, total));
+                }
+            }
+        }
+        let (above, below, total) = best.ok_or(SeamError::Empty)?;
+        if total == FORBIDDEN {
+            return Err(SeamError::Blocked);
+        }
+        let mut bottom_seam = trace(&bottom, Pos(below, bottom_last));
+        let top_seam = match (above, top_last) {
+            (Some(x), Some(y)) => trace(&top, Pos(x, y)),
+            _ => Vec::new(),
+        };
+        self.top.remove(&top_seam);
+        self.bottom.remove(&bottom_seam);
+        // From the bottom of the image to its top
+        bottom_seam.reverse();
+        let flip = self.flip();
+        let mut seam: Vec<Pos> = bottom_seam.into_iter().map(flip).collect();
+        seam.extend(top_seam);
+        debug_assert_eq!(seam.len(), height as usize);
+        self.size.0 -= 1;
+        Ok((seam, total))
+    }
+}
+
+/// The cheapest seam of a filled table ending at the given position, from the bottom to the top
+fn trace(table: &SeamTable, end: Pos) -> Vec<Pos> {
+    core::iter::successors(Some(end), |&pos| table.predecessor(pos)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec;
+
+    use super::*;
+
+    /// Energies without ties between seams, in the coordinates of the carved image
+    fn energy(Pos(x, y): Pos) -> u32 {
+        (x.wrapping_mul(2_654_435_761) ^ y.wrapping_mul(40_503)) % 1000
+    }
+
+    #[test]
+    fn finds_the_seams_of_a_seam_finder() {
+        for (size, wrap) in [(Pos(12, 9), false), (Pos(12, 8), true), (Pos(5, 2), false)] {
+            let mut bidirectional = BidirectionalSeamFinder::new(size);
+            bidirectional.set_wrapping(wrap);
+            let mut finder = SeamFinder::new(size);
+            finder.set_wrapping(wrap);
+            for _ in 0..size.0 {
+                let expected = finder.extract_seam_with_energy(energy);
+                assert_eq!(bidirectional.extract_seam_with_energy(energy), expected);
+            }
+            assert_eq!(bidirectional.size(), Pos(0, size.1));
+            let empty = bidirectional.extract_seam_with_energy(energy);
+            assert_eq!(empty, Err(SeamError::Empty));
+        }
+    }
+
+    #[test]
+    fn carves_short_images() {
+        for height in 0..2 {
+            let mut finder = BidirectionalSeamFinder::new(Pos(3, height));
+            let (seam, _total) = finder.extract_seam_with_energy(|Pos(x, _)| 2 - x).unwrap();
+            assert_eq!(seam, vec![Pos(2, 0); height as usize]);
+            assert_eq!(finder.size(), Pos(2, height));
+        }
+        let mut blocked = BidirectionalSeamFinder::new(Pos(3, 4));
+        let wall = |Pos(_, y): Pos| if y == 2 { FORBIDDEN } else { 1 };
+        assert_eq!(blocked.extract_seam(wall), None);
+    }
+
+    #[test]
+    #[cfg(feature = "rayon")]
+    fn fills_both_halves_on_two_threads() {
+        let mut sequential = BidirectionalSeamFinder::new(Pos(20, 31));
+        let mut parallel = sequential.clone();
+        for _ in 0..10 {
+            let expected = sequential.extract_seam_with_energy(energy);
+            assert_eq!(parallel.par_extract_seam_with_energy(energy), expected);
+        }
+    }
+}
@@ -0,0 +1,890 @@
+//! Command line interface to seamcarving.
+//!
+//! ```text
+//! seamcarve input.jpg output.jpg --width 80% --height 400
+//! ```
+//!
+//! `seamcarve video` narrows the frames of a video, extracted beforehand:
+//!
+//! ```text
+//! ffmpeg -i input.mp4 frames/%05d.png
+//! seamcarve video frames carved --width 80%
+//! ffmpeg -i carved/%05d.png output.mp4
+//! ```
+//!
+//! `seamcarve enlarge` inserts seams instead, a few at a time:
+//!
+//! ```text
+//! seamcarve enlarge input.jpg output.jpg --width 150% --max-step 10%
+//! ```
+//!
+//! `seamcarve remove-object` removes what a mask covers, and can restore the dimensions:
+//!
+//! ```text
+//! seamcarve remove-object input.jpg output.jpg --mask object.png --restore-size
+//! ```
+//!
+//! `seamcarve compare` measures how much of an image a resized version keeps:
+//!
+//! ```text
+//! seamcarve compare input.jpg output.jpg --diff missing.png --json
+//! ```
+//!
+//! The command only uses the file system and never starts a thread,
+//! so it also runs on WASI, with the directories of the images preopened:
+//!
+//! ```text
+//! cargo build --release --features cli --bin seamcarve --target wasm32-wasip1
+//! wasmtime --dir . target/wasm32-wasip1/release/seamcarve.wasm input.jpg output.jpg -w 80%
+//! ```
+use std::error::Error;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use image::{DynamicImage, GenericImageView};
+use seamcarving::{
+    AxisOrder, Backend, Carver, Checkpoint, Comparison, Dimension, EnergyParams, EnergyRegistry,
+    EnergyStage, FileReport, FrameCarver, InsertionBlend, Plan, Preset,
+};
+
+/// Resizes images without distorting their content, by removing their least visible seams
+#[derive(Parser, Debug)]
+#[command(name = "seamcarve", version)]
+#[command(args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
+struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+    /// The image to resize
+    #[arg(required = true)]
+    input: Option<PathBuf>,
+    /// Where to write the resized image, in the format given by its extension,
+    /// with the EXIF and XMP metadata of JPEG and PNG images.
+    /// WebP, AVIF and TIFF need the webp, avif and tiff features
+    #[arg(required = true)]
+    output: Option<PathBuf>,
+    /// Target width, in pixels or as a percentage of the original width, like 80%
+    #[arg(short, long, value_parser = parse_dimension)]
+    width: Option<Dimension>,
+    /// Target height, in pixels or as a percentage of the original height
+    #[arg(short = 'H', long, value_parser = parse_dimension)]
+    height: Option<Dimension>,
+    /// Carves the image to the aspect ratio of a social network format,
+    /// with the mix of carving and scaling that suits it,
+    /// and scales it down to fit in its resolution if it is larger:
+    /// instagram-square (1080x1080), story (1080x1920) or youtube-thumb (1280x720)
+    #[arg(long, value_parser = parse_preset, conflicts_with_all = ["width", "height"])]
+    preset: Option<Preset>,
+    /// Energy protecting the content of the image on top of the gradient the seams avoid:
+    /// gradient, sobel, entropy, saliency, center or textures.
+    /// center protects the middle of the image, so that its periphery is carved first
+    #[arg(long)]
+    energy: Option<String>,
+    /// A parameter of the energy, like strength=2, repeated for each parameter.
+    /// sobel takes a strength, entropy and textures a radius and a strength,
+    /// saliency a sigma and a strength, and center a strength and a falloff
+    #[arg(long = "energy-arg", value_name = "NAME=VALUE", requires = "energy")]
+    energy_args: Vec<String>,
+    /// Appends a report of the resize to this file, for batch jobs:
+    /// a CSV line when it ends with .csv, with a header when the file is new,
+    /// and a JSON object on its own line otherwise
+    #[arg(long)]
+    report: Option<PathBuf>,
+    /// Writes the order in which the seams are removed from the image to this file,
+    /// so that it can be rendered at any width down to the resized one, see web/README.md
+    #[arg(long)]
+    seam_map: Option<PathBuf>,
+    /// Writes diagnostic images next to the resized one, as PNG files named after it,
+    /// like output.seams.png: the removed seams, the energy, the energy as a heatmap,
+    /// or all of them side by side with the resized image
+    #[arg(long, value_enum, value_delimiter = ',')]
+    viz: Vec<Viz>,
+    /// Prints what resizing would do, the seams of each axis, the algorithm,
+    /// and estimations of the memory and time it takes, without writing any file.
+    /// `--dry-run=json` prints it as a JSON object
+    #[arg(long, value_enum, num_args = 0..=1, default_missing_value = "text")]
+    dry_run: Option<DryRun>,
+    /// Saves the seams removed so far to this file as the image is resized,
+    /// so that a long resize that is interrupted can be resumed with --resume.
+    /// The file is removed once the resized image is written
+    #[arg(long)]
+    checkpoint: Option<PathBuf>,
+    /// Computes the seams with this backend, giving the same image faster:
+    /// auto chooses from the image and the host, cpu computes one position at a time,
+    /// simd uses vector instructions, in builds with the simd feature,
+    /// and gpu computes the energy on a graphics card, in builds with the gpu feature.
+    /// Not available with --energy, --preset, --seam-map, --viz, --checkpoint, --report
+    /// and --max-cpu
+    #[arg(
+        long,
+        value_enum,
+        conflicts_with_all = [
+            "energy", "preset", "seam_map", "viz", "checkpoint", "report", "max_cpu"
+        ]
+    )]
+    backend: Option<BackendChoice>,
+    /// Removes the seams saved in the checkpoint again before finding the next ones,
+    /// when it was saved for the same image with the same options
+    #[arg(long, requires = "checkpoint")]
+    resume: bool,
+    /// Keeps the processor busy for at most this fraction of the time, like 0.25,
+    /// sleeping between the seams, so that long resizes leave the machine usable
+    #[arg(long, value_parser = parse_fraction)]
+    max_cpu: Option<f32>,
+    /// Lowers the priority of the resize, so that the other programs of the machine go first,
+    /// on the systems that allow it
+    #[arg(long)]
+    low_priority: bool,
+    /// Removes the same seams from all the pages of a multi-page TIFF image,
+    /// so that aligned scans stay aligned, instead of carving each page on its own.
+    /// The pages are written to the output, which has to be a TIFF image too.
+    /// Multi-page images can only be resized with --width and --height
+    #[cfg(feature = "tiff")]
+    #[arg(long)]
+    shared_seams: bool,
+}
+
+/// The backends of [Cli::backend]
+#[derive(ValueEnum, Clone, Copy, PartialEq, Eq, Debug)]
+enum BackendChoice {
+    Auto,
+    Cpu,
+    Simd,
+    Gpu,
+}
+
+/// How [Cli::dry_run] prints the plan
+#[derive(Clone, Copy, PartialEq, Eq, Debug, ValueEnum)]
+enum DryRun {
+    Text,
+    Json,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Narrows the frames of a video, reusing the seams of each frame for the next one.
+    ///
+    /// The frames are read from a directory and written to another one, as video files
+    /// are not decoded: `ffmpeg -i input.mp4 frames/%05d.png` extracts them,
+    /// `seamcarve video frames carved --width 80%` carves them,
+    /// and `ffmpeg -i carved/%05d.png output.mp4` encodes them again.
+    Video(VideoArgs),
+    /// Enlarges an image, by duplicating its least visible seams
+    Enlarge(EnlargeArgs),
+    /// Removes an object from an image, by removing seams going through it
+    RemoveObject(RemoveObjectArgs),
+    /// Prints quality metrics of a resized image compared to its original
+    Compare(CompareArgs),
+}
+
+/// The arguments of `seamcarve enlarge`
+#[derive(Args, Debug)]
+struct EnlargeArgs {
+    /// The image to enlarge
+    input: PathBuf,
+    /// Where to write the enlarged image, with the metadata of the image
+    output: PathBuf,
+    /// Target width, in pixels or as a percentage of the original width, like 150%.
+    /// Widths smaller than the original one are ignored
+    #[arg(short, long, value_parser = parse_dimension)]
+    width: Option<Dimension>,
+    /// Target height, in pixels or as a percentage of the original height
+    #[arg(short = 'H', long, value_parser = parse_dimension)]
+    height: Option<Dimension>,
+    /// Carves the image to the aspect ratio of a social network format,
+    /// with the mix of carving and scaling that suits it,
+    /// and scales it down to fit in its resolution if it is larger:
+    /// instagram-square (1080x1080), story (1080x1920) or youtube-thumb (1280x720)
+    #[arg(long, value_parser = parse_preset, conflicts_with_all = ["width", "height"])]
+    preset: Option<Preset>,
+    /// The most seams inserted at once, in pixels or as a percentage of the image enlarged
+    /// so far: the seams are found again after each batch, so smaller steps spread them
+    /// over more of the image, and larger steps are faster
+    #[arg(long, value_parser = parse_dimension, default_value = "25%")]
+    max_step: Dimension,
+    /// Blends the inserted seams in the gradient domain instead of averaging their neighbours,
+    /// which hides the stripes duplicated seams tend to leave
+    #[arg(long)]
+    gradient_blend: bool,
+}
+
+/// The arguments of `seamcarve video`
+#[derive(Args, Debug)]
+struct VideoArgs {
+    /// The directory of the frames of the video, images sorted by file name,
+    /// extracted for instance with `ffmpeg -i input.mp4 frames/%05d.png`.
+    /// Video files are not decoded
+    input: PathBuf,
+    /// The directory where the carved frames are written, with the names of the frames,
+    /// to be encoded for instance with `ffmpeg -i carved/%05d.png output.mp4`
+    output: PathBuf,
+    /// Target width, in pixels or as a percentage of the width of the first frame.
+    /// The height of the frames is kept
+    #[arg(short, long, value_parser = parse_dimension)]
+    width: Dimension,
+    /// How much the energy of a pixel has to change from one frame to the next
+    /// for the seams going through it to be computed again:
+    /// higher values make the seams steadier over time, and carving faster
+    #[arg(long, default_value_t = 100)]
+    temporal_strength: u32,
+    /// The fraction of the pixels whose energy has to change for a frame to be carved
+    /// as the first frame of a new scene, between 0 and 1
+    #[arg(long, default_value_t = 0.5)]
+    scene_cut: f32,
+    /// The energy added to a pixel for each pixel it moved since the previous frame,
+    /// estimated by matching blocks of the frames, so that moving subjects are preserved
+    /// and static backgrounds carved first
+    #[arg(long)]
+    motion_strength: Option<f32>,
+}
+
+/// The arguments of `seamcarve remove-object`
+#[derive(Args, Debug)]
+struct RemoveObjectArgs {
+    /// The image to remove the object from
+    input: PathBuf,
+    /// Where to write the image without the object, with the metadata of the image
+    output: PathBuf,
+    /// An image of the same dimensions, in which the object is not black
+    #[arg(long)]
+    mask: PathBuf,
+    /// Inserts seams back once the object is removed, so that the image keeps its dimensions
+    #[arg(long)]
+    restore_size: bool,
+}
+
+/// The arguments of `seamcarve compare`
+#[derive(Args, Debug)]
+struct CompareArgs {
+    /// The original image
+    original: PathBuf,
+    /// The resized image
+    resized: PathBuf,
+    /// Writes the original image to this file, with the regions missing from the resized image
+    /// tinted in red
+    #[arg(long)]
+    diff: Option<PathBuf>,
+    /// Prints the metrics as a JSON object instead of text
+    #[arg(long)]
+    json: bool,
+}
+
+/// A diagnostic image written by [Cli::viz]
+#[derive(Clone, Copy, PartialEq, Eq, Debug, ValueEnum)]
+enum Viz {
+    Seams,
+    Energy,
+    Heatmap,
+    Diff,
+}
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let cli = Cli::parse();
+    match &cli.command {
+        Some(Command::Video(video)) => carve_video(video),
+        Some(Command::Enlarge(enlarge)) => enlarge_image(enlarge),
+        Some(Command::RemoveObject(remove)) => remove_object(remove),
+        Some(Command::Compare(args)) => compare(args),
+        None => resize(&cli),
+    }
+}
+
+/// The library backend of a [BackendChoice],
+/// or why it is not available in this build
+fn backend(choice: BackendChoice) -> Result<Backend, String> {
+    match choice {
+        BackendChoice::Auto => Ok(Backend::Auto),
+        BackendChoice::Cpu => Ok(Backend::Scalar),
+        #[cfg(feature = "simd")]
+        BackendChoice::Simd => Ok(Backend::Simd),
+        #[cfg(not(feature = "simd"))]
+        BackendChoice::Simd => Err("the simd backend needs a build with the simd feature: \
+             cargo install seamcarving --features cli,simd"
+            .to_string()),
+        #[cfg(feature = "gpu")]
+        BackendChoice::Gpu => seamcarving::Gpu::new()
+            .map(Backend::Gpu)
+            .map_err(|e| format!("the gpu backend cannot be used: {}", e)),
+        #[cfg(not(feature = "gpu"))]
+        BackendChoice::Gpu => Err("the gpu backend needs a build with the gpu feature: \
+             cargo install seamcarving --features cli,gpu"
+            .to_string()),
+    }
+}
+
+/// Resizes the image of the command line
+fn resize(cli: &Cli) -> Result<(), Box<dyn Error>> {
+    let (Some(input), Some(output)) = (&cli.input, &cli.output) else {
+        unreachable!("the input and the output are required without a subcommand");
+    };
+    let start = Instant::now();
+    if cli.low_priority && !seamcarving::lower_priority() {
+        eprintln!("warning: the priority of the resize cannot be lowered on this system");
+    }
+    #[cfg(feature = "tiff")]
+    if image::ImageFormat::from_path(input).ok() == Some(image::ImageFormat::Tiff) {
+        let pages = seamcarving::open_pages(input)?;
+        if pages.len() > 1 {
+            return resize_document(cli, &pages, output);
+        }
+    }
+    let (img, metadata) = seamcarving::open_image_with_metadata(input)?;
+    let mut carver = match cli.preset {
+        Some(preset) => preset.carver(&img),
+        None => Carver::new(),
+    };
+    if let Some(width) = cli.width {
+        carver = carver.width(width);
+    }
+    if let Some(height) = cli.height {
+        carver = carver.height(height);
+    }
+    if let Some(fraction) = cli.max_cpu {
+        carver = carver.max_cpu(fraction);
+    }
+    if let Some(name) = &cli.energy {
+        let mut params = EnergyParams::new();
+        for pair in &cli.energy_args {
+            params.parse_pair(pair)?;
+        }
+        let energy = EnergyRegistry::new().create(name, &params)?;
+        carver = carver.importance(&energy.importance(&img.to_rgba8()));
+    }
+    if let Some(format) = cli.dry_run {
+        println!("{}", describe_plan(input, img.dimensions(), &carver.plan(&img), format));
+        return Ok(());
+    }
+    if let Some(choice) = cli.backend {
+        let backend = backend(choice)?;
+        if !backend.is_supported() {
+            eprintln!(
+                "warning: this processor supports neither AVX-512 nor NEON, \
+                 the seams are computed one position at a time"
+            );
+        }
+        let target = carver.target_dimensions(&img);
+        let resized = match img.as_luma8() {
+            // Document scans and depth maps are carved on their single channel
+            Some(gray) => {
+                let pixels: Vec<[u8; 1]> = gray.pixels().map(|pixel| pixel.0).collect();
+                let resized = seamcarving::resize_buffer_with_backend(
+                    &pixels,
+                    img.dimensions(),
+                    target,
+                    &backend,
+                )?;
+                let resized = image::GrayImage::from_raw(target.0, target.1, resized.concat())
+                    .expect("the resized pixels fill the target dimensions");
+                DynamicImage::ImageLuma8(resized)
+            }
+            None => {
+                let pixels: Vec<[u8; 4]> = img.to_rgba8().pixels().map(|pixel| pixel.0).collect();
+                let resized = seamcarving::resize_buffer_with_backend(
+                    &pixels,
+                    img.dimensions(),
+                    target,
+                    &backend,
+                )?;
+                let resized = image::RgbaImage::from_raw(target.0, target.1, resized.concat())
+                    .expect("the resized pixels fill the target dimensions");
+                DynamicImage::ImageRgba8(resized)
+            }
+        };
+        seamcarving::save_dynamic_image_with_metadata(&resized, output, &metadata)?;
+        return Ok(());
+    }
+    let (resized, report) = match &cli.checkpoint {
+        Some(path) => {
+            let (resized, report) =
+                carver.resize_checkpointed(&img, &Checkpoint::new(path), cli.resume)?;
+            (DynamicImage::ImageRgba8(resized), report)
+        }
+        None => carver.resize_dynamic(&img)?,
+    };
+    let carved_width = resized.width();
+    let saved = fit(cli.preset, resized);
+    seamcarving::save_dynamic_image_with_metadata(&saved, output, &metadata)?;
+    if let Some(path) = &cli.checkpoint {
+        std::fs::remove_file(path)?;
+    }
+    if let Some(path) = &cli.seam_map {
+        let map = carver.seam_index_map(&img, carved_width)?;
+        std::fs::write(path, map.to_bytes())?;
+    }
+    for &viz in &cli.viz {
+        let path = viz_path(output, viz);
+        match viz {
+            Viz::Seams => seamcarving::seams_image(&carver, &img)?.save(path)?,
+            Viz::Energy => seamcarving::energy_image(&img).save(path)?,
+            Viz::Heatmap => seamcarving::energy_heatmap(&img).save(path)?,
+            Viz::Diff => seamcarving::diff_image(&carver, &img, true)?.save(path)?,
+        }
+    }
+    if let Some(path) = &cli.report {
+        let file = input.display().to_string();
+        let report = FileReport::new(file, img.dimensions(), report, start.elapsed());
+        append_report(path, &report)?;
+    }
+    Ok(())
+}
+
+/// Fits a resized image in the resolution of a preset, if any.
+/// Gray images stay gray, see [seamcarving::encode_dynamic_image]
+fn fit(preset: Option<Preset>, img: DynamicImage) -> DynamicImage {
+    match (preset, img) {
+        (Some(preset), DynamicImage::ImageLuma8(gray)) => {
+            DynamicImage::ImageLuma8(preset.fit(gray))
+        }
+        (Some(preset), DynamicImage::ImageLumaA8(gray)) => {
+            DynamicImage::ImageLumaA8(preset.fit(gray))
+        }
+        (Some(preset), img) => DynamicImage::ImageRgba8(preset.fit(img.to_rgba8())),
+        (None, img) => img,
+    }
+}
+
+/// Resizes the pages of a multi-page TIFF image, see [Cli::shared_seams]
+#[cfg(feature = "tiff")]
+fn resize_document(cli: &Cli, pages: &[DynamicImage], output: &Path) -> Result<(), Box<dyn Error>> {
+    let other_options = cli.preset.is_some()
+        || cli.energy.is_some()
+        || cli.dry_run.is_some()
+        || cli.backend.is_some()
+        || cli.checkpoint.is_some()
+        || cli.seam_map.is_some()
+        || !cli.viz.is_empty()
+        || cli.report.is_some()
+        || cli.max_cpu.is_some();
+    if other_options {
+        return Err("multi-page images can only be resized with --width, --height \
+             and --shared-seams"
+            .into());
+    }
+    let mut carver = Carver::new();
+    if let Some(width) = cli.width {
+        carver = carver.width(width);
+    }
+    if let Some(height) = cli.height {
+        carver = carver.height(height);
+    }
+    let pages: Vec<_> = pages.iter().map(DynamicImage::to_rgba8).collect();
+    let resized = seamcarving::resize_pages(&carver, &pages, cli.shared_seams)?;
+    seamcarving::save_pages(&resized, output)?;
+    Ok(())
+}
+
+/// Carves the frames of a video, see [VideoArgs]
+fn carve_video(args: &VideoArgs) -> Result<(), Box<dyn Error>> {
+    if args.input.is_file() {
+        let message = format!(
+            "{0} is a file, and seamcarve video only carves directories of frames: \
+             extract them first, for instance with ffmpeg -i {0} frames/%05d.png, \
+             and carve the frames directory",
+            args.input.display()
+        );
+        return Err(message.into());
+    }
+    if !args.input.is_dir() {
+        let message = format!(
+            "{} is not a directory of frames: extract them first, \
+             for instance with ffmpeg -i input.mp4 frames/%05d.png",
+            args.input.display()
+        );
+        return Err(message.into());
+    }
+    let mut frames = Vec::new();
+    for entry in std::fs::read_dir(&args.input)? {
+        let path = entry?.path();
+        if image::ImageFormat::from_path(&path).is_ok() {
+            frames.push(path);
+        }
+    }
+    frames.sort();
+    std::fs::create_dir_all(&args.output)?;
+    let mut carver = None;
+    for path in &frames {
+        let frame = seamcarving::open_image(path)?;
+        let carver = carver.get_or_insert_with(|| frame_carver(args, frame.width()));
+        let name = path.file_name().expect("frames are files");
+        seamcarving::save_image(&carver.carve_frame(&frame), args.output.join(name))?;
+    }
+    Ok(())
+}
+
+/// The carver of the frames of `seamcarve video`, for frames of the given width
+fn frame_carver(args: &VideoArgs, frame_width: u32) -> FrameCarver {
+    let width = args.width.resolve(frame_width);
+    let carver = FrameCarver::new(width, args.temporal_strength).scene_cut(args.scene_cut);
+    match args.motion_strength {
+        Some(strength) => carver.motion_strength(strength),
+        None => carver,
+    }
+}
+
+/// Enlarges the image of `seamcarve enlarge`, see [EnlargeArgs]
+fn enlarge_image(args: &EnlargeArgs) -> Result<(), Box<dyn Error>> {
+    let (img, metadata) = seamcarving::open_image_with_metadata(&args.input)?;
+    let width = enlarged(args.width, img.width());
+    let height = enlarged(args.height, img.height());
+    let blend = if args.gradient_blend {
+        InsertionBlend::Gradient
+    } else {
+        InsertionBlend::Average
+    };
+    let enlarged = seamcarving::enlarge_with_max_step(&img, width, height, blend, args.max_step);
+    seamcarving::save_image_with_metadata(&enlarged, &args.output, &metadata)?;
+    Ok(())
+}
+
+/// Removes the object of `seamcarve remove-object`, see [RemoveObjectArgs]
+fn remove_object(args: &RemoveObjectArgs) -> Result<(), Box<dyn Error>> {
+    let (img, metadata) = seamcarving::open_image_with_metadata(&args.input)?;
+    let mask = seamcarving::open_image(&args.mask)?.to_luma8();
+    if mask.dimensions() != img.dimensions() {
+        let message = format!(
+            "the mask is {}x{} but the image is {}x{}",
+            mask.width(),
+            mask.height(),
+            img.width(),
+            img.height()
+        );
+        return Err(message.into());
+    }
+    let removed = seamcarving::remove_object(&img, &mask, args.restore_size)?;
+    seamcarving::save_image_with_metadata(&removed, &args.output, &metadata)?;
+    Ok(())
+}
+
+/// Compares the images of `seamcarve compare`, see [CompareArgs]
+fn compare(args: &CompareArgs) -> Result<(), Box<dyn Error>> {
+    let original = seamcarving::open_image(&args.original)?;
+    let resized = seamcarving::open_image(&args.resized)?;
+    let comparison = seamcarving::compare_images(&original, &resized);
+    if let Some(path) = &args.diff {
+        comparison.diff_image(&original).save(path)?;
+    }
+    if args.json {
+        println!("{}", comparison.to_json());
+    } else {
+        println!("{}", describe_comparison(&comparison));
+    }
+    Ok(())
+}
+
+/// The metrics of `seamcarve compare`, as text
+fn describe_comparison(comparison: &Comparison) -> String {
+    format!(
+        "energy removed: {:.1}%\ncompleteness: {:.5}\ncoherence: {:.5}\n\
+         bidirectional similarity: {:.5}",
+        comparison.energy_removed * 100.,
+        comparison.completeness,
+        comparison.coherence,
+        comparison.bidirectional_similarity()
+    )
+}
+
+/// The number of pixels a dimension of `seamcarve enlarge` represents,
+/// which unlike [Dimension::resolve] can exceed the original one
+fn enlarged(dimension: Option<Dimension>, original: u32) -> u32 {
+    let original = u64::from(original);
+    let pixels = match dimension {
+        None => original,
+        Some(Dimension::Pixels(pixels)) => u64::from(pixels),
+        Some(Dimension::Percent(percent)) => (original * u64::from(percent) + 50) / 100,
+        Some(Dimension::Scale(scale)) => (original as f64 * scale.max(0.)).round() as u64,
+    };
+    pixels.min(u64::from(u32::MAX)) as u32
+}
+
+/// Adds a line to a batch report, see [Cli::report]
+fn append_report(path: &Path, report: &FileReport) -> std::io::Result<()> {
+    let csv = path.extension().is_some_and(|extension| extension == "csv");
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    if !csv {
+        return writeln!(file, "{}", report.to_json());
+    }
+    if file.metadata()?.len() == 0 {
+        writeln!(file, "{}", FileReport::csv_header())?;
+    }
+    writeln!(file, "{}", report.to_csv())
+}
+
+/// The plan of the resize of an image of the `original` size, see [Cli::dry_run]
+fn describe_plan(input: &Path, original: (u32, u32), plan: &Plan, format: DryRun) -> String {
+    if format == DryRun::Json {
+        return plan.to_json();
+    }
+    let (width, height) = original;
+    let (vertical, horizontal) = plan.seams;
+    let order = match plan.order {
+        AxisOrder::WidthFirst => "width first",
+        _ => "cheapest axis first",
+    };
+    let scaling = if plan.may_scale {
+        "may scale the image instead of carving it"
+    } else {
+        "only carves"
+    };
+    format!(
+        "{}: {}x{} -> {}x{}\n\
+         seams: {} vertical, {} horizontal, {} algorithm, {}, {}\n\
+         peak memory: {:.1} MB\n\
+         estimated time: {:.2} s",
+        input.display(),
+        width,
+        height,
+        plan.size.0,
+        plan.size.1,
+        vertical,
+        horizontal,
+        format!("{:?}", plan.algorithm).to_lowercase(),
+        order,
+        scaling,
+        plan.peak_memory as f64 / 1e6,
+        plan.estimated_time().as_secs_f64(),
+    )
+}
+
+/// Where a diagnostic image is written: next to the output, with the name of the image
+/// in place of its extension, like `output.seams.png`
+fn viz_path(output: &Path, viz: Viz) -> PathBuf {
+    let name = viz.to_possible_value().expect("no value is skipped");
+    output.with_extension(format!("{}.png", name.get_name()))
+}
+
+/// Parses the name of a [Preset], like `story`
+fn parse_preset(value: &str) -> Result<Preset, String> {
+    Preset::from_name(value).ok_or_else(|| {
+        let names: Vec<&str> = Preset::ALL.iter().map(|preset| preset.name()).collect();
+        format!("{} is not a preset, choose one of {}", value, names.join(", "))
+    })
+}
+
+/// Parses a number of pixels, like `320`, or a percentage, like `80%`
+fn parse_dimension(value: &str) -> Result<Dimension, String> {
+    let invalid = |_| format!("{} is neither a number of pixels nor a percentage", value);
+    match value.strip_suffix('%') {
+        Some(percent) => percent
+            .trim()
+            .parse()
+            .map(Dimension::Percent)
+            .map_err(invalid),
+        None => value.trim().parse().map(Dimension::Pixels).map_err(invalid),
+    }
+}
+
+/// Parses a fraction of the time of the processor, above 0 and up to 1
+fn parse_fraction(value: &str) -> Result<f32, String> {
+    match value.trim().parse() {
+        Ok(fraction) if fraction > 0. && fraction <= 1. => Ok(fraction),
+        _ => Err(format!("{} is not a fraction above 0 and up to 1", value)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::Parser;
+    use image::{DynamicImage, GrayImage};
+    use seamcarving::{Carver, Dimension, Preset};
+
+    use std::path::Path;
+
+    use super::{
+        backend, carve_video, describe_comparison, describe_plan, enlarged, fit, viz_path,
+        BackendChoice, Cli, Command, DryRun, Viz,
+    };
+
+    #[test]
+    fn parses_dimensions() {
+        let cli = Cli::try_parse_from(["seamcarve", "in.png", "out.png", "-w", "80%"]).unwrap();
+        assert_eq!(cli.width, Some(Dimension::Percent(80)));
+        assert!(cli.command.is_none());
+        assert_eq!(cli.height, None);
+        let cli = Cli::try_parse_from(["seamcarve", "in.png", "out.png", "--height", "120"]);
+        assert_eq!(cli.unwrap().height, Some(Dimension::Pixels(120)));
+        assert!(Cli::try_parse_from(["seamcarve", "in.png", "out.png", "-w", "wide"]).is_err());
+    }
+
+    #[test]
+    fn fits_gray_images_in_presets() {
+        let gray = DynamicImage::ImageLuma8(GrayImage::new(2560, 1440));
+        let fitted = fit(Some(Preset::YoutubeThumbnail), gray.clone());
+        assert_eq!(fitted.as_luma8().map(GrayImage::dimensions), Some((1280, 720)));
+        assert_eq!(fit(None, gray.clone()), gray);
+        let color = DynamicImage::ImageRgb8(image::RgbImage::new(2560, 1440));
+        let fitted = fit(Some(Preset::YoutubeThumbnail), color);
+        assert_eq!(fitted.as_rgba8().map(image::RgbaImage::dimensions), Some((1280, 720)));
+    }
+
+    #[test]
+    fn parses_presets() {
+        let cli = Cli::try_parse_from(["seamcarve", "in.png", "out.png", "--preset", "story"]);
+        assert_eq!(cli.unwrap().preset, Some(Preset::Story));
+        let unknown = ["seamcarve", "in.png", "out.png", "--preset", "tiktok"];
+        assert!(Cli::try_parse_from(unknown).is_err());
+        let sized = ["seamcarve", "in.png", "out.png", "--preset", "story", "-w", "80"];
+        assert!(Cli::try_parse_from(sized).is_err());
+    }
+
+    #[test]
+    fn parses_energies() {
+        let args = ["seamcarve", "in.png", "out.png", "--energy", "entropy"];
+        let extra = ["--energy-arg", "radius=3", "--energy-arg", "strength=10"];
+        let cli = Cli::try_parse_from(args.iter().chain(&extra)).unwrap();
+        assert_eq!(cli.energy.as_deref(), Some("entropy"));
+        assert_eq!(cli.energy_args, ["radius=3", "strength=10"]);
+        // Parameters without an energy
+        let args = ["seamcarve", "in.png", "out.png", "--energy-arg", "radius=3"];
+        assert!(Cli::try_parse_from(args).is_err());
+    }
+
+    #[test]
+    fn parses_visualizations() {
+        let args = ["seamcarve", "in.png", "out/resized.jpg", "--viz", "seams,heatmap"];
+        let cli = Cli::try_parse_from(args).unwrap();
+        assert_eq!(cli.viz, [Viz::Seams, Viz::Heatmap]);
+        let path = viz_path(cli.output.as_ref().unwrap(), Viz::Heatmap);
+        assert_eq!(path, Path::new("out/resized.heatmap.png"));
+        assert!(Cli::try_parse_from(["seamcarve", "in.png", "out.png", "--viz", "x"]).is_err());
+    }
+
+    #[test]
+    fn describes_plans() {
+        let cli = Cli::try_parse_from(["seamcarve", "in.png", "out.png", "--dry-run"]).unwrap();
+        assert_eq!(cli.dry_run, Some(DryRun::Text));
+        let args = ["seamcarve", "in.png", "out.png", "--dry-run=json"];
+        assert_eq!(Cli::try_parse_from(args).unwrap().dry_run, Some(DryRun::Json));
+        let img = image::RgbImage::new(40, 30);
+        let plan = Carver::new().width(30).plan(&img);
+        let text = describe_plan(Path::new("in.png"), (40, 30), &plan, DryRun::Text);
+        assert!(text.starts_with("in.png: 40x30 -> 30x30\nseams: 10 vertical, 0 horizontal"));
+        let json = describe_plan(Path::new("in.png"), (40, 30), &plan, DryRun::Json);
+        assert!(json.starts_with(r#"{"width":30,"height":30,"vertical_seams":10,"#));
+    }
+
+    #[test]
+    fn asks_for_the_frames_of_video_files() {
+        let video = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests").join("input.png");
+        let args = ["seamcarve", "video", video.to_str().unwrap(), "carved", "-w", "80%"];
+        let Some(Command::Video(args)) = Cli::try_parse_from(args).unwrap().command else {
+            panic!("not a video");
+        };
+        let error = carve_video(&args).unwrap_err().to_string();
+        assert!(error.contains("only carves directories of frames"), "{}", error);
+        assert!(error.contains("ffmpeg -i"));
+    }
+
+    #[test]
+    fn parses_videos() {
+        let args = ["seamcarve", "video", "frames", "carved", "-w", "80%", "--scene-cut", "0.3"];
+        let Some(Command::Video(video)) = Cli::try_parse_from(args).unwrap().command else {
+            panic!("not a video");
+        };
+        assert_eq!(video.width, Dimension::Percent(80));
+        assert_eq!((video.temporal_strength, video.scene_cut), (100, 0.3));
+        assert_eq!(video.motion_strength, None);
+        let args = ["seamcarve", "video", "frames", "carved", "-w", "80", "--motion-strength", "5"];
+        let Some(Command::Video(video)) = Cli::try_parse_from(args).unwrap().command else {
+            panic!("not a video");
+        };
+        assert_eq!(video.motion_strength, Some(5.));
+        assert!(Cli::try_parse_from(["seamcarve", "video", "frames", "carved"]).is_err());
+        assert!(Cli::try_parse_from(["seamcarve", "in.png"]).is_err());
+    }
+
+    #[test]
+    fn parses_enlargements() {
+        let args = ["seamcarve", "enlarge", "in.png", "out.png", "-w", "150%", "--max-step", "8"];
+        let Some(Command::Enlarge(enlarge)) = Cli::try_parse_from(args).unwrap().command else {
+            panic!("not an enlargement");
+        };
+        assert_eq!(enlarge.max_step, Dimension::Pixels(8));
+        assert_eq!(enlarged(enlarge.width, 40), 60);
+        assert_eq!(enlarged(enlarge.height, 30), 30);
+        let args = ["seamcarve", "enlarge", "in.png", "out.png", "-H", "50"];
+        let Some(Command::Enlarge(enlarge)) = Cli::try_parse_from(args).unwrap().command else {
+            panic!("not an enlargement");
+        };
+        assert_eq!(enlarge.max_step, Dimension::Percent(25));
+        assert!(!enlarge.gradient_blend);
+    }
+
+    #[test]
+    fn parses_object_removals() {
+        let args = ["seamcarve", "remove-object", "in.png", "out.png", "--mask", "obj.png"];
+        let Some(Command::RemoveObject(remove)) = Cli::try_parse_from(args).unwrap().command else {
+            panic!("not an object removal");
+        };
+        assert_eq!((remove.mask.to_str(), remove.restore_size), (Some("obj.png"), false));
+        let args = ["seamcarve", "remove-object", "in.png", "out.png", "--restore-size"];
+        assert!(Cli::try_parse_from(args).is_err());
+    }
+
+    #[test]
+    fn describes_comparisons() {
+        let args = ["seamcarve", "compare", "in.png", "out.png", "--json"];
+        let Some(Command::Compare(compare)) = Cli::try_parse_from(args).unwrap().command else {
+            panic!("not a comparison");
+        };
+        assert!(compare.json && compare.diff.is_none());
+        let img = image::RgbImage::from_fn(12, 8, |x, y| image::Rgb([(x * 20) as u8, y as u8, 0]));
+        let comparison = seamcarving::compare_images(&img, &img);
+        let text = describe_comparison(&comparison);
+        assert!(text.starts_with("energy removed: 0.0%\n"));
+        assert!(text.ends_with("bidirectional similarity: 0.00000"));
+    }
+
+    #[test]
+    fn parses_checkpoints() {
+        let args = ["seamcarve", "in.png", "out.png", "--checkpoint", "job.state", "--resume"];
+        let cli = Cli::try_parse_from(args).unwrap();
+        assert_eq!(cli.checkpoint.as_deref(), Some(Path::new("job.state")));
+        assert!(cli.resume);
+        assert!(Cli::try_parse_from(["seamcarve", "in.png", "out.png", "--resume"]).is_err());
+    }
+
+    #[test]
+    #[cfg(feature = "tiff")]
+    fn parses_shared_seams() {
+        let args = ["seamcarve", "scans.tiff", "out.tiff", "-w", "80%", "--shared-seams"];
+        assert!(Cli::try_parse_from(args).unwrap().shared_seams);
+        let args = ["seamcarve", "scans.tiff", "out.tiff", "-w", "80%"];
+        assert!(!Cli::try_parse_from(args).unwrap().shared_seams);
+    }
+
+    #[test]
+    fn parses_throttling() {
+        let args = ["seamcarve", "in.png", "out.png", "-w", "80%", "--max-cpu", "0.25"];
+        let cli = Cli::try_parse_from(args).unwrap();
+        assert_eq!((cli.max_cpu, cli.low_priority), (Some(0.25), false));
+        let args = ["seamcarve", "in.png", "out.png", "--max-cpu", "0.5", "--low-priority"];
+        assert!(Cli::try_parse_from(args).unwrap().low_priority);
+        for fraction in ["0", "1.5", "half"] {
+            let args = ["seamcarve", "in.png", "out.png", "--max-cpu", fraction];
+            assert!(Cli::try_parse_from(args).is_err());
+        }
+        let args = ["seamcarve", "in.png", "out.png", "--max-cpu", "0.5", "--backend", "cpu"];
+        assert!(Cli::try_parse_from(args).is_err());
+    }
+
+    #[test]
+    fn chooses_backends() {
+        let args = ["seamcarve", "in.png", "out.png", "--backend", "cpu"];
+        assert_eq!(Cli::try_parse_from(args).unwrap().backend, Some(BackendChoice::Cpu));
+        let args = ["seamcarve", "in.png", "out.png", "--backend", "auto", "--viz", "seams"];
+        assert!(Cli::try_parse_from(args).is_err());
+        assert!(backend(BackendChoice::Auto).is_ok());
+        let gpu = backend(BackendChoice::Gpu);
+        #[cfg(not(feature = "gpu"))]
+        assert!(gpu.unwrap_err().contains("--features cli,gpu"));
+        // Hosts with neither a graphics card nor a software renderer cannot use it
+        #[cfg(feature = "gpu")]
+        assert!(gpu.map_or_else(
+            |e| e.starts_with("the gpu backend cannot be used"),
+            |gpu| matches!(gpu, seamcarving::Backend::Gpu(_))
+        ));
+        assert_eq!(backend(BackendChoice::Simd).is_ok(), cfg!(feature = "simd"));
+    }
+}
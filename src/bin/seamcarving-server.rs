@@ -0,0 +1,186 @@
+//! An HTTP server that resizes images with seam carving.
+//!
+//! `POST /resize?w=<width>&h=<height>` with an image as the body responds with the carved image,
+//! in the same format when it can be encoded, in PNG otherwise,
+//! with the EXIF and XMP metadata of JPEG and PNG images.
+//! Either dimension can be omitted to keep it unchanged.
+//!
+//! ```text
+//! seamcarving-server [ADDRESS] [MAX_CONCURRENT_RESIZES]
+//! ```
+//!
+//! The server listens on 127.0.0.1:8080 by default, and resizes as many images at once
+//! as there are cores, the other requests waiting for their turn.
+//! Images of more than 50 megapixels are rejected before they are decoded,
+//! and each resize is limited to 1 GiB of memory and 30 seconds,
+//! after which the image is scaled down to the requested dimensions.
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::body::Bytes;
+use axum::extract::{DefaultBodyLimit, Query, State};
+use axum::http::{header, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::routing::post;
+use axum::Router;
+use image::ImageFormat;
+use seamcarving::{encode_image, image_dimensions, load_image, Carver, Metadata, OverBudget};
+use tokio::sync::Semaphore;
+
+const DEFAULT_ADDRESS: &str = "127.0.0.1:8080";
+/// Largest image accepted, in bytes
+const MAX_BODY_SIZE: usize = 32 << 20;
+/// Largest image decoded, in pixels, since small files can decode to huge images
+const MAX_PIXELS: u64 = 50_000_000;
+/// Memory each resize is limited to, in bytes, see [Carver::max_memory]
+const MAX_MEMORY: usize = 1 << 30;
+/// Time after which the rest of a resize is scaled instead of carved
+const TIME_BUDGET: Duration = Duration::from_secs(30);
+
+#[tokio::main]
+async fn main() {
+    let mut args = std::env::args().skip(1);
+    let address = args.next().unwrap_or_else(|| DEFAULT_ADDRESS.to_string());
+    let max_concurrent = args
+        .next()
+        .map(|n| {
+            n.parse()
+                .expect("the maximal number of resizes must be a number")
+        })
+        .unwrap_or_else(|| std::thread::available_parallelism().map_or(1, |n| n.get()));
+    let app = Router::new()
+        .route("/resize", post(resize))
+        .layer(DefaultBodyLimit::max(MAX_BODY_SIZE))
+        .with_state(Arc::new(Semaphore::new(max_concurrent.max(1))));
+    let listener = tokio::net::TcpListener::bind(&address)
+        .await
+        .unwrap_or_else(|e| panic!("cannot listen on {}: {}", address, e));
+    axum::serve(listener, app).await.expect("the server failed");
+}
+
+async fn resize(
+    State(permits): State<Arc<Semaphore>>,
+    Query(params): Query<HashMap<String, String>>,
+    body: Bytes,
+) -> Response {
+    let target = match (dimension(&params, "w"), dimension(&params, "h")) {
+        (Ok(width), Ok(height)) => (width, height),
+        (Err(message), _) | (_, Err(message)) => {
+            return (StatusCode::BAD_REQUEST, message).into_response()
+        }
+    };
+    let permit = permits
+        .acquire_owned()
+        .await
+        .expect("the semaphore is never closed");
+    let carved = tokio::task::spawn_blocking(move || {
+        // Released once the image is carved, even if the client is gone
+        let _permit = permit;
+        carve(&body, target)
+    })
+    .await;
+    match carved {
+        Ok(Ok((bytes, mime))) => ([(header::CONTENT_TYPE, mime)], bytes).into_response(),
+        Ok(Err(message)) => (StatusCode::UNPROCESSABLE_ENTITY, message).into_response(),
+        Err(_) => StatusCode::INTERNAL_SERVER_ERROR.into_response(),
+    }
+}
+
+/// The value of an optional dimension in the query string
+fn dimension(params: &HashMap<String, String>, name: &str) -> Result<Option<u32>, String> {
+    params
+        .get(name)
+        .map(|value| {
+            value
+                .parse()
+                .map_err(|_| format!("{} must be a positive integer", name))
+        })
+        .transpose()
+}
+
+/// Carves an encoded image, and returns it encoded with its mime type
+fn carve(
+    body: &[u8],
+    (width, height): (Option<u32>, Option<u32>),
+) -> Result<(Vec<u8>, &'static str), String> {
+    let format = image::guess_format(body).map_err(|e| e.to_string())?;
+    let (original_width, original_height) = image_dimensions(body).map_err(|e| e.to_string())?;
+    if u64::from(original_width) * u64::from(original_height) > MAX_PIXELS {
+        return Err(format!(
+            "the image is {}x{}, larger than the {} pixels this server decodes",
+            original_width, original_height, MAX_PIXELS
+        ));
+    }
+    let img = load_image(body).map_err(|e| e.to_string())?;
+    let mut carver = Carver::new()
+        .max_memory(MAX_MEMORY)
+        .time_budget(TIME_BUDGET, OverBudget::Scale);
+    if let Some(width) = width {
+        carver = carver.width(width);
+    }
+    if let Some(height) = height {
+        carver = carver.height(height);
+    }
+    let resized = carver.resize(&img).map_err(|e| e.to_string())?;
+    let format = match mime_type(format) {
+        Some(_) => format,
+        None => ImageFormat::Png,
+    };
+    let (bytes, format) = match encode_image(&resized, format) {
+        Ok(bytes) => (bytes, format),
+        // Some formats can be decoded but not encoded
+        Err(_) => {
+            let png = encode_image(&resized, ImageFormat::Png);
+            (png.map_err(|e| e.to_string())?, ImageFormat::Png)
+        }
+    };
+    let (width, height) = resized.dimensions();
+    let bytes = Metadata::read(body).resized(width, height).embed(&bytes);
+    Ok((bytes, mime_type(format).unwrap_or("image/png")))
+}
+
+/// The mime type of the formats the server responds with
+fn mime_type(format: ImageFormat) -> Option<&'static str> {
+    match format {
+        ImageFormat::Png => Some("image/png"),
+        ImageFormat::Jpeg => Some("image/jpeg"),
+        ImageFormat::WebP => Some("image/webp"),
+        ImageFormat::Avif => Some("image/avif"),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use image::{DynamicImage, ImageOutputFormat, Rgb, RgbImage};
+
+    use super::carve;
+
+    #[test]
+    fn carves_encoded_images() {
+        let img = RgbImage::from_fn(20, 10, |x, y| Rgb([(x * 12) as u8, (y * 25) as u8, 0]));
+        let mut png = Vec::new();
+        let img = DynamicImage::ImageRgb8(img);
+        img.write_to(&mut png, ImageOutputFormat::Png).unwrap();
+        let (carved, mime) = carve(&png, (Some(15), None)).unwrap();
+        assert_eq!(mime, "image/png");
+        let carved = image::load_from_memory(&carved).unwrap();
+        assert_eq!(image::GenericImageView::dimensions(&carved), (15, 10));
+        assert!(carve(b"not an image", (Some(15), None)).is_err());
+    }
+
+    #[test]
+    fn rejects_images_too_large_to_decode() {
+        let mut jpeg = Vec::new();
+        let mut encoder = image::jpeg::JpegEncoder::new(&mut jpeg);
+        encoder.encode(&[0; 3], 1, 1, image::ColorType::Rgb8).unwrap();
+        // The start of frame segment holds the height then the width, after its length and
+        // precision, and unlike a PNG header it has no checksum to update
+        let sof = jpeg.windows(2).position(|w| w == [0xFF, 0xC0]).unwrap();
+        jpeg[sof + 5..sof + 7].copy_from_slice(&10_000u16.to_be_bytes());
+        jpeg[sof + 7..sof + 9].copy_from_slice(&10_000u16.to_be_bytes());
+        let error = carve(&jpeg, (Some(15), None)).unwrap_err();
+        assert!(error.contains("10000x10000"), "{}", error);
+    }
+}
@@ -0,0 +1,81 @@
+use image::{GenericImageView, Pixel};
+use num_traits::{NumCast, ToPrimitive};
+
+use crate::pos::Pos;
+use crate::{image_view_to_buffer, Carved, ImageBufferOf};
+
+/// Fraction of the difference with the pixel on the other side of a removed seam
+/// that is added to a pixel when blending
+const BLEND_WEIGHT: f64 = 0.25;
+
+/// Copies a carved image to a buffer.
+/// If `blend` is set, the pixels on both sides of the removed seams are moved slightly
+/// towards each other, to soften the hard edges seams leave on smooth gradients.
+pub(crate) fn carved_to_buffer<IMG: GenericImageView>(
+    carved: &Carved<IMG>,
+    blend: bool,
+) -> ImageBufferOf<IMG>
+where
+    <IMG as GenericImageView>::Pixel: 'static,
+{
+    let mut buffer = image_view_to_buffer(carved);
+    if !blend {
+        return buffer;
+    }
+    let (width, height) = carved.dimensions();
+    for pos in Pos::iter_in_rect(Pos(0, 0), Pos(width, height)) {
+        let Pos(x, y) = pos;
+        // Neighbours are read from the carved image, so that blending one seam
+        // does not influence an adjacent one
+        let left = Some(x)
+            .filter(|_| carved.follows_seam(pos))
+            .map(|x| carved.get_pixel(x - 1, y));
+        let right = Some(x + 1)
+            .filter(|&x| x < width && carved.follows_seam(Pos(x, y)))
+            .map(|x| carved.get_pixel(x, y));
+        if left.is_some() || right.is_some() {
+            let pixel = carved.get_pixel(x, y);
+            let mut blended = pixel;
+            for neighbour in left.iter().chain(right.iter()) {
+                blended = move_towards(blended, pixel, *neighbour);
+            }
+            buffer.put_pixel(x, y, blended);
+        }
+    }
+    buffer
+}
+
+/// Adds to `current` a fraction of the difference between `neighbour` and `pixel`
+fn move_towards<P: Pixel>(current: P, pixel: P, neighbour: P) -> P {
+    let mut moved = current;
+    let channels = pixel.channels().iter().zip(neighbour.channels());
+    for (out, (&p, &n)) in moved.channels_mut().iter_mut().zip(channels) {
+        let (p, n) = (p.to_f64().unwrap_or(0.), n.to_f64().unwrap_or(0.));
+        let value = out.to_f64().unwrap_or(0.) + (n - p) * BLEND_WEIGHT;
+        *out = NumCast::from(value.round()).unwrap_or(*out);
+    }
+    moved
+}
+
+#[cfg(test)]
+mod tests {
+    use image::{GrayImage, Luma};
+
+    use super::*;
+
+    #[test]
+    fn softens_seam_edges() {
+        let img = GrayImage::from_fn(5, 1, |x, _y| Luma([(x * 40) as u8]));
+        let mut carved = Carved::new(&img);
+        carved.remove_seam(&[Pos(2, 0)]);
+        assert_eq!(
+            carved_to_buffer(&carved, false).into_raw(),
+            vec![0, 40, 120, 160]
+        );
+        // The step of 80 around the seam becomes a step of 40
+        assert_eq!(
+            carved_to_buffer(&carved, true).into_raw(),
+            vec![0, 60, 100, 160]
+        );
+    }
+}
@@ -0,0 +1,167 @@
+use image::{GenericImageView, ImageBuffer, Pixel};
+use num_traits::ToPrimitive;
+
+use crate::importance::ImportanceMap;
+use crate::mask::PROTECTED_ENERGY;
+use crate::matrix::Matrix;
+use crate::pos::Pos;
+use crate::{max_pos, resize_with_importance, Rect};
+
+/// Width of the uniform borders (such as letterboxing black bars) on each side of an image
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Default)]
+pub struct Borders {
+    pub top: u32,
+    pub bottom: u32,
+    pub left: u32,
+    pub right: u32,
+}
+
+impl Borders {
+    /// Finds the uniform lines and columns on the sides of an image.
+    ///
+    /// A line is part of a border when none of the channels of its pixels differ by more than
+    /// `tolerance` from the outermost pixel on the same side of the image.
+    pub fn detect<IMG: GenericImageView>(img: &IMG, tolerance: u32) -> Self {
+        let (width, height) = img.dimensions();
+        if width == 0 || height == 0 {
+            return Borders::default();
+        }
+        let close = |reference: IMG::Pixel, x: u32, y: u32| {
+            max_channel_diff(reference, img.get_pixel(x, y)) <= tolerance
+        };
+        let uniform_row = |reference, y| (0..width).all(|x| close(reference, x, y));
+        let uniform_column = |reference, x| (0..height).all(|y| close(reference, x, y));
+
+        let (top_ref, bottom_ref) = (img.get_pixel(0, 0), img.get_pixel(0, height - 1));
+        let top = (0..height).take_while(|&y| uniform_row(top_ref, y)).count() as u32;
+        // The borders never overlap, a uniform image is all top border
+        let bottom = (top..height)
+            .rev()
+            .take_while(|&y| uniform_row(bottom_ref, y))
+            .count() as u32;
+        let (left_ref, right_ref) = (img.get_pixel(0, 0), img.get_pixel(width - 1, 0));
+        let left = (0..width)
+            .take_while(|&x| uniform_column(left_ref, x))
+            .count() as u32;
+        let right = (left..width)
+            .rev()
+            .take_while(|&x| uniform_column(right_ref, x))
+            .count() as u32;
+        Borders {
+            top,
+            bottom,
+            left,
+            right,
+        }
+    }
+
+    /// The part of an image of the given size that is inside the borders
+    pub fn content(&self, (width, height): (u32, u32)) -> Rect {
+        Rect {
+            x: self.left,
+            y: self.top,
+            width: width.saturating_sub(self.left.saturating_add(self.right)),
+            height: height.saturating_sub(self.top.saturating_add(self.bottom)),
+        }
+    }
+}
+
+fn max_channel_diff<P: Pixel>(p1: P, p2: P) -> u32 {
+    p1.channels()
+        .iter()
+        .zip(p2.channels())
+        .map(|(a, b)| {
+            let a = a.to_f64().unwrap_or(0.);
+            let b = b.to_f64().unwrap_or(0.);
+            (a - b).abs().round() as u32
+        })
+        .max()
+        .unwrap_or(0)
+}
+
+impl ImportanceMap {
+    /// Gives importance to everything that is inside the given borders,
+    /// so that the borders are carved away before any of the real content of the image.
+    pub fn inside_borders(size: (u32, u32), borders: &Borders) -> Self {
+        let content = borders.content(size);
+        let size = Pos(size.0, size.1);
+        let bias = Matrix::from_fn(size, |x, y| {
+            if content.contains(x as u32, y as u32) {
+                PROTECTED_ENERGY
+            } else {
+                0
+            }
+        });
+        ImportanceMap::from_bias(size, bias)
+    }
+}
+
+/// Resizes an image like [resize](crate::resize),
+/// but first removes the uniform borders (letterboxing, pillarboxing...),
+/// before touching the real contents of the image.
+///
+/// See [Borders::detect] for the meaning of `tolerance`.
+pub fn resize_removing_borders<IMG: GenericImageView>(
+    img: &IMG,
+    width: u32,
+    height: u32,
+    tolerance: u32,
+) -> ImageBuffer<IMG::Pixel, Vec<<<IMG as GenericImageView>::Pixel as Pixel>::Subpixel>>
+where
+    <IMG as GenericImageView>::Pixel: 'static,
+{
+    let borders = Borders::detect(img, tolerance);
+    let size = max_pos(img);
+    let importance = ImportanceMap::inside_borders((size.0, size.1), &borders);
+    resize_with_importance(img, width, height, &importance).expect("no pixel is forbidden")
+}
+
+#[cfg(test)]
+mod tests {
+    use image::{GrayImage, Luma};
+
+    use super::*;
+
+    fn letterboxed() -> GrayImage {
+        // 2 black columns on the left, 1 black row on the top, a noisy 4x3 image in the middle
+        GrayImage::from_fn(6, 4, |x, y| {
+            Luma([if x < 2 || y < 1 {
+                0
+            } else {
+                (x * 37 + y * 91) as u8
+            }])
+        })
+    }
+
+    #[test]
+    fn detects_borders() {
+        let borders = Borders::detect(&letterboxed(), 0);
+        let expected = Borders {
+            top: 1,
+            bottom: 0,
+            left: 2,
+            right: 0,
+        };
+        assert_eq!(borders, expected);
+    }
+
+    #[test]
+    fn removes_borders_first() {
+        let img = letterboxed();
+        let resized = resize_removing_borders(&img, 4, 3, 0);
+        let expected = GrayImage::from_fn(4, 3, |x, y| *img.get_pixel(x + 2, y + 1));
+        assert_eq!(resized, expected);
+    }
+
+    #[test]
+    fn contents_of_borders_wider_than_the_image() {
+        let borders = Borders {
+            top: 1,
+            bottom: u32::MAX,
+            left: u32::MAX,
+            right: u32::MAX,
+        };
+        let content = borders.content((6, 4));
+        assert_eq!((content.width, content.height), (0, 0));
+    }
+}
@@ -0,0 +1,369 @@
+use alloc::vec::Vec;
+
+use crate::backend::Backend;
+use crate::matrix::Matrix;
+use crate::pos::Pos;
+use crate::seam_finder::{SeamFinder, FORBIDDEN};
+use crate::SeamError;
+#[cfg(feature = "rayon")]
+use crate::Threads;
+
+/// Resizes an image stored as a slice of pixels, row by row, like [resize](crate::resize),
+/// without depending on the `image` crate.
+///
+/// Pixels are arrays of channels, such as `[u8; 4]` for RGBA images.
+/// The horizontal seams are removed after the vertical ones, and the result
+/// is stored row by row as well.
+/// Fails with [SeamError::SizeMismatch] if the number of pixels is not `width * height`.
+///
+/// ```
+/// let pixels: Vec<[u8; 3]> = (0..6 * 4).map(|i| [i as u8 * 10, 0, 0]).collect();
+/// let resized = seamcarving::resize_buffer(&pixels, (6, 4), (4, 3)).unwrap();
+/// assert_eq!(resized.len(), 4 * 3);
+/// ```
+pub fn resize_buffer<T: Copy + Into<i32>, const N: usize>(
+    pixels: &[[T; N]],
+    size: (u32, u32),
+    target: (u32, u32),
+) -> Result<Vec<[T; N]>, SeamError> {
+    resize_with(pixels, size, target, |_finder, _pixels| {})
+}
+
+/// Resizes an image stored as a slice of pixels like [resize_buffer],
+/// computing the energies of wide images on the given threads.
+///
+/// The result is bit-identical to the one of [resize_buffer], whatever the number of threads.
+///
+/// ```
+/// use seamcarving::Threads;
+/// let pixels = vec![[0u8, 128, 255]; 400 * 20];
+/// let threads = Threads::Count(2);
+/// let resized = seamcarving::resize_buffer_parallel(&pixels, (400, 20), (390, 20), &threads);
+/// assert_eq!(resized.unwrap().len(), 390 * 20);
+/// ```
+#[cfg(feature = "rayon")]
+pub fn resize_buffer_parallel<T: Copy + Into<i32> + Send + Sync, const N: usize>(
+    pixels: &[[T; N]],
+    size: (u32, u32),
+    target: (u32, u32),
+    threads: &Threads,
+) -> Result<Vec<[T; N]>, SeamError> {
+    threads.install(|| {
+        resize_with(pixels, size, target, |finder, pixels| {
+            let size = Pos(pixels.width(), pixels.height());
+            finder.par_fill(|pos| energy(pixels, pos.surrounding(size)))
+        })
+    })
+}
+
+/// Resizes an image stored as a slice of pixels like [resize_buffer],
+/// computing its seams with the given backend.
+///
+/// The result is bit-identical to the one of [resize_buffer], whatever the backend.
+///
+/// ```
+/// use seamcarving::Backend;
+/// let pixels = vec![[0u8, 128, 255]; 400 * 20];
+/// let backend = Backend::Auto;
+/// let resized = seamcarving::resize_buffer_with_backend(&pixels, (400, 20), (390, 20), &backend);
+/// assert_eq!(resized.unwrap().len(), 390 * 20);
+/// ```
+#[cfg_attr(not(feature = "simd"), allow(unused_variables))]
+pub fn resize_buffer_with_backend<T: Copy + Into<i32> + Send + Sync, const N: usize>(
+    pixels: &[[T; N]],
+    size: (u32, u32),
+    target: (u32, u32),
+    backend: &Backend,
+) -> Result<Vec<[T; N]>, SeamError> {
+    let seams = size.0.saturating_sub(target.0) + size.1.saturating_sub(target.1);
+    let vectorized = match backend.resolve(size, seams) {
+        #[cfg(feature = "rayon")]
+        Backend::Threads(threads) => return resize_buffer_parallel(pixels, size, target, &threads),
+        #[cfg(feature = "gpu")]
+        Backend::Gpu(gpu) => {
+            return resize_with(pixels, size, target, |finder, pixels| {
+                // After each seam, only the energies around it are computed again
+                if finder.is_unfilled() {
+                    gpu.fill(finder, pixels)
+                }
+            })
+        }
+        Backend::Scalar => false,
+        _ => true,
+    };
+    resize_with(pixels, size, target, |finder, _pixels| {
+        #[cfg(feature = "simd")]
+        finder.set_vectorized(vectorized);
+    })
+}
+
+/// Memory kept between resizes of buffers, for services that resize many images:
+/// once it has grown to the size of the largest of them, resizing an image
+/// allocates nothing but the result, or nothing at all with
+/// [resize_buffer_into](BufferScratch::resize_buffer_into).
+///
+/// ```
+/// use seamcarving::BufferScratch;
+/// let mut scratch = BufferScratch::new();
+/// let mut resized = Vec::new();
+/// for width in [40, 36, 44] {
+///     let pixels = vec![[0u8, 128, 255]; width as usize * 10];
+///     scratch.resize_buffer_into(&pixels, (width, 10), (30, 8), &mut resized).unwrap();
+///     assert_eq!(resized.len(), 30 * 8);
+/// }
+/// ```
+#[derive(Clone, Debug)]
+pub struct BufferScratch<P> {
+    pixels: Matrix<P>,
+    // The memory of the pixels before they were transposed
+    spare: Vec<P>,
+    finder: SeamFinder,
+    seam: Vec<Pos>,
+}
+
+impl<P> BufferScratch<P> {
+    /// Scratch memory that has not grown yet
+    pub fn new() -> Self {
+        BufferScratch {
+            pixels: Matrix::from_vec(Pos(0, 0), Vec::new()).expect("the matrix is empty"),
+            spare: Vec::new(),
+            finder: SeamFinder::new(Pos(0, 0)),
+            seam: Vec::new(),
+        }
+    }
+
+    /// Scratch memory holding the given pixels, and a finder that may already be filled for them
+    #[cfg(feature = "png-stream")]
+    pub(crate) fn with_pixels(pixels: Matrix<P>, finder: SeamFinder) -> Self {
+        BufferScratch {
+            pixels,
+            spare: Vec::new(),
+            finder,
+            seam: Vec::new(),
+        }
+    }
+}
+
+impl<P> Default for BufferScratch<P> {
+    fn default() -> Self {
+        BufferScratch::new()
+    }
+}
+
+impl<T: Copy + Into<i32>, const N: usize> BufferScratch<[T; N]> {
+    /// Resizes an image stored as a slice of pixels like [resize_buffer]
+    pub fn resize_buffer(
+        &mut self,
+        pixels: &[[T; N]],
+        size: (u32, u32),
+        target: (u32, u32),
+    ) -> Result<Vec<[T; N]>, SeamError> {
+        let mut resized = Vec::new();
+        self.resize_buffer_into(pixels, size, target, &mut resized)?;
+        Ok(resized)
+    }
+
+    /// Resizes an image stored as a slice of pixels like [resize_buffer],
+    /// replacing the contents of `resized` with the result
+    pub fn resize_buffer_into(
+        &mut self,
+        pixels: &[[T; N]],
+        size: (u32, u32),
+        target: (u32, u32),
+        resized: &mut Vec<[T; N]>,
+    ) -> Result<(), SeamError> {
+        self.resize_with(pixels, size, target, resized, |_finder, _pixels| {})
+    }
+
+    /// Resizes the image, calling `prefill` before each seam is extracted
+    fn resize_with(
+        &mut self,
+        pixels: &[[T; N]],
+        (width, height): (u32, u32),
+        target: (u32, u32),
+        resized: &mut Vec<[T; N]>,
+        prefill: impl FnMut(&mut SeamFinder, &Matrix<[T; N]>),
+    ) -> Result<(), SeamError> {
+        self.pixels
+            .copy_from(Pos(width, height), pixels)
+            .ok_or(SeamError::SizeMismatch)?;
+        self.finder.reset(Pos(width, height))?;
+        self.carve(target, resized, prefill)
+    }
+
+    /// Removes the seams from the pixels, starting with the vertical ones, which the finder
+    /// is ready for, and calling `prefill` before each seam is extracted
+    pub(crate) fn carve(
+        &mut self,
+        (target_width, target_height): (u32, u32),
+        resized: &mut Vec<[T; N]>,
+        mut prefill: impl FnMut(&mut SeamFinder, &Matrix<[T; N]>),
+    ) -> Result<(), SeamError> {
+        let Pos(width, height) = Pos(self.pixels.width(), self.pixels.height());
+        self.carve_columns(width.saturating_sub(target_width), &mut prefill)?;
+        self.pixels.transpose_reusing(&mut self.spare);
+        self.finder.reset(Pos(self.pixels.width(), self.pixels.height()))?;
+        self.carve_columns(height.saturating_sub(target_height), &mut prefill)?;
+        // Transposing leaves no gap between the rows
+        self.pixels.transpose_reusing(&mut self.spare);
+        resized.clear();
+        resized.extend_from_slice(self.pixels.as_slice());
+        Ok(())
+    }
+
+    /// Removes `count` vertical seams from the pixels, the finder being ready for them
+    fn carve_columns(
+        &mut self,
+        count: u32,
+        prefill: &mut impl FnMut(&mut SeamFinder, &Matrix<[T; N]>),
+    ) -> Result<(), SeamError> {
+        let BufferScratch {
+            pixels: matrix,
+            finder,
+            seam,
+            ..
+        } = self;
+        for _ in 0..count {
+            let pixels = &*matrix;
+            let size = Pos(pixels.width(), pixels.height());
+            prefill(finder, pixels);
+            finder.extract_seam_into(|pos| energy(pixels, pos.surrounding(size)), seam)?;
+            matrix.remove_seam(seam);
+        }
+        Ok(())
+    }
+}
+
+/// Resizes the image, calling `prefill` before each seam is extracted
+fn resize_with<T: Copy + Into<i32>, const N: usize>(
+    pixels: &[[T; N]],
+    size: (u32, u32),
+    target: (u32, u32),
+    prefill: impl FnMut(&mut SeamFinder, &Matrix<[T; N]>),
+) -> Result<Vec<[T; N]>, SeamError> {
+    let mut resized = Vec::new();
+    BufferScratch::new().resize_with(pixels, size, target, &mut resized, prefill)?;
+    Ok(resized)
+}
+
+/// Gradient energy of a pixel, computed from its top, bottom, left and right neighbours
+fn energy<T: Copy + Into<i32>, const N: usize>(
+    pixels: &Matrix<[T; N]>,
+    surrounding: [Pos; 4],
+) -> u32 {
+    gradient(surrounding.map(|pos| pixels[pos]))
+}
+
+/// Gradient energy of a pixel, from the values of its top, bottom, left and right neighbours
+pub(crate) fn gradient<T: Copy + Into<i32>, const N: usize>(
+    [top, bottom, left, right]: [[T; N]; 4],
+) -> u32 {
+    let square_diff = |a: [T; N], b: [T; N]| {
+        a.iter().zip(b.iter()).fold(0u32, |sum, (&a, &b)| {
+            let diff = (a.into() - b.into()).unsigned_abs();
+            sum.saturating_add(diff.saturating_mul(diff))
+        })
+    };
+    square_diff(top, bottom)
+        .saturating_add(square_diff(left, right))
+        .min(FORBIDDEN - 1)
+}
+
+#[cfg(all(test, feature = "image"))]
+mod tests {
+    use image::{Rgba, RgbaImage};
+
+    use super::*;
+
+    #[test]
+    fn resizes_like_images() {
+        let img = RgbaImage::from_fn(24, 16, |x, y| {
+            Rgba([(x * x) as u8, (y * 13) as u8, ((x + y) * 7) as u8, 255])
+        });
+        let pixels: Vec<[u8; 4]> = img.pixels().map(|p| p.0).collect();
+        let resized = resize_buffer(&pixels, (24, 16), (17, 11)).unwrap();
+        let expected = crate::resize(&img, 17, 11);
+        let expected: Vec<[u8; 4]> = expected.pixels().map(|p| p.0).collect();
+        assert_eq!(resized, expected);
+        assert!(resize_buffer(&pixels, (24, 15), (17, 11)).is_err());
+    }
+
+    #[test]
+    #[cfg(feature = "rayon")]
+    fn resizes_on_the_given_threads() {
+        let (width, height) = (700, 40);
+        let pixels: Vec<[u8; 1]> = (0..width * height)
+            .map(|i| [((i % width) * (i / width) % 251) as u8])
+            .collect();
+        let expected = resize_buffer(&pixels, (width, height), (600, 30)).unwrap();
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(3)
+            .build()
+            .unwrap();
+        for threads in [
+            Threads::Current,
+            Threads::Count(2),
+            Threads::Pool(pool.into()),
+        ] {
+            let resized = resize_buffer_parallel(&pixels, (width, height), (600, 30), &threads);
+            assert_eq!(resized.unwrap(), expected);
+        }
+    }
+
+    #[test]
+    fn reuses_scratch_memory() {
+        let pixels = |width: u32, height: u32| -> Vec<[u8; 3]> {
+            (0..width * height)
+                .map(|i| [(i % width * 9) as u8, (i / width * 5) as u8, (i % 13) as u8])
+                .collect()
+        };
+        let mut scratch = BufferScratch::new();
+        let mut resized = Vec::new();
+        let capacities = |scratch: &BufferScratch<_>, resized: &Vec<_>| {
+            let seam = scratch.seam.capacity();
+            (
+                scratch.pixels.capacity(),
+                scratch.spare.capacity(),
+                seam,
+                resized.capacity(),
+            )
+        };
+        let mut grown = None;
+        for (size, target) in [
+            ((40, 30), (31, 22)),
+            ((36, 30), (30, 22)),
+            ((40, 28), (31, 20)),
+        ] {
+            let pixels = pixels(size.0, size.1);
+            scratch
+                .resize_buffer_into(&pixels, size, target, &mut resized)
+                .unwrap();
+            assert_eq!(resized, resize_buffer(&pixels, size, target).unwrap());
+            let grown = *grown.get_or_insert(capacities(&scratch, &resized));
+            assert_eq!(capacities(&scratch, &resized), grown);
+        }
+        let wrong_size = scratch.resize_buffer(&pixels(4, 4), (4, 5), (3, 3));
+        assert_eq!(wrong_size, Err(SeamError::SizeMismatch));
+    }
+
+    #[test]
+    fn resizes_identically_on_any_backend() {
+        let (width, height) = (300, 20);
+        let pixels: Vec<[u8; 2]> = (0..width * height)
+            .map(|i| [(i % 7 * 40) as u8, (i / width * 11) as u8])
+            .collect();
+        let expected = resize_buffer(&pixels, (width, height), (250, 15)).unwrap();
+        let backends = [
+            Backend::Auto,
+            Backend::Scalar,
+            #[cfg(feature = "simd")]
+            Backend::Simd,
+            #[cfg(feature = "rayon")]
+            Backend::Threads(Threads::Count(2)),
+        ];
+        for backend in backends {
+            let resized = resize_buffer_with_backend(&pixels, (width, height), (250, 15), &backend);
+            assert_eq!(resized.unwrap(), expected);
+        }
+    }
+}
@@ -0,0 +1,163 @@
+use std::time::Duration;
+
+use image::{GenericImageView, Luma};
+
+use crate::carver::{Algorithm, AxisOrder, Carver, Dimension, OverBudget};
+use crate::error::ConfigError;
+use crate::importance::ImportanceMap;
+use crate::mask::Rect;
+use crate::pos::BorderMode;
+
+/// Collects the settings of a [Carver], and checks that they work together
+/// before any image is resized, see [Carver::builder].
+///
+/// Each method does the same as the method of [Carver] of the same name.
+///
+/// ```
+/// use seamcarving::{AxisOrder, Carver, ConfigError, Dimension};
+/// let carver = Carver::builder().width(Dimension::Percent(50)).build();
+/// assert!(carver.is_ok());
+/// let enlarging = Carver::builder().width(Dimension::Percent(150)).build();
+/// assert_eq!(enlarging.unwrap_err(), ConfigError::Enlargement);
+/// ```
+#[derive(Clone, Default)]
+pub struct CarverBuilder {
+    carver: Carver,
+}
+
+macro_rules! setters {
+    ($($(#[$attr:meta])* fn $name:ident($($arg:ident: $ty:ty),*);)*) => {
+        $(
+            $(#[$attr])*
+            pub fn $name(mut self, $($arg: $ty),*) -> Self {
+                self.carver = self.carver.$name($($arg),*);
+                self
+            }
+        )*
+    };
+}
+
+impl CarverBuilder {
+    setters! {
+        /// See [Carver::width]
+        fn width(width: impl Into<Dimension>);
+        /// See [Carver::height]
+        fn height(height: impl Into<Dimension>);
+        /// See [Carver::shrink_width_by]
+        fn shrink_width_by(fraction: f64);
+        /// See [Carver::shrink_height_by]
+        fn shrink_height_by(fraction: f64);
+        /// See [Carver::importance]
+        fn importance(importance: &ImportanceMap);
+        /// See [Carver::carve_region]
+        fn carve_region(region: Rect);
+        /// See [Carver::pin_columns]
+        fn pin_columns(columns: impl IntoIterator<Item = u32>);
+        /// See [Carver::pin_rows]
+        fn pin_rows(rows: impl IntoIterator<Item = u32>);
+        /// See [Carver::max_local_removal]
+        fn max_local_removal(radius: u32, max_seams: u32);
+        /// See [Carver::max_seam_energy]
+        fn max_seam_energy(max_energy: u32);
+        /// See [Carver::energy_budget]
+        fn energy_budget(budget: u64);
+        /// See [Carver::min_similarity]
+        fn min_similarity(ssim: f64, every_n_seams: u32);
+        /// See [Carver::time_budget]
+        fn time_budget(budget: Duration, then: OverBudget);
+        /// See [Carver::compact_every]
+        fn compact_every(seams: u32);
+        /// See [Carver::max_cpu]
+        fn max_cpu(fraction: f32);
+        /// See [Carver::axis_order]
+        fn axis_order(order: AxisOrder);
+        /// See [Carver::algorithm]
+        fn algorithm(algorithm: Algorithm);
+        /// See [Carver::wrapping]
+        fn wrapping(wrap: bool);
+        /// See [Carver::equirectangular]
+        fn equirectangular(equirectangular: bool);
+        /// See [Carver::keep_borders]
+        fn keep_borders(keep_borders: bool);
+        /// See [Carver::border_mode]
+        fn border_mode(border: BorderMode);
+        /// See [Carver::quantize_energy]
+        fn quantize_energy(quantized: bool);
+        /// See [Carver::max_memory]
+        fn max_memory(bytes: usize);
+        /// See [Carver::blend_seams]
+        fn blend_seams(blend: bool);
+        /// See [Carver::measure_timings]
+        fn measure_timings(timed: bool);
+        /// See [Carver::debug_preview]
+        #[cfg(feature = "preview")]
+        fn debug_preview(preview: bool);
+    }
+
+    /// See [Carver::valid_region]
+    pub fn valid_region<M: GenericImageView<Pixel = Luma<u8>>>(mut self, valid: &M) -> Self {
+        self.carver = self.carver.valid_region(valid);
+        self
+    }
+
+    /// Returns the carver, or the first reason why its settings cannot work together
+    pub fn build(self) -> Result<Carver, ConfigError> {
+        self.carver.validate()?;
+        Ok(self.carver)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use image::GrayImage;
+
+    use super::*;
+
+    #[test]
+    fn rejects_incompatible_settings() {
+        let builder = Carver::builder().width(10).height(Dimension::Scale(0.5));
+        let img = GrayImage::from_fn(20, 10, |x, y| Luma([(x * y) as u8]));
+        let carver = builder.clone().build().unwrap();
+        assert_eq!(carver.resize(&img).unwrap().dimensions(), (10, 5));
+
+        let failing = [
+            (
+                builder.clone().height(Dimension::Scale(1.5)),
+                ConfigError::Enlargement,
+            ),
+            (
+                builder.clone().shrink_width_by(f64::NAN),
+                ConfigError::InvalidDimension,
+            ),
+            (
+                builder.clone().max_local_removal(3, 0),
+                ConfigError::NothingRemovable,
+            ),
+            (
+                builder
+                    .clone()
+                    .max_local_removal(3, 1)
+                    .axis_order(AxisOrder::Greedy),
+                ConfigError::GreedyLocalRemoval,
+            ),
+            (
+                builder.clone().wrapping(true).keep_borders(true),
+                ConfigError::WrappingKeptBorders,
+            ),
+            (
+                builder.clone().equirectangular(true).keep_borders(true),
+                ConfigError::WrappingKeptBorders,
+            ),
+            (
+                builder
+                    .clone()
+                    .importance(&ImportanceMap::from_mask(&GrayImage::new(4, 4)))
+                    .valid_region(&GrayImage::new(5, 4)),
+                ConfigError::MaskSizeMismatch,
+            ),
+        ];
+        for (builder, error) in failing {
+            assert_eq!(builder.build().err(), Some(error));
+        }
+    }
+}
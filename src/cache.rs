@@ -0,0 +1,261 @@
+use std::convert::TryInto;
+use std::fs;
+use std::hash::Hasher;
+use std::io;
+use std::path::PathBuf;
+
+use image::{GenericImageView, Pixel};
+use num_traits::ToPrimitive;
+
+use crate::blend::carved_to_buffer;
+use crate::pos::Pos;
+use crate::{image_view_to_buffer, Carved, ImageBufferOf, Rotated};
+
+/// Version of the format of the cache files.
+/// It is part of the keys, so that files written by other versions are ignored.
+const FORMAT_VERSION: u32 = 2;
+
+/// A directory in which the seams removed from images are stored,
+/// so that resizing the same image again with the same settings
+/// does not need to compute them again.
+///
+/// Entries are keyed by a hash of the contents of the image and of the settings of the carver,
+/// see [Carver::resize_cached](crate::Carver::resize_cached).
+#[derive(Clone, Debug)]
+pub struct SeamCache {
+    dir: PathBuf,
+}
+
+impl SeamCache {
+    /// Uses the given directory, which is created when the first entry is stored
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        SeamCache { dir: dir.into() }
+    }
+
+    fn path(&self, key: u64) -> PathBuf {
+        self.dir.join(format!("{:016x}.seams", key))
+    }
+
+    /// Returns None if the entry is missing or unreadable
+    pub(crate) fn load(&self, key: u64) -> Option<SeamLog> {
+        SeamLog::decode(&fs::read(self.path(key)).ok()?)
+    }
+
+    pub(crate) fn store(&self, key: u64, log: &SeamLog) -> io::Result<()> {
+        fs::create_dir_all(&self.dir)?;
+        // Write then rename, so that concurrent readers never see a partial entry
+        let tmp = self.path(key).with_extension("tmp");
+        fs::write(&tmp, log.encode())?;
+        fs::rename(tmp, self.path(key))
+    }
+}
+
+/// Direction of a removed seam
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum Axis {
+    /// A vertical seam, that removes a column
+    Vertical,
+    /// A horizontal seam, that removes a row.
+    /// Seam logs store them in the coordinates of the rotated image.
+    Horizontal,
+}
+
+/// The seams removed by a two-axis resize, in the order they were removed
+#[derive(Clone, Debug, Default)]
+pub(crate) struct SeamLog {
+    seams: Vec<(Axis, Vec<Pos>)>,
+}
+
+impl SeamLog {
+    pub fn push(&mut self, axis: Axis, seam: Vec<Pos>) {
+        self.seams.push((axis, seam));
+    }
+
+    pub fn seams(&self) -> &[(Axis, Vec<Pos>)] {
+        &self.seams
+    }
+
+    fn encode(&self) -> Vec<u8> {
+        let mut bytes = (self.seams.len() as u32).to_le_bytes().to_vec();
+        for (axis, seam) in &self.seams {
+            push_record(&mut bytes, *axis, seam);
+        }
+        bytes
+    }
+
+    fn decode(bytes: &[u8]) -> Option<SeamLog> {
+        let mut words = words(bytes);
+        let count = words.next()?;
+        let seams = (0..count)
+            .map(|_| decode_record(&mut words))
+            .collect::<Option<_>>()?;
+        Some(SeamLog { seams })
+    }
+}
+
+/// Appends the record of a seam to `bytes`, as in the files of seam logs:
+/// its axis, its length and its positions, in little-endian words
+pub(crate) fn push_record(bytes: &mut Vec<u8>, axis: Axis, seam: &[Pos]) {
+    let mut push = |v: u32| bytes.extend_from_slice(&v.to_le_bytes());
+    push(match axis {
+        Axis::Vertical => 0,
+        Axis::Horizontal => 1,
+    });
+    push(seam.len() as u32);
+    for &Pos(x, y) in seam {
+        push(x);
+        push(y);
+    }
+}
+
+/// The little-endian words of the files of seam logs
+pub(crate) fn words(bytes: &[u8]) -> impl Iterator<Item = u32> + '_ {
+    bytes
+        .chunks_exact(4)
+        .map(|c| u32::from_le_bytes(c.try_into().expect("chunks of 4 bytes")))
+}
+
+/// Decodes the record of a seam written by [push_record] from the next words,
+/// or returns None if they end before it does, or do not start with an axis
+pub(crate) fn decode_record(words: &mut impl Iterator<Item = u32>) -> Option<(Axis, Vec<Pos>)> {
+    let axis = match words.next()? {
+        0 => Axis::Vertical,
+        1 => Axis::Horizontal,
+        _ => return None,
+    };
+    let len = words.next()?;
+    let seam = (0..len)
+        .map(|_| Some(Pos(words.next()?, words.next()?)))
+        .collect::<Option<_>>()?;
+    Some((axis, seam))
+}
+
+/// Removes the logged seams from the image again.
+/// Returns None if the seams do not fit in the image.
+///
+/// If `blend` is set, the seam edges are blended like during the resize that logged them:
+/// after each run of seams in the same direction, or after each seam if `seam_by_seam` is set.
+pub(crate) fn replay<IMG: GenericImageView>(
+    img: &IMG,
+    log: &SeamLog,
+    blend: bool,
+    seam_by_seam: bool,
+) -> Option<ImageBufferOf<IMG>>
+where
+    <IMG as GenericImageView>::Pixel: 'static,
+{
+    replay_every(img, log, blend, seam_by_seam, usize::MAX, |_, _| ())
+}
+
+/// Removes the logged seams from the image again like [replay], and calls `on_step`
+/// with the image and the number of seams removed from it, every `every` seams
+/// and after the last one.
+/// Runs of seams are interrupted at each step, so the seam edges may be blended slightly
+/// differently than during the resize.
+pub(crate) fn replay_every<IMG: GenericImageView>(
+    img: &IMG,
+    log: &SeamLog,
+    blend: bool,
+    seam_by_seam: bool,
+    every: usize,
+    mut on_step: impl FnMut(&ImageBufferOf<IMG>, usize),
+) -> Option<ImageBufferOf<IMG>>
+where
+    <IMG as GenericImageView>::Pixel: 'static,
+{
+    let mut current = image_view_to_buffer(img);
+    let mut remaining = &log.seams[..];
+    let mut removed = 0;
+    while let Some((axis, _)) = remaining.first() {
+        // Consecutive seams in the same direction are removed from the same view
+        let run = if blend && seam_by_seam {
+            1
+        } else {
+            remaining.iter().take_while(|(a, _)| a == axis).count()
+        };
+        let run = run.min(every - removed % every);
+        let seams = remaining[..run].iter().map(|(_, seam)| seam);
+        current = match axis {
+            Axis::Vertical => carved_to_buffer(&replay_seams(&current, seams)?, blend),
+            Axis::Horizontal => {
+                let rotated = Rotated(&current);
+                let carved = carved_to_buffer(&replay_seams(&rotated, seams)?, blend);
+                image_view_to_buffer(&Rotated(&carved))
+            }
+        };
+        remaining = &remaining[run..];
+        removed += run;
+        if removed % every == 0 || remaining.is_empty() {
+            on_step(&current, removed);
+        }
+    }
+    Some(current)
+}
+
+fn replay_seams<'a, 'b, IMG: GenericImageView>(
+    img: &'a IMG,
+    seams: impl Iterator<Item = &'b Vec<Pos>>,
+) -> Option<Carved<'a, IMG>> {
+    let mut carved = Carved::new(img);
+    for seam in seams {
+        let (width, height) = carved.dimensions();
+        // Seams go from the bottom to the top of the image
+        let fits = seam.len() == height as usize
+            && seam
+                .iter()
+                .rev()
+                .enumerate()
+                .all(|(y, &Pos(px, py))| py as usize == y && px < width);
+        if !fits {
+            return None;
+        }
+        carved.remove_seam(seam);
+    }
+    Some(carved)
+}
+
+/// 64-bit FNV-1a hash.
+/// Unlike the hasher of the standard library, it is guaranteed to stay the same
+/// across platforms and compiler versions, so it can be used for file names.
+pub(crate) struct ContentHasher(u64);
+
+impl ContentHasher {
+    pub fn new() -> Self {
+        let mut hasher = ContentHasher(0xcbf2_9ce4_8422_2325);
+        hasher.write_u32(FORMAT_VERSION);
+        hasher
+    }
+
+    /// Hashes the dimensions and the pixels of an image
+    pub fn write_image<IMG: GenericImageView>(&mut self, img: &IMG) {
+        let (width, height) = img.dimensions();
+        self.write_u32(width);
+        self.write_u32(height);
+        for (_x, _y, pixel) in img.pixels() {
+            for channel in pixel.channels() {
+                self.write_u64(channel.to_f64().unwrap_or(0.).to_bits());
+            }
+        }
+    }
+}
+
+impl Hasher for ContentHasher {
+    fn finish(&self) -> u64 {
+        self.0
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.0 ^= u64::from(byte);
+            self.0 = self.0.wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+
+    fn write_u32(&mut self, i: u32) {
+        self.write(&i.to_le_bytes())
+    }
+
+    fn write_u64(&mut self, i: u64) {
+        self.write(&i.to_le_bytes())
+    }
+}
@@ -1,5 +1,8 @@
+use std::ops::Range;
+
 use image::{GenericImageView, ImageBuffer, Pixel};
 
+use crate::mask::Rect;
 use crate::matrix::Matrix;
 use crate::pos::Pos;
 use crate::{image_view_to_buffer, max_pos};
@@ -28,18 +31,53 @@ impl<'a, IMG: GenericImageView> Carved<'a, IMG> {
         self.pos_aliases.remove_seam(seam);
         self.removed += 1;
     }
+    /// For every position of the carved image, the column it comes from in the original image
+    pub(crate) fn into_pos_aliases(self) -> Matrix<u32> {
+        self.pos_aliases
+    }
+    /// Reads the pixels of another image of the same dimensions from now on
+    pub(crate) fn set_image(&mut self, img: &'a IMG) {
+        assert_eq!(
+            self.img.dimensions(),
+            img.dimensions(),
+            "the new image must have the same dimensions as the original one"
+        );
+        self.img = img;
+    }
+    /// The columns of row `y` of the carved image whose pixels come from
+    /// the given columns of the original image
+    pub(crate) fn columns_from(&self, y: u32, columns: Range<u32>) -> Range<u32> {
+        let region = Rect {
+            x: 0,
+            y,
+            width: self.pos_aliases.width(),
+            height: 1,
+        };
+        let row = self.pos_aliases.view(region).row(0);
+        // Removing seams keeps the pixels of each row in order
+        let start = row.partition_point(|&x| x < columns.start);
+        let end = row.partition_point(|&x| x < columns.end);
+        start as u32..end as u32
+    }
+    /// Frees the memory used by the positions of the removed pixels
+    pub(crate) fn shrink_to_fit(&mut self) {
+        self.pos_aliases.shrink_to_fit();
+    }
     /// Given a position in the carved image, return a position in the original
     #[inline(always)]
-    fn transform_pos(&self, pos: Pos) -> Pos {
+    pub(crate) fn transform_pos(&self, pos: Pos) -> Pos {
         let mut pos = pos;
         pos.0 = self.pos_aliases[pos];
         pos
     }
+    /// Whether a seam was removed between the given position and the one on its left
+    pub(crate) fn follows_seam(&self, Pos(x, y): Pos) -> bool {
+        x > 0 && self.pos_aliases[Pos(x, y)] - self.pos_aliases[Pos(x - 1, y)] > 1
+    }
 }
 
-impl<'a, 'b, IMG: GenericImageView>
-    Into<ImageBuffer<IMG::Pixel, Vec<<<IMG as GenericImageView>::Pixel as Pixel>::Subpixel>>>
-    for &'b Carved<'a, IMG>
+impl<'a, 'b, IMG: GenericImageView> From<&'b Carved<'a, IMG>>
+    for ImageBuffer<IMG::Pixel, Vec<<<IMG as GenericImageView>::Pixel as Pixel>::Subpixel>>
 where
     <IMG as GenericImageView>::Pixel: 'static,
 {
@@ -47,10 +85,8 @@ where
     ///
     /// Creating the buffer is expensive, but accessing image data from a buffer is then
     /// faster than from [Carved](Carved) instance.
-    fn into(
-        self,
-    ) -> ImageBuffer<IMG::Pixel, Vec<<<IMG as GenericImageView>::Pixel as Pixel>::Subpixel>> {
-        image_view_to_buffer(self)
+    fn from(carved: &'b Carved<'a, IMG>) -> Self {
+        image_view_to_buffer(carved)
     }
 }
 
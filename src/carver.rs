@@ -0,0 +1,1655 @@
+use image::imageops::{crop_imm, resize, FilterType};
+use image::{
+    DynamicImage, GenericImage, GenericImageView, GrayImage, ImageBuffer, Luma, LumaA, Pixel,
+};
+
+use std::borrow::Cow;
+use std::hash::Hasher;
+use std::ops::ControlFlow;
+use std::time::{Duration, Instant};
+
+use crate::animation::Frame;
+use crate::blend::carved_to_buffer;
+use crate::builder::CarverBuilder;
+use crate::cache::{replay, replay_every, Axis, ContentHasher, SeamCache, SeamLog};
+use crate::checkpoint::Checkpoint;
+use crate::energy::luminosity;
+use crate::error::{CheckpointError, ConfigError};
+use crate::importance::ImportanceMap;
+use crate::index_map::SeamIndexMap;
+use crate::layers::Layers;
+use crate::mask::Rect;
+use crate::matrix::Matrix;
+use crate::metrics::gray_ssim;
+use crate::pos::{BorderMode, Pos};
+use crate::remap::{scale_annotation, Annotation, CoordinateMap};
+use crate::seam_finder::{SeamFinder, FORBIDDEN};
+use crate::sequence::SeamSequence;
+use crate::validate::{validate, InputMask};
+use crate::{
+    image_view_to_buffer, max_pos, resize_greedy, resize_reporting, scale_down, validity_matrix,
+    Carvable, ImageBufferOf, Limits, PixelBuffer, Plan, Report, SeamError, SeamObserver,
+    SeamStats, ValidationError,
+};
+
+/// The position in the original image of each pixel of a carved image
+type Origins = Matrix<Pos>;
+
+/// A target width or height, relative to the size of the image or absolute
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Dimension {
+    /// An exact number of pixels
+    Pixels(u32),
+    /// A percentage of the original dimension, `Percent(80)` removes 20% of the pixels
+    Percent(u32),
+    /// A fraction of the original dimension, between 0 and 1
+    Scale(f64),
+}
+
+impl Dimension {
+    /// The number of pixels this dimension represents for an image dimension of `original`.
+    /// The result never exceeds `original`: images are only ever reduced.
+    pub fn resolve(self, original: u32) -> u32 {
+        let pixels = match self {
+            Dimension::Pixels(pixels) => pixels,
+            Dimension::Percent(percent) => ((u64::from(original) * u64::from(percent) + 50) / 100)
+                .min(u64::from(original)) as u32,
+            Dimension::Scale(scale) => (f64::from(original) * scale.max(0.)).round() as u32,
+        };
+        pixels.min(original)
+    }
+}
+
+impl From<u32> for Dimension {
+    fn from(pixels: u32) -> Self {
+        Dimension::Pixels(pixels)
+    }
+}
+
+/// Mean energy of the pixels of a seam above which [Algorithm::Hybrid] scales images
+/// instead of carving them, unless [Carver::max_seam_energy] is set.
+/// It is about the energy of pixels where 8-bit RGB images change by 13 levels per channel.
+pub const HYBRID_MAX_SEAM_ENERGY: u32 = 1000;
+
+/// How seams are chosen. All algorithms find the seams with a dynamic programming table,
+/// they differ by the energy of the pixels and by the way both axes are carved.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Default)]
+pub enum Algorithm {
+    /// The energy of a pixel is the gradient of the image around it,
+    /// and vertical seams are removed before horizontal ones
+    #[default]
+    Backward,
+    /// The energy of a pixel is the difference between the pixels that become neighbours
+    /// once it is removed. This avoids creating new edges where seams cross
+    /// smooth objects, at the cost of sometimes carving through details.
+    Forward,
+    /// Like [Backward](Algorithm::Backward), but the cheapest of the vertical and horizontal
+    /// seams is removed at each step, see [AxisOrder::Greedy]
+    Greedy,
+    /// Like [Backward](Algorithm::Backward), but the image is scaled instead of carved
+    /// once seams become too visible, see [Carver::max_seam_energy].
+    /// The limit defaults to [HYBRID_MAX_SEAM_ENERGY].
+    Hybrid,
+}
+
+/// The order in which vertical and horizontal seams are removed
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Default)]
+pub enum AxisOrder {
+    /// Removes all the vertical seams, and then all the horizontal seams
+    #[default]
+    WidthFirst,
+    /// At each step, removes the cheapest of the best vertical seam
+    /// and the best horizontal seam, comparing the mean energy of their pixels.
+    ///
+    /// This is slower, as the seams of both directions have to be computed again
+    /// after each removal, but avoids removing an expensive vertical seam
+    /// when a horizontal one would have been almost free.
+    /// [max_local_removal](Carver::max_local_removal) limits are not kept from one step
+    /// to the next in this mode.
+    Greedy,
+}
+
+/// What to do when the [time budget](Carver::time_budget) of a resize is exhausted
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum OverBudget {
+    /// Returns the image as it is, larger than requested
+    Stop,
+    /// Scales the image down to the requested size, which is much faster than carving it
+    Scale,
+}
+
+/// Configures a resizing operation, to be run on one or several images
+///
+/// ```no_run
+/// use seamcarving::{Carver, Dimension};
+/// let img = image::open("./my_image.jpg").unwrap();
+/// let resized = Carver::new()
+///     .width(Dimension::Percent(80))
+///     .shrink_height_by(0.1)
+///     .resize(&img)
+///     .unwrap();
+/// resized.save("./resized.jpg");
+/// ```
+#[derive(Clone, Debug)]
+pub struct Carver {
+    width: Dimension,
+    height: Dimension,
+    importance: Option<ImportanceMap>,
+    valid: Option<image::GrayImage>,
+    region: Option<Rect>,
+    pinned_columns: Vec<u32>,
+    pinned_rows: Vec<u32>,
+    max_local_removal: Option<(u32, u32)>,
+    max_seam_energy: Option<u32>,
+    energy_budget: Option<u64>,
+    // Lowest similarity to the original, and number of seams between measures
+    min_similarity: Option<(f64, u32)>,
+    axis_order: AxisOrder,
+    algorithm: Algorithm,
+    time_budget: Option<(Duration, OverBudget)>,
+    compact_every: Option<u32>,
+    max_cpu: Option<f32>,
+    wrap: bool,
+    equirectangular: bool,
+    // First row of the stripe being carved, and height of the whole image
+    stripe_rows: Option<(u32, u32)>,
+    keep_borders: bool,
+    border: BorderMode,
+    quantized: bool,
+    max_memory: Option<usize>,
+    blend: bool,
+    timed: bool,
+    #[cfg(feature = "preview")]
+    preview: bool,
+}
+
+impl Default for Carver {
+    fn default() -> Self {
+        Carver {
+            width: Dimension::Percent(100),
+            height: Dimension::Percent(100),
+            importance: None,
+            valid: None,
+            region: None,
+            pinned_columns: Vec::new(),
+            pinned_rows: Vec::new(),
+            max_local_removal: None,
+            max_seam_energy: None,
+            energy_budget: None,
+            min_similarity: None,
+            axis_order: AxisOrder::default(),
+            algorithm: Algorithm::default(),
+            time_budget: None,
+            compact_every: None,
+            max_cpu: None,
+            wrap: false,
+            equirectangular: false,
+            stripe_rows: None,
+            keep_borders: false,
+            border: BorderMode::default(),
+            quantized: false,
+            max_memory: None,
+            blend: false,
+            timed: false,
+            #[cfg(feature = "preview")]
+            preview: false,
+        }
+    }
+}
+
+impl Carver {
+    /// A carver that leaves images untouched until a target size is given
+    pub fn new() -> Self {
+        Carver::default()
+    }
+
+    /// A builder of carvers, that checks that their settings work together
+    pub fn builder() -> CarverBuilder {
+        CarverBuilder::default()
+    }
+
+    /// Sets the target width of the images
+    pub fn width(mut self, width: impl Into<Dimension>) -> Self {
+        self.width = width.into();
+        self
+    }
+
+    /// Sets the target height of the images
+    pub fn height(mut self, height: impl Into<Dimension>) -> Self {
+        self.height = height.into();
+        self
+    }
+
+    /// Removes the given fraction of the width of the images, `0.2` makes them 20% narrower
+    pub fn shrink_width_by(self, fraction: f64) -> Self {
+        self.width(Dimension::Scale(1. - fraction))
+    }
+
+    /// Removes the given fraction of the height of the images
+    pub fn shrink_height_by(self, fraction: f64) -> Self {
+        self.height(Dimension::Scale(1. - fraction))
+    }
+
+    /// Adds importance to the pixels of the images, see [resize_with_importance](crate::resize_with_importance).
+    /// Calling this several times combines the importance maps.
+    pub fn importance(mut self, importance: &ImportanceMap) -> Self {
+        match &mut self.importance {
+            Some(current) => current.add(importance),
+            None => self.importance = Some(importance.clone()),
+        }
+        self
+    }
+
+    /// Only carves the pixels that are not black in the given mask,
+    /// see [resize_within_region](crate::resize_within_region)
+    pub fn valid_region<M: GenericImageView<Pixel = Luma<u8>>>(mut self, valid: &M) -> Self {
+        let (width, height) = valid.dimensions();
+        self.valid = Some(image::GrayImage::from_fn(width, height, |x, y| {
+            valid.get_pixel(x, y)
+        }));
+        self
+    }
+
+    /// Only removes the pixels inside the given rectangle, leaving all the others
+    /// pixel-exact, for instance to trim one busy side of a composition.
+    ///
+    /// Since seams cross the whole image, the rectangle must span the height of the images
+    /// for their width to be carved, and their width for their height to be carved:
+    /// otherwise, resizing fails with [SeamError::Blocked].
+    /// [Blended seams](Carver::blend_seams) can still change the pixels on its edges.
+    ///
+    /// ```
+    /// use seamcarving::{Carver, Rect};
+    /// let img = image::GrayImage::from_fn(30, 10, |x, y| image::Luma([(x * y) as u8]));
+    /// let right = Rect { x: 20, y: 0, width: 10, height: 10 };
+    /// let resized = Carver::new().width(25).carve_region(right).resize(&img).unwrap();
+    /// assert!(resized.enumerate_pixels().all(|(x, y, p)| x >= 20 || p == img.get_pixel(x, y)));
+    /// ```
+    pub fn carve_region(mut self, region: Rect) -> Self {
+        self.region = Some(region);
+        self
+    }
+
+    /// Forbids vertical seams from going through the given columns,
+    /// such as a ruler or the chrome of an application baked into a screenshot,
+    /// which then stay pixel-exact while the width is carved.
+    /// Calling this several times pins all the given columns.
+    ///
+    /// Horizontal seams still go through pinned columns when the height is carved,
+    /// unless they are also in [pinned rows](Carver::pin_rows).
+    /// Columns outside of the images are ignored.
+    ///
+    /// ```
+    /// use seamcarving::Carver;
+    /// let img = image::GrayImage::from_fn(30, 10, |x, y| image::Luma([(x * y) as u8]));
+    /// let resized = Carver::new().width(20).pin_columns(0..5).resize(&img).unwrap();
+    /// assert!(resized.enumerate_pixels().all(|(x, y, p)| x >= 5 || p == img.get_pixel(x, y)));
+    /// ```
+    pub fn pin_columns(mut self, columns: impl IntoIterator<Item = u32>) -> Self {
+        self.pinned_columns.extend(columns);
+        self
+    }
+
+    /// Forbids horizontal seams from going through the given rows,
+    /// like [pin_columns](Carver::pin_columns) does for vertical seams
+    pub fn pin_rows(mut self, rows: impl IntoIterator<Item = u32>) -> Self {
+        self.pinned_rows.extend(rows);
+        self
+    }
+
+    /// Spreads the removed seams across the images,
+    /// see [resize_with_max_distortion](crate::resize_with_max_distortion)
+    pub fn max_local_removal(mut self, radius: u32, max_seams: u32) -> Self {
+        self.max_local_removal = Some((radius, max_seams));
+        self
+    }
+
+    /// Stops carving an axis as soon as the mean energy of the pixels of the cheapest seam
+    /// exceeds `max_energy`, and reaches the target size by scaling the image down instead.
+    ///
+    /// This protects the quality of images that are too dense for seam carving.
+    /// See [resize_with_report](Carver::resize_with_report) to know how the work was split.
+    pub fn max_seam_energy(mut self, max_energy: u32) -> Self {
+        self.max_seam_energy = Some(max_energy);
+        self
+    }
+
+    /// Stops carving as soon as the total energy of the removed seams would exceed `budget`,
+    /// leaving the image larger than requested rather than removing visible seams.
+    ///
+    /// This shrinks images as much as can safely be done: the energy of a seam is the sum
+    /// of the energies of its pixels. [resize_with_report](Carver::resize_with_report)
+    /// tells the size that was reached.
+    pub fn energy_budget(mut self, budget: u64) -> Self {
+        self.energy_budget = Some(budget);
+        self
+    }
+
+    /// Stops carving once the image is less similar to the original than `ssim`,
+    /// leaving it larger than requested: "carve until quality X" rather than
+    /// "carve to size X", with a threshold between 0 and 1.
+    ///
+    /// Every `every_n_seams` seams, the carved image is scaled back to the dimensions
+    /// of the original, and their [ssim](crate::ssim) is measured: the seams removed since
+    /// the last measure are kept when it drops below the threshold.
+    /// An image carved down to a width or a height of 0 has the lowest similarity, -1.
+    /// Measuring copies the luminosity of the image, so frequent measures slow the resize down.
+    /// [resize_with_report](Carver::resize_with_report) tells whether the threshold was reached.
+    /// Images [carved in stripes](Carver::max_memory) are not measured.
+    ///
+    /// ```
+    /// use seamcarving::Carver;
+    /// let img = image::GrayImage::from_fn(40, 20, |x, y| image::Luma([(x * x + y * 5) as u8]));
+    /// let carver = Carver::new().width(4).min_similarity(0.9, 4);
+    /// let (resized, report) = carver.resize_with_report(&img).unwrap();
+    /// assert!(report.too_dissimilar);
+    /// assert!(resized.width() > 4);
+    /// ```
+    pub fn min_similarity(mut self, ssim: f64, every_n_seams: u32) -> Self {
+        self.min_similarity = Some((ssim, every_n_seams.max(1)));
+        self
+    }
+
+    /// Limits the time spent resizing each image.
+    ///
+    /// As many seams as possible are removed within the budget, then the resize
+    /// stops or finishes by scaling the image, depending on `then`.
+    /// [resize_with_report](Carver::resize_with_report) tells whether the budget was exhausted.
+    /// The budget does not account for the time needed to copy the result,
+    /// so it can be slightly exceeded.
+    ///
+    /// ```no_run
+    /// use seamcarving::{Carver, OverBudget};
+    /// use std::time::Duration;
+    /// let img = image::open("./my_image.jpg").unwrap();
+    /// let carver = Carver::new()
+    ///     .width(100)
+    ///     .time_budget(Duration::from_millis(200), OverBudget::Scale);
+    /// let (resized, report) = carver.resize_with_report(&img).unwrap();
+    /// ```
+    pub fn time_budget(mut self, budget: Duration, then: OverBudget) -> Self {
+        self.time_budget = Some((budget, then));
+        self
+    }
+
+    /// Frees the memory used by the removed pixels every `seams` seams.
+    ///
+    /// Removing a seam does not release memory, so the memory used by a resize
+    /// stays proportional to the size of the original image until the end of each axis.
+    /// Compacting bounds it on long resizes, at the cost of copying the remaining pixels.
+    pub fn compact_every(mut self, seams: u32) -> Self {
+        self.compact_every = Some(seams);
+        self
+    }
+
+    /// Keeps the processor busy for at most this fraction of the time, between 0 and 1,
+    /// by sleeping between the seams, so that long batch resizes can run on a desktop
+    /// without making it unusable. The result does not depend on it.
+    /// The sleeps count in the [time budget](Carver::time_budget).
+    /// Infinite fractions and NaN leave the processor unthrottled.
+    ///
+    /// [lower_priority](crate::lower_priority) also lets the other programs go first.
+    ///
+    /// ```no_run
+    /// let img = image::open("./my_image.jpg").unwrap();
+    /// let carver = seamcarving::Carver::new().width(100).max_cpu(0.25);
+    /// let resized = carver.resize(&img).unwrap();
+    /// ```
+    pub fn max_cpu(mut self, fraction: f32) -> Self {
+        self.max_cpu = Some(fraction).filter(|fraction| fraction.is_finite());
+        self
+    }
+
+    /// Chooses the order in which vertical and horizontal seams are removed
+    pub fn axis_order(mut self, order: AxisOrder) -> Self {
+        self.axis_order = order;
+        self
+    }
+
+    /// Chooses the algorithm used to find seams.
+    ///
+    /// [Algorithm::Greedy] is the same as [axis_order](Carver::axis_order)
+    /// with [AxisOrder::Greedy], and [Algorithm::Hybrid] the same as
+    /// a [max_seam_energy](Carver::max_seam_energy) of [HYBRID_MAX_SEAM_ENERGY].
+    pub fn algorithm(mut self, algorithm: Algorithm) -> Self {
+        self.algorithm = algorithm;
+        self
+    }
+
+    /// Lets seams wrap around the borders of the images,
+    /// see [resize_tileable](crate::resize_tileable)
+    pub fn wrapping(mut self, wrap: bool) -> Self {
+        self.wrap = wrap;
+        self
+    }
+
+    /// Carves equirectangular images, such as 360° panoramas, whose left and right borders
+    /// are next to each other and whose top and bottom rows are stretched around the poles.
+    ///
+    /// Vertical seams wrap around the left and right borders, like with
+    /// [wrapping](Carver::wrapping), and the energy of each pixel is weighted by the cosine
+    /// of its latitude, so that the stretched details near the poles are carved before
+    /// the ones near the horizon.
+    pub fn equirectangular(mut self, equirectangular: bool) -> Self {
+        self.equirectangular = equirectangular;
+        self
+    }
+
+    /// Keeps seams away from the borders of the images, so that the first and last columns
+    /// stay pixel-identical when the width is carved, and the first and last rows
+    /// when the height is carved.
+    ///
+    /// This keeps the edges of tiles matching their neighbours, at the cost of the seams
+    /// that would have been cheapest near the borders.
+    /// Unlike [wrapping](Carver::wrapping), the borders themselves are never changed.
+    /// Images at most two pixels wide (or high) cannot be carved in that direction.
+    pub fn keep_borders(mut self, keep_borders: bool) -> Self {
+        self.keep_borders = keep_borders;
+        self
+    }
+
+    /// Chooses how the gradient of the pixels on the borders of the images is computed,
+    /// which decides how likely seams are to go along the borders.
+    /// [BorderMode::Clamp], the default, compares the pixels of the borders with their only
+    /// neighbour inside the image, which draws seams to the first and last columns;
+    /// [BorderMode::Wrap] compares them with the pixels of the opposite border instead,
+    /// so all the seams are found again after each removed seam, which is slower.
+    ///
+    /// ```
+    /// use seamcarving::{BorderMode, Carver};
+    /// // The left border is flat, and different from the right one
+    /// let img = image::GrayImage::from_fn(20, 10, |x, _y| image::Luma([(x * 12) as u8]));
+    /// let clamped = Carver::new().width(19).resize(&img).unwrap();
+    /// assert_eq!(clamped.get_pixel(0, 0).0, [12]);
+    /// let wrapped = Carver::new().width(19).border_mode(BorderMode::Wrap).resize(&img).unwrap();
+    /// assert_eq!(wrapped.get_pixel(0, 0).0, [0]);
+    /// ```
+    pub fn border_mode(mut self, border: BorderMode) -> Self {
+        self.border = border;
+        self
+    }
+
+    /// Stores the energies of the seams in 4 bytes per pixel instead of 12,
+    /// which lets much larger images be carved in the same memory,
+    /// see [plan](Carver::plan).
+    ///
+    /// The energy of each pixel is rounded to a multiple of 8 that fits in 16 bits,
+    /// so seams with close energies may be removed in a different order.
+    /// [Energy limits](Carver::max_seam_energy) and [reports](Report) keep the same unit.
+    pub fn quantize_energy(mut self, quantized: bool) -> Self {
+        self.quantized = quantized;
+        self
+    }
+
+    /// Limits the memory used by resizes to about `bytes`, as estimated by [plan](Carver::plan).
+    ///
+    /// Images that would need more are carved with [quantized energies](Carver::quantize_energy),
+    /// and if that is not enough, in horizontal stripes that are resized independently
+    /// and stacked back together, each within the limit. Seams then do not cross
+    /// the borders of the stripes, which may show in the result, see [Report::striped].
+    /// The [energy budget](Carver::energy_budget) and the [time budget](Carver::time_budget)
+    /// are shared equally between the stripes. The seams of striped resizes are not given
+    /// to the callback of [resize_with_callback](Carver::resize_with_callback),
+    /// nor [cached](Carver::resize_cached).
+    ///
+    /// If even stripes of a single row do not fit, [SeamError::MemoryExceeded] is returned.
+    pub fn max_memory(mut self, bytes: usize) -> Self {
+        self.max_memory = Some(bytes);
+        self
+    }
+
+    /// Slightly blends the pixels on both sides of each removed seam,
+    /// which hides the one-pixel steps that seams leave in smooth gradients, like skies.
+    /// This makes sharp edges crossed by seams a bit blurrier.
+    pub fn blend_seams(mut self, blend: bool) -> Self {
+        self.blend = blend;
+        self
+    }
+
+    /// Measures the time spent in each step of the resizes,
+    /// which [resize_with_report](Carver::resize_with_report) returns in [Report::timings].
+    ///
+    /// This is meant to quickly find out what makes a resize slow.
+    /// Measuring the time taken by the energy of every pixel slows resizes down.
+    pub fn measure_timings(mut self, timed: bool) -> Self {
+        self.timed = timed;
+        self
+    }
+
+    /// Opens a window showing the image, the energy of its pixels,
+    /// and the seam about to be removed, while carving.
+    ///
+    /// This is meant to tune energies and importance maps, and makes resizes much slower.
+    /// Closing the window lets the resize go on without it.
+    /// Seams are not shown with [AxisOrder::Greedy] or [Algorithm::Greedy].
+    #[cfg(feature = "preview")]
+    pub fn debug_preview(mut self, preview: bool) -> Self {
+        self.preview = preview;
+        self
+    }
+
+    /// The size the given image will have once resized
+    pub fn target_dimensions<IMG: GenericImageView>(&self, img: &IMG) -> (u32, u32) {
+        let (width, height) = img.dimensions();
+        (self.width.resolve(width), self.height.resolve(height))
+    }
+
+    /// Describes what resizing the given image would do, without carving it.
+    /// This can be used to choose a strategy or to warn about long resizes.
+    ///
+    /// The memory and cost figures are estimations, and do not depend on the contents
+    /// of the image: if an [energy limit](Carver::max_seam_energy) or
+    /// a [time budget](Carver::time_budget) is set, fewer seams may actually be removed.
+    pub fn plan<IMG: GenericImageView>(&self, img: &IMG) -> Plan {
+        let pixel_bytes = std::mem::size_of::<IMG::Pixel>();
+        self.plan_for(img.dimensions(), self.target_dimensions(img), pixel_bytes)
+    }
+
+    /// The [plan](Carver::plan) of an image of the given size, with pixels of `pixel_bytes`
+    fn plan_for(
+        &self,
+        (width, height): (u32, u32),
+        (target_width, target_height): (u32, u32),
+        pixel_bytes: usize,
+    ) -> Plan {
+        let seams = (width - target_width, height - target_height);
+        let may_scale = self.seam_energy_limit().is_some()
+            || self
+                .time_budget
+                .is_some_and(|(_, then)| then == OverBudget::Scale);
+
+        // Bytes used for each pixel by the seam finder, the pixel positions and the layers
+        let biased = self.importance.is_some() || self.region.is_some();
+        let layer_bytes = if biased { 4 } else { 0 }
+            + self.valid.as_ref().map_or(0, |_| 1)
+            + self.max_local_removal.map_or(0, |_| 8)
+            + [&self.pinned_columns, &self.pinned_rows]
+                .iter()
+                .filter(|pinned| !pinned.is_empty())
+                .count();
+        let per_pixel = SeamFinder::memory_per_position(self.quantized) + 4 + layer_bytes;
+        let area = |w: u32, h: u32| w as usize * h as usize;
+        let full = area(width, height);
+        let (peak_memory, cost) = match self.order() {
+            AxisOrder::WidthFirst => {
+                let x_pass = full * per_pixel;
+                // The positions of the first pass are kept during the second one
+                let y_pass = full * 4
+                    + area(target_width, height) * per_pixel
+                    + area(target_width, target_height) * pixel_bytes;
+                let cost =
+                    pass_cost(width, height, seams.0) + pass_cost(height, target_width, seams.1);
+                (x_pass.max(y_pass), cost)
+            }
+            AxisOrder::Greedy => {
+                // Two copies of the image, one seam finder, and a copy of the layers
+                let memory = full * (2 * pixel_bytes + per_pixel + layer_bytes);
+                // Both directions are computed from scratch at each step
+                let steps = u64::from(seams.0) + u64::from(seams.1);
+                (memory, steps * 2 * full as u64)
+            }
+        };
+        Plan {
+            seams,
+            may_scale,
+            peak_memory,
+            cost,
+            size: (target_width, target_height),
+            algorithm: self.algorithm,
+            order: self.order(),
+        }
+    }
+
+    /// Resizes an image with the current settings.
+    ///
+    /// The importance map and the validity mask must have the same dimensions as the image.
+    /// If the image cannot be carved to the requested size
+    /// because of forbidden or invalid pixels, [SeamError::Blocked] is returned.
+    pub fn resize<IMG: GenericImageView>(&self, img: &IMG) -> Result<ImageBufferOf<IMG>, SeamError>
+    where
+        <IMG as GenericImageView>::Pixel: 'static,
+    {
+        self.resize_with_report(img)
+            .map(|(resized, _report)| resized)
+    }
+
+    /// Resizes an image buffer like [resize](Carver::resize), but borrows it
+    /// instead of copying it when the target size is the size of the image
+    pub fn resize_cow<'a, P: Pixel + 'static>(
+        &self,
+        img: &'a PixelBuffer<P>,
+    ) -> Result<Cow<'a, PixelBuffer<P>>, SeamError> {
+        if self.target_dimensions(img) == img.dimensions() {
+            return Ok(Cow::Borrowed(img));
+        }
+        self.resize(img).map(Cow::Owned)
+    }
+
+    /// Resizes an image like [resize](Carver::resize),
+    /// and reports how many seams were removed and how much the image was scaled
+    pub fn resize_with_report<IMG: GenericImageView>(
+        &self,
+        img: &IMG,
+    ) -> Result<(ImageBufferOf<IMG>, Report), SeamError>
+    where
+        <IMG as GenericImageView>::Pixel: 'static,
+    {
+        self.resize_logging(img, SeamObserver::default())
+    }
+
+    /// Resizes a decoded image like [resize_with_report](Carver::resize_with_report),
+    /// carving grayscale images on their single channel.
+    ///
+    /// The pixels of a [DynamicImage] are converted to RGBA each time they are read,
+    /// which makes document scans and depth maps pay for three color channels.
+    /// Grayscale images are carved as the buffers of their own pixel type instead,
+    /// without any conversion, and keep it: their energies are the ones of a single channel.
+    /// The other images are carved in RGBA.
+    ///
+    /// ```
+    /// use image::{DynamicImage, GrayImage, Luma};
+    /// use seamcarving::Carver;
+    /// let scan = GrayImage::from_fn(30, 20, |x, y| Luma([(x * x + y) as u8]));
+    /// let carver = Carver::new().width(24);
+    /// let (resized, _report) = carver.resize_dynamic(&DynamicImage::ImageLuma8(scan.clone()))
+    ///     .unwrap();
+    /// assert_eq!(resized.as_luma8(), Some(&carver.resize(&scan).unwrap()));
+    /// ```
+    pub fn resize_dynamic(&self, img: &DynamicImage) -> Result<(DynamicImage, Report), SeamError> {
+        fn carved<P: Pixel + 'static>(
+            result: Result<(PixelBuffer<P>, Report), SeamError>,
+            variant: fn(PixelBuffer<P>) -> DynamicImage,
+        ) -> Result<(DynamicImage, Report), SeamError> {
+            result.map(|(resized, report)| (variant(resized), report))
+        }
+        match img {
+            DynamicImage::ImageLuma8(gray) => {
+                carved(self.resize_with_report(gray), DynamicImage::ImageLuma8)
+            }
+            DynamicImage::ImageLuma16(gray) => {
+                carved(self.resize_with_report(gray), DynamicImage::ImageLuma16)
+            }
+            DynamicImage::ImageLumaA8(gray) => {
+                carved(self.resize_with_report(gray), DynamicImage::ImageLumaA8)
+            }
+            DynamicImage::ImageLumaA16(gray) => {
+                carved(self.resize_with_report(gray), DynamicImage::ImageLumaA16)
+            }
+            _ => carved(self.resize_with_report(img), DynamicImage::ImageRgba8),
+        }
+    }
+
+    /// Resizes an image like [resize](Carver::resize), and also returns the position
+    /// in the original image of each pixel of the resized image, so that annotations such as
+    /// bounding boxes or segmentation masks can follow the pixels.
+    ///
+    /// Pixels of an image that was also scaled down are made from several pixels:
+    /// the position of the one closest to their center is returned.
+    /// No positions are returned when the image is [carved in stripes](Carver::max_memory).
+    ///
+    /// ```
+    /// use seamcarving::{Carver, Pos};
+    /// let img = image::GrayImage::from_fn(20, 10, |x, y| image::Luma([(x * y) as u8]));
+    /// let (resized, origins) = Carver::new().width(15).resize_with_provenance(&img).unwrap();
+    /// let origins = origins.unwrap();
+    /// assert_eq!((origins.width(), origins.height()), resized.dimensions());
+    /// let Pos(x, y) = origins[Pos(14, 9)];
+    /// assert_eq!(resized.get_pixel(14, 9), img.get_pixel(x, y));
+    /// ```
+    pub fn resize_with_provenance<IMG: GenericImageView>(
+        &self,
+        img: &IMG,
+    ) -> Result<(ImageBufferOf<IMG>, Option<Matrix<Pos>>), SeamError>
+    where
+        <IMG as GenericImageView>::Pixel: 'static,
+    {
+        let (resized, map) = self.resize_with_map(img)?;
+        Ok((resized, map.map(|map| map.origins())))
+    }
+
+    /// Resizes an image like [resize](Carver::resize), and also returns the seams it removed,
+    /// to remove them again from other rasters of the same size, see [SeamSequence].
+    /// No seams are returned when the image is [carved in stripes](Carver::max_memory).
+    pub fn resize_with_seams<IMG: GenericImageView>(
+        &self,
+        img: &IMG,
+    ) -> Result<(ImageBufferOf<IMG>, Option<SeamSequence>), SeamError>
+    where
+        <IMG as GenericImageView>::Pixel: 'static,
+    {
+        let (resized, _report, log) = self.resize_with_log(img)?;
+        let (size, carved) = (max_pos(img), max_pos(&resized));
+        Ok((resized, log.map(|log| SeamSequence::new(log, size, carved))))
+    }
+
+    /// Resizes an image like [resize](Carver::resize), and also returns where its pixels went,
+    /// to translate positions from the original image to the resized one and back,
+    /// unless the image was [carved in stripes](Carver::max_memory)
+    pub fn resize_with_map<IMG: GenericImageView>(
+        &self,
+        img: &IMG,
+    ) -> Result<(ImageBufferOf<IMG>, Option<CoordinateMap>), SeamError>
+    where
+        <IMG as GenericImageView>::Pixel: 'static,
+    {
+        let (resized, _report, origins) = self.resize_tracking(img)?;
+        let size = max_pos(&resized);
+        let map = origins.map(|origins| CoordinateMap::new(max_pos(img), origins, size));
+        Ok((resized, map))
+    }
+
+    /// Resizes an image like [resize](Carver::resize), and moves the given annotations
+    /// of the original image along with its pixels, see [CoordinateMap::carry].
+    /// Annotations are None when all their pixels were removed, or were outside of the image.
+    ///
+    /// When the image is [carved in stripes](Carver::max_memory), where the pixels went is
+    /// not known, and the annotations are moved as if the image had been scaled down.
+    ///
+    /// ```
+    /// use seamcarving::{Annotation, Carver, Rect};
+    /// let img = image::GrayImage::from_fn(20, 10, |x, y| image::Luma([(x * y) as u8]));
+    /// let boxes = [Annotation::Rect(Rect { x: 2, y: 2, width: 16, height: 6 })];
+    /// let carver = Carver::new().width(15);
+    /// let (_resized, moved) = carver.resize_with_annotations(&img, &boxes).unwrap();
+    /// assert!(matches!(moved[0], Some(Annotation::Rect(Rect { height: 6, .. }))));
+    /// ```
+    pub fn resize_with_annotations<IMG: GenericImageView>(
+        &self,
+        img: &IMG,
+        annotations: &[Annotation],
+    ) -> Result<(ImageBufferOf<IMG>, Vec<Option<Annotation>>), SeamError>
+    where
+        <IMG as GenericImageView>::Pixel: 'static,
+    {
+        let (resized, map) = self.resize_with_map(img)?;
+        let carried = annotations.iter().map(|&annotation| match &map {
+            Some(map) => map.carry(annotation),
+            None => scale_annotation(annotation, img.dimensions(), resized.dimensions()),
+        });
+        let carried = carried.collect();
+        Ok((resized, carried))
+    }
+
+    /// Resizes an image like [resize_with_report](Carver::resize_with_report),
+    /// calling `on_seam` after each removed seam with its positions and some statistics.
+    ///
+    /// The positions are in the coordinates of the image before the seam was removed.
+    /// Returning `ControlFlow::Break` stops carving right after that seam:
+    /// the image is then left larger than requested, and [Report::stopped] is set.
+    ///
+    /// ```
+    /// use std::ops::ControlFlow;
+    /// use seamcarving::Carver;
+    /// let img = image::GrayImage::from_fn(20, 10, |x, y| image::Luma([(x * y) as u8]));
+    /// let carver = Carver::new().width(10);
+    /// let (resized, report) = carver
+    ///     .resize_with_callback(&img, |_seam, stats| {
+    ///         if stats.removed.0 < 4 {
+    ///             ControlFlow::Continue(())
+    ///         } else {
+    ///             ControlFlow::Break(())
+    ///         }
+    ///     })
+    ///     .unwrap();
+    /// assert_eq!(resized.width(), 16);
+    /// assert!(report.stopped);
+    /// ```
+    pub fn resize_with_callback<IMG: GenericImageView>(
+        &self,
+        img: &IMG,
+        mut on_seam: impl FnMut(&[Pos], SeamStats) -> ControlFlow<()>,
+    ) -> Result<(ImageBufferOf<IMG>, Report), SeamError>
+    where
+        <IMG as GenericImageView>::Pixel: 'static,
+    {
+        let observer = SeamObserver {
+            on_seam: Some(&mut on_seam),
+            ..SeamObserver::default()
+        };
+        self.resize_logging(img, observer)
+    }
+
+    fn resize_logging<IMG: GenericImageView>(
+        &self,
+        img: &IMG,
+        mut observer: SeamObserver,
+    ) -> Result<(ImageBufferOf<IMG>, Report), SeamError>
+    where
+        <IMG as GenericImageView>::Pixel: 'static,
+    {
+        let Some((min_similarity, every)) = self.min_similarity else {
+            return self.resize_observed(img, observer);
+        };
+        let mut monitor = SimilarityMonitor::new(img, min_similarity, every);
+        let mut inner = observer.on_seam.take();
+        let mut stopped = false;
+        let mut on_seam = |seam: &[Pos], stats: SeamStats| {
+            if let Some(on_seam) = inner.as_mut() {
+                if on_seam(seam, stats).is_break() {
+                    stopped = true;
+                    return ControlFlow::Break(());
+                }
+            }
+            monitor.seam(seam, stats)
+        };
+        let observer = SeamObserver {
+            on_seam: Some(&mut on_seam),
+            ..observer
+        };
+        let (resized, mut report) = self.resize_observed(img, observer)?;
+        if monitor.dissimilar {
+            report.too_dissimilar = true;
+            report.stopped = stopped;
+        }
+        Ok((resized, report))
+    }
+
+    /// Resizes an image, reporting the seams to the given observer
+    fn resize_observed<IMG: GenericImageView>(
+        &self,
+        img: &IMG,
+        observer: SeamObserver,
+    ) -> Result<(ImageBufferOf<IMG>, Report), SeamError>
+    where
+        <IMG as GenericImageView>::Pixel: 'static,
+    {
+        if let Some(max_memory) = self.max_memory {
+            if self.plan(img).peak_memory > max_memory {
+                return self.resize_within(img, max_memory, observer);
+            }
+        }
+        self.resize_whole(img, observer)
+    }
+
+    /// Resizes the whole image at once, whatever the memory it needs
+    fn resize_whole<IMG: GenericImageView>(
+        &self,
+        img: &IMG,
+        observer: SeamObserver,
+    ) -> Result<(ImageBufferOf<IMG>, Report), SeamError>
+    where
+        <IMG as GenericImageView>::Pixel: 'static,
+    {
+        let target = self.target_dimensions(img);
+        let limits = Limits {
+            max_seam_energy: self.seam_energy_limit(),
+            deadline: self.time_budget.map(|(budget, _)| Instant::now() + budget),
+            scale_when_late: self
+                .time_budget
+                .is_some_and(|(_, then)| then == OverBudget::Scale),
+            compact_every: self.compact_every,
+            timed: self.timed,
+            energy_budget: self.energy_budget,
+            max_cpu: self.max_cpu,
+            #[cfg(feature = "preview")]
+            preview: self.preview,
+        };
+        let layers = self.layers(img);
+        let wrap = self.wrapping_axes();
+        match self.order() {
+            AxisOrder::WidthFirst => {
+                resize_reporting(img, target, layers, wrap, self.blend, limits, observer)
+            }
+            AxisOrder::Greedy => {
+                resize_greedy(img, target, layers, wrap, self.blend, limits, observer)
+            }
+        }
+    }
+
+    /// Resizes an image that needs more than `max_memory` bytes with the current settings,
+    /// see [max_memory](Carver::max_memory)
+    fn resize_within<IMG: GenericImageView>(
+        &self,
+        img: &IMG,
+        max_memory: usize,
+        observer: SeamObserver,
+    ) -> Result<(ImageBufferOf<IMG>, Report), SeamError>
+    where
+        <IMG as GenericImageView>::Pixel: 'static,
+    {
+        let quantized = Carver {
+            quantized: true,
+            ..self.clone()
+        };
+        if quantized.plan(img).peak_memory <= max_memory {
+            return quantized.resize_whole(img, observer);
+        }
+        let (width, height) = img.dimensions();
+        let target = self.target_dimensions(img);
+        let pixel_bytes = std::mem::size_of::<IMG::Pixel>();
+        // The stripes are stacked in the result as they are resized
+        let result_bytes = target.0 as usize * target.1 as usize * pixel_bytes;
+        let stripes = (2..=height)
+            .find(|&stripes| {
+                let rows = (height.div_ceil(stripes), target.1.div_ceil(stripes));
+                let plan = quantized.plan_for((width, rows.0), (target.0, rows.1), pixel_bytes);
+                result_bytes + plan.peak_memory <= max_memory
+            })
+            .ok_or(SeamError::MemoryExceeded)?;
+        quantized.resize_striped(img, target, stripes)
+    }
+
+    /// Resizes horizontal stripes of the image independently, and stacks them back together
+    fn resize_striped<IMG: GenericImageView>(
+        &self,
+        img: &IMG,
+        (target_width, target_height): (u32, u32),
+        stripes: u32,
+    ) -> Result<(ImageBufferOf<IMG>, Report), SeamError>
+    where
+        <IMG as GenericImageView>::Pixel: 'static,
+    {
+        let (width, height) = img.dimensions();
+        // The first row of each stripe, in the image and in the result
+        let start = |i: u32| (u64::from(height) * u64::from(i) / u64::from(stripes)) as u32;
+        let target_start =
+            |y: u32| (u64::from(y) * u64::from(target_height) / u64::from(height)) as u32;
+        let started = Instant::now();
+        let carver = Carver {
+            importance: None,
+            valid: None,
+            energy_budget: self.energy_budget.map(|budget| budget / u64::from(stripes)),
+            ..self.clone()
+        };
+        let mut report = Report {
+            requested: (target_width, target_height),
+            striped: true,
+            ..Report::default()
+        };
+        let mut resized = Vec::with_capacity(stripes as usize);
+        for i in 0..stripes {
+            let rows = start(i)..start(i + 1);
+            let stripe_height = rows.end - rows.start;
+            let stripe_carver = Carver {
+                width: Dimension::Pixels(target_width),
+                height: Dimension::Pixels(target_start(rows.end) - target_start(rows.start)),
+                importance: self.importance.as_ref().map(|map| map.rows(rows.clone())),
+                valid: self
+                    .valid
+                    .as_ref()
+                    .map(|valid| crop_imm(valid, 0, rows.start, width, stripe_height).to_image()),
+                stripe_rows: Some((rows.start, height)),
+                pinned_rows: self
+                    .pinned_rows
+                    .iter()
+                    .filter(|&row| rows.contains(row))
+                    .map(|row| row - rows.start)
+                    .collect(),
+                region: self.region.map(|region| {
+                    let top = region.y.clamp(rows.start, rows.end);
+                    let bottom = region.y.saturating_add(region.height).clamp(top, rows.end);
+                    Rect {
+                        y: top - rows.start,
+                        height: bottom - top,
+                        ..region
+                    }
+                }),
+                time_budget: self.time_budget.map(|(budget, then)| {
+                    let left = budget.saturating_sub(started.elapsed());
+                    (left / (stripes - i), then)
+                }),
+                ..carver.clone()
+            };
+            let stripe = crop_imm(img, 0, rows.start, width, stripe_height);
+            let (stripe, stripe_report) =
+                stripe_carver.resize_whole(&stripe, SeamObserver::default())?;
+            report.seams.0 = report.seams.0.max(stripe_report.seams.0);
+            report.seams.1 += stripe_report.seams.1;
+            report.scaled.0 = report.scaled.0.max(stripe_report.scaled.0);
+            report.scaled.1 += stripe_report.scaled.1;
+            report.out_of_time |= stripe_report.out_of_time;
+            report.out_of_energy |= stripe_report.out_of_energy;
+            report.removed_energy += stripe_report.removed_energy;
+            report.timings = match (report.timings, stripe_report.timings) {
+                (Some(mut timings), Some(stripe_timings)) => {
+                    timings += stripe_timings;
+                    Some(timings)
+                }
+                (timings, stripe_timings) => timings.or(stripe_timings),
+            };
+            resized.push(stripe);
+        }
+        // Stripes that ran out of budget are wider than the others
+        let width = resized
+            .iter()
+            .map(|stripe| stripe.width())
+            .min()
+            .unwrap_or(0);
+        let height = resized.iter().map(|stripe| stripe.height()).sum();
+        let mut result = ImageBuffer::new(width, height);
+        let mut y = 0;
+        for stripe in resized {
+            let stripe_height = stripe.height();
+            let (stripe, _scaled) = scale_down(stripe, width, stripe_height);
+            result
+                .copy_from(&stripe, 0, y)
+                .expect("the stripes fit in the result");
+            y += stripe_height;
+        }
+        report.size = result.dimensions();
+        Ok((result, report))
+    }
+
+    /// Creates several narrower versions of an image at once,
+    /// for instance to generate responsive images.
+    ///
+    /// Seams are computed only once: each version is a snapshot taken
+    /// while carving the image down to the smallest of the given widths.
+    /// The versions are returned in the same order as the widths.
+    /// The target width and height of this carver are ignored.
+    ///
+    /// ```no_run
+    /// let img = image::open("./my_image.jpg").unwrap();
+    /// let versions = seamcarving::Carver::new().resize_widths(&img, &[320, 640, 1024]).unwrap();
+    /// ```
+    pub fn resize_widths<IMG: GenericImageView>(
+        &self,
+        img: &IMG,
+        widths: &[u32],
+    ) -> Result<Vec<ImageBufferOf<IMG>>, SeamError>
+    where
+        <IMG as GenericImageView>::Pixel: 'static,
+    {
+        let mut order: Vec<usize> = (0..widths.len()).collect();
+        // Widest first, as they are reached first
+        order.sort_by_key(|&i| std::cmp::Reverse(widths[i]));
+        let mut carvable = Carvable::new(img);
+        carvable.set_layers(self.layers(img));
+        carvable.set_wrapping(self.wrapping_axes().0);
+        let mut versions = vec![None; widths.len()];
+        for i in order {
+            while carvable.carved.width() > widths[i] {
+                carvable.try_remove_seam()?;
+            }
+            versions[i] = Some(carved_to_buffer(carvable.result(), self.blend));
+        }
+        Ok(versions.into_iter().flatten().collect())
+    }
+
+    /// Computes the order in which the vertical seams of an image are removed
+    /// until it is `min_width` pixels wide, so that it can then be rendered at any width
+    /// in between without carving it again, see [SeamIndexMap].
+    ///
+    /// Like [resize_widths](Carver::resize_widths), this ignores the target width and height
+    /// of this carver. Horizontal seams are not supported.
+    ///
+    /// ```no_run
+    /// let img = image::open("./my_image.jpg").unwrap();
+    /// let map = seamcarving::Carver::new().seam_index_map(&img, 100).unwrap();
+    /// std::fs::write("./my_image.seams", map.to_bytes()).unwrap();
+    /// ```
+    pub fn seam_index_map<IMG: GenericImageView>(
+        &self,
+        img: &IMG,
+        min_width: u32,
+    ) -> Result<SeamIndexMap, SeamError> {
+        let mut carvable = Carvable::new(img);
+        carvable.set_layers(self.layers(img));
+        carvable.set_wrapping(self.wrapping_axes().0);
+        let mut map = SeamIndexMap::new(max_pos(img));
+        while carvable.carved.width() > min_width {
+            let (seam, _energy) = carvable.find_next_seam()?;
+            map.push_seam(seam.iter().map(|&pos| carvable.carved.transform_pos(pos)));
+            carvable.apply_seam(&seam);
+        }
+        Ok(map)
+    }
+
+    /// Checks masks and importance maps like [validate_inputs](crate::validate_inputs),
+    /// along with the [importance](Carver::importance) and the [valid region](Carver::valid_region)
+    /// of this carver, which come last in their lists, only in the directions this carver
+    /// removes seams in, and for the number of seams it removes in each direction.
+    ///
+    /// ```
+    /// use seamcarving::{Carver, InputMask, ValidationError};
+    /// let img = image::RgbImage::new(20, 10);
+    /// // Only the left half of the image may be carved
+    /// let forbidden = image::GrayImage::from_fn(20, 10, |x, _| {
+    ///     image::Luma([if x < 10 { 0 } else { 255 }])
+    /// });
+    /// let masks = [InputMask::Forbid(&forbidden)];
+    /// assert!(Carver::new().width(12).validate_inputs(&img, &masks, &[]).is_ok());
+    /// let error = Carver::new().width(8).validate_inputs(&img, &masks, &[]);
+    /// assert!(matches!(error, Err(ValidationError::Blocked { .. })));
+    /// ```
+    pub fn validate_inputs<IMG: GenericImageView>(
+        &self,
+        img: &IMG,
+        masks: &[InputMask],
+        weights: &[ImportanceMap],
+    ) -> Result<(), ValidationError> {
+        let (width, height) = img.dimensions();
+        let (target_width, target_height) = self.target_dimensions(img);
+        let masks = masks
+            .iter()
+            .copied()
+            .chain(self.valid.as_ref().map(InputMask::ValidRegion));
+        let weights = weights.iter().chain(self.importance.as_ref());
+        let seams = (width.saturating_sub(target_width), height.saturating_sub(target_height));
+        validate(max_pos(img), masks, weights, seams)
+    }
+
+    /// Resizes an image like [resize](Carver::resize), but reuses the seams stored in the cache
+    /// when the same image was already resized with the same settings.
+    ///
+    /// The [time budget](Carver::time_budget) is not part of the key:
+    /// resizes that ran out of time are not cached.
+    /// The cache is only an optimization: unreadable entries are recomputed,
+    /// and failing to store an entry does not prevent the image from being resized.
+    ///
+    /// ```no_run
+    /// use seamcarving::{Carver, SeamCache};
+    /// let cache = SeamCache::new("./seams");
+    /// let img = image::open("./my_image.jpg").unwrap();
+    /// let resized = Carver::new().width(100).resize_cached(&img, &cache).unwrap();
+    /// ```
+    pub fn resize_cached<IMG: GenericImageView>(
+        &self,
+        img: &IMG,
+        cache: &SeamCache,
+    ) -> Result<ImageBufferOf<IMG>, SeamError>
+    where
+        <IMG as GenericImageView>::Pixel: 'static,
+    {
+        let key = self.cache_key(img);
+        let (width, height) = self.target_dimensions(img);
+        let greedy = self.order() == AxisOrder::Greedy;
+        let replayed = cache
+            .load(key)
+            .and_then(|log| replay(img, &log, self.blend, greedy));
+        if let Some(carved) = replayed {
+            return Ok(scale_down(carved, width, height).0);
+        }
+        let mut log = SeamLog::default();
+        let observer = SeamObserver {
+            log: Some(&mut log),
+            ..SeamObserver::default()
+        };
+        let (resized, report) = self.resize_logging(img, observer)?;
+        // An unfinished resize would be served again even with more time,
+        // and the replayed seams would be scaled down to the requested size
+        if !report.out_of_time && !report.out_of_energy && !report.striped {
+            // Best effort: the result is correct even if it cannot be cached
+            let _ = cache.store(key, &log);
+        }
+        Ok(resized)
+    }
+
+    /// Resizes an image like [resize_with_report](Carver::resize_with_report),
+    /// saving the removed seams to `checkpoint` as it goes, so that long resizes can be resumed.
+    ///
+    /// With `resume`, the seams saved by an interrupted resize of the same image
+    /// with the same settings are removed again first, without being searched for,
+    /// and only the remaining seams are found, in the partly carved image,
+    /// so the result can differ slightly from the one of an uninterrupted resize.
+    /// The [importance](Carver::importance) and the [valid region](Carver::valid_region)
+    /// are carved like the image. The energy and time budgets only count from the resume,
+    /// and the report counts the resumed seams, but not their energy.
+    /// A checkpoint saved for another image or other settings is overwritten.
+    /// The checkpoint is left in place once the image is resized.
+    ///
+    /// Fails with [CheckpointError::Unsupported] if the carver has a setting that depends
+    /// on the positions in the original image: a [carved region](Carver::carve_region),
+    /// pinned columns or rows, [limits on local removals](Carver::max_local_removal),
+    /// or a [memory limit](Carver::max_memory).
+    ///
+    /// ```no_run
+    /// use seamcarving::{Carver, Checkpoint};
+    /// let img = image::open("./panorama.tif").unwrap();
+    /// let checkpoint = Checkpoint::new("./panorama.state").every(100);
+    /// let carver = Carver::new().width(40_000);
+    /// // After an interruption, the same call picks up where the last one left off
+    /// let (resized, _report) = carver.resize_checkpointed(&img, &checkpoint, true).unwrap();
+    /// ```
+    pub fn resize_checkpointed<IMG: GenericImageView>(
+        &self,
+        img: &IMG,
+        checkpoint: &Checkpoint,
+        resume: bool,
+    ) -> Result<(ImageBufferOf<IMG>, Report), CheckpointError>
+    where
+        <IMG as GenericImageView>::Pixel: 'static,
+    {
+        let unsupported = [
+            (self.region.is_some(), "carved region"),
+            (!self.pinned_columns.is_empty(), "pinned columns"),
+            (!self.pinned_rows.is_empty(), "pinned rows"),
+            (self.max_local_removal.is_some(), "limit on local removals"),
+            (self.max_memory.is_some(), "memory limit"),
+        ];
+        if let Some(&(_, setting)) = unsupported.iter().find(|(set, _)| *set) {
+            return Err(CheckpointError::Unsupported { setting });
+        }
+        let key = self.cache_key(img);
+        let greedy = self.order() == AxisOrder::Greedy;
+        let saved = if resume { checkpoint.load(key)? } else { None };
+        // Seams that do not fit in the image come from a corrupted checkpoint
+        let resumed = saved.and_then(|log| Some((replay(img, &log, self.blend, greedy)?, log)));
+        let (partial, log) = match resumed {
+            Some((partial, log)) => (partial, log),
+            None => (image_view_to_buffer(img), SeamLog::default()),
+        };
+        // Written again, without the seam an interruption may have cut short
+        let mut writer = checkpoint.create(key, &log)?;
+        let (width, height) = self.target_dimensions(img);
+        let mut rest = self.clone().width(width).height(height);
+        if let Some(importance) = &self.importance {
+            let (w, h) = importance.dimensions();
+            let bias: ImageBuffer<Luma<u32>, Vec<u32>> =
+                ImageBuffer::from_fn(w, h, |x, y| Luma([importance.bias[Pos(x, y)]]));
+            if let Some(carved) = replay(&bias, &log, false, false) {
+                let size = max_pos(&carved);
+                let bias = Matrix::from_fn(size, |x, y| carved.get_pixel(x as u32, y as u32).0[0]);
+                rest.importance = Some(ImportanceMap::from_bias(size, bias));
+            }
+        }
+        if let Some(valid) = &self.valid {
+            rest.valid = replay(valid, &log, false, false).or_else(|| Some(valid.clone()));
+        }
+
+        let mut failure = None;
+        let mut pending = 0;
+        let (resized, mut report) = rest.resize_with_callback(&partial, |seam, stats| {
+            // Seam logs store horizontal seams in the coordinates of the rotated image
+            let seam: Vec<Pos> = match stats.axis {
+                Axis::Vertical => seam.to_vec(),
+                Axis::Horizontal => seam.iter().map(|&Pos(x, y)| Pos(y, x)).collect(),
+            };
+            writer.push(stats.axis, &seam);
+            pending += 1;
+            if pending == checkpoint.batch() {
+                pending = 0;
+                if let Err(error) = writer.flush() {
+                    failure = Some(error);
+                    return ControlFlow::Break(());
+                }
+            }
+            ControlFlow::Continue(())
+        })?;
+        if let Some(error) = failure {
+            return Err(error.into());
+        }
+        writer.flush()?;
+        for (axis, _seam) in log.seams() {
+            match axis {
+                Axis::Vertical => report.seams.0 += 1,
+                Axis::Horizontal => report.seams.1 += 1,
+            }
+        }
+        Ok((resized, report))
+    }
+
+    /// Resizes an image like [resize_with_report](Carver::resize_with_report), and calls
+    /// `on_snapshot` with a copy of the image every `every_n_seams` removed seams,
+    /// and once the last one is removed, for timelines that scrub through the carving,
+    /// or assets showing it halfway.
+    ///
+    /// The snapshots are taken once all the seams are found, by removing them again,
+    /// and each one is owned, so it can be sent to another thread.
+    /// No snapshot is taken when the image is [carved in stripes](Carver::max_memory).
+    ///
+    /// ```
+    /// use std::sync::mpsc;
+    /// use seamcarving::Carver;
+    /// let img = image::GrayImage::from_fn(20, 10, |x, y| image::Luma([(x * y) as u8]));
+    /// let (sender, receiver) = mpsc::channel();
+    /// let carver = Carver::new().width(14);
+    /// carver
+    ///     .resize_with_snapshots(&img, 4, |frame| sender.send(frame).unwrap())
+    ///     .unwrap();
+    /// drop(sender);
+    /// let widths: Vec<u32> = receiver.iter().map(|frame| frame.image.width()).collect();
+    /// assert_eq!(widths, vec![16, 14]);
+    /// ```
+    pub fn resize_with_snapshots<IMG: GenericImageView>(
+        &self,
+        img: &IMG,
+        every_n_seams: u32,
+        mut on_snapshot: impl FnMut(Frame<IMG::Pixel>),
+    ) -> Result<(ImageBufferOf<IMG>, Report), SeamError>
+    where
+        <IMG as GenericImageView>::Pixel: 'static,
+    {
+        let every = every_n_seams.max(1) as usize;
+        self.resize_recording(img, every, |image, seams| {
+            on_snapshot(Frame {
+                image: image.clone(),
+                seams: seams as u32,
+            })
+        })
+    }
+
+    /// Resizes an image like [resize_with_report](Carver::resize_with_report), then removes
+    /// the same seams from the image again to call `on_step` with the carved image
+    /// every `every` seams, unless the image was carved in stripes
+    fn resize_recording<IMG: GenericImageView>(
+        &self,
+        img: &IMG,
+        every: usize,
+        mut on_step: impl FnMut(&ImageBufferOf<IMG>, usize),
+    ) -> Result<(ImageBufferOf<IMG>, Report), SeamError>
+    where
+        <IMG as GenericImageView>::Pixel: 'static,
+    {
+        let (resized, report, log) = self.resize_with_log(img)?;
+        if let Some(log) = log {
+            let greedy = self.order() == AxisOrder::Greedy;
+            // The logged seams always fit in the image they were removed from
+            replay_every(img, &log, self.blend, greedy, every, &mut on_step);
+        }
+        Ok((resized, report))
+    }
+
+    /// Resizes an image like [resize_with_report](Carver::resize_with_report), and returns
+    /// the position in the original image of each pixel of the carved image, before it was
+    /// scaled down, unless the image was carved in stripes
+    pub(crate) fn resize_tracking<IMG: GenericImageView>(
+        &self,
+        img: &IMG,
+    ) -> Result<(ImageBufferOf<IMG>, Report, Option<Origins>), SeamError>
+    where
+        <IMG as GenericImageView>::Pixel: 'static,
+    {
+        let (resized, report, log) = self.resize_with_log(img)?;
+        let (width, height) = img.dimensions();
+        // The same seams removed from an image whose pixels are their own coordinates
+        let coordinates: ImageBuffer<LumaA<u32>, Vec<u32>> =
+            ImageBuffer::from_fn(width, height, |x, y| LumaA([x, y]));
+        let origins = log
+            .and_then(|log| replay(&coordinates, &log, false, false))
+            .map(|carved| {
+                let size = max_pos(&carved);
+                Matrix::from_fn(size, |x, y| {
+                    let LumaA([x, y]) = *carved.get_pixel(x as u32, y as u32);
+                    Pos(x, y)
+                })
+            });
+        Ok((resized, report, origins))
+    }
+
+    /// Resizes an image like [resize_with_report](Carver::resize_with_report), and returns
+    /// the removed seams in the order they were removed, unless it was carved in stripes
+    fn resize_with_log<IMG: GenericImageView>(
+        &self,
+        img: &IMG,
+    ) -> Result<(ImageBufferOf<IMG>, Report, Option<SeamLog>), SeamError>
+    where
+        <IMG as GenericImageView>::Pixel: 'static,
+    {
+        let mut log = SeamLog::default();
+        let observer = SeamObserver {
+            log: Some(&mut log),
+            ..SeamObserver::default()
+        };
+        let (resized, report) = self.resize_logging(img, observer)?;
+        // The seams of stripes are in the coordinates of the stripes
+        let log = Some(log).filter(|_| !report.striped);
+        Ok((resized, report, log))
+    }
+
+    /// Hash of everything that influences the seams removed from the image
+    fn cache_key<IMG: GenericImageView>(&self, img: &IMG) -> u64 {
+        let mut hasher = ContentHasher::new();
+        hasher.write_image(img);
+        let (width, height) = self.target_dimensions(img);
+        hasher.write_u32(width);
+        hasher.write_u32(height);
+        hasher.write_u8(self.wrap as u8);
+        if let Some(importance) = &self.importance {
+            hasher.write_u8(1);
+            let (w, h) = importance.dimensions();
+            for pos in Pos::iter_in_rect(Pos(0, 0), Pos(w, h)) {
+                hasher.write_u32(importance.bias[pos]);
+            }
+        }
+        if let Some(valid) = &self.valid {
+            hasher.write_u8(2);
+            hasher.write_image(valid);
+        }
+        if let Some((radius, max_seams)) = self.max_local_removal {
+            hasher.write_u8(3);
+            hasher.write_u32(radius);
+            hasher.write_u32(max_seams);
+        }
+        if let Some(max_energy) = self.seam_energy_limit() {
+            hasher.write_u8(4);
+            hasher.write_u32(max_energy);
+        }
+        if self.order() == AxisOrder::Greedy {
+            hasher.write_u8(5);
+        }
+        if self.blend {
+            hasher.write_u8(6);
+        }
+        if self.keep_borders {
+            hasher.write_u8(7);
+        }
+        if self.algorithm == Algorithm::Forward {
+            hasher.write_u8(8);
+        }
+        if let Some(budget) = self.energy_budget {
+            hasher.write_u8(9);
+            hasher.write_u64(budget);
+        }
+        if self.quantized {
+            hasher.write_u8(10);
+        }
+        if let Some(max_memory) = self.max_memory {
+            hasher.write_u8(11);
+            hasher.write_u64(max_memory as u64);
+        }
+        if let Some(region) = self.region {
+            hasher.write_u8(12);
+            for value in [region.x, region.y, region.width, region.height] {
+                hasher.write_u32(value);
+            }
+        }
+        for (tag, pinned) in [(13, &self.pinned_columns), (14, &self.pinned_rows)] {
+            if !pinned.is_empty() {
+                hasher.write_u8(tag);
+                pinned.iter().for_each(|&line| hasher.write_u32(line));
+            }
+        }
+        if self.equirectangular {
+            hasher.write_u8(15);
+        }
+        if let Some((min_similarity, every)) = self.min_similarity {
+            hasher.write_u8(16);
+            hasher.write_u64(min_similarity.to_bits());
+            hasher.write_u32(every);
+        }
+        if self.border != BorderMode::Clamp {
+            hasher.write_u8(17);
+            hasher.write_u8(self.border as u8);
+        }
+        hasher.finish()
+    }
+
+    fn layers<IMG: GenericImageView>(&self, img: &IMG) -> Layers {
+        let size = max_pos(img);
+        let mut layers = Layers::default();
+        if let Some(importance) = &self.importance {
+            assert_eq!(
+                img.dimensions(),
+                importance.dimensions(),
+                "the importance map must have the same dimensions as the image"
+            );
+            layers.bias = Some(importance.bias.clone());
+        }
+        if let Some(region) = self.region {
+            let inside = |x: usize, y: usize| region.contains(x as u32, y as u32);
+            let bias = layers.bias.take();
+            layers.bias = Some(Matrix::from_fn(size, |x, y| match (&bias, inside(x, y)) {
+                (_, false) => FORBIDDEN,
+                (Some(bias), true) => bias[Pos(x as u32, y as u32)],
+                (None, true) => 0,
+            }));
+        }
+        if let Some(valid) = &self.valid {
+            assert_eq!(
+                img.dimensions(),
+                valid.dimensions(),
+                "the mask must have the same dimensions as the image"
+            );
+            layers.set_valid(validity_matrix(valid), size);
+        }
+        if let Some((radius, max_seams)) = self.max_local_removal {
+            layers.set_spread(radius, max_seams, size);
+        }
+        layers.set_keep_borders(self.keep_borders);
+        layers.set_pinned(&self.pinned_columns, &self.pinned_rows, size);
+        if self.equirectangular {
+            let (first_row, height) = self.stripe_rows.unwrap_or((0, size.1));
+            layers.set_latitude_scale(size, first_row, height);
+        }
+        layers.set_forward_energy(self.algorithm == Algorithm::Forward);
+        layers.set_border_mode(self.border);
+        layers.quantized = self.quantized;
+        layers
+    }
+
+    /// Checks the settings that are incompatible whatever the image,
+    /// see [CarverBuilder::build]
+    pub(crate) fn validate(&self) -> Result<(), ConfigError> {
+        for dimension in [self.width, self.height] {
+            match dimension {
+                Dimension::Scale(scale) if !scale.is_finite() || scale < 0. => {
+                    return Err(ConfigError::InvalidDimension)
+                }
+                Dimension::Scale(scale) if scale > 1. => return Err(ConfigError::Enlargement),
+                Dimension::Percent(percent) if percent > 100 => {
+                    return Err(ConfigError::Enlargement)
+                }
+                _ => {}
+            }
+        }
+        if let (Some(importance), Some(valid)) = (&self.importance, &self.valid) {
+            if importance.dimensions() != valid.dimensions() {
+                return Err(ConfigError::MaskSizeMismatch);
+            }
+        }
+        if let Some((_radius, max_seams)) = self.max_local_removal {
+            if max_seams == 0 {
+                return Err(ConfigError::NothingRemovable);
+            }
+            if self.order() == AxisOrder::Greedy {
+                return Err(ConfigError::GreedyLocalRemoval);
+            }
+        }
+        if (self.wrap || self.equirectangular) && self.keep_borders {
+            return Err(ConfigError::WrappingKeptBorders);
+        }
+        if self.region.is_some_and(|region| region.width == 0 || region.height == 0) {
+            return Err(ConfigError::NothingRemovable);
+        }
+        Ok(())
+    }
+
+    /// Whether vertical and horizontal seams wrap around the borders of the images
+    fn wrapping_axes(&self) -> (bool, bool) {
+        (self.wrap || self.equirectangular, self.wrap)
+    }
+
+    /// The axis order, including the one implied by the algorithm
+    fn order(&self) -> AxisOrder {
+        match self.algorithm {
+            Algorithm::Greedy => AxisOrder::Greedy,
+            _ => self.axis_order,
+        }
+    }
+
+    /// The energy limit, including the one implied by the algorithm
+    fn seam_energy_limit(&self) -> Option<u32> {
+        match self.algorithm {
+            Algorithm::Hybrid => self.max_seam_energy.or(Some(HYBRID_MAX_SEAM_ENERGY)),
+            _ => self.max_seam_energy,
+        }
+    }
+}
+
+/// Estimation of the number of energy computations needed to remove vertical seams:
+/// the whole image is computed once, then every removed seam invalidates the positions
+/// that depended on it, which spread at most one column per row
+fn pass_cost(width: u32, height: u32, seams: u32) -> u64 {
+    let (w, h) = (u64::from(width), u64::from(height));
+    let per_seam = h * w.min(2 * h);
+    w * h + u64::from(seams) * per_seam
+}
+
+/// Measures the similarity of an image being carved to its original,
+/// see [Carver::min_similarity]
+struct SimilarityMonitor {
+    min_similarity: f64,
+    every: u32,
+    original: GrayImage,
+    // The luminosity of the image once the seams measured so far are removed
+    current: GrayImage,
+    pending: SeamLog,
+    pending_count: u32,
+    // Whether the image became less similar than the threshold
+    dissimilar: bool,
+}
+
+impl SimilarityMonitor {
+    fn new<IMG: GenericImageView>(img: &IMG, min_similarity: f64, every: u32) -> Self {
+        let original = luminosity(img);
+        SimilarityMonitor {
+            min_similarity,
+            every,
+            current: original.clone(),
+            original,
+            pending: SeamLog::default(),
+            pending_count: 0,
+            dissimilar: false,
+        }
+    }
+
+    /// Measures the similarity every few seams, and breaks once it is too low
+    fn seam(&mut self, seam: &[Pos], stats: SeamStats) -> ControlFlow<()> {
+        // Seam logs store horizontal seams in the coordinates of the rotated image
+        let seam: Vec<Pos> = match stats.axis {
+            Axis::Vertical => seam.to_vec(),
+            Axis::Horizontal => seam.iter().map(|&Pos(x, y)| Pos(y, x)).collect(),
+        };
+        self.pending.push(stats.axis, seam);
+        self.pending_count += 1;
+        if self.pending_count < self.every {
+            return ControlFlow::Continue(());
+        }
+        self.pending_count = 0;
+        let batch = std::mem::take(&mut self.pending);
+        let Some(carved) = replay(&self.current, &batch, false, false) else {
+            return ControlFlow::Continue(());
+        };
+        self.current = carved;
+        let similarity = if self.current.width() == 0 || self.current.height() == 0 {
+            // Nothing is left to scale back
+            -1.
+        } else {
+            let (width, height) = self.original.dimensions();
+            let restored = resize(&self.current, width, height, FilterType::Triangle);
+            gray_ssim(&self.original, &restored)
+        };
+        if similarity < self.min_similarity {
+            self.dissimilar = true;
+            ControlFlow::Break(())
+        } else {
+            ControlFlow::Continue(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Dimension;
+
+    #[test]
+    fn resolves_dimensions() {
+        assert_eq!(Dimension::Pixels(30).resolve(100), 30);
+        assert_eq!(Dimension::Pixels(300).resolve(100), 100);
+        assert_eq!(Dimension::Percent(80).resolve(101), 81);
+        assert_eq!(Dimension::Percent(150).resolve(101), 101);
+        assert_eq!(Dimension::Scale(0.25).resolve(10), 3);
+        assert_eq!(Dimension::Scale(-1.).resolve(10), 0);
+    }
+}
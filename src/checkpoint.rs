@@ -0,0 +1,117 @@
+use std::fs::{File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use crate::cache::{self, Axis, SeamLog};
+use crate::pos::Pos;
+
+/// First bytes of the checkpoint files, followed by the version of their format
+const MAGIC: &[u8; 4] = b"SCCP";
+
+/// Version of the format of the checkpoint files
+const FORMAT_VERSION: u32 = 1;
+
+/// A file in which a long resize saves the seams it removed as it goes,
+/// so that it can be resumed after being interrupted, for instance when the machine
+/// running it is preempted, see [Carver::resize_checkpointed](crate::Carver::resize_checkpointed).
+///
+/// The seams are appended to the file in batches, so a checkpoint interrupted while
+/// it is being written only loses its last batch.
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+pub struct Checkpoint {
+    path: PathBuf,
+    every: u32,
+}
+
+impl Checkpoint {
+    /// A checkpoint in the given file, saved every 64 seams
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Checkpoint {
+            path: path.into(),
+            every: 64,
+        }
+    }
+
+    /// Saves the checkpoint every `seams` removed seams.
+    /// Saving more often loses less work when the resize is interrupted,
+    /// but writes to the file more often.
+    pub fn every(mut self, seams: u32) -> Self {
+        self.every = seams.max(1);
+        self
+    }
+
+    /// The file of the checkpoint
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub(crate) fn batch(&self) -> usize {
+        self.every as usize
+    }
+
+    /// The seams saved for the resize of the given key,
+    /// or None if the file is missing, or was saved for another image or other settings
+    pub(crate) fn load(&self, key: u64) -> io::Result<Option<SeamLog>> {
+        let bytes = match std::fs::read(&self.path) {
+            Ok(bytes) => bytes,
+            Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(error) => return Err(error),
+        };
+        let mut header = MAGIC.to_vec();
+        header.extend_from_slice(&FORMAT_VERSION.to_le_bytes());
+        header.extend_from_slice(&key.to_le_bytes());
+        let Some(records) = bytes.strip_prefix(&header[..]) else {
+            return Ok(None);
+        };
+        let mut words = cache::words(records);
+        let mut log = SeamLog::default();
+        // A record cut short by an interruption ends the seams
+        while let Some((axis, seam)) = cache::decode_record(&mut words) {
+            log.push(axis, seam);
+        }
+        Ok(Some(log))
+    }
+
+    /// Creates the file of the checkpoint for the resize of the given key,
+    /// with the seams already removed, and opens it to append the next ones
+    pub(crate) fn create(&self, key: u64, log: &SeamLog) -> io::Result<CheckpointWriter> {
+        // Write then rename, so that an interruption never loses the previous checkpoint
+        let tmp = self.path.with_extension("tmp");
+        let mut writer = CheckpointWriter {
+            file: File::create(&tmp)?,
+            pending: Vec::new(),
+        };
+        writer.pending.extend_from_slice(MAGIC);
+        writer.pending.extend_from_slice(&FORMAT_VERSION.to_le_bytes());
+        writer.pending.extend_from_slice(&key.to_le_bytes());
+        for (axis, seam) in log.seams() {
+            writer.push(*axis, seam);
+        }
+        writer.flush()?;
+        std::fs::rename(tmp, &self.path)?;
+        Ok(CheckpointWriter {
+            file: OpenOptions::new().append(true).open(&self.path)?,
+            pending: Vec::new(),
+        })
+    }
+}
+
+/// Appends seams to the file of a [Checkpoint]
+pub(crate) struct CheckpointWriter {
+    file: File,
+    pending: Vec<u8>,
+}
+
+impl CheckpointWriter {
+    /// Adds a seam to the next batch, in the coordinates of seam logs
+    pub fn push(&mut self, axis: Axis, seam: &[Pos]) {
+        cache::push_record(&mut self.pending, axis, seam);
+    }
+
+    /// Writes the batch to the disk
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.file.write_all(&self.pending)?;
+        self.pending.clear();
+        self.file.sync_data()
+    }
+}
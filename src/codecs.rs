@@ -0,0 +1,305 @@
+use std::path::Path;
+
+#[cfg(any(feature = "webp", feature = "avif"))]
+use image::error::{EncodingError, ImageError, ImageFormatHint};
+use image::{
+    DynamicImage, GenericImageView, ImageFormat, ImageOutputFormat, ImageResult, Rgba, RgbaImage,
+};
+
+use crate::{scale_down, Carver, Metadata};
+
+/// Quality of the JPEG images written by [save_image] and [encode_image]
+const JPEG_QUALITY: u8 = 90;
+
+/// Opens an image like `image::open`, and also decodes WebP images
+/// when the `webp` feature is enabled, and the first page of TIFF images
+/// when the `tiff` feature is enabled, see [open_pages](crate::open_pages) for the others
+pub fn open_image(path: impl AsRef<Path>) -> ImageResult<DynamicImage> {
+    load_image(&std::fs::read(path)?)
+}
+
+/// Opens an image like [open_image], along with its EXIF and XMP [Metadata],
+/// to be written with the resized image by [save_image_with_metadata]
+pub fn open_image_with_metadata(path: impl AsRef<Path>) -> ImageResult<(DynamicImage, Metadata)> {
+    let bytes = std::fs::read(path)?;
+    Ok((load_image(&bytes)?, Metadata::read(&bytes)))
+}
+
+/// Decodes an image in any of the formats supported by [open_image]
+pub fn load_image(bytes: &[u8]) -> ImageResult<DynamicImage> {
+    let format = image::guess_format(bytes)?;
+    match format {
+        #[cfg(feature = "webp")]
+        ImageFormat::WebP => decode_webp(bytes),
+        #[cfg(feature = "tiff")]
+        ImageFormat::Tiff => crate::pages::decode_tiff(bytes),
+        _ => image::load_from_memory_with_format(bytes, format),
+    }
+}
+
+/// Reads the dimensions of an image in any of the formats supported by [open_image]
+/// from its header, without decoding its pixels, so that images too large to be decoded,
+/// such as decompression bombs, can be rejected first.
+///
+/// ```
+/// let img = image::RgbaImage::new(30, 20);
+/// let png = seamcarving::encode_image(&img, image::ImageFormat::Png).unwrap();
+/// assert_eq!(seamcarving::image_dimensions(&png).unwrap(), (30, 20));
+/// ```
+pub fn image_dimensions(bytes: &[u8]) -> ImageResult<(u32, u32)> {
+    let format = image::guess_format(bytes)?;
+    match format {
+        #[cfg(feature = "webp")]
+        ImageFormat::WebP => webp_dimensions(bytes),
+        #[cfg(feature = "tiff")]
+        ImageFormat::Tiff => crate::pages::tiff_dimensions(bytes),
+        _ => image::io::Reader::with_format(std::io::Cursor::new(bytes), format).into_dimensions(),
+    }
+}
+
+/// Saves an image, such as the result of [resize](crate::resize), in the format given
+/// by the extension of the path.
+///
+/// Besides the formats the `image` crate can write, WebP images are written
+/// with the `webp` feature, losslessly, AVIF images with the `avif` feature,
+/// and TIFF images with the `tiff` feature.
+///
+/// ```no_run
+/// let img = seamcarving::open_image("./my_image.webp").unwrap();
+/// let resized = seamcarving::resize(&img, 100, 100);
+/// seamcarving::save_image(&resized, "./resized.avif").unwrap();
+/// ```
+pub fn save_image(img: &RgbaImage, path: impl AsRef<Path>) -> ImageResult<()> {
+    let format = ImageFormat::from_path(&path)?;
+    std::fs::write(path, encode_image(img, format)?)?;
+    Ok(())
+}
+
+/// Saves an image like [save_image], with the metadata of the original image,
+/// in which the width and height are replaced by those of the saved image.
+/// Metadata is only written to JPEG and PNG images.
+///
+/// ```no_run
+/// let (img, metadata) = seamcarving::open_image_with_metadata("./photo.jpg").unwrap();
+/// let resized = seamcarving::resize(&img, 100, 100);
+/// seamcarving::save_image_with_metadata(&resized, "./resized.jpg", &metadata).unwrap();
+/// ```
+pub fn save_image_with_metadata(
+    img: &RgbaImage,
+    path: impl AsRef<Path>,
+    metadata: &Metadata,
+) -> ImageResult<()> {
+    let format = ImageFormat::from_path(&path)?;
+    let (width, height) = img.dimensions();
+    let encoded = encode_image(img, format)?;
+    std::fs::write(path, metadata.resized(width, height).embed(&encoded))?;
+    Ok(())
+}
+
+/// Saves an image like [save_image_with_metadata], keeping the channels of gray images,
+/// see [encode_dynamic_image]
+pub fn save_dynamic_image_with_metadata(
+    img: &DynamicImage,
+    path: impl AsRef<Path>,
+    metadata: &Metadata,
+) -> ImageResult<()> {
+    let format = ImageFormat::from_path(&path)?;
+    let encoded = encode_dynamic_image(img, format)?;
+    std::fs::write(path, metadata.resized(img.width(), img.height()).embed(&encoded))?;
+    Ok(())
+}
+
+/// Encodes an image like [encode_image], except that 8 bit gray images stay gray in PNG,
+/// with their alpha channel, and in JPEG, without it.
+/// Other images and formats are encoded in RGBA.
+pub fn encode_dynamic_image(img: &DynamicImage, format: ImageFormat) -> ImageResult<Vec<u8>> {
+    let mut bytes = Vec::new();
+    match (img, format) {
+        // The `image` crate writes 16 bit PNG images in the wrong byte order
+        (DynamicImage::ImageLuma8(_) | DynamicImage::ImageLumaA8(_), ImageFormat::Png) => {
+            img.write_to(&mut bytes, format)?
+        }
+        (DynamicImage::ImageLuma8(_) | DynamicImage::ImageLumaA8(_), ImageFormat::Jpeg) => {
+            DynamicImage::ImageLuma8(img.to_luma8())
+                .write_to(&mut bytes, ImageOutputFormat::Jpeg(JPEG_QUALITY))?
+        }
+        _ => return encode_image(&img.to_rgba8(), format),
+    }
+    Ok(bytes)
+}
+
+/// Encodes an image in the given format, see [save_image]
+pub fn encode_image(img: &RgbaImage, format: ImageFormat) -> ImageResult<Vec<u8>> {
+    let mut bytes = Vec::new();
+    match format {
+        #[cfg(feature = "webp")]
+        ImageFormat::WebP => {
+            let encoder = image_webp::WebPEncoder::new(&mut bytes);
+            let (width, height) = img.dimensions();
+            encoder
+                .encode(img, width, height, image_webp::ColorType::Rgba8)
+                .map_err(|e| encoding_error(format, e))?;
+        }
+        #[cfg(feature = "avif")]
+        ImageFormat::Avif => {
+            let pixels: Vec<ravif::RGBA8> = img
+                .pixels()
+                .map(|p| ravif::RGBA8::new(p[0], p[1], p[2], p[3]))
+                .collect();
+            let (width, height) = (img.width() as usize, img.height() as usize);
+            let encoded = ravif::Encoder::new()
+                .encode_rgba(ravif::Img::new(&pixels[..], width, height))
+                .map_err(|e| encoding_error(format, e))?;
+            bytes = encoded.avif_file;
+        }
+        #[cfg(feature = "tiff")]
+        ImageFormat::Tiff => bytes = crate::pages::encode_pages(std::slice::from_ref(img))?,
+        // JPEG has no alpha channel
+        ImageFormat::Jpeg => {
+            DynamicImage::ImageRgb8(DynamicImage::ImageRgba8(img.clone()).to_rgb8())
+                .write_to(&mut bytes, ImageOutputFormat::Jpeg(JPEG_QUALITY))?
+        }
+        _ => DynamicImage::ImageRgba8(img.clone()).write_to(&mut bytes, format)?,
+    }
+    Ok(bytes)
+}
+
+/// Creates and encodes narrower versions of an image at once, such as the images
+/// of a responsive `srcset`. The versions are returned in the same order as the widths.
+///
+/// Seams are computed only once for all the versions, see [Carver::resize_widths].
+/// With `max_carved` set, at most that fraction of the width of the image is carved away,
+/// and versions that are narrower than that are carved to that width,
+/// then scaled down to their own width.
+///
+/// ```no_run
+/// use image::ImageFormat;
+/// let img = seamcarving::open_image("./my_image.jpg").unwrap();
+/// let widths = [320, 640, 1024];
+/// let variants = seamcarving::generate_variants(&img, &widths, ImageFormat::Jpeg, Some(0.4)).unwrap();
+/// for (width, bytes) in widths.iter().zip(variants) {
+///     std::fs::write(format!("./my_image_{}w.jpg", width), bytes).unwrap();
+/// }
+/// ```
+pub fn generate_variants<IMG: GenericImageView<Pixel = Rgba<u8>>>(
+    img: &IMG,
+    widths: &[u32],
+    format: ImageFormat,
+    max_carved: Option<f64>,
+) -> ImageResult<Vec<Vec<u8>>> {
+    let (width, height) = img.dimensions();
+    let min_carved_width = max_carved.map_or(0, |fraction| {
+        (f64::from(width) * (1. - fraction.clamp(0., 1.))).ceil() as u32
+    });
+    let carved_widths: Vec<u32> = widths.iter().map(|&w| w.max(min_carved_width)).collect();
+    let versions = Carver::new()
+        .resize_widths(img, &carved_widths)
+        .expect("no pixel is forbidden");
+    versions
+        .into_iter()
+        .zip(widths)
+        .map(|(version, &width)| encode_image(&scale_down(version, width, height).0, format))
+        .collect()
+}
+
+#[cfg(feature = "webp")]
+fn decode_webp(bytes: &[u8]) -> ImageResult<DynamicImage> {
+    use image::error::DecodingError;
+    let error = |e| ImageError::Decoding(DecodingError::new(ImageFormat::WebP.into(), e));
+    let mut decoder = image_webp::WebPDecoder::new(std::io::Cursor::new(bytes)).map_err(error)?;
+    let (width, height) = decoder.dimensions();
+    let mut pixels = vec![0; decoder.output_buffer_size().unwrap_or(0)];
+    decoder.read_image(&mut pixels).map_err(error)?;
+    let img = if decoder.has_alpha() {
+        image::RgbaImage::from_raw(width, height, pixels).map(DynamicImage::ImageRgba8)
+    } else {
+        image::RgbImage::from_raw(width, height, pixels).map(DynamicImage::ImageRgb8)
+    };
+    img.ok_or_else(|| {
+        ImageError::Decoding(DecodingError::from_format_hint(ImageFormat::WebP.into()))
+    })
+}
+
+#[cfg(feature = "webp")]
+fn webp_dimensions(bytes: &[u8]) -> ImageResult<(u32, u32)> {
+    use image::error::DecodingError;
+    let decoder = image_webp::WebPDecoder::new(std::io::Cursor::new(bytes))
+        .map_err(|e| ImageError::Decoding(DecodingError::new(ImageFormat::WebP.into(), e)))?;
+    Ok(decoder.dimensions())
+}
+
+#[cfg(any(feature = "webp", feature = "avif"))]
+fn encoding_error(
+    format: ImageFormat,
+    error: impl std::error::Error + Send + Sync + 'static,
+) -> ImageError {
+    ImageError::Encoding(EncodingError::new(ImageFormatHint::Exact(format), error))
+}
+
+#[cfg(test)]
+mod tests {
+    use image::{GrayImage, ImageFormat, Luma, Rgba, RgbaImage};
+
+    use super::*;
+
+    fn image() -> RgbaImage {
+        RgbaImage::from_fn(12, 8, |x, y| Rgba([(x * 20) as u8, (y * 30) as u8, 7, 255]))
+    }
+
+    #[test]
+    fn encodes_and_decodes_png() {
+        let bytes = encode_image(&image(), ImageFormat::Png).unwrap();
+        assert_eq!(load_image(&bytes).unwrap().to_rgba8(), image());
+        let jpeg = encode_image(&image(), ImageFormat::Jpeg).unwrap();
+        assert_eq!(image::guess_format(&jpeg).unwrap(), ImageFormat::Jpeg);
+    }
+
+    #[test]
+    fn keeps_gray_images_gray() {
+        let gray = GrayImage::from_fn(12, 8, |x, y| Luma([(x * 20 + y) as u8]));
+        let img = DynamicImage::ImageLuma8(gray.clone());
+        let png = load_image(&encode_dynamic_image(&img, ImageFormat::Png).unwrap()).unwrap();
+        assert_eq!(png.as_luma8(), Some(&gray));
+        let jpeg = load_image(&encode_dynamic_image(&img, ImageFormat::Jpeg).unwrap()).unwrap();
+        assert!(jpeg.as_luma8().is_some());
+        let alpha = DynamicImage::ImageLumaA8(img.to_luma_alpha8());
+        let png = load_image(&encode_dynamic_image(&alpha, ImageFormat::Png).unwrap()).unwrap();
+        assert_eq!(png.as_luma_alpha8(), alpha.as_luma_alpha8());
+        // Color images are encoded like any other
+        let color = DynamicImage::ImageRgba8(image());
+        let bytes = encode_dynamic_image(&color, ImageFormat::Png).unwrap();
+        assert_eq!(bytes, encode_image(&image(), ImageFormat::Png).unwrap());
+    }
+
+    #[test]
+    fn generates_variants() {
+        let widths = [10, 4, 8];
+        let variants = generate_variants(&image(), &widths, ImageFormat::Png, None).unwrap();
+        let decoded: Vec<RgbaImage> = variants
+            .iter()
+            .map(|bytes| load_image(bytes).unwrap().to_rgba8())
+            .collect();
+        assert_eq!(decoded[0], crate::resize(&image(), 10, 8));
+        assert_eq!(decoded[1], crate::resize(&image(), 4, 8));
+        // At most half of the width is carved, the rest is scaled
+        let capped = generate_variants(&image(), &widths, ImageFormat::Png, Some(0.5)).unwrap();
+        assert_eq!(capped[0], variants[0]);
+        let narrowest = load_image(&capped[1]).unwrap().to_rgba8();
+        assert_eq!(narrowest.dimensions(), (4, 8));
+        assert_ne!(narrowest, decoded[1]);
+    }
+
+    #[test]
+    #[cfg(feature = "webp")]
+    fn encodes_and_decodes_webp() {
+        let bytes = encode_image(&image(), ImageFormat::WebP).unwrap();
+        assert_eq!(load_image(&bytes).unwrap().to_rgba8(), image());
+    }
+
+    #[test]
+    #[cfg(feature = "avif")]
+    fn encodes_avif() {
+        let bytes = encode_image(&image(), ImageFormat::Avif).unwrap();
+        assert_eq!(&bytes[4..12], b"ftypavif");
+    }
+}
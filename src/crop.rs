@@ -0,0 +1,103 @@
+use image::GenericImageView;
+
+use crate::energy::energy_fn;
+use crate::max_pos;
+use crate::pos::Pos;
+use crate::Rect;
+
+/// Finds the crop window of the given size that keeps the largest total energy,
+/// that is, the crop that loses the least content.
+///
+/// This uses the same energy function as [resize](crate::resize), so it can be used
+/// to decide whether cropping or seam carving is better suited to an image.
+/// Dimensions larger than the image are clipped to the image,
+/// and a width or height of 0 gives an empty crop at the top left corner.
+///
+/// ```no_run
+/// let img = image::open("./my_image.jpg").unwrap();
+/// let crop = seamcarving::suggest_crop(&img, 100, 100);
+/// let cropped = image::imageops::crop_imm(&img, crop.x, crop.y, crop.width, crop.height);
+/// ```
+pub fn suggest_crop<IMG: GenericImageView>(img: &IMG, width: u32, height: u32) -> Rect {
+    let size = max_pos(img);
+    let (width, height) = (width.min(size.0), height.min(size.1));
+    if width == 0 || height == 0 {
+        return Rect {
+            x: 0,
+            y: 0,
+            width,
+            height,
+        };
+    }
+    // Summed-area table of the energy, with an additional line and column of zeros
+    let stride = size.0 as usize + 1;
+    let mut sums = vec![0u64; stride * (size.1 as usize + 1)];
+    for Pos(x, y) in Pos::iter_in_rect(Pos(0, 0), size) {
+        let (x, y) = (x as usize, y as usize);
+        sums[(y + 1) * stride + x + 1] = u64::from(energy_fn(img, Pos(x as u32, y as u32)))
+            + sums[y * stride + x + 1]
+            + sums[(y + 1) * stride + x]
+            - sums[y * stride + x];
+    }
+    let at = |x: u32, y: u32| sums[y as usize * stride + x as usize];
+    let kept = |x: u32, y: u32| {
+        at(x + width, y + height) + at(x, y) - at(x + width, y) - at(x, y + height)
+    };
+
+    let (x, y) = (0..=size.1 - height)
+        .flat_map(|y| (0..=size.0 - width).map(move |x| (x, y)))
+        // Keep the first window among equally good ones
+        .max_by_key(|&(x, y)| (kept(x, y), std::cmp::Reverse((y, x))))
+        .unwrap_or((0, 0));
+    Rect {
+        x,
+        y,
+        width,
+        height,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use image::{GrayImage, Luma};
+
+    use crate::{suggest_crop, Rect};
+
+    #[test]
+    fn crops_around_detail() {
+        // A flat image with a small checkerboard near its bottom right corner
+        let img = GrayImage::from_fn(10, 8, |x, y| {
+            Luma([
+                if (6..9).contains(&x) && (4..7).contains(&y) && (x + y) % 2 == 0 {
+                    255
+                } else {
+                    0
+                },
+            ])
+        });
+        let crop = suggest_crop(&img, 5, 5);
+        assert!(crop.x <= 6 && crop.x + crop.width >= 9);
+        assert!(crop.y <= 4 && crop.y + crop.height >= 7);
+        let full = suggest_crop(&img, 20, 20);
+        let expected = Rect {
+            x: 0,
+            y: 0,
+            width: 10,
+            height: 8,
+        };
+        assert_eq!(full, expected);
+    }
+
+    #[test]
+    fn crops_empty_images() {
+        let empty = Rect {
+            x: 0,
+            y: 0,
+            width: 0,
+            height: 0,
+        };
+        assert_eq!(suggest_crop(&GrayImage::new(0, 4), 0, 2), Rect { height: 2, ..empty });
+        assert_eq!(suggest_crop(&GrayImage::new(0, 0), 3, 2), empty);
+        assert_eq!(suggest_crop(&GrayImage::new(5, 4), 0, 0), empty);
+    }
+}
@@ -0,0 +1,167 @@
+use image::{GenericImageView, GrayImage, Luma, Pixel, Rgb, RgbImage, Rgba, RgbaImage};
+
+use crate::energy::{energy_fn, energy_level, to_8bit};
+use crate::matrix::Matrix;
+use crate::pos::Pos;
+use crate::{max_pos, Carver, SeamError};
+
+/// Color mixed with the removed pixels of the original image
+const REMOVED_TINT: [u8; 3] = [255, 0, 0];
+
+/// Resizes an image with the given carver, and shows what changed in a single picture:
+/// the original image with its removed pixels tinted in red, next to the resized image,
+/// and, if `with_energy` is set, next to the energy of the pixels of the original image.
+///
+/// Each panel is as large as the original image, and the resized image is in the top left
+/// corner of its panel, on a transparent background.
+/// Pixels removed by scaling the image down are not tinted,
+/// and nothing is tinted when the image is [carved in stripes](Carver::max_memory).
+///
+/// ```
+/// let img = image::RgbaImage::from_fn(20, 10, |x, y| image::Rgba([(x * y) as u8, 0, 0, 255]));
+/// let carver = seamcarving::Carver::new().width(15);
+/// let diff = seamcarving::diff_image(&carver, &img, true).unwrap();
+/// assert_eq!(diff.dimensions(), (60, 10));
+/// ```
+pub fn diff_image<IMG: GenericImageView>(
+    carver: &Carver,
+    img: &IMG,
+    with_energy: bool,
+) -> Result<RgbaImage, SeamError>
+where
+    <IMG as GenericImageView>::Pixel: 'static,
+{
+    let (resized, _report, origins) = carver.resize_tracking(img)?;
+    let (width, height) = img.dimensions();
+    let panels = if with_energy { 3 } else { 2 };
+    let mut diff = RgbaImage::new(width * panels, height);
+    for (x, y, pixel) in tint_removed(img, origins.as_ref()).enumerate_pixels() {
+        diff.put_pixel(x, y, *pixel);
+    }
+    for (x, y, &pixel) in resized.enumerate_pixels() {
+        diff.put_pixel(width + x, y, rgba8(pixel));
+    }
+    if with_energy {
+        for (x, y, &Luma([level])) in energy_image(img).enumerate_pixels() {
+            diff.put_pixel(2 * width + x, y, Rgba([level, level, level, 255]));
+        }
+    }
+    Ok(diff)
+}
+
+/// Resizes an image with the given carver, and returns the original image
+/// with the pixels of the removed seams tinted in red, the first panel of [diff_image]
+pub fn seams_image<IMG: GenericImageView>(
+    carver: &Carver,
+    img: &IMG,
+) -> Result<RgbaImage, SeamError>
+where
+    <IMG as GenericImageView>::Pixel: 'static,
+{
+    let (_resized, _report, origins) = carver.resize_tracking(img)?;
+    Ok(tint_removed(img, origins.as_ref()))
+}
+
+/// The energy of each pixel of an image, from black for flat regions to white,
+/// the last panel of [diff_image]
+pub fn energy_image<IMG: GenericImageView>(img: &IMG) -> GrayImage {
+    GrayImage::from_fn(img.width(), img.height(), |x, y| {
+        Luma([energy_level(energy_fn(img, Pos(x, y)))])
+    })
+}
+
+/// The energy of each pixel of an image, like [energy_image], with the colors of fire:
+/// black for flat regions, then red, yellow, and white for the strongest edges
+pub fn energy_heatmap<IMG: GenericImageView>(img: &IMG) -> RgbImage {
+    let energies = energy_image(img);
+    RgbImage::from_fn(img.width(), img.height(), |x, y| {
+        let Luma([level]) = *energies.get_pixel(x, y);
+        // Each third of the levels brings up one more channel
+        let channel = |third: u16| {
+            let level = u16::from(level).saturating_sub(85 * third);
+            (level * 3).min(255) as u8
+        };
+        Rgb([channel(0), channel(1), channel(2)])
+    })
+}
+
+/// The image, with the pixels that are not in the origins of the resized image tinted
+fn tint_removed<IMG: GenericImageView>(img: &IMG, origins: Option<&Matrix<Pos>>) -> RgbaImage {
+    let mut kept = Matrix::from_fn(max_pos(img), |_, _| origins.is_none());
+    for &pos in origins.iter().flat_map(|origins| origins.as_slice()) {
+        kept[pos] = true;
+    }
+    RgbaImage::from_fn(img.width(), img.height(), |x, y| {
+        let Rgba([r, g, b, a]) = rgba8(img.get_pixel(x, y));
+        if kept[Pos(x, y)] {
+            Rgba([r, g, b, a])
+        } else {
+            let [tr, tg, tb] = REMOVED_TINT;
+            let mix = |c: u8, t: u8| ((u16::from(c) + u16::from(t)) / 2) as u8;
+            Rgba([mix(r, tr), mix(g, tg), mix(b, tb), 255])
+        }
+    })
+}
+
+fn rgba8<P: Pixel>(pixel: P) -> Rgba<u8> {
+    let Rgba([r, g, b, a]) = pixel.to_rgba();
+    Rgba([to_8bit(r), to_8bit(g), to_8bit(b), to_8bit(a)])
+}
+
+#[cfg(test)]
+mod tests {
+    use image::{GrayImage, Luma};
+
+    use super::*;
+
+    #[test]
+    fn tints_the_removed_pixels() {
+        // A uniform column, the only one without any energy
+        let img = GrayImage::from_fn(9, 6, |x, y| match x {
+            3..=5 => Luma([100]),
+            _ => Luma([((x * 73 + y * 31) % 256) as u8]),
+        });
+        let carver = Carver::new().width(8);
+        let diff = diff_image(&carver, &img, false).unwrap();
+        assert_eq!(diff.dimensions(), (18, 6));
+        for y in 0..6 {
+            let tinted: Vec<u32> = (0..9)
+                .filter(|&x| diff.get_pixel(x, y) != &img.get_pixel(x, y).to_rgba())
+                .collect();
+            assert_eq!(tinted, vec![4]);
+        }
+        assert_eq!(diff.get_pixel(3, 0), &Rgba([100, 100, 100, 255]));
+        // The resized image, then transparency
+        assert_eq!(diff.get_pixel(9 + 4, 0), &Rgba([100, 100, 100, 255]));
+        assert_eq!(diff.get_pixel(17, 0), &Rgba([0, 0, 0, 0]));
+        let with_energy = diff_image(&carver, &img, true).unwrap();
+        assert_eq!(with_energy.dimensions(), (27, 6));
+        assert_eq!(with_energy.get_pixel(18 + 4, 0), &Rgba([0, 0, 0, 255]));
+        // Every pixel of the resized image is kept once, whatever the direction of its seams
+        let diff = diff_image(&Carver::new().width(7).height(4), &img, false).unwrap();
+        let kept = img
+            .enumerate_pixels()
+            .filter(|&(x, y, pixel)| diff.get_pixel(x, y) == &pixel.to_rgba())
+            .count();
+        assert_eq!(kept, 7 * 4);
+    }
+
+    #[test]
+    fn shows_seams_and_energy() {
+        let img = GrayImage::from_fn(9, 6, |x, _y| Luma([if x < 4 { 0 } else { 255 }]));
+        let carver = Carver::new().width(7);
+        let diff = diff_image(&carver, &img, true).unwrap();
+        let seams = seams_image(&carver, &img).unwrap();
+        assert_eq!(seams, diff.view(0, 0, 9, 6).to_image());
+        let energy = energy_image(&img);
+        let panel = diff.view(18, 0, 9, 6).to_image();
+        assert!(energy.enumerate_pixels().all(|(x, y, &Luma([l]))| {
+            panel.get_pixel(x, y) == &Rgba([l, l, l, 255])
+        }));
+        let heatmap = energy_heatmap(&img);
+        assert_eq!(heatmap.get_pixel(0, 0), &Rgb([0, 0, 0]));
+        // The edge is halfway between red and yellow
+        assert_eq!(energy.get_pixel(4, 0), &Luma([127]));
+        assert_eq!(heatmap.get_pixel(4, 0), &Rgb([255, 126, 0]));
+    }
+}
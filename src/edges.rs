@@ -0,0 +1,106 @@
+use image::{GenericImageView, GrayImage, Luma, Pixel, RgbaImage};
+use imageproc::edges::canny;
+use imageproc::gradients::sobel_gradients;
+
+use crate::energy::to_8bit;
+use crate::importance::ImportanceMap;
+use crate::matrix::Matrix;
+use crate::max_pos;
+use crate::pipeline::EnergyStage;
+use crate::seam_finder::FORBIDDEN;
+
+impl ImportanceMap {
+    /// Protects the edges of the image, with an importance proportional to the magnitude
+    /// of their Sobel gradient, as computed by imageproc, multiplied by `strength`.
+    ///
+    /// The gradient is computed on the luminosity of the image, on the 8-bit scale.
+    pub fn sobel<IMG: GenericImageView>(img: &IMG, strength: f32) -> Self {
+        let size = max_pos(img);
+        let gradients = sobel_gradients(&gray_image(img));
+        let bias = Matrix::from_fn(size, |x, y| {
+            let Luma([magnitude]) = *gradients.get_pixel(x as u32, y as u32);
+            (f32::from(magnitude) * strength).min((FORBIDDEN - 1) as f32) as u32
+        });
+        ImportanceMap::from_bias(size, bias)
+    }
+
+    /// Gives the given importance to the edges found by imageproc's Canny edge detector,
+    /// with the given hysteresis thresholds on the gradient of the luminosity.
+    ///
+    /// Panics if `high_threshold` is lower than `low_threshold`.
+    pub fn canny<IMG: GenericImageView>(
+        img: &IMG,
+        low_threshold: f32,
+        high_threshold: f32,
+        importance: u32,
+    ) -> Self {
+        let size = max_pos(img);
+        let edges = canny(&gray_image(img), low_threshold, high_threshold);
+        let bias = Matrix::from_fn(size, |x, y| {
+            if edges.get_pixel(x as u32, y as u32)[0] > 0 {
+                importance
+            } else {
+                0
+            }
+        });
+        ImportanceMap::from_bias(size, bias)
+    }
+}
+
+/// The importance of the edges found by the Canny edge detector, see [ImportanceMap::canny]
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Canny {
+    pub low: f32,
+    pub high: f32,
+    pub importance: u32,
+}
+
+impl EnergyStage for Canny {
+    fn name(&self) -> String {
+        format!(
+            "canny(low={}, high={}, importance={})",
+            self.low, self.high, self.importance
+        )
+    }
+
+    fn importance(&self, img: &RgbaImage) -> ImportanceMap {
+        ImportanceMap::canny(img, self.low, self.high, self.importance)
+    }
+}
+
+/// The luminosity of the image, as the 8-bit grayscale image imageproc works on
+fn gray_image<IMG: GenericImageView>(img: &IMG) -> GrayImage {
+    GrayImage::from_fn(img.width(), img.height(), |x, y| {
+        let Luma([l]) = img.get_pixel(x, y).to_luma();
+        Luma([to_8bit(l)])
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use image::{GrayImage, Luma};
+
+    use crate::pos::Pos;
+    use crate::ImportanceMap;
+
+    /// A dark left half and a bright right half
+    fn step() -> GrayImage {
+        GrayImage::from_fn(20, 10, |x, _y| Luma([if x < 10 { 20 } else { 220 }]))
+    }
+
+    #[test]
+    fn sobel_protects_edges() {
+        let importance = ImportanceMap::sobel(&step(), 2.);
+        assert_eq!(importance.bias[Pos(2, 5)], 0);
+        assert_eq!(importance.bias[Pos(17, 5)], 0);
+        assert!(importance.bias[Pos(10, 5)] > 1000);
+    }
+
+    #[test]
+    fn canny_protects_edges() {
+        let importance = ImportanceMap::canny(&step(), 20., 60., 500);
+        let row: Vec<u32> = (0..20).map(|x| importance.bias[Pos(x, 5)]).collect();
+        assert_eq!(row.iter().filter(|&&b| b == 500).count(), 1);
+        assert!(row[..7].iter().all(|&b| b == 0));
+    }
+}
@@ -0,0 +1,284 @@
+use image::imageops::{blur, grayscale};
+use image::{GrayImage, RgbaImage};
+
+use crate::importance::ImportanceMap;
+use crate::matrix::Matrix;
+use crate::max_pos;
+use crate::pipeline::EnergyStage;
+use crate::pos::Pos;
+use crate::seam_finder::FORBIDDEN;
+
+/// The energy of the carver alone, the gradient of the image around each pixel,
+/// which this stage does not add anything to
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Default)]
+pub struct Gradient;
+
+impl EnergyStage for Gradient {
+    fn name(&self) -> String {
+        "gradient".to_string()
+    }
+
+    fn importance(&self, img: &RgbaImage) -> ImportanceMap {
+        importance_from(img, |_x, _y| 0.)
+    }
+}
+
+/// The magnitude of the Sobel gradient of the luminosity of the images,
+/// on the 8-bit scale, multiplied by `strength`.
+/// Sobel kernels smooth the gradient over 3x3 squares, so they are less sensitive to noise
+/// than the gradient of the carver.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Sobel {
+    pub strength: f32,
+}
+
+impl EnergyStage for Sobel {
+    fn name(&self) -> String {
+        format!("sobel(strength={})", self.strength)
+    }
+
+    fn importance(&self, img: &RgbaImage) -> ImportanceMap {
+        let gray = grayscale(img);
+        let (width, height) = gray.dimensions();
+        // The pixels outside of the image are the ones on its edge
+        let luma = |x: u32, dx: i32, y: u32, dy: i32| {
+            let x = (x as i32 + dx).clamp(0, width as i32 - 1) as u32;
+            let y = (y as i32 + dy).clamp(0, height as i32 - 1) as u32;
+            i32::from(gray.get_pixel(x, y).0[0])
+        };
+        importance_from(img, |x, y| {
+            let l = |dx, dy| luma(x, dx, y, dy);
+            let gx = l(1, -1) + 2 * l(1, 0) + l(1, 1) - l(-1, -1) - 2 * l(-1, 0) - l(-1, 1);
+            let gy = l(-1, 1) + 2 * l(0, 1) + l(1, 1) - l(-1, -1) - 2 * l(0, -1) - l(1, -1);
+            let magnitude = ((gx * gx + gy * gy) as f32).sqrt().trunc();
+            magnitude * self.strength
+        })
+    }
+}
+
+/// The Shannon entropy, in bits, of the luminosities in a square of side `2 * radius + 1`
+/// centered on each pixel, multiplied by `strength`.
+/// It is high in detailed regions whatever their contrast,
+/// and low in flat regions and smooth gradients.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Entropy {
+    pub radius: u32,
+    pub strength: f32,
+}
+
+impl EnergyStage for Entropy {
+    fn name(&self) -> String {
+        format!(
+            "entropy(radius={}, strength={})",
+            self.radius, self.strength
+        )
+    }
+
+    fn importance(&self, img: &RgbaImage) -> ImportanceMap {
+        let gray = grayscale(img);
+        let entropies = local_entropies(&gray, self.radius);
+        importance_from(img, |x, y| entropies[Pos(x, y)] as f32 * self.strength)
+    }
+}
+
+/// The squared distance between the color of each pixel, blurred with a gaussian
+/// of standard deviation `sigma`, and the mean color of the whole image,
+/// multiplied by `strength`, as in frequency-tuned salient region detection:
+/// regions that stand out from the rest of the image are protected.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Saliency {
+    pub sigma: f32,
+    pub strength: f32,
+}
+
+impl EnergyStage for Saliency {
+    fn name(&self) -> String {
+        format!("saliency(sigma={}, strength={})", self.sigma, self.strength)
+    }
+
+    fn importance(&self, img: &RgbaImage) -> ImportanceMap {
+        let count = f64::from(img.width()) * f64::from(img.height());
+        let mut mean = [0.; 3];
+        for pixel in img.pixels() {
+            for (sum, &channel) in mean.iter_mut().zip(&pixel.0) {
+                *sum += f64::from(channel) / count;
+            }
+        }
+        let blurred = blur(img, self.sigma);
+        importance_from(img, |x, y| {
+            let pixel = blurred.get_pixel(x, y).0;
+            let distance: f64 = mean
+                .iter()
+                .zip(&pixel)
+                .map(|(&mean, &channel)| (f64::from(channel) - mean).powi(2))
+                .sum();
+            distance as f32 * self.strength
+        })
+    }
+}
+
+/// Importance growing toward the center of the images, up to `strength`,
+/// the sharper the higher `falloff` is, see [ImportanceMap::center_bias].
+/// Seams then remove the periphery of the images first, which suits most photos,
+/// whose subject tends to be in the middle, without computing any saliency.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct CenterBias {
+    pub strength: f32,
+    pub falloff: f32,
+}
+
+impl EnergyStage for CenterBias {
+    fn name(&self) -> String {
+        format!("center(strength={}, falloff={})", self.strength, self.falloff)
+    }
+
+    fn importance(&self, img: &RgbaImage) -> ImportanceMap {
+        let (width, height) = img.dimensions();
+        ImportanceMap::center_bias(width, height, self.strength, self.falloff)
+    }
+}
+
+/// An importance map of the size of the image, from the importance of each pixel
+fn importance_from(img: &RgbaImage, importance: impl Fn(u32, u32) -> f32) -> ImportanceMap {
+    let size = max_pos(img);
+    let bias = Matrix::from_fn(size, |x, y| {
+        let value = importance(x as u32, y as u32);
+        // Saturates, and NaN becomes 0
+        value.clamp(0., (FORBIDDEN - 1) as f32) as u32
+    });
+    ImportanceMap::from_bias(size, bias)
+}
+
+/// The entropy of the luminosities around each pixel, with a histogram of the square
+/// around the pixel that slides along each row
+fn local_entropies(gray: &GrayImage, radius: u32) -> Matrix<f64> {
+    let size = max_pos(gray);
+    let Pos(width, height) = size;
+    let mut entropies = Matrix::from_fn(size, |_, _| 0.);
+    // Sum of `count * log2(count)` over the bins, from which the entropy is computed
+    let c_log_c = |count: u32| {
+        let count = f64::from(count);
+        if count > 0. {
+            count * count.log2()
+        } else {
+            0.
+        }
+    };
+    for y in 0..height {
+        let rows = y.saturating_sub(radius)..y.saturating_add(radius + 1).min(height);
+        let mut histogram = [0u32; 256];
+        let (mut total, mut sum) = (0u32, 0.);
+        let mut update = |x: u32, added: bool| {
+            for y in rows.clone() {
+                let bin = &mut histogram[usize::from(gray.get_pixel(x, y).0[0])];
+                sum -= c_log_c(*bin);
+                if added {
+                    *bin += 1;
+                    total += 1;
+                } else {
+                    *bin -= 1;
+                    total -= 1;
+                }
+                sum += c_log_c(*bin);
+            }
+            (total, sum)
+        };
+        let mut state = (0, 0.);
+        for x in 0..radius.saturating_add(1).min(width) {
+            state = update(x, true);
+        }
+        for x in 0..width {
+            let (total, sum) = state;
+            let total = f64::from(total);
+            entropies[Pos(x, y)] = (total.log2() - sum / total).max(0.);
+            if x >= radius {
+                state = update(x - radius, false);
+            }
+            if let Some(next) = x.checked_add(radius + 1).filter(|&next| next < width) {
+                state = update(next, true);
+            }
+        }
+    }
+    entropies
+}
+
+#[cfg(test)]
+mod tests {
+    use image::Rgba;
+
+    use super::*;
+
+    /// A flat left half and a noisy right half
+    fn image() -> RgbaImage {
+        RgbaImage::from_fn(16, 8, |x, y| {
+            let value = if x < 8 {
+                100
+            } else {
+                ((x * 37 + y * 101) % 256) as u8
+            };
+            Rgba([value, value, value, 255])
+        })
+    }
+
+    #[test]
+    fn entropy_is_high_in_details() {
+        let importance = Entropy {
+            radius: 1,
+            strength: 1000.,
+        }
+        .importance(&image());
+        assert_eq!(importance.bias[Pos(2, 4)], 0);
+        // Nine different values around the pixel
+        let expected = (9f64.log2() * 1000.) as u32;
+        assert!(importance.bias[Pos(12, 4)].abs_diff(expected) <= 1);
+        // The corner only sees four pixels
+        assert_eq!(importance.bias[Pos(15, 0)], 2000);
+    }
+
+    #[test]
+    fn sobel_and_saliency_find_the_details() {
+        let img = image();
+        let sobel = Sobel { strength: 1. }.importance(&img);
+        assert_eq!(sobel.bias[Pos(3, 3)], 0);
+        assert!(sobel.bias[Pos(12, 3)] > 100);
+        #[cfg(feature = "imageproc")]
+        {
+            let expected = ImportanceMap::sobel(&img, 1.);
+            assert_eq!(sobel.bias.as_slice(), expected.bias.as_slice());
+        }
+        let gradient = Gradient.importance(&img);
+        assert!(gradient.bias.as_slice().iter().all(|&bias| bias == 0));
+        // A bright spot on a dark background
+        let spot = RgbaImage::from_fn(9, 9, |x, y| {
+            Rgba(if (x, y) == (4, 4) {
+                [255; 4]
+            } else {
+                [0, 0, 0, 255]
+            })
+        });
+        let saliency = Saliency {
+            sigma: 1.,
+            strength: 1.,
+        }
+        .importance(&spot);
+        assert!(saliency.bias[Pos(4, 4)] > saliency.bias[Pos(0, 0)]);
+    }
+
+    #[test]
+    fn center_bias_grows_toward_the_center() {
+        let center = CenterBias {
+            strength: 1000.,
+            falloff: 2.,
+        }
+        .importance(&image());
+        let bias = |x, y| center.bias[Pos(x, y)];
+        assert!(bias(7, 3) > 800);
+        assert!(bias(7, 3) > bias(3, 3) && bias(3, 3) > bias(0, 3));
+        assert_eq!((bias(7, 3), bias(0, 0)), (bias(8, 4), bias(15, 7)));
+        assert!(bias(0, 0) < 10);
+        let linear = ImportanceMap::center_bias(16, 8, 1000., 1.);
+        assert!(linear.bias[Pos(3, 3)] > bias(3, 3));
+        let none = ImportanceMap::center_bias(16, 8, f32::NAN, 1.);
+        assert!(none.bias.as_slice().iter().all(|&bias| bias == 0));
+    }
+}
@@ -1,11 +1,79 @@
+use crate::matrix::Matrix;
 use crate::max_pos;
-use crate::pos::Pos;
-use image::{GenericImageView, Pixel};
-use num_traits::ToPrimitive;
+use crate::pos::{BorderMode, Pos};
+use image::{GenericImageView, GrayImage, Luma, Pixel, Primitive};
+use num_traits::{Bounded, NumCast};
 
 pub(crate) fn energy_fn<IMG: GenericImageView>(img: &IMG, pos: Pos) -> u32 {
     let last_pos = max_pos(img);
-    let [top, bottom, left, right] = pos.surrounding(last_pos);
+    gradient_energy(img, pos.surrounding(last_pos))
+}
+
+/// Energy of a pixel, where the left and right neighbours can wrap around the borders
+/// of the image, where the other neighbours outside of the image are chosen by the border mode,
+/// and where neighbours outside of the valid region are ignored
+pub(crate) fn energy_in<IMG: GenericImageView>(
+    img: &IMG,
+    pos: Pos,
+    wrap: bool,
+    border: BorderMode,
+    valid: Option<&Matrix<bool>>,
+) -> u32 {
+    gradient_energy(img, surrounding_in(img, pos, wrap, border, valid))
+}
+
+/// Forward energy of a pixel: the differences between the pixels that become neighbours
+/// when it is removed, instead of the differences between its own neighbours.
+/// Its left and right neighbours always become adjacent, and the pixel above it
+/// becomes adjacent to one of them if the seam moves diagonally,
+/// which is counted for the cheapest of the two.
+/// Neighbours are found as in [energy_in].
+pub(crate) fn forward_energy_in<IMG: GenericImageView>(
+    img: &IMG,
+    pos: Pos,
+    wrap: bool,
+    border: BorderMode,
+    valid: Option<&Matrix<bool>>,
+) -> u32 {
+    let [top, _bottom, left, right] = surrounding_in(img, pos, wrap, border, valid);
+    let top_px = img.get_pixel(top.0, top.1);
+    let left_px = img.get_pixel(left.0, left.1);
+    let right_px = img.get_pixel(right.0, right.1);
+    let diagonal = square_diff_px(top_px, left_px).min(square_diff_px(top_px, right_px));
+    square_diff_px(left_px, right_px) + diagonal
+}
+
+/// The top, bottom, left and right neighbours of a pixel used to compute its energy
+#[inline(always)]
+fn surrounding_in<IMG: GenericImageView>(
+    img: &IMG,
+    pos: Pos,
+    wrap: bool,
+    border: BorderMode,
+    valid: Option<&Matrix<bool>>,
+) -> [Pos; 4] {
+    let last_pos = max_pos(img);
+    let mut surrounding = match border {
+        BorderMode::Clamp => pos.surrounding(last_pos),
+        border => pos.surrounding_with(last_pos, border),
+    };
+    if wrap {
+        // Seams crossing the left and right borders need them to be compared
+        let [_top, _bottom, left, right] = pos.surrounding_wrapping(last_pos);
+        surrounding[2..].copy_from_slice(&[left, right]);
+    }
+    if let Some(valid) = valid {
+        surrounding
+            .iter_mut()
+            .filter(|p| !valid[**p])
+            .for_each(|p| *p = pos);
+    }
+    surrounding
+}
+
+#[inline(always)]
+fn gradient_energy<IMG: GenericImageView>(img: &IMG, surrounding: [Pos; 4]) -> u32 {
+    let [top, bottom, left, right] = surrounding;
     let top_px = img.get_pixel(top.0, top.1);
     let bottom_px = img.get_pixel(bottom.0, bottom.1);
     let left_px = img.get_pixel(left.0, left.1);
@@ -14,7 +82,7 @@ pub(crate) fn energy_fn<IMG: GenericImageView>(img: &IMG, pos: Pos) -> u32 {
         square_diff_px(left_px, right_px)
 }
 
-fn square_diff_px<P: Pixel>(p1: P, p2: P) -> u32 {
+pub(crate) fn square_diff_px<P: Pixel>(p1: P, p2: P) -> u32 {
     let (ch1, ch2) = (p1.channels(), p2.channels());
     let count = <P as Pixel>::CHANNEL_COUNT as usize;
     let mut sum = 0;
@@ -24,10 +92,58 @@ fn square_diff_px<P: Pixel>(p1: P, p2: P) -> u32 {
     sum
 }
 
+/// Squared difference of two channels, on the scale of 8-bit channels,
+/// so that the energy of an image does not depend on the type of its pixels
 #[inline]
-fn square_diff<T: ToPrimitive>(a: T, b: T) -> u32 {
-    let a = a.to_i32().unwrap_or(i32::max_value());
-    let b = b.to_i32().unwrap_or(i32::max_value());
-    let diff = a - b;
-    (diff * diff) as u32
+fn square_diff<T: Primitive>(a: T, b: T) -> u32 {
+    match channel_scale::<T>() {
+        None => {
+            let a = a.to_i32().unwrap_or(i32::MAX);
+            let b = b.to_i32().unwrap_or(i32::MAX);
+            let diff = a - b;
+            (diff * diff) as u32
+        }
+        Some(scale) => {
+            let diff = (a.to_f64().unwrap_or(0.) - b.to_f64().unwrap_or(0.)) * scale;
+            (diff * diff).round().min(u32::MAX as f64) as u32
+        }
+    }
+}
+
+/// Gray level representing an energy in a picture.
+/// The square root keeps the details of low energies visible.
+pub(crate) fn energy_level(energy: u32) -> u8 {
+    let energy: f64 = energy.into();
+    (energy.sqrt() / 2.).min(255.) as u8
+}
+
+/// The 8-bit luminosity of an image
+pub(crate) fn luminosity<IMG: GenericImageView>(img: &IMG) -> GrayImage {
+    GrayImage::from_fn(img.width(), img.height(), |x, y| {
+        Luma([to_8bit(img.get_pixel(x, y).to_luma().0[0])])
+    })
+}
+
+/// Converts a channel to the 8-bit scale, see [channel_scale]
+pub(crate) fn to_8bit<T: Primitive>(value: T) -> u8 {
+    match channel_scale::<T>() {
+        None => value.to_u8().unwrap_or(u8::MAX),
+        Some(scale) => (value.to_f64().unwrap_or(0.) * scale).round().clamp(0., 255.) as u8,
+    }
+}
+
+/// The factor that brings the channels of type `T` to the range of 8-bit channels,
+/// or None for 8-bit channels, which are compared as they are.
+/// Floating point channels range from 0 to 1, integer channels up to their maximum value.
+#[inline(always)]
+fn channel_scale<T: Primitive>() -> Option<f64> {
+    let max = <T as Bounded>::max_value().to_f64().unwrap_or(f64::MAX);
+    let is_float = <T as NumCast>::from(0.5).and_then(|half: T| half.to_f64()) == Some(0.5);
+    if is_float {
+        Some(255.)
+    } else if max == 255. {
+        None
+    } else {
+        Some(255. / max)
+    }
 }
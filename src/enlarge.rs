@@ -0,0 +1,382 @@
+use image::{GenericImageView, ImageBuffer, Pixel};
+use num_traits::{Bounded, NumCast, ToPrimitive};
+
+use crate::matrix::Matrix;
+use crate::pos::Pos;
+use crate::{image_view_to_buffer, Carvable, Dimension, ImageBufferOf, PixelBuffer, Rotated};
+
+/// Number of Gauss-Seidel iterations used to blend the inserted seams in the gradient domain
+const BLEND_ITERATIONS: usize = 100;
+
+/// Percentage of the width of an image that is inserted at most in one step.
+/// Seams are then found again in the enlarged image, so that large enlargements
+/// do not duplicate the same low-energy band over and over.
+const MAX_INSERTED_PERCENT: u32 = 25;
+
+/// How the pixels of the seams inserted by [enlarge_with_blend] are computed
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum InsertionBlend {
+    /// Each inserted pixel is the average of the pixel it duplicates and of its right neighbour
+    Average,
+    /// The inserted pixels are blended in the gradient domain: they keep the vertical
+    /// gradients of the averaged pixels, but are smoothly adjusted to their neighbours
+    /// by solving a Poisson equation.
+    /// This hides the stripes that duplicated seams tend to create, at the cost of some time.
+    Gradient,
+}
+
+/// How [enlarge_with_sampling] chooses the seams it inserts.
+///
+/// Instead of always duplicating the cheapest remaining seam, each inserted seam is drawn
+/// at random among the `candidates` cheapest ones, which breaks the bands that form
+/// when neighbouring low-energy seams are all duplicated.
+/// The same seed always gives the same image.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct SeamSampling {
+    /// Seed of the pseudo-random generator drawing the seams
+    pub seed: u64,
+    /// Number of seams each inserted seam is drawn from.
+    /// With a single candidate, the seams are the same as without sampling.
+    pub candidates: u32,
+}
+
+/// Enlarges an image to the given width and height, by inserting seams where
+/// they are the least visible, see [enlarge_with_blend].
+///
+/// ```no_run
+/// use image::GenericImageView;
+/// let img = image::open("./my_image.jpg").unwrap();
+/// let (width, height) = (img.width(), img.height());
+/// let enlarged = seamcarving::enlarge(&img, width + 100, height);
+/// enlarged.save("./enlarged.jpg");
+/// ```
+pub fn enlarge<IMG: GenericImageView>(img: &IMG, width: u32, height: u32) -> ImageBufferOf<IMG>
+where
+    <IMG as GenericImageView>::Pixel: 'static,
+{
+    enlarge_with_blend(img, width, height, InsertionBlend::Average)
+}
+
+/// Enlarges an image to the given width and height.
+///
+/// To insert n vertical seams, the n seams that [resize](crate::resize) would remove first
+/// are found, and duplicated, computing the new pixels as specified by `blend`.
+/// The image is then enlarged vertically the same way.
+/// An image is enlarged at most by a quarter of its size at once, so larger enlargements
+/// are done in several steps, each one finding the seams of the already enlarged image.
+/// Dimensions that are smaller than the ones of the image are left untouched.
+pub fn enlarge_with_blend<IMG: GenericImageView>(
+    img: &IMG,
+    width: u32,
+    height: u32,
+    blend: InsertionBlend,
+) -> ImageBufferOf<IMG>
+where
+    <IMG as GenericImageView>::Pixel: 'static,
+{
+    enlarge_seams(img, width, height, blend, None, None)
+}
+
+/// Enlarges an image like [enlarge_with_blend], inserting at most `max_step` seams at once,
+/// in pixels or relative to the dimension of the image enlarged so far,
+/// instead of a quarter of it.
+/// Smaller steps find the seams again more often, which spreads the inserted seams
+/// over more of the image, at the cost of time, and larger steps are faster.
+///
+/// ```
+/// use seamcarving::{enlarge_with_max_step, Dimension, InsertionBlend};
+/// let img = image::GrayImage::from_fn(20, 10, |x, y| image::Luma([(x * y) as u8]));
+/// let max_step = Dimension::Pixels(2);
+/// let enlarged = enlarge_with_max_step(&img, 30, 12, InsertionBlend::Average, max_step);
+/// assert_eq!(enlarged.dimensions(), (30, 12));
+/// ```
+pub fn enlarge_with_max_step<IMG: GenericImageView>(
+    img: &IMG,
+    width: u32,
+    height: u32,
+    blend: InsertionBlend,
+    max_step: Dimension,
+) -> ImageBufferOf<IMG>
+where
+    <IMG as GenericImageView>::Pixel: 'static,
+{
+    enlarge_seams(img, width, height, blend, None, Some(max_step))
+}
+
+/// Enlarges an image like [enlarge_with_blend], drawing the inserted seams at random
+/// among the cheapest ones, as specified by `sampling`.
+///
+/// ```
+/// use seamcarving::{enlarge_with_sampling, InsertionBlend, SeamSampling};
+/// let img = image::GrayImage::from_fn(20, 10, |x, y| image::Luma([(x * y) as u8]));
+/// let sampling = SeamSampling { seed: 42, candidates: 4 };
+/// let enlarged = enlarge_with_sampling(&img, 30, 10, InsertionBlend::Average, sampling);
+/// assert_eq!(enlarged.dimensions(), (30, 10));
+/// assert_eq!(enlarged, enlarge_with_sampling(&img, 30, 10, InsertionBlend::Average, sampling));
+/// ```
+pub fn enlarge_with_sampling<IMG: GenericImageView>(
+    img: &IMG,
+    width: u32,
+    height: u32,
+    blend: InsertionBlend,
+    sampling: SeamSampling,
+) -> ImageBufferOf<IMG>
+where
+    <IMG as GenericImageView>::Pixel: 'static,
+{
+    enlarge_seams(img, width, height, blend, Some(sampling), None)
+}
+
+fn enlarge_seams<IMG: GenericImageView>(
+    img: &IMG,
+    width: u32,
+    height: u32,
+    blend: InsertionBlend,
+    sampling: Option<SeamSampling>,
+    max_step: Option<Dimension>,
+) -> ImageBufferOf<IMG>
+where
+    <IMG as GenericImageView>::Pixel: 'static,
+{
+    // The seed is changed between the two axes, and between the steps of each axis
+    let mut rng = sampling.map(|s| (Random(s.seed), s.candidates.max(1)));
+    let wide = widen(img, width, blend, rng.as_mut(), max_step);
+    let tall = widen(&Rotated(&wide), height, blend, rng.as_mut(), max_step);
+    image_view_to_buffer(&Rotated(&tall))
+}
+
+/// Enlarges an image horizontally to the given width.
+/// With a random generator, the seams are drawn among the given number of cheapest seams.
+/// Without a maximum step, a quarter of the width is inserted at most at once.
+pub(crate) fn widen<IMG: GenericImageView>(
+    img: &IMG,
+    width: u32,
+    blend: InsertionBlend,
+    mut sampling: Option<&mut (Random, u32)>,
+    max_step: Option<Dimension>,
+) -> ImageBufferOf<IMG>
+where
+    <IMG as GenericImageView>::Pixel: 'static,
+{
+    let mut current = image_view_to_buffer(img);
+    while current.width() < width && current.width() > 0 {
+        let step = match max_step {
+            Some(max_step) => max_step.resolve(current.width()).max(1),
+            None => (current.width() * MAX_INSERTED_PERCENT).div_ceil(100),
+        };
+        let count = (width - current.width()).min(step);
+        current = insert_seams(&current, count, blend, sampling.as_deref_mut());
+    }
+    current
+}
+
+/// Duplicates the `count` first seams that would be removed from the image,
+/// or, with a random generator, `count` seams drawn one by one
+/// among the given number of next seams that would be removed
+fn insert_seams<P: Pixel + 'static>(
+    img: &PixelBuffer<P>,
+    count: u32,
+    blend: InsertionBlend,
+    sampling: Option<&mut (Random, u32)>,
+) -> PixelBuffer<P> {
+    let (width, height) = img.dimensions();
+    let mut duplicated = Matrix::from_fn(Pos(width, height), |_x, _y| false);
+    let candidates = sampling.as_ref().map_or(1, |(_rng, n)| *n);
+    let available = (count + candidates - 1).min(width);
+    let mut seams = Carvable::new(img)
+        .peek_seams(available)
+        .expect("no pixel is forbidden");
+    if let Some((rng, candidates)) = sampling {
+        // The drawn seam is replaced by the next one in the order of removal
+        for i in 0..count as usize {
+            let pool = (*candidates as usize).min(seams.len() - i);
+            let drawn = i + rng.below(pool);
+            seams[i..=drawn].rotate_right(1);
+        }
+    }
+    seams.truncate(count as usize);
+    for &pos in seams.iter().flat_map(|seam| seam.positions()) {
+        duplicated[pos] = true;
+    }
+
+    let new_width = width + count;
+    let mut inserted = Matrix::from_fn(Pos(new_width, height), |_x, _y| false);
+    let mut output = ImageBuffer::new(new_width, height);
+    for y in 0..height {
+        let mut out_x = 0;
+        for x in 0..width {
+            let pixel = *img.get_pixel(x, y);
+            output.put_pixel(out_x, y, pixel);
+            out_x += 1;
+            if duplicated[Pos(x, y)] {
+                let right = *img.get_pixel((x + 1).min(width - 1), y);
+                output.put_pixel(out_x, y, average(pixel, right));
+                inserted[Pos(out_x, y)] = true;
+                out_x += 1;
+            }
+        }
+    }
+    if blend == InsertionBlend::Gradient {
+        poisson_blend(&mut output, &inserted);
+    }
+    output
+}
+
+/// A small pseudo-random generator (splitmix64),
+/// so that the images drawn from a seed are the same on every platform
+pub(crate) struct Random(pub(crate) u64);
+
+impl Random {
+    pub(crate) fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// A number between 0 included and `n` excluded
+    pub(crate) fn below(&mut self, n: usize) -> usize {
+        (self.next() % n as u64) as usize
+    }
+}
+
+fn average<P: Pixel>(a: P, b: P) -> P {
+    a.map2(&b, |a, b| {
+        let mean = (a.to_f64().unwrap_or(0.) + b.to_f64().unwrap_or(0.)) / 2.;
+        NumCast::from(mean.round()).unwrap_or(a)
+    })
+}
+
+/// Solves the Poisson equation on the inserted pixels with Gauss-Seidel iterations.
+/// The guidance field is made of the vertical gradients of the initial image,
+/// and the pixels that were not inserted are fixed boundary values.
+fn poisson_blend<P: Pixel + 'static>(img: &mut PixelBuffer<P>, inserted: &Matrix<bool>) {
+    let (width, height) = img.dimensions();
+    let channels = P::CHANNEL_COUNT as usize;
+    let index =
+        |Pos(x, y): Pos, c: usize| (y as usize * width as usize + x as usize) * channels + c;
+    let guide: Vec<f64> = img
+        .pixels()
+        .flat_map(|p| p.channels().iter().map(|c| c.to_f64().unwrap_or(0.)))
+        .collect();
+    let mut values = guide.clone();
+    let positions: Vec<Pos> = Pos::iter_in_rect(Pos(0, 0), Pos(width, height))
+        .filter(|&p| inserted[p])
+        .collect();
+
+    for _ in 0..BLEND_ITERATIONS {
+        for &pos in &positions {
+            let Pos(x, y) = pos;
+            let horizontal = [x.checked_sub(1), Some(x + 1).filter(|&x| x < width)];
+            let vertical = [y.checked_sub(1), Some(y + 1).filter(|&y| y < height)];
+            for c in 0..channels {
+                let mut sum = 0.;
+                let mut n = 0.;
+                for q in horizontal.iter().flatten().map(|&x| Pos(x, y)) {
+                    sum += values[index(q, c)];
+                    n += 1.;
+                }
+                for q in vertical.iter().flatten().map(|&y| Pos(x, y)) {
+                    sum += values[index(q, c)] + guide[index(pos, c)] - guide[index(q, c)];
+                    n += 1.;
+                }
+                if n > 0. {
+                    values[index(pos, c)] = sum / n;
+                }
+            }
+        }
+    }
+
+    let max = <P::Subpixel as Bounded>::max_value()
+        .to_f64()
+        .unwrap_or(f64::MAX);
+    let min = <P::Subpixel as Bounded>::min_value()
+        .to_f64()
+        .unwrap_or(f64::MIN);
+    for &pos in &positions {
+        let pixel = img.get_pixel_mut(pos.0, pos.1);
+        for (c, channel) in pixel.channels_mut().iter_mut().enumerate() {
+            let value = values[index(pos, c)].round().max(min).min(max);
+            *channel = NumCast::from(value).unwrap_or(*channel);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use image::GrayImage;
+
+    use super::*;
+
+    #[test]
+    fn inserts_averaged_seams() {
+        let img = GrayImage::from_raw(4, 2, vec![0, 10, 20, 200, 0, 10, 20, 200]).unwrap();
+        let enlarged = enlarge(&img, 5, 2);
+        // The cheapest seam is the one on the left, in the flat part of the image
+        assert_eq!(
+            enlarged.into_raw(),
+            vec![0, 5, 10, 20, 200, 0, 5, 10, 20, 200]
+        );
+    }
+
+    #[test]
+    fn samples_among_the_cheapest_seams() {
+        let img = GrayImage::from_fn(24, 6, |x, y| image::Luma([(x * x + y * 3) as u8]));
+        let sampled = |seed, candidates| {
+            let sampling = SeamSampling { seed, candidates };
+            enlarge_with_sampling(&img, 30, 6, InsertionBlend::Average, sampling)
+        };
+        assert_eq!(sampled(1, 1), enlarge(&img, 30, 6));
+        assert_eq!(sampled(1, 8), sampled(1, 8));
+        assert_ne!(sampled(1, 8), enlarge(&img, 30, 6));
+        assert_ne!(sampled(1, 8), sampled(2, 8));
+        // More candidates than seams, and enlargements in several steps
+        let wide = GrayImage::from_fn(4, 2, |x, _y| image::Luma([x as u8 * 60]));
+        let sampling = SeamSampling {
+            seed: 3,
+            candidates: 100,
+        };
+        let enlarged = enlarge_with_sampling(&wide, 13, 5, InsertionBlend::Average, sampling);
+        assert_eq!(enlarged.dimensions(), (13, 5));
+    }
+
+    #[test]
+    fn enlarges_in_steps() {
+        let img = GrayImage::from_fn(8, 4, |x, y| image::Luma([(x * x * 3 + y) as u8]));
+        let blend = InsertionBlend::Average;
+        // At most a quarter of the width is inserted at once
+        let mut steps = image_view_to_buffer(&img);
+        for width in [10, 13, 16] {
+            steps = insert_seams(&steps, width - steps.width(), blend, None);
+        }
+        assert_eq!(enlarge(&img, 16, 4), steps);
+        // Inserting all the seams at once duplicates every column
+        let at_once = insert_seams(&image_view_to_buffer(&img), 8, blend, None);
+        assert_ne!(at_once, steps);
+        assert_eq!(enlarge(&img, 9, 4).width(), 9);
+        // Steps of two seams, and the whole width, at most
+        let mut steps = image_view_to_buffer(&img);
+        for width in [10, 12, 14, 16] {
+            steps = insert_seams(&steps, width - steps.width(), blend, None);
+        }
+        let two = enlarge_with_max_step(&img, 16, 4, blend, Dimension::Pixels(2));
+        assert_eq!(two, steps);
+        let whole = enlarge_with_max_step(&img, 16, 4, blend, Dimension::Percent(500));
+        assert_eq!(whole, at_once);
+        let zero = enlarge_with_max_step(&img, 11, 4, blend, Dimension::Pixels(0));
+        assert_eq!(zero.width(), 11);
+    }
+
+    #[test]
+    fn gradient_blend_smooths_ramps_like_averaging() {
+        // A ramp is already as smooth as possible
+        let ramp = GrayImage::from_fn(8, 3, |x, _y| image::Luma([(x * 20) as u8]));
+        let blended = enlarge_with_blend(&ramp, 11, 3, InsertionBlend::Gradient);
+        assert_eq!(blended.dimensions(), (11, 3));
+        assert_eq!(
+            blended,
+            enlarge_with_blend(&ramp, 11, 3, InsertionBlend::Average)
+        );
+    }
+}
@@ -0,0 +1,348 @@
+use core::fmt::{Display, Formatter};
+
+#[cfg(feature = "image")]
+use crate::cache::Axis;
+
+/// The reason why a seam could not be removed from an image
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+#[non_exhaustive]
+pub enum SeamError {
+    /// One of the required positions is outside of the image
+    OutOfBounds { x: u32, y: u32 },
+    /// No single seam can go through all the required positions
+    UnreachableAnchors,
+    /// Every remaining seam goes through a forbidden pixel
+    Blocked,
+    /// The image has no column left to remove
+    Empty,
+    /// The seam finder was asked for a seam before computing all the energies it depends on.
+    /// This is a bug in this crate, not in the calling code.
+    Unfilled,
+    /// The image is larger than the memory allocated for it,
+    /// see [SeamFinder::with_capacity](crate::SeamFinder::with_capacity)
+    CapacityExceeded,
+    /// The number of pixels of a buffer does not match the dimensions of the image
+    SizeMismatch,
+    /// The number of channels of an array does not match the one of the pixel type
+    ChannelMismatch,
+    /// The image cannot be resized within the memory it was given,
+    /// even in stripes of a single row, see [Carver::max_memory](crate::Carver::max_memory)
+    MemoryExceeded,
+    /// A seam does not have exactly one position on this row, see [apply_seam](crate::apply_seam)
+    InvalidSeam { y: u32 },
+}
+
+impl Display for SeamError {
+    fn fmt(&self, f: &mut Formatter) -> core::fmt::Result {
+        match self {
+            SeamError::OutOfBounds { x, y } => {
+                write!(f, "position ({}, {}) is outside of the image", x, y)
+            }
+            SeamError::UnreachableAnchors => {
+                write!(f, "no seam can go through all the anchor points")
+            }
+            SeamError::Blocked => write!(f, "every seam goes through a forbidden pixel"),
+            SeamError::Empty => write!(f, "the image has no column left"),
+            SeamError::Unfilled => {
+                write!(f, "the seam finder has positions that were not computed")
+            }
+            SeamError::CapacityExceeded => {
+                write!(f, "the image is larger than the capacity of the seam finder")
+            }
+            SeamError::SizeMismatch => {
+                write!(f, "the number of pixels does not match the dimensions of the image")
+            }
+            SeamError::ChannelMismatch => {
+                write!(f, "the number of channels does not match the pixel type")
+            }
+            SeamError::MemoryExceeded => {
+                write!(f, "the image cannot be resized within the memory limit")
+            }
+            SeamError::InvalidSeam { y } => {
+                write!(f, "the seam does not have exactly one position on row {}", y)
+            }
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for SeamError {}
+
+/// The reason why the settings of a carver cannot work together,
+/// returned by [CarverBuilder::build](crate::CarverBuilder::build)
+#[cfg(feature = "image")]
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+#[non_exhaustive]
+pub enum ConfigError {
+    /// A target dimension is larger than the original one:
+    /// carvers only shrink images, see [enlarge](crate::enlarge)
+    Enlargement,
+    /// A target dimension is negative or not a number
+    InvalidDimension,
+    /// The importance map and the validity mask have different dimensions
+    MaskSizeMismatch,
+    /// The limit on local removals, or an empty [carved region](crate::Carver::carve_region),
+    /// does not let any pixel be removed
+    NothingRemovable,
+    /// Limits on local removals are not kept by the greedy axis order
+    GreedyLocalRemoval,
+    /// Seams cannot wrap around borders they must stay away from
+    WrappingKeptBorders,
+}
+
+#[cfg(feature = "image")]
+impl Display for ConfigError {
+    fn fmt(&self, f: &mut Formatter) -> core::fmt::Result {
+        match self {
+            ConfigError::Enlargement => write!(f, "carvers cannot make images larger"),
+            ConfigError::InvalidDimension => write!(f, "a target dimension is not valid"),
+            ConfigError::MaskSizeMismatch => {
+                write!(f, "the importance map and the validity mask have different sizes")
+            }
+            ConfigError::NothingRemovable => {
+                let reason = "the limit on local removals or the carved region";
+                write!(f, "{} forbids removing any pixel", reason)
+            }
+            ConfigError::GreedyLocalRemoval => {
+                write!(f, "limits on local removals cannot be kept with the greedy axis order")
+            }
+            ConfigError::WrappingKeptBorders => {
+                write!(f, "seams cannot wrap around borders that are kept")
+            }
+        }
+    }
+}
+
+#[cfg(feature = "image")]
+impl std::error::Error for ConfigError {}
+
+/// The reason why masks and importance maps cannot be used to carve an image,
+/// returned by [validate_inputs](crate::validate_inputs)
+#[cfg(feature = "image")]
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+#[non_exhaustive]
+pub enum ValidationError {
+    /// The mask at this index of the list of masks does not have the dimensions of the image
+    MaskSizeMismatch { index: usize },
+    /// The importance map at this index of the list of weights
+    /// does not have the dimensions of the image
+    WeightsSizeMismatch { index: usize },
+    /// The mask at this index has a pixel that is neither black nor white,
+    /// such as the ones of anti-aliased edges or of lossy compression.
+    /// Any pixel that is not black counts as part of the mask.
+    GrayMask { index: usize, x: u32, y: u32 },
+    /// Fewer pixels of this row, for vertical seams, or of this column, for horizontal seams,
+    /// can be removed than the number of seams to remove, because the others are forbidden
+    Blocked { axis: Axis, line: u32 },
+    /// Seams have to go through protected pixels of this row or column,
+    /// because too few of its pixels are neither protected nor forbidden
+    Protected { axis: Axis, line: u32 },
+}
+
+#[cfg(feature = "image")]
+impl Display for ValidationError {
+    fn fmt(&self, f: &mut Formatter) -> core::fmt::Result {
+        let line = |axis: &Axis| match axis {
+            Axis::Vertical => "row",
+            Axis::Horizontal => "column",
+        };
+        match self {
+            ValidationError::MaskSizeMismatch { index } => {
+                write!(f, "mask {} does not have the dimensions of the image", index)
+            }
+            ValidationError::WeightsSizeMismatch { index } => {
+                write!(f, "importance map {} does not have the dimensions of the image", index)
+            }
+            ValidationError::GrayMask { index, x, y } => {
+                write!(f, "mask {} is neither black nor white at ({}, {})", index, x, y)
+            }
+            ValidationError::Blocked { axis, line: l } => {
+                write!(f, "too many pixels of {} {} are forbidden", line(axis), l)
+            }
+            ValidationError::Protected { axis, line: l } => {
+                write!(f, "seams have to go through protected pixels of {} {}", line(axis), l)
+            }
+        }
+    }
+}
+
+#[cfg(feature = "image")]
+impl std::error::Error for ValidationError {}
+
+/// The reason why an [EnergyRegistry](crate::EnergyRegistry) could not create an energy stage
+#[cfg(feature = "image")]
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+#[non_exhaustive]
+pub enum RegistryError {
+    /// No energy is registered under this name
+    UnknownEnergy { name: String },
+    /// The energy does not take this parameter
+    UnknownParameter { energy: String, parameter: String },
+    /// The value of the parameter cannot be parsed, or is out of its range
+    InvalidParameter { parameter: String, value: String },
+    /// The text is neither an energy name, nor a name followed by parameters in parentheses,
+    /// nor a `name=value` parameter
+    Malformed { text: String },
+}
+
+#[cfg(feature = "image")]
+impl Display for RegistryError {
+    fn fmt(&self, f: &mut Formatter) -> core::fmt::Result {
+        match self {
+            RegistryError::UnknownEnergy { name } => write!(f, "unknown energy '{}'", name),
+            RegistryError::UnknownParameter { energy, parameter } => {
+                write!(f, "the energy '{}' has no parameter '{}'", energy, parameter)
+            }
+            RegistryError::InvalidParameter { parameter, value } => {
+                write!(f, "invalid value '{}' for the parameter '{}'", value, parameter)
+            }
+            RegistryError::Malformed { text } => write!(f, "cannot parse '{}'", text),
+        }
+    }
+}
+
+#[cfg(feature = "image")]
+impl std::error::Error for RegistryError {}
+
+/// The reason why a resize saving a [Checkpoint](crate::Checkpoint) failed,
+/// see [Carver::resize_checkpointed](crate::Carver::resize_checkpointed)
+#[cfg(feature = "image")]
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum CheckpointError {
+    /// The checkpoint could not be read or written
+    Io(std::io::Error),
+    /// The seams could not be removed from the image
+    Seam(SeamError),
+    /// The carver has a setting that depends on the positions in the original image,
+    /// that a resumed resize cannot follow
+    Unsupported { setting: &'static str },
+}
+
+#[cfg(feature = "image")]
+impl Display for CheckpointError {
+    fn fmt(&self, f: &mut Formatter) -> core::fmt::Result {
+        match self {
+            CheckpointError::Io(error) => write!(f, "cannot use the checkpoint: {}", error),
+            CheckpointError::Seam(error) => error.fmt(f),
+            CheckpointError::Unsupported { setting } => {
+                write!(f, "resizes with a {} cannot be checkpointed", setting)
+            }
+        }
+    }
+}
+
+#[cfg(feature = "image")]
+impl std::error::Error for CheckpointError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            CheckpointError::Io(error) => Some(error),
+            CheckpointError::Seam(error) => Some(error),
+            CheckpointError::Unsupported { .. } => None,
+        }
+    }
+}
+
+#[cfg(feature = "image")]
+impl From<std::io::Error> for CheckpointError {
+    fn from(error: std::io::Error) -> Self {
+        CheckpointError::Io(error)
+    }
+}
+
+#[cfg(feature = "image")]
+impl From<SeamError> for CheckpointError {
+    fn from(error: SeamError) -> Self {
+        CheckpointError::Seam(error)
+    }
+}
+
+/// The reason why a PNG image could not be resized by [resize_png](crate::resize_png)
+#[cfg(feature = "png-stream")]
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum DecodeError {
+    /// The image could not be read or is not a valid PNG image
+    Png(png::DecodingError),
+    /// The seams could not be removed from the decoded image
+    Seam(SeamError),
+}
+
+#[cfg(feature = "png-stream")]
+impl Display for DecodeError {
+    fn fmt(&self, f: &mut Formatter) -> core::fmt::Result {
+        match self {
+            DecodeError::Png(error) => write!(f, "cannot decode the image: {}", error),
+            DecodeError::Seam(error) => error.fmt(f),
+        }
+    }
+}
+
+#[cfg(feature = "png-stream")]
+impl std::error::Error for DecodeError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            DecodeError::Png(error) => Some(error),
+            DecodeError::Seam(error) => Some(error),
+        }
+    }
+}
+
+#[cfg(feature = "png-stream")]
+impl From<png::DecodingError> for DecodeError {
+    fn from(error: png::DecodingError) -> Self {
+        DecodeError::Png(error)
+    }
+}
+
+#[cfg(feature = "png-stream")]
+impl From<SeamError> for DecodeError {
+    fn from(error: SeamError) -> Self {
+        DecodeError::Seam(error)
+    }
+}
+
+/// The reason why no [Gpu](crate::Gpu) could be created
+#[cfg(feature = "gpu")]
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum GpuError {
+    /// The host has no graphics card, and no software renderer either
+    Adapter(wgpu::RequestAdapterError),
+    /// The graphics card could not be opened
+    Device(wgpu::RequestDeviceError),
+}
+
+#[cfg(feature = "gpu")]
+impl Display for GpuError {
+    fn fmt(&self, f: &mut Formatter) -> core::fmt::Result {
+        match self {
+            GpuError::Adapter(error) => write!(f, "no graphics card found: {}", error),
+            GpuError::Device(error) => write!(f, "cannot open the graphics card: {}", error),
+        }
+    }
+}
+
+#[cfg(feature = "gpu")]
+impl std::error::Error for GpuError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            GpuError::Adapter(error) => Some(error),
+            GpuError::Device(error) => Some(error),
+        }
+    }
+}
+
+#[cfg(feature = "gpu")]
+impl From<wgpu::RequestAdapterError> for GpuError {
+    fn from(error: wgpu::RequestAdapterError) -> Self {
+        GpuError::Adapter(error)
+    }
+}
+
+#[cfg(feature = "gpu")]
+impl From<wgpu::RequestDeviceError> for GpuError {
+    fn from(error: wgpu::RequestDeviceError) -> Self {
+        GpuError::Device(error)
+    }
+}
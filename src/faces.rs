@@ -0,0 +1,149 @@
+//! Protection of human faces, using the [rustface](https://crates.io/crates/rustface) detector.
+//!
+//! This module is only available when the `rustface` feature is enabled.
+//! Rustface needs a model file to work, you can get one from
+//! [the rustface repository](https://github.com/atomashpolskiy/rustface/tree/master/model).
+//!
+//! ```no_run
+//! let img = image::open("./portrait.jpg").unwrap();
+//! let mut detector = rustface::create_detector("./seeta_fd_frontal_v1.0.bin").unwrap();
+//! let resized = seamcarving::faces::resize_protecting_faces(&img, 100, 100, &mut *detector);
+//! resized.save("./resized.jpg");
+//! ```
+use image::{GenericImageView, ImageBuffer, Pixel};
+use rustface::{Detector, ImageData};
+
+use crate::mask::{rects_bias, Rect};
+use crate::{max_pos, resize_with_importance, ImportanceMap};
+
+/// Returns the bounding boxes of all the faces found in the image
+pub fn detect_faces<IMG>(img: &IMG, detector: &mut dyn Detector) -> Vec<Rect>
+where
+    IMG: GenericImageView,
+    IMG::Pixel: Pixel<Subpixel = u8>,
+{
+    let (width, height) = img.dimensions();
+    if width == 0 || height == 0 {
+        // rustface panics on empty images
+        return vec![];
+    }
+    let gray: Vec<u8> = (0..height)
+        .flat_map(|y| (0..width).map(move |x| img.get_pixel(x, y).to_luma().0[0]))
+        .collect();
+    detector
+        .detect(&ImageData::new(&gray, width, height))
+        .iter()
+        .map(|face| {
+            // Detected faces can be partially outside of the image
+            let b = face.bbox();
+            let clip = |v: i64, max: u32| v.max(0).min(i64::from(max)) as u32;
+            let (x, y) = (clip(b.x().into(), width), clip(b.y().into(), height));
+            let right = clip(i64::from(b.x()) + i64::from(b.width()), width);
+            let bottom = clip(i64::from(b.y()) + i64::from(b.height()), height);
+            Rect {
+                x,
+                y,
+                width: right - x,
+                height: bottom - y,
+            }
+        })
+        .collect()
+}
+
+/// Protects the faces found in the image, and a margin around them
+pub fn protect_faces<IMG>(img: &IMG, detector: &mut dyn Detector) -> ImportanceMap
+where
+    IMG: GenericImageView,
+    IMG::Pixel: Pixel<Subpixel = u8>,
+{
+    let faces: Vec<Rect> = detect_faces(img, detector)
+        .into_iter()
+        // Protect the hair and the chin too, not only the detected face
+        .map(|face| face.grow(face.width.max(face.height) / 4, img.dimensions()))
+        .collect();
+    let size = max_pos(img);
+    ImportanceMap::from_bias(size, rects_bias(size, &faces))
+}
+
+/// Resizes an image like [resize](crate::resize),
+/// but protects the faces found in the image.
+pub fn resize_protecting_faces<IMG>(
+    img: &IMG,
+    width: u32,
+    height: u32,
+    detector: &mut dyn Detector,
+) -> ImageBuffer<IMG::Pixel, Vec<<<IMG as GenericImageView>::Pixel as Pixel>::Subpixel>>
+where
+    IMG: GenericImageView,
+    IMG::Pixel: Pixel<Subpixel = u8> + 'static,
+{
+    resize_with_importance(img, width, height, &protect_faces(img, detector))
+        .expect("no pixel is forbidden")
+}
+
+#[cfg(test)]
+mod tests {
+    use image::{GrayImage, Luma};
+    use rustface::{FaceInfo, Rectangle};
+
+    use super::*;
+
+    /// Finds the same faces in every image, instead of loading a model
+    struct FixedFaces(Vec<Rectangle>);
+
+    impl Detector for FixedFaces {
+        fn detect(&mut self, _: &ImageData) -> Vec<FaceInfo> {
+            let face = |&bbox: &Rectangle| {
+                let mut face = FaceInfo::new();
+                *face.bbox_mut() = bbox;
+                face
+            };
+            self.0.iter().map(face).collect()
+        }
+        fn set_window_size(&mut self, _: u32) {}
+        fn set_slide_window_step(&mut self, _: u32, _: u32) {}
+        fn set_min_face_size(&mut self, _: u32) {}
+        fn set_max_face_size(&mut self, _: u32) {}
+        fn set_pyramid_scale_factor(&mut self, _: f32) {}
+        fn set_score_thresh(&mut self, _: f64) {}
+    }
+
+    /// A flat face in the middle of a noisy background, cheaper to carve than anything else
+    fn portrait() -> GrayImage {
+        GrayImage::from_fn(30, 10, |x, y| match x {
+            12..=17 => Luma([100]),
+            _ => Luma([((x * 37 + y * 91) % 256) as u8]),
+        })
+    }
+
+    /// Number of columns of the face still in the image
+    fn face_columns(img: &GrayImage) -> usize {
+        let (width, height) = img.dimensions();
+        (0..width)
+            .filter(|&x| (0..height).all(|y| img[(x, y)] == Luma([100])))
+            .count()
+    }
+
+    #[test]
+    fn clips_faces_to_the_image() {
+        let mut detector = FixedFaces(vec![Rectangle::new(-4, 2, 10, 30)]);
+        let expected = Rect {
+            x: 0,
+            y: 2,
+            width: 6,
+            height: 8,
+        };
+        assert_eq!(detect_faces(&portrait(), &mut detector), vec![expected]);
+        assert_eq!(detect_faces(&GrayImage::new(0, 10), &mut detector), vec![]);
+    }
+
+    #[test]
+    fn keeps_the_faces() {
+        let img = portrait();
+        assert!(face_columns(&crate::resize(&img, 22, 10)) < 6);
+        let mut detector = FixedFaces(vec![Rectangle::new(12, 0, 6, 10)]);
+        let resized = resize_protecting_faces(&img, 22, 10, &mut detector);
+        assert_eq!(resized.dimensions(), (22, 10));
+        assert_eq!(face_columns(&resized), 6);
+    }
+}
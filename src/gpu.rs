@@ -0,0 +1,359 @@
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use std::sync::mpsc::{sync_channel, SyncSender};
+
+use crate::error::GpuError;
+use crate::matrix::Matrix;
+use crate::pos::Pos;
+use crate::seam_finder::SeamFinder;
+
+/// Number of pixels whose energy is computed by one dispatch: the rows of each band are sent
+/// to the CPU as soon as the band is done, while the graphics card computes the next one
+const BAND_PIXELS: u32 = 1 << 18;
+
+/// Number of bands computed ahead of the rows being cumulated
+const BANDS_AHEAD: usize = 2;
+
+/// Number of columns computed by each workgroup of the shader
+const WORKGROUP_WIDTH: u32 = 64;
+
+/// The gradient energy of [buffer::gradient](crate::buffer), on a band of rows.
+/// The channels are stored as `i32`, and the sums saturate like on the CPU.
+const SHADER: &str = r#"
+struct Band {
+    width: u32,
+    height: u32,
+    channels: u32,
+    // First row of the band, and first row of its pixels, which include the row above it
+    first_row: u32,
+    first_pixel_row: u32,
+    rows: u32,
+    _padding: vec2<u32>,
+}
+
+@group(0) @binding(0) var<uniform> band: Band;
+@group(0) @binding(1) var<storage, read> pixels: array<i32>;
+@group(0) @binding(2) var<storage, read_write> energies: array<u32>;
+
+fn saturating_add(a: u32, b: u32) -> u32 {
+    let sum = a + b;
+    return select(sum, 0xffffffffu, sum < a);
+}
+
+fn square_diff(a: vec2<u32>, b: vec2<u32>) -> u32 {
+    let first_a = ((a.y - band.first_pixel_row) * band.width + a.x) * band.channels;
+    let first_b = ((b.y - band.first_pixel_row) * band.width + b.x) * band.channels;
+    var sum = 0u;
+    for (var c = 0u; c < band.channels; c = c + 1u) {
+        let va = pixels[first_a + c];
+        let vb = pixels[first_b + c];
+        // The wrapping difference of the bits is the exact distance of the values
+        let diff = select(u32(vb) - u32(va), u32(va) - u32(vb), va >= vb);
+        sum = saturating_add(sum, select(0xffffffffu, diff * diff, diff <= 0xffffu));
+    }
+    return sum;
+}
+
+@compute @workgroup_size(64)
+fn main(@builtin(global_invocation_id) id: vec3<u32>) {
+    let x = id.x;
+    let row = id.y;
+    if (x >= band.width || row >= band.rows) {
+        return;
+    }
+    let y = band.first_row + row;
+    let top = vec2<u32>(x, select(y - 1u, 0u, y == 0u));
+    let bottom = vec2<u32>(x, min(y + 1u, band.height - 1u));
+    let left = vec2<u32>(select(x - 1u, 0u, x == 0u), y);
+    let right = vec2<u32>(min(x + 1u, band.width - 1u), y);
+    let energy = saturating_add(square_diff(top, bottom), square_diff(left, right));
+    // The largest energy is reserved for forbidden positions
+    energies[row * band.width + x] = min(energy, 0xfffffffeu);
+}
+"#;
+
+/// A graphics card computing the energy of images, see [Backend::Gpu](crate::Backend::Gpu).
+///
+/// Creating it is slow, so it is meant to be kept and cloned for all the resizes:
+/// its clones share the same device.
+///
+/// ```no_run
+/// use seamcarving::{Backend, Gpu};
+/// let pixels = vec![[0u8, 128, 255]; 400 * 20];
+/// let backend = Backend::Gpu(Gpu::new().unwrap());
+/// let resized = seamcarving::resize_buffer_with_backend(&pixels, (400, 20), (390, 20), &backend);
+/// assert_eq!(resized.unwrap().len(), 390 * 20);
+/// ```
+#[derive(Clone, Debug)]
+pub struct Gpu {
+    context: Arc<Context>,
+}
+
+#[derive(Debug)]
+struct Context {
+    name: String,
+    device: wgpu::Device,
+    queue: wgpu::Queue,
+    pipeline: wgpu::ComputePipeline,
+}
+
+impl Gpu {
+    /// The most powerful graphics card of the host, through Vulkan, Metal, DirectX 12 or OpenGL,
+    /// or a software renderer such as llvmpipe when the host has no graphics card.
+    /// The backends can be chosen with the `WGPU_BACKEND` environment variable.
+    pub fn new() -> Result<Gpu, GpuError> {
+        let instance = wgpu::Instance::new(&wgpu::InstanceDescriptor::from_env_or_default());
+        let options = wgpu::RequestAdapterOptions {
+            power_preference: wgpu::PowerPreference::HighPerformance,
+            ..Default::default()
+        };
+        let adapter = pollster::block_on(instance.request_adapter(&options))?;
+        let descriptor = wgpu::DeviceDescriptor {
+            label: Some("seamcarving"),
+            // The largest images the graphics card can hold
+            required_limits: adapter.limits(),
+            ..Default::default()
+        };
+        let (device, queue) = pollster::block_on(adapter.request_device(&descriptor))?;
+        let module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("energy"),
+            source: wgpu::ShaderSource::Wgsl(SHADER.into()),
+        });
+        let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: Some("energy"),
+            layout: None,
+            module: &module,
+            entry_point: Some("main"),
+            compilation_options: Default::default(),
+            cache: None,
+        });
+        let name = adapter.get_info().name;
+        let context = Context {
+            name,
+            device,
+            queue,
+            pipeline,
+        };
+        Ok(Gpu {
+            context: Arc::new(context),
+        })
+    }
+
+    /// Name of the graphics card, or of the software renderer
+    pub fn name(&self) -> &str {
+        &self.context.name
+    }
+
+    /// Fills the table of the finder from the energies of the pixels computed on the graphics
+    /// card, cumulating each band of rows on the calling thread while the next ones are computed.
+    ///
+    /// If the graphics card cannot compute them, for instance because the image is too large
+    /// for it, the rows it did not send are left to the energy function of the finder.
+    pub(crate) fn fill<T: Copy + Into<i32> + Send + Sync, const N: usize>(
+        &self,
+        finder: &mut SeamFinder,
+        pixels: &Matrix<[T; N]>,
+    ) {
+        let (width, height) = (pixels.width(), pixels.height());
+        let band_rows = (BAND_PIXELS / width.max(1)).clamp(1, height.max(1));
+        let (rows, received) = sync_channel(band_rows as usize * BANDS_AHEAD);
+        std::thread::scope(|scope| {
+            scope.spawn(|| self.send_energy_rows(pixels, band_rows, rows));
+            // Stops at the first missing row, computed again on the CPU
+            let _ = finder.fill_rows(received);
+        });
+    }
+
+    /// Computes the gradient energies of the pixels, band by band,
+    /// and sends them as soon as each band is done, until the receiver hangs up.
+    /// Returns None if the graphics card cannot compute them.
+    fn send_energy_rows<T: Copy + Into<i32>, const N: usize>(
+        &self,
+        pixels: &Matrix<[T; N]>,
+        band_rows: u32,
+        rows: SyncSender<Vec<u32>>,
+    ) -> Option<()> {
+        let Context {
+            device,
+            queue,
+            pipeline,
+            ..
+        } = &*self.context;
+        let size = Pos(pixels.width(), pixels.height());
+        if size.area() == 0 {
+            return Some(());
+        }
+        let limits = device.limits();
+        let row_bytes = u64::from(size.0) * 4;
+        // The pixels of a band include the rows above and below it
+        let pixel_bytes = row_bytes * (u64::from(band_rows) + 2) * N as u64;
+        let energy_bytes = row_bytes * u64::from(band_rows);
+        let workgroups = size.0.div_ceil(WORKGROUP_WIDTH);
+        let dimensions = limits.max_compute_workgroups_per_dimension;
+        if pixel_bytes.max(energy_bytes) > u64::from(limits.max_storage_buffer_binding_size)
+            || workgroups > dimensions
+            || band_rows > dimensions
+            || N == 0
+        {
+            return None;
+        }
+        let buffer = |label, size, usage| {
+            device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some(label),
+                size,
+                usage,
+                mapped_at_creation: false,
+            })
+        };
+        use wgpu::BufferUsages as Usages;
+        let band = buffer("band", 32, Usages::UNIFORM | Usages::COPY_DST);
+        let input = buffer("pixels", pixel_bytes, Usages::STORAGE | Usages::COPY_DST);
+        let output = buffer("energies", energy_bytes, Usages::STORAGE | Usages::COPY_SRC);
+        let staging = buffer("staging", energy_bytes, Usages::MAP_READ | Usages::COPY_DST);
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("energy"),
+            layout: &pipeline.get_bind_group_layout(0),
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: band.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: input.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: output.as_entire_binding(),
+                },
+            ],
+        });
+        let mut bytes = Vec::new();
+        for first_row in (0..size.1).step_by(band_rows as usize) {
+            let rows_in_band = band_rows.min(size.1 - first_row);
+            let first_pixel_row = first_row.saturating_sub(1);
+            let last_pixel_row = (first_row + rows_in_band).min(size.1 - 1);
+            let header = [
+                size.0,
+                size.1,
+                N as u32,
+                first_row,
+                first_pixel_row,
+                rows_in_band,
+                0,
+                0,
+            ];
+            queue.write_buffer(&band, 0, &words_to_bytes(&header));
+            bytes.clear();
+            for y in first_pixel_row..=last_pixel_row {
+                for x in 0..size.0 {
+                    let channels: [i32; N] = pixels[Pos(x, y)].map(Into::into);
+                    bytes.extend(channels.iter().flat_map(|c| c.to_ne_bytes()));
+                }
+            }
+            queue.write_buffer(&input, 0, &bytes);
+            let band_bytes = row_bytes * u64::from(rows_in_band);
+            let mut encoder = device.create_command_encoder(&Default::default());
+            {
+                let mut pass = encoder.begin_compute_pass(&Default::default());
+                pass.set_pipeline(pipeline);
+                pass.set_bind_group(0, &bind_group, &[]);
+                pass.dispatch_workgroups(workgroups, rows_in_band, 1);
+            }
+            encoder.copy_buffer_to_buffer(&output, 0, &staging, 0, band_bytes);
+            queue.submit([encoder.finish()]);
+            let slice = staging.slice(..band_bytes);
+            let (mapped, is_mapped) = sync_channel(1);
+            slice.map_async(wgpu::MapMode::Read, move |result| {
+                let _ = mapped.send(result);
+            });
+            device.poll(wgpu::PollType::Wait).ok()?;
+            is_mapped.recv().ok()?.ok()?;
+            let energies: Vec<u32> = slice
+                .get_mapped_range()
+                .chunks_exact(4)
+                .map(|word| u32::from_ne_bytes([word[0], word[1], word[2], word[3]]))
+                .collect();
+            staging.unmap();
+            for row in energies.chunks_exact(size.0 as usize) {
+                // The finder hung up, after an error
+                rows.send(row.to_vec()).ok()?;
+            }
+        }
+        Some(())
+    }
+}
+
+fn words_to_bytes(words: &[u32]) -> Vec<u8> {
+    words.iter().flat_map(|w| w.to_ne_bytes()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::buffer::gradient;
+    use crate::{resize_buffer, resize_buffer_with_backend, Backend};
+
+    /// The graphics card of the host, or None to skip the tests on hosts without any,
+    /// not even a software renderer
+    fn gpu() -> Option<Gpu> {
+        Gpu::new()
+            .map_err(|error| eprintln!("skipped, no graphics card: {}", error))
+            .ok()
+    }
+
+    /// The energies computed on the graphics card, row by row
+    fn energy_rows<T: Copy + Into<i32>, const N: usize>(
+        gpu: &Gpu,
+        pixels: &Matrix<[T; N]>,
+        band_rows: u32,
+    ) -> Vec<Vec<u32>> {
+        let (rows, received) = sync_channel(pixels.height() as usize);
+        assert_eq!(gpu.send_energy_rows(pixels, band_rows, rows), Some(()));
+        received.iter().collect()
+    }
+
+    fn cpu_energy_rows<T: Copy + Into<i32>, const N: usize>(
+        pixels: &Matrix<[T; N]>,
+    ) -> Vec<Vec<u32>> {
+        let size = Pos(pixels.width(), pixels.height());
+        let energy = |x, y| gradient(Pos(x, y).surrounding(size).map(|p| pixels[p]));
+        (0..size.1)
+            .map(|y| (0..size.0).map(|x| energy(x, y)).collect())
+            .collect()
+    }
+
+    #[test]
+    fn computes_the_energies_of_the_cpu() {
+        let Some(gpu) = gpu() else { return };
+        let rgba = Matrix::from_fn(Pos(70, 9), |x, y| {
+            [(x * x) as u8, (y * 13) as u8, ((x + y) * 7) as u8, 255]
+        });
+        for band_rows in [1, 4, 9] {
+            assert_eq!(energy_rows(&gpu, &rgba, band_rows), cpu_energy_rows(&rgba));
+        }
+        // Differences too large for their squares, which saturate
+        let extremes = Matrix::from_fn(Pos(5, 3), |x, y| match (x + y) % 3 {
+            0 => [-1_000_000, 0],
+            1 => [1_000_000, 70_000],
+            _ => [0, -70_000],
+        });
+        assert_eq!(energy_rows(&gpu, &extremes, 2), cpu_energy_rows(&extremes));
+        let column = Matrix::from_fn(Pos(1, 4), |_, y| [y as u16 * 1000]);
+        assert_eq!(energy_rows(&gpu, &column, 3), cpu_energy_rows(&column));
+    }
+
+    #[test]
+    fn resizes_like_the_cpu() {
+        let Some(gpu) = gpu() else { return };
+        let (width, height) = (600, 500);
+        let pixels: Vec<[u8; 3]> = (0..width * height)
+            .map(|i| [(i % width * 7) as u8, (i / width * 3) as u8, (i * 31 % 251) as u8])
+            .collect();
+        let backend = Backend::Gpu(gpu);
+        // Several bands, and both axes
+        let resized = resize_buffer_with_backend(&pixels, (width, height), (597, 498), &backend);
+        let expected = resize_buffer(&pixels, (width, height), (597, 498));
+        assert_eq!(resized, expected);
+    }
+}
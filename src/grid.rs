@@ -0,0 +1,105 @@
+use alloc::vec::Vec;
+
+use crate::matrix::Matrix;
+use crate::pos::Pos;
+use crate::seam_finder::SeamFinder;
+use crate::SeamError;
+
+/// Resizes any two-dimensional data, such as an elevation grid, a heatmap or a spectrogram,
+/// by removing the seams of cells with the lowest energy, given by `energy` from the data
+/// and the position of a cell in it. The vertical seams are removed first.
+///
+/// Energies are usually computed from the differences between a cell and its neighbours,
+/// so that seams go through the regions where the data is flat.
+/// Cells with a [FORBIDDEN](crate::FORBIDDEN) energy are avoided, and [SeamError::Blocked]
+/// is returned if a seam has to go through one of them.
+///
+/// ```
+/// use seamcarving::{resize_matrix, Matrix, Pos};
+/// let elevation = Matrix::from_fn(Pos(30, 20), |x, y| ((x * y) % 17) as f32 * 0.5);
+/// let resized = resize_matrix(&elevation, (24, 16), |grid, Pos(x, y)| {
+///     let right = Pos((x + 1).min(grid.width() - 1), y);
+///     let below = Pos(x, (y + 1).min(grid.height() - 1));
+///     let slope = (grid[right] - grid[Pos(x, y)]).abs() + (grid[below] - grid[Pos(x, y)]).abs();
+///     (slope * 100.) as u32
+/// })
+/// .unwrap();
+/// assert_eq!((resized.width(), resized.height()), (24, 16));
+/// ```
+pub fn resize_matrix<T: Clone>(
+    matrix: &Matrix<T>,
+    (width, height): (u32, u32),
+    mut energy: impl FnMut(&Matrix<T>, Pos) -> u32,
+) -> Result<Matrix<T>, SeamError> {
+    let mut grids = [matrix.clone()];
+    let size = dimensions(matrix);
+    let seams = (size.0.saturating_sub(width), size.1.saturating_sub(height));
+    let mut energy = |grids: &[Matrix<T>], pos| energy(&grids[0], pos);
+    carve(&mut grids, seams.0, false, &mut energy)?;
+    carve(&mut grids, seams.1, true, &mut energy)?;
+    let [resized] = grids;
+    Ok(resized)
+}
+
+pub(crate) fn dimensions<T>(grid: &Matrix<T>) -> Pos {
+    Pos(grid.width(), grid.height())
+}
+
+/// Removes `count` seams from grids of the same size, which are horizontal
+/// if `horizontal` is set, finding them with the energy of the positions of the grids
+pub(crate) fn carve<T>(
+    grids: &mut [Matrix<T>],
+    count: u32,
+    horizontal: bool,
+    energy: &mut impl FnMut(&[Matrix<T>], Pos) -> u32,
+) -> Result<(), SeamError> {
+    // Horizontal seams are found as the vertical seams of the transposed grids
+    let transpose = |Pos(x, y): Pos| if horizontal { Pos(y, x) } else { Pos(x, y) };
+    let Some(size) = grids.first().map(dimensions) else {
+        return Ok(());
+    };
+    let mut finder = SeamFinder::new(transpose(size));
+    for _ in 0..count {
+        let grids_ref = &*grids;
+        let (seam, _energy) =
+            finder.extract_seam_with_energy(|pos| energy(grids_ref, transpose(pos)))?;
+        for grid in grids.iter_mut() {
+            if horizontal {
+                let seam: Vec<Pos> = seam.iter().map(|&pos| transpose(pos)).collect();
+                grid.remove_horizontal_seam(&seam);
+            } else {
+                grid.remove_seam(&seam);
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::FORBIDDEN;
+
+    #[test]
+    fn carves_cells_with_the_lowest_energy() {
+        // Rows and columns of high values, in a grid of zeroes
+        let grid = Matrix::from_fn(Pos(12, 10), |x, y| match (x, y) {
+            (3, _) | (8, _) | (_, 6) => 9u8,
+            _ => 0,
+        });
+        let resized = resize_matrix(&grid, (6, 5), |grid, pos| u32::from(grid[pos])).unwrap();
+        let high = |pos: Pos| resized[pos] == 9;
+        let columns = (0..6).filter(|&x| (0..5).all(|y| high(Pos(x, y)))).count();
+        let rows = (0..5).filter(|&y| (0..6).all(|x| high(Pos(x, y)))).count();
+        assert_eq!((columns, rows), (2, 1));
+        // Only the cells of the fifth column can be removed
+        let forbidden = |grid: &Matrix<u8>, pos| match grid[pos] {
+            1 => 0,
+            _ => FORBIDDEN,
+        };
+        let grid = Matrix::from_fn(Pos(12, 10), |x, _y| u8::from(x == 5));
+        assert!(resize_matrix(&grid, (11, 10), forbidden).is_ok());
+        let blocked = resize_matrix(&grid, (10, 10), forbidden);
+        assert_eq!(blocked.err(), Some(SeamError::Blocked));
+    }
+}
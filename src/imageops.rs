@@ -0,0 +1,99 @@
+//! Functions shaped like the ones of [image::imageops], where seam carving is one more filter.
+//!
+//! Code built on the image crate can switch its imports to this module,
+//! and then choose [FilterType::ContentAware] wherever it resizes images:
+//!
+//! ```
+//! // use image::imageops::{resize, FilterType};
+//! use seamcarving::imageops::{resize, FilterType};
+//! let img = image::RgbImage::from_fn(40, 30, |x, y| image::Rgb([(x * y) as u8, 0, 0]));
+//! let smooth = resize(&img, 20, 30, FilterType::Lanczos3);
+//! let carved = resize(&img, 20, 30, FilterType::ContentAware);
+//! assert_eq!(smooth.dimensions(), carved.dimensions());
+//! ```
+use image::imageops;
+use image::{GenericImageView, ImageBuffer, Pixel};
+
+use crate::retarget;
+
+/// The filters of [image::imageops::FilterType], and seam carving
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum FilterType {
+    /// Nearest neighbour
+    Nearest,
+    /// Linear
+    Triangle,
+    /// Cubic
+    CatmullRom,
+    /// Gaussian
+    Gaussian,
+    /// Lanczos with a window of 3
+    Lanczos3,
+    /// Carves the seams the image can lose, and inserts seams where it must grow,
+    /// see [retarget](crate::retarget)
+    ContentAware,
+}
+
+impl From<imageops::FilterType> for FilterType {
+    fn from(filter: imageops::FilterType) -> Self {
+        match filter {
+            imageops::FilterType::Nearest => FilterType::Nearest,
+            imageops::FilterType::Triangle => FilterType::Triangle,
+            imageops::FilterType::CatmullRom => FilterType::CatmullRom,
+            imageops::FilterType::Gaussian => FilterType::Gaussian,
+            imageops::FilterType::Lanczos3 => FilterType::Lanczos3,
+        }
+    }
+}
+
+impl FilterType {
+    /// The filter of the image crate, or None for [ContentAware](FilterType::ContentAware)
+    pub fn to_image_filter(self) -> Option<imageops::FilterType> {
+        match self {
+            FilterType::Nearest => Some(imageops::FilterType::Nearest),
+            FilterType::Triangle => Some(imageops::FilterType::Triangle),
+            FilterType::CatmullRom => Some(imageops::FilterType::CatmullRom),
+            FilterType::Gaussian => Some(imageops::FilterType::Gaussian),
+            FilterType::Lanczos3 => Some(imageops::FilterType::Lanczos3),
+            FilterType::ContentAware => None,
+        }
+    }
+}
+
+/// Resizes an image to exactly the given dimensions with the given filter,
+/// like [image::imageops::resize], which it calls for the filters of the image crate.
+/// [ContentAware](FilterType::ContentAware) images are as large as with the other filters,
+/// but carved instead of scaled along the dimensions that shrink.
+pub fn resize<I: GenericImageView>(
+    image: &I,
+    nwidth: u32,
+    nheight: u32,
+    filter: FilterType,
+) -> ImageBuffer<I::Pixel, Vec<<I::Pixel as Pixel>::Subpixel>>
+where
+    I::Pixel: 'static,
+    <I::Pixel as Pixel>::Subpixel: 'static,
+{
+    match filter.to_image_filter() {
+        Some(filter) => imageops::resize(image, nwidth, nheight, filter),
+        None => retarget(image, nwidth, nheight),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use image::{GrayImage, Luma};
+
+    use super::*;
+
+    #[test]
+    fn resizes_like_the_image_crate() {
+        let img = GrayImage::from_fn(30, 20, |x, y| Luma([(x * x + y * 7) as u8]));
+        for filter in [imageops::FilterType::Nearest, imageops::FilterType::Lanczos3] {
+            let expected = imageops::resize(&img, 45, 12, filter);
+            assert_eq!(resize(&img, 45, 12, filter.into()), expected);
+        }
+        assert_eq!(resize(&img, 45, 12, FilterType::ContentAware), retarget(&img, 45, 12));
+        assert_eq!(FilterType::ContentAware.to_image_filter(), None);
+    }
+}
@@ -0,0 +1,280 @@
+use image::{GenericImageView, Luma, Pixel};
+use num_traits::ToPrimitive;
+
+use std::ops::Range;
+
+use crate::mask::{mask_bias, shapes_mask, Shape, PROTECTED_ENERGY};
+use crate::matrix::Matrix;
+use crate::max_pos;
+use crate::pos::Pos;
+use crate::seam_finder::FORBIDDEN;
+
+/// Additional energy given to each pixel of an image.
+/// Seams avoid the pixels that have a high importance.
+///
+/// An importance map can also contain forbidden pixels, created with
+/// [forbidden](ImportanceMap::forbidden), through which no seam may ever go.
+///
+/// Importance maps from different sources can be combined with [add](ImportanceMap::add),
+/// and then used with [resize_with_importance](crate::resize_with_importance)
+/// or [Carvable::add_importance](crate::Carvable::add_importance).
+///
+/// ```no_run
+/// use seamcarving::ImportanceMap;
+/// let img = image::open("./forest.jpg").unwrap();
+/// let mask = image::open("./mask.png").unwrap().to_luma();
+/// let mut importance = ImportanceMap::from_mask(&mask);
+/// importance.add(&ImportanceMap::textures(&img, 2, 1.0));
+/// let resized = seamcarving::resize_with_importance(&img, 100, 100, &importance);
+/// ```
+#[derive(Clone, Debug)]
+pub struct ImportanceMap {
+    size: Pos,
+    pub(crate) bias: Matrix<u32>,
+}
+
+impl ImportanceMap {
+    pub(crate) fn from_bias(size: Pos, bias: Matrix<u32>) -> Self {
+        ImportanceMap { size, bias }
+    }
+
+    /// Protects the pixels that are not black in the given mask
+    pub fn from_mask<M: GenericImageView<Pixel = Luma<u8>>>(mask: &M) -> Self {
+        ImportanceMap::from_bias(max_pos(mask), mask_bias(mask))
+    }
+
+    /// Forbids seams from going through the pixels that are not black in the given mask.
+    ///
+    /// Unlike [from_mask](ImportanceMap::from_mask), this is a hard constraint:
+    /// when every possible seam goes through a forbidden pixel,
+    /// carving fails with [SeamError::Blocked](crate::SeamError::Blocked).
+    pub fn forbidden<M: GenericImageView<Pixel = Luma<u8>>>(mask: &M) -> Self {
+        let bias = Matrix::from_fn(max_pos(mask), |x, y| {
+            if mask.get_pixel(x as u32, y as u32).0[0] == 0 {
+                0
+            } else {
+                FORBIDDEN
+            }
+        });
+        ImportanceMap::from_bias(max_pos(mask), bias)
+    }
+
+    /// Protects each pixel in proportion to its weight, between 0.0 and 1.0,
+    /// so that seams can prefer to avoid some pixels more than others,
+    /// a weight of 0.99 protecting about as much as [from_mask](ImportanceMap::from_mask).
+    ///
+    /// A weight of 1.0 (or more) forbids the pixel, like [forbidden](ImportanceMap::forbidden),
+    /// so that seams never go through it. Weights below 0.0, or NaN, do not protect anything.
+    ///
+    /// ```
+    /// use seamcarving::{ImportanceMap, Matrix, Pos};
+    /// // Prefer to keep the left of the image, and never touch its right edge
+    /// let weights = Matrix::from_fn(Pos(10, 5), |x, _y| {
+    ///     if x == 9 { 1. } else { 0.5 - x as f32 / 20. }
+    /// });
+    /// let importance = ImportanceMap::from_weights(&weights);
+    /// let img = image::RgbImage::new(10, 5);
+    /// let resized = seamcarving::resize_with_importance(&img, 6, 5, &importance).unwrap();
+    /// ```
+    pub fn from_weights(weights: &Matrix<f32>) -> Self {
+        let size = Pos(weights.width(), weights.height());
+        let bias = Matrix::from_fn(size, |x, y| weight_bias(weights[Pos(x as u32, y as u32)]));
+        ImportanceMap::from_bias(size, bias)
+    }
+
+    /// Protects the pixels of a grayscale mask like [from_weights](ImportanceMap::from_weights),
+    /// with weights going from 0.0 for black to 1.0 for white, which forbids the pixel.
+    pub fn from_soft_mask<M: GenericImageView<Pixel = Luma<u8>>>(mask: &M) -> Self {
+        let bias = Matrix::from_fn(max_pos(mask), |x, y| {
+            let Luma([value]) = mask.get_pixel(x as u32, y as u32);
+            weight_bias(f32::from(value) / 255.)
+        });
+        ImportanceMap::from_bias(max_pos(mask), bias)
+    }
+
+    /// Protects the pixels inside any of the shapes, in an image of the given size,
+    /// like [from_mask](ImportanceMap::from_mask) with [shapes_mask](crate::shapes_mask)
+    pub fn from_shapes(width: u32, height: u32, shapes: &[Shape]) -> Self {
+        ImportanceMap::from_mask(&shapes_mask(width, height, shapes))
+    }
+
+    /// Boosts the importance of richly textured regions, such as foliage or crowds,
+    /// which seam carving tends to melt when it has to go through them.
+    ///
+    /// The importance of each pixel is the variance of the luminosity
+    /// in a square of side `2 * radius + 1` centered on it, multiplied by `strength`.
+    pub fn textures<IMG: GenericImageView>(img: &IMG, radius: u32, strength: f32) -> Self {
+        let size = max_pos(img);
+        let luma = |x: u32, y: u32| {
+            let Luma([l]) = img.get_pixel(x, y).to_luma();
+            l.to_f64().unwrap_or(0.)
+        };
+        let sums = SummedArea::new(size, luma);
+        let bias = Matrix::from_fn(size, |x, y| {
+            let (x, y) = (x as u32, y as u32);
+            let start = Pos(x.saturating_sub(radius), y.saturating_sub(radius));
+            let end = Pos(
+                x.saturating_add(radius + 1).min(size.0),
+                y.saturating_add(radius + 1).min(size.1),
+            );
+            let variance = sums.variance(start, end);
+            (variance * f64::from(strength)).min(f64::from(FORBIDDEN - 1)) as u32
+        });
+        ImportanceMap::from_bias(size, bias)
+    }
+
+    /// A prior for photos without any saliency model: the importance of the pixels
+    /// of an image of the given size grows toward its center, so that seams remove
+    /// the periphery of the image first.
+    ///
+    /// The importance of a pixel is `strength * (1 - d).powf(falloff)`, where `d` goes from 0
+    /// at the center of the image to 1 in its corners, the distance being measured in halves of
+    /// the width and the height, so that the prior has the shape of the image.
+    /// A `falloff` of 1 decreases the importance linearly, and higher ones concentrate it
+    /// around the center. Negative or NaN strengths do not protect anything.
+    ///
+    /// ```
+    /// use seamcarving::ImportanceMap;
+    /// let img = image::RgbImage::from_fn(40, 20, |x, y| image::Rgb([(x * y) as u8, 0, 0]));
+    /// let prior = ImportanceMap::center_bias(40, 20, 1000., 2.);
+    /// let resized = seamcarving::resize_with_importance(&img, 30, 20, &prior).unwrap();
+    /// ```
+    pub fn center_bias(width: u32, height: u32, strength: f32, falloff: f32) -> Self {
+        let size = Pos(width, height);
+        let (half_width, half_height) = (f64::from(width) / 2., f64::from(height) / 2.);
+        let bias = Matrix::from_fn(size, |x, y| {
+            let dx = (x as f64 + 0.5 - half_width) / half_width;
+            let dy = (y as f64 + 0.5 - half_height) / half_height;
+            let distance = ((dx * dx + dy * dy) / 2.).sqrt().min(1.);
+            let importance = f64::from(strength) * (1. - distance).powf(f64::from(falloff));
+            // Saturates, and NaN becomes 0
+            importance.clamp(0., f64::from(FORBIDDEN - 1)) as u32
+        });
+        ImportanceMap::from_bias(size, bias)
+    }
+
+    /// Width and height of the image this importance map applies to
+    pub fn dimensions(&self) -> (u32, u32) {
+        (self.size.0, self.size.1)
+    }
+
+    /// Adds the importance of another map to this one.
+    /// Pixels that are forbidden in either map are forbidden in the result.
+    /// Both maps must have the same dimensions.
+    pub fn add(&mut self, other: &ImportanceMap) {
+        assert_eq!(
+            self.size, other.size,
+            "importance maps must have the same dimensions"
+        );
+        for pos in Pos::iter_in_rect(Pos(0, 0), self.size) {
+            self.bias[pos] = combine(self.bias[pos], other.bias[pos]);
+        }
+    }
+
+    /// The importance of the given rows only
+    pub(crate) fn rows(&self, rows: Range<u32>) -> ImportanceMap {
+        let size = Pos(self.size.0, rows.len() as u32);
+        let bias = Matrix::from_fn(size, |x, y| self.bias[Pos(x as u32, rows.start + y as u32)]);
+        ImportanceMap::from_bias(size, bias)
+    }
+}
+
+/// The energy added to a pixel protected with the given weight
+fn weight_bias(weight: f32) -> u32 {
+    if weight >= 1. {
+        FORBIDDEN
+    } else if weight > 0. {
+        (weight * PROTECTED_ENERGY as f32) as u32
+    } else {
+        0
+    }
+}
+
+/// Adds two importance values, keeping forbidden pixels forbidden
+#[inline(always)]
+pub(crate) fn combine(a: u32, b: u32) -> u32 {
+    if a == FORBIDDEN || b == FORBIDDEN {
+        FORBIDDEN
+    } else {
+        a.saturating_add(b).min(FORBIDDEN - 1)
+    }
+}
+
+/// Summed-area tables of values and squared values, to compute variances in constant time
+struct SummedArea {
+    width: usize,
+    sums: Vec<(f64, f64)>,
+}
+
+impl SummedArea {
+    fn new(size: Pos, mut value: impl FnMut(u32, u32) -> f64) -> Self {
+        let width = size.0 as usize + 1;
+        let mut sums = vec![(0., 0.); width * (size.1 as usize + 1)];
+        for y in 0..size.1 as usize {
+            for x in 0..size.0 as usize {
+                let v = value(x as u32, y as u32);
+                let (above, left, diag) = (
+                    sums[y * width + x + 1],
+                    sums[(y + 1) * width + x],
+                    sums[y * width + x],
+                );
+                sums[(y + 1) * width + x + 1] = (
+                    v + above.0 + left.0 - diag.0,
+                    v * v + above.1 + left.1 - diag.1,
+                );
+            }
+        }
+        SummedArea { width, sums }
+    }
+
+    /// Variance of the values in the rectangle between start (inclusive) and end (exclusive)
+    fn variance(&self, start: Pos, end: Pos) -> f64 {
+        let at = |x: u32, y: u32| self.sums[y as usize * self.width + x as usize];
+        let (a, b, c, d) = (
+            at(start.0, start.1),
+            at(end.0, start.1),
+            at(start.0, end.1),
+            at(end.0, end.1),
+        );
+        let n = f64::from((end.0 - start.0) * (end.1 - start.1));
+        let mean = (d.0 - b.0 - c.0 + a.0) / n;
+        let mean_sq = (d.1 - b.1 - c.1 + a.1) / n;
+        (mean_sq - mean * mean).max(0.)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use image::GrayImage;
+
+    use crate::mask::PROTECTED_ENERGY;
+    use crate::matrix::Matrix;
+    use crate::pos::Pos;
+    use crate::seam_finder::FORBIDDEN;
+    use crate::ImportanceMap;
+
+    #[test]
+    fn textures_importance() {
+        // A smooth left half and a checkerboard right half
+        let img = GrayImage::from_fn(8, 4, |x, y| {
+            image::Luma([if x >= 4 && (x + y) % 2 == 0 { 200 } else { 0 }])
+        });
+        let importance = ImportanceMap::textures(&img, 1, 1.0);
+        assert_eq!(importance.bias[Pos(0, 0)], 0);
+        assert_eq!(importance.bias[Pos(1, 2)], 0);
+        // 5 bright pixels out of 9
+        assert_eq!(importance.bias[Pos(5, 1)], 9876);
+    }
+
+    #[test]
+    fn soft_mask_importance() {
+        let mask = GrayImage::from_fn(4, 1, |x, _y| image::Luma([[0, 51, 254, 255][x as usize]]));
+        let importance = ImportanceMap::from_soft_mask(&mask);
+        let bias: Vec<u32> = (0..4).map(|x| importance.bias[Pos(x, 0)]).collect();
+        assert_eq!(bias, [0, PROTECTED_ENERGY / 5, 1_044_463, FORBIDDEN]);
+        let weights = Matrix::from_vec(Pos(3, 1), vec![-1., f32::NAN, 0.5]).unwrap();
+        let importance = ImportanceMap::from_weights(&weights);
+        let bias: Vec<u32> = (0..3).map(|x| importance.bias[Pos(x, 0)]).collect();
+        assert_eq!(bias, [0, 0, PROTECTED_ENERGY / 2]);
+    }
+}
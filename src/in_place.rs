@@ -0,0 +1,120 @@
+use std::ops::ControlFlow;
+
+use image::{ImageBuffer, Pixel};
+
+use crate::layers::Layers;
+use crate::matrix::Matrix;
+use crate::pos::Pos;
+use crate::{carve, max_pos, Limits, PixelBuffer, Rotated};
+
+/// Resizes an image like [resize](crate::resize), but modifies it instead of returning a copy.
+///
+/// The remaining pixels are moved within the buffer of the image,
+/// and the memory of the removed ones is released at the end, with a single reallocation.
+/// Besides the bookkeeping of the seams, no memory is allocated for the pixels.
+///
+/// ```
+/// let mut img = image::GrayImage::from_fn(8, 6, |x, y| image::Luma([(x * y) as u8]));
+/// seamcarving::resize_in_place(&mut img, 5, 4);
+/// assert_eq!(img.dimensions(), (5, 4));
+/// ```
+pub fn resize_in_place<P: Pixel + 'static>(img: &mut PixelBuffer<P>, width: u32, height: u32) {
+    let Pos(to_remove_x, to_remove_y) = max_pos(img) - Pos(width, height);
+    if to_remove_x == img.width() || to_remove_y == img.height() {
+        // No pixel is left, and there is no seam to find in an empty image
+        let (width, height) = (width.min(img.width()), height.min(img.height()));
+        *img = ImageBuffer::new(width, height);
+        return;
+    }
+    let limits = Limits::default();
+    let columns = carve(img, to_remove_x, Layers::default(), false, limits, |_, _, _| ControlFlow::Continue(()))
+        .expect("no pixel is forbidden")
+        .carved
+        .into_pos_aliases();
+    compact_columns(img, &columns);
+    let rows = carve(
+        &Rotated(&*img),
+        to_remove_y,
+        Layers::default(),
+        false,
+        limits,
+        |_, _, _| ControlFlow::Continue(()),
+    )
+    .expect("no pixel is forbidden")
+    .carved
+    .into_pos_aliases();
+    compact_rows(img, &rows);
+}
+
+/// Keeps, on each row, the pixels at the columns given by `columns[Pos(x, y)]`
+fn compact_columns<P: Pixel + 'static>(img: &mut PixelBuffer<P>, columns: &Matrix<u32>) {
+    let (width, height) = img.dimensions();
+    let (new_width, channels) = (columns.width(), P::CHANNEL_COUNT as usize);
+    compact(img, (new_width, height), |Pos(x, y)| {
+        (y as usize * width as usize + columns[Pos(x, y)] as usize) * channels
+    });
+}
+
+/// Keeps, on each column, the pixels at the rows given by `rows[Pos(y, x)]`
+fn compact_rows<P: Pixel + 'static>(img: &mut PixelBuffer<P>, rows: &Matrix<u32>) {
+    let width = img.width();
+    let (new_height, channels) = (rows.width(), P::CHANNEL_COUNT as usize);
+    compact(img, (width, new_height), |Pos(x, y)| {
+        (rows[Pos(y, x)] as usize * width as usize + x as usize) * channels
+    });
+}
+
+/// Replaces the image by one of the given size, whose pixel at each position
+/// is read at the given offset in the previous buffer.
+/// The offsets must never be before the position of the pixel in the new buffer.
+fn compact<P: Pixel + 'static>(
+    img: &mut PixelBuffer<P>,
+    (width, height): (u32, u32),
+    source: impl Fn(Pos) -> usize,
+) {
+    let channels = P::CHANNEL_COUNT as usize;
+    let mut buffer = std::mem::replace(img, ImageBuffer::new(0, 0)).into_raw();
+    let mut target = 0;
+    // Pixels only move towards the start of the buffer, and are moved in order,
+    // so every pixel is read before it is overwritten
+    for pos in Pos::iter_in_rect(Pos(0, 0), Pos(width, height)) {
+        let start = source(pos);
+        debug_assert!(start >= target);
+        buffer.copy_within(start..start + channels, target);
+        target += channels;
+    }
+    buffer.truncate(target);
+    buffer.shrink_to_fit();
+    *img = ImageBuffer::from_raw(width, height, buffer)
+        .expect("the buffer has one pixel per position");
+}
+
+#[cfg(test)]
+mod tests {
+    use image::{Rgb, RgbImage};
+
+    use super::*;
+
+    #[test]
+    fn carves_like_resize() {
+        let img = RgbImage::from_fn(20, 14, |x, y| {
+            Rgb([(x * x) as u8, (y * 17) as u8, (x ^ y) as u8])
+        });
+        let mut carved = img.clone();
+        resize_in_place(&mut carved, 13, 9);
+        assert_eq!(carved, crate::resize(&img, 13, 9));
+        let mut unchanged = img.clone();
+        resize_in_place(&mut unchanged, 30, 30);
+        assert_eq!(unchanged, img);
+    }
+
+    #[test]
+    fn carves_to_empty_images() {
+        let img = RgbImage::from_fn(6, 4, |x, y| Rgb([(x * 40) as u8, (y * 60) as u8, 0]));
+        for (width, height) in [(0, 4), (6, 0), (0, 0), (0, 9)] {
+            let mut carved = img.clone();
+            resize_in_place(&mut carved, width, height);
+            assert_eq!(carved, crate::resize(&img, width, height));
+        }
+    }
+}
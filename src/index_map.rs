@@ -0,0 +1,213 @@
+use alloc::vec::Vec;
+
+#[cfg(feature = "image")]
+use image::{GenericImageView, ImageBuffer};
+
+use crate::matrix::Matrix;
+use crate::pos::Pos;
+#[cfg(feature = "image")]
+use crate::ImageBufferOf;
+use crate::SeamError;
+
+/// Index of the pixels that no seam of a [SeamIndexMap] removes
+const KEPT: u32 = u32::MAX;
+
+/// First bytes of the maps written by [SeamIndexMap::to_bytes]
+const MAGIC: &[u8; 4] = b"SIM1";
+
+/// For each pixel of an image, the index of the vertical seam that removes it,
+/// as computed once by [Carver::seam_index_map](crate::Carver::seam_index_map).
+///
+/// The image can then be rendered at any width between [min_width](SeamIndexMap::min_width)
+/// and its own width in a single pass over its pixels, without computing any energy,
+/// for instance in a browser while the user resizes it.
+/// The map can be stored next to the image with [to_bytes](SeamIndexMap::to_bytes).
+///
+/// ```
+/// # #[cfg(feature = "image")] {
+/// use seamcarving::Carver;
+/// let img = image::GrayImage::from_fn(20, 10, |x, y| image::Luma([(x * y) as u8]));
+/// let map = Carver::new().seam_index_map(&img, 5).unwrap();
+/// assert_eq!(map.min_width(), 5);
+/// assert_eq!(map.render_image(&img, 12).unwrap().dimensions(), (12, 10));
+/// # }
+/// ```
+#[derive(Clone, Debug)]
+pub struct SeamIndexMap {
+    order: Matrix<u32>,
+    seams: u32,
+}
+
+impl SeamIndexMap {
+    /// A map of an image of the given size from which no seam was removed yet
+    pub(crate) fn new(size: Pos) -> Self {
+        SeamIndexMap {
+            order: Matrix::from_fn(size, |_, _| KEPT),
+            seams: 0,
+        }
+    }
+
+    /// Records the next seam, in the coordinates of the original image
+    pub(crate) fn push_seam(&mut self, seam: impl IntoIterator<Item = Pos>) {
+        for pos in seam {
+            self.order[pos] = self.seams;
+        }
+        self.seams += 1;
+    }
+
+    /// Width and height of the original image
+    pub fn dimensions(&self) -> (u32, u32) {
+        (self.order.width(), self.order.height())
+    }
+
+    /// The narrowest width the image can be rendered at
+    pub fn min_width(&self) -> u32 {
+        self.order.width() - self.seams
+    }
+
+    /// The index of the seam that removes the given pixel, the first seam being removed first,
+    /// or None if the pixel is kept at every width
+    pub fn seam_index(&self, x: u32, y: u32) -> Option<u32> {
+        self.order
+            .get(Pos(x, y))
+            .copied()
+            .filter(|&index| index != KEPT)
+    }
+
+    /// Renders the image, stored as a slice of pixels row by row, at the given width,
+    /// clamped between [min_width](SeamIndexMap::min_width) and the width of the image,
+    /// into `output`, which is cleared first. Returns the width of the rendered image.
+    ///
+    /// Fails with [SeamError::SizeMismatch] if the number of pixels does not match the map.
+    pub fn render_into<P: Copy>(
+        &self,
+        pixels: &[P],
+        width: u32,
+        output: &mut Vec<P>,
+    ) -> Result<u32, SeamError> {
+        let (original, height) = self.dimensions();
+        if Pos(original, height).checked_area() != Some(pixels.len()) {
+            return Err(SeamError::SizeMismatch);
+        }
+        let width = width.clamp(self.min_width(), original);
+        // The seams removed first are the ones that are gone at this width
+        let removed = original - width;
+        output.clear();
+        output.reserve(Pos(width, height).area());
+        let kept = |(&pixel, &index): (&P, &u32)| (index >= removed).then_some(pixel);
+        output.extend(pixels.iter().zip(self.order.as_slice()).filter_map(kept));
+        Ok(width)
+    }
+
+    /// Renders an image at the given width, see [render_into](SeamIndexMap::render_into)
+    #[cfg(feature = "image")]
+    pub fn render_image<IMG: GenericImageView>(
+        &self,
+        img: &IMG,
+        width: u32,
+    ) -> Result<ImageBufferOf<IMG>, SeamError>
+    where
+        <IMG as GenericImageView>::Pixel: 'static,
+    {
+        if img.dimensions() != self.dimensions() {
+            return Err(SeamError::SizeMismatch);
+        }
+        let pixels: Vec<_> = img.pixels().map(|(_x, _y, pixel)| pixel).collect();
+        let mut rendered = Vec::new();
+        let width = self.render_into(&pixels, width, &mut rendered)?;
+        let mut rendered = rendered.into_iter();
+        let height = img.height();
+        Ok(ImageBuffer::from_fn(width, height, |_x, _y| {
+            rendered.next().expect("every row has the rendered width")
+        }))
+    }
+
+    /// Encodes the map: four magic bytes, the width, the height
+    /// and the number of seams, then the seam index of each pixel, row by row,
+    /// as little endian 32 bits integers, `u32::MAX` meaning that the pixel is always kept
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let (width, height) = self.dimensions();
+        let mut bytes = Vec::with_capacity(16 + 4 * self.order.as_slice().len());
+        bytes.extend_from_slice(MAGIC);
+        for value in [width, height, self.seams] {
+            bytes.extend_from_slice(&value.to_le_bytes());
+        }
+        for &index in self.order.as_slice() {
+            bytes.extend_from_slice(&index.to_le_bytes());
+        }
+        bytes
+    }
+
+    /// Decodes a map encoded by [to_bytes](SeamIndexMap::to_bytes).
+    /// Returns None if the bytes are not a valid map.
+    pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        let rest = bytes.strip_prefix(MAGIC)?;
+        let mut values = rest
+            .chunks_exact(4)
+            .map(|chunk| u32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]));
+        let (width, height, seams) = (values.next()?, values.next()?, values.next()?);
+        if rest.len() % 4 != 0 || seams > width {
+            return None;
+        }
+        let order: Vec<u32> = values.collect();
+        let removed = |&index: &u32| index != KEPT;
+        // Each seam removes exactly one pixel on each row
+        let row_is_valid = |row: &[u32]| {
+            row.iter().all(|&index| index == KEPT || index < seams)
+                && row.iter().filter(|&i| removed(i)).count() == seams as usize
+        };
+        if width > 0 && !order.chunks(width as usize).all(row_is_valid) {
+            return None;
+        }
+        let order = Matrix::from_vec(Pos(width, height), order)?;
+        Some(SeamIndexMap { order, seams })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec;
+
+    use super::*;
+
+    /// A 4x2 image, from which the first seam removes the second column
+    /// and the second seam the last pixel of each row
+    fn map() -> SeamIndexMap {
+        let mut map = SeamIndexMap::new(Pos(4, 2));
+        map.push_seam([Pos(1, 0), Pos(1, 1)]);
+        map.push_seam([Pos(3, 0), Pos(3, 1)]);
+        map
+    }
+
+    #[test]
+    fn renders_any_width() {
+        let map = map();
+        let pixels: Vec<u32> = (0..8).collect();
+        let mut rendered = Vec::new();
+        assert_eq!(map.render_into(&pixels, 4, &mut rendered), Ok(4));
+        assert_eq!(rendered, pixels);
+        assert_eq!(map.render_into(&pixels, 3, &mut rendered), Ok(3));
+        assert_eq!(rendered, vec![0, 2, 3, 4, 6, 7]);
+        // Narrower than the map allows
+        assert_eq!(map.render_into(&pixels, 0, &mut rendered), Ok(2));
+        assert_eq!(rendered, vec![0, 2, 4, 6]);
+        let mismatch = map.render_into(&pixels[1..], 3, &mut rendered);
+        assert_eq!(mismatch, Err(SeamError::SizeMismatch));
+    }
+
+    #[test]
+    fn encodes_and_decodes() {
+        let map = map();
+        let bytes = map.to_bytes();
+        assert_eq!(bytes.len(), 16 + 4 * 8);
+        let decoded = SeamIndexMap::from_bytes(&bytes).unwrap();
+        assert_eq!(decoded.to_bytes(), bytes);
+        assert_eq!(decoded.seam_index(3, 1), Some(1));
+        assert_eq!(decoded.seam_index(2, 1), None);
+        assert!(SeamIndexMap::from_bytes(&bytes[..bytes.len() - 4]).is_none());
+        // The second seam would remove two pixels of the first row
+        let mut invalid = bytes.clone();
+        invalid[16..20].copy_from_slice(&1u32.to_le_bytes());
+        assert!(SeamIndexMap::from_bytes(&invalid).is_none());
+    }
+}
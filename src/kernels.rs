@@ -0,0 +1,226 @@
+use crate::seam_finder::{cumulate, FORBIDDEN};
+
+/// The cheapest seam reaching a position from the cumulative energies of its left,
+/// middle and right predecessors, given the energy of the position itself,
+/// and the offset of the predecessor it comes from, the leftmost one winning ties.
+/// Missing predecessors have a [FORBIDDEN] energy.
+#[inline(always)]
+pub(crate) fn best_of(left: u32, middle: u32, right: u32, delta_e: u32) -> (u32, i8) {
+    if delta_e == FORBIDDEN {
+        // No seam can go through this position
+        return (FORBIDDEN, 0);
+    }
+    let cost = |e: u32| {
+        let energy = cumulate(e, delta_e);
+        if e != FORBIDDEN {
+            energy
+        } else {
+            FORBIDDEN
+        }
+    };
+    let (mut energy, mut dx) = (cost(left), -1);
+    let middle = cost(middle);
+    if middle < energy {
+        energy = middle;
+        dx = 0;
+    }
+    let right = cost(right);
+    if right < energy {
+        energy = right;
+        dx = 1;
+    }
+    (energy, if energy == FORBIDDEN { 0 } else { dx })
+}
+
+/// Applies [best_of] to consecutive positions: the predecessors of position `i`
+/// are `above[i]`, `above[i + 1]` and `above[i + 2]`, and its energy is `delta[i]`.
+///
+/// The positions are processed with AVX-512 or NEON instructions
+/// when the processor supports them, with exactly the same results.
+#[cfg(feature = "simd")]
+pub(crate) fn best_predecessors(above: &[u32], delta: &[u32], energies: &mut [u32], dx: &mut [i8]) {
+    let n = delta.len();
+    assert!(above.len() == n + 2 && energies.len() == n && dx.len() == n);
+    #[cfg(target_arch = "x86_64")]
+    {
+        if std::is_x86_feature_detected!("avx512f") {
+            // SAFETY: the processor supports AVX-512F, and the lengths were checked
+            return unsafe { avx512::best_predecessors(above, delta, energies, dx) };
+        }
+    }
+    #[cfg(target_arch = "aarch64")]
+    {
+        if std::arch::is_aarch64_feature_detected!("neon") {
+            // SAFETY: the processor supports NEON, and the lengths were checked
+            return unsafe { neon::best_predecessors(above, delta, energies, dx) };
+        }
+    }
+    best_predecessors_from(0, above, delta, energies, dx)
+}
+
+/// Whether [best_predecessors] uses vector instructions on this processor
+#[cfg(feature = "simd")]
+pub(crate) fn is_vectorized() -> bool {
+    #[cfg(target_arch = "x86_64")]
+    let vectorized = std::is_x86_feature_detected!("avx512f");
+    #[cfg(target_arch = "aarch64")]
+    let vectorized = std::arch::is_aarch64_feature_detected!("neon");
+    #[cfg(not(any(target_arch = "x86_64", target_arch = "aarch64")))]
+    let vectorized = false;
+    vectorized
+}
+
+/// The scalar version of [best_predecessors], for the positions from `start` on
+#[cfg(feature = "simd")]
+fn best_predecessors_from(
+    start: usize,
+    above: &[u32],
+    delta: &[u32],
+    energies: &mut [u32],
+    dx: &mut [i8],
+) {
+    for i in start..delta.len() {
+        (energies[i], dx[i]) = best_of(above[i], above[i + 1], above[i + 2], delta[i]);
+    }
+}
+
+#[cfg(all(feature = "simd", target_arch = "x86_64"))]
+mod avx512 {
+    use core::arch::x86_64::*;
+
+    use super::best_predecessors_from;
+    use crate::seam_finder::FORBIDDEN;
+
+    /// Number of positions processed at once
+    const LANES: usize = 16;
+
+    /// Cumulative energies of seams coming from the predecessors `a`
+    #[inline]
+    #[target_feature(enable = "avx512f")]
+    unsafe fn cost(a: __m512i, delta: __m512i) -> __m512i {
+        let forbidden = _mm512_set1_epi32(FORBIDDEN as i32);
+        let sum = _mm512_add_epi32(a, delta);
+        // Saturating addition, then the same cap as cumulate
+        let sum = _mm512_mask_mov_epi32(sum, _mm512_cmplt_epu32_mask(sum, a), forbidden);
+        let sum = _mm512_min_epu32(sum, _mm512_set1_epi32((FORBIDDEN - 1) as i32));
+        _mm512_mask_mov_epi32(sum, _mm512_cmpeq_epi32_mask(a, forbidden), forbidden)
+    }
+
+    #[target_feature(enable = "avx512f")]
+    pub unsafe fn best_predecessors(
+        above: &[u32],
+        delta: &[u32],
+        energies: &mut [u32],
+        dx: &mut [i8],
+    ) {
+        let forbidden = _mm512_set1_epi32(FORBIDDEN as i32);
+        let load = |slice: &[u32], i: usize| _mm512_loadu_si512(slice.as_ptr().add(i).cast());
+        let mut i = 0;
+        while i + LANES <= delta.len() {
+            let d = load(delta, i);
+            let mut best = cost(load(above, i), d);
+            let mut offset = _mm512_set1_epi32(-1);
+            let middle = cost(load(above, i + 1), d);
+            let lower = _mm512_cmplt_epu32_mask(middle, best);
+            best = _mm512_mask_mov_epi32(best, lower, middle);
+            offset = _mm512_mask_mov_epi32(offset, lower, _mm512_set1_epi32(0));
+            let right = cost(load(above, i + 2), d);
+            let lower = _mm512_cmplt_epu32_mask(right, best);
+            best = _mm512_mask_mov_epi32(best, lower, right);
+            offset = _mm512_mask_mov_epi32(offset, lower, _mm512_set1_epi32(1));
+            best = _mm512_mask_mov_epi32(best, _mm512_cmpeq_epi32_mask(d, forbidden), forbidden);
+            let unreachable = _mm512_cmpeq_epi32_mask(best, forbidden);
+            offset = _mm512_mask_mov_epi32(offset, unreachable, _mm512_set1_epi32(0));
+            _mm512_storeu_si512(energies.as_mut_ptr().add(i).cast(), best);
+            _mm_storeu_si128(dx.as_mut_ptr().add(i).cast(), _mm512_cvtepi32_epi8(offset));
+            i += LANES;
+        }
+        best_predecessors_from(i, above, delta, energies, dx)
+    }
+}
+
+#[cfg(all(feature = "simd", target_arch = "aarch64"))]
+mod neon {
+    use core::arch::aarch64::*;
+
+    use super::best_predecessors_from;
+    use crate::seam_finder::FORBIDDEN;
+
+    /// Number of positions processed at once
+    const LANES: usize = 4;
+
+    /// Cumulative energies of seams coming from the predecessors `a`
+    #[inline]
+    #[target_feature(enable = "neon")]
+    unsafe fn cost(a: uint32x4_t, delta: uint32x4_t) -> uint32x4_t {
+        let forbidden = vdupq_n_u32(FORBIDDEN);
+        let sum = vminq_u32(vqaddq_u32(a, delta), vdupq_n_u32(FORBIDDEN - 1));
+        vbslq_u32(vceqq_u32(a, forbidden), forbidden, sum)
+    }
+
+    #[target_feature(enable = "neon")]
+    pub unsafe fn best_predecessors(
+        above: &[u32],
+        delta: &[u32],
+        energies: &mut [u32],
+        dx: &mut [i8],
+    ) {
+        let forbidden = vdupq_n_u32(FORBIDDEN);
+        let mut i = 0;
+        while i + LANES <= delta.len() {
+            let d = vld1q_u32(delta.as_ptr().add(i));
+            let mut best = cost(vld1q_u32(above.as_ptr().add(i)), d);
+            let mut offset = vdupq_n_s32(-1);
+            let middle = cost(vld1q_u32(above.as_ptr().add(i + 1)), d);
+            let lower = vcltq_u32(middle, best);
+            best = vbslq_u32(lower, middle, best);
+            offset = vbslq_s32(lower, vdupq_n_s32(0), offset);
+            let right = cost(vld1q_u32(above.as_ptr().add(i + 2)), d);
+            let lower = vcltq_u32(right, best);
+            best = vbslq_u32(lower, right, best);
+            offset = vbslq_s32(lower, vdupq_n_s32(1), offset);
+            best = vbslq_u32(vceqq_u32(d, forbidden), forbidden, best);
+            offset = vbslq_s32(vceqq_u32(best, forbidden), vdupq_n_s32(0), offset);
+            vst1q_u32(energies.as_mut_ptr().add(i), best);
+            let mut offsets = [0; 8];
+            vst1_s8(
+                offsets.as_mut_ptr(),
+                vmovn_s16(vcombine_s16(vmovn_s32(offset), vdup_n_s16(0))),
+            );
+            dx[i..i + LANES].copy_from_slice(&offsets[..LANES]);
+            i += LANES;
+        }
+        best_predecessors_from(i, above, delta, energies, dx)
+    }
+}
+
+#[cfg(all(test, feature = "simd"))]
+mod tests {
+    use alloc::vec;
+    use alloc::vec::Vec;
+
+    use super::*;
+
+    #[test]
+    fn vectorized_kernels_match_the_scalar_one() {
+        // Energies around the limits, with many ties
+        let values = [0, 1, 2, 7, FORBIDDEN - 2, FORBIDDEN - 1, FORBIDDEN, u32::MAX / 2];
+        let mut state = 12345u32;
+        let mut next = || {
+            state ^= state << 13;
+            state ^= state >> 17;
+            state ^= state << 5;
+            values[state as usize % values.len()]
+        };
+        for n in [0, 1, 3, 4, 15, 16, 17, 33, 100] {
+            let above: Vec<u32> = (0..n + 2).map(|_| next()).collect();
+            let delta: Vec<u32> = (0..n).map(|_| next()).collect();
+            let (mut energies, mut dx) = (vec![0; n], vec![0; n]);
+            best_predecessors(&above, &delta, &mut energies, &mut dx);
+            let (mut expected_energies, mut expected_dx) = (vec![0; n], vec![0; n]);
+            best_predecessors_from(0, &above, &delta, &mut expected_energies, &mut expected_dx);
+            assert_eq!(energies, expected_energies);
+            assert_eq!(dx, expected_dx);
+        }
+    }
+}
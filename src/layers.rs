@@ -0,0 +1,262 @@
+use image::GenericImageView;
+
+use crate::energy::{energy_in, forward_energy_in};
+use crate::importance::combine;
+use crate::matrix::Matrix;
+use crate::pos::{BorderMode, Pos};
+use crate::seam_finder::FORBIDDEN;
+
+/// Per-pixel information that follows the pixels of an image while it is carved
+#[derive(Clone, Default)]
+pub(crate) struct Layers {
+    // Energy added to each pixel
+    pub bias: Option<Matrix<u32>>,
+    // Whether each pixel is part of the region that seams can go through
+    valid: Option<Matrix<bool>>,
+    // Number of valid pixels on each row
+    valid_counts: Vec<u32>,
+    // Limit on the number of pixels removed close to each other
+    spread: Option<Spread>,
+    // Whether seams are kept away from the first and last columns
+    keep_borders: bool,
+    // Whether each pixel is on a pinned column, that seams never go through
+    pinned_columns: Option<Matrix<bool>>,
+    // Whether each pixel is on a pinned row, that seams avoid once the layers are transposed
+    pinned_rows: Option<Matrix<bool>>,
+    // Whether the forward energy is used instead of the gradient of the image
+    forward: bool,
+    // How the neighbours of the pixels on the borders are chosen
+    border: BorderMode,
+    // Factor applied to the energy of each pixel, out of u16::MAX
+    scale: Option<Matrix<u16>>,
+    // Whether the seam finder stores quantized energies
+    pub quantized: bool,
+}
+
+/// Limits how many pixels can be removed on each row
+/// in the neighbourhood of every column of the original image
+#[derive(Clone)]
+struct Spread {
+    radius: u32,
+    max_removed: u32,
+    // Width of the original image
+    width: u32,
+    // Column of each pixel of the carved image in the original image
+    columns: Matrix<u32>,
+    // Number of removed pixels at most radius columns away from each original pixel
+    removed: Matrix<u32>,
+}
+
+impl Layers {
+    pub fn with_bias(bias: Option<Matrix<u32>>) -> Self {
+        Layers {
+            bias,
+            ..Layers::default()
+        }
+    }
+
+    pub fn set_valid(&mut self, valid: Matrix<bool>, size: Pos) {
+        self.valid_counts = (0..size.1)
+            .map(|y| (0..size.0).filter(|&x| valid[Pos(x, y)]).count() as u32)
+            .collect();
+        self.valid = Some(valid);
+    }
+
+    /// Forbids removing a pixel when `max_removed` pixels at most `radius` columns
+    /// away from it in the original image were already removed on the same row
+    pub fn set_spread(&mut self, radius: u32, max_removed: u32, size: Pos) {
+        self.spread = Some(Spread {
+            radius,
+            max_removed,
+            width: size.0,
+            columns: Matrix::from_fn(size, |x, _y| x as u32),
+            removed: Matrix::from_fn(size, |_x, _y| 0),
+        });
+    }
+
+    /// Forbids seams from going through the first and last columns of the image
+    pub fn set_keep_borders(&mut self, keep_borders: bool) {
+        self.keep_borders = keep_borders;
+    }
+
+    /// Forbids seams from going through the given columns of the image,
+    /// and horizontal seams from going through the given rows once the layers are transposed
+    pub fn set_pinned(&mut self, columns: &[u32], rows: &[u32], size: Pos) {
+        let lines = |pinned: &[u32], length: u32| {
+            let mut lines = vec![false; length as usize];
+            for &line in pinned.iter().filter(|&&line| line < length) {
+                lines[line as usize] = true;
+            }
+            lines
+        };
+        if !columns.is_empty() {
+            let columns = lines(columns, size.0);
+            self.pinned_columns = Some(Matrix::from_fn(size, |x, _y| columns[x]));
+        }
+        if !rows.is_empty() {
+            let rows = lines(rows, size.1);
+            self.pinned_rows = Some(Matrix::from_fn(size, |_x, y| rows[y]));
+        }
+    }
+
+    /// Scales the energy of each pixel by the cosine of its latitude, for an image
+    /// that is the given rows of an equirectangular panorama `panorama_height` pixels high
+    pub fn set_latitude_scale(&mut self, size: Pos, first_row: u32, panorama_height: u32) {
+        let scales: Vec<u16> = (0..size.1)
+            .map(|y| {
+                let row = f64::from(first_row + y) + 0.5;
+                let latitude = (row / f64::from(panorama_height) - 0.5) * std::f64::consts::PI;
+                (latitude.cos().max(0.) * f64::from(u16::MAX)) as u16
+            })
+            .collect();
+        self.scale = Some(Matrix::from_fn(size, |_x, y| scales[y]));
+    }
+
+    /// Uses the forward energy of the pixels instead of their gradient
+    pub fn set_forward_energy(&mut self, forward: bool) {
+        self.forward = forward;
+    }
+
+    /// Chooses the neighbours of the pixels on the borders of the image
+    pub fn set_border_mode(&mut self, border: BorderMode) {
+        self.border = border;
+    }
+
+    /// Energy of a pixel of the carved image, including its bias and validity
+    #[inline(always)]
+    pub fn energy<IMG: GenericImageView>(&self, img: &IMG, pos: Pos, wrap: bool) -> u32 {
+        if self.keep_borders && (pos.0 == 0 || pos.0 + 1 == img.width()) {
+            return FORBIDDEN;
+        }
+        if self.pinned_columns.as_ref().is_some_and(|pinned| pinned[pos]) {
+            return FORBIDDEN;
+        }
+        if let Some(spread) = &self.spread {
+            if spread.removed[Pos(spread.columns[pos], pos.1)] >= spread.max_removed {
+                return FORBIDDEN;
+            }
+        }
+        let energy = match &self.valid {
+            Some(valid) if !valid[pos] => {
+                if self.valid_counts[pos.1 as usize] > 0 {
+                    return FORBIDDEN;
+                }
+                // A seam has to cross this row somewhere
+                0
+            }
+            valid if self.forward => {
+                forward_energy_in(img, pos, wrap, self.border, valid.as_ref()).min(FORBIDDEN - 1)
+            }
+            valid => energy_in(img, pos, wrap, self.border, valid.as_ref()).min(FORBIDDEN - 1),
+        };
+        let energy = self.scale.as_ref().map_or(energy, |scale| {
+            (u64::from(energy) * u64::from(scale[pos]) / u64::from(u16::MAX)) as u32
+        });
+        self.bias.as_ref().map_or(energy, |b| combine(energy, b[pos]))
+    }
+
+    /// Removes the seam from all layers.
+    /// Returns true if the energies of the pixels that were not on the seam changed.
+    pub fn remove_seam(&mut self, seam: &[Pos]) -> bool {
+        let mut changed = false;
+        if let Some(valid) = &mut self.valid {
+            for &pos in seam {
+                if valid[pos] {
+                    let count = &mut self.valid_counts[pos.1 as usize];
+                    *count -= 1;
+                    // Invalid pixels of this row are not forbidden anymore
+                    changed |= *count == 0;
+                }
+            }
+            valid.remove_seam(seam);
+        }
+        if let Some(bias) = &mut self.bias {
+            bias.remove_seam(seam);
+        }
+        for pinned in self.pinned_columns.iter_mut().chain(&mut self.pinned_rows) {
+            pinned.remove_seam(seam);
+        }
+        if let Some(scale) = &mut self.scale {
+            scale.remove_seam(seam);
+        }
+        if let Some(spread) = &mut self.spread {
+            changed |= spread.remove_seam(seam);
+        }
+        // Wrapped borders are compared with the opposite borders, far from the seam
+        changed | self.wraps_borders()
+    }
+
+    /// Whether the energy of a pixel on a border depends on the pixels of the opposite border
+    pub fn wraps_borders(&self) -> bool {
+        self.border == BorderMode::Wrap
+    }
+
+    /// Frees the memory used by the removed pixels in all layers
+    pub fn shrink_to_fit(&mut self) {
+        if let Some(valid) = &mut self.valid {
+            valid.shrink_to_fit();
+        }
+        if let Some(bias) = &mut self.bias {
+            bias.shrink_to_fit();
+        }
+        for pinned in self.pinned_columns.iter_mut().chain(&mut self.pinned_rows) {
+            pinned.shrink_to_fit();
+        }
+        if let Some(scale) = &mut self.scale {
+            scale.shrink_to_fit();
+        }
+        if let Some(spread) = &mut self.spread {
+            // The removed counts are indexed by columns of the original image
+            spread.columns.shrink_to_fit();
+        }
+    }
+
+    /// Swaps rows and columns in all layers. `size` is the current size of the layers.
+    pub fn transposed(self, size: Pos) -> Self {
+        fn transpose<T: Copy>(mut matrix: Matrix<T>) -> Matrix<T> {
+            matrix.transpose();
+            matrix
+        }
+        let mut layers = Layers::with_bias(self.bias.map(transpose));
+        if let Some(valid) = self.valid {
+            layers.set_valid(transpose(valid), Pos(size.1, size.0));
+        }
+        // Each direction is limited independently, starting from the image carved so far
+        if let Some(Spread {
+            radius,
+            max_removed,
+            ..
+        }) = self.spread
+        {
+            layers.set_spread(radius, max_removed, Pos(size.1, size.0));
+        }
+        // The first and last rows become the first and last columns
+        layers.keep_borders = self.keep_borders;
+        layers.pinned_columns = self.pinned_rows.map(transpose);
+        layers.pinned_rows = self.pinned_columns.map(transpose);
+        layers.forward = self.forward;
+        layers.border = self.border;
+        layers.scale = self.scale.map(transpose);
+        layers.quantized = self.quantized;
+        layers
+    }
+}
+
+impl Spread {
+    /// Returns true if some pixels cannot be removed anymore
+    fn remove_seam(&mut self, seam: &[Pos]) -> bool {
+        let mut changed = false;
+        for &pos in seam {
+            let column = self.columns[pos];
+            let start = column.saturating_sub(self.radius);
+            let end = column.saturating_add(self.radius).min(self.width - 1);
+            for x in start..=end {
+                let removed = &mut self.removed[Pos(x, pos.1)];
+                *removed += 1;
+                changed |= *removed == self.max_removed;
+            }
+        }
+        self.columns.remove_seam(seam);
+        changed
+    }
+}
@@ -4,20 +4,230 @@
 //! it takes an image, and removes horizontal and vertical seams
 //! until it fits a given size.
 //!
-use image::{GenericImageView, ImageBuffer, Pixel};
+//! Without the default `image` feature, the crate does not depend on the
+//! [image](https://crates.io/crates/image) crate, and only provides [resize_buffer],
+//! which works on slices of pixels, and the [Matrix], [Pos] and [SeamFinder] types
+//! it is built on.
+//! Without the `std` feature, which `image` enables, the crate is `no_std`,
+//! and only needs an allocator.
+#![cfg_attr(not(feature = "std"), no_std)]
+// Most of the helpers of the core types are only used by the image functions
+#![cfg_attr(not(feature = "image"), allow(dead_code))]
 
-pub use crate::carved::Carved;
-use crate::energy::energy_fn;
-use crate::pos::Pos;
-pub use crate::rotated::Rotated;
-use crate::seam_finder::SeamFinder;
+extern crate alloc;
 
+#[cfg(feature = "image")]
+use std::borrow::Cow;
+#[cfg(feature = "image")]
+use std::ops::ControlFlow;
+#[cfg(feature = "image")]
+use std::time::{Duration, Instant};
+
+#[cfg(feature = "image")]
+use image::{GenericImageView, ImageBuffer, Luma, Pixel};
+
+pub use crate::backend::Backend;
+pub use crate::bidirectional::BidirectionalSeamFinder;
+pub use crate::buffer::{resize_buffer, resize_buffer_with_backend, BufferScratch};
+#[cfg(feature = "rayon")]
+pub use crate::{buffer::resize_buffer_parallel, threads::Threads};
+#[cfg(feature = "imageproc")]
+pub use crate::edges::Canny;
+#[cfg(feature = "ndarray")]
+pub use crate::arrays::{from_gray_ndarray, from_ndarray, to_gray_ndarray, to_ndarray};
+#[cfg(feature = "image")]
+pub use crate::error::{CheckpointError, ConfigError, RegistryError, ValidationError};
+pub use crate::error::SeamError;
+#[cfg(feature = "gpu")]
+pub use crate::{error::GpuError, gpu::Gpu};
+pub use crate::grid::resize_matrix;
+pub use crate::index_map::SeamIndexMap;
+pub use crate::mask::{Rect, Shape};
+pub use crate::matrix::{Matrix, MatrixView, MatrixViewMut};
+pub use crate::planes::resize_planes;
+#[cfg(feature = "png-stream")]
+pub use crate::{error::DecodeError, png_stream::resize_png};
+#[cfg(feature = "tiff")]
+pub use crate::pages::{encode_pages, load_pages, open_pages, resize_pages, save_pages};
+pub use crate::pos::{BorderMode, Connectivity, Neighbours, Pos, PosRow};
+pub use crate::seam::{apply_seam, seam_fingerprint, Seam, SeamTarget};
+pub use crate::seam_finder::{SeamFinder, SeamTable, FORBIDDEN};
+#[cfg(feature = "image")]
+pub use crate::{
+    animation::{encode_animation, record_animation, AnimationEncoder, Frame},
+    axes::{choose_axes, AxisChoice, Goal},
+    atlas::retarget_atlas,
+    borders::{resize_removing_borders, Borders},
+    builder::CarverBuilder,
+    cache::{Axis, SeamCache},
+    checkpoint::Checkpoint,
+    carved::Carved,
+    codecs::{
+        encode_dynamic_image, encode_image, generate_variants, image_dimensions, load_image,
+        open_image, open_image_with_metadata, save_dynamic_image_with_metadata, save_image,
+        save_image_with_metadata,
+    },
+    carver::{Algorithm, AxisOrder, Carver, Dimension, OverBudget, HYBRID_MAX_SEAM_ENERGY},
+    crop::suggest_crop,
+    diff::{diff_image, energy_heatmap, energy_image, seams_image},
+    energies::{CenterBias, Entropy, Gradient, Saliency, Sobel},
+    enlarge::{
+        enlarge, enlarge_with_blend, enlarge_with_max_step, enlarge_with_sampling, InsertionBlend,
+        SeamSampling,
+    },
+    importance::ImportanceMap,
+    in_place::resize_in_place,
+    lines::LineOptions,
+    mask::shapes_mask,
+    metadata::Metadata,
+    metrics::{compare_images, ssim, Comparison},
+    pipeline::{
+        Blur, CarveStage, Constraint, EnergyStage, Forbid, Pipeline, Postprocess, Prefilter,
+        Protect, Sharpen, Textures,
+    },
+    presets::Preset,
+    registry::{EnergyParams, EnergyRegistry},
+    relocate::{relocate_object, remove_object},
+    remap::{Annotation, CoordinateMap},
+    report::{reports_to_csv, reports_to_json, FileReport, Plan, Report, SeamStats, Timings},
+    retargeter::{Capabilities, Retargeter},
+    rotated::Rotated,
+    sequence::SeamSequence,
+    shared::{Progress, SharedCarver},
+    sprites::{resize_frames, resize_sprite_sheet},
+    stats::{energy_stats, EnergyStats},
+    stereo::resize_stereo_pair,
+    stitch::blend_seam,
+    throttle::lower_priority,
+    validate::{validate_inputs, InputMask},
+    video::{FlowField, FrameCarver},
+};
+#[cfg(feature = "image")]
+use crate::{
+    blend::carved_to_buffer,
+    cache::SeamLog,
+    importance::combine,
+    layers::Layers,
+    throttle::Pacer,
+};
+
+// Defines macros, so it has to come first
+#[macro_use]
+mod trace;
+
+#[cfg(feature = "image")]
+mod animation;
+#[cfg(feature = "ndarray")]
+mod arrays;
+#[cfg(feature = "image")]
+mod atlas;
+#[cfg(feature = "image")]
+mod axes;
+mod backend;
+#[cfg(feature = "std")]
+pub mod bench;
+mod bidirectional;
+#[cfg(feature = "image")]
+mod blend;
+#[cfg(feature = "image")]
+mod borders;
+#[cfg(feature = "image")]
+mod builder;
+mod buffer;
+#[cfg(feature = "image")]
+mod cache;
+#[cfg(feature = "image")]
+mod checkpoint;
+#[cfg(feature = "image")]
 mod carved;
+#[cfg(feature = "image")]
+mod codecs;
+#[cfg(feature = "image")]
+mod carver;
+#[cfg(feature = "image")]
+mod crop;
+#[cfg(feature = "image")]
+mod diff;
+#[cfg(feature = "image")]
 mod energy;
+#[cfg(feature = "imageproc")]
+mod edges;
+#[cfg(feature = "image")]
+mod energies;
+#[cfg(feature = "image")]
+mod enlarge;
+mod error;
+#[cfg(feature = "rustface")]
+pub mod faces;
+#[cfg(feature = "gpu")]
+mod gpu;
+mod grid;
+#[cfg(feature = "image")]
+pub mod imageops;
+#[cfg(feature = "image")]
+mod importance;
+mod index_map;
+#[cfg(feature = "image")]
+mod in_place;
+mod kernels;
+#[cfg(feature = "image")]
+mod layers;
+#[cfg(feature = "image")]
+mod lines;
+mod mask;
 mod matrix;
+#[cfg(feature = "image")]
+mod metadata;
+#[cfg(feature = "image")]
+mod metrics;
+#[cfg(feature = "tiff")]
+mod pages;
+mod planes;
+#[cfg(feature = "image")]
+mod pipeline;
+#[cfg(feature = "png-stream")]
+mod png_stream;
 mod pos;
+#[cfg(feature = "image")]
+mod presets;
+#[cfg(feature = "preview")]
+mod preview;
+#[cfg(feature = "image")]
+mod registry;
+#[cfg(feature = "image")]
+mod relocate;
+#[cfg(feature = "image")]
+mod remap;
+#[cfg(feature = "image")]
+mod report;
+#[cfg(feature = "image")]
+mod retargeter;
+#[cfg(feature = "image")]
 mod rotated;
+mod seam;
 mod seam_finder;
+#[cfg(feature = "image")]
+mod sequence;
+#[cfg(feature = "image")]
+mod shared;
+#[cfg(feature = "image")]
+mod sprites;
+#[cfg(feature = "image")]
+mod stats;
+#[cfg(feature = "image")]
+mod stereo;
+#[cfg(feature = "image")]
+mod stitch;
+#[cfg(feature = "image")]
+pub mod testimg;
+#[cfg(feature = "rayon")]
+mod threads;
+#[cfg(feature = "image")]
+mod throttle;
+#[cfg(feature = "image")]
+mod validate;
+#[cfg(feature = "image")]
+mod video;
 
 /// Resizes an image to a lower width and height,
 /// using seam carving to avoid deforming the contents.
@@ -29,41 +239,684 @@ mod seam_finder;
 /// If the image is already smaller than the given dimensions,
 /// then the returned image is identical to the input.
 ///
+/// Images can have any pixel type, such as `Luma<u8>`, `LumaA<u8>`, `Rgb<u16>` or `Rgba<f32>`,
+/// without being converted first: channels are compared on the scale of 8-bit channels,
+/// floating point channels ranging from 0 to 1,
+/// so that the seams do not depend on the depth of the image.
+///
 /// ```no_run
 /// let img = image::open("./my_image.jpg").unwrap();
 /// let resized = seamcarving::resize(&img, 100, 100); // Creates a 100x100 version of the image
 /// resized.save("./resized.jpg");
 /// ```
+#[cfg(feature = "image")]
 pub fn resize<IMG: GenericImageView>(
     img: &IMG,
     width: u32,
     height: u32,
 ) -> ImageBuffer<IMG::Pixel, Vec<<<IMG as GenericImageView>::Pixel as Pixel>::Subpixel>>
+where
+    <IMG as GenericImageView>::Pixel: 'static,
+{
+    resize_with_layers(img, width, height, Layers::default(), false).expect("no pixel is forbidden")
+}
+
+/// Resizes an image buffer like [resize], but borrows it instead of copying it
+/// when it already fits in the given dimensions.
+///
+/// ```
+/// use std::borrow::Cow;
+/// let img = image::GrayImage::new(40, 30);
+/// assert!(matches!(seamcarving::resize_cow(&img, 40, 30), Cow::Borrowed(_)));
+/// assert!(matches!(seamcarving::resize_cow(&img, 20, 30), Cow::Owned(_)));
+/// ```
+#[cfg(feature = "image")]
+pub fn resize_cow<P: Pixel + 'static>(
+    img: &PixelBuffer<P>,
+    width: u32,
+    height: u32,
+) -> Cow<'_, PixelBuffer<P>> {
+    if img.width() <= width && img.height() <= height {
+        Cow::Borrowed(img)
+    } else {
+        Cow::Owned(resize(img, width, height))
+    }
+}
+
+/// Resizes an image to exactly the given width and height, carving seams out of the
+/// dimensions that are too large, and inserting seams in the ones that are too small.
+///
+/// When both dimensions shrink, the cheapest of the vertical and horizontal seams
+/// is removed at each step, see [AxisOrder::Greedy]: this is slower than [resize],
+/// but does not decide upfront how many seams to remove along each axis first.
+/// Seams are removed before any is inserted, see [enlarge].
+///
+/// ```no_run
+/// let img = image::open("./my_image.jpg").unwrap();
+/// let banner = seamcarving::retarget(&img, 1200, 300);
+/// banner.save("./banner.jpg");
+/// ```
+#[cfg(feature = "image")]
+pub fn retarget<IMG: GenericImageView>(img: &IMG, width: u32, height: u32) -> ImageBufferOf<IMG>
+where
+    <IMG as GenericImageView>::Pixel: 'static,
+{
+    let (original_width, original_height) = img.dimensions();
+    let order = if width < original_width && height < original_height {
+        AxisOrder::Greedy
+    } else {
+        AxisOrder::WidthFirst
+    };
+    let shrunk = Carver::new()
+        .width(width)
+        .height(height)
+        .axis_order(order)
+        .resize(img)
+        .expect("no pixel is forbidden");
+    if width > original_width || height > original_height {
+        enlarge(&shrunk, width, height)
+    } else {
+        shrunk
+    }
+}
+
+/// Carves an image until its width and height have the given ratio,
+/// removing either vertical or horizontal seams, but never both.
+///
+/// The `(width, height)` ratio can be any pair of integers, such as `(16, 9)` or `(1, 1)`.
+///
+/// ```no_run
+/// let img = image::open("./my_image.jpg").unwrap();
+/// let square = seamcarving::retarget_aspect(&img, (1, 1));
+/// square.save("./square.jpg");
+/// ```
+#[cfg(feature = "image")]
+pub fn retarget_aspect<IMG: GenericImageView>(
+    img: &IMG,
+    ratio: (u32, u32),
+) -> ImageBuffer<IMG::Pixel, Vec<<<IMG as GenericImageView>::Pixel as Pixel>::Subpixel>>
+where
+    <IMG as GenericImageView>::Pixel: 'static,
+{
+    let (width, height) = aspect_dimensions(img.dimensions(), ratio);
+    resize(img, width, height)
+}
+
+/// The largest dimensions with the given ratio that fit in the given size
+#[cfg(feature = "image")]
+fn aspect_dimensions((width, height): (u32, u32), (rw, rh): (u32, u32)) -> (u32, u32) {
+    assert!(rw > 0 && rh > 0, "the aspect ratio must not be zero");
+    let (w, h, rw, rh) = (
+        u64::from(width),
+        u64::from(height),
+        u64::from(rw),
+        u64::from(rh),
+    );
+    if w * rh > h * rw {
+        // Too wide: keep the height
+        let new_width = (h * rw + rh / 2) / rh;
+        (new_width.max(1).min(w) as u32, height)
+    } else {
+        let new_height = (w * rh + rw / 2) / rw;
+        (width, new_height.max(1).min(h) as u32)
+    }
+}
+
+/// Resizes an image like [resize],
+/// but avoids removing the pixels that are not black in the given mask.
+///
+/// The mask must have the same dimensions as the image.
+/// Protection is not absolute: a seam will still go through protected pixels
+/// when there is no other way to reach the requested size.
+///
+/// ```no_run
+/// let img = image::open("./my_image.jpg").unwrap();
+/// let mask = image::open("./my_mask.png").unwrap().to_luma();
+/// let resized = seamcarving::resize_with_mask(&img, 100, 100, &mask);
+/// resized.save("./resized.jpg");
+/// ```
+#[cfg(feature = "image")]
+pub fn resize_with_mask<IMG: GenericImageView, M: GenericImageView<Pixel = Luma<u8>>>(
+    img: &IMG,
+    width: u32,
+    height: u32,
+    mask: &M,
+) -> ImageBuffer<IMG::Pixel, Vec<<<IMG as GenericImageView>::Pixel as Pixel>::Subpixel>>
+where
+    <IMG as GenericImageView>::Pixel: 'static,
+{
+    resize_with_importance(img, width, height, &ImportanceMap::from_mask(mask))
+        .expect("no pixel is forbidden")
+}
+
+/// Resizes an image like [resize],
+/// but adds the given importance to the energy of every pixel.
+///
+/// The importance map must have the same dimensions as the image.
+/// If it contains [forbidden](ImportanceMap::forbidden) pixels
+/// that make it impossible to reach the requested size,
+/// [SeamError::Blocked] is returned.
+#[cfg(feature = "image")]
+pub fn resize_with_importance<IMG: GenericImageView>(
+    img: &IMG,
+    width: u32,
+    height: u32,
+    importance: &ImportanceMap,
+) -> Result<ImageBufferOf<IMG>, SeamError>
+where
+    <IMG as GenericImageView>::Pixel: 'static,
+{
+    assert_eq!(
+        img.dimensions(),
+        importance.dimensions(),
+        "the importance map must have the same dimensions as the image"
+    );
+    let layers = Layers::with_bias(Some(importance.bias.clone()));
+    resize_with_layers(img, width, height, layers, false)
+}
+
+/// Resizes a tileable texture like [resize],
+/// letting seams wrap around the borders of the image,
+/// so that the result tiles as well as the original did.
+///
+/// ```no_run
+/// let texture = image::open("./bricks.png").unwrap();
+/// let resized = seamcarving::resize_tileable(&texture, 200, 256);
+/// resized.save("./bricks_200.png");
+/// ```
+#[cfg(feature = "image")]
+pub fn resize_tileable<IMG: GenericImageView>(
+    img: &IMG,
+    width: u32,
+    height: u32,
+) -> ImageBuffer<IMG::Pixel, Vec<<<IMG as GenericImageView>::Pixel as Pixel>::Subpixel>>
+where
+    <IMG as GenericImageView>::Pixel: 'static,
+{
+    resize_with_layers(img, width, height, Layers::default(), true).expect("no pixel is forbidden")
+}
+
+/// Resizes an image like [resize],
+/// but only removes pixels that are not black in the given validity mask.
+///
+/// This is useful for images with an irregular shape, such as stitched panoramas
+/// surrounded by black borders: the valid region is shrunk in a content-aware way,
+/// and the pixels outside of it are ignored in the computation of the energy.
+/// Rows or columns that do not contain any valid pixel can be carved anywhere.
+/// If the valid region is too thin to reach the requested size,
+/// [SeamError::Blocked] is returned.
+#[cfg(feature = "image")]
+pub fn resize_within_region<IMG: GenericImageView, M: GenericImageView<Pixel = Luma<u8>>>(
+    img: &IMG,
+    width: u32,
+    height: u32,
+    valid: &M,
+) -> Result<ImageBufferOf<IMG>, SeamError>
+where
+    <IMG as GenericImageView>::Pixel: 'static,
+{
+    assert_eq!(
+        img.dimensions(),
+        valid.dimensions(),
+        "the mask must have the same dimensions as the image"
+    );
+    let mut layers = Layers::default();
+    layers.set_valid(validity_matrix(valid), max_pos(img));
+    resize_with_layers(img, width, height, layers, false)
+}
+
+/// Resizes an image like [resize],
+/// but spreads the removed seams across the image instead of hollowing out
+/// a single low-energy band.
+///
+/// On every row (and then every column), at most `max_seams` pixels may be removed
+/// within `radius` pixels of any pixel of the original image.
+/// If this makes it impossible to reach the requested size,
+/// [SeamError::Blocked] is returned.
+///
+/// ```no_run
+/// let img = image::open("./my_image.jpg").unwrap();
+/// // Never remove more than 3 pixels out of 11 consecutive ones
+/// let resized = seamcarving::resize_with_max_distortion(&img, 100, 100, 5, 3).unwrap();
+/// resized.save("./resized.jpg");
+/// ```
+#[cfg(feature = "image")]
+pub fn resize_with_max_distortion<IMG: GenericImageView>(
+    img: &IMG,
+    width: u32,
+    height: u32,
+    radius: u32,
+    max_seams: u32,
+) -> Result<ImageBufferOf<IMG>, SeamError>
+where
+    <IMG as GenericImageView>::Pixel: 'static,
+{
+    let mut layers = Layers::default();
+    layers.set_spread(radius, max_seams, max_pos(img));
+    resize_with_layers(img, width, height, layers, false)
+}
+
+#[cfg(feature = "image")]
+fn validity_matrix<M: GenericImageView<Pixel = Luma<u8>>>(mask: &M) -> Matrix<bool> {
+    Matrix::from_fn(max_pos(mask), |x, y| mask.get_pixel(x as u32, y as u32).0[0] != 0)
+}
+
+#[cfg(feature = "image")]
+fn resize_with_layers<IMG: GenericImageView>(
+    img: &IMG,
+    width: u32,
+    height: u32,
+    layers: Layers,
+    wrap: bool,
+) -> Result<ImageBufferOf<IMG>, SeamError>
+where
+    <IMG as GenericImageView>::Pixel: 'static,
+{
+    let limits = Limits::default();
+    let observer = SeamObserver::default();
+    let (resized, _report) =
+        resize_reporting(img, (width, height), layers, (wrap, wrap), false, limits, observer)?;
+    Ok(resized)
+}
+
+/// Conditions under which carving stops before the requested size is reached
+#[cfg(feature = "image")]
+#[derive(Clone, Copy, Default)]
+struct Limits {
+    // Maximal mean energy of the pixels of a removed seam
+    max_seam_energy: Option<u32>,
+    // Time after which no more seams are removed
+    deadline: Option<Instant>,
+    // Whether the image is scaled down to the requested size when out of time
+    scale_when_late: bool,
+    // Number of seams after which the memory used by the removed pixels is freed
+    compact_every: Option<u32>,
+    // Whether the time spent in each step is measured
+    timed: bool,
+    // Maximal total energy of the removed seams
+    energy_budget: Option<u64>,
+    // Fraction of the time the processor is kept busy, sleeping between the seams
+    max_cpu: Option<f32>,
+    // Whether the seams are shown in a window as they are removed
+    #[cfg(feature = "preview")]
+    preview: bool,
+}
+
+#[cfg(feature = "image")]
+impl Limits {
+    fn too_energetic(&self, mean_energy: u32) -> bool {
+        self.max_seam_energy.is_some_and(|max| mean_energy > max)
+    }
+
+    fn over_energy_budget(&self, total_energy: u64) -> bool {
+        self.energy_budget.is_some_and(|budget| total_energy > budget)
+    }
+
+    fn out_of_time(&self) -> bool {
+        self.deadline.is_some_and(|deadline| Instant::now() >= deadline)
+    }
+
+    fn compacts_after(&self, seams: u32) -> bool {
+        self.compact_every.is_some_and(|every| seams.is_multiple_of(every.max(1)))
+    }
+}
+
+/// Callback of [Carver::resize_with_callback]
+#[cfg(feature = "image")]
+type OnSeam<'a> = dyn FnMut(&[Pos], SeamStats) -> ControlFlow<()> + 'a;
+
+/// Where the seams are reported as they are removed
+#[cfg(feature = "image")]
+#[derive(Default)]
+struct SeamObserver<'a> {
+    log: Option<&'a mut SeamLog>,
+    on_seam: Option<&'a mut OnSeam<'a>>,
+    // Whether the callback asked to stop carving
+    stopped: bool,
+}
+
+#[cfg(feature = "image")]
+impl SeamObserver<'_> {
+    /// Reports a seam of the carved image of size `size`, or of the rotated image
+    /// if it is horizontal, just before it is removed
+    fn seam(&mut self, axis: Axis, seam: &[Pos], energy: u32, removed: (u32, u32), size: Pos) {
+        if let Some(log) = self.log.as_mut() {
+            log.push(axis, seam.to_vec())
+        }
+        let Some(on_seam) = self.on_seam.as_mut() else {
+            return;
+        };
+        let (positions, size) = match axis {
+            Axis::Vertical => (Cow::Borrowed(seam), (size.0 - 1, size.1)),
+            Axis::Horizontal => {
+                let positions = seam.iter().map(|&Pos(x, y)| Pos(y, x)).collect();
+                (Cow::Owned(positions), (size.1, size.0 - 1))
+            }
+        };
+        let stats = SeamStats {
+            axis,
+            energy,
+            removed,
+            size,
+        };
+        self.stopped |= on_seam(&positions, stats).is_break();
+    }
+}
+
+/// Carves an image in both directions, stopping early on an axis when one of the limits
+/// is reached, and then scales the image down to the requested size.
+/// The removed seams are reported to `observer`.
+/// `wrap` tells whether vertical and horizontal seams wrap around the edges of the image.
+/// If `blend` is set, the edges of the seams are blended after each pass.
+#[cfg(feature = "image")]
+fn resize_reporting<IMG: GenericImageView>(
+    img: &IMG,
+    (width, height): (u32, u32),
+    layers: Layers,
+    wrap: (bool, bool),
+    blend: bool,
+    limits: Limits,
+    mut observer: SeamObserver,
+) -> Result<(ImageBufferOf<IMG>, Report), SeamError>
 where
     <IMG as GenericImageView>::Pixel: 'static,
 {
     let Pos(to_remove_x, to_remove_y) = max_pos(img) - Pos(width, height);
-    let carved_x = carve(img, to_remove_x);
-    let rotated = Rotated(&carved_x);
-    let carved_y = carve(&rotated, to_remove_y);
-    let re_rotated = Rotated(&carved_y);
-    image_view_to_buffer(&re_rotated)
+    #[cfg(feature = "preview")]
+    let mut preview = limits.preview.then(preview::Preview::default);
+    let mut removed = (0, 0);
+    let carvable_x = carve(img, to_remove_x, layers, wrap.0, limits, |carvable, seam, energy| {
+        #[cfg(feature = "preview")]
+        if let Some(preview) = &mut preview {
+            preview.show(carvable, seam, false);
+        }
+        removed.0 += 1;
+        let size = max_pos(&carvable.carved);
+        observer.seam(Axis::Vertical, seam, energy, removed, size);
+        continue_unless(observer.stopped)
+    })?;
+    let mut timings = carvable_x.timings;
+    let removed_energy_x = carvable_x.removed_energy;
+    let out_of_energy_x = carvable_x.out_of_energy;
+    let wide = timed(&mut timings, |t| &mut t.removal, || {
+        carved_to_buffer(&carvable_x.carved, blend)
+    });
+    let rotated = Rotated(&wide);
+    let layers = carvable_x.layers.transposed(max_pos(&wide));
+    // What is left of the energy budget is spent on the other axis
+    let limits_y = Limits {
+        energy_budget: limits
+            .energy_budget
+            .map(|budget| budget.saturating_sub(removed_energy_x)),
+        ..limits
+    };
+    let to_remove_y = if observer.stopped { 0 } else { to_remove_y };
+    let wrap = wrap.1;
+    let carvable_y = carve(&rotated, to_remove_y, layers, wrap, limits_y, |carvable, seam, energy| {
+        #[cfg(feature = "preview")]
+        if let Some(preview) = &mut preview {
+            preview.show(carvable, seam, true);
+        }
+        removed.1 += 1;
+        let size = max_pos(&carvable.carved);
+        observer.seam(Axis::Horizontal, seam, energy, removed, size);
+        continue_unless(observer.stopped)
+    })?;
+    if let (Some(timings), Some(timings_y)) = (&mut timings, carvable_y.timings) {
+        *timings += timings_y;
+    }
+    let re_rotated = timed(&mut timings, |t| &mut t.removal, || {
+        image_view_to_buffer(&Rotated(&carved_to_buffer(&carvable_y.carved, blend)))
+    });
+    let seams = max_pos(img) - max_pos(&re_rotated);
+    let unfinished = seams.0 < to_remove_x || seams.1 < to_remove_y;
+    let out_of_time = unfinished && limits.out_of_time();
+    let out_of_energy = out_of_energy_x || carvable_y.out_of_energy;
+    let stopped = observer.stopped;
+    let (resized, scaled) = if (out_of_time && !limits.scale_when_late) || out_of_energy || stopped
+    {
+        (re_rotated, (0, 0))
+    } else {
+        scale_down(re_rotated, width, height)
+    };
+    let report = Report {
+        seams: (seams.0, seams.1),
+        scaled,
+        out_of_time,
+        removed_energy: removed_energy_x + carvable_y.removed_energy,
+        out_of_energy,
+        size: resized.dimensions(),
+        requested: (width, height),
+        stopped,
+        too_dissimilar: false,
+        striped: false,
+        timings,
+    };
+    Ok((resized, report))
 }
 
+#[cfg(feature = "image")]
+fn continue_unless(stop: bool) -> ControlFlow<()> {
+    if stop {
+        ControlFlow::Break(())
+    } else {
+        ControlFlow::Continue(())
+    }
+}
+
+/// Resizes an image like [resize_reporting], but instead of removing all the vertical seams
+/// first, removes at each step the cheapest of the best vertical and horizontal seams.
+/// If `blend` is set, the edges of each seam are blended as soon as it is removed.
+#[cfg(feature = "image")]
+fn resize_greedy<IMG: GenericImageView>(
+    img: &IMG,
+    (width, height): (u32, u32),
+    mut layers: Layers,
+    wrap: (bool, bool),
+    blend: bool,
+    limits: Limits,
+    mut observer: SeamObserver,
+) -> Result<(ImageBufferOf<IMG>, Report), SeamError>
+where
+    <IMG as GenericImageView>::Pixel: 'static,
+{
+    let mut current = image_view_to_buffer(img);
+    let mut seams = (0, 0);
+    let mut timings = limits.timed.then(Timings::default);
+    let (mut removed_energy, mut out_of_energy) = (0, false);
+    let mut pacer = limits.max_cpu.map(Pacer::new);
+    loop {
+        if let Some(pacer) = &mut pacer {
+            pacer.pause();
+        }
+        if limits.out_of_time() {
+            break;
+        }
+        let size = max_pos(&current);
+        let vertical = if size.0 > width {
+            cheapest_seam(&current, layers.clone(), wrap.0, &mut timings)
+        } else {
+            Err(SeamError::Blocked)
+        };
+        let horizontal = if size.1 > height {
+            let transposed = layers.clone().transposed(size);
+            cheapest_seam(&Rotated(&current), transposed, wrap.1, &mut timings)
+        } else {
+            Err(SeamError::Blocked)
+        };
+        let mean = |(seam, energy): &(Vec<Pos>, u32)| energy / (seam.len() as u32).max(1);
+        let (axis, (seam, energy)) = match (vertical, horizontal) {
+            (Ok(v), Ok(h)) if mean(&v) <= mean(&h) => (Axis::Vertical, v),
+            (_, Ok(h)) => (Axis::Horizontal, h),
+            (Ok(v), Err(_)) => (Axis::Vertical, v),
+            (Err(e), Err(_)) if size.0 > width || size.1 > height => return Err(e),
+            (Err(_), Err(_)) => break,
+        };
+        if limits.too_energetic(energy / (seam.len() as u32).max(1)) {
+            break;
+        }
+        if limits.over_energy_budget(removed_energy + u64::from(energy)) {
+            out_of_energy = true;
+            break;
+        }
+        removed_energy += u64::from(energy);
+        match axis {
+            Axis::Vertical => seams.0 += 1,
+            Axis::Horizontal => seams.1 += 1,
+        }
+        let rotated_size = match axis {
+            Axis::Vertical => size,
+            Axis::Horizontal => Pos(size.1, size.0),
+        };
+        observer.seam(axis, &seam, energy, seams, rotated_size);
+        let start = Instant::now();
+        current = match axis {
+            Axis::Vertical => {
+                let mut carved = Carved::new(&current);
+                carved.remove_seam(&seam);
+                layers.remove_seam(&seam);
+                carved_to_buffer(&carved, blend)
+            }
+            Axis::Horizontal => {
+                let rotated = Rotated(&current);
+                let mut carved = Carved::new(&rotated);
+                carved.remove_seam(&seam);
+                let mut transposed = layers.transposed(size);
+                transposed.remove_seam(&seam);
+                layers = transposed.transposed(Pos(size.1 - 1, size.0));
+                image_view_to_buffer(&Rotated(&carved_to_buffer(&carved, blend)))
+            }
+        };
+        if let Some(timings) = &mut timings {
+            timings.removal += start.elapsed();
+        }
+        // The image is copied at each step, only the layers keep removed pixels
+        if limits.compacts_after(seams.0 + seams.1) {
+            layers.shrink_to_fit();
+        }
+        if observer.stopped {
+            break;
+        }
+    }
+    let unfinished = current.width() > width || current.height() > height;
+    let out_of_time = unfinished && limits.out_of_time();
+    let stopped = observer.stopped;
+    let (resized, scaled) = if (out_of_time && !limits.scale_when_late) || out_of_energy || stopped
+    {
+        (current, (0, 0))
+    } else {
+        scale_down(current, width, height)
+    };
+    let report = Report {
+        seams,
+        scaled,
+        out_of_time,
+        removed_energy,
+        out_of_energy,
+        size: resized.dimensions(),
+        requested: (width, height),
+        stopped,
+        too_dissimilar: false,
+        striped: false,
+        timings,
+    };
+    Ok((resized, report))
+}
+
+/// The vertical seam with the lowest energy, and its total energy.
+/// The time spent finding it is added to `timings`.
+#[cfg(feature = "image")]
+fn cheapest_seam<IMG: GenericImageView>(
+    img: &IMG,
+    layers: Layers,
+    wrap: bool,
+    timings: &mut Option<Timings>,
+) -> Result<(Vec<Pos>, u32), SeamError>
+where
+    <IMG as GenericImageView>::Pixel: 'static,
+{
+    let mut carvable = Carvable::new(img);
+    carvable.set_layers(layers);
+    carvable.set_wrapping(wrap);
+    carvable.timings = timings.take();
+    let found = carvable.find_next_seam();
+    *timings = carvable.timings;
+    found
+}
+
+/// Scales an image down until it is at most as large as the given size,
+/// and returns the number of columns and rows that were removed
+#[cfg(feature = "image")]
+fn scale_down<P: Pixel + 'static>(
+    img: PixelBuffer<P>,
+    width: u32,
+    height: u32,
+) -> (PixelBuffer<P>, (u32, u32)) {
+    let Pos(dx, dy) = max_pos(&img) - Pos(width, height);
+    if dx == 0 && dy == 0 {
+        return (img, (0, 0));
+    }
+    let (w, h) = (img.width() - dx, img.height() - dy);
+    let scaled = image::imageops::resize(&img, w, h, image::imageops::FilterType::Lanczos3);
+    (scaled, (dx, dy))
+}
+
+/// The type of image buffer that can hold the pixels of an IMG
+#[cfg(feature = "image")]
+type ImageBufferOf<IMG> = ImageBuffer<
+    <IMG as GenericImageView>::Pixel,
+    Vec<<<IMG as GenericImageView>::Pixel as Pixel>::Subpixel>,
+>;
+
+/// The type of image buffer that can hold pixels of type P
+#[cfg(feature = "image")]
+type PixelBuffer<P> = ImageBuffer<P, Vec<<P as Pixel>::Subpixel>>;
+
+#[cfg(feature = "image")]
 fn max_pos<IMG: GenericImageView>(img: &IMG) -> Pos {
     Pos(img.width(), img.height())
 }
 
+/// The seams removed from a [Carvable] as they are iterated over, see [Carvable::seams]
+#[cfg(feature = "image")]
+pub struct Seams<'c, 'a, IMG: GenericImageView>
+where
+    <IMG as GenericImageView>::Pixel: 'a,
+{
+    carvable: &'c mut Carvable<'a, IMG>,
+}
+
+#[cfg(feature = "image")]
+impl<IMG: GenericImageView> Iterator for Seams<'_, '_, IMG> {
+    type Item = Seam;
+
+    fn next(&mut self) -> Option<Seam> {
+        self.carvable.try_remove_seam_returning().ok()
+    }
+}
+
 /// A structure that allows removing vertical seams of content
 /// from an image
+#[cfg(feature = "image")]
 pub struct Carvable<'a, IMG: GenericImageView>
 where
     <IMG as GenericImageView>::Pixel: 'a,
 {
     carved: Carved<'a, IMG>,
     seam_finder: SeamFinder,
+    // Energies and validity of the pixels of the carved image
+    layers: Layers,
+    // Whether seams wrap around the left and right borders
+    wrap: bool,
+    // Time spent in each step, if it is measured
+    timings: Option<Timings>,
+    // Total energy of the seams removed by carve
+    removed_energy: u64,
+    // Whether carve stopped because of the energy budget
+    out_of_energy: bool,
 }
 
+#[cfg(feature = "image")]
 impl<'a, IMG: GenericImageView> Carvable<'a, IMG> {
     /// Creates a new proxy object that will allow reducing an image width.
     /// Notice that it does not take a mutable pointer.
@@ -74,14 +927,330 @@ impl<'a, IMG: GenericImageView> Carvable<'a, IMG> {
         Carvable {
             carved,
             seam_finder,
+            layers: Layers::default(),
+            wrap: false,
+            timings: None,
+            removed_energy: 0,
+            out_of_energy: false,
         }
     }
+    /// Computes the energy of the pixels with the given layers,
+    /// and stores it the way they require
+    fn set_layers(&mut self, layers: Layers) {
+        self.seam_finder.set_quantized(layers.quantized);
+        self.layers = layers;
+    }
+    /// Lets seams cross the left and right borders of the image,
+    /// for textures that tile horizontally.
+    /// The energy of the pixels on the borders is then computed as if the image
+    /// was repeated horizontally, so the carved image tiles too.
+    pub fn set_wrapping(&mut self, wrap: bool) {
+        self.wrap = wrap;
+        self.seam_finder.set_wrapping(wrap);
+    }
+    /// Makes seams avoid the pixels that are not black in the given mask.
+    /// The mask must have the same dimensions as the current carved image.
+    /// Gray levels can protect pixels more or less with [ImportanceMap::from_soft_mask].
+    pub fn protect<M: GenericImageView<Pixel = Luma<u8>>>(&mut self, mask: &M) {
+        self.add_importance(&ImportanceMap::from_mask(mask));
+    }
+    /// Forbids seams from going through the pixels that are not black in the given mask.
+    /// The mask must have the same dimensions as the current carved image.
+    pub fn forbid<M: GenericImageView<Pixel = Luma<u8>>>(&mut self, mask: &M) {
+        self.add_importance(&ImportanceMap::forbidden(mask));
+    }
+    /// Only lets seams go through the pixels that are not black in the given mask,
+    /// see [resize_within_region].
+    /// The mask must have the same dimensions as the current carved image.
+    pub fn set_valid_region<M: GenericImageView<Pixel = Luma<u8>>>(&mut self, valid: &M) {
+        let size = max_pos(&self.carved);
+        assert_eq!(
+            (size.0, size.1),
+            valid.dimensions(),
+            "the mask must have the same dimensions as the image"
+        );
+        self.layers.set_valid(validity_matrix(valid), size);
+        self.seam_finder.invalidate();
+    }
+    /// Limits the distortion of the image: from now on, at most `max_seams` pixels
+    /// may be removed on each row within `radius` columns of any pixel,
+    /// see [resize_with_max_distortion].
+    pub fn set_max_local_removal(&mut self, radius: u32, max_seams: u32) {
+        self.layers.set_spread(radius, max_seams, max_pos(&self.carved));
+        self.seam_finder.invalidate();
+    }
+    /// Adds the given importance to the energy of the pixels of the image.
+    /// The importance map must have the same dimensions as the current carved image.
+    pub fn add_importance(&mut self, importance: &ImportanceMap) {
+        let size = max_pos(&self.carved);
+        assert_eq!(
+            (size.0, size.1),
+            importance.dimensions(),
+            "the importance map must have the same dimensions as the image"
+        );
+        let bias = &importance.bias;
+        self.layers.bias = Some(match self.layers.bias.take() {
+            Some(current) => Matrix::from_fn(size, |x, y| {
+                let pos = Pos(x as u32, y as u32);
+                combine(current[pos], bias[pos])
+            }),
+            None => bias.clone(),
+        });
+        // Cached seam energies are not valid anymore
+        self.seam_finder.invalidate();
+    }
+    /// Forgets the cached seams that may go through the given rectangle of the original image,
+    /// after its pixels were edited, for instance through a view that reads shared pixels.
+    /// The seams above the rectangle, and the ones too far from it to go through it,
+    /// are not computed again.
+    /// Pixels of the rectangle that were already removed are ignored.
+    pub fn mark_dirty(&mut self, rect: Rect) {
+        let (width, height) = self.carved.dimensions();
+        if width == 0 || height == 0 {
+            // Every pixel was already removed, along with the seams going through them
+            return;
+        }
+        if self.layers.wraps_borders() {
+            // The pixels of the opposite borders may be compared with the edited ones
+            return self.seam_finder.invalidate();
+        }
+        let w = i64::from(width);
+        // The energy of a pixel depends on the pixels around it
+        let rows = rect.y.saturating_sub(1)..rect.y.saturating_add(rect.height).saturating_add(1);
+        let columns = rect.x..rect.x.saturating_add(rect.width);
+        // Columns of the row above whose seams changed, beyond the borders when wrapping
+        let mut cone: Option<(i64, i64)> = None;
+        let mut dirty = Vec::new();
+        for y in rows.start..height {
+            let edited = match rows.contains(&y) {
+                true => self.carved.columns_from(y, columns.clone()),
+                false => 0..0,
+            };
+            let edited = Some(edited)
+                .filter(|edited| !edited.is_empty())
+                .map(|edited| (i64::from(edited.start) - 1, i64::from(edited.end) + 1));
+            // Seams can go one column further on each row
+            let below = cone.map(|(start, end)| (start - 1, end + 1));
+            cone = match (below, edited) {
+                (Some(a), Some(b)) => Some((a.0.min(b.0), a.1.max(b.1))),
+                (a, b) => a.or(b),
+            };
+            if !self.wrap {
+                cone = cone.map(|(start, end)| (start.max(0), end.min(w)));
+            }
+            let Some((start, end)) = cone else {
+                continue;
+            };
+            let columns = (start..end).take(width as usize);
+            dirty.extend(columns.map(|x| Pos(x.rem_euclid(w) as u32, y)));
+        }
+        self.seam_finder.invalidate_positions(dirty);
+    }
+    /// Carves another image of the same dimensions from now on, such as an edited copy
+    /// of the original image that only differs from it inside `edited`,
+    /// reusing the seams computed elsewhere, see [mark_dirty](Carvable::mark_dirty).
+    /// The seams removed so far are removed from the new image too.
+    ///
+    /// ```
+    /// use image::GenericImageView;
+    /// use seamcarving::{Carvable, Rect};
+    /// let img = image::GrayImage::from_fn(12, 8, |x, y| image::Luma([(x * y * 3) as u8]));
+    /// let mut edited = img.clone();
+    /// edited.put_pixel(5, 5, image::Luma([0]));
+    /// let mut carvable = Carvable::new(&img);
+    /// carvable.remove_seam();
+    /// carvable.replace_image(&edited, Rect { x: 5, y: 5, width: 1, height: 1 });
+    /// carvable.remove_seam();
+    /// assert_eq!(carvable.result().width(), 10);
+    /// ```
+    pub fn replace_image(&mut self, img: &'a IMG, edited: Rect) {
+        self.carved.set_image(img);
+        self.mark_dirty(edited);
+    }
     /// Removes a vertical seam from the image,
     /// diminishing its width by 1.
+    ///
+    /// # Panics
+    ///
+    /// Panics if every seam goes through a [forbidden](Carvable::forbid) pixel.
+    /// Use [try_remove_seam](Carvable::try_remove_seam) to handle that case.
     pub fn remove_seam(&mut self) {
-        let img = &self.carved;
-        let seam = self.seam_finder.extract_seam(|p| energy_fn(img, p));
-        self.carved.remove_seam(&seam);
+        if let Err(e) = self.try_remove_seam() {
+            panic!("unable to remove a seam: {}", e)
+        }
+    }
+    /// Removes a vertical seam from the image, diminishing its width by 1,
+    /// or returns [SeamError::Blocked] if every seam goes through a forbidden pixel,
+    /// and [SeamError::Empty] if the image has no column left.
+    pub fn try_remove_seam(&mut self) -> Result<(), SeamError> {
+        self.remove_next_seam().map(|_seam| ())
+    }
+    /// Removes a vertical seam like [try_remove_seam](Carvable::try_remove_seam),
+    /// and returns it, in the coordinates of the image before the removal.
+    ///
+    /// ```
+    /// use seamcarving::Carvable;
+    /// let img = image::GrayImage::from_fn(4, 3, |x, _y| image::Luma([(x * x * 20) as u8]));
+    /// let mut carvable = Carvable::new(&img);
+    /// let seam = carvable.try_remove_seam_returning().unwrap();
+    /// assert_eq!(seam.len(), 3);
+    /// assert_eq!(seam.sorted_top_down()[0].1, 0);
+    /// ```
+    pub fn try_remove_seam_returning(&mut self) -> Result<Seam, SeamError> {
+        self.remove_next_seam().map(Seam::new)
+    }
+    /// Removes a vertical seam
+    /// like [try_remove_seam_returning](Carvable::try_remove_seam_returning),
+    /// and also returns the energy of the seam on each of its rows, from the bottom to the top,
+    /// to plot where it went through expensive content,
+    /// see [SeamFinder::extract_seam_with_profile].
+    ///
+    /// ```
+    /// use seamcarving::Carvable;
+    /// let img = image::GrayImage::from_fn(6, 4, |x, y| image::Luma([(x * y * 9) as u8]));
+    /// let mut carvable = Carvable::new(&img);
+    /// let (seam, profile) = carvable.try_remove_seam_with_profile().unwrap();
+    /// assert_eq!(profile.len(), seam.len());
+    /// ```
+    pub fn try_remove_seam_with_profile(&mut self) -> Result<(Seam, Vec<u32>), SeamError> {
+        let (img, layers, wrap) = (&self.carved, &self.layers, self.wrap);
+        let (seam, profile) = self
+            .seam_finder
+            .extract_seam_with_profile(|p| layers.energy(img, p, wrap))?;
+        self.apply_seam(&seam);
+        Ok((Seam::new(seam), profile))
+    }
+    /// Removes vertical seams one after the other as the returned iterator is advanced,
+    /// yielding each of them like [try_remove_seam_returning](Carvable::try_remove_seam_returning).
+    /// The iteration ends when no seam can be removed anymore,
+    /// because the image has no column left or every seam goes through a forbidden pixel.
+    ///
+    /// The iterator is [Send] when the image is [Sync], so that seams can be found
+    /// on one thread while another one applies or encodes the previous ones:
+    ///
+    /// ```
+    /// use std::sync::mpsc;
+    /// use image::GenericImageView;
+    /// use seamcarving::Carvable;
+    /// let img = image::GrayImage::from_fn(40, 20, |x, y| image::Luma([(x * y) as u8]));
+    /// let mut carvable = Carvable::new(&img);
+    /// let (sender, receiver) = mpsc::sync_channel(4);
+    /// let seams = carvable.seams().take(10);
+    /// let encoded: usize = std::thread::scope(|scope| {
+    ///     scope.spawn(move || seams.for_each(|seam| sender.send(seam).unwrap()));
+    ///     receiver.iter().map(|seam| seam.len()).sum()
+    /// });
+    /// assert_eq!(encoded, 10 * 20);
+    /// assert_eq!(carvable.result().width(), 30);
+    /// ```
+    pub fn seams(&mut self) -> Seams<'_, 'a, IMG> {
+        Seams { carvable: self }
+    }
+    /// Finds the `count` seams that would be removed next, without removing them.
+    ///
+    /// The seams are disjoint, and returned in the order they would be removed,
+    /// in the coordinates of the current carved image.
+    /// The cached seam energies are reused, but left untouched.
+    /// Fails if fewer than `count` seams can be removed.
+    pub fn peek_seams(&self, count: u32) -> Result<Vec<Seam>, SeamError> {
+        let mut lookahead = Carvable::new(&self.carved);
+        lookahead.seam_finder = self.seam_finder.clone();
+        lookahead.layers = self.layers.clone();
+        lookahead.wrap = self.wrap;
+        (0..count)
+            .map(|_| {
+                let (seam, _energy) = lookahead.find_next_seam()?;
+                // The lookahead image does not contain the seams found before
+                let current = seam.iter().map(|&pos| lookahead.carved.transform_pos(pos));
+                let current = Seam::new(current.collect());
+                lookahead.apply_seam(&seam);
+                Ok(current)
+            })
+            .collect()
+    }
+    /// Computes the energies of all the seams of the current carved image,
+    /// and returns the dynamic programming table from which the next seam will be extracted
+    ///
+    /// ```
+    /// use seamcarving::{Carvable, Pos};
+    /// let img = image::GrayImage::from_fn(4, 3, |x, _y| image::Luma([(x * x * 20) as u8]));
+    /// let mut carvable = Carvable::new(&img);
+    /// let table = carvable.seam_table();
+    /// println!("{}", table.cumulative_energies().to_ascii_heatmap());
+    /// assert_eq!(table.predecessor(Pos(0, 0)), None);
+    /// ```
+    pub fn seam_table(&mut self) -> SeamTable<'_> {
+        let (img, layers, wrap) = (&self.carved, &self.layers, self.wrap);
+        self.seam_finder.table(|p| layers.energy(img, p, wrap))
+    }
+    /// Frees the memory used by the pixels removed so far.
+    ///
+    /// Removing seams does not release any memory by itself: this can be called
+    /// periodically to bound the memory used while carving many seams.
+    pub fn shrink_to_fit(&mut self) {
+        self.carved.shrink_to_fit();
+        self.seam_finder.shrink_to_fit();
+        self.layers.shrink_to_fit();
+    }
+    /// Removes the vertical seam with the lowest energy, and returns it
+    pub(crate) fn remove_next_seam(&mut self) -> Result<Vec<Pos>, SeamError> {
+        let (seam, _energy) = self.find_next_seam()?;
+        self.apply_seam(&seam);
+        Ok(seam)
+    }
+    /// Finds the vertical seam with the lowest energy, and its total energy.
+    /// The seam is forgotten by the seam finder, so it must be applied next.
+    fn find_next_seam(&mut self) -> Result<(Vec<Pos>, u32), SeamError> {
+        let (img, layers, wrap) = (&self.carved, &self.layers, self.wrap);
+        let energy = |p| layers.energy(img, p, wrap);
+        let Some(timings) = &mut self.timings else {
+            return self.seam_finder.extract_seam_with_energy(energy);
+        };
+        let mut energy_time = Duration::ZERO;
+        let start = Instant::now();
+        self.seam_finder.fill(|p| {
+            let start = Instant::now();
+            let e = energy(p);
+            energy_time += start.elapsed();
+            e
+        });
+        let filled = Instant::now();
+        // Everything is cached, only the backtracking is left
+        let seam = self.seam_finder.extract_seam_with_energy(energy);
+        timings.energy += energy_time;
+        timings.fill += (filled - start).saturating_sub(energy_time);
+        timings.backtrack += filled.elapsed();
+        seam
+    }
+    /// Removes the vertical seam with the lowest energy among the ones
+    /// that go through all the given (x, y) positions of the carved image.
+    ///
+    /// At most one anchor is allowed per row. If no seam can go through all the anchors,
+    /// an error is returned and the image is left untouched.
+    pub fn remove_seam_through(&mut self, anchors: &[(u32, u32)]) -> Result<(), SeamError> {
+        let size = max_pos(&self.carved);
+        let anchors: Vec<Pos> = anchors.iter().map(|&anchor| Pos::from(anchor)).collect();
+        if let Some(&Pos(x, y)) = anchors.iter().find(|p| !p.is_inside(size)) {
+            return Err(SeamError::OutOfBounds { x, y });
+        }
+        let (img, layers, wrap) = (&self.carved, &self.layers, self.wrap);
+        let seam = self
+            .seam_finder
+            .extract_seam_through(|p| layers.energy(img, p, wrap), &anchors)
+            .ok_or(SeamError::UnreachableAnchors)?;
+        self.apply_seam(&seam);
+        Ok(())
+    }
+    fn apply_seam(&mut self, seam: &[Pos]) {
+        span!(TRACE, "remove_seam", height = seam.len());
+        let start = self.timings.map(|_| Instant::now());
+        self.carved.remove_seam(seam);
+        if self.layers.remove_seam(seam) {
+            self.seam_finder.invalidate();
+        }
+        if let (Some(timings), Some(start)) = (&mut self.timings, start) {
+            timings.removal += start.elapsed();
+        }
     }
     /// Get the resulting carved image
     pub fn result(&self) -> &Carved<'a, IMG> {
@@ -91,6 +1260,7 @@ impl<'a, IMG: GenericImageView> Carvable<'a, IMG> {
 
 /// Converts [GenericImageView](GenericImageView)
 /// to an [ImageBuffer](ImageBuffer)
+#[cfg(feature = "image")]
 pub fn image_view_to_buffer<IMG: GenericImageView>(
     img: &IMG,
 ) -> ImageBuffer<IMG::Pixel, Vec<<<IMG as GenericImageView>::Pixel as Pixel>::Subpixel>>
@@ -101,22 +1271,84 @@ where
     ImageBuffer::from_fn(w, h, |x, y| img.get_pixel(x, y))
 }
 
-fn carve<IMG: GenericImageView>(img: &IMG, pixel_count: u32) -> Carved<IMG>
+/// Runs `f`, adding the time it took to the field of `timings` selected by `field`,
+/// if timings are measured
+#[cfg(feature = "image")]
+fn timed<T>(
+    timings: &mut Option<Timings>,
+    field: impl FnOnce(&mut Timings) -> &mut Duration,
+    f: impl FnOnce() -> T,
+) -> T {
+    let Some(timings) = timings else {
+        return f();
+    };
+    let start = Instant::now();
+    let result = f();
+    *field(timings) += start.elapsed();
+    result
+}
+
+/// Removes up to `pixel_count` vertical seams,
+/// calling `record` with each seam and its energy just before it is removed.
+/// Carving stops after the seam for which `record` breaks.
+#[cfg(feature = "image")]
+fn carve<'a, IMG: GenericImageView>(
+    img: &'a IMG,
+    pixel_count: u32,
+    layers: Layers,
+    wrap: bool,
+    limits: Limits,
+    mut record: impl FnMut(&Carvable<'a, IMG>, &[Pos], u32) -> ControlFlow<()>,
+) -> Result<Carvable<'a, IMG>, SeamError>
 where
     <IMG as GenericImageView>::Pixel: 'static,
 {
+    span!(DEBUG, "carve", width = img.width(), height = img.height(), seams = pixel_count);
     let mut carvable = Carvable::new(img);
-    (0..pixel_count).for_each(|_| carvable.remove_seam());
-    carvable.carved
+    carvable.set_layers(layers);
+    carvable.set_wrapping(wrap);
+    carvable.timings = limits.timed.then(Timings::default);
+    let mut pacer = limits.max_cpu.map(Pacer::new);
+    for removed in 1..=pixel_count {
+        if let Some(pacer) = &mut pacer {
+            pacer.pause();
+        }
+        if limits.out_of_time() {
+            break;
+        }
+        let (seam, energy) = carvable.find_next_seam()?;
+        let mean_energy = energy / (seam.len() as u32).max(1);
+        if limits.too_energetic(mean_energy) {
+            // The finder is not used anymore, so it does not matter that it lost the seam
+            break;
+        }
+        let removed_energy = carvable.removed_energy + u64::from(energy);
+        if limits.over_energy_budget(removed_energy) {
+            carvable.out_of_energy = true;
+            break;
+        }
+        carvable.removed_energy = removed_energy;
+        let flow = record(&carvable, &seam, energy);
+        carvable.apply_seam(&seam);
+        if limits.compacts_after(removed) {
+            carvable.shrink_to_fit();
+        }
+        if flow.is_break() {
+            break;
+        }
+    }
+    Ok(carvable)
 }
 
-#[cfg(test)]
+#[cfg(all(test, feature = "image"))]
 mod tests {
-    use image::{GrayImage, ImageBuffer, Luma};
+    use image::{GenericImageView, GrayImage, ImageBuffer, Luma};
 
-    use crate::{energy_fn, Pos};
+    use crate::energy::energy_fn;
+    use crate::Pos;
 
     #[test]
+    #[allow(clippy::identity_op)]
     fn energy_fn_correct() {
         let img = GrayImage::from_raw(3, 2, vec![3, 1, 4, 1, 5, 9]).unwrap();
         let energy = ImageBuffer::from_fn(3, 2, |x, y| Luma([energy_fn(&img, Pos(x, y))]));
@@ -130,4 +1362,18 @@ mod tests {
         ];
         assert_eq!(energy.into_raw(), expected);
     }
+
+    #[test]
+    fn iterates_over_seams_until_none_is_left() {
+        fn assert_send<T: Send>(_: &T) {}
+        let img = GrayImage::from_fn(6, 4, |x, y| Luma([(x * x + y) as u8]));
+        let mut carvable = crate::Carvable::new(&img);
+        let first = carvable.peek_seams(1).unwrap().remove(0);
+        let seams = carvable.seams();
+        assert_send(&seams);
+        let seams: Vec<_> = seams.collect();
+        assert_eq!(seams.len(), 6);
+        assert_eq!(seams[0], first);
+        assert_eq!(carvable.result().width(), 0);
+    }
 }
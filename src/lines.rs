@@ -0,0 +1,142 @@
+use image::GenericImageView;
+
+use crate::energy::energy_fn;
+use crate::importance::ImportanceMap;
+use crate::matrix::Matrix;
+use crate::max_pos;
+use crate::pos::Pos;
+
+/// Number of angles tested by the Hough transform, covering half a turn
+const ANGLES: usize = 180;
+
+/// Parameters of the detection of straight lines,
+/// used by [ImportanceMap::lines](crate::ImportanceMap::lines)
+#[derive(Clone, Debug, PartialEq)]
+pub struct LineOptions {
+    /// Minimal energy of a pixel for it to be considered part of an edge
+    pub edge_threshold: u32,
+    /// Minimal number of edge pixels a line has to go through to be detected
+    pub min_votes: u32,
+    /// Maximal number of lines to protect, starting with the most visible ones
+    pub max_lines: usize,
+    /// Maximal distance in pixels from a line for a pixel to be protected
+    pub thickness: u32,
+    /// Importance added to the pixels along the lines
+    pub importance: u32,
+}
+
+impl Default for LineOptions {
+    fn default() -> Self {
+        LineOptions {
+            edge_threshold: 2000,
+            min_votes: 50,
+            max_lines: 8,
+            thickness: 1,
+            importance: 1 << 16,
+        }
+    }
+}
+
+/// A straight line in Hough space:
+/// the points (x, y) such that `x cos(angle) + y sin(angle) = distance`
+#[derive(Clone, Copy, Debug)]
+struct Line {
+    angle: usize,
+    distance: i32,
+}
+
+impl Line {
+    fn distance_to(&self, Pos(x, y): Pos) -> f32 {
+        let (sin, cos) = angle_radians(self.angle).sin_cos();
+        (x as f32 * cos + y as f32 * sin - self.distance as f32).abs()
+    }
+}
+
+fn angle_radians(angle: usize) -> f32 {
+    angle as f32 * std::f32::consts::PI / ANGLES as f32
+}
+
+/// Finds the dominant straight lines of an image with a Hough transform
+fn detect_lines<IMG: GenericImageView>(img: &IMG, options: &LineOptions) -> Vec<Line> {
+    let size = max_pos(img);
+    let max_distance = ((size.0 as f32).hypot(size.1 as f32)).ceil() as i32;
+    let distances = 2 * max_distance as usize + 1;
+    let trig: Vec<(f32, f32)> = (0..ANGLES).map(|a| angle_radians(a).sin_cos()).collect();
+    let mut votes = vec![0u32; ANGLES * distances];
+    Pos::iter_in_rect(Pos(0, 0), size)
+        .filter(|&p| energy_fn(img, p) >= options.edge_threshold)
+        .for_each(|Pos(x, y)| {
+            for (angle, &(sin, cos)) in trig.iter().enumerate() {
+                let distance = (x as f32 * cos + y as f32 * sin).round() as i32;
+                votes[angle * distances + (distance + max_distance) as usize] += 1;
+            }
+        });
+
+    let mut candidates: Vec<usize> = (0..votes.len())
+        .filter(|&i| votes[i] >= options.min_votes)
+        .collect();
+    candidates.sort_by_key(|&i| std::cmp::Reverse(votes[i]));
+    let mut lines: Vec<Line> = Vec::with_capacity(options.max_lines);
+    for i in candidates {
+        if lines.len() >= options.max_lines {
+            break;
+        }
+        let line = Line {
+            angle: i / distances,
+            distance: (i % distances) as i32 - max_distance,
+        };
+        // Neighbouring cells of the accumulator describe the same physical line
+        let is_duplicate = lines.iter().any(|l| {
+            let angle_diff = (l.angle as i32 - line.angle as i32).abs();
+            let angle_diff = angle_diff.min(ANGLES as i32 - angle_diff);
+            angle_diff <= 2 && (l.distance - line.distance).abs() <= 2
+        });
+        if !is_duplicate {
+            lines.push(line);
+        }
+    }
+    lines
+}
+
+impl ImportanceMap {
+    /// Detects the dominant straight lines in the image (horizons, architectural edges, poles...)
+    /// and protects the pixels along them, so that seams do not kink them.
+    pub fn lines<IMG: GenericImageView>(img: &IMG, options: &LineOptions) -> Self {
+        let size = max_pos(img);
+        let lines = detect_lines(img, options);
+        let thickness = options.thickness as f32 + 0.5;
+        let bias = Matrix::from_fn(size, |x, y| {
+            let pos = Pos(x as u32, y as u32);
+            if lines.iter().any(|l| l.distance_to(pos) < thickness) {
+                options.importance
+            } else {
+                0
+            }
+        });
+        ImportanceMap::from_bias(size, bias)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use image::{GrayImage, Luma};
+
+    use crate::pos::Pos;
+    use crate::{ImportanceMap, LineOptions};
+
+    #[test]
+    fn protects_horizon() {
+        // A bright sky above a dark ground
+        let img = GrayImage::from_fn(100, 40, |_x, y| Luma([if y < 20 { 200 } else { 10 }]));
+        let options = LineOptions {
+            thickness: 0,
+            ..LineOptions::default()
+        };
+        let importance = ImportanceMap::lines(&img, &options);
+        for x in 0..100 {
+            assert_eq!(importance.bias[Pos(x, 0)], 0);
+            assert_eq!(importance.bias[Pos(x, 19)], options.importance);
+            assert_eq!(importance.bias[Pos(x, 30)], 0);
+        }
+    }
+}
@@ -0,0 +1,205 @@
+use alloc::vec::Vec;
+
+#[cfg(feature = "image")]
+use image::{GenericImageView, GrayImage, Luma};
+
+use crate::matrix::Matrix;
+#[cfg(feature = "image")]
+use crate::max_pos;
+use crate::pos::Pos;
+
+/// Energy added to every protected pixel.
+///
+/// It is larger than the energy of any pixel in an 8-bit RGBA image,
+/// but small enough for a seam to cross thousands of protected pixels
+/// before its total energy saturates.
+pub(crate) const PROTECTED_ENERGY: u32 = 1 << 20;
+
+/// Builds an energy bias from a mask, in which every non-zero pixel is protected
+#[cfg(feature = "image")]
+pub(crate) fn mask_bias<M: GenericImageView<Pixel = Luma<u8>>>(mask: &M) -> Matrix<u32> {
+    Matrix::from_fn(max_pos(mask), |x, y| {
+        if mask.get_pixel(x as u32, y as u32).0[0] == 0 {
+            0
+        } else {
+            PROTECTED_ENERGY
+        }
+    })
+}
+
+/// An axis-aligned rectangle in image coordinates
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct Rect {
+    /// Horizontal coordinate of the left side of the rectangle
+    pub x: u32,
+    /// Vertical coordinate of the top side of the rectangle
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Rect {
+    /// Returns the same rectangle, grown by `margin` pixels on every side,
+    /// and clipped to an image of the given size
+    pub fn grow(self, margin: u32, (width, height): (u32, u32)) -> Rect {
+        let x = self.x.saturating_sub(margin);
+        let y = self.y.saturating_sub(margin);
+        let right = self.x.saturating_add(self.width).saturating_add(margin).min(width);
+        let bottom = self.y.saturating_add(self.height).saturating_add(margin).min(height);
+        Rect {
+            x,
+            y,
+            width: right.saturating_sub(x),
+            height: bottom.saturating_sub(y),
+        }
+    }
+
+    /// Whether the given pixel coordinates are inside the rectangle
+    #[inline]
+    pub fn contains(&self, x: u32, y: u32) -> bool {
+        x >= self.x && x - self.x < self.width && y >= self.y && y - self.y < self.height
+    }
+}
+
+/// A region of an image, such as a bounding box returned by an object detector,
+/// that can be protected or removed without drawing a mask, see [shapes_mask].
+///
+/// A pixel is inside a circle or a polygon when its center is,
+/// the center of the pixel `(x, y)` being at `(x + 0.5, y + 0.5)`.
+#[derive(Clone, PartialEq, Debug)]
+pub enum Shape {
+    Rect(Rect),
+    /// A disc, given by the coordinates of its center and its radius, in pixels
+    Circle { x: f32, y: f32, radius: f32 },
+    /// A polygon, given by the coordinates of its vertices, in order.
+    /// Self-intersecting polygons contain the pixels inside an odd number of their loops.
+    Polygon(Vec<(f32, f32)>),
+}
+
+impl Shape {
+    /// Whether the given pixel is inside the shape
+    pub fn contains(&self, x: u32, y: u32) -> bool {
+        let (px, py) = (x as f32 + 0.5, y as f32 + 0.5);
+        match self {
+            Shape::Rect(rect) => rect.contains(x, y),
+            Shape::Circle { x, y, radius } => {
+                let (dx, dy) = (px - x, py - y);
+                dx * dx + dy * dy <= radius * radius
+            }
+            Shape::Polygon(vertices) => {
+                let edges = vertices.iter().zip(vertices.iter().cycle().skip(1));
+                // Counts the edges crossed by a ray going right from the pixel
+                let crossed = edges.filter(|&(&(x0, y0), &(x1, y1))| {
+                    (y0 > py) != (y1 > py) && px < x0 + (py - y0) * (x1 - x0) / (y1 - y0)
+                });
+                crossed.count() % 2 == 1
+            }
+        }
+    }
+}
+
+impl From<Rect> for Shape {
+    fn from(rect: Rect) -> Self {
+        Shape::Rect(rect)
+    }
+}
+
+/// Draws a mask of the given size, in which the pixels inside any of the shapes are white,
+/// to use wherever a mask is expected: to protect pixels with
+/// [ImportanceMap::from_mask](crate::ImportanceMap::from_mask), to forbid them with
+/// [ImportanceMap::forbidden](crate::ImportanceMap::forbidden),
+/// or to remove an object with [relocate_object](crate::relocate_object).
+///
+/// ```
+/// use seamcarving::{shapes_mask, ImportanceMap, Rect, Shape};
+/// // The bounding boxes found by a detector
+/// let boxes = [(2, 2, 6, 4), (12, 1, 3, 3)];
+/// let mut shapes: Vec<Shape> = boxes
+///     .iter()
+///     .map(|&(x, y, width, height)| Shape::Rect(Rect { x, y, width, height }))
+///     .collect();
+/// shapes.push(Shape::Circle { x: 16., y: 7., radius: 2. });
+/// let img = image::RgbImage::new(20, 10);
+/// let importance = ImportanceMap::from_mask(&shapes_mask(20, 10, &shapes));
+/// let resized = seamcarving::resize_with_importance(&img, 12, 10, &importance).unwrap();
+/// ```
+#[cfg(feature = "image")]
+pub fn shapes_mask(width: u32, height: u32, shapes: &[Shape]) -> GrayImage {
+    GrayImage::from_fn(width, height, |x, y| {
+        let inside = shapes.iter().any(|shape| shape.contains(x, y));
+        Luma([if inside { 255 } else { 0 }])
+    })
+}
+
+/// Builds an energy bias protecting all the pixels inside the given rectangles
+#[cfg_attr(not(feature = "rustface"), allow(dead_code))]
+pub(crate) fn rects_bias(size: Pos, rects: &[Rect]) -> Matrix<u32> {
+    Matrix::from_fn(size, |x, y| {
+        if rects.iter().any(|r| r.contains(x as u32, y as u32)) {
+            PROTECTED_ENERGY
+        } else {
+            0
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec;
+
+    use super::*;
+
+    #[test]
+    fn contains_the_pixels_inside_shapes() {
+        let circle = Shape::Circle {
+            x: 5.,
+            y: 5.,
+            radius: 2.,
+        };
+        assert!(circle.contains(4, 4) && circle.contains(5, 6));
+        assert!(!circle.contains(3, 3) && !circle.contains(7, 5));
+        // A triangle with its right angle at the origin
+        let triangle = Shape::Polygon(vec![(0., 0.), (8., 0.), (0., 8.)]);
+        assert!(triangle.contains(0, 0) && triangle.contains(6, 0) && triangle.contains(3, 3));
+        assert!(!triangle.contains(4, 4) && !triangle.contains(8, 0));
+        assert!(!Shape::Polygon(vec![]).contains(0, 0));
+        let rect = Shape::from(Rect {
+            x: 1,
+            y: 1,
+            width: 2,
+            height: 1,
+        });
+        assert!(rect.contains(2, 1) && !rect.contains(2, 2));
+    }
+
+    #[test]
+    fn grows_rectangles() {
+        let rect = Rect {
+            x: 1,
+            y: 4,
+            width: 2,
+            height: 3,
+        };
+        let grown = Rect {
+            x: 0,
+            y: 2,
+            width: 5,
+            height: 7,
+        };
+        assert_eq!(rect.grow(2, (5, 10)), grown);
+        // Rectangles reaching past the largest coordinates are clipped too
+        let huge = Rect {
+            x: u32::MAX - 1,
+            y: u32::MAX,
+            width: u32::MAX,
+            height: 4,
+        };
+        let clipped = Rect {
+            x: u32::MAX - 3,
+            y: u32::MAX - 2,
+            width: 3,
+            height: 2,
+        };
+        assert_eq!(huge.grow(2, (u32::MAX, u32::MAX)), clipped);
+    }
+}
@@ -1,30 +1,129 @@
-use std::fmt::{Debug, Formatter};
-use std::ops::{Index, IndexMut};
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::fmt::{Debug, Display, Formatter};
+use core::ops::{Index, IndexMut};
 
-use crate::Pos;
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
 
-pub(crate) struct Matrix<T> {
+use num_traits::ToPrimitive;
+
+use crate::{Pos, Rect};
+
+/// Side of the square blocks in which matrices are transposed
+const TRANSPOSE_BLOCK: usize = 32;
+
+/// Number of rows and columns displayed by [Display](core::fmt::Display),
+/// larger matrices are truncated
+const DISPLAY_LIMIT: usize = 16;
+
+/// Characters of [to_ascii_heatmap](Matrix::to_ascii_heatmap), from the lowest to the highest value
+const HEATMAP_RAMP: &[u8] = b" .:-=+*#%@";
+
+/// A two-dimensional container, indexed by [Pos].
+///
+/// Elements are stored row by row. Vertical seams can be removed from a matrix
+/// the same way they are removed from an image, which shifts the elements on their right:
+/// this is how the seam carving algorithm keeps per-pixel data aligned with the carved image.
+///
+/// ```
+/// use seamcarving::{Matrix, Pos};
+/// let mut matrix = Matrix::from_fn(Pos(3, 2), |x, y| x + 10 * y);
+/// assert_eq!(matrix[Pos(2, 1)], 12);
+/// matrix.remove_seam(&[Pos(0, 1), Pos(1, 0)]);
+/// assert_eq!((matrix.width(), matrix.height()), (2, 2));
+/// assert_eq!(matrix[Pos(1, 0)], 2);
+/// assert_eq!(matrix[Pos(0, 1)], 11);
+/// ```
+#[derive(Clone)]
+pub struct Matrix<T> {
     original_width: usize,
     current_width: usize,
     contents: Vec<T>,
 }
 
 impl<T: Debug> Debug for Matrix<T> {
-    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+    fn fmt(&self, f: &mut Formatter) -> core::fmt::Result {
         writeln!(f, "Matrix {{")?;
         for line in self.contents.chunks_exact(self.original_width) {
-            writeln!(f, "  {:?}", line)?;
+            writeln!(f, "  {:?}", &line[..self.current_width])?;
         }
         writeln!(f, "}}")
     }
 }
 
+/// Displays the matrix as a grid with aligned columns,
+/// truncated to the first rows and columns for large matrices
+impl<T: Display> Display for Matrix<T> {
+    fn fmt(&self, f: &mut Formatter) -> core::fmt::Result {
+        let (width, height) = (self.current_width, self.height() as usize);
+        let cells: Vec<Vec<String>> = (0..height.min(DISPLAY_LIMIT))
+            .map(|y| {
+                (0..width.min(DISPLAY_LIMIT))
+                    .map(|x| self[Pos(x as u32, y as u32)].to_string())
+                    .collect()
+            })
+            .collect();
+        let cell_width = cells.iter().flatten().map(|c| c.len()).max().unwrap_or(0);
+        for row in &cells {
+            let line: Vec<String> = row
+                .iter()
+                .map(|c| format!("{:>width$}", c, width = cell_width))
+                .collect();
+            write!(f, "{}", line.join(" "))?;
+            writeln!(f, "{}", if width > DISPLAY_LIMIT { " …" } else { "" })?;
+        }
+        if height > DISPLAY_LIMIT {
+            writeln!(f, "… ({}x{})", width, height)?;
+        }
+        Ok(())
+    }
+}
+
+impl<T: ToPrimitive> Matrix<T> {
+    /// Draws the matrix with one character per element, denser characters standing
+    /// for higher values, which makes it easy to eyeball an energy or a cumulated energy table.
+    /// Values are scaled between the minimum and the maximum of the matrix.
+    ///
+    /// ```
+    /// use seamcarving::{Matrix, Pos};
+    /// let matrix = Matrix::from_fn(Pos(4, 2), |x, _y| x);
+    /// assert_eq!(matrix.to_ascii_heatmap(), " -*@\n -*@\n");
+    /// ```
+    pub fn to_ascii_heatmap(&self) -> String {
+        let (width, height) = (self.current_width, self.height() as usize);
+        let values: Vec<f64> = (0..height)
+            .flat_map(|y| (0..width).map(move |x| Pos(x as u32, y as u32)))
+            .map(|pos| self[pos].to_f64().unwrap_or(0.))
+            .collect();
+        let min = values.iter().cloned().fold(f64::INFINITY, f64::min);
+        let max = values.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+        let last = (HEATMAP_RAMP.len() - 1) as f64;
+        let mut heatmap = String::with_capacity((width + 1) * height);
+        for row in values.chunks(width.max(1)).take(height) {
+            for &value in row {
+                let level = if max > min {
+                    // Rounded to the nearest level, f64::round needs std
+                    ((value - min) / (max - min) * last + 0.5) as usize
+                } else {
+                    0
+                };
+                heatmap.push(HEATMAP_RAMP[level] as char);
+            }
+            heatmap.push('\n');
+        }
+        heatmap
+    }
+}
+
 impl<T> Matrix<T> {
+    /// Creates a matrix of the given size, calling `f` with the column and the row
+    /// of each element
     pub fn from_fn(size: Pos, mut f: impl FnMut(usize, usize) -> T) -> Self {
-        let (width, height) = (size.0 as usize, size.1 as usize);
-        let size = width * height;
-        let mut contents = Vec::with_capacity(size);
-        contents.extend((0..width * height).map(|i| f(i % width, i / width)));
+        let (width, area) = (size.0 as usize, size.area());
+        let mut contents = Vec::with_capacity(area);
+        contents.extend((0..area).map(|i| f(i % width, i / width)));
         Matrix {
             original_width: width,
             current_width: width,
@@ -32,19 +131,266 @@ impl<T> Matrix<T> {
         }
     }
 
+    /// Wraps elements stored row by row: the element at `Pos(x, y)` is at index
+    /// `x + y * width`.
+    /// Returns None if the number of elements does not match the size.
+    ///
+    /// ```
+    /// use seamcarving::{Matrix, Pos};
+    /// let matrix = Matrix::from_vec(Pos(2, 2), vec![1, 2, 3, 4]).unwrap();
+    /// assert_eq!(matrix[Pos(0, 1)], 3);
+    /// assert_eq!(matrix.into_vec(), vec![1, 2, 3, 4]);
+    /// ```
+    pub fn from_vec(size: Pos, contents: Vec<T>) -> Option<Self> {
+        let width = size.0 as usize;
+        if size.checked_area() != Some(contents.len()) {
+            return None;
+        }
+        Some(Matrix {
+            original_width: width,
+            current_width: width,
+            contents,
+        })
+    }
+
+    /// Returns the elements row by row, in the layout expected by [from_vec](Matrix::from_vec)
+    pub fn into_vec(self) -> Vec<T> {
+        if self.current_width == self.original_width {
+            return self.contents;
+        }
+        let (current, original) = (self.current_width, self.original_width);
+        // Drop the elements left behind the end of the rows by removed seams
+        self.contents
+            .into_iter()
+            .enumerate()
+            .filter(|(i, _)| i % original < current)
+            .map(|(_, element)| element)
+            .collect()
+    }
+
+    /// The storage of the matrix, row by row.
+    /// Each row starts [stride](Matrix::stride) elements after the previous one:
+    /// when seams were removed, the end of each row is unused.
+    pub fn as_slice(&self) -> &[T] {
+        &self.contents
+    }
+
+    /// Distance between the starts of two consecutive rows in [as_slice](Matrix::as_slice).
+    /// It is the width the matrix had when it was created.
+    pub fn stride(&self) -> usize {
+        self.original_width
+    }
+
+    /// Number of columns, which decreases by one each time a seam is removed
+    pub fn width(&self) -> u32 {
+        self.current_width as u32
+    }
+
+    /// Number of rows
+    pub fn height(&self) -> u32 {
+        self.contents
+            .len()
+            .checked_div(self.original_width)
+            .unwrap_or(0) as u32
+    }
+
+    /// Number of bytes allocated for the elements of the matrix.
+    /// Removing seams does not free memory, see [shrink_to_fit](Matrix::shrink_to_fit).
+    pub fn capacity_bytes(&self) -> usize {
+        self.contents.capacity() * core::mem::size_of::<T>()
+    }
+
+    /// Number of elements the matrix can hold without allocating
+    pub(crate) fn capacity(&self) -> usize {
+        self.contents.capacity()
+    }
+
+    /// Turns the matrix into a matrix of the given size filled with `value`,
+    /// reusing its memory, which is only reallocated if it is too small
+    pub(crate) fn reset(&mut self, size: Pos, value: T)
+    where
+        T: Clone,
+    {
+        let width = size.0 as usize;
+        self.contents.clear();
+        self.contents.resize(size.area(), value);
+        self.original_width = width;
+        self.current_width = width;
+    }
+
+    /// Turns the matrix into a copy of `values`, stored row by row like in
+    /// [from_vec](Matrix::from_vec), reusing its memory when it is large enough.
+    /// Returns None if the number of values does not match the size.
+    pub(crate) fn copy_from(&mut self, size: Pos, values: &[T]) -> Option<()>
+    where
+        T: Clone,
+    {
+        let width = size.0 as usize;
+        if size.checked_area() != Some(values.len()) {
+            return None;
+        }
+        self.contents.clear();
+        self.contents.extend_from_slice(values);
+        self.original_width = width;
+        self.current_width = width;
+        Some(())
+    }
+
+    /// Row `y`, along with the row above it, which is empty for the top row
+    pub(crate) fn row_and_above_mut(&mut self, y: u32) -> (&[T], &mut [T]) {
+        let (width, start) = (self.current_width, y as usize * self.original_width);
+        let (before, after) = self.contents.split_at_mut(start);
+        let above = match start.checked_sub(self.original_width) {
+            Some(above_start) if y > 0 => &before[above_start..above_start + width],
+            _ => &before[..0],
+        };
+        (above, &mut after[..width])
+    }
+
+    /// Moves the rows next to each other, dropping the elements left behind
+    /// by removed seams, and frees the memory that is not needed anymore
+    pub fn shrink_to_fit(&mut self) {
+        let (width, height) = (self.current_width, self.height() as usize);
+        if width != self.original_width {
+            // Each element moves to a lower index, that was already read
+            for y in 1..height {
+                for x in 0..width {
+                    self.contents
+                        .swap(x + y * width, x + y * self.original_width);
+                }
+            }
+            self.contents.truncate(width * height);
+            self.original_width = width;
+        }
+        self.contents.shrink_to_fit();
+    }
+
+    /// The element at the given position, or None if the position is outside of the matrix
+    #[inline]
+    pub fn get(&self, pos: Pos) -> Option<&T> {
+        let index = self.checked_index(pos)?;
+        self.contents.get(index)
+    }
+
+    /// Mutable access to the element at the given position,
+    /// or None if the position is outside of the matrix
+    #[inline]
+    pub fn get_mut(&mut self, pos: Pos) -> Option<&mut T> {
+        let index = self.checked_index(pos)?;
+        self.contents.get_mut(index)
+    }
+
+    /// Index of a position in the contents, if it is inside the current width.
+    /// Rows below the matrix give indices past the end of the contents.
+    #[inline(always)]
+    fn checked_index(&self, Pos(x, y): Pos) -> Option<usize> {
+        if (x as usize) < self.current_width {
+            Some(x as usize + y as usize * self.original_width)
+        } else {
+            None
+        }
+    }
+
+    /// Removes one element from each row, shifting the following elements of the row
+    /// to the left.
+    /// The seam contains one position per row, in any order,
+    /// like the [positions](crate::Seam::positions) of a [Seam](crate::Seam).
     #[inline]
     pub fn remove_seam(&mut self, seam: &[Pos]) {
         let current_width = self.current_width;
         self.current_width -= 1;
-        self.contents
-            .chunks_exact_mut(self.original_width)
-            .zip(seam.iter().rev())
-            .for_each(|(aliases, &Pos(x, _y))| {
-                let end = &mut aliases[x as usize..current_width];
-                if !end.is_empty() {
-                    end.rotate_left(1)
+        for &Pos(x, y) in seam {
+            let start = y as usize * self.original_width;
+            let end = &mut self.contents[start + x as usize..start + current_width];
+            if !end.is_empty() {
+                end.rotate_left(1)
+            }
+        }
+    }
+
+    /// Removes one element from each column, shifting the following elements of the column up.
+    /// The seam contains one position per column, in any order.
+    ///
+    /// This is equivalent to transposing the matrix, removing a vertical seam
+    /// and transposing it back, without copying the whole matrix twice.
+    pub fn remove_horizontal_seam(&mut self, seam: &[Pos]) {
+        debug_assert_eq!(seam.len(), self.current_width, "one position per column");
+        let last_row = (self.height() as usize).saturating_sub(1);
+        for &Pos(x, y) in seam {
+            let x = x as usize;
+            for row in y as usize..last_row {
+                let below = x + (row + 1) * self.original_width;
+                self.contents.swap(x + row * self.original_width, below);
+            }
+        }
+        self.contents.truncate(last_row * self.original_width);
+    }
+}
+
+impl<T: Copy> Matrix<T> {
+    /// Swaps rows and columns, so that the element at `Pos(x, y)` moves to `Pos(y, x)`.
+    ///
+    /// Horizontal seams of the matrix become vertical seams of the transposed matrix.
+    /// Square matrices from which no seam was removed are transposed in place,
+    /// other matrices are copied block by block, to stay cache-friendly on large matrices.
+    pub fn transpose(&mut self) {
+        self.transpose_reusing(&mut Vec::new())
+    }
+
+    /// Transposes the matrix like [transpose](Matrix::transpose), copying it into `spare`
+    /// if needed, which then holds the memory of the matrix before it was transposed
+    pub(crate) fn transpose_reusing(&mut self, spare: &mut Vec<T>) {
+        let (width, height) = (self.current_width, self.height() as usize);
+        if width == height && self.original_width == width {
+            for y in 0..height {
+                for x in y + 1..width {
+                    self.contents.swap(x + y * width, y + x * width);
                 }
-            });
+            }
+            return;
+        }
+        let transposed = spare;
+        transposed.clear();
+        if let Some(&first) = self.contents.first() {
+            transposed.resize(width * height, first);
+        }
+        for block_y in (0..height).step_by(TRANSPOSE_BLOCK) {
+            for block_x in (0..width).step_by(TRANSPOSE_BLOCK) {
+                for y in block_y..(block_y + TRANSPOSE_BLOCK).min(height) {
+                    for x in block_x..(block_x + TRANSPOSE_BLOCK).min(width) {
+                        transposed[y + x * height] = self.contents[x + y * self.original_width];
+                    }
+                }
+            }
+        }
+        core::mem::swap(&mut self.contents, transposed);
+        self.original_width = height;
+        self.current_width = height;
+    }
+}
+
+/// Parallel iteration, only available when the `rayon` feature is enabled
+#[cfg(feature = "rayon")]
+impl<T: Send + Sync> Matrix<T> {
+    /// The rows of the matrix, from top to bottom, in parallel
+    pub fn par_rows(&self) -> impl IndexedParallelIterator<Item = &[T]> {
+        let width = self.current_width;
+        self.contents
+            .par_chunks(self.original_width.max(1))
+            .map(move |row| &row[..width])
+    }
+
+    /// Mutable rows of the matrix, from top to bottom, in parallel
+    pub fn par_rows_mut(&mut self) -> impl IndexedParallelIterator<Item = &mut [T]> {
+        let width = self.current_width;
+        self.contents
+            .par_chunks_mut(self.original_width.max(1))
+            .map(move |row| &mut row[..width])
+    }
+
+    /// Mutable access to all the elements of the matrix, in parallel
+    pub fn par_iter_mut(&mut self) -> impl ParallelIterator<Item = &mut T> {
+        self.par_rows_mut().flat_map(|row| row.par_iter_mut())
     }
 }
 
@@ -65,3 +411,277 @@ impl<T> IndexMut<Pos> for Matrix<T> {
         &mut self.contents[x + y * self.original_width]
     }
 }
+
+/// A borrowed rectangular region of a [Matrix], indexed relatively to its top left corner
+pub struct MatrixView<'a, T> {
+    matrix: &'a Matrix<T>,
+    region: Rect,
+}
+
+// Not derived, as views can be copied even when their elements cannot
+impl<'a, T> Clone for MatrixView<'a, T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<'a, T> Copy for MatrixView<'a, T> {}
+
+/// A mutably borrowed rectangular region of a [Matrix],
+/// indexed relatively to its top left corner
+pub struct MatrixViewMut<'a, T> {
+    matrix: &'a mut Matrix<T>,
+    region: Rect,
+}
+
+impl<T> Matrix<T> {
+    /// Borrows a region of the matrix without copying it.
+    /// Panics if the region does not fit in the matrix.
+    pub fn view(&self, region: Rect) -> MatrixView<'_, T> {
+        self.check_region(region);
+        MatrixView {
+            matrix: self,
+            region,
+        }
+    }
+
+    /// Mutably borrows a region of the matrix without copying it.
+    /// Panics if the region does not fit in the matrix.
+    pub fn view_mut(&mut self, region: Rect) -> MatrixViewMut<'_, T> {
+        self.check_region(region);
+        MatrixViewMut {
+            matrix: self,
+            region,
+        }
+    }
+
+    fn check_region(&self, region: Rect) {
+        let fits = u64::from(region.x) + u64::from(region.width) <= u64::from(self.width())
+            && u64::from(region.y) + u64::from(region.height) <= u64::from(self.height());
+        assert!(fits, "the region {:?} does not fit in the matrix", region);
+    }
+
+    /// The elements of a row of the region, `y` being relative to the region
+    fn region_row(&self, region: Rect, y: u32) -> &[T] {
+        assert!(y < region.height, "row {} is outside of the view", y);
+        let start = region.x as usize + (region.y + y) as usize * self.original_width;
+        &self.contents[start..start + region.width as usize]
+    }
+
+    fn region_row_mut(&mut self, region: Rect, y: u32) -> &mut [T] {
+        assert!(y < region.height, "row {} is outside of the view", y);
+        let start = region.x as usize + (region.y + y) as usize * self.original_width;
+        &mut self.contents[start..start + region.width as usize]
+    }
+}
+
+/// Position in the matrix of a position relative to a region.
+/// Positions outside of the region panic, even when they are inside the matrix.
+#[inline(always)]
+fn in_region(region: Rect, Pos(x, y): Pos) -> Pos {
+    assert!(
+        x < region.width && y < region.height,
+        "{:?} is outside of the view",
+        Pos(x, y)
+    );
+    Pos(region.x + x, region.y + y)
+}
+
+impl<'a, T> MatrixView<'a, T> {
+    /// Number of columns of the region
+    pub fn width(&self) -> u32 {
+        self.region.width
+    }
+
+    /// Number of rows of the region
+    pub fn height(&self) -> u32 {
+        self.region.height
+    }
+
+    /// The elements of a row of the region
+    pub fn row(&self, y: u32) -> &'a [T] {
+        self.matrix.region_row(self.region, y)
+    }
+
+    /// The rows of the region, from top to bottom
+    pub fn rows(&self) -> impl Iterator<Item = &'a [T]> + '_ {
+        (0..self.height()).map(move |y| self.row(y))
+    }
+}
+
+impl<'a, T> MatrixViewMut<'a, T> {
+    /// Number of columns of the region
+    pub fn width(&self) -> u32 {
+        self.region.width
+    }
+
+    /// Number of rows of the region
+    pub fn height(&self) -> u32 {
+        self.region.height
+    }
+
+    /// Reborrows the region immutably
+    pub fn as_view(&self) -> MatrixView<'_, T> {
+        MatrixView {
+            matrix: self.matrix,
+            region: self.region,
+        }
+    }
+
+    /// The elements of a row of the region
+    pub fn row_mut(&mut self, y: u32) -> &mut [T] {
+        self.matrix.region_row_mut(self.region, y)
+    }
+
+    /// Sets every element of the region to the same value
+    pub fn fill(&mut self, value: T)
+    where
+        T: Clone,
+    {
+        for y in 0..self.height() {
+            self.row_mut(y).fill(value.clone());
+        }
+    }
+}
+
+impl<'a, T> Index<Pos> for MatrixView<'a, T> {
+    type Output = T;
+
+    #[inline(always)]
+    fn index(&self, pos: Pos) -> &T {
+        &self.matrix[in_region(self.region, pos)]
+    }
+}
+
+impl<'a, T> Index<Pos> for MatrixViewMut<'a, T> {
+    type Output = T;
+
+    #[inline(always)]
+    fn index(&self, pos: Pos) -> &T {
+        &self.matrix[in_region(self.region, pos)]
+    }
+}
+
+impl<'a, T> IndexMut<Pos> for MatrixViewMut<'a, T> {
+    #[inline(always)]
+    fn index_mut(&mut self, pos: Pos) -> &mut T {
+        &mut self.matrix[in_region(self.region, pos)]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec;
+
+    use super::*;
+
+    #[test]
+    fn transposes() {
+        let mut square = Matrix::from_fn(Pos(3, 3), |x, y| (x, y));
+        square.transpose();
+        assert_eq!(square[Pos(2, 0)], (0, 2));
+        assert_eq!(square[Pos(1, 1)], (1, 1));
+
+        let mut carved = Matrix::from_fn(Pos(40, 35), |x, y| (x, y));
+        let seam: Vec<Pos> = (0..35).map(|y| Pos(0, y)).collect();
+        carved.remove_seam(&seam);
+        carved.transpose();
+        assert_eq!((carved.width(), carved.height()), (35, 39));
+        assert_eq!(carved[Pos(34, 38)], (39, 34));
+        assert_eq!(carved[Pos(3, 0)], (1, 3));
+    }
+
+    #[test]
+    fn removes_horizontal_seams() {
+        let mut matrix = Matrix::from_fn(Pos(3, 3), |x, y| (x, y));
+        matrix.remove_horizontal_seam(&[Pos(0, 0), Pos(1, 2), Pos(2, 1)]);
+        assert_eq!((matrix.width(), matrix.height()), (3, 2));
+        let expected = Matrix::from_fn(Pos(3, 3), |x, y| (x, y));
+        let mut vertical = expected.clone();
+        vertical.transpose();
+        vertical.remove_seam(&[Pos(1, 2), Pos(2, 1), Pos(0, 0)]);
+        vertical.transpose();
+        for pos in Pos::iter_in_rect(Pos(0, 0), Pos(3, 2)) {
+            assert_eq!(matrix[pos], vertical[pos]);
+        }
+        assert_eq!(matrix[Pos(0, 0)], (0, 1));
+        assert_eq!(matrix[Pos(1, 1)], (1, 1));
+    }
+
+    #[test]
+    fn checks_accesses() {
+        let mut matrix = Matrix::from_fn(Pos(3, 2), |x, y| x + 10 * y);
+        assert_eq!(matrix.get(Pos(2, 1)), Some(&12));
+        assert_eq!(matrix.get(Pos(0, 2)), None);
+        matrix.remove_seam(&[Pos(2, 1), Pos(2, 0)]);
+        // The removed column is still allocated, but not accessible anymore
+        assert_eq!(matrix.get(Pos(2, 0)), None);
+        *matrix.get_mut(Pos(1, 1)).unwrap() = 0;
+        assert_eq!(matrix[Pos(1, 1)], 0);
+        assert!(matrix.get_mut(Pos(u32::MAX, 0)).is_none());
+    }
+
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn iterates_in_parallel() {
+        let mut matrix = Matrix::from_fn(Pos(3, 2), |x, y| x + 10 * y);
+        matrix.remove_seam(&[Pos(0, 1), Pos(0, 0)]);
+        let sums: Vec<usize> = matrix.par_rows().map(|row| row.iter().sum()).collect();
+        assert_eq!(sums, vec![3, 23]);
+        matrix.par_iter_mut().for_each(|e| *e *= 2);
+        assert_eq!(matrix.into_vec(), vec![2, 4, 22, 24]);
+    }
+
+    #[test]
+    fn displays_small_matrices() {
+        let matrix = Matrix::from_fn(Pos(3, 2), |x, y| x * 50 + y);
+        assert_eq!(matrix.to_string(), "  0  50 100\n  1  51 101\n");
+        let large = Matrix::from_fn(Pos(20, 20), |_x, _y| 1);
+        let text = large.to_string();
+        assert_eq!(text.lines().count(), DISPLAY_LIMIT + 1);
+        assert!(text.ends_with("… (20x20)\n"));
+    }
+
+    #[test]
+    fn compacts_carved_contents() {
+        let mut matrix = Matrix::from_vec(Pos(3, 2), vec![1, 2, 3, 4, 5, 6]).unwrap();
+        assert!(Matrix::from_vec(Pos(3, 3), vec![0; 6]).is_none());
+        matrix.remove_seam(&[Pos(0, 1), Pos(1, 0)]);
+        assert_eq!(matrix.as_slice().len(), 6);
+        assert_eq!(matrix.stride(), 3);
+        assert_eq!(matrix.into_vec(), vec![1, 3, 5, 6]);
+    }
+
+    #[test]
+    fn shrinks_to_fit() {
+        let mut matrix = Matrix::from_fn(Pos(4, 3), |x, y| x + 10 * y);
+        assert_eq!(matrix.capacity_bytes(), 12 * core::mem::size_of::<usize>());
+        matrix.remove_seam(&[Pos(0, 2), Pos(1, 1), Pos(3, 0)]);
+        matrix.shrink_to_fit();
+        assert_eq!(matrix.capacity_bytes(), 9 * core::mem::size_of::<usize>());
+        assert_eq!(matrix.stride(), 3);
+        assert_eq!(matrix.as_slice(), &[0, 1, 2, 10, 12, 13, 21, 22, 23]);
+        matrix.remove_seam(&[Pos(0, 2), Pos(0, 1), Pos(0, 0)]);
+        assert_eq!(matrix[Pos(1, 2)], 23);
+    }
+
+    #[test]
+    fn views_regions() {
+        let mut matrix = Matrix::from_fn(Pos(4, 3), |x, y| x + 10 * y);
+        let region = Rect {
+            x: 1,
+            y: 1,
+            width: 2,
+            height: 2,
+        };
+        let view = matrix.view(region);
+        assert_eq!(view[Pos(0, 0)], 11);
+        assert_eq!(view.rows().collect::<Vec<_>>(), vec![&[11, 12], &[21, 22]]);
+        let mut view = matrix.view_mut(region);
+        view[Pos(1, 1)] = 0;
+        view.row_mut(0).fill(7);
+        assert_eq!(view.as_view()[Pos(0, 1)], 21);
+        assert_eq!(matrix.view(region).rows().collect::<Vec<_>>(), vec![&[7, 7], &[21, 0]]);
+        assert_eq!(matrix[Pos(3, 1)], 13);
+    }
+}
@@ -0,0 +1,403 @@
+//! EXIF and XMP blocks of JPEG and PNG files, copied from the original image to the resized one.
+use std::convert::TryInto;
+
+/// Header of the JPEG APP1 segments holding EXIF data
+const JPEG_EXIF: &[u8] = b"Exif\0\0";
+
+/// Header of the JPEG APP1 segments and keyword of the PNG iTXt chunks holding XMP data
+const XMP_NAMESPACE: &[u8] = b"http://ns.adobe.com/xap/1.0/\0";
+const PNG_XMP_KEYWORD: &[u8] = b"XML:com.adobe.xmp";
+
+const PNG_SIGNATURE: &[u8] = b"\x89PNG\r\n\x1a\n";
+
+/// Largest payload of a JPEG segment, after its two length bytes
+const JPEG_MAX_SEGMENT: usize = 0xffff - 2;
+
+/// EXIF tags of the width and height of the image, in the first IFD and in the EXIF IFD
+const EXIF_IMAGE_WIDTH: u16 = 0x0100;
+const EXIF_IMAGE_LENGTH: u16 = 0x0101;
+const EXIF_IFD_POINTER: u16 = 0x8769;
+const EXIF_PIXEL_X_DIMENSION: u16 = 0xa002;
+const EXIF_PIXEL_Y_DIMENSION: u16 = 0xa003;
+
+/// XMP properties holding the width and the height of the image
+const XMP_WIDTHS: [&str; 2] = ["exif:PixelXDimension", "tiff:ImageWidth"];
+const XMP_HEIGHTS: [&str; 2] = ["exif:PixelYDimension", "tiff:ImageLength"];
+
+/// The EXIF and XMP blocks of an encoded image, see [open_image_with_metadata].
+///
+/// Only JPEG and PNG files are read and written, other formats have no metadata.
+///
+/// ```
+/// use seamcarving::Metadata;
+/// let metadata = Metadata::read(b"not an image");
+/// assert_eq!(metadata, Metadata::default());
+/// ```
+///
+/// [open_image_with_metadata]: crate::open_image_with_metadata
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct Metadata {
+    /// The EXIF data, starting with its TIFF header
+    pub exif: Option<Vec<u8>>,
+    /// The XMP packet
+    pub xmp: Option<Vec<u8>>,
+}
+
+impl Metadata {
+    /// The metadata of an encoded JPEG or PNG image. Malformed files have none.
+    pub fn read(bytes: &[u8]) -> Metadata {
+        if bytes.starts_with(&[0xff, 0xd8]) {
+            read_jpeg(bytes)
+        } else if bytes.starts_with(PNG_SIGNATURE) {
+            read_png(bytes)
+        } else {
+            Metadata::default()
+        }
+    }
+
+    /// The same metadata, describing an image of the given size:
+    /// the widths and heights in the EXIF data and in the XMP packet are replaced.
+    pub fn resized(&self, width: u32, height: u32) -> Metadata {
+        let exif = self.exif.clone().map(|mut exif| {
+            set_exif_dimensions(&mut exif, width, height);
+            exif
+        });
+        let xmp = self.xmp.as_ref().map(|xmp| match std::str::from_utf8(xmp) {
+            Ok(text) => set_xmp_dimensions(text, width, height).into_bytes(),
+            Err(_) => xmp.clone(),
+        });
+        Metadata { exif, xmp }
+    }
+
+    /// Adds this metadata to an encoded JPEG or PNG image.
+    /// Images in other formats are returned unchanged, and so are the blocks
+    /// that do not fit in a single JPEG segment.
+    pub fn embed(&self, encoded: &[u8]) -> Vec<u8> {
+        if encoded.starts_with(&[0xff, 0xd8]) {
+            self.embed_in_jpeg(encoded)
+        } else if encoded.starts_with(PNG_SIGNATURE) {
+            self.embed_in_png(encoded)
+        } else {
+            encoded.to_vec()
+        }
+    }
+
+    fn embed_in_jpeg(&self, encoded: &[u8]) -> Vec<u8> {
+        // The APP1 segments come after the JFIF APP0 segment, which has to be first
+        let mut start = 2;
+        if let Some((0xe0, payload)) = jpeg_segments(encoded).next() {
+            start += 4 + payload.len();
+        }
+        let mut bytes = encoded[..start].to_vec();
+        let blocks = [(JPEG_EXIF, &self.exif), (XMP_NAMESPACE, &self.xmp)];
+        for (header, block) in blocks {
+            let block = match block {
+                Some(block) if header.len() + block.len() <= JPEG_MAX_SEGMENT => block,
+                _ => continue,
+            };
+            bytes.extend_from_slice(&[0xff, 0xe1]);
+            bytes.extend_from_slice(&((2 + header.len() + block.len()) as u16).to_be_bytes());
+            bytes.extend_from_slice(header);
+            bytes.extend_from_slice(block);
+        }
+        bytes.extend_from_slice(&encoded[start..]);
+        bytes
+    }
+
+    fn embed_in_png(&self, encoded: &[u8]) -> Vec<u8> {
+        // The eXIf chunk has to come before the image data, right after the header is fine
+        let start = match png_chunks(encoded).next() {
+            Some((b"IHDR", data)) => PNG_SIGNATURE.len() + 12 + data.len(),
+            _ => return encoded.to_vec(),
+        };
+        let mut bytes = encoded[..start].to_vec();
+        if let Some(exif) = &self.exif {
+            push_png_chunk(&mut bytes, b"eXIf", exif);
+        }
+        if let Some(xmp) = &self.xmp {
+            // Uncompressed, with empty language and translated keyword
+            let mut data = PNG_XMP_KEYWORD.to_vec();
+            data.extend_from_slice(&[0, 0, 0, 0, 0]);
+            data.extend_from_slice(xmp);
+            push_png_chunk(&mut bytes, b"iTXt", &data);
+        }
+        bytes.extend_from_slice(&encoded[start..]);
+        bytes
+    }
+}
+
+fn read_jpeg(bytes: &[u8]) -> Metadata {
+    let mut metadata = Metadata::default();
+    for (marker, payload) in jpeg_segments(bytes) {
+        if marker != 0xe1 {
+            continue;
+        }
+        if let Some(exif) = payload.strip_prefix(JPEG_EXIF) {
+            metadata.exif.get_or_insert_with(|| exif.to_vec());
+        } else if let Some(xmp) = payload.strip_prefix(XMP_NAMESPACE) {
+            metadata.xmp.get_or_insert_with(|| xmp.to_vec());
+        }
+    }
+    metadata
+}
+
+fn read_png(bytes: &[u8]) -> Metadata {
+    let mut metadata = Metadata::default();
+    for (kind, data) in png_chunks(bytes) {
+        match kind {
+            b"eXIf" => {
+                metadata.exif.get_or_insert_with(|| data.to_vec());
+            }
+            b"iTXt" => {
+                if let Some(xmp) = png_xmp(data) {
+                    metadata.xmp.get_or_insert_with(|| xmp.to_vec());
+                }
+            }
+            _ => {}
+        }
+    }
+    metadata
+}
+
+/// The text of an uncompressed iTXt chunk holding XMP data
+fn png_xmp(data: &[u8]) -> Option<&[u8]> {
+    let rest = data.strip_prefix(PNG_XMP_KEYWORD)?.strip_prefix(&[0])?;
+    let (compressed, rest) = rest.split_first()?;
+    if *compressed != 0 {
+        return None;
+    }
+    // Compression method, then the language and the translated keyword, both terminated by 0
+    let rest = rest.get(1..)?;
+    let language_end = rest.iter().position(|&b| b == 0)?;
+    let rest = &rest[language_end + 1..];
+    let keyword_end = rest.iter().position(|&b| b == 0)?;
+    Some(&rest[keyword_end + 1..])
+}
+
+/// The markers and payloads of the segments of a JPEG file, up to the image data
+fn jpeg_segments(bytes: &[u8]) -> impl Iterator<Item = (u8, &[u8])> {
+    let mut offset = 2;
+    std::iter::from_fn(move || {
+        let header = bytes.get(offset..offset + 4)?;
+        // Start of scan: the entropy-coded data follows
+        if header[0] != 0xff || header[1] == 0xda {
+            return None;
+        }
+        let length = usize::from(u16::from_be_bytes([header[2], header[3]]));
+        let payload = bytes.get(offset + 4..(offset + 2 + length).max(offset + 4))?;
+        offset += 2 + length;
+        Some((header[1], payload))
+    })
+}
+
+/// The types and data of the chunks of a PNG file
+fn png_chunks(bytes: &[u8]) -> impl Iterator<Item = (&[u8], &[u8])> {
+    let mut offset = PNG_SIGNATURE.len();
+    std::iter::from_fn(move || {
+        let header = bytes.get(offset..offset + 8)?;
+        let length = u32::from_be_bytes([header[0], header[1], header[2], header[3]]) as usize;
+        let data = bytes.get(offset + 8..(offset + 8).checked_add(length)?)?;
+        offset += 12 + length;
+        Some((&header[4..], data))
+    })
+}
+
+fn push_png_chunk(bytes: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+    bytes.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    let start = bytes.len();
+    bytes.extend_from_slice(kind);
+    bytes.extend_from_slice(data);
+    let crc = crc32(&bytes[start..]);
+    bytes.extend_from_slice(&crc.to_be_bytes());
+}
+
+/// The CRC-32 of PNG chunks, one bit at a time: metadata blocks are small
+fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &byte in bytes {
+        crc ^= u32::from(byte);
+        for _ in 0..8 {
+            crc = (crc >> 1) ^ (0xedb8_8320 & (crc & 1).wrapping_neg());
+        }
+    }
+    !crc
+}
+
+/// Replaces the width and height in the first IFD and in the EXIF IFD of EXIF data,
+/// leaving malformed data unchanged
+fn set_exif_dimensions(exif: &mut [u8], width: u32, height: u32) {
+    let big_endian = match exif.get(..4) {
+        Some(b"MM\0*") => true,
+        Some(b"II*\0") => false,
+        _ => return,
+    };
+    let tiff = Tiff { big_endian };
+    let Some(first_ifd) = tiff.u32(exif, 4) else {
+        return;
+    };
+    let dimensions = [(EXIF_IMAGE_WIDTH, width), (EXIF_IMAGE_LENGTH, height)];
+    if let Some(exif_ifd) = tiff.set_dimensions(exif, first_ifd, dimensions) {
+        let dimensions = [
+            (EXIF_PIXEL_X_DIMENSION, width),
+            (EXIF_PIXEL_Y_DIMENSION, height),
+        ];
+        tiff.set_dimensions(exif, exif_ifd, dimensions);
+    }
+}
+
+/// Byte order of EXIF data
+struct Tiff {
+    big_endian: bool,
+}
+
+impl Tiff {
+    fn u16(&self, bytes: &[u8], offset: usize) -> Option<u16> {
+        let b: [u8; 2] = bytes.get(offset..offset + 2)?.try_into().ok()?;
+        Some(if self.big_endian {
+            u16::from_be_bytes(b)
+        } else {
+            u16::from_le_bytes(b)
+        })
+    }
+
+    fn u32(&self, bytes: &[u8], offset: usize) -> Option<usize> {
+        let b: [u8; 4] = bytes.get(offset..offset + 4)?.try_into().ok()?;
+        let value = if self.big_endian {
+            u32::from_be_bytes(b)
+        } else {
+            u32::from_le_bytes(b)
+        };
+        Some(value as usize)
+    }
+
+    fn put(&self, bytes: &mut [u8], offset: usize, value: &[u8]) {
+        let mut value = value.to_vec();
+        if !self.big_endian {
+            value.reverse();
+        }
+        bytes[offset..offset + value.len()].copy_from_slice(&value);
+    }
+
+    /// Replaces the values of the given single-valued tags of the IFD at `ifd`,
+    /// as LONG values, and returns the offset of the EXIF IFD it points to
+    fn set_dimensions(&self, bytes: &mut [u8], ifd: usize, tags: [(u16, u32); 2]) -> Option<usize> {
+        let entries = self.u16(bytes, ifd)?;
+        let mut exif_ifd = None;
+        for entry in (0..usize::from(entries)).map(|i| ifd + 2 + 12 * i) {
+            if bytes.len() < entry + 12 {
+                break;
+            }
+            let tag = self.u16(bytes, entry)?;
+            if tag == EXIF_IFD_POINTER {
+                exif_ifd = self.u32(bytes, entry + 8);
+            }
+            let Some(&(_, value)) = tags.iter().find(|&&(t, _)| t == tag) else {
+                continue;
+            };
+            if self.u32(bytes, entry + 4) == Some(1) {
+                // Type LONG
+                self.put(bytes, entry + 2, &4u16.to_be_bytes());
+                self.put(bytes, entry + 8, &value.to_be_bytes());
+            }
+        }
+        exif_ifd
+    }
+}
+
+/// Replaces the values of the width and height properties of an XMP packet,
+/// written either as attributes or as elements
+fn set_xmp_dimensions(xmp: &str, width: u32, height: u32) -> String {
+    let mut xmp = xmp.to_string();
+    for (names, value) in [(XMP_WIDTHS, width), (XMP_HEIGHTS, height)] {
+        for name in names {
+            xmp = set_xmp_property(&xmp, name, value);
+        }
+    }
+    xmp
+}
+
+fn set_xmp_property(xmp: &str, name: &str, value: u32) -> String {
+    let mut result = String::with_capacity(xmp.len());
+    let mut rest = xmp;
+    while let Some(found) = rest.find(name) {
+        let after = &rest[found + name.len()..];
+        let (open, close) = if after.starts_with("=\"") {
+            (2, '"')
+        } else if after.starts_with("='") {
+            (2, '\'')
+        } else if after.starts_with('>') && rest[..found].ends_with('<') {
+            (1, '<')
+        } else {
+            result.push_str(&rest[..found + name.len()]);
+            rest = after;
+            continue;
+        };
+        let Some(end) = after[open..].find(close) else {
+            break;
+        };
+        result.push_str(&rest[..found + name.len() + open]);
+        result.push_str(&value.to_string());
+        rest = &after[open + end..];
+    }
+    result.push_str(rest);
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use image::{ImageFormat, Rgba, RgbaImage};
+
+    use super::*;
+    use crate::{encode_image, load_image};
+
+    /// Little endian EXIF data, with a width in the first IFD and dimensions in the EXIF IFD
+    fn exif() -> Vec<u8> {
+        let mut exif = b"II*\0\x08\0\0\0".to_vec();
+        // First IFD: ImageWidth as a SHORT, and the pointer to the EXIF IFD
+        exif.extend_from_slice(&[2, 0]);
+        exif.extend_from_slice(&[0x00, 0x01, 3, 0, 1, 0, 0, 0, 40, 0, 0, 0]);
+        exif.extend_from_slice(&[0x69, 0x87, 4, 0, 1, 0, 0, 0, 38, 0, 0, 0]);
+        exif.extend_from_slice(&[0, 0, 0, 0]);
+        // EXIF IFD: PixelXDimension and PixelYDimension as LONGs
+        exif.extend_from_slice(&[2, 0]);
+        exif.extend_from_slice(&[0x02, 0xa0, 4, 0, 1, 0, 0, 0, 40, 0, 0, 0]);
+        exif.extend_from_slice(&[0x03, 0xa0, 4, 0, 1, 0, 0, 0, 30, 0, 0, 0]);
+        exif.extend_from_slice(&[0, 0, 0, 0]);
+        exif
+    }
+
+    fn xmp() -> Vec<u8> {
+        let xmp = r#"<x:xmpmeta><rdf:Description exif:PixelXDimension="40" tiff:ImageWidth='40'>
+<exif:PixelYDimension>30</exif:PixelYDimension></rdf:Description></x:xmpmeta>"#;
+        xmp.as_bytes().to_vec()
+    }
+
+    #[test]
+    fn copies_metadata_with_new_dimensions() {
+        let img = RgbaImage::from_pixel(12, 8, Rgba([10, 20, 30, 255]));
+        let metadata = Metadata {
+            exif: Some(exif()),
+            xmp: Some(xmp()),
+        };
+        let resized = metadata.resized(12, 8);
+        for format in [ImageFormat::Jpeg, ImageFormat::Png] {
+            let bytes = metadata.embed(&encode_image(&img, format).unwrap());
+            assert_eq!(Metadata::read(&bytes), metadata);
+            let bytes = resized.embed(&bytes);
+            assert_eq!(load_image(&bytes).unwrap().to_rgba8().dimensions(), (12, 8));
+        }
+        let exif = resized.exif.unwrap();
+        assert_eq!(&exif[10..20], &[0x00, 0x01, 4, 0, 1, 0, 0, 0, 12, 0]);
+        assert_eq!(&exif[40..50], &[0x02, 0xa0, 4, 0, 1, 0, 0, 0, 12, 0]);
+        assert_eq!(&exif[52..62], &[0x03, 0xa0, 4, 0, 1, 0, 0, 0, 8, 0]);
+        let xmp = String::from_utf8(resized.xmp.unwrap()).unwrap();
+        assert!(xmp.contains(r#"exif:PixelXDimension="12" tiff:ImageWidth='12'"#));
+        assert!(xmp.contains("<exif:PixelYDimension>8</exif:PixelYDimension>"));
+    }
+
+    #[test]
+    fn computes_png_checksums() {
+        assert_eq!(crc32(b"IEND"), 0xae42_6082);
+    }
+}
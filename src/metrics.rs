@@ -0,0 +1,315 @@
+use image::imageops::{resize, FilterType};
+use image::{GenericImageView, GrayImage, Luma, Pixel, Rgb, RgbImage, Rgba, RgbaImage};
+
+use crate::energy::{energy_fn, luminosity, to_8bit};
+use crate::pos::Pos;
+
+/// The images are compared at most this many pixels wide or tall,
+/// since every patch of each image is compared to every patch of the other one
+const MAX_COMPARED_SIDE: u32 = 48;
+
+/// Radius of the square patches compared by the bidirectional similarity
+const PATCH_RADIUS: i64 = 2;
+
+/// Quality metrics of a resized image compared to its original, see [compare_images]
+#[derive(Clone, PartialEq, Debug)]
+pub struct Comparison {
+    /// The fraction of the gradient energy of the original image that the resized image
+    /// lacks, negative when the resized image has more energy, as enlarged images do
+    pub energy_removed: f64,
+    /// The mean distance between each patch of the original image
+    /// and the most similar patch of the resized image, between 0 and 1:
+    /// high when content of the original is missing from the resized image
+    pub completeness: f64,
+    /// The mean distance between each patch of the resized image
+    /// and the most similar patch of the original image, between 0 and 1:
+    /// high when the resized image has artifacts that are not in the original
+    pub coherence: f64,
+    /// How much of each region of the original image is missing from the resized image,
+    /// of the dimensions of the original image, from black for regions that were kept to white
+    pub missing: GrayImage,
+}
+
+impl Comparison {
+    /// The bidirectional similarity of the images, the sum of their
+    /// [completeness](Comparison::completeness) and [coherence](Comparison::coherence):
+    /// 0 for identical images, and higher the more the resized image differs
+    pub fn bidirectional_similarity(&self) -> f64 {
+        self.completeness + self.coherence
+    }
+
+    /// The metrics as a JSON object, without the missing regions
+    pub fn to_json(&self) -> String {
+        format!(
+            "{{\"energy_removed\":{},\"completeness\":{},\"coherence\":{},\
+             \"bidirectional_similarity\":{}}}",
+            self.energy_removed,
+            self.completeness,
+            self.coherence,
+            self.bidirectional_similarity()
+        )
+    }
+
+    /// The original image, with the regions that are missing from the resized image
+    /// tinted in red, the more they are missing
+    pub fn diff_image<IMG: GenericImageView>(&self, original: &IMG) -> RgbaImage {
+        RgbaImage::from_fn(original.width(), original.height(), |x, y| {
+            let pixel = original.get_pixel(x, y).to_rgb();
+            let alpha = f32::from(self.missing.get_pixel(x, y).0[0]) / 255.;
+            let mix = |channel, tint: f32| {
+                let channel = f32::from(to_8bit(channel));
+                (channel * (1. - alpha) + tint * alpha).round() as u8
+            };
+            let [r, g, b] = pixel.0;
+            Rgba([mix(r, 255.), mix(g, 0.), mix(b, 0.), 255])
+        })
+    }
+}
+
+/// Measures how well a resized image keeps the content of the original one,
+/// so that carving settings can be evaluated automatically.
+///
+/// The energy removed compares the gradient energies of the images.
+/// The bidirectional similarity compares their 5x5 patches, as in
+/// "Summarizing visual data using bidirectional similarity" (Simakov et al.):
+/// every patch of each image is matched with the most similar patch of the other one.
+/// Images larger than 48 pixels are scaled down for this, by the same factor.
+/// A resized image of width or height 0 lacks all the content and energy of the original:
+/// its completeness is 1, its coherence 0, and all of the original is missing.
+///
+/// ```
+/// let img = image::RgbImage::from_fn(24, 16, |x, y| image::Rgb([(x * 10) as u8, y as u8, 0]));
+/// let resized = seamcarving::resize(&img, 18, 16);
+/// let comparison = seamcarving::compare_images(&img, &resized);
+/// assert!(comparison.bidirectional_similarity() > 0.);
+/// assert_eq!(seamcarving::compare_images(&img, &img).bidirectional_similarity(), 0.);
+/// ```
+pub fn compare_images<A: GenericImageView, B: GenericImageView>(
+    original: &A,
+    resized: &B,
+) -> Comparison {
+    let (original_energy, resized_energy) = (total_energy(original), total_energy(resized));
+    let energy_removed = if original_energy == 0 {
+        0.
+    } else {
+        1. - resized_energy as f64 / original_energy as f64
+    };
+
+    let (width, height) = original.dimensions();
+    let scale = (f64::from(MAX_COMPARED_SIDE) / f64::from(width.max(height).max(1))).min(1.);
+    let small_original = scaled(original, scale);
+    let small_resized = scaled(resized, scale);
+    let missing_patches = nearest_patches(&small_original, &small_resized);
+    let extra_patches = nearest_patches(&small_resized, &small_original);
+    let mean = |distances: &[f64]| {
+        if distances.is_empty() {
+            0.
+        } else {
+            distances.iter().sum::<f64>() / distances.len() as f64
+        }
+    };
+    let missing = GrayImage::from_fn(small_original.width(), small_original.height(), |x, y| {
+        let distance = missing_patches[(y * small_original.width() + x) as usize];
+        Luma([(distance.sqrt() * 255.).round().min(255.) as u8])
+    });
+    Comparison {
+        energy_removed,
+        completeness: mean(&missing_patches),
+        coherence: mean(&extra_patches),
+        missing: if missing.dimensions() == (width, height) {
+            missing
+        } else {
+            resize(&missing, width, height, FilterType::Triangle)
+        },
+    }
+}
+
+/// The structural similarity (SSIM) of two images of the same dimensions,
+/// from 1 for identical images down to 0, or below for inverted ones.
+///
+/// It compares the mean, the contrast and the correlation of the luminosities
+/// of the images in windows of 8x8 pixels that do not overlap, a cheap approximation
+/// of the sliding gaussian windows of "Image quality assessment: from error visibility
+/// to structural similarity" (Wang et al.), and averages them.
+///
+/// ```
+/// let img = image::GrayImage::from_fn(32, 16, |x, y| image::Luma([(x * 8 + y) as u8]));
+/// assert_eq!(seamcarving::ssim(&img, &img), 1.);
+/// let noisy = image::GrayImage::from_fn(32, 16, |x, y| image::Luma([(x * y * 37 % 256) as u8]));
+/// assert!(seamcarving::ssim(&img, &noisy) < 0.5);
+/// ```
+///
+/// # Panics
+///
+/// If the images do not have the same dimensions
+pub fn ssim<A: GenericImageView, B: GenericImageView>(a: &A, b: &B) -> f64 {
+    assert_eq!(a.dimensions(), b.dimensions(), "the images must have the same dimensions");
+    gray_ssim(&luminosity(a), &luminosity(b))
+}
+
+/// The [ssim] of two 8-bit luminosities of the same dimensions
+pub(crate) fn gray_ssim(a: &GrayImage, b: &GrayImage) -> f64 {
+    const WINDOW: u32 = 8;
+    const C1: f64 = (0.01 * 255.) * (0.01 * 255.);
+    const C2: f64 = (0.03 * 255.) * (0.03 * 255.);
+    let (width, height) = a.dimensions();
+    let mut windows = Vec::new();
+    for top in (0..height).step_by(WINDOW as usize) {
+        for left in (0..width).step_by(WINDOW as usize) {
+            let window = Pos::iter_in_rect(
+                Pos(left, top),
+                Pos((left + WINDOW).min(width), (top + WINDOW).min(height)),
+            );
+            let (mut sum_a, mut sum_b, mut sum_aa, mut sum_bb, mut sum_ab) = (0., 0., 0., 0., 0.);
+            let mut count = 0.;
+            for Pos(x, y) in window {
+                let va = f64::from(a.get_pixel(x, y).0[0]);
+                let vb = f64::from(b.get_pixel(x, y).0[0]);
+                sum_a += va;
+                sum_b += vb;
+                sum_aa += va * va;
+                sum_bb += vb * vb;
+                sum_ab += va * vb;
+                count += 1.;
+            }
+            let (mean_a, mean_b) = (sum_a / count, sum_b / count);
+            let variance_a = sum_aa / count - mean_a * mean_a;
+            let variance_b = sum_bb / count - mean_b * mean_b;
+            let covariance = sum_ab / count - mean_a * mean_b;
+            let luminance = (2. * mean_a * mean_b + C1) / (mean_a * mean_a + mean_b * mean_b + C1);
+            let structure = (2. * covariance + C2) / (variance_a + variance_b + C2);
+            windows.push(luminance * structure);
+        }
+    }
+    if windows.is_empty() {
+        1.
+    } else {
+        windows.iter().sum::<f64>() / windows.len() as f64
+    }
+}
+
+/// The sum of the gradient energies of the pixels of an image
+fn total_energy<IMG: GenericImageView>(img: &IMG) -> u64 {
+    pixels(img).map(|pos| u64::from(energy_fn(img, pos))).sum()
+}
+
+/// The positions of the pixels of an image
+fn pixels<IMG: GenericImageView>(img: &IMG) -> impl Iterator<Item = Pos> {
+    let (width, height) = img.dimensions();
+    Pos::iter_in_rect(Pos(0, 0), Pos(width, height))
+}
+
+/// The image in 8-bit RGB, scaled by the given factor
+fn scaled<IMG: GenericImageView>(img: &IMG, scale: f64) -> RgbImage {
+    let rgb = RgbImage::from_fn(img.width(), img.height(), |x, y| {
+        let [r, g, b] = img.get_pixel(x, y).to_rgb().0;
+        Rgb([to_8bit(r), to_8bit(g), to_8bit(b)])
+    });
+    if scale >= 1. || rgb.width() == 0 || rgb.height() == 0 {
+        return rgb;
+    }
+    let side = |side: u32| ((f64::from(side) * scale).round() as u32).max(1);
+    resize(
+        &rgb,
+        side(img.width()),
+        side(img.height()),
+        FilterType::Triangle,
+    )
+}
+
+/// For each patch of `from`, in row order, the distance to the most similar patch of `to`:
+/// the mean squared difference of their channels, between 0 and 1.
+/// The pixels outside of the images are the ones on their edges.
+fn nearest_patches(from: &RgbImage, to: &RgbImage) -> Vec<f64> {
+    let (from_patches, to_patches) = (patches(from), patches(to));
+    from_patches
+        .iter()
+        .map(|patch| {
+            to_patches
+                .iter()
+                .map(|other| {
+                    let squares = patch.iter().zip(other).map(|(a, b)| (a - b) * (a - b));
+                    squares.sum::<f32>()
+                })
+                .fold(f32::INFINITY, f32::min)
+        })
+        .map(|distance| {
+            let values = patch_len() as f64;
+            if distance.is_finite() {
+                f64::from(distance) / values / (255. * 255.)
+            } else {
+                1.
+            }
+        })
+        .collect()
+}
+
+/// The number of channel values of a patch
+fn patch_len() -> usize {
+    let side = (2 * PATCH_RADIUS + 1) as usize;
+    side * side * 3
+}
+
+/// The channels of the patch around each pixel of the image, in row order
+fn patches(img: &RgbImage) -> Vec<Vec<f32>> {
+    let (width, height) = (i64::from(img.width()), i64::from(img.height()));
+    pixels(img)
+        .map(|Pos(x, y)| {
+            let mut patch = Vec::with_capacity(patch_len());
+            for dy in -PATCH_RADIUS..=PATCH_RADIUS {
+                for dx in -PATCH_RADIUS..=PATCH_RADIUS {
+                    let px = (i64::from(x) + dx).clamp(0, width - 1) as u32;
+                    let py = (i64::from(y) + dy).clamp(0, height - 1) as u32;
+                    patch.extend(img.get_pixel(px, py).0.iter().map(|&c| f32::from(c)));
+                }
+            }
+            patch
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn compares_with_empty_images() {
+        let img = RgbImage::from_fn(60, 20, |x, y| Rgb([(x * 4) as u8, (y * 9) as u8, 0]));
+        for empty in [RgbImage::new(0, 20), RgbImage::new(60, 0), RgbImage::new(0, 0)] {
+            let comparison = compare_images(&img, &empty);
+            assert_eq!(comparison.energy_removed, 1.);
+            assert_eq!((comparison.completeness, comparison.coherence), (1., 0.));
+            assert_eq!(comparison.missing.dimensions(), (60, 20));
+            assert!(comparison.missing.pixels().all(|pixel| pixel.0[0] == 255));
+        }
+        let empty = compare_images(&RgbImage::new(0, 5), &RgbImage::new(0, 5));
+        assert_eq!(empty.bidirectional_similarity(), 0.);
+    }
+
+    #[test]
+    fn finds_missing_content() {
+        // A bright square on the left of a flat image
+        let img = RgbImage::from_fn(24, 12, |x, y| {
+            Rgb(if (2..6).contains(&x) && (4..8).contains(&y) {
+                [255, 255, 255]
+            } else {
+                [40, 80, 120]
+            })
+        });
+        let without_square = RgbImage::from_pixel(20, 12, Rgb([40, 80, 120]));
+        let comparison = compare_images(&img, &without_square);
+        assert_eq!(comparison.energy_removed, 1.);
+        assert_eq!(comparison.coherence, 0.);
+        assert!(comparison.completeness > 0.);
+        assert_eq!(comparison.missing.dimensions(), (24, 12));
+        assert!(comparison.missing.get_pixel(3, 5).0[0] > 100);
+        assert_eq!(comparison.missing.get_pixel(20, 5).0[0], 0);
+        let diff = comparison.diff_image(&img);
+        assert_eq!(diff.get_pixel(20, 5), &Rgba([40, 80, 120, 255]));
+        assert!(comparison.to_json().starts_with("{\"energy_removed\":1,"));
+
+        let carved = crate::resize(&img, 20, 12);
+        let carved = compare_images(&img, &carved);
+        assert!(carved.bidirectional_similarity() < comparison.bidirectional_similarity());
+    }
+}
@@ -0,0 +1,197 @@
+use std::io::Cursor;
+use std::path::Path;
+
+use image::error::{
+    DecodingError, EncodingError, ImageError, ImageFormatHint, UnsupportedError,
+    UnsupportedErrorKind,
+};
+use image::{DynamicImage, GenericImageView, ImageBuffer, ImageFormat, ImageResult, RgbaImage};
+use tiff::decoder::{Decoder, DecodingResult};
+use tiff::encoder::{colortype, TiffEncoder};
+use tiff::{ColorType, TiffError};
+
+use crate::{load_image, resize_frames, save_image, Carver, PixelBuffer, SeamError};
+
+/// Opens all the pages of a multi-page TIFF image, such as an archive of scanned documents.
+/// Images in the other formats supported by [open_image](crate::open_image) have one page.
+///
+/// ```no_run
+/// let pages = seamcarving::open_pages("./scans.tiff").unwrap();
+/// let pages: Vec<_> = pages.iter().map(|page| page.to_rgba8()).collect();
+/// let carver = seamcarving::Carver::new().width(seamcarving::Dimension::Percent(80));
+/// let resized = seamcarving::resize_pages(&carver, &pages, true).unwrap();
+/// seamcarving::save_pages(&resized, "./resized.tiff").unwrap();
+/// ```
+pub fn open_pages(path: impl AsRef<Path>) -> ImageResult<Vec<DynamicImage>> {
+    load_pages(&std::fs::read(path)?)
+}
+
+/// Decodes all the pages of an image, see [open_pages]
+pub fn load_pages(bytes: &[u8]) -> ImageResult<Vec<DynamicImage>> {
+    if image::guess_format(bytes)? != ImageFormat::Tiff {
+        return Ok(vec![load_image(bytes)?]);
+    }
+    let mut decoder = Decoder::new(Cursor::new(bytes)).map_err(decoding_error)?;
+    let mut pages = vec![decode_page(&mut decoder)?];
+    while decoder.more_images() {
+        decoder.next_image().map_err(decoding_error)?;
+        pages.push(decode_page(&mut decoder)?);
+    }
+    Ok(pages)
+}
+
+/// Decodes the first page of a TIFF image, for [load_image]
+pub(crate) fn decode_tiff(bytes: &[u8]) -> ImageResult<DynamicImage> {
+    let mut decoder = Decoder::new(Cursor::new(bytes)).map_err(decoding_error)?;
+    decode_page(&mut decoder)
+}
+
+/// The dimensions of the first page of a TIFF image, read without decoding it
+pub(crate) fn tiff_dimensions(bytes: &[u8]) -> ImageResult<(u32, u32)> {
+    let mut decoder = Decoder::new(Cursor::new(bytes)).map_err(decoding_error)?;
+    decoder.dimensions().map_err(decoding_error)
+}
+
+/// Saves pages to a multi-page TIFF file. Files in other formats, given by the extension
+/// of the path, can only hold a single page, which is saved with [save_image].
+pub fn save_pages(pages: &[RgbaImage], path: impl AsRef<Path>) -> ImageResult<()> {
+    let format = ImageFormat::from_path(&path)?;
+    match (format, pages) {
+        (ImageFormat::Tiff, _) => std::fs::write(path, encode_pages(pages)?)?,
+        (_, [page]) => save_image(page, path)?,
+        _ => {
+            return Err(ImageError::Unsupported(UnsupportedError::from_format_and_kind(
+                ImageFormatHint::Exact(format),
+                UnsupportedErrorKind::GenericFeature(format!("{} pages", pages.len())),
+            )))
+        }
+    }
+    Ok(())
+}
+
+/// Encodes pages as a multi-page TIFF image, without compression
+pub fn encode_pages(pages: &[RgbaImage]) -> ImageResult<Vec<u8>> {
+    let mut bytes = Cursor::new(Vec::new());
+    let mut encoder = TiffEncoder::new(&mut bytes).map_err(encoding_error)?;
+    for page in pages {
+        let (width, height) = page.dimensions();
+        encoder
+            .write_image::<colortype::RGBA8>(width, height, page.as_raw())
+            .map_err(encoding_error)?;
+    }
+    Ok(bytes.into_inner())
+}
+
+/// Resizes the pages of a document with a carver, to the dimensions it gives to each page.
+///
+/// With `shared_seams`, the same seams are removed from all the pages, as [resize_frames] does,
+/// so that aligned scans, such as the layers of a map or the sides of a form,
+/// stay aligned. The pages are then resized to the dimensions the carver gives
+/// to the first one, and fail with [SeamError::SizeMismatch] if they do not all have
+/// the same dimensions. Only the target dimensions of the carver are used in that case.
+pub fn resize_pages<IMG: GenericImageView>(
+    carver: &Carver,
+    pages: &[IMG],
+    shared_seams: bool,
+) -> Result<Vec<PixelBuffer<IMG::Pixel>>, SeamError>
+where
+    IMG::Pixel: 'static,
+{
+    match pages.first() {
+        Some(first) if shared_seams => {
+            let (width, height) = carver.target_dimensions(first);
+            resize_frames(pages, width, height)
+        }
+        _ => pages.iter().map(|page| carver.resize(page)).collect(),
+    }
+}
+
+/// Decodes the current page of a TIFF image
+fn decode_page(decoder: &mut Decoder<Cursor<&[u8]>>) -> ImageResult<DynamicImage> {
+    let (width, height) = decoder.dimensions().map_err(decoding_error)?;
+    let color = decoder.colortype().map_err(decoding_error)?;
+    let pixels = decoder.read_image().map_err(decoding_error)?;
+    let page = match (color, pixels) {
+        (ColorType::Gray(8), DecodingResult::U8(pixels)) => {
+            ImageBuffer::from_raw(width, height, pixels).map(DynamicImage::ImageLuma8)
+        }
+        (ColorType::GrayA(8), DecodingResult::U8(pixels)) => {
+            ImageBuffer::from_raw(width, height, pixels).map(DynamicImage::ImageLumaA8)
+        }
+        (ColorType::RGB(8), DecodingResult::U8(pixels)) => {
+            ImageBuffer::from_raw(width, height, pixels).map(DynamicImage::ImageRgb8)
+        }
+        (ColorType::RGBA(8), DecodingResult::U8(pixels)) => {
+            ImageBuffer::from_raw(width, height, pixels).map(DynamicImage::ImageRgba8)
+        }
+        (ColorType::Gray(16), DecodingResult::U16(pixels)) => {
+            ImageBuffer::from_raw(width, height, pixels).map(DynamicImage::ImageLuma16)
+        }
+        (ColorType::GrayA(16), DecodingResult::U16(pixels)) => {
+            ImageBuffer::from_raw(width, height, pixels).map(DynamicImage::ImageLumaA16)
+        }
+        (ColorType::RGB(16), DecodingResult::U16(pixels)) => {
+            ImageBuffer::from_raw(width, height, pixels).map(DynamicImage::ImageRgb16)
+        }
+        (ColorType::RGBA(16), DecodingResult::U16(pixels)) => {
+            ImageBuffer::from_raw(width, height, pixels).map(DynamicImage::ImageRgba16)
+        }
+        (color, _) => {
+            return Err(ImageError::Unsupported(UnsupportedError::from_format_and_kind(
+                ImageFormat::Tiff.into(),
+                UnsupportedErrorKind::GenericFeature(format!("{:?} pixels", color)),
+            )))
+        }
+    };
+    page.ok_or_else(|| {
+        ImageError::Decoding(DecodingError::from_format_hint(ImageFormat::Tiff.into()))
+    })
+}
+
+fn decoding_error(error: TiffError) -> ImageError {
+    ImageError::Decoding(DecodingError::new(ImageFormat::Tiff.into(), error))
+}
+
+fn encoding_error(error: TiffError) -> ImageError {
+    ImageError::Encoding(EncodingError::new(ImageFormat::Tiff.into(), error))
+}
+
+#[cfg(test)]
+mod tests {
+    use image::{GrayImage, Luma, Rgba};
+
+    use super::*;
+
+    fn page(i: u32) -> RgbaImage {
+        RgbaImage::from_fn(16, 10, |x, y| Rgba([(x * y + i * 40) as u8, (x * 9) as u8, 0, 255]))
+    }
+
+    #[test]
+    fn encodes_and_decodes_pages() {
+        let pages = [page(0), page(1), page(2)];
+        let bytes = encode_pages(&pages).unwrap();
+        let decoded: Vec<RgbaImage> =
+            load_pages(&bytes).unwrap().iter().map(|page| page.to_rgba8()).collect();
+        assert_eq!(decoded, pages);
+        assert_eq!(load_image(&bytes).unwrap().to_rgba8(), pages[0]);
+
+        let png = crate::encode_image(&page(0), ImageFormat::Png).unwrap();
+        assert_eq!(load_pages(&png).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn removes_the_same_seams_from_all_the_pages() {
+        // A column at a different place on each page
+        let pages: Vec<GrayImage> = (0..2)
+            .map(|i| GrayImage::from_fn(12, 6, |x, _y| Luma([if x == 3 + i * 5 { 200 } else { 0 }])))
+            .collect();
+        let carver = Carver::new().width(8);
+        let shared = resize_pages(&carver, &pages, true).unwrap();
+        assert_eq!(shared, resize_frames(&pages, 8, 6).unwrap());
+        let independent = resize_pages(&carver, &pages, false).unwrap();
+        assert_eq!(independent[1], carver.resize(&pages[1]).unwrap());
+        let mismatched = [GrayImage::new(12, 6), GrayImage::new(10, 6)];
+        assert_eq!(resize_pages(&carver, &mismatched, true), Err(SeamError::SizeMismatch));
+        assert_eq!(resize_pages(&carver, &mismatched, false).unwrap().len(), 2);
+    }
+}
@@ -0,0 +1,344 @@
+use std::sync::Arc;
+
+use image::imageops::{blur, unsharpen};
+use image::{GrayImage, RgbaImage};
+
+use crate::{Carver, ImportanceMap, SeamError};
+
+/// Transforms an image before its energy is computed, in a [Pipeline].
+/// The seams are removed from the transformed image, which must keep its dimensions.
+pub trait Prefilter: Send + Sync {
+    /// Describes the stage and its parameters, such as `blur(sigma=1.5)`
+    fn name(&self) -> String;
+
+    /// Returns the transformed image
+    fn filter(&self, img: &RgbaImage) -> RgbaImage;
+}
+
+/// Computes the importance that seams avoid, in a [Pipeline].
+/// The importance of all the energy stages is added to the energy of the carver.
+pub trait EnergyStage: Send + Sync {
+    /// Describes the stage and its parameters
+    fn name(&self) -> String;
+
+    /// Returns the importance of each pixel of the prefiltered image
+    fn importance(&self, img: &RgbaImage) -> ImportanceMap;
+}
+
+impl<S: EnergyStage + ?Sized> EnergyStage for Box<S> {
+    fn name(&self) -> String {
+        (**self).name()
+    }
+
+    fn importance(&self, img: &RgbaImage) -> ImportanceMap {
+        (**self).importance(img)
+    }
+}
+
+/// Protects or forbids pixels of the images, in a [Pipeline].
+pub trait Constraint: Send + Sync {
+    /// Describes the stage and its parameters
+    fn name(&self) -> String;
+
+    /// Returns the protected and forbidden pixels of the prefiltered image,
+    /// see [ImportanceMap::from_mask] and [ImportanceMap::forbidden]
+    fn constrain(&self, img: &RgbaImage) -> ImportanceMap;
+}
+
+/// Removes the seams from the images, in a [Pipeline], such as a [Carver]
+pub trait CarveStage: Send + Sync {
+    /// Describes the stage and its parameters
+    fn name(&self) -> String;
+
+    /// Resizes the prefiltered image, seams avoiding the pixels of the given importance
+    fn carve(
+        &self,
+        img: &RgbaImage,
+        importance: Option<&ImportanceMap>,
+    ) -> Result<RgbaImage, SeamError>;
+}
+
+/// Transforms the resized images, in a [Pipeline]
+pub trait Postprocess: Send + Sync {
+    /// Describes the stage and its parameters
+    fn name(&self) -> String;
+
+    /// Returns the transformed image
+    fn process(&self, img: RgbaImage) -> RgbaImage;
+}
+
+/// A resizing configuration made of stages, declared once and then used on any number
+/// of images: prefilters, energy stages, constraints, the carver, and postprocesses,
+/// run in this order. Stages of the same kind run in the order they were added.
+///
+/// Every stage is a trait object, so applications can add their own.
+/// Pipelines are cheap to clone, and can be shared between threads.
+/// [stages](Pipeline::stages) describes each of them, for logs and configuration files.
+///
+/// ```
+/// use seamcarving::{Blur, Carver, Pipeline, Textures};
+/// let pipeline = Pipeline::new(Carver::new().width(20))
+///     .prefilter(Blur(1.))
+///     .energy(Textures { radius: 2, strength: 0.5 });
+/// let img = image::RgbaImage::from_fn(30, 10, |x, y| image::Rgba([(x * y) as u8, 0, 0, 255]));
+/// assert_eq!(pipeline.run(&img).unwrap().dimensions(), (20, 10));
+/// assert_eq!(pipeline.stages()[0], "blur(sigma=1)");
+/// ```
+#[derive(Clone)]
+pub struct Pipeline {
+    prefilters: Vec<Arc<dyn Prefilter>>,
+    energies: Vec<Arc<dyn EnergyStage>>,
+    constraints: Vec<Arc<dyn Constraint>>,
+    carver: Arc<dyn CarveStage>,
+    postprocesses: Vec<Arc<dyn Postprocess>>,
+}
+
+impl Pipeline {
+    /// A pipeline that only removes seams with the given carver
+    pub fn new(carver: impl CarveStage + 'static) -> Self {
+        Pipeline {
+            prefilters: Vec::new(),
+            energies: Vec::new(),
+            constraints: Vec::new(),
+            carver: Arc::new(carver),
+            postprocesses: Vec::new(),
+        }
+    }
+
+    /// Adds a prefilter, run after the ones already added
+    pub fn prefilter(mut self, stage: impl Prefilter + 'static) -> Self {
+        self.prefilters.push(Arc::new(stage));
+        self
+    }
+
+    /// Adds an energy stage
+    pub fn energy(mut self, stage: impl EnergyStage + 'static) -> Self {
+        self.energies.push(Arc::new(stage));
+        self
+    }
+
+    /// Adds a constraint
+    pub fn constraint(mut self, stage: impl Constraint + 'static) -> Self {
+        self.constraints.push(Arc::new(stage));
+        self
+    }
+
+    /// Replaces the carver
+    pub fn carver(mut self, stage: impl CarveStage + 'static) -> Self {
+        self.carver = Arc::new(stage);
+        self
+    }
+
+    /// Adds a postprocess, run after the ones already added
+    pub fn postprocess(mut self, stage: impl Postprocess + 'static) -> Self {
+        self.postprocesses.push(Arc::new(stage));
+        self
+    }
+
+    /// The names of the stages, in the order they are run
+    pub fn stages(&self) -> Vec<String> {
+        let prefilters = self.prefilters.iter().map(|stage| stage.name());
+        let energies = self.energies.iter().map(|stage| stage.name());
+        let constraints = self.constraints.iter().map(|stage| stage.name());
+        let postprocesses = self.postprocesses.iter().map(|stage| stage.name());
+        prefilters
+            .chain(energies)
+            .chain(constraints)
+            .chain(Some(self.carver.name()))
+            .chain(postprocesses)
+            .collect()
+    }
+
+    /// Runs all the stages on an image.
+    ///
+    /// Fails with [SeamError::SizeMismatch] if a prefilter changes the dimensions of the image,
+    /// or if the importance of an energy stage or of a constraint does not match them.
+    pub fn run(&self, img: &RgbaImage) -> Result<RgbaImage, SeamError> {
+        let mut filtered = None;
+        for prefilter in &self.prefilters {
+            let next = prefilter.filter(filtered.as_ref().unwrap_or(img));
+            if next.dimensions() != img.dimensions() {
+                return Err(SeamError::SizeMismatch);
+            }
+            filtered = Some(next);
+        }
+        let filtered = filtered.as_ref().unwrap_or(img);
+        let energies = self.energies.iter().map(|stage| stage.importance(filtered));
+        let constraints = self
+            .constraints
+            .iter()
+            .map(|stage| stage.constrain(filtered));
+        let mut importance: Option<ImportanceMap> = None;
+        for map in energies.chain(constraints) {
+            if map.dimensions() != filtered.dimensions() {
+                return Err(SeamError::SizeMismatch);
+            }
+            match &mut importance {
+                Some(importance) => importance.add(&map),
+                None => importance = Some(map),
+            }
+        }
+        let resized = self.carver.carve(filtered, importance.as_ref())?;
+        Ok(self
+            .postprocesses
+            .iter()
+            .fold(resized, |img, stage| stage.process(img)))
+    }
+}
+
+impl CarveStage for Carver {
+    fn name(&self) -> String {
+        "carver".to_string()
+    }
+
+    fn carve(
+        &self,
+        img: &RgbaImage,
+        importance: Option<&ImportanceMap>,
+    ) -> Result<RgbaImage, SeamError> {
+        match importance {
+            Some(importance) => self.clone().importance(importance).resize(img),
+            None => self.resize(img),
+        }
+    }
+}
+
+/// A gaussian blur of the given standard deviation, so that seams ignore noise and grain
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Blur(pub f32);
+
+impl Prefilter for Blur {
+    fn name(&self) -> String {
+        format!("blur(sigma={})", self.0)
+    }
+
+    fn filter(&self, img: &RgbaImage) -> RgbaImage {
+        blur(img, self.0)
+    }
+}
+
+/// The importance of richly textured regions, see [ImportanceMap::textures]
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Textures {
+    pub radius: u32,
+    pub strength: f32,
+}
+
+impl EnergyStage for Textures {
+    fn name(&self) -> String {
+        format!(
+            "textures(radius={}, strength={})",
+            self.radius, self.strength
+        )
+    }
+
+    fn importance(&self, img: &RgbaImage) -> ImportanceMap {
+        ImportanceMap::textures(img, self.radius, self.strength)
+    }
+}
+
+/// Protects the pixels that are not black in a mask, see [ImportanceMap::from_mask]
+#[derive(Clone, Debug)]
+pub struct Protect(pub GrayImage);
+
+impl Constraint for Protect {
+    fn name(&self) -> String {
+        "protect".to_string()
+    }
+
+    fn constrain(&self, _img: &RgbaImage) -> ImportanceMap {
+        ImportanceMap::from_mask(&self.0)
+    }
+}
+
+/// Forbids the pixels that are not black in a mask, see [ImportanceMap::forbidden]
+#[derive(Clone, Debug)]
+pub struct Forbid(pub GrayImage);
+
+impl Constraint for Forbid {
+    fn name(&self) -> String {
+        "forbid".to_string()
+    }
+
+    fn constrain(&self, _img: &RgbaImage) -> ImportanceMap {
+        ImportanceMap::forbidden(&self.0)
+    }
+}
+
+/// Sharpens the resized images with an unsharp mask, see [image::imageops::unsharpen]
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Sharpen {
+    pub sigma: f32,
+    pub threshold: i32,
+}
+
+impl Postprocess for Sharpen {
+    fn name(&self) -> String {
+        format!(
+            "sharpen(sigma={}, threshold={})",
+            self.sigma, self.threshold
+        )
+    }
+
+    fn process(&self, img: RgbaImage) -> RgbaImage {
+        unsharpen(&img, self.sigma, self.threshold)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use image::{Luma, Rgba};
+
+    use super::*;
+
+    /// A postprocess turning every pixel white
+    struct White;
+
+    impl Postprocess for White {
+        fn name(&self) -> String {
+            "white".to_string()
+        }
+
+        fn process(&self, img: RgbaImage) -> RgbaImage {
+            RgbaImage::from_pixel(img.width(), img.height(), Rgba([255; 4]))
+        }
+    }
+
+    #[test]
+    fn runs_every_stage() {
+        let img = RgbaImage::from_fn(20, 8, |x, y| Rgba([(x * 13 + y * 7) as u8, 0, 0, 255]));
+        // A bright column that a forbidding constraint keeps
+        let column = GrayImage::from_fn(20, 8, |x, _y| Luma([if x == 4 { 255 } else { 0 }]));
+        let pipeline = Pipeline::new(Carver::new().width(12))
+            .prefilter(Blur(0.5))
+            .energy(Textures {
+                radius: 1,
+                strength: 1.,
+            })
+            .constraint(Forbid(column.clone()));
+        let carver_only = Carver::new()
+            .width(12)
+            .importance(&ImportanceMap::forbidden(&column));
+        let blurred = blur(&img, 0.5);
+        let expected = carver_only
+            .importance(&ImportanceMap::textures(&blurred, 1, 1.))
+            .resize(&blurred)
+            .unwrap();
+        assert_eq!(pipeline.run(&img).unwrap(), expected);
+        let white = pipeline.clone().postprocess(White).run(&img).unwrap();
+        assert!(white.pixels().all(|&p| p == Rgba([255; 4])));
+        assert_eq!(
+            pipeline.clone().postprocess(White).stages(),
+            vec![
+                "blur(sigma=0.5)",
+                "textures(radius=1, strength=1)",
+                "forbid",
+                "carver",
+                "white"
+            ]
+        );
+        let small = GrayImage::new(10, 8);
+        let mismatch = pipeline.constraint(Protect(small)).run(&img);
+        assert_eq!(mismatch, Err(SeamError::SizeMismatch));
+    }
+}
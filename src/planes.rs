@@ -0,0 +1,88 @@
+use alloc::vec::Vec;
+
+use crate::grid::{carve, dimensions};
+use crate::matrix::Matrix;
+use crate::pos::Pos;
+use crate::seam_finder::FORBIDDEN;
+use crate::SeamError;
+
+/// Resizes all the planes of an image at once, such as its color channels, its alpha channel,
+/// a depth map or any other data stored as floats, removing the same pixels from all of them.
+/// Returns the carved planes, in the same order.
+///
+/// The seams are only found in the planes with a non-zero weight: the energy of a pixel
+/// is the sum of the squared gradients of these planes, after their values are multiplied
+/// by their weight. A weight of 255 gives planes between 0 and 1 the energy
+/// of the channels of an 8-bit image. The vertical seams are removed first.
+///
+/// Fails with [SeamError::SizeMismatch] if the planes do not all have the same dimensions,
+/// or if there is not one weight per plane.
+///
+/// ```
+/// use seamcarving::{resize_planes, Matrix, Pos};
+/// let red = Matrix::from_fn(Pos(20, 10), |x, y| (x * y) as f32 / 200.);
+/// let depth = Matrix::from_fn(Pos(20, 10), |x, _y| x as f32);
+/// // Seams are found in the color only
+/// let planes = resize_planes(&[red, depth], &[255., 0.], (15, 8)).unwrap();
+/// assert!(planes.iter().all(|plane| (plane.width(), plane.height()) == (15, 8)));
+/// ```
+pub fn resize_planes(
+    planes: &[Matrix<f32>],
+    weights: &[f32],
+    (width, height): (u32, u32),
+) -> Result<Vec<Matrix<f32>>, SeamError> {
+    let size = planes.first().map_or(Pos(0, 0), dimensions);
+    if weights.len() != planes.len() || planes.iter().any(|plane| dimensions(plane) != size) {
+        return Err(SeamError::SizeMismatch);
+    }
+    let mut planes = planes.to_vec();
+    let mut energy = |planes: &[Matrix<f32>], pos: Pos| {
+        let [top, bottom, left, right] = pos.surrounding(dimensions(&planes[0]));
+        let weighted = planes.iter().zip(weights);
+        let energy: f32 = weighted
+            .filter(|&(_, &weight)| weight != 0.)
+            .map(|(plane, &weight)| {
+                let vertical = (plane[top] - plane[bottom]) * weight;
+                let horizontal = (plane[left] - plane[right]) * weight;
+                vertical * vertical + horizontal * horizontal
+            })
+            .sum();
+        // Not a number is 0
+        (energy as u32).min(FORBIDDEN - 1)
+    };
+    let seams = (size.0.saturating_sub(width), size.1.saturating_sub(height));
+    carve(&mut planes, seams.0, false, &mut energy)?;
+    carve(&mut planes, seams.1, true, &mut energy)?;
+    Ok(planes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn removes_the_same_pixels_from_every_plane() {
+        let size = Pos(16, 12);
+        let color = Matrix::from_fn(size, |x, y| ((x * 7 + y * 13) % 11) as f32 / 10.);
+        // The coordinates of the pixels, which seams do not look at
+        let columns = Matrix::from_fn(size, |x, _y| x as f32);
+        let rows = Matrix::from_fn(size, |_x, y| y as f32);
+        let planes = [color.clone(), columns, rows];
+        let carved = resize_planes(&planes, &[255., 0., 0.], (11, 9)).unwrap();
+        for pos in Pos::iter_in_rect(Pos(0, 0), Pos(11, 9)) {
+            let origin = Pos(carved[1][pos] as u32, carved[2][pos] as u32);
+            assert_eq!(carved[0][pos], color[origin]);
+        }
+        // Seams avoid the pixels that stand out in the weighted planes
+        let spot = Matrix::from_fn(size, |x, y| match (x, y) {
+            (4..=6, 2..=4) => 1.,
+            _ => 0.,
+        });
+        let carved = resize_planes(&[spot, planes[1].clone()], &[255., 0.], (10, 12)).unwrap();
+        let kept = Pos::iter_in_rect(Pos(0, 0), Pos(10, 12)).filter(|&pos| carved[0][pos] == 1.);
+        assert_eq!(kept.count(), 9);
+        let mismatch = resize_planes(&planes, &[1.], (10, 10));
+        assert_eq!(mismatch.unwrap_err(), SeamError::SizeMismatch);
+        assert!(resize_planes(&[], &[], (1, 1)).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,158 @@
+use std::io::Read;
+
+use png::{ColorType, Decoder, DecodingError, Reader, Transformations};
+
+use crate::buffer::{gradient, BufferScratch};
+use crate::error::DecodeError;
+use crate::matrix::Matrix;
+use crate::pos::Pos;
+use crate::seam_finder::SeamFinder;
+
+/// The RGBA pixels of a resized image, row by row, and its width and height
+type Resized = (Vec<[u8; 4]>, (u32, u32));
+
+/// Decodes a PNG image from a reader and resizes it like [resize_buffer](crate::resize_buffer),
+/// cumulating the energy of its first rows while the next ones are still being decoded,
+/// so that the seams are found as soon as the last row is read.
+/// The file itself is never held in memory, only its pixels.
+///
+/// Returns the pixels of the resized image as RGBA, row by row, and its width and height.
+/// Interlaced images are decoded entirely before their energy is computed.
+///
+/// ```no_run
+/// let file = std::fs::File::open("./my_image.png").unwrap();
+/// let (pixels, (width, height)) = seamcarving::resize_png(file, (100, 100)).unwrap();
+/// assert_eq!(pixels.len(), (width * height) as usize);
+/// ```
+pub fn resize_png<R: Read>(reader: R, target: (u32, u32)) -> Result<Resized, DecodeError> {
+    let mut decoder = Decoder::new(reader);
+    decoder.set_transformations(Transformations::EXPAND | Transformations::STRIP_16);
+    let (info, mut reader) = decoder.read_info()?;
+    let size = Pos(info.width, info.height);
+    let mut rows = EnergyRows {
+        color: reader.output_color_type().0,
+        pixels: Vec::with_capacity(size.area()),
+        reader,
+        size,
+        next: 0,
+        error: None,
+    };
+    if rows.reader.info().interlaced {
+        let mut frame = vec![0; rows.reader.output_buffer_size()];
+        rows.reader.next_frame(&mut frame)?;
+        for line in frame.chunks_exact(info.line_size.max(1)) {
+            rows.push(line);
+        }
+    }
+    let mut finder = SeamFinder::new(size);
+    let filled = finder.fill_rows(&mut rows);
+    if let Some(error) = rows.error {
+        return Err(error.into());
+    }
+    filled?;
+    let pixels = Matrix::from_vec(size, rows.pixels).expect("every row was decoded");
+    let mut resized = Vec::new();
+    BufferScratch::with_pixels(pixels, finder).carve(target, &mut resized, |_, _| {})?;
+    let resized_size = (target.0.min(size.0), target.1.min(size.1));
+    Ok((resized, resized_size))
+}
+
+/// The energies of the rows of an image, computed while it is being decoded
+struct EnergyRows<R: Read> {
+    reader: Reader<R>,
+    color: ColorType,
+    size: Pos,
+    // The pixels decoded so far, row by row
+    pixels: Vec<[u8; 4]>,
+    // The next row whose energy is computed
+    next: u32,
+    error: Option<DecodingError>,
+}
+
+impl<R: Read> EnergyRows<R> {
+    /// Converts a decoded row to RGBA
+    fn push(&mut self, line: &[u8]) {
+        let (width, color) = (self.size.0 as usize, self.color);
+        let rgba = |p: &[u8]| match color {
+            ColorType::RGBA => [p[0], p[1], p[2], p[3]],
+            ColorType::RGB => [p[0], p[1], p[2], 255],
+            ColorType::GrayscaleAlpha => [p[0], p[0], p[0], p[1]],
+            // Indexed images are expanded to colors
+            ColorType::Grayscale | ColorType::Indexed => [p[0], p[0], p[0], 255],
+        };
+        let samples = color.samples();
+        let row = line.chunks_exact(samples).take(width).map(rgba);
+        self.pixels.extend(row);
+    }
+
+    fn decoded_rows(&self) -> u32 {
+        (self.pixels.len() / (self.size.0 as usize).max(1)) as u32
+    }
+}
+
+impl<R: Read> Iterator for EnergyRows<R> {
+    type Item = Vec<u32>;
+
+    fn next(&mut self) -> Option<Vec<u32>> {
+        let Pos(width, height) = self.size;
+        let y = self.next;
+        if y >= height {
+            return None;
+        }
+        // The energy of a row depends on the row below it
+        while self.decoded_rows() < (y + 2).min(height) {
+            match self.reader.next_row() {
+                Ok(Some(line)) => {
+                    let line = line.to_vec();
+                    self.push(&line)
+                }
+                Ok(None) => return None,
+                Err(error) => {
+                    self.error = Some(error);
+                    return None;
+                }
+            }
+        }
+        self.next += 1;
+        let pixel = |Pos(x, y): Pos| self.pixels[(x + y * width) as usize];
+        let energies = (0..width).map(|x| gradient(Pos(x, y).surrounding(self.size).map(pixel)));
+        Some(energies.collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use png::{BitDepth, Encoder};
+
+    use super::*;
+    use crate::resize_buffer;
+
+    /// Encodes the colors of RGBA pixels as a PNG image
+    fn encode(pixels: &[[u8; 4]], (width, height): (u32, u32)) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        let mut encoder = Encoder::new(&mut bytes, width, height);
+        encoder.set_color(ColorType::RGB);
+        encoder.set_depth(BitDepth::Eight);
+        let data: Vec<u8> = pixels.iter().flat_map(|p| p[..3].to_vec()).collect();
+        encoder
+            .write_header()
+            .unwrap()
+            .write_image_data(&data)
+            .unwrap();
+        bytes
+    }
+
+    #[test]
+    fn resizes_while_decoding() {
+        let size = (18, 12);
+        let pixels: Vec<[u8; 4]> = (0..18 * 12)
+            .map(|i| [(i * 37 % 251) as u8, (i % 18 * 10) as u8, 0, 255])
+            .collect();
+        let expected = resize_buffer(&pixels, size, (13, 9)).unwrap();
+        let resized = resize_png(&encode(&pixels, size)[..], (13, 9)).unwrap();
+        assert_eq!(resized, (expected, (13, 9)));
+        let encoded = encode(&pixels, size);
+        let truncated = resize_png(&encoded[..encoded.len() - 20], (13, 9));
+        assert!(matches!(truncated, Err(DecodeError::Png(_))));
+    }
+}
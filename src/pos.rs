@@ -1,34 +1,80 @@
-use std::ops::{Sub, Add};
+use core::convert::TryFrom;
+use core::ops::{Sub, Add, Range};
 
+/// A position in an image or in a [Matrix](crate::Matrix): a column and a row
+///
+/// ```
+/// use seamcarving::Pos;
+/// let pos = Pos::from((3, 4));
+/// assert_eq!(pos + Pos(1, 1), Pos(4, 5));
+/// // Subtraction saturates at zero, use checked_sub to detect it
+/// assert_eq!(pos - Pos(5, 1), Pos(0, 3));
+/// assert_eq!(pos.checked_sub(Pos(5, 1)), None);
+/// assert_eq!(pos.checked_offset(-1, 2), Some(Pos(2, 6)));
+/// let (x, y): (u32, u32) = pos.into();
+/// ```
 #[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
-pub(crate) struct Pos(pub u32, pub u32);
+pub struct Pos(pub u32, pub u32);
 
 impl Pos {
-    pub fn successors(self, size: Pos) -> PosLine {
-        let Pos(x0, y0) = self;
-        let x_end = (x0 + 1).min(size.0 - 1);
-        let y = y0 + 1;
-        let (x, y) = if y < size.1 {
-            (x0.saturating_sub(1), y)
-        } else { (x_end + 1, y0) };
-        PosLine { x, y, x_end }
+    /// The sum of two positions, or None if a coordinate overflows
+    pub fn checked_add(self, other: Pos) -> Option<Pos> {
+        Some(Pos(self.0.checked_add(other.0)?, self.1.checked_add(other.1)?))
     }
 
-    pub fn predecessors(self, size: Pos) -> PosLine {
-        let Pos(x0, y0) = self;
-        let x_end = (x0 + 1).min(size.0 - 1);
-        let (x, y) = if let Some(y) = y0.checked_sub(1) {
-            (x0.saturating_sub(1), y)
-        } else { (x_end + 1, 0) };
-        PosLine { x, y, x_end }
+    /// The difference of two positions, or None if a coordinate would be negative
+    pub fn checked_sub(self, other: Pos) -> Option<Pos> {
+        Some(Pos(self.0.checked_sub(other.0)?, self.1.checked_sub(other.1)?))
     }
 
-    pub fn iter_in_rect(start: Pos, end: Pos) -> RectIterator {
-        RectIterator { current: start, start, end }
+    /// Moves the position by `dx` columns and `dy` rows,
+    /// or returns None if a coordinate would be negative or overflow
+    pub fn checked_offset(self, dx: i32, dy: i32) -> Option<Pos> {
+        let offset = |v: u32, d: i32| u32::try_from(i64::from(v) + i64::from(d)).ok();
+        Some(Pos(offset(self.0, dx)?, offset(self.1, dy)?))
+    }
+
+    /// Number of positions in a matrix or an image of this size,
+    /// or None if it does not fit in the address space of the platform
+    pub(crate) fn checked_area(self) -> Option<usize> {
+        usize::try_from(self.0).ok()?.checked_mul(usize::try_from(self.1).ok()?)
+    }
+
+    /// [checked_area](Pos::checked_area), for sizes that must fit in memory
+    pub(crate) fn area(self) -> usize {
+        self.checked_area().expect("image too large for the address space of this platform")
+    }
+
+    /// Whether the position is inside a matrix or an image of the given size
+    pub fn is_inside(self, size: Pos) -> bool {
+        self.0 < size.0 && self.1 < size.1
+    }
+
+    pub(crate) fn iter_in_rect(start: Pos, end: Pos) -> RectIterator {
+        // Empty rectangles start on their last row, where the iteration ends
+        let empty = start.0 >= end.0 || start.1 >= end.1;
+        let current = if empty { Pos(start.0, end.1) } else { start };
+        RectIterator { current, start, end }
+    }
+
+    /// The rows of the rectangle from `start` (included) to `end` (excluded), from top to bottom.
+    ///
+    /// Each row only depends on the ones above it in the seam carving dynamic program,
+    /// so the positions of a row can be processed in parallel once the previous rows are done.
+    ///
+    /// ```
+    /// use seamcarving::Pos;
+    /// let rows: Vec<Vec<Pos>> = Pos::rows_in_rect(Pos(1, 0), Pos(3, 2))
+    ///     .map(|row| row.collect())
+    ///     .collect();
+    /// assert_eq!(rows, vec![vec![Pos(1, 0), Pos(2, 0)], vec![Pos(1, 1), Pos(2, 1)]]);
+    /// ```
+    pub fn rows_in_rect(start: Pos, end: Pos) -> impl DoubleEndedIterator<Item = PosRow> {
+        (start.1..end.1.max(start.1)).map(move |y| PosRow { xs: start.0..end.0.max(start.0), y })
     }
 
     /// Returns the top,bottom,left and right positions, in this order
-    pub fn surrounding(self, size: Pos) -> [Pos; 4] {
+    pub(crate) fn surrounding(self, size: Pos) -> [Pos; 4] {
         let Pos(x, y) = self;
         [
             Pos(x, y.saturating_sub(1)),
@@ -37,9 +83,247 @@ impl Pos {
             Pos((x + 1).min(size.0 - 1), y),
         ]
     }
+
+    /// Like [surrounding](Pos::surrounding),
+    /// with the positions outside of the image chosen by the border mode
+    pub(crate) fn surrounding_with(self, size: Pos, border: BorderMode) -> [Pos; 4] {
+        let before = |v: u32, length: u32| match (v.checked_sub(1), border) {
+            (Some(before), _) => before,
+            (None, BorderMode::Clamp) => 0,
+            (None, BorderMode::Mirror) => 1.min(length - 1),
+            (None, BorderMode::Wrap) => length - 1,
+        };
+        let after = |v: u32, length: u32| match border {
+            _ if v + 1 < length => v + 1,
+            BorderMode::Clamp => length - 1,
+            BorderMode::Mirror => v.saturating_sub(1),
+            BorderMode::Wrap => 0,
+        };
+        let Pos(x, y) = self;
+        [
+            Pos(x, before(y, size.1)),
+            Pos(x, after(y, size.1)),
+            Pos(before(x, size.0), y),
+            Pos(after(x, size.0), y),
+        ]
+    }
+
+    /// Like [surrounding](Pos::surrounding),
+    /// but the left and right positions wrap around the borders
+    pub(crate) fn surrounding_wrapping(self, size: Pos) -> [Pos; 4] {
+        let Pos(x, y) = self;
+        [
+            Pos(x, y.saturating_sub(1)),
+            Pos(x, (y + 1).min(size.1 - 1)),
+            Pos(x.checked_sub(1).unwrap_or(size.0 - 1), y),
+            Pos(if x + 1 < size.0 { x + 1 } else { 0 }, y),
+        ]
+    }
+}
+
+/// How the neighbours of the pixels on the borders of an image are chosen
+/// when computing their gradient, see [Carver::border_mode](crate::Carver::border_mode)
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Default)]
+pub enum BorderMode {
+    /// The neighbours outside of the image are the pixels of its border.
+    /// The gradient across the border is the difference with the only neighbour inside,
+    /// so the energy of the first and last columns tends to be about half of what it would be
+    /// inside the image, and seams are drawn to them
+    #[default]
+    Clamp,
+    /// The neighbours outside of the image are the reflections of the ones inside,
+    /// without repeating the border. The gradient across the border is 0,
+    /// so seams are drawn to the borders even more, but only to where they are flat
+    /// along the border
+    Mirror,
+    /// The neighbours outside of the image are the pixels of the opposite border,
+    /// as if the image tiled, so that the borders are compared with each other
+    Wrap,
+}
+
+/// Which positions of the adjacent rows are neighbours of a position
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Default)]
+pub enum Connectivity {
+    /// The three closest positions of the adjacent row, this is how seams are connected
+    #[default]
+    Three,
+    /// The five closest positions of the adjacent row,
+    /// for seams that can move two columns at each row
+    Five,
+    /// The eight surrounding positions, split in the order of a row by row traversal:
+    /// the predecessors are the three positions of the previous row and the one on the left,
+    /// the successors are the one on the right and the three positions of the next row
+    Eight,
+}
+
+impl Connectivity {
+    /// Number of columns a neighbour in an adjacent row can be away from the position
+    fn radius(self) -> u32 {
+        match self {
+            Connectivity::Three | Connectivity::Eight => 1,
+            Connectivity::Five => 2,
+        }
+    }
+}
+
+impl Pos {
+    /// The neighbours of this position in the previous row, in a matrix or image of the
+    /// given size. If `wrap` is set, the x coordinates wrap around the left and right borders.
+    #[inline(always)]
+    pub fn predecessors_with(self, size: Pos, wrap: bool, connectivity: Connectivity) -> Neighbours {
+        let neighbours = match self.1.checked_sub(1) {
+            Some(y) => Neighbours::row(self.0, y, size.0, wrap, connectivity.radius()),
+            None => Neighbours::empty(),
+        };
+        if connectivity != Connectivity::Eight {
+            return neighbours;
+        }
+        let left = self.0.checked_sub(1).or(if wrap { size.0.checked_sub(1) } else { None });
+        let left = left.filter(|&x| x != self.0).map(|x| Pos(x, self.1));
+        neighbours.with_side(left, false)
+    }
+
+    /// The neighbours of this position in the next row, see
+    /// [predecessors_with](Pos::predecessors_with)
+    #[inline(always)]
+    pub fn successors_with(self, size: Pos, wrap: bool, connectivity: Connectivity) -> Neighbours {
+        let neighbours = match self.1.checked_add(1).filter(|&y| y < size.1) {
+            Some(y) => Neighbours::row(self.0, y, size.0, wrap, connectivity.radius()),
+            None => Neighbours::empty(),
+        };
+        if connectivity != Connectivity::Eight {
+            return neighbours;
+        }
+        let right = self.0.checked_add(1).filter(|&x| x < size.0);
+        let right = right.or(if wrap { Some(0) } else { None });
+        // In a single column, the position would be its own neighbour
+        let right = right.filter(|&x| x != self.0).map(|x| Pos(x, self.1));
+        neighbours.with_side(right, true)
+    }
+
+    /// The three closest positions of the previous row, in which seams can continue
+    #[inline(always)]
+    pub(crate) fn predecessors_in(self, size: Pos, wrap: bool) -> Neighbours {
+        self.predecessors_with(size, wrap, Connectivity::Three)
+    }
+
+    /// The three closest positions of the next row
+    #[inline(always)]
+    pub(crate) fn successors_in(self, size: Pos, wrap: bool) -> Neighbours {
+        self.successors_with(size, wrap, Connectivity::Three)
+    }
+}
+
+/// The neighbours of a position, from left to right on each row
+#[derive(Clone, Debug)]
+pub struct Neighbours {
+    row: Row,
+    // Neighbour in the same row, yielded before the other ones if `side_first`
+    side: Option<Pos>,
+    side_first: bool,
+}
+
+/// Neighbouring positions on a line, with or without wrapping around the borders
+#[derive(Clone, Debug)]
+enum Row {
+    Line(PosLine),
+    Wrapping(WrappingPosLine),
+}
+
+impl Neighbours {
+    /// The positions at most `radius` columns away from x on row y
+    #[inline(always)]
+    fn row(x: u32, y: u32, width: u32, wrap: bool, radius: u32) -> Self {
+        let row = if wrap {
+            Row::Wrapping(WrappingPosLine::new(x, y, width, radius))
+        } else {
+            Row::Line(PosLine::new(x, y, width, radius))
+        };
+        Neighbours { row, side: None, side_first: false }
+    }
+
+    fn empty() -> Self {
+        Neighbours { row: Row::Wrapping(WrappingPosLine::empty()), side: None, side_first: false }
+    }
+
+    /// Adds a neighbour in the same row
+    fn with_side(mut self, side: Option<Pos>, first: bool) -> Self {
+        self.side = side;
+        self.side_first = first;
+        self
+    }
+}
+
+impl Iterator for Neighbours {
+    type Item = Pos;
+
+    #[inline(always)]
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.side_first && self.side.is_some() {
+            return self.side.take();
+        }
+        let next = match &mut self.row {
+            Row::Line(line) => line.next(),
+            Row::Wrapping(line) => line.next(),
+        };
+        next.or_else(|| self.side.take())
+    }
 }
 
-pub(crate) struct PosLine { x: u32, y: u32, x_end: u32 }
+/// The (up to five) positions at most radius columns away from x on a line, modulo the width
+#[derive(Clone, Debug)]
+struct WrappingPosLine { xs: [u32; 5], len: u8, i: u8, y: u32 }
+
+impl WrappingPosLine {
+    #[inline(always)]
+    fn new(x: u32, y: u32, width: u32, radius: u32) -> Self {
+        let mut line = WrappingPosLine { xs: [0; 5], len: 0, i: 0, y };
+        if width < 2 * radius + 1 {
+            // Every column is a neighbour
+            line.len = width as u8;
+            for (i, x) in line.xs.iter_mut().zip(0..width) {
+                *i = x;
+            }
+        } else {
+            // Computed in 64 bits, as x + width may not fit in 32 bits
+            let (x, width) = (u64::from(x), u64::from(width));
+            for nx in x + width - u64::from(radius)..=x + width + u64::from(radius) {
+                line.xs[line.len as usize] = (nx % width) as u32;
+                line.len += 1;
+            }
+        }
+        line
+    }
+
+    fn empty() -> Self {
+        WrappingPosLine { xs: [0; 5], len: 0, i: 0, y: 0 }
+    }
+}
+
+impl Iterator for WrappingPosLine {
+    type Item = Pos;
+
+    #[inline(always)]
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.i >= self.len { None } else {
+            let p = Pos(self.xs[self.i as usize], self.y);
+            self.i += 1;
+            Some(p)
+        }
+    }
+}
+
+/// The positions at most radius columns away from x on a line, clipped to the width
+#[derive(Clone, Debug)]
+struct PosLine { x: u32, y: u32, x_end: u32 }
+
+impl PosLine {
+    #[inline(always)]
+    fn new(x: u32, y: u32, width: u32, radius: u32) -> Self {
+        let x_end = x.saturating_add(radius).min(width.saturating_sub(1));
+        PosLine { x: x.saturating_sub(radius), y, x_end }
+    }
+}
 
 impl Iterator for PosLine {
     type Item = Pos;
@@ -54,6 +338,54 @@ impl Iterator for PosLine {
     }
 }
 
+/// The positions of a part of a row, see [rows_in_rect](Pos::rows_in_rect)
+#[derive(Clone, Debug)]
+pub struct PosRow {
+    xs: Range<u32>,
+    y: u32,
+}
+
+impl PosRow {
+    /// The row of the positions
+    pub fn y(&self) -> u32 {
+        self.y
+    }
+
+    /// Splits the row in chunks of at most `size` consecutive positions,
+    /// for instance to distribute them between threads
+    pub fn chunks(&self, size: u32) -> impl Iterator<Item = PosRow> {
+        let (Range { start, end }, y) = (self.xs.clone(), self.y);
+        let size = size.max(1);
+        (start..end)
+            .step_by(size as usize)
+            .map(move |x| PosRow { xs: x..(x.saturating_add(size)).min(end), y })
+    }
+
+    /// The positions of the row, in parallel.
+    /// Only available when the `rayon` feature is enabled.
+    #[cfg(feature = "rayon")]
+    pub fn into_par_iter(self) -> impl rayon::iter::IndexedParallelIterator<Item = Pos> {
+        use rayon::prelude::*;
+        let y = self.y;
+        self.xs.into_par_iter().map(move |x| Pos(x, y))
+    }
+}
+
+impl Iterator for PosRow {
+    type Item = Pos;
+
+    #[inline(always)]
+    fn next(&mut self) -> Option<Self::Item> {
+        self.xs.next().map(|x| Pos(x, self.y))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.xs.size_hint()
+    }
+}
+
+impl ExactSizeIterator for PosRow {}
+
 pub(crate) struct RectIterator {
     current: Pos,
     start: Pos,
@@ -88,6 +420,7 @@ impl Add<Pos> for Pos {
     }
 }
 
+/// Saturating subtraction: coordinates that would be negative are zero
 impl Sub<Pos> for Pos {
     type Output = Pos;
 
@@ -96,3 +429,103 @@ impl Sub<Pos> for Pos {
         Pos(self.0.saturating_sub(rhs.0), self.1.saturating_sub(rhs.1))
     }
 }
+
+impl From<(u32, u32)> for Pos {
+    #[inline(always)]
+    fn from((x, y): (u32, u32)) -> Self {
+        Pos(x, y)
+    }
+}
+
+impl From<Pos> for (u32, u32) {
+    #[inline(always)]
+    fn from(Pos(x, y): Pos) -> Self {
+        (x, y)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec;
+    use alloc::vec::Vec;
+
+    use super::*;
+
+    fn xs(neighbours: Neighbours) -> Vec<(u32, u32)> {
+        neighbours.map(Pos::into).collect()
+    }
+
+    #[test]
+    fn splits_rows_in_chunks() {
+        let row = Pos::rows_in_rect(Pos(2, 5), Pos(9, 6)).next().unwrap();
+        assert_eq!(row.len(), 7);
+        let chunks: Vec<Vec<u32>> = row.chunks(3).map(|c| c.map(|p| p.0).collect()).collect();
+        assert_eq!(chunks, vec![vec![2, 3, 4], vec![5, 6, 7], vec![8]]);
+        assert_eq!(Pos::rows_in_rect(Pos(0, 3), Pos(4, 1)).count(), 0);
+    }
+
+    #[test]
+    fn iterates_over_rectangles() {
+        let positions: Vec<Pos> = Pos::iter_in_rect(Pos(1, 2), Pos(3, 4)).collect();
+        assert_eq!(positions, vec![Pos(1, 2), Pos(2, 2), Pos(1, 3), Pos(2, 3)]);
+        assert_eq!(Pos::iter_in_rect(Pos(0, 0), Pos(0, 5)).count(), 0);
+        assert_eq!(Pos::iter_in_rect(Pos(0, 0), Pos(5, 0)).count(), 0);
+        assert_eq!(Pos::iter_in_rect(Pos(4, 3), Pos(2, 1)).count(), 0);
+    }
+
+    #[test]
+    fn chooses_the_neighbours_outside_of_the_borders() {
+        let size = Pos(4, 3);
+        let corner = |border| Pos(0, 2).surrounding_with(size, border);
+        let clamped = [Pos(0, 1), Pos(0, 2), Pos(0, 2), Pos(1, 2)];
+        assert_eq!(corner(BorderMode::Clamp), clamped);
+        assert_eq!(Pos(0, 2).surrounding(size), clamped);
+        let mirrored = [Pos(0, 1), Pos(0, 1), Pos(1, 2), Pos(1, 2)];
+        assert_eq!(corner(BorderMode::Mirror), mirrored);
+        let wrapped = [Pos(0, 1), Pos(0, 0), Pos(3, 2), Pos(1, 2)];
+        assert_eq!(corner(BorderMode::Wrap), wrapped);
+        let inside = Pos(2, 1).surrounding(size);
+        assert_eq!(Pos(2, 1).surrounding_with(size, BorderMode::Mirror), inside);
+        // A single pixel is its own neighbour
+        let single = Pos(0, 0).surrounding_with(Pos(1, 1), BorderMode::Mirror);
+        assert_eq!(single, [Pos(0, 0); 4]);
+    }
+
+    #[test]
+    fn lists_neighbours() {
+        let size = Pos(6, 3);
+        assert_eq!(xs(Pos(0, 1).predecessors_in(size, false)), vec![(0, 0), (1, 0)]);
+        assert_eq!(xs(Pos(0, 1).predecessors_in(size, true)), vec![(5, 0), (0, 0), (1, 0)]);
+        assert_eq!(xs(Pos(3, 2).successors_in(size, false)), vec![]);
+        assert_eq!(
+            xs(Pos(5, 0).successors_with(size, true, Connectivity::Five)),
+            vec![(3, 1), (4, 1), (5, 1), (0, 1), (1, 1)]
+        );
+        assert_eq!(
+            xs(Pos(1, 1).predecessors_with(size, false, Connectivity::Eight)),
+            vec![(0, 0), (1, 0), (2, 0), (0, 1)]
+        );
+        assert_eq!(
+            xs(Pos(5, 2).successors_with(size, false, Connectivity::Eight)),
+            vec![]
+        );
+        assert_eq!(xs(Pos(1, 1).predecessors_in(Pos(2, 2), true)), vec![(0, 0), (1, 0)]);
+    }
+
+    #[test]
+    fn lists_neighbours_in_huge_images() {
+        let size = Pos(u32::MAX, u32::MAX);
+        let last = u32::MAX - 1;
+        assert_eq!(
+            xs(Pos(0, 1).predecessors_in(size, true)),
+            vec![(last, 0), (0, 0), (1, 0)]
+        );
+        assert_eq!(
+            xs(Pos(last, 0).successors_in(size, true)),
+            vec![(last - 1, 1), (last, 1), (0, 1)]
+        );
+        assert_eq!(xs(Pos(last, last).successors_in(size, false)), vec![]);
+        let area = usize::try_from(2 * u64::from(u32::MAX)).ok();
+        assert_eq!(Pos(u32::MAX, 2).checked_area(), area);
+    }
+}
@@ -0,0 +1,152 @@
+use image::imageops::{resize, FilterType};
+use image::{GenericImageView, Pixel};
+
+use crate::carver::{Algorithm, Carver, HYBRID_MAX_SEAM_ENERGY};
+use crate::{aspect_dimensions, ImageBufferOf, PixelBuffer, SeamError};
+
+/// The formats of social networks and video platforms, with their aspect ratio,
+/// their recommended resolution, and the mix of carving and scaling that suits them.
+///
+/// ```no_run
+/// use seamcarving::Preset;
+/// let img = image::open("./my_image.jpg").unwrap();
+/// let preset = Preset::from_name("instagram-square").unwrap();
+/// preset.retarget(&img).unwrap().save("./square.jpg").unwrap();
+/// ```
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+#[non_exhaustive]
+pub enum Preset {
+    /// A square post, 1080x1080.
+    /// Carves until the seams become visible, and then scales, see [Algorithm::Hybrid].
+    InstagramSquare,
+    /// A vertical story or reel, 9:16 at 1080x1920.
+    /// Reaching it from a landscape removes most of the columns, so the image is scaled
+    /// as soon as the seams are half as visible as with
+    /// [InstagramSquare](Preset::InstagramSquare).
+    Story,
+    /// A video thumbnail, 16:9 at 1280x720.
+    /// Only carves, with the forward energy, which does not create new edges in the faces
+    /// and the titles thumbnails are made of, see [Algorithm::Forward].
+    YoutubeThumbnail,
+}
+
+impl Preset {
+    /// All the presets
+    pub const ALL: [Preset; 3] = [
+        Preset::InstagramSquare,
+        Preset::Story,
+        Preset::YoutubeThumbnail,
+    ];
+
+    /// The name of the preset, as in the `--preset` option of the command line tool
+    pub fn name(self) -> &'static str {
+        match self {
+            Preset::InstagramSquare => "instagram-square",
+            Preset::Story => "story",
+            Preset::YoutubeThumbnail => "youtube-thumb",
+        }
+    }
+
+    /// The preset with the given [name](Preset::name)
+    pub fn from_name(name: &str) -> Option<Self> {
+        Preset::ALL.iter().copied().find(|preset| preset.name() == name)
+    }
+
+    /// The `(width, height)` ratio of the images of the preset
+    pub fn aspect(self) -> (u32, u32) {
+        match self {
+            Preset::InstagramSquare => (1, 1),
+            Preset::Story => (9, 16),
+            Preset::YoutubeThumbnail => (16, 9),
+        }
+    }
+
+    /// The recommended dimensions of the images of the preset
+    pub fn resolution(self) -> (u32, u32) {
+        match self {
+            Preset::InstagramSquare => (1080, 1080),
+            Preset::Story => (1080, 1920),
+            Preset::YoutubeThumbnail => (1280, 720),
+        }
+    }
+
+    /// A carver reducing the image to the aspect ratio of the preset, like
+    /// [retarget_aspect](crate::retarget_aspect), with the operators of the preset
+    pub fn carver<IMG: GenericImageView>(self, img: &IMG) -> Carver {
+        let (width, height) = aspect_dimensions(img.dimensions(), self.aspect());
+        let carver = Carver::new().width(width).height(height);
+        match self {
+            Preset::InstagramSquare => carver.algorithm(Algorithm::Hybrid),
+            Preset::Story => carver
+                .algorithm(Algorithm::Hybrid)
+                .max_seam_energy(HYBRID_MAX_SEAM_ENERGY / 2),
+            Preset::YoutubeThumbnail => carver.algorithm(Algorithm::Forward),
+        }
+    }
+
+    /// Scales an image down to fit in the [resolution](Preset::resolution) of the preset,
+    /// keeping smaller images as they are
+    pub fn fit<P: Pixel + 'static>(self, img: PixelBuffer<P>) -> PixelBuffer<P> {
+        let (width, height) = img.dimensions();
+        let (max_width, max_height) = self.resolution();
+        if width <= max_width && height <= max_height {
+            return img;
+        }
+        let (fit_width, fit_height) = if u64::from(width) * u64::from(max_height)
+            > u64::from(height) * u64::from(max_width)
+        {
+            let fit_height = u64::from(height) * u64::from(max_width) / u64::from(width);
+            (max_width, fit_height.max(1) as u32)
+        } else {
+            let fit_width = u64::from(width) * u64::from(max_height) / u64::from(height);
+            (fit_width.max(1) as u32, max_height)
+        };
+        resize(&img, fit_width, fit_height, FilterType::Lanczos3)
+    }
+
+    /// Carves an image to the aspect ratio of the preset with its [carver](Preset::carver),
+    /// and [fits](Preset::fit) it in the resolution of the preset.
+    ///
+    /// ```
+    /// use seamcarving::Preset;
+    /// let img = image::RgbImage::from_fn(48, 32, |x, y| image::Rgb([(x * y) as u8, 0, 0]));
+    /// let square = Preset::InstagramSquare.retarget(&img).unwrap();
+    /// assert_eq!(square.dimensions(), (32, 32));
+    /// let story = Preset::Story.retarget(&img).unwrap();
+    /// assert_eq!(story.dimensions(), (18, 32));
+    /// ```
+    pub fn retarget<IMG: GenericImageView>(
+        self,
+        img: &IMG,
+    ) -> Result<ImageBufferOf<IMG>, SeamError>
+    where
+        <IMG as GenericImageView>::Pixel: 'static,
+    {
+        self.carver(img).resize(img).map(|carved| self.fit(carved))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use image::{GrayImage, Luma};
+
+    use super::*;
+
+    #[test]
+    fn fits_the_resolution_of_the_presets() {
+        for preset in Preset::ALL {
+            assert_eq!(Preset::from_name(preset.name()), Some(preset));
+            let (width, height) = preset.resolution();
+            let (rw, rh) = preset.aspect();
+            assert_eq!(width * rh, height * rw);
+        }
+        assert_eq!(Preset::from_name("instagram"), None);
+
+        let large = GrayImage::from_fn(1600, 900, |x, y| Luma([(x ^ y) as u8]));
+        assert_eq!(Preset::YoutubeThumbnail.fit(large).dimensions(), (1280, 720));
+        let small = GrayImage::new(640, 360);
+        assert_eq!(Preset::YoutubeThumbnail.fit(small).dimensions(), (640, 360));
+        let tall = GrayImage::new(200, 4000);
+        assert_eq!(Preset::Story.fit(tall).dimensions(), (96, 1920));
+    }
+}
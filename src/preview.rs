@@ -0,0 +1,132 @@
+use image::{GenericImageView, Pixel, Rgb};
+use minifb::{Window, WindowOptions};
+
+use crate::energy::{energy_level, to_8bit};
+use crate::pos::Pos;
+use crate::Carvable;
+
+/// Color of the seams drawn over the image and its energy, as 0RGB
+const SEAM_COLOR: u32 = 0x00ff_0000;
+
+/// A window showing the image being carved, the energy of its pixels,
+/// and the seam that is about to be removed,
+/// see [Carver::debug_preview](crate::Carver::debug_preview)
+#[derive(Default)]
+pub(crate) struct Preview {
+    window: Option<Window>,
+    // Set when the window could not be opened, or was closed
+    closed: bool,
+    // Pixels of the window, as 0RGB
+    buffer: Vec<u32>,
+    // Dimensions of the window: the image and its energy are shown side by side
+    size: (usize, usize),
+}
+
+impl Preview {
+    /// Shows the carved image and the seam that was found in it.
+    /// If `transposed` is set, the image is a [Rotated](crate::Rotated) view,
+    /// and is shown the right way up.
+    pub fn show<IMG: GenericImageView>(
+        &mut self,
+        carvable: &Carvable<IMG>,
+        seam: &[Pos],
+        transposed: bool,
+    ) {
+        let img = &carvable.carved;
+        let (width, height) = img.dimensions();
+        let on_screen = |Pos(x, y): Pos| {
+            let (x, y) = if transposed { (y, x) } else { (x, y) };
+            (x as usize, y as usize)
+        };
+        if !self.open(on_screen(Pos(width, height))) {
+            return;
+        }
+        let (window_width, window_height) = self.size;
+        let panel_width = window_width / 2;
+        // Images larger than the first one shown are cropped
+        let visible = |(sx, sy): (usize, usize)| sx < panel_width && sy < window_height;
+        self.buffer.iter_mut().for_each(|p| *p = 0);
+        for y in 0..height {
+            for x in 0..width {
+                let pos = Pos(x, y);
+                let (sx, sy) = on_screen(pos);
+                if !visible((sx, sy)) {
+                    continue;
+                }
+                let Rgb([r, g, b]) = img.get_pixel(x, y).to_rgb();
+                let color = rgb(to_8bit(r), to_8bit(g), to_8bit(b));
+                let level = energy_level(carvable.layers.energy(img, pos, carvable.wrap));
+                self.buffer[sy * window_width + sx] = color;
+                self.buffer[sy * window_width + panel_width + sx] = rgb(level, level, level);
+            }
+        }
+        for &pos in seam {
+            let (sx, sy) = on_screen(pos);
+            if visible((sx, sy)) {
+                self.buffer[sy * window_width + sx] = SEAM_COLOR;
+                self.buffer[sy * window_width + panel_width + sx] = SEAM_COLOR;
+            }
+        }
+        let (buffer, size) = (&self.buffer, self.size);
+        let open = self.window.as_mut().is_some_and(|window| {
+            window.update_with_buffer(buffer, size.0, size.1).is_ok() && window.is_open()
+        });
+        if !open {
+            self.close();
+        }
+    }
+
+    /// Opens the window the first time an image is shown,
+    /// large enough for an image of the given size and its energy.
+    /// Returns false if the window could not be opened, or was closed.
+    fn open(&mut self, (width, height): (usize, usize)) -> bool {
+        if self.closed {
+            return false;
+        }
+        if self.window.is_none() {
+            self.size = (2 * width, height);
+            self.buffer = vec![0; self.size.0 * self.size.1];
+            let options = WindowOptions {
+                resize: true,
+                ..WindowOptions::default()
+            };
+            match Window::new("seamcarving", self.size.0, self.size.1, options) {
+                Ok(window) => self.window = Some(window),
+                Err(_) => self.close(),
+            }
+        }
+        self.window.is_some()
+    }
+
+    /// Stops showing anything, and lets carving go on at full speed
+    fn close(&mut self) {
+        self.closed = true;
+        self.window = None;
+        self.buffer = Vec::new();
+    }
+}
+
+fn rgb(r: u8, g: u8, b: u8) -> u32 {
+    u32::from(r) << 16 | u32::from(g) << 8 | u32::from(b)
+}
+
+#[cfg(test)]
+mod tests {
+    use image::{Luma, RgbImage};
+
+    use crate::Carver;
+
+    #[test]
+    fn carves_the_same_with_a_preview() {
+        let img = RgbImage::from_fn(16, 10, |x, y| {
+            image::Rgb([(x * 15) as u8, (y * 25) as u8, 0])
+        });
+        let carver = Carver::new().width(12).height(8);
+        let expected = carver.resize(&img).unwrap();
+        // Without a display, the window cannot be opened, and carving goes on without it
+        assert_eq!(carver.debug_preview(true).resize(&img).unwrap(), expected);
+        let gray = image::GrayImage::from_fn(16, 10, |x, _y| Luma([x as u8]));
+        let resized = Carver::new().width(12).debug_preview(true).resize(&gray);
+        assert_eq!(resized.unwrap().dimensions(), (12, 10));
+    }
+}
@@ -0,0 +1,319 @@
+use std::collections::BTreeMap;
+use std::str::FromStr;
+use std::sync::Arc;
+
+use crate::error::RegistryError;
+use crate::energies::{CenterBias, Entropy, Gradient, Saliency, Sobel};
+use crate::pipeline::{EnergyStage, Textures};
+
+/// Builds an energy stage from its parameters, see [EnergyRegistry::register]
+type Constructor =
+    dyn Fn(&EnergyParams) -> Result<Box<dyn EnergyStage>, RegistryError> + Send + Sync;
+
+/// The parameters given to an energy of an [EnergyRegistry], by name.
+///
+/// They are parsed from `name=value` pairs separated by commas, such as `radius=2, strength=0.5`,
+/// and each energy parses the values it takes.
+#[derive(Clone, PartialEq, Eq, Hash, Debug, Default)]
+pub struct EnergyParams {
+    values: BTreeMap<String, String>,
+}
+
+impl EnergyParams {
+    /// No parameters, every energy using its default values
+    pub fn new() -> Self {
+        EnergyParams::default()
+    }
+
+    /// Sets a parameter
+    pub fn with(mut self, name: &str, value: impl ToString) -> Self {
+        self.values.insert(name.to_string(), value.to_string());
+        self
+    }
+
+    /// Sets the parameter of a `name=value` pair, such as a command line argument
+    pub fn parse_pair(&mut self, pair: &str) -> Result<(), RegistryError> {
+        let malformed = || RegistryError::Malformed {
+            text: pair.to_string(),
+        };
+        let (name, value) = pair.split_once('=').ok_or_else(malformed)?;
+        let (name, value) = (name.trim(), value.trim());
+        if name.is_empty() {
+            return Err(malformed());
+        }
+        self.values.insert(name.to_string(), value.to_string());
+        Ok(())
+    }
+
+    /// The names of the parameters that are set
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.values.keys().map(String::as_str)
+    }
+
+    /// The value of a parameter, or `default` if it is not set.
+    /// Fails with [RegistryError::InvalidParameter] if it cannot be parsed.
+    pub fn get<T: FromStr>(&self, name: &str, default: T) -> Result<T, RegistryError> {
+        match self.values.get(name) {
+            None => Ok(default),
+            Some(value) => value.parse().map_err(|_| RegistryError::InvalidParameter {
+                parameter: name.to_string(),
+                value: value.clone(),
+            }),
+        }
+    }
+}
+
+impl FromStr for EnergyParams {
+    type Err = RegistryError;
+
+    fn from_str(pairs: &str) -> Result<Self, RegistryError> {
+        let mut params = EnergyParams::new();
+        for pair in pairs.split(',').filter(|pair| !pair.trim().is_empty()) {
+            params.parse_pair(pair)?;
+        }
+        Ok(params)
+    }
+}
+
+#[derive(Clone)]
+struct Registered {
+    parameters: Vec<String>,
+    constructor: Arc<Constructor>,
+}
+
+/// Energy stages of a [Pipeline](crate::Pipeline), created by name at runtime,
+/// so that applications and the command line can read them from configuration files,
+/// and other crates can add their own.
+///
+/// [new](EnergyRegistry::new) registers the energies of this crate, with the parameters
+/// of their stages: `gradient` ([Gradient]), `sobel` ([Sobel]), `entropy` ([Entropy]),
+/// `saliency` ([Saliency]), `center` ([CenterBias]), `textures` ([Textures]),
+/// and with the `imageproc` feature, `canny`, with the `low`, `high` and `importance`
+/// parameters of `ImportanceMap::canny`.
+///
+/// ```
+/// use seamcarving::{Carver, EnergyRegistry, EnergyStage, Pipeline};
+/// let registry = EnergyRegistry::new();
+/// let energy = registry.parse("textures(radius=3, strength=0.5)").unwrap();
+/// assert_eq!(energy.name(), "textures(radius=3, strength=0.5)");
+/// let pipeline = Pipeline::new(Carver::new().width(20)).energy(energy);
+/// assert!(registry.parse("textures(size=3)").is_err());
+/// ```
+#[derive(Clone)]
+pub struct EnergyRegistry {
+    energies: BTreeMap<String, Registered>,
+}
+
+impl EnergyRegistry {
+    /// A registry of the energies of this crate
+    pub fn new() -> Self {
+        let mut registry = EnergyRegistry::empty();
+        registry.register("gradient", &[], |_params| Ok(Box::new(Gradient)));
+        registry.register("sobel", &["strength"], |params| {
+            Ok(Box::new(Sobel {
+                strength: params.get("strength", 1.)?,
+            }))
+        });
+        registry.register("entropy", &["radius", "strength"], |params| {
+            Ok(Box::new(Entropy {
+                radius: params.get("radius", 4)?,
+                strength: params.get("strength", 1000.)?,
+            }))
+        });
+        registry.register("saliency", &["sigma", "strength"], |params| {
+            Ok(Box::new(Saliency {
+                sigma: params.get("sigma", 1.)?,
+                strength: params.get("strength", 1.)?,
+            }))
+        });
+        registry.register("center", &["strength", "falloff"], |params| {
+            Ok(Box::new(CenterBias {
+                strength: params.get("strength", 1000.)?,
+                falloff: params.get("falloff", 2.)?,
+            }))
+        });
+        registry.register("textures", &["radius", "strength"], |params| {
+            Ok(Box::new(Textures {
+                radius: params.get("radius", 2)?,
+                strength: params.get("strength", 1.)?,
+            }))
+        });
+        #[cfg(feature = "imageproc")]
+        registry.register("canny", &["low", "high", "importance"], |params| {
+            let (low, high): (f32, f32) = (params.get("low", 20.)?, params.get("high", 60.)?);
+            // Canny panics when the thresholds are in the wrong order
+            if low > high {
+                return Err(RegistryError::InvalidParameter {
+                    parameter: "high".to_string(),
+                    value: high.to_string(),
+                });
+            }
+            Ok(Box::new(crate::edges::Canny {
+                low,
+                high,
+                importance: params.get("importance", 1000)?,
+            }))
+        });
+        registry
+    }
+
+    /// A registry without any energy
+    pub fn empty() -> Self {
+        EnergyRegistry {
+            energies: BTreeMap::new(),
+        }
+    }
+
+    /// Registers an energy under the given name, replacing the one registered before it.
+    /// [create](EnergyRegistry::create) rejects the parameters that are not in `parameters`
+    /// before calling `constructor`.
+    pub fn register<F>(&mut self, name: &str, parameters: &[&str], constructor: F)
+    where
+        F: Fn(&EnergyParams) -> Result<Box<dyn EnergyStage>, RegistryError>,
+        F: Send + Sync + 'static,
+    {
+        let registered = Registered {
+            parameters: parameters.iter().map(|p| p.to_string()).collect(),
+            constructor: Arc::new(constructor),
+        };
+        self.energies.insert(name.to_string(), registered);
+    }
+
+    /// The names of the registered energies, in alphabetical order
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.energies.keys().map(String::as_str)
+    }
+
+    /// The names of the parameters of an energy, or None if it is not registered
+    pub fn parameters(&self, name: &str) -> Option<&[String]> {
+        self.energies
+            .get(name)
+            .map(|registered| &registered.parameters[..])
+    }
+
+    /// Creates the energy registered under the given name
+    pub fn create(
+        &self,
+        name: &str,
+        params: &EnergyParams,
+    ) -> Result<Box<dyn EnergyStage>, RegistryError> {
+        let registered = self
+            .energies
+            .get(name)
+            .ok_or_else(|| RegistryError::UnknownEnergy {
+                name: name.to_string(),
+            })?;
+        if let Some(unknown) = params
+            .names()
+            .find(|&param| !registered.parameters.iter().any(|p| p == param))
+        {
+            return Err(RegistryError::UnknownParameter {
+                energy: name.to_string(),
+                parameter: unknown.to_string(),
+            });
+        }
+        (registered.constructor)(params)
+    }
+
+    /// Creates an energy from a name optionally followed by parameters in parentheses,
+    /// such as `textures` or `textures(radius=2, strength=0.5)`,
+    /// in the format of the [names](crate::EnergyStage::name) of the energies of this crate
+    pub fn parse(&self, spec: &str) -> Result<Box<dyn EnergyStage>, RegistryError> {
+        let spec = spec.trim();
+        let (name, params) = match spec.split_once('(') {
+            None => (spec, EnergyParams::new()),
+            Some((name, rest)) => {
+                let params = rest.strip_suffix(')').ok_or(RegistryError::Malformed {
+                    text: spec.to_string(),
+                })?;
+                (name.trim(), params.parse()?)
+            }
+        };
+        self.create(name, &params)
+    }
+}
+
+impl Default for EnergyRegistry {
+    fn default() -> Self {
+        EnergyRegistry::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use image::{Rgba, RgbaImage};
+
+    use super::*;
+    use crate::ImportanceMap;
+
+    /// An energy that gives the same importance to every pixel
+    struct Flat(u32);
+
+    impl EnergyStage for Flat {
+        fn name(&self) -> String {
+            format!("flat(value={})", self.0)
+        }
+
+        fn importance(&self, img: &RgbaImage) -> ImportanceMap {
+            let size = crate::max_pos(img);
+            ImportanceMap::from_bias(size, crate::Matrix::from_fn(size, |_, _| self.0))
+        }
+    }
+
+    #[test]
+    fn creates_energies_by_name() {
+        let mut registry = EnergyRegistry::new();
+        let img = RgbaImage::from_fn(8, 6, |x, y| Rgba([(x * 30) as u8, (y * 40) as u8, 0, 255]));
+        let params = EnergyParams::new().with("radius", 1);
+        let textures = registry.create("textures", &params).unwrap();
+        let expected = ImportanceMap::textures(&img, 1, 1.);
+        assert_eq!(
+            textures.importance(&img).bias.as_slice(),
+            expected.bias.as_slice()
+        );
+        // Names of energies can be parsed back
+        let parsed = registry.parse(&textures.name()).unwrap();
+        assert_eq!(parsed.name(), textures.name());
+
+        registry.register("flat", &["value"], |params| {
+            Ok(Box::new(Flat(params.get("value", 0)?)))
+        });
+        let flat = registry.parse("flat(value=7)").unwrap();
+        assert!(flat
+            .importance(&img)
+            .bias
+            .as_slice()
+            .iter()
+            .all(|&b| b == 7));
+        assert!(registry.names().any(|name| name == "flat"));
+
+        let error = |spec: &str| registry.parse(spec).err().unwrap();
+        let unknown = RegistryError::UnknownEnergy {
+            name: "blur".to_string(),
+        };
+        assert_eq!(error("blur"), unknown);
+        let unknown = RegistryError::UnknownParameter {
+            energy: "flat".to_string(),
+            parameter: "radius".to_string(),
+        };
+        assert_eq!(error("flat(radius=1)"), unknown);
+        let invalid = RegistryError::InvalidParameter {
+            parameter: "value".to_string(),
+            value: "-1".to_string(),
+        };
+        assert_eq!(error("flat(value=-1)"), invalid);
+        let malformed = RegistryError::Malformed {
+            text: "value".to_string(),
+        };
+        assert_eq!(error("flat(value)"), malformed);
+        assert!(registry.parse("sobel(strength=2)").is_ok());
+        #[cfg(feature = "imageproc")]
+        {
+            let inverted = RegistryError::InvalidParameter {
+                parameter: "high".to_string(),
+                value: "10".to_string(),
+            };
+            assert_eq!(error("canny(low=50, high=10)"), inverted);
+        }
+    }
+}
@@ -0,0 +1,259 @@
+use image::{GenericImageView, Luma, Pixel};
+use num_traits::ToPrimitive;
+
+use crate::blend::carved_to_buffer;
+use crate::enlarge::{widen, InsertionBlend};
+use crate::mask::Rect;
+use crate::{image_view_to_buffer, Carvable, ImageBufferOf, PixelBuffer, Rotated, SeamError};
+
+/// Moves the object covered by the pixels that are not black in the mask
+/// by `offset` pixels, filling the hole it leaves in a content-aware way.
+///
+/// The object is first carved out of the image, by removing seams that go through it,
+/// then as many seams are inserted back where they are the least visible, as
+/// [enlarge] does. The object is then pasted at its new location,
+/// and blended with the background along its edges.
+/// The mask must have the same dimensions as the image.
+/// If the object would not be entirely inside the image once moved,
+/// [SeamError::OutOfBounds] is returned, and if a row of the object spans the whole width
+/// of the image, no background is left to fill its hole with and [SeamError::Empty] is returned.
+///
+/// ```no_run
+/// let img = image::open("./beach.jpg").unwrap();
+/// let boat = image::open("./boat_mask.png").unwrap().to_luma8();
+/// let moved = seamcarving::relocate_object(&img, &boat, (-120, 0)).unwrap();
+/// moved.save("./moved_boat.jpg").unwrap();
+/// ```
+///
+/// [enlarge]: crate::enlarge
+pub fn relocate_object<IMG: GenericImageView, M: GenericImageView<Pixel = Luma<u8>>>(
+    img: &IMG,
+    mask: &M,
+    (dx, dy): (i32, i32),
+) -> Result<ImageBufferOf<IMG>, SeamError>
+where
+    <IMG as GenericImageView>::Pixel: 'static,
+{
+    assert_eq!(
+        img.dimensions(),
+        mask.dimensions(),
+        "the mask must have the same dimensions as the image"
+    );
+    let (width, height) = img.dimensions();
+    let in_object = |x, y| mask.get_pixel(x, y).0[0] != 0;
+    let Some(object) = bounding_rect(width, height, in_object) else {
+        return Ok(crate::image_view_to_buffer(img));
+    };
+    let moved = |x: u32, y: u32| {
+        let (x, y) = (i64::from(x) + i64::from(dx), i64::from(y) + i64::from(dy));
+        (x >= 0 && x < i64::from(width) && y >= 0 && y < i64::from(height))
+            .then_some((x as u32, y as u32))
+    };
+    let last = (object.x + object.width - 1, object.y + object.height - 1);
+    if moved(object.x, object.y).is_none() || moved(last.0, last.1).is_none() {
+        let clamp = |v: i64| v.clamp(0, i64::from(u32::MAX)) as u32;
+        return Err(SeamError::OutOfBounds {
+            x: clamp(i64::from(last.0) + i64::from(dx)),
+            y: clamp(i64::from(last.1) + i64::from(dy)),
+        });
+    }
+
+    let seams = widest_row(mask);
+    if seams == width {
+        return Err(SeamError::Empty);
+    }
+    let hollowed = carve_out(img, mask, seams)?;
+    let mut output = widen(&hollowed, width, InsertionBlend::Average, None, None);
+
+    for y in object.y..=last.1 {
+        for x in object.x..=last.0 {
+            if !in_object(x, y) {
+                continue;
+            }
+            // Pixels on the edges of the object are partly covered by it
+            let neighbours = Rect {
+                x,
+                y,
+                width: 1,
+                height: 1,
+            }
+            .grow(1, (width, height));
+            let covered = (neighbours.y..neighbours.y + neighbours.height)
+                .flat_map(|ny| {
+                    (neighbours.x..neighbours.x + neighbours.width).map(move |nx| (nx, ny))
+                })
+                .filter(|&(nx, ny)| in_object(nx, ny))
+                .count();
+            let alpha = covered as f64 / 9.;
+            let (to_x, to_y) = moved(x, y).expect("the moved object is inside the image");
+            let background = *output.get_pixel(to_x, to_y);
+            output.put_pixel(to_x, to_y, mix(background, img.get_pixel(x, y), alpha));
+        }
+    }
+    Ok(output)
+}
+
+/// Removes the object covered by the pixels that are not black in the mask,
+/// by removing seams that go through it, as [relocate_object] does.
+///
+/// The seams are vertical, as many as the pixels of the widest row of the object,
+/// or horizontal when the object is not as tall as it is wide.
+/// With `restore_size`, as many seams are then inserted back where they are the least visible,
+/// as [enlarge] does, so that the image keeps its dimensions.
+/// The mask must have the same dimensions as the image.
+///
+/// ```
+/// let img = image::GrayImage::from_fn(20, 10, |x, y| image::Luma([(x * y) as u8]));
+/// let object = image::GrayImage::from_fn(20, 10, |x, y| {
+///     image::Luma([if (4..7).contains(&x) && y < 6 { 255 } else { 0 }])
+/// });
+/// let removed = seamcarving::remove_object(&img, &object, false).unwrap();
+/// assert_eq!(removed.dimensions(), (17, 10));
+/// let restored = seamcarving::remove_object(&img, &object, true).unwrap();
+/// assert_eq!(restored.dimensions(), (20, 10));
+/// ```
+///
+/// [enlarge]: crate::enlarge
+pub fn remove_object<IMG: GenericImageView, M: GenericImageView<Pixel = Luma<u8>>>(
+    img: &IMG,
+    mask: &M,
+    restore_size: bool,
+) -> Result<ImageBufferOf<IMG>, SeamError>
+where
+    <IMG as GenericImageView>::Pixel: 'static,
+{
+    assert_eq!(
+        img.dimensions(),
+        mask.dimensions(),
+        "the mask must have the same dimensions as the image"
+    );
+    let (width, height) = img.dimensions();
+    let (columns, rows) = (widest_row(mask), widest_row(&Rotated(mask)));
+    if rows < columns {
+        let hollowed = carve_out(&Rotated(img), &Rotated(mask), rows)?;
+        let restored = if restore_size {
+            widen(&hollowed, height, InsertionBlend::Average, None, None)
+        } else {
+            hollowed
+        };
+        return Ok(image_view_to_buffer(&Rotated(&restored)));
+    }
+    let hollowed = carve_out(img, mask, columns)?;
+    Ok(if restore_size {
+        widen(&hollowed, width, InsertionBlend::Average, None, None)
+    } else {
+        hollowed
+    })
+}
+
+/// The number of pixels of the object on its widest row
+fn widest_row<M: GenericImageView<Pixel = Luma<u8>>>(mask: &M) -> u32 {
+    let (width, height) = mask.dimensions();
+    (0..height)
+        .map(|y| (0..width).filter(|&x| mask.get_pixel(x, y).0[0] != 0).count() as u32)
+        .max()
+        .unwrap_or(0)
+}
+
+/// Removes `seams` vertical seams going through the object:
+/// every row loses its object pixels, and then any other pixels
+fn carve_out<IMG: GenericImageView, M: GenericImageView<Pixel = Luma<u8>>>(
+    img: &IMG,
+    mask: &M,
+    seams: u32,
+) -> Result<PixelBuffer<IMG::Pixel>, SeamError>
+where
+    <IMG as GenericImageView>::Pixel: 'static,
+{
+    let mut carvable = Carvable::new(img);
+    carvable.set_valid_region(mask);
+    for _ in 0..seams {
+        carvable.try_remove_seam()?;
+    }
+    Ok(carved_to_buffer(carvable.result(), false))
+}
+
+/// Smallest rectangle containing all the positions for which `inside` is true
+fn bounding_rect(width: u32, height: u32, inside: impl Fn(u32, u32) -> bool) -> Option<Rect> {
+    let (mut min, mut max) = ((u32::MAX, u32::MAX), (0, 0));
+    for y in 0..height {
+        for x in (0..width).filter(|&x| inside(x, y)) {
+            min = (min.0.min(x), min.1.min(y));
+            max = (max.0.max(x), max.1.max(y));
+        }
+    }
+    (min.0 <= max.0).then(|| Rect {
+        x: min.0,
+        y: min.1,
+        width: max.0 - min.0 + 1,
+        height: max.1 - min.1 + 1,
+    })
+}
+
+/// Mixes two pixels, `alpha` being the weight of the second one
+fn mix<P: Pixel>(a: P, b: P, alpha: f64) -> P {
+    a.map2(&b, |a, b| {
+        let (a_value, b_value) = (a.to_f64().unwrap_or(0.), b.to_f64().unwrap_or(0.));
+        let mixed = a_value * (1. - alpha) + b_value * alpha;
+        num_traits::cast(mixed.round()).unwrap_or(b)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use image::{GrayImage, Rgb, RgbImage};
+
+    use super::*;
+
+    #[test]
+    fn moves_objects_and_fills_their_hole() {
+        let red = Rgb([250, 0, 0]);
+        let img = RgbImage::from_fn(30, 12, |x, y| match (x, y) {
+            (4..=9, 3..=8) => red,
+            _ => Rgb([(x * 4) as u8, 100, (y * 10) as u8]),
+        });
+        let mask = GrayImage::from_fn(30, 12, |x, y| {
+            Luma([if img[(x, y)] == red { 255 } else { 0 }])
+        });
+        let moved = relocate_object(&img, &mask, (15, 1)).unwrap();
+        assert_eq!(moved.dimensions(), (30, 12));
+        let across = GrayImage::from_fn(30, 12, |_x, y| Luma([if y == 1 { 255 } else { 0 }]));
+        assert_eq!(relocate_object(&img, &across, (0, 1)), Err(SeamError::Empty));
+        // The inside of the object is copied as is, and its edges are blended
+        assert!((20..=23).all(|x| (5..=8).all(|y| moved[(x, y)] == red)));
+        assert_ne!(moved[(19, 4)], red);
+        let red_pixels = |x_range: std::ops::Range<u32>| {
+            x_range
+                .flat_map(|x| (0..12).map(move |y| (x, y)))
+                .filter(|&(x, y)| moved[(x, y)] == red)
+                .count()
+        };
+        assert_eq!(red_pixels(0..15), 0);
+        assert_eq!(red_pixels(15..30), 16);
+
+        let too_far = relocate_object(&img, &mask, (25, 0));
+        assert_eq!(too_far, Err(SeamError::OutOfBounds { x: 34, y: 8 }));
+        let nothing = GrayImage::new(30, 12);
+        assert_eq!(relocate_object(&img, &nothing, (5, 5)).unwrap(), img);
+    }
+
+    #[test]
+    fn removes_objects_along_their_narrowest_side() {
+        let red = Rgb([250, 0, 0]);
+        let img = RgbImage::from_fn(30, 12, |x, y| match (x, y) {
+            (4..=13, 5..=6) => red,
+            _ => Rgb([(x * 4) as u8, 100, (y * 10) as u8]),
+        });
+        let mask = GrayImage::from_fn(30, 12, |x, y| {
+            Luma([if img[(x, y)] == red { 255 } else { 0 }])
+        });
+        let removed = remove_object(&img, &mask, false).unwrap();
+        assert_eq!(removed.dimensions(), (30, 10));
+        assert!(removed.pixels().all(|&p| p != red));
+        let restored = remove_object(&img, &mask, true).unwrap();
+        assert_eq!(restored.dimensions(), (30, 12));
+        assert!(restored.pixels().all(|&p| p != red));
+        let nothing = GrayImage::new(30, 12);
+        assert_eq!(remove_object(&img, &nothing, true).unwrap(), img);
+    }
+}
@@ -0,0 +1,230 @@
+use crate::mask::Rect;
+use crate::matrix::Matrix;
+use crate::pos::Pos;
+
+/// A position or a region of an image, such as a label of a dataset,
+/// that follows the pixels of the image when it is resized,
+/// see [Carver::resize_with_annotations](crate::Carver::resize_with_annotations)
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum Annotation {
+    /// A pixel, given by its (x, y) coordinates
+    Point(u32, u32),
+    /// A rectangle, which becomes the bounding box of its pixels that are kept
+    Rect(Rect),
+}
+
+/// Where the pixels of an image went when it was resized, in both directions,
+/// returned by [Carver::resize_with_map](crate::Carver::resize_with_map).
+///
+/// Positions are given as (x, y) pixel coordinates.
+/// Pixels of an image that was also scaled down are made from several pixels
+/// of the original image, and the pixels of the original image end up in the pixel
+/// of the resized image that covers them.
+#[derive(Clone, Debug)]
+pub struct CoordinateMap {
+    // Position in the original image of each pixel of the carved image, before it was scaled
+    origins: Matrix<Pos>,
+    // Position in the carved image of each pixel of the original image, unless it was removed
+    destinations: Matrix<Option<Pos>>,
+    // Dimensions of the resized image
+    size: Pos,
+}
+
+impl CoordinateMap {
+    /// The map of an image of the given size carved to the positions of `origins`,
+    /// then scaled to `size`
+    pub(crate) fn new(original: Pos, origins: Matrix<Pos>, size: Pos) -> Self {
+        let mut destinations = Matrix::from_fn(original, |_, _| None);
+        for y in 0..origins.height() {
+            for x in 0..origins.width() {
+                destinations[origins[Pos(x, y)]] = Some(Pos(x, y));
+            }
+        }
+        CoordinateMap {
+            origins,
+            destinations,
+            size,
+        }
+    }
+
+    /// The position in the original image of a pixel of the resized image,
+    /// or None if it is outside of the resized image.
+    /// For scaled images, it is the position of the pixel closest to its center.
+    pub fn origin(&self, x: u32, y: u32) -> Option<(u32, u32)> {
+        if !Pos(x, y).is_inside(self.size) {
+            return None;
+        }
+        let x = nearest_center(x, self.size.0, self.origins.width());
+        let y = nearest_center(y, self.size.1, self.origins.height());
+        Some(self.origins[Pos(x, y)].into())
+    }
+
+    /// The position in the resized image of a pixel of the original image,
+    /// or None if it was removed with a seam, or is outside of the original image
+    ///
+    /// ```
+    /// use seamcarving::Carver;
+    /// let img = image::GrayImage::from_fn(20, 10, |x, y| image::Luma([(x * y) as u8]));
+    /// let (_resized, map) = Carver::new().width(15).resize_with_map(&img).unwrap();
+    /// let map = map.unwrap();
+    /// let removed = (0..20).filter(|&x| map.destination(x, 3).is_none()).count();
+    /// assert_eq!(removed, 5);
+    /// let (x, y) = map.destination(19, 3).unwrap();
+    /// assert_eq!(map.origin(x, y), Some((19, 3)));
+    /// ```
+    pub fn destination(&self, x: u32, y: u32) -> Option<(u32, u32)> {
+        let Pos(x, y) = *self.destinations.get(Pos(x, y))?.as_ref()?;
+        let scale = |i: u32, count: u32, scaled: u32| {
+            (u64::from(i) * u64::from(scaled) / u64::from(count)) as u32
+        };
+        Some((
+            scale(x, self.origins.width(), self.size.0),
+            scale(y, self.origins.height(), self.size.1),
+        ))
+    }
+
+    /// Moves an annotation of the original image to the resized image,
+    /// or returns None if all its pixels were removed
+    pub fn carry(&self, annotation: Annotation) -> Option<Annotation> {
+        let rect = match annotation {
+            Annotation::Point(x, y) => {
+                let (x, y) = self.destination(x, y)?;
+                return Some(Annotation::Point(x, y));
+            }
+            Annotation::Rect(rect) => rect,
+        };
+        let (width, height) = (self.destinations.width(), self.destinations.height());
+        let rect = rect.grow(0, (width, height));
+        let end = Pos(rect.x + rect.width, rect.y + rect.height);
+        let kept = Pos::rows_in_rect(Pos(rect.x, rect.y), end)
+            .flatten()
+            .filter_map(|Pos(x, y)| self.destination(x, y));
+        let (mut left, mut top, mut right, mut bottom) = (u32::MAX, u32::MAX, 0, 0);
+        for (x, y) in kept {
+            (left, top) = (left.min(x), top.min(y));
+            (right, bottom) = (right.max(x + 1), bottom.max(y + 1));
+        }
+        let bounds = Rect {
+            x: left,
+            y: top,
+            width: right.checked_sub(left)?,
+            height: bottom.checked_sub(top)?,
+        };
+        Some(Annotation::Rect(bounds))
+    }
+
+    /// The position in the original image of each pixel of the resized image,
+    /// see [origin](CoordinateMap::origin)
+    pub fn origins(&self) -> Matrix<Pos> {
+        Matrix::from_fn(self.size, |x, y| {
+            let (x, y) = self
+                .origin(x as u32, y as u32)
+                .expect("inside the resized image");
+            Pos(x, y)
+        })
+    }
+}
+
+/// Moves an annotation of an image that was scaled from `original` to `size`,
+/// or returns None if it is outside of the image
+pub(crate) fn scale_annotation(
+    annotation: Annotation,
+    original: (u32, u32),
+    size: (u32, u32),
+) -> Option<Annotation> {
+    // The row or column covering row or column `i`, or the first one after it
+    let scale = |i: u32, count: u32, scaled: u32, after: bool| {
+        let (i, count, scaled) = (u64::from(i), u64::from(count), u64::from(scaled));
+        let scaled = if after {
+            (i * scaled).div_ceil(count)
+        } else {
+            i * scaled / count
+        };
+        scaled as u32
+    };
+    match annotation {
+        Annotation::Point(x, y) => {
+            if !Pos(x, y).is_inside(original.into()) {
+                return None;
+            }
+            let x = scale(x, original.0, size.0, false);
+            let y = scale(y, original.1, size.1, false);
+            Some(Annotation::Point(x, y))
+        }
+        Annotation::Rect(rect) => {
+            let rect = rect.grow(0, original);
+            if rect.width == 0 || rect.height == 0 {
+                return None;
+            }
+            let x = scale(rect.x, original.0, size.0, false);
+            let y = scale(rect.y, original.1, size.1, false);
+            let right = scale(rect.x + rect.width, original.0, size.0, true);
+            let bottom = scale(rect.y + rect.height, original.1, size.1, true);
+            let scaled = Rect {
+                x,
+                y,
+                width: right - x,
+                height: bottom - y,
+            };
+            Some(Annotation::Rect(scaled)).filter(|_| scaled.width > 0 && scaled.height > 0)
+        }
+    }
+}
+
+/// The row or column among `count` ones closest to the center of row or column `i`
+/// of an image in which there are `scaled` ones instead
+fn nearest_center(i: u32, scaled: u32, count: u32) -> u32 {
+    ((2 * u64::from(i) + 1) * u64::from(count) / (2 * u64::from(scaled))) as u32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn maps_scaled_pixels_both_ways() {
+        // Column 1 removed from a 4x2 image, then scaled to 2x1
+        let origins = Matrix::from_fn(Pos(3, 2), |x, y| Pos([0, 2, 3][x], y as u32));
+        let map = CoordinateMap::new(Pos(4, 2), origins, Pos(2, 1));
+        assert_eq!(map.origin(0, 0), Some((0, 1)));
+        assert_eq!(map.origin(1, 0), Some((3, 1)));
+        assert_eq!(map.origin(2, 0), None);
+        assert_eq!(map.destination(1, 0), None);
+        assert_eq!(map.destination(2, 1), Some((0, 0)));
+        assert_eq!(map.destination(3, 0), Some((1, 0)));
+        assert_eq!(map.destination(4, 0), None);
+        assert_eq!(map.origins().into_vec(), vec![Pos(0, 1), Pos(3, 1)]);
+    }
+
+    #[test]
+    fn carries_annotations() {
+        // Column 2 removed from a 5x3 image
+        let origins = Matrix::from_fn(Pos(4, 3), |x, y| Pos([0, 1, 3, 4][x], y as u32));
+        let map = CoordinateMap::new(Pos(5, 3), origins, Pos(4, 3));
+        let rect = |x, y, width, height| {
+            Annotation::Rect(Rect {
+                x,
+                y,
+                width,
+                height,
+            })
+        };
+        assert_eq!(
+            map.carry(Annotation::Point(3, 1)),
+            Some(Annotation::Point(2, 1))
+        );
+        assert_eq!(map.carry(Annotation::Point(2, 1)), None);
+        assert_eq!(map.carry(rect(1, 0, 3, 2)), Some(rect(1, 0, 2, 2)));
+        assert_eq!(map.carry(rect(2, 0, 1, 3)), None);
+        assert_eq!(map.carry(rect(3, 2, 10, 10)), Some(rect(2, 2, 2, 1)));
+        assert_eq!(map.carry(rect(5, 0, 2, 2)), None);
+        // The same image scaled instead
+        let scaled = |annotation| scale_annotation(annotation, (5, 3), (4, 3));
+        assert_eq!(
+            scaled(Annotation::Point(3, 1)),
+            Some(Annotation::Point(2, 1))
+        );
+        assert_eq!(scaled(rect(1, 0, 3, 2)), Some(rect(0, 0, 4, 2)));
+        assert_eq!(scaled(rect(5, 0, 2, 2)), None);
+    }
+}
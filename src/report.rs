@@ -0,0 +1,344 @@
+use std::time::Duration;
+
+use crate::cache::Axis;
+use crate::carver::{Algorithm, AxisOrder};
+
+/// How an image was reduced to its final size,
+/// returned by [Carver::resize_with_report](crate::Carver::resize_with_report)
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Default)]
+#[non_exhaustive]
+pub struct Report {
+    /// Number of vertical and horizontal seams that were removed
+    pub seams: (u32, u32),
+    /// Number of columns and rows that were removed by scaling the image down,
+    /// because the remaining seams were too visible, or there was no time left to find them
+    pub scaled: (u32, u32),
+    /// Whether carving was stopped because the [time budget](crate::Carver::time_budget)
+    /// was exhausted
+    pub out_of_time: bool,
+    /// Total energy of the removed seams
+    pub removed_energy: u64,
+    /// Whether carving was stopped because the [energy budget](crate::Carver::energy_budget)
+    /// was exhausted, in which case the image is larger than requested
+    pub out_of_energy: bool,
+    /// Dimensions of the resized image
+    pub size: (u32, u32),
+    /// Dimensions that were requested
+    pub requested: (u32, u32),
+    /// Whether carving was stopped by the callback of
+    /// [resize_with_callback](crate::Carver::resize_with_callback)
+    pub stopped: bool,
+    /// Whether carving was stopped because the image became less similar to the original
+    /// than the [minimum similarity](crate::Carver::min_similarity)
+    pub too_dissimilar: bool,
+    /// Whether the image was carved in horizontal stripes to fit in the
+    /// [memory limit](crate::Carver::max_memory). The vertical seams and scaled columns
+    /// are then the largest numbers of all the stripes, and the horizontal ones their sums.
+    pub striped: bool,
+    /// Time spent in each step of the resize,
+    /// if [measure_timings](crate::Carver::measure_timings) was enabled
+    pub timings: Option<Timings>,
+}
+
+/// Information about a seam that was just removed,
+/// given to the callback of [Carver::resize_with_callback](crate::Carver::resize_with_callback)
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+#[non_exhaustive]
+pub struct SeamStats {
+    /// Direction of the seam
+    pub axis: Axis,
+    /// Sum of the energies of the pixels of the seam
+    pub energy: u32,
+    /// Number of vertical and horizontal seams removed so far, including this one
+    pub removed: (u32, u32),
+    /// Dimensions of the image once the seam is removed
+    pub size: (u32, u32),
+}
+
+impl SeamStats {
+    /// The mean energy of the pixels of the seam
+    pub fn mean_energy(&self) -> u32 {
+        let length = match self.axis {
+            Axis::Vertical => self.size.1,
+            Axis::Horizontal => self.size.0,
+        };
+        self.energy / length.max(1)
+    }
+}
+
+/// Time spent in each step of a resize, see [Carver::measure_timings](crate::Carver::measure_timings)
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Default)]
+#[non_exhaustive]
+pub struct Timings {
+    /// Computing the energy of the pixels
+    pub energy: Duration,
+    /// Computing the cumulative energies of the seams, without the energy of the pixels
+    pub fill: Duration,
+    /// Following the cheapest seams from the bottom of the image to its top
+    pub backtrack: Duration,
+    /// Removing the seams from the image and copying the carved pixels
+    pub removal: Duration,
+}
+
+impl Timings {
+    /// The time spent in all the steps
+    pub fn total(&self) -> Duration {
+        self.energy + self.fill + self.backtrack + self.removal
+    }
+}
+
+impl std::ops::AddAssign for Timings {
+    fn add_assign(&mut self, other: Timings) {
+        self.energy += other.energy;
+        self.fill += other.fill;
+        self.backtrack += other.backtrack;
+        self.removal += other.removal;
+    }
+}
+
+/// What a resize would do, returned by [Carver::plan](crate::Carver::plan)
+/// without looking at the pixels of the image
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Default)]
+#[non_exhaustive]
+pub struct Plan {
+    /// Number of vertical and horizontal seams to remove
+    pub seams: (u32, u32),
+    /// Whether some of the seams may be replaced by scaling,
+    /// because of an energy limit or of a time budget
+    pub may_scale: bool,
+    /// Estimation of the maximal amount of memory used during the resize, in bytes
+    pub peak_memory: usize,
+    /// Estimation of the number of times the energy of a pixel is computed,
+    /// which is roughly proportional to the time the resize takes
+    pub cost: u64,
+    /// Dimensions of the resized image
+    pub size: (u32, u32),
+    /// How the seams are chosen
+    pub algorithm: Algorithm,
+    /// The order in which vertical and horizontal seams are removed
+    pub order: AxisOrder,
+}
+
+/// Energies computed per second by an optimized build on a desktop processor,
+/// from which [Plan::estimated_time] is derived
+const ENERGIES_PER_SECOND: f64 = 120e6;
+
+impl Plan {
+    /// A rough estimation of the time the resize takes with an optimized build,
+    /// from its [cost](Plan::cost)
+    pub fn estimated_time(&self) -> Duration {
+        Duration::from_secs_f64(self.cost as f64 / ENERGIES_PER_SECOND)
+    }
+
+    /// The plan as a JSON object on a single line
+    pub fn to_json(&self) -> String {
+        let algorithm = match self.algorithm {
+            Algorithm::Backward => "backward",
+            Algorithm::Forward => "forward",
+            Algorithm::Greedy => "greedy",
+            Algorithm::Hybrid => "hybrid",
+        };
+        let order = match self.order {
+            AxisOrder::WidthFirst => "width_first",
+            AxisOrder::Greedy => "greedy",
+        };
+        format!(
+            "{{\"width\":{},\"height\":{},\"vertical_seams\":{},\"horizontal_seams\":{},\
+             \"algorithm\":\"{}\",\"axis_order\":\"{}\",\"may_scale\":{},\
+             \"peak_memory_bytes\":{},\"cost\":{},\"estimated_seconds\":{}}}",
+            self.size.0,
+            self.size.1,
+            self.seams.0,
+            self.seams.1,
+            algorithm,
+            order,
+            self.may_scale,
+            self.peak_memory,
+            self.cost,
+            self.estimated_time().as_secs_f64(),
+        )
+    }
+}
+
+/// Columns of the CSV lines written by [FileReport::to_csv]
+const CSV_HEADER: &str = "file,original_width,original_height,width,height,\
+vertical_seams,horizontal_seams,total_seam_energy,mean_seam_energy,wall_time_seconds,scaled";
+
+/// The outcome of the resize of one file of a batch, which can be exported
+/// as a JSON object or as a CSV line for other tools.
+///
+/// ```
+/// use std::time::Duration;
+/// use seamcarving::{Carver, FileReport};
+/// let img = image::RgbImage::new(20, 10);
+/// let (_resized, report) = Carver::new().width(15).resize_with_report(&img).unwrap();
+/// let file = FileReport::new("photo.jpg", (20, 10), report, Duration::from_millis(3));
+/// assert!(file.to_json().starts_with(r#"{"file":"photo.jpg","original_width":20,"#));
+/// assert!(file.to_csv().starts_with("photo.jpg,20,10,15,10,5,0,"));
+/// ```
+#[derive(Clone, PartialEq, Debug)]
+#[non_exhaustive]
+pub struct FileReport {
+    /// Name of the resized file
+    pub file: String,
+    /// Dimensions of the original image
+    pub original: (u32, u32),
+    /// How the image was reduced to its final size
+    pub report: Report,
+    /// Time spent resizing the image, from decoding it to encoding the result
+    pub wall_time: Duration,
+}
+
+impl FileReport {
+    /// The report of the resize of the named file, whose image had the `original` dimensions
+    pub fn new(
+        file: impl Into<String>,
+        original: (u32, u32),
+        report: Report,
+        wall_time: Duration,
+    ) -> FileReport {
+        FileReport {
+            file: file.into(),
+            original,
+            report,
+            wall_time,
+        }
+    }
+
+    /// The mean energy of the removed seams, 0 when no seam was removed
+    pub fn mean_seam_energy(&self) -> f64 {
+        let seams = self.report.seams.0 + self.report.seams.1;
+        if seams == 0 {
+            return 0.;
+        }
+        self.report.removed_energy as f64 / f64::from(seams)
+    }
+
+    /// Whether some of the rows or columns were removed by scaling the image down
+    /// instead of carving it
+    pub fn scaled(&self) -> bool {
+        self.report.scaled != (0, 0)
+    }
+
+    /// The report as a JSON object on a single line
+    pub fn to_json(&self) -> String {
+        format!(
+            "{{\"file\":\"{}\",\"original_width\":{},\"original_height\":{},\
+             \"width\":{},\"height\":{},\"vertical_seams\":{},\"horizontal_seams\":{},\
+             \"total_seam_energy\":{},\"mean_seam_energy\":{},\"wall_time_seconds\":{},\
+             \"scaled\":{}}}",
+            json_escape(&self.file),
+            self.original.0,
+            self.original.1,
+            self.report.size.0,
+            self.report.size.1,
+            self.report.seams.0,
+            self.report.seams.1,
+            self.report.removed_energy,
+            self.mean_seam_energy(),
+            self.wall_time.as_secs_f64(),
+            self.scaled(),
+        )
+    }
+
+    /// The names of the columns of [to_csv](FileReport::to_csv)
+    pub fn csv_header() -> &'static str {
+        CSV_HEADER
+    }
+
+    /// The report as a line of CSV, without line terminator,
+    /// see [csv_header](FileReport::csv_header)
+    pub fn to_csv(&self) -> String {
+        format!(
+            "{},{},{},{},{},{},{},{},{},{},{}",
+            csv_escape(&self.file),
+            self.original.0,
+            self.original.1,
+            self.report.size.0,
+            self.report.size.1,
+            self.report.seams.0,
+            self.report.seams.1,
+            self.report.removed_energy,
+            self.mean_seam_energy(),
+            self.wall_time.as_secs_f64(),
+            self.scaled(),
+        )
+    }
+}
+
+/// The reports of a batch as a JSON array, one file per line
+pub fn reports_to_json(reports: &[FileReport]) -> String {
+    let lines: Vec<String> = reports.iter().map(FileReport::to_json).collect();
+    format!("[\n{}\n]\n", lines.join(",\n"))
+}
+
+/// The reports of a batch as CSV, with a header line
+pub fn reports_to_csv(reports: &[FileReport]) -> String {
+    let mut csv = format!("{}\n", CSV_HEADER);
+    for report in reports {
+        csv.push_str(&report.to_csv());
+        csv.push('\n');
+    }
+    csv
+}
+
+fn json_escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            c if u32::from(c) < 0x20 => escaped.push_str(&format!("\\u{:04x}", u32::from(c))),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Quotes fields containing separators, quotes or line breaks
+fn csv_escape(text: &str) -> String {
+    if text.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", text.replace('"', "\"\""))
+    } else {
+        text.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn file_report(file: &str) -> FileReport {
+        let report = Report {
+            seams: (3, 1),
+            scaled: (2, 0),
+            removed_energy: 100,
+            size: (15, 9),
+            requested: (15, 9),
+            ..Report::default()
+        };
+        FileReport::new(file, (20, 10), report, Duration::from_millis(1500))
+    }
+
+    #[test]
+    fn exports_reports() {
+        let reports = [file_report("a.png"), file_report("b, \"c\".png")];
+        assert_eq!(
+            reports_to_csv(&reports),
+            format!(
+                "{}\na.png,20,10,15,9,3,1,100,25,1.5,true\n\
+                 \"b, \"\"c\"\".png\",20,10,15,9,3,1,100,25,1.5,true\n",
+                CSV_HEADER
+            )
+        );
+        let json = reports_to_json(&reports);
+        assert!(json.starts_with("[\n{\"file\":\"a.png\",\"original_width\":20,"));
+        assert!(json.contains(
+            "{\"file\":\"b, \\\"c\\\".png\",\"original_width\":20,\"original_height\":10,\
+             \"width\":15,\"height\":9,\"vertical_seams\":3,\"horizontal_seams\":1,\
+             \"total_seam_energy\":100,\"mean_seam_energy\":25,\"wall_time_seconds\":1.5,\
+             \"scaled\":true}\n]"
+        ));
+    }
+}
@@ -0,0 +1,110 @@
+use image::RgbaImage;
+
+use crate::{Carver, Plan, SeamError};
+
+/// Something that resizes images to a size it was configured with, such as a [Carver].
+///
+/// Applications can depend on this trait rather than on [Carver] directly,
+/// to switch between implementations at runtime or to replace the carver
+/// by a cheaper one in their tests. The trait is object safe.
+///
+/// ```
+/// use seamcarving::{Carver, Retargeter};
+/// fn thumbnail(retargeter: &dyn Retargeter, img: &image::RgbaImage) -> image::RgbaImage {
+///     retargeter.resize(img).unwrap()
+/// }
+/// let img = image::RgbaImage::new(40, 30);
+/// let retargeter = Carver::new().width(20);
+/// assert_eq!(thumbnail(&retargeter, &img).dimensions(), (20, 30));
+/// ```
+pub trait Retargeter {
+    /// Resizes an image
+    fn resize(&self, img: &RgbaImage) -> Result<RgbaImage, SeamError>;
+
+    /// Describes what resizing the given image would do, without resizing it
+    fn plan(&self, img: &RgbaImage) -> Plan;
+
+    /// What this implementation supports
+    fn capabilities(&self) -> Capabilities;
+}
+
+/// The features supported by a [Retargeter]
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Default)]
+#[non_exhaustive]
+pub struct Capabilities {
+    /// Whether images can be made larger than they are
+    pub enlarges: bool,
+    /// Whether importance maps and masks are taken into account
+    pub masks: bool,
+    /// Whether the time spent on each image can be limited
+    pub time_budget: bool,
+    /// Whether the image may be scaled when carving it would be too visible
+    pub scales: bool,
+}
+
+impl Retargeter for Carver {
+    fn resize(&self, img: &RgbaImage) -> Result<RgbaImage, SeamError> {
+        Carver::resize(self, img)
+    }
+
+    fn plan(&self, img: &RgbaImage) -> Plan {
+        Carver::plan(self, img)
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        Capabilities {
+            enlarges: false,
+            masks: true,
+            time_budget: true,
+            scales: true,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use image::imageops::FilterType;
+
+    use super::*;
+
+    /// Scales images instead of carving them, like a test double would
+    struct Scaler(u32, u32);
+
+    impl Retargeter for Scaler {
+        fn resize(&self, img: &RgbaImage) -> Result<RgbaImage, SeamError> {
+            Ok(image::imageops::resize(
+                img,
+                self.0,
+                self.1,
+                FilterType::Nearest,
+            ))
+        }
+
+        fn plan(&self, _img: &RgbaImage) -> Plan {
+            Plan::default()
+        }
+
+        fn capabilities(&self) -> Capabilities {
+            Capabilities {
+                enlarges: true,
+                scales: true,
+                ..Capabilities::default()
+            }
+        }
+    }
+
+    #[test]
+    fn swaps_implementations() {
+        let img = RgbaImage::from_fn(12, 8, |x, y| {
+            image::Rgba([(x * 20) as u8, (y * 30) as u8, 0, 255])
+        });
+        let retargeters: Vec<Box<dyn Retargeter>> =
+            vec![Box::new(Carver::new().width(6)), Box::new(Scaler(6, 8))];
+        for retargeter in &retargeters {
+            assert_eq!(retargeter.resize(&img).unwrap().dimensions(), (6, 8));
+        }
+        assert_eq!(retargeters[0].plan(&img).seams, (6, 0));
+        assert!(!retargeters[0].capabilities().enlarges);
+        assert!(retargeters[1].capabilities().enlarges);
+    }
+}
@@ -0,0 +1,237 @@
+use alloc::vec;
+use alloc::vec::Vec;
+
+#[cfg(feature = "image")]
+use image::{ImageBuffer, Pixel};
+
+use crate::matrix::Matrix;
+use crate::pos::Pos;
+use crate::SeamError;
+
+/// A vertical seam: one position per row of an image
+///
+/// The positions are always ordered from the bottom row to the top row,
+/// which is the order in which the seam carving algorithm finds them.
+/// Use [sorted_top_down](Seam::sorted_top_down) to get them in reading order.
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+pub struct Seam {
+    positions: Vec<Pos>,
+}
+
+impl Seam {
+    pub(crate) fn new(positions: Vec<Pos>) -> Self {
+        Seam { positions }
+    }
+
+    /// The positions of the seam, from the bottom row to the top row
+    pub fn positions(&self) -> &[Pos] {
+        &self.positions
+    }
+
+    /// The positions of the seam, from the top row to the bottom row
+    pub fn sorted_top_down(&self) -> Vec<Pos> {
+        self.positions.iter().rev().copied().collect()
+    }
+
+    /// The column of the seam on the given row, if the seam crosses it
+    pub fn x_at(&self, y: u32) -> Option<u32> {
+        let height = self.positions.len() as u32;
+        let index = height.checked_sub(y + 1)?;
+        self.positions.get(index as usize).map(|pos| pos.0)
+    }
+
+    /// Number of rows crossed by the seam
+    pub fn len(&self) -> usize {
+        self.positions.len()
+    }
+
+    /// Whether the seam is empty, which only happens for images without any row
+    pub fn is_empty(&self) -> bool {
+        self.positions.is_empty()
+    }
+}
+
+impl From<Seam> for Vec<Pos> {
+    /// The positions of the seam, from the bottom row to the top row
+    fn from(seam: Seam) -> Self {
+        seam.positions
+    }
+}
+
+impl AsRef<[Pos]> for Seam {
+    fn as_ref(&self) -> &[Pos] {
+        &self.positions
+    }
+}
+
+/// A fingerprint of a list of seams, to check cheaply that two runs, possibly
+/// on different machines or with different versions of this crate, removed the same seams.
+///
+/// The seams can be [Seam]s, or the positions returned by
+/// [SeamFinder::extract_seam](crate::SeamFinder::extract_seam).
+/// The fingerprint is stable: it is the 64 bits FNV-1a hash of the number of seams,
+/// then of the number of positions of each seam followed by the `x` and `y` coordinates
+/// of its positions, in their order, all written as little endian 32 bits integers.
+///
+/// ```
+/// # #[cfg(feature = "image")] {
+/// use seamcarving::{seam_fingerprint, Carvable};
+/// let img = image::GrayImage::from_fn(8, 4, |x, y| image::Luma([(x * y * 30) as u8]));
+/// let seams = |img| {
+///     let mut carvable = Carvable::new(img);
+///     (0..3).map(|_| carvable.try_remove_seam_returning().unwrap()).collect::<Vec<_>>()
+/// };
+/// assert_eq!(seam_fingerprint(&seams(&img)), seam_fingerprint(&seams(&img.clone())));
+/// # }
+/// ```
+pub fn seam_fingerprint<S: AsRef<[Pos]>>(seams: &[S]) -> u64 {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    let mut write = |value: u32| {
+        for byte in value.to_le_bytes() {
+            hash ^= u64::from(byte);
+            hash = hash.wrapping_mul(0x0100_0000_01b3);
+        }
+    };
+    write(seams.len() as u32);
+    for seam in seams {
+        let positions = seam.as_ref();
+        write(positions.len() as u32);
+        for &Pos(x, y) in positions {
+            write(x);
+            write(y);
+        }
+    }
+    hash
+}
+
+/// Images and matrices from which [apply_seam] removes seams
+pub trait SeamTarget {
+    /// Width and height
+    fn dimensions(&self) -> (u32, u32);
+
+    /// Removes a vertical seam, with exactly one position inside on each row,
+    /// shifting the rest of each row to the left
+    fn remove_checked_seam(&mut self, seam: &[Pos]);
+}
+
+impl<T> SeamTarget for Matrix<T> {
+    fn dimensions(&self) -> (u32, u32) {
+        (self.width(), self.height())
+    }
+
+    fn remove_checked_seam(&mut self, seam: &[Pos]) {
+        self.remove_seam(seam)
+    }
+}
+
+#[cfg(feature = "image")]
+impl<P: Pixel + 'static> SeamTarget for ImageBuffer<P, Vec<P::Subpixel>> {
+    fn dimensions(&self) -> (u32, u32) {
+        ImageBuffer::dimensions(self)
+    }
+
+    fn remove_checked_seam(&mut self, seam: &[Pos]) {
+        let (width, height) = ImageBuffer::dimensions(self);
+        let channels = usize::from(P::CHANNEL_COUNT);
+        let mut columns = vec![0; height as usize];
+        for &Pos(x, y) in seam {
+            columns[y as usize] = x as usize * channels;
+        }
+        let row_length = width as usize * channels;
+        let mut raw = core::mem::replace(self, ImageBuffer::new(0, 0)).into_raw();
+        let mut end = 0;
+        for (y, &removed) in columns.iter().enumerate() {
+            let start = y * row_length;
+            raw.copy_within(start..start + removed, end);
+            raw.copy_within(start + removed + channels..start + row_length, end + removed);
+            end += row_length - channels;
+        }
+        raw.truncate(end);
+        *self = ImageBuffer::from_raw(width - 1, height, raw).expect("every row lost one pixel");
+    }
+}
+
+/// Removes a vertical seam computed elsewhere, for instance by another tool
+/// or by a [SeamFinder](crate::SeamFinder) on a smaller version of the image,
+/// from an image or a matrix, after checking that it can be removed.
+///
+/// The seam must have exactly one position on each row, in any order: its positions
+/// do not have to be next to each other. Fails with [SeamError::OutOfBounds]
+/// if a position is outside, with [SeamError::InvalidSeam] if a row
+/// has no position or several of them, and with [SeamError::Empty] if there is no column left.
+/// Nothing is removed when it fails.
+///
+/// ```
+/// # #[cfg(feature = "image")] {
+/// use seamcarving::{apply_seam, Pos, SeamError};
+/// let mut img = image::GrayImage::from_fn(4, 2, |x, y| image::Luma([(x + 4 * y) as u8]));
+/// apply_seam(&mut img, &[Pos(1, 0), Pos(3, 1)]).unwrap();
+/// assert_eq!(img.into_raw(), vec![0, 2, 3, 4, 5, 6]);
+/// let mut img = image::GrayImage::new(4, 2);
+/// assert_eq!(apply_seam(&mut img, &[Pos(1, 0)]), Err(SeamError::InvalidSeam { y: 1 }));
+/// # }
+/// ```
+pub fn apply_seam<T: SeamTarget + ?Sized>(target: &mut T, seam: &[Pos]) -> Result<(), SeamError> {
+    let (width, height) = target.dimensions();
+    if width == 0 {
+        return Err(SeamError::Empty);
+    }
+    let mut rows = vec![false; height as usize];
+    for &Pos(x, y) in seam {
+        if x >= width || y >= height {
+            return Err(SeamError::OutOfBounds { x, y });
+        }
+        if core::mem::replace(&mut rows[y as usize], true) {
+            return Err(SeamError::InvalidSeam { y });
+        }
+    }
+    if let Some(y) = rows.iter().position(|&row| !row) {
+        return Err(SeamError::InvalidSeam { y: y as u32 });
+    }
+    target.remove_checked_seam(seam);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn orders_positions() {
+        let seam = Seam::new(vec![Pos(2, 2), Pos(1, 1), Pos(1, 0)]);
+        assert_eq!(seam.sorted_top_down(), vec![Pos(1, 0), Pos(1, 1), Pos(2, 2)]);
+        assert_eq!(seam.x_at(2), Some(2));
+        assert_eq!(seam.x_at(0), Some(1));
+        assert_eq!(seam.x_at(3), None);
+    }
+
+    #[test]
+    fn fingerprints_are_stable() {
+        let seams = [
+            Seam::new(vec![Pos(2, 1), Pos(1, 0)]),
+            Seam::new(vec![Pos(0, 1), Pos(0, 0)]),
+        ];
+        assert_eq!(seam_fingerprint(&seams), 0x2432_4052_ec65_7874);
+        let positions: Vec<Vec<Pos>> = seams.iter().cloned().map(Vec::from).collect();
+        assert_eq!(seam_fingerprint(&positions), seam_fingerprint(&seams));
+        assert_ne!(seam_fingerprint(&seams[..1]), seam_fingerprint(&seams));
+        let swapped = [seams[1].clone(), seams[0].clone()];
+        assert_ne!(seam_fingerprint(&swapped), seam_fingerprint(&seams));
+        assert_eq!(seam_fingerprint::<Seam>(&[]), 0x4d25_767f_9dce_13f5);
+    }
+
+    #[test]
+    fn applies_checked_seams() {
+        let mut matrix = Matrix::from_fn(Pos(3, 2), |x, y| (x + 3 * y) as u32);
+        assert_eq!(
+            apply_seam(&mut matrix, &[Pos(0, 1), Pos(3, 0)]),
+            Err(SeamError::OutOfBounds { x: 3, y: 0 })
+        );
+        let twice = [Pos(0, 0), Pos(1, 0)];
+        assert_eq!(apply_seam(&mut matrix, &twice), Err(SeamError::InvalidSeam { y: 0 }));
+        assert_eq!(matrix.width(), 3);
+        apply_seam(&mut matrix, &[Pos(2, 1), Pos(0, 0)]).unwrap();
+        let row = |y| [matrix[Pos(0, y)], matrix[Pos(1, y)]];
+        assert_eq!([row(0), row(1)], [[1, 2], [3, 4]]);
+    }
+}
@@ -1,29 +1,281 @@
-use std::iter::successors;
+use alloc::collections::BinaryHeap;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::cmp::Reverse;
+use core::iter::successors;
+use core::ops::Range;
 
+use crate::error::SeamError;
+use crate::kernels::best_of;
+use crate::mask::Rect;
+#[cfg(feature = "simd")]
+use crate::kernels::best_predecessors;
 use crate::matrix::Matrix;
 use crate::pos::Pos;
 
-#[derive(Debug)]
-pub(crate) struct SeamFinder {
+/// Energy of the positions that no seam may go through.
+/// The cumulative energy of seams that avoid them saturates just below this value.
+pub const FORBIDDEN: u32 = u32::MAX;
+
+/// Narrowest dirty band that [SeamFinder::par_fill] splits between threads
+#[cfg(feature = "rayon")]
+const PAR_FILL_MIN_COLUMNS: u32 = 256;
+
+/// Narrowest dirty band that [SeamFinder::fill] computes in panels, see [fill_blocked]
+const BLOCKED_FILL_MIN_COLUMNS: usize = 4096;
+
+/// Columns and rows of the panels of [fill_blocked]:
+/// the positions of a panel and the row above it take about 400 kB at most
+const BLOCK_SIZE: (usize, u32) = (1024, 32);
+
+/// Number of consecutive positions of a row computed by each rayon task
+#[cfg(feature = "rayon")]
+const PAR_FILL_CHUNK: usize = 128;
+
+/// Quantized finders divide energies by 2 to the power of this, so that they fit in 16 bits
+const QUANTIZATION_SHIFT: u32 = 3;
+
+/// Cumulative energy of the forbidden positions in a quantized finder,
+/// whose cells keep the predecessor in their two lowest bits
+const PACKED_FORBIDDEN: u32 = u32::MAX >> 2;
+
+/// Columns around a removed seam whose cached information is computed again,
+/// from the left of the seam: the ones that lost a predecessor or a neighbour,
+/// or whose energy changed. When the seam moves between rows, the columns it skipped are too.
+const REMOVED_NEIGHBOURS: Range<i64> = -2..2;
+
+/// Percentage of the table above which filling it again is faster than computing again
+/// the positions of an update one by one: both compute the energy of every position,
+/// but filling computes runs of positions, and never compares them with their previous values
+const REFILL_PERCENTAGE: usize = 75;
+
+/// Positions whose cached information must be computed again, row by row from the top
+#[derive(Clone, Debug, Default)]
+struct DirtyQueue {
+    // Positions queued in any order, as (y, x)
+    queued: BinaryHeap<Reverse<(u32, u32)>>,
+    // Sorted columns of the row `row` below positions whose cumulative energy changed
+    frontier: Vec<u32>,
+    row: u32,
+    // Memory for the frontier of the next row
+    spare: Vec<u32>,
+}
+
+impl DirtyQueue {
+    fn is_empty(&self) -> bool {
+        self.queued.is_empty() && self.frontier.is_empty()
+    }
+
+    fn clear(&mut self) {
+        self.queued.clear();
+        self.frontier.clear();
+    }
+
+    /// Number of positions each buffer can hold without allocating
+    fn capacity(&self) -> usize {
+        let frontiers = self.frontier.capacity().min(self.spare.capacity());
+        self.queued.capacity().min(frontiers)
+    }
+
+    fn reserve(&mut self, capacity: usize) {
+        self.queued.reserve(capacity);
+        self.frontier.reserve(capacity);
+        self.spare.reserve(capacity);
+    }
+
+    /// Most positions that can be computed again when updating the queued ones in an image
+    /// of the given size: the queued positions and the cone below them
+    fn cone_area(&self, size: Pos) -> usize {
+        let mut columns = self.queued.iter().map(|&Reverse((_y, x))| x);
+        let Some(first) = columns.next() else {
+            return 0;
+        };
+        let (start, end) = columns.fold((first, first), |(start, end), x| {
+            (start.min(x), end.max(x))
+        });
+        let top = self.queued.peek().map_or(size.1, |&Reverse((y, _x))| y);
+        let width = (end - start + 1) as usize;
+        (0..(size.1 - top) as usize)
+            .map(|row| (width + 2 * row).min(size.0 as usize))
+            .sum()
+    }
+
+    /// The first row with positions to compute again
+    fn next_row(&self) -> Option<u32> {
+        let queued = self.queued.peek().map(|&Reverse((y, _x))| y);
+        match self.frontier.is_empty() {
+            true => queued,
+            false => Some(queued.map_or(self.row, |y| y.min(self.row))),
+        }
+    }
+}
+
+#[inline(always)]
+pub(crate) fn cumulate(energy: u32, delta_e: u32) -> u32 {
+    energy.saturating_add(delta_e).min(FORBIDDEN - 1)
+}
+
+/// Finds the vertical seams with the lowest energy in an image, given the energy of its pixels.
+///
+/// The energies of the seams are cached: after a seam was extracted, only the positions
+/// around it are computed again, and then the positions below them
+/// whose cumulative energy actually changed, row by row. When the previous seam changed most
+/// of the table, the whole table is filled again instead, which is faster.
+/// The energy function receives positions in the image with all the extracted seams removed,
+/// which is the same as the image of [Carved](crate::Carved) when the `std` feature is enabled.
+///
+/// ```
+/// use seamcarving::{Pos, SeamFinder};
+/// // The energy of a pixel is its distance to the middle column
+/// let mut finder = SeamFinder::new(Pos(5, 3));
+/// let (seam, energy) = finder.extract_seam_with_energy(|Pos(x, _y)| (x as i32 - 2).unsigned_abs()).unwrap();
+/// assert_eq!(seam, vec![Pos(2, 2), Pos(2, 1), Pos(2, 0)]);
+/// assert_eq!(energy, 0);
+/// ```
+#[derive(Clone, Debug)]
+pub struct SeamFinder {
     size: Pos,
 
     // The dependencies and energies
-    contents: Matrix<Option<SeamElem>>,
+    contents: Contents,
 
-    // Vector used during invalid position clearing
-    to_clear: Vec<Pos>,
+    // Positions to compute again, when the rest of the table is cached
+    dirty: DirtyQueue,
 
     // min and max x values that will have to be recomputed
     dirty_bounds: DirtyBounds,
+
+    // Estimation of the number of positions the next update computes again,
+    // from the last one, to choose between updating the table and filling it again
+    updated: usize,
+
+    // Whether seams can cross the left and right borders
+    wrap: bool,
+
+    // Whether the memory was allocated once and for all by with_capacity
+    fixed_capacity: bool,
+
+    // Whether runs of positions are computed with vector instructions, when available
+    vectorized: bool,
+}
+
+/// The table of a seam finder, in full precision or quantized
+#[derive(Clone, Debug)]
+enum Contents {
+    Full(Matrix<Option<SeamElem>>),
+    Quantized(Matrix<Packed>),
+}
+
+/// Runs the same code on the cells of the table, whatever their type
+macro_rules! with_cells {
+    ($contents:expr, |$cells:ident| $body:expr) => {
+        match $contents {
+            Contents::Full($cells) => $body,
+            Contents::Quantized($cells) => $body,
+        }
+    };
+}
+
+/// How the cached information about a position is stored in the table
+trait Cell: Clone + Send + Sync {
+    /// A position that was not computed yet
+    const EMPTY: Self;
+    fn get(&self) -> Option<SeamElem>;
+    fn from_elem(elem: SeamElem) -> Self;
+    fn is_filled(&self) -> bool;
+    /// The cumulative energy, which is [FORBIDDEN] for empty cells
+    fn energy(&self) -> u32;
+}
+
+impl Cell for Option<SeamElem> {
+    const EMPTY: Self = None;
+    #[inline(always)]
+    fn get(&self) -> Option<SeamElem> {
+        *self
+    }
+    #[inline(always)]
+    fn from_elem(elem: SeamElem) -> Self {
+        Some(elem)
+    }
+    #[inline(always)]
+    fn is_filled(&self) -> bool {
+        self.is_some()
+    }
+    #[inline(always)]
+    fn energy(&self) -> u32 {
+        self.map_or(FORBIDDEN, |e| e.energy)
+    }
+}
+
+/// A cumulative energy of 30 bits, and a predecessor in the two lowest bits:
+/// 0, 1 and 2 for the left, middle and right ones, 3 for an empty cell
+#[derive(Clone, Copy, Debug)]
+struct Packed(u32);
+
+impl Cell for Packed {
+    const EMPTY: Self = Packed(u32::MAX);
+    #[inline(always)]
+    fn get(&self) -> Option<SeamElem> {
+        let predecessor = self.0 & 3;
+        if predecessor == 3 {
+            return None;
+        }
+        let energy = match self.0 >> 2 {
+            PACKED_FORBIDDEN => FORBIDDEN,
+            energy => energy,
+        };
+        Some(SeamElem {
+            predecessor_dx: predecessor as i8 - 1,
+            energy,
+        })
+    }
+    #[inline(always)]
+    fn from_elem(elem: SeamElem) -> Self {
+        let energy = match elem.energy {
+            FORBIDDEN => PACKED_FORBIDDEN,
+            energy => energy.min(PACKED_FORBIDDEN - 1),
+        };
+        Packed(energy << 2 | (elem.predecessor_dx + 1) as u32)
+    }
+    #[inline(always)]
+    fn is_filled(&self) -> bool {
+        self.0 & 3 != 3
+    }
+    #[inline(always)]
+    fn energy(&self) -> u32 {
+        // Empty cells have the energy bits of forbidden ones
+        match self.0 >> 2 {
+            PACKED_FORBIDDEN => FORBIDDEN,
+            energy => energy,
+        }
+    }
+}
+
+/// Energy of a pixel in a quantized finder
+#[inline(always)]
+fn quantize(energy: u32) -> u32 {
+    match energy {
+        FORBIDDEN => FORBIDDEN,
+        energy => (energy.saturating_add(1 << (QUANTIZATION_SHIFT - 1)) >> QUANTIZATION_SHIFT)
+            .min(u32::from(u16::MAX)),
+    }
+}
+
+/// Cumulative energy of a quantized finder, in the unit of the energies it was given
+fn dequantize(energy: u32) -> u32 {
+    match energy {
+        FORBIDDEN => FORBIDDEN,
+        energy => (u64::from(energy) << QUANTIZATION_SHIFT).min(u64::from(FORBIDDEN - 1)) as u32,
+    }
 }
 
-#[derive(Debug)]
+#[derive(Clone, Copy, Debug)]
 struct SeamElem {
     predecessor_dx: i8,
     energy: u32,
 }
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 struct DirtyBounds(u32, u32);
 
 impl DirtyBounds {
@@ -33,15 +285,6 @@ impl DirtyBounds {
     fn dirty(size: Pos) -> Self {
         DirtyBounds(0, size.0)
     }
-    #[inline(always)]
-    fn update(&mut self, Pos(x, _): Pos) {
-        if x < self.0 {
-            self.0 = x
-        }
-        if x >= self.1 {
-            self.1 = x + 1
-        }
-    }
 }
 
 impl SeamElem {
@@ -50,106 +293,1115 @@ impl SeamElem {
         SeamElem { predecessor_dx: 0, energy }
     }
 
+    /// The cheapest seam ending at column `x`, whose own energy is `delta_e`,
+    /// given the row above it, which is empty for the top row.
+    ///
+    /// The three predecessors are compared with selects rather than branches,
+    /// the leftmost one winning ties.
     #[inline(always)]
-    fn set_dx(&mut self, current: Pos, predecessor: Pos) {
-        self.predecessor_dx = predecessor.0.wrapping_sub(current.0) as i8
+    fn best<C: Cell>(x: usize, delta_e: u32, above: &[C], wrap: bool) -> Self {
+        if delta_e == FORBIDDEN {
+            // No seam can go through this position
+            return SeamElem::new(FORBIDDEN);
+        }
+        let width = above.len();
+        if width == 0 {
+            // We are on the top row
+            return SeamElem::new(delta_e.min(FORBIDDEN - 1));
+        }
+        // In images narrower than three columns, wrapping adds no neighbour
+        let wrap = wrap && width > 2;
+        let has_left = x > 0 || wrap;
+        let has_right = x + 1 < width || wrap;
+        let left = if x > 0 { x - 1 } else { width - 1 };
+        let right = if x + 1 < width { x + 1 } else { 0 };
+        let energy_at = |i: usize, exists: bool| {
+            let e = above[i].energy();
+            if exists {
+                e
+            } else {
+                FORBIDDEN
+            }
+        };
+        let (left, middle) = (energy_at(left, has_left), above[x].energy());
+        let right = energy_at(right, has_right);
+        let (energy, predecessor_dx) = best_of(left, middle, right, delta_e);
+        SeamElem {
+            predecessor_dx,
+            energy,
+        }
+    }
+
+    #[inline(always)]
+    fn set_dx(&mut self, current: Pos, predecessor: Pos, width: u32) {
+        let dx = i64::from(predecessor.0) - i64::from(current.0);
+        // A predecessor across the border of a wrapping image is still a direct neighbour
+        self.predecessor_dx = if dx > 1 {
+            dx - i64::from(width)
+        } else if dx < -1 {
+            dx + i64::from(width)
+        } else {
+            dx
+        } as i8
     }
 
     #[inline(always)]
-    fn predecessor(&self, pos: Pos) -> Pos {
-        Pos(
-            pos.0.wrapping_add(self.predecessor_dx as u32),
-            pos.1 - 1,
-        )
+    fn predecessor(&self, pos: Pos, width: u32) -> Pos {
+        let (x, width) = (i64::from(pos.0) + i64::from(self.predecessor_dx), i64::from(width));
+        // Predecessors across the border of a wrapping image
+        let x = if x < 0 {
+            x + width
+        } else if x >= width {
+            x - width
+        } else {
+            x
+        };
+        Pos(x as u32, pos.1 - 1)
     }
 }
 
 impl SeamFinder {
+    /// A seam finder for an image of the given width and height
     pub fn new(size: Pos) -> Self {
-        let contents: Matrix<Option<SeamElem>> = Matrix::from_fn(size, |_, _| None);
-        let to_clear = Vec::with_capacity(size.1 as usize);
+        let contents = Contents::Full(Matrix::from_fn(size, |_, _| None));
+        let dirty = DirtyQueue::default();
         let dirty_bounds = DirtyBounds::dirty(size);
         SeamFinder {
             size,
             contents,
-            to_clear,
+            dirty,
             dirty_bounds,
+            updated: 0,
+            wrap: false,
+            fixed_capacity: false,
+            vectorized: true,
         }
     }
 
-    pub fn extract_seam<F: FnMut(Pos) -> u32>(&mut self, energy: F) -> Vec<Pos> {
-        self.fill(energy);
-        let mut seam = Vec::with_capacity(self.size.1 as usize);
-        // Find the bottom pixel with the lowest energy
-        let bottom_y: Option<u32> = self.size.1.checked_sub(1);
-        let init = (0..self.size.0)
-            .flat_map(|x| bottom_y.map(|y| Pos(x, y)))
-            .min_by_key(|&p|
-                self.contents[p].as_ref().expect("should have been filled").energy);
-        seam.extend(successors(init, |&pos| {
-            let next = if pos.1 == 0 {
-                None
+    /// A seam finder for an image of up to `max_size` pixels, that allocates all the memory
+    /// it needs upfront, and never allocates afterwards.
+    ///
+    /// It starts ready for an image of `max_size`, [reset](SeamFinder::reset) prepares it
+    /// for another image, and fails if the image does not fit.
+    /// Seams should be extracted with [extract_seam_into](SeamFinder::extract_seam_into)
+    /// to reuse their memory too.
+    ///
+    /// ```
+    /// use seamcarving::{Pos, SeamError, SeamFinder};
+    /// let mut finder = SeamFinder::with_capacity(Pos(64, 32));
+    /// let mut seam = Vec::with_capacity(32);
+    /// finder.reset(Pos(40, 30)).unwrap();
+    /// assert_eq!(finder.extract_seam_into(|Pos(x, _y)| x, &mut seam), Ok(0));
+    /// assert_eq!(seam.len(), 30);
+    /// assert_eq!(finder.reset(Pos(100, 30)), Err(SeamError::CapacityExceeded));
+    /// ```
+    pub fn with_capacity(max_size: Pos) -> Self {
+        let mut finder = SeamFinder::new(max_size);
+        finder.dirty.reserve(queue_capacity(max_size));
+        finder.fixed_capacity = true;
+        finder
+    }
+
+    /// Forgets everything about the current image, to find seams in an image of the given size.
+    ///
+    /// The memory of the finder is reused. If it was created
+    /// [with_capacity](SeamFinder::with_capacity) and the image is too large for it,
+    /// [SeamError::CapacityExceeded] is returned instead of allocating more memory.
+    pub fn reset(&mut self, size: Pos) -> Result<(), SeamError> {
+        let too_large = size.checked_area().is_none_or(|area| area > self.capacity())
+            || queue_capacity(size) > self.dirty.capacity();
+        if self.fixed_capacity && too_large {
+            return Err(SeamError::CapacityExceeded);
+        }
+        self.size = size;
+        self.invalidate();
+        self.updated = 0;
+        Ok(())
+    }
+
+    /// Number of positions the finder can hold without allocating
+    fn capacity(&self) -> usize {
+        with_cells!(&self.contents, |cells| cells.capacity())
+    }
+
+    /// Number of bytes used by the seam finder for every position of the image,
+    /// see [set_quantized](SeamFinder::set_quantized)
+    pub(crate) fn memory_per_position(quantized: bool) -> usize {
+        if quantized {
+            core::mem::size_of::<Packed>()
+        } else {
+            core::mem::size_of::<Option<SeamElem>>()
+        }
+    }
+
+    /// Stores the energies of the seams in a third of the memory,
+    /// at the cost of precision: the energy of each position is rounded
+    /// to a multiple of 8, and at most 65535 times 8, so seams with close energies may be
+    /// found in a different order. The energies the finder returns are in the same unit
+    /// as the ones it is given.
+    /// This invalidates all the cached information.
+    pub fn set_quantized(&mut self, quantized: bool) {
+        if self.is_quantized() != quantized {
+            // The memory of the other table is reserved upfront too
+            let capacity = self.capacity();
+            self.contents = if quantized {
+                Contents::Quantized(empty_matrix(capacity))
             } else {
-                Some(self.contents[pos]
-                    .as_ref()
-                    .expect("should be filled")
-                    .predecessor(pos))
+                Contents::Full(empty_matrix(capacity))
             };
-            self.clear(pos);
-            next
-        }));
+        }
+        self.invalidate();
+    }
+
+    fn is_quantized(&self) -> bool {
+        matches!(self.contents, Contents::Quantized(_))
+    }
+
+    /// Allows seams to cross the left and right borders of the image,
+    /// going from x=0 to x=width-1 or the other way around.
+    /// This invalidates all the cached information.
+    pub fn set_wrapping(&mut self, wrap: bool) {
+        self.wrap = wrap;
+        self.invalidate();
+    }
+
+    /// Computes runs of positions with AVX-512 or NEON instructions when the processor
+    /// supports them, which is the default, or one position at a time.
+    /// The cached information is exactly the same either way.
+    #[cfg(feature = "simd")]
+    pub fn set_vectorized(&mut self, vectorized: bool) {
+        self.vectorized = vectorized;
+    }
+
+    /// Forgets all cached information, for instance after the energy function has changed
+    pub fn invalidate(&mut self) {
+        with_cells!(&mut self.contents, |cells| cells.reset(self.size, Cell::EMPTY));
+        self.dirty_bounds = DirtyBounds::dirty(self.size);
+        self.dirty.clear();
+    }
+
+    /// Extracts the seam with the lowest energy.
+    /// Positions with a [FORBIDDEN] energy are avoided,
+    /// and None is returned if every seam has to go through one of them.
+    /// The seam is returned from the bottom to the top of the image.
+    pub fn extract_seam<F: FnMut(Pos) -> u32>(&mut self, energy: F) -> Option<Vec<Pos>> {
+        self.extract_seam_with_energy(energy).ok().map(|(seam, _energy)| seam)
+    }
+
+    /// Extracts the seam with the lowest energy like [extract_seam](SeamFinder::extract_seam),
+    /// and returns its total energy along with it.
+    /// Fails with [SeamError::Empty] if there is no column left,
+    /// and with [SeamError::Blocked] if every seam goes through a forbidden position.
+    pub fn extract_seam_with_energy<F: FnMut(Pos) -> u32>(
+        &mut self,
+        energy: F,
+    ) -> Result<(Vec<Pos>, u32), SeamError> {
+        let mut seam = Vec::with_capacity(self.size.1 as usize);
+        let total = self.extract_seam_into(energy, &mut seam)?;
+        Ok((seam, total))
+    }
+
+    /// Extracts the seam with the lowest energy
+    /// like [extract_seam_with_energy](SeamFinder::extract_seam_with_energy),
+    /// but stores its positions in `seam`, replacing its contents, and only returns its energy.
+    ///
+    /// This does not allocate when `seam` can already hold one position per row.
+    pub fn extract_seam_into<F: FnMut(Pos) -> u32>(
+        &mut self,
+        energy: F,
+        seam: &mut Vec<Pos>,
+    ) -> Result<u32, SeamError> {
+        self.extract_seam_profiled(energy, seam, None)
+    }
+
+    /// Extracts the seam with the lowest energy
+    /// like [extract_seam_with_energy](SeamFinder::extract_seam_with_energy),
+    /// and returns the energy it gained on each of its rows along with it,
+    /// in the same order as its positions, from the bottom to the top of the image.
+    ///
+    /// The energies of the rows add up to the total energy of the seam, so they show
+    /// where along its length a seam went through expensive content.
+    /// Each one is the cumulative energy of the seam on its row minus the one on the row above,
+    /// which is the energy of the position unless the table is
+    /// [quantized](SeamFinder::set_quantized), or the cumulative energies saturate.
+    ///
+    /// ```
+    /// use seamcarving::{Pos, SeamFinder};
+    /// // Only the bottom row is expensive
+    /// let mut finder = SeamFinder::new(Pos(3, 3));
+    /// let (seam, profile) = finder.extract_seam_with_profile(|Pos(x, y)| x + y * 10).unwrap();
+    /// assert_eq!(seam, vec![Pos(0, 2), Pos(0, 1), Pos(0, 0)]);
+    /// assert_eq!(profile, vec![20, 10, 0]);
+    /// ```
+    pub fn extract_seam_with_profile<F: FnMut(Pos) -> u32>(
+        &mut self,
+        energy: F,
+    ) -> Result<(Vec<Pos>, Vec<u32>), SeamError> {
+        let mut seam = Vec::with_capacity(self.size.1 as usize);
+        let mut profile = Vec::with_capacity(self.size.1 as usize);
+        self.extract_seam_profiled(energy, &mut seam, Some(&mut profile))?;
+        Ok((seam, profile))
+    }
+
+    /// Extracts the seam with the lowest energy into `seam`,
+    /// and the energy of each of its rows into `profile` if it is given
+    fn extract_seam_profiled<F: FnMut(Pos) -> u32>(
+        &mut self,
+        energy: F,
+        seam: &mut Vec<Pos>,
+        mut profile: Option<&mut Vec<u32>>,
+    ) -> Result<u32, SeamError> {
+        span!(TRACE, "extract_seam", width = self.size.0, height = self.size.1);
+        seam.clear();
+        if let Some(profile) = profile.as_mut() {
+            profile.clear();
+        }
+        if self.size.0 == 0 {
+            return Err(SeamError::Empty);
+        }
+        self.fill(energy);
+        let Some(bottom_y) = self.size.1.checked_sub(1) else {
+            // An image without rows has a seam without positions
+            self.size.0 -= 1;
+            return Ok(0);
+        };
+        // Find the bottom pixel with the lowest energy, the leftmost one in case of a tie
+        let mut bottom: Option<(Pos, u32)> = None;
+        for x in 0..self.size.0 {
+            let pos = Pos(x, bottom_y);
+            let energy = self.filled(pos)?.energy;
+            if bottom.is_none_or(|(_, lowest)| energy < lowest) {
+                bottom = Some((pos, energy));
+            }
+        }
+        let (mut pos, total) = bottom.ok_or(SeamError::Empty)?;
+        if total == FORBIDDEN {
+            return Err(SeamError::Blocked);
+        }
+        loop {
+            seam.push(pos);
+            if let Some(profile) = profile.as_mut() {
+                profile.push(self.unit_energy(self.filled(pos)?.energy));
+            }
+            if pos.1 == 0 {
+                break;
+            }
+            pos = self.filled(pos)?.predecessor(pos, self.size.0);
+        }
+        if let Some(profile) = profile {
+            // From the cumulative energies of the rows to the energy each of them adds
+            for i in 1..profile.len() {
+                profile[i - 1] = profile[i - 1].saturating_sub(profile[i]);
+            }
+        }
+        self.remove(seam);
+        Ok(self.unit_energy(total))
+    }
+
+    /// The cached information about a position, that must have been computed by [fill]
+    #[inline(always)]
+    fn filled(&self, pos: Pos) -> Result<SeamElem, SeamError> {
+        self.cell(pos).ok_or(SeamError::Unfilled)
+    }
+
+    #[inline(always)]
+    fn cell(&self, pos: Pos) -> Option<SeamElem> {
+        with_cells!(&self.contents, |cells| cells[pos].get())
+    }
+
+    /// A cumulative energy from the table, in the unit of the energies the finder was given
+    fn unit_energy(&self, energy: u32) -> u32 {
+        if self.is_quantized() {
+            dequantize(energy)
+        } else {
+            energy
+        }
+    }
+
+    /// Width and height of the table
+    pub(crate) fn size(&self) -> Pos {
+        self.size
+    }
+
+    /// Removes the positions of an extracted seam, from the bottom to the top, from the table,
+    /// and queues the positions around it
+    pub(crate) fn remove(&mut self, seam: &[Pos]) {
         self.size.0 -= 1;
-        self.contents.remove_seam(&seam);
-        seam
+        with_cells!(&mut self.contents, |cells| cells.remove_seam(seam));
+        if !self.dirty.is_empty() {
+            // The queued positions are in the columns of the table before the removal
+            return self.invalidate();
+        }
+        if self.dirty_bounds.0 < self.dirty_bounds.1 || self.size.0 == 0 {
+            return;
+        }
+        let (width, wrap) = (i64::from(self.size.0), self.wrap);
+        let around = seam.iter().enumerate().flat_map(|(i, &Pos(_, y))| {
+            // The seam on this row and the rows below and above it,
+            // from which it may have crossed the borders
+            let rows = &seam[i.saturating_sub(1)..seam.len().min(i + 2)];
+            let columns = rows.iter().map(|pos| i64::from(pos.0));
+            let (start, end) = (columns.clone().min(), columns.max());
+            let start = start.unwrap_or_default() + REMOVED_NEIGHBOURS.start;
+            let end = end.unwrap_or_default() + REMOVED_NEIGHBOURS.end;
+            let columns = match wrap {
+                true => start..end.min(start + width),
+                false => start.max(0)..end.min(width),
+            };
+            columns.map(move |x| Pos(x.rem_euclid(width) as u32, y))
+        });
+        self.queue(around);
     }
 
-    fn fill<F: FnMut(Pos) -> u32>(&mut self, mut energy: F) {
-        let start = Pos(self.dirty_bounds.0, 0);
-        let end = Pos(self.dirty_bounds.1, self.size.1);
-        for pos in Pos::iter_in_rect(start, end) {
-            if self.contents[pos].is_some() {
+    /// Queues positions to compute again, or invalidates everything
+    /// when they do not fit in the memory of a finder created with a fixed capacity
+    fn queue(&mut self, positions: impl Iterator<Item = Pos>) {
+        let positions = positions.map(|Pos(x, y)| Reverse((y, x)));
+        let queued = &mut self.dirty.queued;
+        if !self.fixed_capacity {
+            return queued.extend(positions);
+        }
+        for position in positions {
+            if queued.len() == queued.capacity() {
+                return self.invalidate();
+            }
+            queued.push(position);
+        }
+    }
+
+    /// Extracts the seam with the lowest energy among the ones that go through all the anchors.
+    /// There can be at most one anchor per row.
+    /// Returns None if no seam goes through all the anchors.
+    /// Unlike the other methods, this allocates a table of the size of the image.
+    pub fn extract_seam_through<F: FnMut(Pos) -> u32>(
+        &mut self,
+        mut energy: F,
+        anchors: &[Pos],
+    ) -> Option<Vec<Pos>> {
+        // The cached table is not constrained by the anchors, so use a separate one
+        let mut anchor_x: Vec<Option<u32>> = vec![None; self.size.1 as usize];
+        for &Pos(x, y) in anchors {
+            match anchor_x[y as usize].replace(x) {
+                Some(other) if other != x => return None,
+                _ => {}
+            }
+        }
+        let mut table: Matrix<Option<SeamElem>> = Matrix::from_fn(self.size, |_, _| None);
+        for pos in Pos::iter_in_rect(Pos(0, 0), self.size) {
+            if anchor_x[pos.1 as usize].is_some_and(|x| x != pos.0) {
                 continue;
             }
             let delta_e = energy(pos);
-            let mut best_elem = SeamElem::new(std::u32::MAX);
-            for predecessor in pos.predecessors(self.size) {
-                if let Some(e) = &self.contents[predecessor] {
-                    let energy = e.energy + delta_e;
-                    if energy < best_elem.energy {
-                        best_elem.energy = energy;
-                        best_elem.set_dx(pos, predecessor);
+            if delta_e == FORBIDDEN {
+                continue;
+            }
+            if pos.1 == 0 {
+                table[pos] = Some(SeamElem::new(delta_e.min(FORBIDDEN - 1)));
+                continue;
+            }
+            let mut best_elem: Option<SeamElem> = None;
+            for predecessor in pos.predecessors_in(self.size, self.wrap) {
+                if let Some(e) = &table[predecessor] {
+                    let energy = cumulate(e.energy, delta_e);
+                    if best_elem.as_ref().is_none_or(|b| energy < b.energy) {
+                        let mut elem = SeamElem::new(energy);
+                        elem.set_dx(pos, predecessor, self.size.0);
+                        best_elem = Some(elem);
                     }
                 }
             }
-            if best_elem.energy == std::u32::MAX { // We are on the top row
-                best_elem.energy = delta_e;
+            table[pos] = best_elem;
+        }
+        let bottom_y = self.size.1.checked_sub(1)?;
+        let init = (0..self.size.0)
+            .map(|x| Pos(x, bottom_y))
+            .filter_map(|p| table[p].as_ref().map(|e| (p, e.energy)))
+            .min_by_key(|&(_, energy)| energy)
+            .map(|(p, _)| p)?;
+        let seam: Vec<Pos> = successors(Some(init), |&pos| {
+            if pos.1 == 0 {
+                None
+            } else {
+                table[pos].as_ref().map(|e| e.predecessor(pos, self.size.0))
+            }
+        })
+        .collect();
+        self.remove(&seam);
+        Some(seam)
+    }
+
+    /// Frees the memory used by the positions of the removed seams,
+    /// unless the finder was created [with_capacity](SeamFinder::with_capacity)
+    pub fn shrink_to_fit(&mut self) {
+        if !self.fixed_capacity {
+            with_cells!(&mut self.contents, |cells| cells.shrink_to_fit());
+        }
+    }
+
+    /// Computes the given positions again before the next seam is extracted,
+    /// for instance because their energy changed, and then the positions below them
+    /// whose cumulative energy changed. The cached information about the others is kept.
+    pub fn invalidate_positions(&mut self, positions: impl IntoIterator<Item = Pos>) {
+        if self.dirty_bounds.0 >= self.dirty_bounds.1 {
+            self.queue(positions.into_iter());
+        }
+    }
+
+    /// Computes the energies of all the positions that are not cached yet
+    pub fn fill<F: FnMut(Pos) -> u32>(&mut self, mut energy: F) {
+        self.choose_refill();
+        if self.dirty_bounds.0 >= self.dirty_bounds.1 {
+            self.update(&mut energy);
+        }
+        #[cfg(any(debug_assertions, feature = "strict"))]
+        let fresh = self.is_empty();
+        if self.dirty_bounds.0 < self.dirty_bounds.1 {
+            let start = Pos(self.dirty_bounds.0, 0);
+            let end = Pos(self.dirty_bounds.1, self.size.1);
+            span!(TRACE, "fill", columns = end.0 - start.0, height = self.size.1);
+            let (size, wrap, vectorized) = (self.size, self.wrap, self.vectorized);
+            match &mut self.contents {
+                Contents::Full(cells) => {
+                    fill_cells(cells, start, end, size, wrap, vectorized, &mut energy)
+                }
+                Contents::Quantized(cells) => {
+                    let energy = |p| quantize(energy(p));
+                    fill_cells(cells, start, end, size, wrap, vectorized, energy)
+                }
+            }
+            self.dirty_bounds = DirtyBounds::clean(self.size);
+        }
+        #[cfg(any(debug_assertions, feature = "strict"))]
+        self.check_table(Some(energy).filter(|_| fresh));
+    }
+
+    /// Whether every column of the table has to be computed again, as after
+    /// [invalidate](SeamFinder::invalidate)
+    #[cfg(feature = "gpu")]
+    pub(crate) fn is_unfilled(&self) -> bool {
+        self.dirty_bounds.0 == 0 && self.dirty_bounds.1 >= self.size.0
+    }
+
+    /// Whether no position of the table is computed, as after [invalidate](SeamFinder::invalidate)
+    #[cfg(any(debug_assertions, feature = "strict"))]
+    fn is_empty(&self) -> bool {
+        // Tables are only ever emptied from a row to the bottom
+        let first = Pos(0, 0);
+        self.size.area() == 0 || with_cells!(&self.contents, |cells| !cells[first].is_filled())
+    }
+
+    /// Panics unless every position of the table continues the cheapest seam above it,
+    /// in debug builds and with the `strict` feature, to catch the regressions
+    /// of the many ways to fill and update the table.
+    ///
+    /// When the whole table was just computed with the given energy function,
+    /// the cumulative energy of every position must also be the one of its predecessor
+    /// plus its own energy. Otherwise the cached positions may have been computed
+    /// with the energies of a previous image, so only their links are checked.
+    #[cfg(any(debug_assertions, feature = "strict"))]
+    fn check_table(&self, energy: Option<impl FnMut(Pos) -> u32>) {
+        let (size, wrap) = (self.size, self.wrap);
+        with_cells!(&self.contents, |cells| check_links(cells, size, wrap));
+        let Some(mut energy) = energy else { return };
+        match &self.contents {
+            Contents::Full(cells) => check_energies(cells, size, wrap, energy),
+            Contents::Quantized(cells) => {
+                check_energies(cells, size, wrap, |p| quantize(energy(p)))
+            }
+        }
+    }
+
+    /// Fills the whole table again instead of updating the queued positions
+    /// when it is estimated to be faster: when the last update computed again most
+    /// of the table, and the positions below the queued ones cover most of it.
+    /// The estimation from the last update is halved every time the table is filled again,
+    /// so that updates are tried again after a few seams.
+    fn choose_refill(&mut self) {
+        if self.dirty.is_empty() || self.dirty_bounds.0 < self.dirty_bounds.1 {
+            return;
+        }
+        let estimate = self.updated.min(self.dirty.cone_area(self.size));
+        if estimate.saturating_mul(100) > self.size.area().saturating_mul(REFILL_PERCENTAGE) {
+            self.invalidate();
+            self.updated = self.updated.min(self.size.area()) / 2;
+        }
+    }
+
+    /// Computes the queued positions again, and the positions below them
+    /// whose cumulative energy changed
+    fn update<F: FnMut(Pos) -> u32>(&mut self, energy: &mut F) {
+        if self.dirty.is_empty() {
+            return;
+        }
+        span!(TRACE, "update", queued = self.dirty.queued.len());
+        self.updated = 0;
+        while let Some(y) = self.dirty.next_row() {
+            self.update_row(y, &mut *energy);
+        }
+    }
+
+    /// Computes the queued positions of row `y` again, like [update_row],
+    /// and counts them
+    fn update_row(&mut self, y: u32, mut energy: impl FnMut(Pos) -> u32) {
+        let (size, wrap, dirty) = (self.size, self.wrap, &mut self.dirty);
+        let max_queued = match self.fixed_capacity {
+            true => dirty.queued.capacity(),
+            false => usize::MAX,
+        };
+        let updated = match &mut self.contents {
+            Contents::Full(cells) => {
+                update_row(cells, dirty, max_queued, y, size, wrap, &mut energy)
+            }
+            Contents::Quantized(cells) => {
+                let mut energy = |p| quantize(energy(p));
+                update_row(cells, dirty, max_queued, y, size, wrap, &mut energy)
+            }
+        };
+        match updated {
+            Some(count) => self.updated = self.updated.saturating_add(count),
+            None => {
+                // This row and the ones below were emptied
+                self.dirty.clear();
+                self.dirty_bounds = DirtyBounds::dirty(self.size);
+                self.updated = usize::MAX;
+            }
+        }
+    }
+
+    /// Computes the energies of all the positions that are not cached yet like [fill](SeamFinder::fill),
+    /// splitting each row between the threads of the current rayon thread pool.
+    ///
+    /// The cached information is exactly the same as with [fill](SeamFinder::fill),
+    /// whatever the number of threads: a row is only split once the row above it is complete,
+    /// and every position is computed from it in the same way, ties included.
+    /// Only wide images benefit from it: narrow rows are computed on the calling thread.
+    #[cfg(feature = "rayon")]
+    pub fn par_fill<F: Fn(Pos) -> u32 + Sync>(&mut self, energy: F) {
+        self.choose_refill();
+        // The bounds may include the column of the last extracted seam
+        let DirtyBounds(start, end) = self.dirty_bounds;
+        let end = end.min(self.size.0);
+        if end < start + PAR_FILL_MIN_COLUMNS {
+            return self.fill(energy);
+        }
+        #[cfg(any(debug_assertions, feature = "strict"))]
+        let fresh = self.is_empty();
+        span!(TRACE, "par_fill", columns = end - start, height = self.size.1);
+        let (size, wrap) = (self.size, self.wrap);
+        let columns = (start as usize, end as usize);
+        match &mut self.contents {
+            Contents::Full(cells) => par_fill_cells(cells, columns, size, wrap, &energy),
+            Contents::Quantized(cells) => {
+                par_fill_cells(cells, columns, size, wrap, |p| quantize(energy(p)))
             }
-            self.contents[pos] = Some(best_elem);
         }
         self.dirty_bounds = DirtyBounds::clean(self.size);
+        #[cfg(any(debug_assertions, feature = "strict"))]
+        self.check_table(Some(energy).filter(|_| fresh));
     }
 
-    /// Recursively invalidates all cached information about a position
-    fn clear(&mut self, p: Pos) {
-        self.to_clear.push(p);
-        while let Some(pos) = self.to_clear.pop() {
-            self.contents[pos] = None;
-            self.dirty_bounds.update(pos);
-            for s in pos.successors(self.size) {
-                if let Some(e) = &self.contents[s] {
-                    if e.predecessor(s) == pos {
-                        self.to_clear.push(s)
-                    }
+    /// Computes the positions that are not cached yet like [fill](SeamFinder::fill),
+    /// from energies computed beforehand, one row at a time from the top.
+    /// Each row holds the energy of every column of the image with the extracted seams removed.
+    ///
+    /// Rows are used as soon as they are produced, so they can be computed elsewhere,
+    /// for instance on another thread or on a graphics card, and received through a channel
+    /// while the rows above them are being cumulated.
+    /// Once the table is complete, the energy functions of the other methods are not called.
+    ///
+    /// Returns [SeamError::SizeMismatch] if there are fewer rows than the height of the image,
+    /// or if a row does not have one energy per column. The rows before it are kept.
+    pub fn fill_rows<R: AsRef<[u32]>>(
+        &mut self,
+        rows: impl IntoIterator<Item = R>,
+    ) -> Result<(), SeamError> {
+        if self.dirty_bounds.0 >= self.dirty_bounds.1 && self.dirty.is_empty() {
+            // Everything is already cached
+            return Ok(());
+        }
+        self.choose_refill();
+        if self.dirty_bounds.0 >= self.dirty_bounds.1 {
+            self.updated = 0;
+        }
+        span!(TRACE, "fill_rows", height = self.size.1);
+        let (size, wrap, vectorized) = (self.size, self.wrap, self.vectorized);
+        let mut rows = rows.into_iter();
+        for y in 0..size.1 {
+            let row = rows.next().ok_or(SeamError::SizeMismatch)?;
+            let row = row.as_ref();
+            if row.len() != size.0 as usize {
+                return Err(SeamError::SizeMismatch);
+            }
+            let mut energy = |p: Pos| row[p.0 as usize];
+            if self.dirty_bounds.0 >= self.dirty_bounds.1 {
+                self.update_row(y, &mut energy);
+            }
+            if self.dirty_bounds.0 >= self.dirty_bounds.1 {
+                continue;
+            }
+            let columns = self.dirty_bounds.0 as usize..(self.dirty_bounds.1.min(size.0) as usize);
+            match &mut self.contents {
+                Contents::Full(cells) => {
+                    fill_row(cells, y, columns, wrap, vectorized, &mut energy)
+                }
+                Contents::Quantized(cells) => {
+                    let mut energy = |p| quantize(energy(p));
+                    fill_row(cells, y, columns, wrap, vectorized, &mut energy)
+                }
+            }
+        }
+        self.dirty_bounds = DirtyBounds::clean(self.size);
+        #[cfg(any(debug_assertions, feature = "strict"))]
+        self.check_table(None::<fn(Pos) -> u32>);
+        Ok(())
+    }
+}
+
+/// Length of the queue of a finder created with a fixed capacity for an image of the given size:
+/// enough for the positions around a seam on every row, and for the positions of a row.
+/// Finders invalidate everything when their queue is full.
+fn queue_capacity(size: Pos) -> usize {
+    REMOVED_NEIGHBOURS.count() * size.1 as usize + size.0 as usize + 2
+}
+
+/// Computes the queued positions of row `y` again, and queues the positions below them
+/// whose cumulative energy changed, while there are fewer than `max_queued` positions
+/// in any order. The successors of a row are in order, except across the borders.
+///
+/// Returns the number of positions computed again, or None if the queue was full,
+/// after emptying this row and the rows below, which must then be filled again.
+fn update_row<C: Cell>(
+    cells: &mut Matrix<C>,
+    dirty: &mut DirtyQueue,
+    max_queued: usize,
+    y: u32,
+    size: Pos,
+    wrap: bool,
+    energy: &mut impl FnMut(Pos) -> u32,
+) -> Option<usize> {
+    let mut current = core::mem::take(&mut dirty.frontier);
+    if dirty.row != y {
+        current.clear();
+    }
+    let mut below = core::mem::take(&mut dirty.spare);
+    below.clear();
+    let mut frontier = current.iter().copied().peekable();
+    let mut previous = None;
+    let mut count = 0;
+    loop {
+        let queued = dirty.queued.peek().filter(|&&Reverse((row_y, _))| row_y == y);
+        let queued = queued.map(|&Reverse((_, x))| x);
+        let x = match (queued, frontier.peek().copied()) {
+            (_, Some(x)) if queued.is_none_or(|queued| x < queued) => {
+                frontier.next();
+                x
+            }
+            (Some(x), _) => {
+                dirty.queued.pop();
+                x
+            }
+            (None, _) => break,
+        };
+        if previous.replace(x) == Some(x) {
+            continue;
+        }
+        count += 1;
+        let (above, row) = cells.row_and_above_mut(y);
+        let cell = C::from_elem(SeamElem::best(x as usize, energy(Pos(x, y)), above, wrap));
+        let changed = !row[x as usize].is_filled() || row[x as usize].energy() != cell.energy();
+        row[x as usize] = cell;
+        if !changed {
+            continue;
+        }
+        for Pos(successor, _) in Pos(x, y).successors_in(size, wrap) {
+            // Across the borders, successors are not in order
+            if successor.abs_diff(x) <= 1 {
+                if below.last().is_none_or(|&last| successor > last) {
+                    below.push(successor);
+                }
+                continue;
+            }
+            if dirty.queued.len() >= max_queued {
+                let rows = Rect {
+                    x: 0,
+                    y,
+                    width: size.0,
+                    height: size.1 - y,
+                };
+                cells.view_mut(rows).fill(C::EMPTY);
+                (dirty.frontier, dirty.spare) = (current, below);
+                return None;
+            }
+            dirty.queued.push(Reverse((y + 1, successor)));
+        }
+    }
+    current.clear();
+    (dirty.frontier, dirty.spare, dirty.row) = (below, current, y + 1);
+    Some(count)
+}
+
+/// A table of the given type, without any position yet,
+/// that can hold `capacity` positions without allocating
+fn empty_matrix<C>(capacity: usize) -> Matrix<C> {
+    Matrix::from_vec(Pos(0, 0), Vec::with_capacity(capacity)).expect("the matrix is empty")
+}
+
+/// Computes the positions of the table between `start` and `end` that are not cached yet
+#[inline(always)]
+fn fill_cells<C: Cell>(
+    cells: &mut Matrix<C>,
+    start: Pos,
+    end: Pos,
+    size: Pos,
+    wrap: bool,
+    vectorized: bool,
+    mut energy: impl FnMut(Pos) -> u32,
+) {
+    // The dirty bounds may include the column of the last extracted seam
+    let columns = start.0 as usize..(end.0.min(size.0) as usize);
+    let rows = start.1..end.1;
+    // Panels leaning to the left cannot wrap around the borders
+    if columns.len() >= BLOCKED_FILL_MIN_COLUMNS && !wrap {
+        return fill_blocked(cells, columns, rows, BLOCK_SIZE, vectorized, energy);
+    }
+    // Positions only depend on the row above them
+    for y in rows {
+        fill_row(cells, y, columns.clone(), wrap, vectorized, &mut energy);
+    }
+}
+
+/// Computes the positions of a part of row `y` that are not cached yet
+#[inline(always)]
+#[cfg_attr(not(feature = "simd"), allow(unused_variables))]
+fn fill_row<C: Cell>(
+    cells: &mut Matrix<C>,
+    y: u32,
+    columns: Range<usize>,
+    wrap: bool,
+    vectorized: bool,
+    energy: &mut impl FnMut(Pos) -> u32,
+) {
+    let (above, row) = cells.row_and_above_mut(y);
+    #[cfg(feature = "simd")]
+    {
+        if vectorized && !above.is_empty() {
+            return fill_row_vectorized(above, row, y, columns, wrap, energy);
+        }
+    }
+    for x in columns {
+        fill_position(above, row, Pos(x as u32, y), wrap, energy);
+    }
+}
+
+/// Computes a position of a row if it is not cached yet
+#[inline(always)]
+fn fill_position<C: Cell>(
+    above: &[C],
+    row: &mut [C],
+    Pos(x, y): Pos,
+    wrap: bool,
+    energy: &mut impl FnMut(Pos) -> u32,
+) {
+    let x = x as usize;
+    if row[x].is_filled() {
+        return;
+    }
+    let delta_e = {
+        span!(TRACE, "energy");
+        energy(Pos(x as u32, y))
+    };
+    row[x] = C::from_elem(SeamElem::best(x, delta_e, above, wrap));
+}
+
+/// The cumulative energy of the positions above `pos`, from left to right,
+/// or [FORBIDDEN] when they are outside of the table, see [SeamElem::best]
+#[cfg(any(debug_assertions, feature = "strict"))]
+fn energies_above<C: Cell>(cells: &Matrix<C>, pos: Pos, size: Pos, wrap: bool) -> [u32; 3] {
+    // In images narrower than three columns, wrapping adds no neighbour
+    let wrap = wrap && size.0 > 2;
+    let energy = |dx: i64| {
+        let x = i64::from(pos.0) + dx;
+        let x = if wrap { x.rem_euclid(i64::from(size.0)) } else { x };
+        match (0..i64::from(size.0)).contains(&x) {
+            true => cells[Pos(x as u32, pos.1 - 1)].energy(),
+            false => FORBIDDEN,
+        }
+    };
+    [energy(-1), energy(0), energy(1)]
+}
+
+/// Checks that every position of a table is filled, and continues the cheapest seam
+/// of the positions above it, the leftmost one winning ties
+#[cfg(any(debug_assertions, feature = "strict"))]
+fn check_links<C: Cell>(cells: &Matrix<C>, size: Pos, wrap: bool) {
+    // Above this energy, seams of different energies are stored with the same one
+    let saturated = C::from_elem(SeamElem::new(FORBIDDEN - 1)).energy();
+    for pos in Pos::iter_in_rect(Pos(0, 0), size) {
+        let cell = cells[pos].get();
+        let cell = cell.unwrap_or_else(|| panic!("{:?} is not filled", pos));
+        let dx = cell.predecessor_dx;
+        assert!((-1..=1).contains(&dx), "{:?} has a predecessor {} columns away", pos, dx);
+        if pos.1 == 0 || cell.energy == FORBIDDEN {
+            continue;
+        }
+        let above = energies_above(cells, pos, size, wrap);
+        let predecessor = above[(dx + 1) as usize];
+        assert!(
+            predecessor <= cell.energy,
+            "{:?} has an energy of {} below its predecessor's, {}",
+            pos,
+            cell.energy,
+            predecessor
+        );
+        if cell.energy >= saturated {
+            continue;
+        }
+        for (other, &energy) in (-1..=1).zip(&above) {
+            let cheaper = if other < dx {
+                energy <= predecessor
+            } else {
+                energy < predecessor
+            };
+            assert!(
+                !cheaper,
+                "{:?} continues a seam of energy {} rather than {}",
+                pos,
+                predecessor,
+                energy
+            );
+        }
+    }
+}
+
+/// Checks that the cumulative energy of every position of a table is the one
+/// of its predecessor plus its own energy
+#[cfg(any(debug_assertions, feature = "strict"))]
+fn check_energies<C: Cell>(
+    cells: &Matrix<C>,
+    size: Pos,
+    wrap: bool,
+    mut energy: impl FnMut(Pos) -> u32,
+) {
+    let stored = |energy: u32| C::from_elem(SeamElem::new(energy)).energy();
+    for pos in Pos::iter_in_rect(Pos(0, 0), size) {
+        let cell = cells[pos].get().expect("the links were checked");
+        let delta_e = energy(pos);
+        let expected = match (delta_e, pos.1) {
+            (FORBIDDEN, _) => FORBIDDEN,
+            (_, 0) => stored(cumulate(0, delta_e)),
+            _ => match energies_above(cells, pos, size, wrap)[(cell.predecessor_dx + 1) as usize] {
+                FORBIDDEN => FORBIDDEN,
+                predecessor => stored(cumulate(predecessor, delta_e)),
+            },
+        };
+        assert!(
+            cell.energy == expected,
+            "the cumulative energy of {:?} is {} instead of {}",
+            pos,
+            cell.energy,
+            expected
+        );
+    }
+}
+
+/// Energies of consecutive positions and of their predecessors,
+/// kept between the runs of [fill_row_vectorized]
+#[cfg(feature = "simd")]
+#[derive(Default)]
+struct Run {
+    above: Vec<u32>,
+    delta: Vec<u32>,
+    energies: Vec<u32>,
+    dx: Vec<i8>,
+}
+
+/// Computes the positions of a part of a row like [fill_row], but computes the positions
+/// that are not cached yet and are not on the borders in runs, with [best_predecessors]
+#[cfg(feature = "simd")]
+fn fill_row_vectorized<C: Cell>(
+    above: &[C],
+    row: &mut [C],
+    y: u32,
+    columns: Range<usize>,
+    wrap: bool,
+    energy: &mut impl FnMut(Pos) -> u32,
+) {
+    let mut run = Run::default();
+    let mut x = columns.start;
+    while x < columns.end {
+        if row[x].is_filled() {
+            x += 1;
+            continue;
+        }
+        let run_end = (x..columns.end)
+            .find(|&x| row[x].is_filled())
+            .unwrap_or(columns.end);
+        // Positions on the borders have their predecessors on both sides of the row
+        let interior = x.max(1)..run_end.min(row.len() - 1);
+        for x in x..interior.start {
+            fill_position(above, row, Pos(x as u32, y), wrap, energy);
+        }
+        if !interior.is_empty() {
+            let above = &above[interior.start - 1..interior.end + 1];
+            run.above.clear();
+            run.above.extend(above.iter().map(Cell::energy));
+            run.delta.clear();
+            run.delta.extend(interior.clone().map(|x| {
+                span!(TRACE, "energy");
+                energy(Pos(x as u32, y))
+            }));
+            run.energies.resize(interior.len(), 0);
+            run.dx.resize(interior.len(), 0);
+            best_predecessors(&run.above, &run.delta, &mut run.energies, &mut run.dx);
+            for (i, cell) in row[interior.clone()].iter_mut().enumerate() {
+                *cell = C::from_elem(SeamElem {
+                    predecessor_dx: run.dx[i],
+                    energy: run.energies[i],
+                });
+            }
+        }
+        for x in interior.start.max(interior.end)..run_end {
+            fill_position(above, row, Pos(x as u32, y), wrap, energy);
+        }
+        x = run_end;
+    }
+}
+
+/// Computes the same positions as [fill_cells] without wrapping, in bands of rows
+/// that are each split into panels of `(columns, rows)` positions.
+///
+/// Each panel leans to the left by one column per row, so that its positions only depend
+/// on the positions of the same panel and of the panels on its left,
+/// which were computed just before and are still in the cache.
+/// A whole row of a wide image would not fit in it.
+fn fill_blocked<C: Cell>(
+    cells: &mut Matrix<C>,
+    columns: Range<usize>,
+    rows: Range<u32>,
+    (panel_columns, band_rows): (usize, u32),
+    vectorized: bool,
+    mut energy: impl FnMut(Pos) -> u32,
+) {
+    let mut band = rows.start;
+    while band < rows.end {
+        let band_end = (band + band_rows).min(rows.end);
+        let lean = (band_end - band - 1) as usize;
+        // The left edge of the panel on its first row
+        let mut left = columns.start;
+        while left < columns.end + lean {
+            for y in band..band_end {
+                let shift = (y - band) as usize;
+                let start = left.saturating_sub(shift).max(columns.start);
+                let end = (left + panel_columns).saturating_sub(shift).min(columns.end);
+                if start < end {
+                    fill_row(cells, y, start..end, false, vectorized, &mut energy);
                 }
             }
+            left += panel_columns;
+        }
+        band = band_end;
+    }
+}
+
+/// Computes the columns of the table between `start` and `end` that are not cached yet,
+/// see [SeamFinder::par_fill]
+#[cfg(feature = "rayon")]
+fn par_fill_cells<C: Cell>(
+    cells: &mut Matrix<C>,
+    (start, end): (usize, usize),
+    size: Pos,
+    wrap: bool,
+    energy: impl Fn(Pos) -> u32 + Sync,
+) {
+    use rayon::prelude::*;
+
+    for y in 0..size.1 {
+        let (above, row) = cells.row_and_above_mut(y);
+        let chunks = row[start..end].par_chunks_mut(PAR_FILL_CHUNK).enumerate();
+        chunks.for_each(|(i, chunk)| {
+            for (j, elem) in chunk.iter_mut().enumerate() {
+                if elem.is_filled() {
+                    continue;
+                }
+                let x = start + i * PAR_FILL_CHUNK + j;
+                let delta_e = energy(Pos(x as u32, y));
+                *elem = C::from_elem(SeamElem::best(x, delta_e, above, wrap));
+            }
+        });
+    }
+}
+
+/// A read-only view of the dynamic programming table of the seam finder,
+/// returned by [Carvable::seam_table](crate::Carvable::seam_table)
+///
+/// For every position, the table holds the lowest total energy of a seam going
+/// from the top row to this position, and the position of the previous row this seam goes through.
+pub struct SeamTable<'a> {
+    finder: &'a SeamFinder,
+}
+
+impl SeamFinder {
+    /// Fills the table and returns a view of it
+    pub fn table<F: FnMut(Pos) -> u32>(&mut self, energy: F) -> SeamTable<'_> {
+        self.fill(energy);
+        SeamTable { finder: self }
+    }
+}
+
+impl<'a> SeamTable<'a> {
+    /// Number of columns of the table, which is the width of the carved image
+    pub fn width(&self) -> u32 {
+        self.finder.size.0
+    }
+
+    /// Number of rows of the table
+    pub fn height(&self) -> u32 {
+        self.finder.size.1
+    }
+
+    /// Lowest total energy of a seam from the top row to the given position.
+    /// It is `u32::MAX` when no seam can reach the position without crossing a forbidden pixel.
+    pub fn cumulative_energy(&self, pos: Pos) -> u32 {
+        self.elem(pos)
+            .map_or(FORBIDDEN, |e| self.finder.unit_energy(e.energy))
+    }
+
+    /// The position of the previous row through which the cheapest seam reaching `pos` goes.
+    /// None on the top row and for positions no seam can reach.
+    pub fn predecessor(&self, pos: Pos) -> Option<Pos> {
+        let elem = self.elem(pos).filter(|e| e.energy != FORBIDDEN)?;
+        if pos.1 == 0 {
+            None
+        } else {
+            Some(elem.predecessor(pos, self.finder.size.0))
         }
     }
+
+    /// Copies the cumulative energies to a matrix, for instance to display them with
+    /// [to_ascii_heatmap](Matrix::to_ascii_heatmap)
+    pub fn cumulative_energies(&self) -> Matrix<u32> {
+        Matrix::from_fn(self.finder.size, |x, y| {
+            self.cumulative_energy(Pos(x as u32, y as u32))
+        })
+    }
+
+    fn elem(&self, pos: Pos) -> Option<SeamElem> {
+        assert!(pos.is_inside(self.finder.size), "{:?} is outside of the table", pos);
+        self.finder.cell(pos)
+    }
 }
 
 #[cfg(test)]
 mod tests {
+    use alloc::vec;
+    use alloc::vec::Vec;
+
+    use crate::error::SeamError;
     use crate::pos::Pos;
-    use crate::seam_finder::SeamFinder;
+    use crate::matrix::Matrix;
+    use crate::seam_finder::{
+        fill_blocked, fill_cells, quantize, Contents, DirtyBounds, SeamElem, SeamFinder, FORBIDDEN,
+    };
 
     #[test]
     fn extracts_correct_seam() {
@@ -160,7 +1412,39 @@ mod tests {
         // | \  \
         // 0  1  2
         let s1 = finder.extract_seam(energy_fn);
-        assert_eq!(s1, vec![Pos(0, 1), Pos(0, 0)]);
+        assert_eq!(s1, Some(vec![Pos(0, 1), Pos(0, 0)]));
+    }
+
+    #[test]
+    fn profiles_the_energy_of_the_rows_of_seams() {
+        let energy = |Pos(x, y): Pos| (x * 7 + y * 13) % 10;
+        let mut finder = SeamFinder::new(Pos(8, 6));
+        let mut expected = finder.clone();
+        for _ in 0..3 {
+            let (seam, total) = expected.extract_seam_with_energy(energy).unwrap();
+            let (profiled, profile) = finder.extract_seam_with_profile(energy).unwrap();
+            assert_eq!(profiled, seam);
+            assert_eq!(profile.iter().sum::<u32>(), total);
+        }
+        // The energies of the positions the seam goes through
+        let mut first = SeamFinder::new(Pos(8, 6));
+        let (seam, profile) = first.extract_seam_with_profile(energy).unwrap();
+        let energies: Vec<u32> = seam.iter().map(|&pos| energy(pos)).collect();
+        assert_eq!(profile, energies);
+        let mut empty = SeamFinder::new(Pos(0, 3));
+        assert_eq!(empty.extract_seam_with_profile(energy), Err(SeamError::Empty));
+    }
+
+    #[test]
+    fn finds_predecessors_across_the_border_of_huge_images() {
+        let width = 3_000_000_000;
+        let mut elem = SeamElem::new(0);
+        elem.set_dx(Pos(0, 1), Pos(width - 1, 0), width);
+        assert_eq!(elem.predecessor(Pos(0, 1), width), Pos(width - 1, 0));
+        elem.set_dx(Pos(width - 1, 1), Pos(0, 0), width);
+        assert_eq!(elem.predecessor(Pos(width - 1, 1), width), Pos(0, 0));
+        elem.set_dx(Pos(width - 2, 1), Pos(width - 1, 0), width);
+        assert_eq!(elem.predecessor(Pos(width - 2, 1), width), Pos(width - 1, 0));
     }
 
     #[test]
@@ -170,7 +1454,178 @@ mod tests {
         let energy_fn = |Pos(x, _y)| x;
         let s1 = finder.extract_seam(energy_fn);
         let expected: Vec<_> = (0..h).rev().map(|y| Pos(0, y)).collect();
-        assert_eq!(s1, expected);
+        assert_eq!(s1, Some(expected));
+    }
+
+    #[test]
+    fn extracts_seam_through_anchors() {
+        let mut finder = SeamFinder::new(Pos(3, 2));
+        let energy_fn = |Pos(x, _y)| x;
+        let s1 = finder.extract_seam_through(energy_fn, &[Pos(2, 1)]);
+        assert_eq!(s1, Some(vec![Pos(2, 1), Pos(1, 0)]));
+        let s2 = finder.extract_seam_through(energy_fn, &[Pos(0, 0), Pos(1, 1)]);
+        assert_eq!(s2, Some(vec![Pos(1, 1), Pos(0, 0)]));
+        let mut finder = SeamFinder::new(Pos(3, 2));
+        assert_eq!(finder.extract_seam_through(energy_fn, &[Pos(0, 0), Pos(2, 1)]), None);
+    }
+
+    #[test]
+    fn avoids_forbidden_positions() {
+        let mut finder = SeamFinder::new(Pos(3, 2));
+        // energy matrix:
+        // F  1  F
+        // 0  F  F
+        let energy_fn = |p| match p {
+            Pos(1, 0) => 1,
+            Pos(0, 1) => 0,
+            _ => FORBIDDEN,
+        };
+        assert_eq!(finder.extract_seam(energy_fn), Some(vec![Pos(0, 1), Pos(1, 0)]));
+        // energy matrix after removal:
+        // F  F
+        // F  F
+        assert_eq!(finder.extract_seam(|_| FORBIDDEN), None);
+        assert_eq!(finder.size, Pos(2, 2));
+    }
+
+    #[test]
+    fn fails_when_no_column_is_left() {
+        let mut finder = SeamFinder::new(Pos(1, 2));
+        assert_eq!(finder.extract_seam(|_| 0), Some(vec![Pos(0, 1), Pos(0, 0)]));
+        assert_eq!(finder.extract_seam_with_energy(|_| 0), Err(SeamError::Empty));
+    }
+
+    #[test]
+    fn exposes_the_table() {
+        let mut finder = SeamFinder::new(Pos(3, 2));
+        // energy matrix:
+        // 1  F  3
+        // 4  5  6
+        let energy_fn = |Pos(x, y)| match (x, y) {
+            (1, 0) => FORBIDDEN,
+            _ => 1 + x + 3 * y,
+        };
+        let table = finder.table(energy_fn);
+        assert_eq!(table.cumulative_energy(Pos(1, 0)), FORBIDDEN);
+        assert_eq!(table.cumulative_energy(Pos(1, 1)), 6);
+        assert_eq!(table.predecessor(Pos(1, 1)), Some(Pos(0, 0)));
+        assert_eq!(table.predecessor(Pos(2, 1)), Some(Pos(2, 0)));
+        assert_eq!(table.predecessor(Pos(2, 0)), None);
+        assert_eq!(table.cumulative_energies().into_vec()[3..], [5, 6, 9]);
+    }
+
+    #[test]
+    fn wrapping_seam_crosses_borders() {
+        // energy matrix:
+        // 0  5  5
+        // 5  5  0
+        let energy_fn = |p| match p {
+            Pos(0, 0) | Pos(2, 1) => 0,
+            _ => 5,
+        };
+        let mut finder = SeamFinder::new(Pos(3, 2));
+        assert_eq!(finder.extract_seam(energy_fn), Some(vec![Pos(0, 1), Pos(0, 0)]));
+        let mut finder = SeamFinder::new(Pos(3, 2));
+        finder.set_wrapping(true);
+        assert_eq!(finder.extract_seam(energy_fn), Some(vec![Pos(2, 1), Pos(0, 0)]));
+    }
+
+    #[test]
+    fn breaks_ties_to_the_left() {
+        // Every seam has the same energy
+        for (width, wrap) in [(1, true), (2, true), (3, false)] {
+            let mut finder = SeamFinder::new(Pos(width, 3));
+            finder.set_wrapping(wrap);
+            let expected = vec![Pos(0, 2), Pos(0, 1), Pos(0, 0)];
+            assert_eq!(finder.extract_seam(|_| 1), Some(expected));
+        }
+        // Across the border, the last column is on the left of the first one
+        let mut finder = SeamFinder::new(Pos(4, 3));
+        finder.set_wrapping(true);
+        let expected = vec![Pos(0, 2), Pos(3, 1), Pos(2, 0)];
+        assert_eq!(finder.extract_seam(|_| 1), Some(expected));
+        let mut finder = SeamFinder::new(Pos(4, 2));
+        finder.set_wrapping(true);
+        let table = finder.table(|Pos(x, y)| if y == 0 && x != 2 { 1 } else { 0 });
+        assert_eq!(table.predecessor(Pos(0, 1)), Some(Pos(3, 0)));
+        assert_eq!(table.predecessor(Pos(3, 1)), Some(Pos(2, 0)));
+    }
+
+    #[test]
+    fn fills_in_panels_like_row_by_row() {
+        let energy_fn = |Pos(x, y): Pos| match (x * 7 + y * 3) % 13 {
+            0 => FORBIDDEN,
+            e => e % 5,
+        };
+        let mut finder = SeamFinder::new(Pos(50, 20));
+        // The whole table, then the band left by removed seams
+        for removed in [0, 3] {
+            for _ in 0..removed {
+                finder.extract_seam(energy_fn).unwrap();
+            }
+            let (DirtyBounds(start, end), size) = (finder.dirty_bounds.clone(), finder.size);
+            let Contents::Full(cells) = &finder.contents else {
+                unreachable!("the finder is not quantized")
+            };
+            let mut rows = cells.clone();
+            fill_cells(&mut rows, Pos(start, 0), Pos(end, size.1), size, false, true, energy_fn);
+            let mut panels = cells.clone();
+            let columns = start as usize..end.min(size.0) as usize;
+            fill_blocked(&mut panels, columns, 0..size.1, (7, 3), true, energy_fn);
+            for p in Pos::iter_in_rect(Pos(0, 0), size) {
+                let elem = |cells: &Matrix<Option<SeamElem>>| {
+                    cells[p].map(|e| (e.energy, e.predecessor_dx))
+                };
+                assert!(rows[p].is_some());
+                assert_eq!(elem(&panels), elem(&rows), "{:?}", p);
+            }
+        }
+    }
+
+    #[test]
+    fn updates_like_a_fresh_table() {
+        for wrap in [false, true] {
+            let mut energies = Matrix::from_fn(Pos(24, 12), |x, y| ((x * 7 + y * 13) % 11) as u32);
+            let mut finder = SeamFinder::new(Pos(24, 12));
+            finder.set_wrapping(wrap);
+            for _ in 0..20 {
+                let mut fresh = SeamFinder::new(Pos(energies.width(), 12));
+                fresh.set_wrapping(wrap);
+                let expected = fresh.extract_seam_with_energy(|p| energies[p]);
+                let seam = finder.extract_seam_with_energy(|p| energies[p]);
+                assert_eq!(seam, expected);
+                energies.remove_seam(&seam.unwrap().0);
+            }
+            // Positions whose energy changed are computed again, along with their successors
+            energies[Pos(1, 3)] = 100;
+            finder.invalidate_positions([Pos(1, 3)]);
+            let mut fresh = SeamFinder::new(Pos(4, 12));
+            fresh.set_wrapping(wrap);
+            let expected = fresh.extract_seam_with_energy(|p| energies[p]);
+            assert_eq!(finder.extract_seam_with_energy(|p| energies[p]), expected);
+        }
+    }
+
+    #[test]
+    fn fills_again_when_updates_cover_most_of_the_table() {
+        let energy_fn = |Pos(x, y): Pos| (x * 7 + y * 13) % 11;
+        let mut finder = SeamFinder::new(Pos(12, 24));
+        finder.fill(energy_fn);
+        finder.updated = finder.size.area();
+        // Nothing is above the last row
+        finder.invalidate_positions([Pos(3, 23)]);
+        finder.choose_refill();
+        assert!(finder.dirty_bounds.0 >= finder.dirty_bounds.1);
+        finder.extract_seam(energy_fn).unwrap();
+        assert_eq!(finder.updated, 1);
+        // The positions below the removed seam cover most of the table
+        finder.updated = finder.size.area();
+        finder.choose_refill();
+        assert!(finder.dirty_bounds.0 < finder.dirty_bounds.1);
+        assert_eq!(finder.updated, finder.size.area() / 2);
+        // Every position is computed again
+        let expected = SeamFinder::new(finder.size).extract_seam_with_energy(energy_fn);
+        assert_eq!(finder.extract_seam_with_energy(energy_fn), expected);
     }
 
     #[test]
@@ -178,6 +1633,161 @@ mod tests {
         let mut finder = SeamFinder::new(Pos(10, 10));
         finder.fill(|_| 42);
         Pos::iter_in_rect(Pos(0, 0), finder.size)
-            .for_each(|p| assert!(finder.contents[p].is_some()))
+            .for_each(|p| assert!(finder.filled(p).is_ok()))
+    }
+
+    /// A filled table, whose position `pos` is then replaced with `elem`
+    #[cfg(any(debug_assertions, feature = "strict"))]
+    fn corrupted(pos: Pos, elem: impl FnOnce(SeamElem) -> SeamElem) -> SeamFinder {
+        let mut finder = SeamFinder::new(Pos(6, 4));
+        finder.fill(|Pos(x, _y)| x);
+        let Contents::Full(cells) = &mut finder.contents else {
+            unreachable!("the finder is not quantized")
+        };
+        cells[pos] = cells[pos].map(elem);
+        finder
+    }
+
+    #[test]
+    #[cfg(any(debug_assertions, feature = "strict"))]
+    #[should_panic(expected = "Pos(3, 2) continues a seam of energy")]
+    fn checks_the_predecessors() {
+        let finder = corrupted(Pos(3, 2), |_elem| SeamElem {
+            predecessor_dx: 1,
+            energy: 100,
+        });
+        finder.check_table(None::<fn(Pos) -> u32>);
+    }
+
+    #[test]
+    #[cfg(any(debug_assertions, feature = "strict"))]
+    #[should_panic(expected = "the cumulative energy of Pos(3, 2) is 7 instead of 6")]
+    fn checks_the_cumulative_energies() {
+        let finder = corrupted(Pos(3, 2), |elem| SeamElem {
+            energy: elem.energy + 1,
+            ..elem
+        });
+        finder.check_table(None::<fn(Pos) -> u32>);
+        finder.check_table(Some(|Pos(x, _y): Pos| x));
+    }
+
+    #[test]
+    fn quantizes_energies() {
+        let energy_fn = |Pos(x, y): Pos| match (x, y) {
+            (2, _) => FORBIDDEN,
+            _ => (x * 5 + y * 3) % 7 * 80,
+        };
+        let mut full = SeamFinder::new(Pos(6, 5));
+        let mut quantized = SeamFinder::new(Pos(6, 5));
+        quantized.set_quantized(true);
+        // Energies are multiples of 8, so the quantized ones are exact
+        for _ in 0..5 {
+            assert_eq!(
+                quantized.extract_seam_with_energy(energy_fn),
+                full.extract_seam_with_energy(energy_fn)
+            );
+        }
+        // Each 13 is rounded to 16
+        let mut quantized = SeamFinder::new(Pos(3, 5));
+        quantized.set_quantized(true);
+        assert_eq!(quantized.table(|_| 13).cumulative_energy(Pos(0, 4)), 5 * 16);
+        quantized.invalidate();
+        assert_eq!(quantized.extract_seam(|_| FORBIDDEN), None);
+        assert_eq!(quantize(FORBIDDEN - 1), u32::from(u16::MAX));
+        assert_eq!(
+            SeamFinder::memory_per_position(true) * 3,
+            SeamFinder::memory_per_position(false)
+        );
+    }
+
+    #[test]
+    fn keeps_a_fixed_capacity() {
+        let mut finder = SeamFinder::with_capacity(Pos(8, 4));
+        let capacity = (finder.capacity(), finder.dirty.capacity());
+        let mut seam = Vec::with_capacity(4);
+        let seam_buffer = seam.as_ptr();
+        for size in [Pos(8, 4), Pos(3, 3), Pos(16, 1)] {
+            finder.reset(size).unwrap();
+            for _ in 0..size.0 {
+                let energy = |Pos(x, y)| (x * 7 + y * size.0) % 5;
+                finder.extract_seam_into(energy, &mut seam).unwrap();
+                assert_eq!(seam.len(), size.1 as usize);
+            }
+            finder.shrink_to_fit();
+        }
+        assert_eq!(finder.reset(Pos(4, 8)), Err(SeamError::CapacityExceeded));
+        assert_eq!(finder.reset(Pos(9, 4)), Err(SeamError::CapacityExceeded));
+        assert_eq!((finder.capacity(), finder.dirty.capacity()), capacity);
+        finder.set_quantized(true);
+        assert_eq!(finder.reset(Pos(8, 4)), Ok(()));
+        assert_eq!(finder.capacity(), capacity.0);
+        assert_eq!(seam.as_ptr(), seam_buffer);
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn fills_from_streamed_rows() {
+        let energy_fn = |Pos(x, y): Pos| (x * 7 + y * 3) % 5;
+        for wrap in [false, true] {
+            let mut expected = SeamFinder::new(Pos(20, 6));
+            expected.set_wrapping(wrap);
+            let mut streamed = expected.clone();
+            for _ in 0..5 {
+                let size = streamed.size;
+                let (sender, receiver) = std::sync::mpsc::channel();
+                let producer = std::thread::spawn(move || {
+                    for y in 0..size.1 {
+                        let row: Vec<u32> = (0..size.0).map(|x| energy_fn(Pos(x, y))).collect();
+                        sender.send(row).unwrap();
+                    }
+                });
+                assert_eq!(streamed.fill_rows(receiver), Ok(()));
+                producer.join().unwrap();
+                let seam = streamed.extract_seam(|_| unreachable!("every position is cached"));
+                assert_eq!(seam, expected.extract_seam(energy_fn));
+            }
+            streamed.invalidate();
+            assert_eq!(streamed.fill_rows([vec![0; 15]]), Err(SeamError::SizeMismatch));
+            assert_eq!(streamed.fill_rows(vec![vec![0; 14]; 6]), Err(SeamError::SizeMismatch));
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "rayon")]
+    fn fills_identically_on_any_number_of_threads() {
+        let size = Pos(700, 12);
+        // Few distinct energies, so that many seams are tied
+        let energy_fn = |Pos(x, y): Pos| (x / 3 + y) % 4;
+        let seams = |finder: &mut SeamFinder, parallel: bool| -> Vec<Vec<Pos>> {
+            (0..20)
+                .map(|_| {
+                    if parallel {
+                        finder.par_fill(energy_fn);
+                    }
+                    finder.extract_seam(energy_fn).unwrap()
+                })
+                .collect()
+        };
+        for wrap in [false, true] {
+            let mut sequential = SeamFinder::new(size);
+            sequential.set_wrapping(wrap);
+            let expected = seams(&mut sequential, false);
+            let table = |finder: &mut SeamFinder| {
+                let table = finder.table(energy_fn);
+                let positions = Pos::iter_in_rect(Pos(0, 0), Pos(table.width(), table.height()));
+                positions
+                    .map(|p| (table.cumulative_energy(p), table.predecessor(p)))
+                    .collect::<Vec<_>>()
+            };
+            let expected_table = table(&mut sequential);
+            for threads in 1..=8 {
+                let pool = rayon::ThreadPoolBuilder::new().num_threads(threads).build().unwrap();
+                let mut parallel = SeamFinder::new(size);
+                parallel.set_wrapping(wrap);
+                assert_eq!(pool.install(|| seams(&mut parallel, true)), expected);
+                pool.install(|| parallel.par_fill(energy_fn));
+                assert_eq!(table(&mut parallel), expected_table);
+            }
+        }
     }
 }
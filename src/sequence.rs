@@ -0,0 +1,76 @@
+use image::imageops::{resize, FilterType};
+use image::GenericImageView;
+
+use crate::cache::{replay, Axis, SeamLog};
+use crate::pos::Pos;
+use crate::{max_pos, ImageBufferOf, SeamError};
+
+/// The seams removed from an image by [resize_with_seams](crate::Carver::resize_with_seams),
+/// in the order they were removed, to remove them again from other rasters of the same size,
+/// such as a segmentation mask, a normal map or a depth map,
+/// so that all the layers of an asset stay in registration.
+///
+/// ```
+/// use seamcarving::Carver;
+/// let img = image::RgbImage::from_fn(20, 10, |x, y| image::Rgb([(x * y) as u8, 0, 0]));
+/// let labels = image::GrayImage::from_fn(20, 10, |x, _y| image::Luma([(x / 5) as u8]));
+/// let (resized, seams) = Carver::new().width(14).height(8).resize_with_seams(&img).unwrap();
+/// let labels = seams.unwrap().replay(&labels).unwrap();
+/// assert_eq!(labels.dimensions(), resized.dimensions());
+/// ```
+#[derive(Clone, Debug)]
+pub struct SeamSequence {
+    log: SeamLog,
+    size: Pos,
+    resized: Pos,
+}
+
+impl SeamSequence {
+    pub(crate) fn new(log: SeamLog, size: Pos, resized: Pos) -> Self {
+        SeamSequence { log, size, resized }
+    }
+
+    /// Width and height of the images the seams can be removed from
+    pub fn dimensions(&self) -> (u32, u32) {
+        (self.size.0, self.size.1)
+    }
+
+    /// Number of removed seams
+    pub fn len(&self) -> usize {
+        self.log.seams().len()
+    }
+
+    /// Whether no seam was removed
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The removed seams, in the order they were removed, from the bottom to the top.
+    /// Horizontal seams are in the coordinates of the image rotated by a quarter turn,
+    /// where they are vertical.
+    pub fn seams(&self) -> impl Iterator<Item = (Axis, &[Pos])> + '_ {
+        let seams = self.log.seams().iter();
+        seams.map(|(axis, seam)| (*axis, seam.as_slice()))
+    }
+
+    /// Removes the seams from another image of the same size, without blending their edges,
+    /// so that labels and vectors are never mixed. When the resized image was also scaled down,
+    /// the image is scaled down in the same way, keeping the closest pixel.
+    ///
+    /// Fails with [SeamError::SizeMismatch] if the image does not have the dimensions
+    /// of the image the seams were removed from.
+    pub fn replay<IMG: GenericImageView>(&self, img: &IMG) -> Result<ImageBufferOf<IMG>, SeamError>
+    where
+        <IMG as GenericImageView>::Pixel: 'static,
+    {
+        if max_pos(img) != self.size {
+            return Err(SeamError::SizeMismatch);
+        }
+        let carved = replay(img, &self.log, false, false).ok_or(SeamError::SizeMismatch)?;
+        if max_pos(&carved) == self.resized {
+            return Ok(carved);
+        }
+        let Pos(width, height) = self.resized;
+        Ok(resize(&carved, width, height, FilterType::Nearest))
+    }
+}
@@ -0,0 +1,231 @@
+use std::ops::ControlFlow;
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+
+use image::{GenericImageView, ImageBuffer, Pixel};
+
+use crate::cache::{replay, Axis, SeamLog};
+use crate::pos::Pos;
+use crate::{image_view_to_buffer, Carver, ImageBufferOf, Report, SeamError};
+
+/// How far the resize of a [SharedCarver] has gone
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Default)]
+#[non_exhaustive]
+pub struct Progress {
+    /// Number of seams removed so far
+    pub removed: u32,
+    /// Number of seams to remove to reach the target dimensions
+    pub total: u32,
+    /// Whether the resize is over, whether it succeeded or not
+    pub done: bool,
+}
+
+impl Progress {
+    /// The fraction of the seams removed so far, between 0 and 1,
+    /// which is 1 once the resize is over
+    pub fn fraction(&self) -> f64 {
+        if self.done || self.total == 0 {
+            1.
+        } else {
+            (f64::from(self.removed) / f64::from(self.total)).min(1.)
+        }
+    }
+}
+
+struct State<P: Pixel> {
+    size: (u32, u32),
+    snapshot: Option<Arc<ImageBuffer<P, Vec<P::Subpixel>>>>,
+    progress: Progress,
+}
+
+/// A [Carver] whose resize can be watched from other threads, such as the UI thread
+/// of an application, while a worker thread carves the image.
+///
+/// Clones share their state: the worker calls [resize](SharedCarver::resize),
+/// and the other clones read the current [size](SharedCarver::size),
+/// the [progress](SharedCarver::progress) and the latest [snapshot](SharedCarver::snapshot)
+/// of the image, which is updated every [snapshot_every](SharedCarver::snapshot_every) seams.
+///
+/// ```
+/// use seamcarving::{Carver, SharedCarver};
+/// let img = image::RgbImage::from_fn(40, 20, |x, y| image::Rgb([(x * y) as u8, 0, 0]));
+/// let shared = SharedCarver::new(Carver::new().width(30)).snapshot_every(4);
+/// let worker = shared.clone();
+/// let handle = std::thread::spawn(move || worker.resize(&img).unwrap());
+/// // In the UI thread
+/// while !handle.is_finished() {
+///     let fraction = shared.progress().fraction();
+///     assert!((0. ..=1.).contains(&fraction));
+/// }
+/// let (resized, _report) = handle.join().unwrap();
+/// assert!(shared.progress().done);
+/// assert_eq!(shared.size(), (30, 20));
+/// assert_eq!(*shared.snapshot().unwrap(), resized);
+/// ```
+pub struct SharedCarver<P: Pixel> {
+    carver: Carver,
+    snapshot_every: u32,
+    state: Arc<Mutex<State<P>>>,
+}
+
+impl<P: Pixel + 'static> SharedCarver<P> {
+    /// Shares the resizes of the given carver, with a snapshot every 16 seams
+    pub fn new(carver: Carver) -> Self {
+        SharedCarver {
+            carver,
+            snapshot_every: 16,
+            state: Arc::new(Mutex::new(State {
+                size: (0, 0),
+                snapshot: None,
+                progress: Progress::default(),
+            })),
+        }
+    }
+
+    /// Updates the snapshot every `seams` removed seams.
+    /// Each snapshot copies the image, so frequent snapshots slow the resize down.
+    pub fn snapshot_every(mut self, seams: u32) -> Self {
+        self.snapshot_every = seams.max(1);
+        self
+    }
+
+    /// Resizes an image like [Carver::resize_with_report], updating the state
+    /// read by the other clones as the seams are removed
+    pub fn resize<IMG>(&self, img: &IMG) -> Result<(ImageBufferOf<IMG>, Report), SeamError>
+    where
+        IMG: GenericImageView<Pixel = P>,
+    {
+        let mut current = image_view_to_buffer(img);
+        let (width, height) = img.dimensions();
+        let target = self.carver.target_dimensions(img);
+        *self.lock() = State {
+            size: (width, height),
+            snapshot: Some(Arc::new(current.clone())),
+            progress: Progress {
+                total: width.saturating_sub(target.0) + height.saturating_sub(target.1),
+                ..Progress::default()
+            },
+        };
+
+        let mut pending = SeamLog::default();
+        let mut pending_count = 0;
+        let result = self.carver.resize_with_callback(img, |seam, stats| {
+            // Seam logs store horizontal seams in the coordinates of the rotated image
+            let seam: Vec<Pos> = match stats.axis {
+                Axis::Vertical => seam.to_vec(),
+                Axis::Horizontal => seam.iter().map(|&Pos(x, y)| Pos(y, x)).collect(),
+            };
+            pending.push(stats.axis, seam);
+            pending_count += 1;
+            let snapshot = if pending_count == self.snapshot_every {
+                pending_count = 0;
+                let batch = std::mem::take(&mut pending);
+                replay(&current, &batch, false, false).map(|carved| {
+                    current = carved;
+                    Arc::new(current.clone())
+                })
+            } else {
+                None
+            };
+            let mut state = self.lock();
+            state.size = stats.size;
+            state.progress.removed = stats.removed.0 + stats.removed.1;
+            if snapshot.is_some() {
+                state.snapshot = snapshot;
+            }
+            ControlFlow::Continue(())
+        });
+
+        let mut state = self.lock();
+        state.progress.done = true;
+        if let Ok((resized, report)) = &result {
+            state.size = resized.dimensions();
+            state.progress.removed = report.seams.0 + report.seams.1;
+            state.snapshot = Some(Arc::new(resized.clone()));
+        }
+        result
+    }
+
+    /// The dimensions of the image being resized, once the last removed seam is removed
+    pub fn size(&self) -> (u32, u32) {
+        self.lock().size
+    }
+
+    /// How far the resize has gone
+    pub fn progress(&self) -> Progress {
+        self.lock().progress
+    }
+
+    /// The latest snapshot of the image being resized: the original image until
+    /// the first snapshot is taken, and the resized image once the resize succeeded,
+    /// or None before the first resize
+    pub fn snapshot(&self) -> Option<Arc<ImageBuffer<P, Vec<P::Subpixel>>>> {
+        self.lock().snapshot.clone()
+    }
+
+    fn lock(&self) -> MutexGuard<'_, State<P>> {
+        // The state is always consistent, even when a resize panicked
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl<P: Pixel> Clone for SharedCarver<P> {
+    fn clone(&self) -> Self {
+        SharedCarver {
+            carver: self.carver.clone(),
+            snapshot_every: self.snapshot_every,
+            state: Arc::clone(&self.state),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use image::{GrayImage, Luma, Rgba, RgbaImage};
+
+    use super::*;
+
+    #[test]
+    fn shares_the_state_of_the_resize() {
+        let img = RgbaImage::from_fn(24, 16, |x, y| Rgba([(x * 10) as u8, y as u8, 0, 255]));
+        let carver = Carver::new().width(20).height(13);
+        let shared = SharedCarver::new(carver.clone()).snapshot_every(2);
+        assert_eq!(shared.snapshot(), None);
+        let watcher = shared.clone();
+        let expected = carver.resize_with_report(&img).unwrap();
+        assert_eq!(shared.resize(&img).unwrap(), expected);
+        assert_eq!(watcher.size(), (20, 13));
+        let progress = watcher.progress();
+        assert_eq!((progress.removed, progress.total), (7, 7));
+        assert!(progress.done);
+        assert_eq!(progress.fraction(), 1.);
+        assert_eq!(*watcher.snapshot().unwrap(), expected.0);
+    }
+
+    #[test]
+    fn snapshots_the_seams_removed_so_far() {
+        let img = GrayImage::from_fn(200, 6, |x, y| Luma([(x * x + y * 7) as u8]));
+        let shared = SharedCarver::new(Carver::new().width(4)).snapshot_every(8);
+        let mut snapshots = Vec::new();
+        let img = &img;
+        std::thread::scope(|scope| {
+            let worker = shared.clone();
+            let worker = scope.spawn(move || worker.resize(img));
+            while !worker.is_finished() {
+                let Some(snapshot) = shared.snapshot() else {
+                    continue;
+                };
+                if !snapshots.last().is_some_and(|last| Arc::ptr_eq(last, &snapshot)) {
+                    snapshots.push(snapshot);
+                }
+            }
+            worker.join().unwrap().unwrap();
+        });
+        let widths: Vec<u32> = snapshots.iter().map(|snapshot| snapshot.width()).collect();
+        assert!(widths.windows(2).all(|pair| pair[0] >= pair[1]));
+        for snapshot in snapshots {
+            // The first seams of a resize are the ones of the resizes to larger widths
+            let carved = Carver::new().width(snapshot.width()).resize(img).unwrap();
+            assert_eq!(*snapshot, carved);
+        }
+    }
+}
@@ -0,0 +1,172 @@
+use image::{GenericImage, GenericImageView, ImageBuffer};
+
+use crate::blend::carved_to_buffer;
+use crate::importance::combine;
+use crate::max_pos;
+use crate::seam_finder::SeamFinder;
+use crate::{image_view_to_buffer, Carvable, PixelBuffer, Rotated, SeamError};
+
+/// Resizes frames that must stay aligned with each other, such as the frames
+/// of a sprite animation or of a flipbook, by removing the same seams from all of them.
+///
+/// The seams are found in the sum of the energies of all the frames, so they avoid
+/// what moves in any of them. Vertical seams are removed first, then horizontal ones.
+/// Fails with [SeamError::SizeMismatch] if the frames do not all have the same dimensions.
+///
+/// ```
+/// let frames: Vec<_> = (0..4)
+///     .map(|i| image::GrayImage::from_fn(20, 10, |x, y| image::Luma([(x * y + i * 10) as u8])))
+///     .collect();
+/// let resized = seamcarving::resize_frames(&frames, 15, 8).unwrap();
+/// assert!(resized.iter().all(|frame| frame.dimensions() == (15, 8)));
+/// ```
+pub fn resize_frames<IMG: GenericImageView>(
+    frames: &[IMG],
+    width: u32,
+    height: u32,
+) -> Result<Vec<PixelBuffer<IMG::Pixel>>, SeamError>
+where
+    IMG::Pixel: 'static,
+{
+    let wide = carve_frames(frames, width)?;
+    let rotated: Vec<_> = wide.iter().map(Rotated).collect();
+    let carved = carve_frames(&rotated, height)?;
+    Ok(carved
+        .iter()
+        .map(|frame| image_view_to_buffer(&Rotated(frame)))
+        .collect())
+}
+
+/// Resizes every frame of a sprite sheet made of `columns` by `rows` frames of the same size,
+/// with the same seams, see [resize_frames].
+/// Returns the new sprite sheet, with the same layout and frames of the given size.
+///
+/// Fails with [SeamError::SizeMismatch] if the dimensions of the sprite sheet
+/// are not multiples of the numbers of columns and rows.
+pub fn resize_sprite_sheet<IMG: GenericImageView>(
+    sheet: &IMG,
+    (columns, rows): (u32, u32),
+    frame_width: u32,
+    frame_height: u32,
+) -> Result<PixelBuffer<IMG::Pixel>, SeamError>
+where
+    IMG::Pixel: 'static,
+{
+    let (width, height) = sheet.dimensions();
+    if columns == 0 || rows == 0 || width % columns != 0 || height % rows != 0 {
+        return Err(SeamError::SizeMismatch);
+    }
+    let (original_width, original_height) = (width / columns, height / rows);
+    let frames: Vec<_> = (0..rows)
+        .flat_map(|row| (0..columns).map(move |column| (column, row)))
+        .map(|(column, row)| {
+            let (x, y) = (column * original_width, row * original_height);
+            sheet.view(x, y, original_width, original_height)
+        })
+        .collect();
+    let resized = resize_frames(&frames, frame_width, frame_height)?;
+    let (frame_width, frame_height) = resized.first().map_or((0, 0), |frame| frame.dimensions());
+    let mut carved = ImageBuffer::new(frame_width * columns, frame_height * rows);
+    for (i, frame) in resized.iter().enumerate() {
+        let (column, row) = (i as u32 % columns, i as u32 / columns);
+        carved
+            .copy_from(frame, column * frame_width, row * frame_height)
+            .expect("the sprite sheet fits all the frames");
+    }
+    Ok(carved)
+}
+
+/// Removes the same vertical seams from all the frames until they are at most `width` wide
+fn carve_frames<IMG: GenericImageView>(
+    frames: &[IMG],
+    width: u32,
+) -> Result<Vec<PixelBuffer<IMG::Pixel>>, SeamError>
+where
+    IMG::Pixel: 'static,
+{
+    let Some(first) = frames.first() else {
+        return Ok(Vec::new());
+    };
+    let size = max_pos(first);
+    if frames.iter().any(|frame| max_pos(frame) != size) {
+        return Err(SeamError::SizeMismatch);
+    }
+    let mut carvables: Vec<Carvable<IMG>> = frames.iter().map(Carvable::new).collect();
+    let mut seam_finder = SeamFinder::new(size);
+    for _ in width.max(1)..size.0 {
+        let energy = |pos| {
+            let energies = carvables
+                .iter()
+                .map(|c| c.layers.energy(&c.carved, pos, false));
+            energies.fold(0, combine)
+        };
+        let (seam, _energy) = seam_finder.extract_seam_with_energy(energy)?;
+        for carvable in &mut carvables {
+            carvable.apply_seam(&seam);
+        }
+    }
+    Ok(carvables
+        .iter()
+        .map(|carvable| carved_to_buffer(&carvable.carved, false))
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use image::{GrayImage, Luma};
+
+    use super::*;
+
+    /// The same image, brighter in each frame, with a bright spot moving along its top
+    fn frames() -> Vec<GrayImage> {
+        (0..3)
+            .map(|i| {
+                GrayImage::from_fn(12, 8, |x, y| {
+                    let spot = y == 0 && x == 3 + 3 * i;
+                    Luma([if spot {
+                        255
+                    } else {
+                        ((x * 7 + y * 13) % 50 + i * 10) as u8
+                    }])
+                })
+            })
+            .collect()
+    }
+
+    #[test]
+    fn removes_the_same_seams_from_every_frame() {
+        let frames = frames();
+        let resized = resize_frames(&frames, 9, 6).unwrap();
+        for (i, frame) in resized.iter().enumerate().skip(1) {
+            assert_eq!(frame.dimensions(), (9, 6));
+            let brighter = |(x, y, p): (u32, u32, &Luma<u8>)| {
+                let first = resized[0].get_pixel(x, y).0[0];
+                p.0[0] == 255 || first == 255 || p.0[0] == first + 10 * i as u8
+            };
+            assert!(frame.enumerate_pixels().all(brighter));
+        }
+        // Seams avoid the spot of every frame
+        for (frame, resized) in frames.iter().zip(&resized) {
+            let spots = |img: &GrayImage| img.pixels().filter(|p| p.0[0] == 255).count();
+            assert_eq!(spots(resized), spots(frame));
+        }
+        let mismatch = [frames[0].clone(), GrayImage::new(12, 7)];
+        assert_eq!(resize_frames(&mismatch, 9, 6), Err(SeamError::SizeMismatch));
+    }
+
+    #[test]
+    fn resizes_sprite_sheets() {
+        let frames = frames();
+        let mut sheet = GrayImage::new(36, 8);
+        for (i, frame) in frames.iter().enumerate() {
+            sheet.copy_from(frame, i as u32 * 12, 0).unwrap();
+        }
+        let resized = resize_sprite_sheet(&sheet, (3, 1), 9, 6).unwrap();
+        assert_eq!(resized.dimensions(), (27, 6));
+        let expected = resize_frames(&frames, 9, 6).unwrap();
+        for (i, frame) in expected.iter().enumerate() {
+            assert_eq!(&resized.view(i as u32 * 9, 0, 9, 6).to_image(), frame);
+        }
+        assert!(resize_sprite_sheet(&sheet, (5, 1), 9, 6).is_err());
+    }
+}
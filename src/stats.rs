@@ -0,0 +1,180 @@
+use image::GenericImageView;
+
+use crate::energy::energy_fn;
+use crate::matrix::Matrix;
+use crate::max_pos;
+use crate::pos::Pos;
+
+/// Summary statistics of the energies of the pixels of an image, see [energy_stats],
+/// to choose thresholds such as [Carver::max_seam_energy](crate::Carver::max_seam_energy)
+/// or [Carver::energy_budget](crate::Carver::energy_budget) for each image
+/// instead of hard-coding them.
+#[derive(Clone, PartialEq, Debug)]
+pub struct EnergyStats {
+    // The energies of the pixels, from the lowest to the highest
+    sorted: Vec<u32>,
+    total: u64,
+    column_concentration: f64,
+    row_concentration: f64,
+}
+
+impl EnergyStats {
+    /// The statistics of a matrix of energies, for instance ones computed with
+    /// an importance map added, or by another energy function
+    pub fn from_matrix(energies: &Matrix<u32>) -> Self {
+        let size = Pos(energies.width(), energies.height());
+        let mut columns = vec![0u64; size.0 as usize];
+        let mut rows = vec![0u64; size.1 as usize];
+        let mut sorted = Vec::with_capacity(size.area());
+        for pos in Pos::iter_in_rect(Pos(0, 0), size) {
+            let energy = energies[pos];
+            columns[pos.0 as usize] += u64::from(energy);
+            rows[pos.1 as usize] += u64::from(energy);
+            sorted.push(energy);
+        }
+        sorted.sort_unstable();
+        EnergyStats {
+            total: sorted.iter().map(|&energy| u64::from(energy)).sum(),
+            sorted,
+            column_concentration: gini(&mut columns),
+            row_concentration: gini(&mut rows),
+        }
+    }
+
+    /// The number of pixels
+    pub fn count(&self) -> usize {
+        self.sorted.len()
+    }
+
+    /// The sum of the energies of the pixels
+    pub fn total(&self) -> u64 {
+        self.total
+    }
+
+    /// The mean energy of the pixels, or 0 for an empty image
+    pub fn mean(&self) -> f64 {
+        if self.sorted.is_empty() {
+            0.
+        } else {
+            self.total as f64 / self.sorted.len() as f64
+        }
+    }
+
+    /// The lowest energy of the pixels, or 0 for an empty image
+    pub fn min(&self) -> u32 {
+        self.sorted.first().copied().unwrap_or(0)
+    }
+
+    /// The highest energy of the pixels, or 0 for an empty image
+    pub fn max(&self) -> u32 {
+        self.sorted.last().copied().unwrap_or(0)
+    }
+
+    /// The energy under which the given fraction of the pixels is, between 0 and 1:
+    /// `percentile(0.5)` is the median energy. It is 0 for an empty image.
+    pub fn percentile(&self, fraction: f64) -> u32 {
+        let Some(last) = self.sorted.len().checked_sub(1) else {
+            return 0;
+        };
+        // The nearest rank, and NaN becomes 0
+        let rank = (fraction.clamp(0., 1.) * last as f64).round() as usize;
+        self.sorted[rank]
+    }
+
+    /// The number of pixels in each of `bins` ranges of energies of the same width,
+    /// from 0 to the [highest energy](EnergyStats::max), which is in the last one
+    pub fn histogram(&self, bins: usize) -> Vec<usize> {
+        let mut histogram = vec![0; bins];
+        if bins == 0 {
+            return histogram;
+        }
+        let width = u64::from(self.max()) / bins as u64 + 1;
+        for &energy in &self.sorted {
+            histogram[(u64::from(energy) / width) as usize] += 1;
+        }
+        histogram
+    }
+
+    /// How concentrated the energy is in a few columns, between 0 and 1:
+    /// the Gini coefficient of the energies of the columns.
+    /// 0 when every column has the same energy, so that any vertical seam removes as much,
+    /// and close to 1 when a few columns hold all of it, so that many seams are almost free.
+    pub fn column_concentration(&self) -> f64 {
+        self.column_concentration
+    }
+
+    /// How concentrated the energy is in a few rows, like
+    /// [column_concentration](EnergyStats::column_concentration) for horizontal seams
+    pub fn row_concentration(&self) -> f64 {
+        self.row_concentration
+    }
+}
+
+/// The statistics of the gradient energies of the pixels of an image,
+/// the energies that [resize](crate::resize) starts from.
+///
+/// ```
+/// use seamcarving::{energy_stats, Carver};
+/// let img = image::GrayImage::from_fn(40, 20, |x, y| image::Luma([(x * y) as u8]));
+/// let stats = energy_stats(&img);
+/// assert!(stats.percentile(0.) <= stats.percentile(0.9));
+/// assert_eq!(stats.histogram(8).iter().sum::<usize>(), 40 * 20);
+/// // Stops before carving through the most detailed quarter of the image
+/// let carver = Carver::new().width(20).max_seam_energy(stats.percentile(0.75));
+/// ```
+pub fn energy_stats<IMG: GenericImageView>(img: &IMG) -> EnergyStats {
+    let energies = Matrix::from_fn(max_pos(img), |x, y| energy_fn(img, Pos(x as u32, y as u32)));
+    EnergyStats::from_matrix(&energies)
+}
+
+/// The Gini coefficient of some values, which it sorts, or 0 when they are all 0
+fn gini(values: &mut [u64]) -> f64 {
+    values.sort_unstable();
+    let total: f64 = values.iter().map(|&value| value as f64).sum();
+    if total == 0. {
+        return 0.;
+    }
+    let count = values.len() as f64;
+    let weighted: f64 = values
+        .iter()
+        .enumerate()
+        .map(|(i, &value)| (2. * (i as f64 + 1.) - count - 1.) * value as f64)
+        .sum();
+    weighted / (count * total)
+}
+
+#[cfg(test)]
+mod tests {
+    use image::{GrayImage, Luma};
+
+    use super::*;
+
+    #[test]
+    fn summarizes_the_energies() {
+        let energies = Matrix::from_vec(Pos(4, 2), vec![0, 10, 0, 0, 0, 30, 0, 0]).unwrap();
+        let stats = EnergyStats::from_matrix(&energies);
+        assert_eq!((stats.count(), stats.total(), stats.mean()), (8, 40, 5.));
+        assert_eq!((stats.min(), stats.max()), (0, 30));
+        assert_eq!(stats.percentile(0.5), 0);
+        assert_eq!(stats.percentile(1.), 30);
+        assert_eq!(stats.histogram(3), vec![7, 0, 1]);
+        // All the energy in one of four columns, and split unevenly between the rows
+        assert_eq!(stats.column_concentration(), 0.75);
+        assert_eq!(stats.row_concentration(), 0.25);
+
+        let empty = energy_stats(&GrayImage::new(0, 0));
+        assert_eq!((empty.max(), empty.percentile(0.5), empty.mean()), (0, 0, 0.));
+        assert!(empty.histogram(0).is_empty());
+    }
+
+    #[test]
+    fn finds_vertical_details() {
+        // Stripes in the columns on the left of the image
+        let img = GrayImage::from_fn(30, 10, |x, _y| {
+            Luma([if x < 6 { (x % 2 * 200) as u8 } else { 0 }])
+        });
+        let stats = energy_stats(&img);
+        assert!(stats.column_concentration() > 0.7);
+        assert_eq!(stats.row_concentration(), 0.);
+    }
+}
@@ -0,0 +1,121 @@
+use image::GenericImageView;
+
+use crate::blend::carved_to_buffer;
+use crate::importance::combine;
+use crate::matrix::Matrix;
+use crate::pos::Pos;
+use crate::seam_finder::SeamFinder;
+use crate::{max_pos, Carvable, PixelBuffer, SeamError};
+
+/// The left and right views of a stereo pair
+type Views<P> = (PixelBuffer<P>, PixelBuffer<P>);
+
+/// Reduces the width of the two views of a stereo pair, removing the same content
+/// from both of them, so that the pair can still be viewed in 3D.
+///
+/// `disparity` tells, for each pixel of the left view, how many pixels to the left
+/// the same point is in the right view, as computed by stereo matching.
+/// Each seam is found in the left view, with the energy of each of its pixels coupled
+/// with the energy of the matching pixel of the right view, and the matching pixels
+/// are removed from the right view. Disparities pointing outside of the right view
+/// are clamped to its borders. The height of the views is kept.
+///
+/// Fails with [SeamError::SizeMismatch] if the views and the disparity map
+/// do not all have the same dimensions.
+///
+/// ```
+/// use seamcarving::{resize_stereo_pair, Matrix, Pos};
+/// let right = image::GrayImage::from_fn(24, 10, |x, y| image::Luma([(x * y) as u8]));
+/// // Everything is 2 pixels to the right in the left view
+/// let left = image::GrayImage::from_fn(24, 10, |x, y| {
+///     *right.get_pixel(x.saturating_sub(2), y)
+/// });
+/// let disparity = Matrix::from_fn(Pos(24, 10), |_x, _y| 2);
+/// let (left, right) = resize_stereo_pair(&left, &right, &disparity, 18).unwrap();
+/// assert_eq!((left.dimensions(), right.dimensions()), ((18, 10), (18, 10)));
+/// ```
+pub fn resize_stereo_pair<IMG: GenericImageView>(
+    left: &IMG,
+    right: &IMG,
+    disparity: &Matrix<u32>,
+    width: u32,
+) -> Result<Views<IMG::Pixel>, SeamError>
+where
+    IMG::Pixel: 'static,
+{
+    let size = max_pos(left);
+    let disparity_size = Pos(disparity.width(), disparity.height());
+    if max_pos(right) != size || disparity_size != size {
+        return Err(SeamError::SizeMismatch);
+    }
+    // Column of the right view on which the content of each pixel of the left view is
+    let mut matches = Matrix::from_fn(size, |x, y| {
+        let x = x as u32;
+        x.saturating_sub(disparity[Pos(x, y as u32)])
+    });
+    let mut left_view = Carvable::new(left);
+    let mut right_view = Carvable::new(right);
+    let mut seam_finder = SeamFinder::new(size);
+    for _ in width.max(1)..size.0 {
+        let energy = |pos: Pos| {
+            let left_energy = left_view.layers.energy(&left_view.carved, pos, false);
+            let right_pos = Pos(matches[pos], pos.1);
+            let right_energy = right_view
+                .layers
+                .energy(&right_view.carved, right_pos, false);
+            combine(left_energy, right_energy)
+        };
+        let (seam, _energy) = seam_finder.extract_seam_with_energy(energy)?;
+        let mut removed = vec![0; size.1 as usize];
+        for &pos in &seam {
+            removed[pos.1 as usize] = matches[pos];
+        }
+        let right_seam: Vec<Pos> = (0..size.1).map(|y| Pos(removed[y as usize], y)).collect();
+        left_view.apply_seam(&seam);
+        right_view.apply_seam(&right_seam);
+        matches.remove_seam(&seam);
+        let carved = max_pos(&left_view.carved);
+        for pos in Pos::iter_in_rect(Pos(0, 0), carved) {
+            let column = &mut matches[pos];
+            // The content on the right of the removed pixel moved left
+            if *column > removed[pos.1 as usize] {
+                *column -= 1;
+            }
+            *column = (*column).min(carved.0 - 1);
+        }
+        // Where the disparity changes, the pixels of the right view whose energy changed
+        // are not next to the seam of the left view
+        seam_finder.invalidate();
+    }
+    Ok((
+        carved_to_buffer(&left_view.carved, false),
+        carved_to_buffer(&right_view.carved, false),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use image::{GrayImage, Luma};
+
+    use super::*;
+
+    #[test]
+    fn removes_the_same_content_from_both_views() {
+        let right = GrayImage::from_fn(20, 6, |x, y| Luma([((x * 37 + y * 11) % 200) as u8]));
+        // The left view sees the same scene 2 pixels to the right,
+        // and a checkerboard that is not in the right view on its left
+        let left = GrayImage::from_fn(20, 6, |x, y| match x {
+            0 | 1 => Luma([if (x + y) % 2 == 0 { 0 } else { 255 }]),
+            x => *right.get_pixel(x - 2, y),
+        });
+        let disparity = Matrix::from_fn(Pos(20, 6), |_x, _y| 2);
+        let (left, right) = resize_stereo_pair(&left, &right, &disparity, 14).unwrap();
+        assert_eq!((left.dimensions(), right.dimensions()), ((14, 6), (14, 6)));
+        for (x, y, pixel) in left.enumerate_pixels().filter(|&(x, _, _)| x >= 2) {
+            assert_eq!(pixel, right.get_pixel(x - 2, y));
+        }
+        let wrong_size = Matrix::from_fn(Pos(19, 6), |_x, _y| 0);
+        let result = resize_stereo_pair(&left, &right, &wrong_size, 10);
+        assert_eq!(result.err(), Some(SeamError::SizeMismatch));
+    }
+}
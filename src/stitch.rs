@@ -0,0 +1,77 @@
+use image::GenericImageView;
+
+use crate::energy::square_diff_px;
+use crate::pos::Pos;
+use crate::seam::Seam;
+use crate::seam_finder::SeamFinder;
+use crate::{max_pos, SeamError};
+
+/// Finds the vertical cut along which two overlapping images differ the least,
+/// such as the overlap of two neighbouring photos of a panorama.
+///
+/// Both images must have the same dimensions: they are the pixels of the overlap
+/// taken from the left and from the right image. Stitching them keeps the pixels
+/// on the left of the seam from `overlap_a`, and the other ones from `overlap_b`,
+/// which the seam itself can come from either.
+/// For images overlapping vertically, pass [Rotated](crate::Rotated) views:
+/// the positions of the seam are then transposed.
+///
+/// ```
+/// use seamcarving::{blend_seam, Pos};
+/// let a = image::GrayImage::from_fn(5, 3, |x, _y| image::Luma([x as u8 * 10]));
+/// let b = image::GrayImage::from_fn(5, 3, |_x, _y| image::Luma([20]));
+/// let seam = blend_seam(&a, &b).unwrap();
+/// assert_eq!(seam.sorted_top_down(), vec![Pos(2, 0), Pos(2, 1), Pos(2, 2)]);
+/// ```
+pub fn blend_seam<A, B>(overlap_a: &A, overlap_b: &B) -> Result<Seam, SeamError>
+where
+    A: GenericImageView,
+    B: GenericImageView<Pixel = A::Pixel>,
+{
+    assert_eq!(
+        overlap_a.dimensions(),
+        overlap_b.dimensions(),
+        "both overlaps must have the same dimensions"
+    );
+    let mut finder = SeamFinder::new(max_pos(overlap_a));
+    let difference =
+        |p: Pos| square_diff_px(overlap_a.get_pixel(p.0, p.1), overlap_b.get_pixel(p.0, p.1));
+    let (seam, _difference) = finder.extract_seam_with_energy(difference)?;
+    Ok(Seam::new(seam))
+}
+
+#[cfg(test)]
+mod tests {
+    use image::{GrayImage, Luma, Rgb, RgbImage};
+
+    use super::*;
+    use crate::Rotated;
+
+    #[test]
+    fn cuts_where_the_images_match() {
+        // The images only match along a diagonal
+        let a = RgbImage::from_fn(8, 6, |x, y| Rgb([(x * 30) as u8, (y * 40) as u8, 0]));
+        let b = RgbImage::from_fn(8, 6, |x, y| {
+            let matching = x == y + 1;
+            if matching {
+                *a.get_pixel(x, y)
+            } else {
+                Rgb([255, 0, 255])
+            }
+        });
+        let seam = blend_seam(&a, &b).unwrap();
+        assert!(seam.positions().iter().all(|&Pos(x, y)| x == y + 1));
+        // Overlapping vertically, the images only match along a row
+        let c = RgbImage::from_fn(8, 6, |x, y| match y {
+            3 => *a.get_pixel(x, y),
+            _ => Rgb([0, 255, 0]),
+        });
+        let horizontal = blend_seam(&Rotated(&a), &Rotated(&c)).unwrap();
+        assert_eq!(horizontal.len(), 8);
+        assert!(horizontal.positions().iter().all(|&Pos(y, _x)| y == 3));
+        let empty = GrayImage::new(0, 4);
+        assert_eq!(blend_seam(&empty, &empty), Err(SeamError::Empty));
+        let single = GrayImage::from_pixel(1, 4, Luma([3]));
+        assert_eq!(blend_seam(&single, &single).unwrap().len(), 4);
+    }
+}
@@ -0,0 +1,149 @@
+//! Synthetic images whose seams are known in advance,
+//! to check energies and algorithms against a ground truth, in tests and benchmarks.
+//!
+//! All the images are grayscale, and the same parameters always give the same image.
+//!
+//! ```
+//! use seamcarving::{testimg, Carvable};
+//! let (img, seam) = testimg::optimal_seam(40, 30, 7);
+//! let mut carvable = Carvable::new(&img);
+//! assert_eq!(carvable.peek_seams(1).unwrap()[0], seam);
+//! ```
+use image::{GrayImage, Luma};
+
+use crate::enlarge::Random;
+use crate::{Pos, Seam};
+
+/// The brightness of the background of [optimal_seam], cycling over the columns
+/// so that no background pixel has the same left and right neighbours
+const STRIPES: [u8; 3] = [160, 208, 255];
+
+/// A linear gradient from black on the left column to white on the right one.
+/// Every pixel away from the borders has the same energy, so all the seams cost as much.
+pub fn gradient(width: u32, height: u32) -> GrayImage {
+    let last = width.saturating_sub(1).max(1);
+    GrayImage::from_fn(width, height, |x, _y| Luma([(x * 255 / last) as u8]))
+}
+
+/// A radial gradient from white at the center to black at the corners,
+/// whose energy grows toward the borders
+pub fn radial_gradient(width: u32, height: u32) -> GrayImage {
+    let (cx, cy) = (width as f32 / 2., height as f32 / 2.);
+    let max = cx.hypot(cy).max(1.);
+    GrayImage::from_fn(width, height, |x, y| {
+        let distance = (x as f32 + 0.5 - cx).hypot(y as f32 + 0.5 - cy);
+        Luma([(255. * (1. - distance / max)).round().clamp(0., 255.) as u8])
+    })
+}
+
+/// Black and white squares of `cell` pixels, whose energy is on the edges of the squares
+pub fn checkerboard(width: u32, height: u32, cell: u32) -> GrayImage {
+    let cell = cell.max(1);
+    GrayImage::from_fn(width, height, |x, y| {
+        Luma([if (x / cell + y / cell).is_multiple_of(2) { 0 } else { 255 }])
+    })
+}
+
+/// A disc of [salient_blobs]
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct Blob {
+    /// The center of the disc
+    pub center: Pos,
+    /// The radius of the disc, in pixels
+    pub radius: u32,
+}
+
+impl Blob {
+    /// Whether a pixel is in the disc
+    pub fn contains(&self, Pos(x, y): Pos) -> bool {
+        let dx = i64::from(x) - i64::from(self.center.0);
+        let dy = i64::from(y) - i64::from(self.center.1);
+        dx * dx + dy * dy <= i64::from(self.radius) * i64::from(self.radius)
+    }
+}
+
+/// Discs of black and white pixels, of a much higher energy than the faint noise around them,
+/// drawn from `seed`. Seam carving should remove the noise and keep all the pixels
+/// of the blobs, as long as enough columns and rows are free of them.
+pub fn salient_blobs(width: u32, height: u32, blobs: &[Blob], seed: u64) -> GrayImage {
+    let mut rng = Random(seed);
+    GrayImage::from_fn(width, height, |x, y| {
+        let noise = 100 + rng.below(16) as u8;
+        if !blobs.iter().any(|blob| blob.contains(Pos(x, y))) {
+            Luma([noise])
+        } else if (x + y).is_multiple_of(2) {
+            Luma([0])
+        } else {
+            Luma([255])
+        }
+    })
+}
+
+/// An image with a single vertical seam of zero energy, wandering randomly from `seed`,
+/// and that seam. Its pixels and their left and right neighbours are black,
+/// and all the other pixels are bright stripes of a higher energy,
+/// so [Carvable](crate::Carvable) must find exactly this seam first.
+///
+/// Panics if the image is less than 5 pixels wide or has no rows.
+pub fn optimal_seam(width: u32, height: u32, seed: u64) -> (GrayImage, Seam) {
+    assert!(width >= 5 && height > 0, "the image must be at least 5x1 pixels");
+    // The seam stays two pixels away from the borders, for its black neighbours
+    let (min, max) = (2, width - 3);
+    let mut rng = Random(seed);
+    let mut x = min + rng.below((max - min + 1) as usize) as u32;
+    let mut columns = Vec::with_capacity(height as usize);
+    for _ in 0..height {
+        columns.push(x);
+        x = match rng.below(3) {
+            0 => x.saturating_sub(1).max(min),
+            1 => x,
+            _ => (x + 1).min(max),
+        };
+    }
+    let img = GrayImage::from_fn(width, height, |x, y| {
+        if x.abs_diff(columns[y as usize]) <= 1 {
+            Luma([0])
+        } else {
+            Luma([STRIPES[x as usize % STRIPES.len()]])
+        }
+    });
+    let positions = (0..height).rev().map(|y| Pos(columns[y as usize], y)).collect();
+    (img, Seam::new(positions))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{resize, Carvable};
+
+    #[test]
+    fn finds_the_optimal_seams() {
+        for seed in 0..8 {
+            let (img, seam) = optimal_seam(24, 40, seed);
+            let positions = seam.positions();
+            assert!(positions.windows(2).all(|w| w[0].0.abs_diff(w[1].0) <= 1));
+            let mut carvable = Carvable::new(&img);
+            assert_eq!(carvable.try_remove_seam_returning().unwrap(), seam);
+        }
+    }
+
+    #[test]
+    fn keeps_the_salient_blobs() {
+        let blob = Blob { center: Pos(30, 15), radius: 6 };
+        let img = salient_blobs(60, 30, &[blob], 3);
+        let salient = |img: &GrayImage| img.pixels().filter(|p| p[0] == 0 || p[0] == 255).count();
+        let carved = resize(&img, 40, 30);
+        assert_eq!(salient(&carved), salient(&img));
+        assert_eq!(salient(&img), 113);
+    }
+
+    #[test]
+    fn draws_the_patterns() {
+        let ramp = gradient(6, 2);
+        assert_eq!((ramp[(0, 1)][0], ramp[(5, 1)][0]), (0, 255));
+        let radial = radial_gradient(10, 10);
+        assert!(radial[(5, 5)][0] > radial[(0, 0)][0]);
+        let board = checkerboard(8, 8, 2);
+        assert_eq!((board[(1, 1)][0], board[(2, 1)][0], board[(2, 2)][0]), (0, 255, 0));
+    }
+}
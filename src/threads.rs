@@ -0,0 +1,34 @@
+use alloc::sync::Arc;
+
+use rayon::{ThreadPool, ThreadPoolBuilder};
+
+/// The threads on which the parallel computations of this crate run,
+/// see [resize_buffer_parallel](crate::resize_buffer_parallel)
+///
+/// The threads only change the speed of the computations, never their results.
+#[derive(Clone, Debug, Default)]
+pub enum Threads {
+    /// The rayon thread pool of the caller, which is the global pool
+    /// unless the caller runs inside [ThreadPool::install]
+    #[default]
+    Current,
+    /// A new thread pool with the given number of threads, created at each call.
+    /// If the threads cannot be created, the current pool is used instead.
+    Count(usize),
+    /// An existing thread pool, shared with other computations
+    Pool(Arc<ThreadPool>),
+}
+
+impl Threads {
+    /// Runs `f` on these threads
+    pub(crate) fn install<R: Send>(&self, f: impl FnOnce() -> R + Send) -> R {
+        match self {
+            Threads::Current => f(),
+            Threads::Count(count) => match ThreadPoolBuilder::new().num_threads(*count).build() {
+                Ok(pool) => pool.install(f),
+                Err(_) => f(),
+            },
+            Threads::Pool(pool) => pool.install(f),
+        }
+    }
+}
@@ -0,0 +1,89 @@
+use std::time::{Duration, Instant};
+
+/// Sleeps between the seams of a resize, so that it keeps the processor busy
+/// for at most a fraction of the time, see [Carver::max_cpu](crate::Carver::max_cpu)
+pub(crate) struct Pacer {
+    max_cpu: f32,
+    // When the current stretch of work started
+    busy_since: Instant,
+    // Sleep owed for the work done, too short to be slept yet
+    owed: Duration,
+}
+
+/// Shortest sleep, below which the operating system may not sleep at all
+const MIN_SLEEP: Duration = Duration::from_millis(2);
+
+impl Pacer {
+    pub(crate) fn new(max_cpu: f32) -> Self {
+        Pacer {
+            // NaN is not clamped
+            max_cpu: if max_cpu.is_nan() { 1. } else { max_cpu.clamp(0.01, 1.) },
+            busy_since: Instant::now(),
+            owed: Duration::ZERO,
+        }
+    }
+
+    /// Sleeps long enough for the work done since the last pause
+    /// to be the allowed fraction of the time
+    pub(crate) fn pause(&mut self) {
+        let busy = self.busy_since.elapsed();
+        self.owed += busy.mul_f32((1. - self.max_cpu) / self.max_cpu);
+        if self.owed >= MIN_SLEEP {
+            std::thread::sleep(self.owed);
+            self.owed = Duration::ZERO;
+        }
+        self.busy_since = Instant::now();
+    }
+}
+
+/// Lowers the scheduling priority of the calling thread, so that long batch resizes
+/// give way to the interactive programs of a desktop. Threads started afterwards,
+/// such as the ones of rayon, inherit it. The priority cannot be raised again.
+///
+/// Returns whether the priority was lowered: this is only supported on Unix,
+/// where some systems lower the priority of the whole process instead.
+///
+/// ```no_run
+/// if !seamcarving::lower_priority() {
+///     eprintln!("the priority of the resize cannot be lowered on this system");
+/// }
+/// ```
+pub fn lower_priority() -> bool {
+    #[cfg(unix)]
+    {
+        extern "C" {
+            fn nice(increment: core::ffi::c_int) -> core::ffi::c_int;
+        }
+        // SAFETY: nice only changes the priority of the caller, and takes no pointer
+        let niceness = unsafe { nice(10) };
+        // -1 is also a valid niceness, but only reached from a priority that was raised
+        niceness != -1
+    }
+    #[cfg(not(unix))]
+    {
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sleeps_for_the_work_done() {
+        let mut pacer = Pacer::new(0.5);
+        let start = Instant::now();
+        std::thread::sleep(Duration::from_millis(20));
+        pacer.pause();
+        assert!(start.elapsed() >= Duration::from_millis(40));
+
+        let mut unthrottled = Pacer::new(1.);
+        std::thread::sleep(Duration::from_millis(20));
+        unthrottled.pause();
+        assert_eq!(unthrottled.owed, Duration::ZERO);
+
+        let mut nan = Pacer::new(f32::NAN);
+        nan.pause();
+        assert_eq!(nan.owed, Duration::ZERO);
+    }
+}
@@ -0,0 +1,11 @@
+//! Instrumentation with the [tracing](https://crates.io/crates/tracing) crate,
+//! compiled only when the `tracing` feature is enabled.
+
+/// Enters a span that lasts until the end of the current block.
+/// Without the `tracing` feature, this expands to nothing.
+macro_rules! span {
+    ($level:ident, $name:expr $(, $($fields:tt)*)?) => {
+        #[cfg(feature = "tracing")]
+        let _span = tracing::span!(tracing::Level::$level, $name $(, $($fields)*)?).entered();
+    };
+}
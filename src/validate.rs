@@ -0,0 +1,210 @@
+use image::{GenericImageView, GrayImage};
+
+use crate::cache::Axis;
+use crate::error::ValidationError;
+use crate::importance::ImportanceMap;
+use crate::matrix::Matrix;
+use crate::max_pos;
+use crate::pos::Pos;
+use crate::seam_finder::FORBIDDEN;
+
+/// A mask given to a carver, see [validate_inputs]
+#[derive(Clone, Copy, Debug)]
+#[non_exhaustive]
+pub enum InputMask<'a> {
+    /// Pixels that seams avoid, see [Carvable::protect](crate::Carvable::protect)
+    Protect(&'a GrayImage),
+    /// Pixels that seams never go through, see [Carvable::forbid](crate::Carvable::forbid)
+    Forbid(&'a GrayImage),
+    /// The only pixels seams go through, see [Carver::valid_region](crate::Carver::valid_region)
+    ValidRegion(&'a GrayImage),
+}
+
+impl<'a> InputMask<'a> {
+    fn image(&self) -> &'a GrayImage {
+        match *self {
+            InputMask::Protect(mask) | InputMask::Forbid(mask) | InputMask::ValidRegion(mask) => {
+                mask
+            }
+        }
+    }
+}
+
+/// How seams can go through a pixel, the most constrained one winning
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Constraint {
+    Free,
+    Protected,
+    Forbidden,
+}
+
+/// Checks that masks and importance maps can be used to carve an image,
+/// before spending any time carving it: they must have the dimensions of the image,
+/// masks must be black and white, and at least one pixel of each row and of each column
+/// must be neither protected nor forbidden, so that seams can be removed in both directions.
+///
+/// Only these obvious problems are found: carving can still fail with
+/// [SeamError::Blocked](crate::SeamError::Blocked) when forbidden pixels make walls
+/// that no seam can cross. [Carver::validate_inputs](crate::Carver::validate_inputs)
+/// only checks the directions a carver removes seams in, for the number of seams it removes.
+///
+/// ```
+/// use seamcarving::{validate_inputs, Axis, InputMask, ValidationError};
+/// let img = image::RgbImage::new(20, 10);
+/// let mut mask = image::GrayImage::new(20, 10);
+/// assert_eq!(validate_inputs(&img, &[InputMask::Forbid(&mask)], &[]), Ok(()));
+/// for x in 0..20 {
+///     mask.put_pixel(x, 3, image::Luma([255]));
+/// }
+/// let error = validate_inputs(&img, &[InputMask::Protect(&mask)], &[]);
+/// assert_eq!(error, Err(ValidationError::Protected { axis: Axis::Vertical, line: 3 }));
+/// ```
+pub fn validate_inputs<IMG: GenericImageView>(
+    img: &IMG,
+    masks: &[InputMask],
+    weights: &[ImportanceMap],
+) -> Result<(), ValidationError> {
+    validate(max_pos(img), masks.iter().copied(), weights.iter(), (1, 1))
+}
+
+/// Checks the inputs for the removal of the given numbers of vertical and horizontal seams
+pub(crate) fn validate<'a>(
+    size: Pos,
+    masks: impl Iterator<Item = InputMask<'a>>,
+    weights: impl Iterator<Item = &'a ImportanceMap>,
+    seams: (u32, u32),
+) -> Result<(), ValidationError> {
+    let mut constraints = Matrix::from_fn(size, |_, _| Constraint::Free);
+    for (index, mask) in masks.enumerate() {
+        let image = mask.image();
+        if image.dimensions() != (size.0, size.1) {
+            return Err(ValidationError::MaskSizeMismatch { index });
+        }
+        if let Some((x, y, _)) = image
+            .enumerate_pixels()
+            .find(|(_, _, p)| !matches!(p.0[0], 0 | 255))
+        {
+            return Err(ValidationError::GrayMask { index, x, y });
+        }
+        let constraint = match mask {
+            InputMask::Protect(_) => Constraint::Protected,
+            InputMask::Forbid(_) => Constraint::Forbidden,
+            // Rows without any valid pixel can be carved anywhere
+            InputMask::ValidRegion(_) => continue,
+        };
+        for (x, y, _) in image.enumerate_pixels().filter(|(_, _, p)| p.0[0] != 0) {
+            let pos = Pos(x, y);
+            constraints[pos] = constraints[pos].max(constraint);
+        }
+    }
+    for (index, weights) in weights.enumerate() {
+        if weights.dimensions() != (size.0, size.1) {
+            return Err(ValidationError::WeightsSizeMismatch { index });
+        }
+        for pos in Pos::rows_in_rect(Pos(0, 0), size).flatten() {
+            if weights.bias[pos] == FORBIDDEN {
+                constraints[pos] = Constraint::Forbidden;
+            }
+        }
+    }
+    let check = |axis: Axis, line: u32, seams: u32, positions: Vec<Pos>| {
+        let count = |max| {
+            positions
+                .iter()
+                .filter(|&&pos| constraints[pos] <= max)
+                .count()
+        };
+        if (count(Constraint::Protected) as u32) < seams {
+            return Err(ValidationError::Blocked { axis, line });
+        }
+        if (count(Constraint::Free) as u32) < seams {
+            return Err(ValidationError::Protected { axis, line });
+        }
+        Ok(())
+    };
+    if seams.0 > 0 {
+        for y in 0..size.1 {
+            let row = (0..size.0).map(|x| Pos(x, y)).collect();
+            check(Axis::Vertical, y, seams.0, row)?;
+        }
+    }
+    if seams.1 > 0 {
+        for x in 0..size.0 {
+            let column = (0..size.1).map(|y| Pos(x, y)).collect();
+            check(Axis::Horizontal, x, seams.1, column)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use image::{GrayImage, Luma, RgbImage};
+
+    use super::*;
+    use crate::Carver;
+
+    /// A mask of the given size, set on the pixels for which `f` returns true
+    fn mask(width: u32, height: u32, f: impl Fn(u32, u32) -> bool) -> GrayImage {
+        GrayImage::from_fn(width, height, |x, y| Luma([if f(x, y) { 255 } else { 0 }]))
+    }
+
+    #[test]
+    fn checks_dimensions_and_values() {
+        let img = RgbImage::new(4, 3);
+        let empty = mask(4, 3, |_, _| false);
+        let small = mask(3, 3, |_, _| false);
+        let masks = [InputMask::Protect(&empty), InputMask::Forbid(&small)];
+        let error = validate_inputs(&img, &masks, &[]);
+        assert_eq!(error, Err(ValidationError::MaskSizeMismatch { index: 1 }));
+        let weights = [ImportanceMap::from_mask(&small)];
+        let error = validate_inputs(&img, &[], &weights);
+        assert_eq!(
+            error,
+            Err(ValidationError::WeightsSizeMismatch { index: 0 })
+        );
+        let mut gray = empty.clone();
+        gray.put_pixel(2, 1, Luma([128]));
+        let error = validate_inputs(&img, &[InputMask::ValidRegion(&gray)], &[]);
+        assert_eq!(
+            error,
+            Err(ValidationError::GrayMask {
+                index: 0,
+                x: 2,
+                y: 1
+            })
+        );
+    }
+
+    #[test]
+    fn checks_feasibility() {
+        let img = RgbImage::new(4, 3);
+        let column = mask(4, 3, |x, _| x == 2);
+        let others = mask(4, 3, |x, _| x != 2);
+        let blocked = |axis, line| Err(ValidationError::Blocked { axis, line });
+        let protected = |axis, line| Err(ValidationError::Protected { axis, line });
+        // Seams can only remove the pixels of the third column
+        let masks = [InputMask::Forbid(&others)];
+        let error = validate_inputs(&img, &masks, &[]);
+        assert_eq!(error, blocked(Axis::Horizontal, 0));
+        // Only the vertical seams that a carver removes are checked
+        assert_eq!(
+            Carver::new().width(3).validate_inputs(&img, &masks, &[]),
+            Ok(())
+        );
+        let error = Carver::new().width(2).validate_inputs(&img, &masks, &[]);
+        assert_eq!(error, blocked(Axis::Vertical, 0));
+        let masks = [InputMask::Protect(&others)];
+        let error = validate_inputs(&img, &masks, &[]);
+        assert_eq!(error, protected(Axis::Horizontal, 0));
+        let carver = Carver::new().width(3);
+        assert_eq!(carver.validate_inputs(&img, &masks, &[]), Ok(()));
+        let weights = [ImportanceMap::forbidden(&column)];
+        let error = carver.validate_inputs(&img, &masks, &weights);
+        assert_eq!(error, protected(Axis::Vertical, 0));
+        let error = carver
+            .importance(&weights[0])
+            .validate_inputs(&img, &[], &[]);
+        assert_eq!(error, Ok(()));
+    }
+}
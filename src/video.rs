@@ -0,0 +1,310 @@
+use image::{GenericImageView, GrayImage};
+
+use crate::energy::{energy_fn, luminosity};
+use crate::importance::{combine, ImportanceMap};
+use crate::matrix::Matrix;
+use crate::pos::Pos;
+use crate::seam_finder::{SeamFinder, FORBIDDEN};
+use crate::{image_view_to_buffer, max_pos, Carvable, ImageBufferOf};
+
+/// Reduces the width of the successive frames of a video.
+///
+/// Consecutive frames of a video are often almost identical, so instead of computing
+/// the seams of every frame from scratch, the computations made for the previous frame
+/// are reused, and only the positions whose energy changed by more than a threshold
+/// are computed again. This is much faster for mostly static scenes,
+/// and also makes the seams more stable from one frame to the next.
+///
+/// ```no_run
+/// let mut carver = seamcarving::FrameCarver::new(640, 100);
+/// for i in 0..100 {
+///     let frame = image::open(format!("./frame_{}.png", i)).unwrap();
+///     carver.carve_frame(&frame).save(format!("./carved_{}.png", i)).unwrap();
+/// }
+/// ```
+pub struct FrameCarver {
+    width: u32,
+    threshold: u32,
+    scene_cut: Option<f32>,
+    motion: Option<Motion>,
+    previous: Option<WarmStart>,
+}
+
+/// The energy added to the pixels that move, see [FrameCarver::motion_strength]
+struct Motion {
+    strength: f32,
+    block: u32,
+    // The luminosity of the previous frame, from which the flow is estimated
+    previous: Option<GrayImage>,
+}
+
+/// The seam computations of the previous frame, before any seam was removed
+struct WarmStart {
+    // Energies the cached seams were computed with
+    energies: Matrix<u32>,
+    size: Pos,
+    finder: SeamFinder,
+}
+
+impl FrameCarver {
+    /// Creates a carver that reduces frames to the given width.
+    /// Positions whose energy changed by at most `threshold` since the previous frame
+    /// keep the seams computed for the previous frame.
+    pub fn new(width: u32, threshold: u32) -> Self {
+        FrameCarver {
+            width,
+            threshold,
+            scene_cut: None,
+            motion: None,
+            previous: None,
+        }
+    }
+
+    /// Computes the seams of a frame from scratch, as the first frame of a new scene,
+    /// when the energy of more than `fraction` of its pixels changed by more than the threshold.
+    ///
+    /// Without it, the positions under the threshold keep the energy of the previous scene,
+    /// which the seams of the new scene then avoid.
+    pub fn scene_cut(mut self, fraction: f32) -> Self {
+        self.scene_cut = Some(fraction);
+        self
+    }
+
+    /// Adds the magnitude of the motion of each pixel since the previous frame, in pixels,
+    /// multiplied by `strength`, to its energy: moving subjects are preserved,
+    /// and static backgrounds carved first.
+    ///
+    /// [carve_frame](FrameCarver::carve_frame) estimates the motion by matching blocks
+    /// of 8x8 pixels of consecutive frames, see [FlowField::estimate],
+    /// and [carve_frame_with_flow](FrameCarver::carve_frame_with_flow) takes a flow field
+    /// computed beforehand, for instance by a video decoder or a dedicated library.
+    pub fn motion_strength(mut self, strength: f32) -> Self {
+        self.motion = Some(Motion {
+            strength,
+            block: 8,
+            previous: None,
+        });
+        self
+    }
+
+    /// Removes vertical seams from a frame until it is at most as wide as requested.
+    /// The height of the frame is kept.
+    pub fn carve_frame<IMG: GenericImageView>(&mut self, frame: &IMG) -> ImageBufferOf<IMG>
+    where
+        <IMG as GenericImageView>::Pixel: 'static,
+    {
+        let flow = self.motion.as_mut().and_then(|motion| {
+            let current = luminosity(frame);
+            let previous = motion.previous.replace(current);
+            let previous = previous.filter(|previous| previous.dimensions() == frame.dimensions())?;
+            let current = motion.previous.as_ref().expect("the frame was just kept");
+            Some(FlowField::estimate(&previous, current, motion.block))
+        });
+        self.carve(frame, flow.as_ref())
+    }
+
+    /// Removes vertical seams from a frame like [carve_frame](FrameCarver::carve_frame),
+    /// with the motion of its pixels since the previous frame given by a flow field
+    /// instead of being estimated, see [motion_strength](FrameCarver::motion_strength).
+    /// The flow is ignored without a motion strength.
+    ///
+    /// # Panics
+    ///
+    /// If the flow field does not have the dimensions of the frame
+    pub fn carve_frame_with_flow<IMG: GenericImageView>(
+        &mut self,
+        frame: &IMG,
+        flow: &FlowField,
+    ) -> ImageBufferOf<IMG>
+    where
+        <IMG as GenericImageView>::Pixel: 'static,
+    {
+        assert_eq!(
+            flow.dimensions(),
+            frame.dimensions(),
+            "the flow field must have the dimensions of the frame"
+        );
+        if let Some(motion) = &mut self.motion {
+            // An estimate for the next frame starts from this one
+            motion.previous = Some(luminosity(frame));
+        }
+        self.carve(frame, Some(flow))
+    }
+
+    fn carve<IMG: GenericImageView>(
+        &mut self,
+        frame: &IMG,
+        flow: Option<&FlowField>,
+    ) -> ImageBufferOf<IMG>
+    where
+        <IMG as GenericImageView>::Pixel: 'static,
+    {
+        let size = max_pos(frame);
+        let strength = self.motion.as_ref().map(|motion| motion.strength);
+        let bias = strength.zip(flow).map(|(strength, flow)| {
+            Matrix::from_fn(size, |x, y| {
+                let motion = flow.magnitude(x as u32, y as u32) * strength;
+                // Saturates without forbidding the pixel, and NaN becomes 0
+                (motion.max(0.) as u32).min(FORBIDDEN - 1)
+            })
+        });
+        let energy = |pos: Pos| {
+            let energy = energy_fn(frame, pos);
+            bias.as_ref().map_or(energy, |bias| combine(energy, bias[pos]))
+        };
+        let fresh = || WarmStart {
+            energies: Matrix::from_fn(size, |x, y| energy(Pos(x as u32, y as u32))),
+            size,
+            finder: SeamFinder::new(size),
+        };
+        let mut warm = match self.previous.take() {
+            Some(mut warm) if warm.size == size => {
+                let threshold = self.threshold;
+                let energies = &mut warm.energies;
+                let changed: Vec<Pos> = Pos::iter_in_rect(Pos(0, 0), size)
+                    .filter(|&pos| {
+                        let energy = energy(pos);
+                        let moved = (i64::from(energy) - i64::from(energies[pos])).abs()
+                            > i64::from(threshold);
+                        if moved {
+                            energies[pos] = energy;
+                        }
+                        moved
+                    })
+                    .collect();
+                let cut = self
+                    .scene_cut
+                    .is_some_and(|fraction| changed.len() as f32 > fraction * size.area() as f32);
+                if cut {
+                    fresh()
+                } else {
+                    warm.finder.invalidate_positions(changed);
+                    warm
+                }
+            }
+            _ => fresh(),
+        };
+        let WarmStart {
+            energies, finder, ..
+        } = &mut warm;
+        finder.fill(|pos| energies[pos]);
+        let finder = warm.finder.clone();
+        self.previous = Some(warm);
+
+        let mut carvable = Carvable::new(frame);
+        if let Some(bias) = bias {
+            // The moving pixels stay expensive as the seams are removed
+            carvable.add_importance(&ImportanceMap::from_bias(size, bias));
+        }
+        carvable.seam_finder = finder;
+        while carvable.carved.width() > self.width {
+            carvable.remove_seam();
+        }
+        image_view_to_buffer(carvable.result())
+    }
+}
+
+/// The motion of each pixel of a frame of a video since the previous frame, in pixels,
+/// see [FrameCarver::motion_strength]
+#[derive(Clone, Debug)]
+pub struct FlowField {
+    vectors: Matrix<(f32, f32)>,
+}
+
+impl FlowField {
+    /// A flow field from the horizontal and vertical motions of the pixels, row by row,
+    /// or None if there are not `width * height` of them
+    pub fn new(width: u32, height: u32, vectors: Vec<(f32, f32)>) -> Option<Self> {
+        let vectors = Matrix::from_vec(Pos(width, height), vectors)?;
+        Some(FlowField { vectors })
+    }
+
+    /// A cheap estimate of the motion between two frames of the same dimensions:
+    /// each block of `block` by `block` pixels of the current frame is matched
+    /// with the most similar block of the previous frame, up to half a block away,
+    /// comparing their luminosities. All the pixels of a block have its motion,
+    /// and flat regions, which match everywhere, do not move.
+    ///
+    /// ```
+    /// use image::{GrayImage, Luma};
+    /// use seamcarving::FlowField;
+    /// // A bright square moving 3 pixels to the right
+    /// let frame = |left| GrayImage::from_fn(32, 16, move |x, y| {
+    ///     Luma([if (left..left + 8).contains(&x) && (4..12).contains(&y) { 255 } else { 0 }])
+    /// });
+    /// let flow = FlowField::estimate(&frame(8), &frame(11), 8);
+    /// assert_eq!(flow.vector(12, 8), (3., 0.));
+    /// assert_eq!(flow.magnitude(28, 8), 0.);
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// If the frames do not have the same dimensions
+    pub fn estimate<A: GenericImageView, B: GenericImageView>(
+        previous: &A,
+        current: &B,
+        block: u32,
+    ) -> Self {
+        assert_eq!(
+            previous.dimensions(),
+            current.dimensions(),
+            "the frames must have the same dimensions"
+        );
+        let (previous, current) = (luminosity(previous), luminosity(current));
+        let (width, height) = current.dimensions();
+        let block = block.max(1);
+        let radius = (block / 2).max(1) as i64;
+        let luma = |img: &GrayImage, x: i64, y: i64| {
+            // The pixels outside of the image are the ones on its edge
+            let x = x.clamp(0, i64::from(width) - 1) as u32;
+            let y = y.clamp(0, i64::from(height) - 1) as u32;
+            i64::from(img.get_pixel(x, y).0[0])
+        };
+        let blocks = Pos(width.div_ceil(block), height.div_ceil(block));
+        let motions = Matrix::from_fn(blocks, |bx, by| {
+            let (left, top) = (bx as i64 * i64::from(block), by as i64 * i64::from(block));
+            let right = (left + i64::from(block)).min(i64::from(width));
+            let bottom = (top + i64::from(block)).min(i64::from(height));
+            // The displacement to the most similar block, the shortest one winning ties
+            let mut best = (i64::MAX, i64::MAX, 0, 0);
+            for dy in -radius..=radius {
+                for dx in -radius..=radius {
+                    let mut difference = 0;
+                    for y in top..bottom {
+                        for x in left..right {
+                            let diff = luma(&current, x, y) - luma(&previous, x + dx, y + dy);
+                            difference += diff.abs();
+                        }
+                    }
+                    let candidate = (difference, dx * dx + dy * dy, dx, dy);
+                    if candidate < best {
+                        best = candidate;
+                    }
+                }
+            }
+            // The pixels of the block came from the displaced block
+            let (_, _, dx, dy) = best;
+            (-dx as f32, -dy as f32)
+        });
+        let vectors = Matrix::from_fn(Pos(width, height), |x, y| {
+            motions[Pos(x as u32 / block, y as u32 / block)]
+        });
+        FlowField { vectors }
+    }
+
+    /// Width and height of the frame of the flow field
+    pub fn dimensions(&self) -> (u32, u32) {
+        (self.vectors.width(), self.vectors.height())
+    }
+
+    /// The horizontal and vertical motion of a pixel, in pixels
+    pub fn vector(&self, x: u32, y: u32) -> (f32, f32) {
+        self.vectors[Pos(x, y)]
+    }
+
+    /// The distance a pixel moved, in pixels
+    pub fn magnitude(&self, x: u32, y: u32) -> f32 {
+        let (dx, dy) = self.vector(x, y);
+        dx.hypot(dy)
+    }
+}
@@ -19,5 +19,20 @@ fn resize_100x100_to_1x1() {
     let img = open_image();
     let resized = resize(&img, 1, 1);
     assert_eq!(resized.dimensions(), (1, 1));
-    assert_eq!(resized.into_raw(), vec![63, 67, 69, 255]);
-}
\ No newline at end of file
+    assert_eq!(resized.into_raw(), vec![0, 0, 2, 255]);
+}
+#[test]
+fn resizes_like_fresh_carvers() {
+    // Every resize by one pixel computes the seam energies of the whole image again
+    let mut img = open_image();
+    let (mut width, mut height) = img.dimensions();
+    while width > 1 {
+        width -= 1;
+        img = DynamicImage::ImageRgba8(resize(&img, width, height));
+    }
+    while height > 1 {
+        height -= 1;
+        img = DynamicImage::ImageRgba8(resize(&img, width, height));
+    }
+    assert_eq!(img.to_rgba8(), resize(&open_image(), 1, 1));
+}
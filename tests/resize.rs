@@ -1,6 +1,15 @@
-use image::{GrayImage, ImageBuffer, Luma};
+use std::ops::ControlFlow;
+use std::time::Duration;
 
-use seamcarving::resize;
+use image::{GenericImageView, GrayImage, ImageBuffer, Luma, LumaA, Rgb, Rgba};
+
+use seamcarving::{
+    apply_seam, enlarge, resize, resize_with_importance, resize_with_mask,
+    resize_with_max_distortion, resize_within_region, retarget, retarget_aspect, Algorithm,
+    Annotation, Axis, AxisOrder, BorderMode, Carvable, Carver, Checkpoint, CheckpointError,
+    Dimension, FlowField, FrameCarver, ImportanceMap, OverBudget, Pos, Rect, SeamCache,
+    SeamError, SeamIndexMap,
+};
 
 fn pi_img_8_3() -> ImageBuffer<Luma<u8>, Vec<u8>> {
     GrayImage::from_raw(
@@ -83,3 +92,1014 @@ fn single_pixel() {
     assert_eq!(resized.dimensions(), (0, 0));
     assert_eq!(resized.into_raw(), vec![]);
 }
+
+#[test]
+fn mask_protects_pixels() {
+    let img = GrayImage::from_raw(3, 2, vec![10, 20, 30, 10, 20, 30]).unwrap();
+    let resized = resize(&img, 2, 2);
+    assert_eq!(resized.into_raw(), vec![20, 30, 20, 30]);
+    let mask = GrayImage::from_raw(3, 2, vec![255, 0, 0, 255, 0, 0]).unwrap();
+    let resized = resize_with_mask(&img, 2, 2, &mask);
+    assert_eq!(resized.into_raw(), vec![10, 20, 10, 20]);
+}
+
+#[test]
+fn removes_seam_through_anchors() {
+    let img = pi_img_8_3();
+    let mut carvable = Carvable::new(&img);
+    carvable.remove_seam_through(&[(0, 1)]).unwrap();
+    let carved: GrayImage = carvable.result().into();
+    assert_eq!(carved.get_pixel(0, 1), &Luma([2]));
+    assert_eq!(
+        carvable.remove_seam_through(&[(0, 0), (5, 2)]),
+        Err(SeamError::UnreachableAnchors)
+    );
+    assert_eq!(
+        carvable.remove_seam_through(&[(7, 0)]),
+        Err(SeamError::OutOfBounds { x: 7, y: 0 })
+    );
+    assert_eq!(carvable.result().dimensions(), (7, 3));
+}
+
+#[test]
+fn forbidden_pixels_are_never_removed() {
+    let img = GrayImage::from_raw(3, 2, vec![10, 20, 30, 10, 20, 30]).unwrap();
+    let forbidden = GrayImage::from_raw(3, 2, vec![255, 0, 255, 0, 0, 255]).unwrap();
+    let importance = ImportanceMap::forbidden(&forbidden);
+    let resized = resize_with_importance(&img, 2, 2, &importance).unwrap();
+    assert_eq!(resized.into_raw(), vec![10, 30, 20, 30]);
+    let result = resize_with_importance(&img, 1, 2, &importance);
+    assert_eq!(result.err(), Some(SeamError::Blocked));
+}
+
+#[test]
+fn center_bias_carves_the_periphery_first() {
+    // The same gradient everywhere, so that only the prior tells the seams apart
+    let img = GrayImage::from_fn(30, 10, |x, _y| Luma([(x * 8) as u8]));
+    let prior = ImportanceMap::center_bias(30, 10, 1000., 2.);
+    let resized = resize_with_importance(&img, 20, 10, &prior).unwrap();
+    let row: Vec<u8> = (0..20).map(|x| resized.get_pixel(x, 5).0[0]).collect();
+    // Both sides lost columns, and the middle of the image is intact
+    assert!(row[0] > 0 && row[19] < 29 * 8);
+    assert!(row.windows(2).any(|pair| pair == [14 * 8, 15 * 8]));
+}
+
+#[test]
+fn border_modes_change_the_energy_of_the_borders() {
+    // The same gradient everywhere, and very different left and right borders
+    let img = GrayImage::from_fn(20, 10, |x, _y| Luma([(x * 12) as u8]));
+    let first_column = |border| {
+        let carver = Carver::new().width(15).border_mode(border);
+        let resized = carver.resize(&img).unwrap();
+        (0..10).map(|y| resized.get_pixel(0, y).0[0]).collect::<Vec<u8>>()
+    };
+    // Clamped and mirrored borders are cheaper than the inside of the image
+    assert_ne!(first_column(BorderMode::Clamp), vec![0; 10]);
+    assert_ne!(first_column(BorderMode::Mirror), vec![0; 10]);
+    // Wrapped ones are compared with the opposite border, much brighter or darker
+    assert_eq!(first_column(BorderMode::Wrap), vec![0; 10]);
+}
+
+#[test]
+fn throttled_resizes_give_the_same_image() {
+    let img = GrayImage::from_fn(40, 20, |x, y| Luma([(x * x + y * 13) as u8]));
+    let carver = Carver::new().width(30).height(16);
+    let throttled = carver.clone().max_cpu(0.5).resize(&img).unwrap();
+    assert_eq!(throttled, carver.resize(&img).unwrap());
+    for fraction in [f32::NAN, f32::INFINITY] {
+        assert_eq!(carver.clone().max_cpu(fraction).resize(&img).unwrap(), throttled);
+    }
+}
+
+#[test]
+fn wrapped_borders_carve_like_one_seam_at_a_time() {
+    for seed in 0..20u32 {
+        let img = GrayImage::from_fn(20, 12, |x, y| {
+            Luma([((x * 37 + y * 101 + seed * 59) ^ (x * y * seed)) as u8])
+        });
+        let carver = |width| Carver::new().width(width).border_mode(BorderMode::Wrap);
+        let one_pass = carver(10).resize(&img).unwrap();
+        let mut seam_by_seam = img.clone();
+        for width in (10..20).rev() {
+            seam_by_seam = carver(width).resize(&seam_by_seam).unwrap();
+        }
+        assert_eq!(one_pass, seam_by_seam, "seed {}", seed);
+    }
+}
+
+#[test]
+fn carves_only_inside_the_region() {
+    let img = GrayImage::from_fn(12, 6, |x, y| Luma([(x * 20 + y) as u8]));
+    let left = Rect { x: 0, y: 0, width: 4, height: 6 };
+    let carver = Carver::new().width(9).carve_region(left);
+    let resized = carver.resize(&img).unwrap();
+    assert_eq!(resized.dimensions(), (9, 6));
+    // The pixels right of the region are only shifted left
+    let outside = |(x, y, p): (u32, u32, &Luma<u8>)| x < 1 || p == img.get_pixel(x + 3, y);
+    assert!(resized.enumerate_pixels().all(outside));
+    // Each stripe of a striped resize carves its part of the region
+    let max_memory = carver.plan(&img).peak_memory / 3;
+    let (striped, report) = carver.clone().max_memory(max_memory).resize_with_report(&img).unwrap();
+    assert!(report.striped);
+    assert!(striped.enumerate_pixels().all(outside));
+    // Vertical seams cannot avoid the rows above and below the region
+    let middle = Rect { x: 0, y: 2, width: 12, height: 2 };
+    let result = Carver::new().width(9).carve_region(middle).resize(&img);
+    assert_eq!(result.err(), Some(SeamError::Blocked));
+}
+
+#[test]
+fn never_carves_pinned_lines() {
+    // The flat left columns and bottom rows are the cheapest to remove
+    let img = GrayImage::from_fn(12, 8, |x, y| {
+        Luma([if x < 3 || y >= 6 { 100 } else { ((x * 37 + y * 53) % 251) as u8 }])
+    });
+    let column = |img: &GrayImage, x| -> Vec<u8> {
+        (0..img.height()).map(|y| img.get_pixel(x, y).0[0]).collect()
+    };
+    let row = |img: &GrayImage, y| -> Vec<u8> {
+        (0..img.width()).map(|x| img.get_pixel(x, y).0[0]).collect()
+    };
+    let unpinned = Carver::new().width(9).resize(&img).unwrap();
+    assert_ne!(column(&unpinned, 1), column(&img, 1));
+    let resized = Carver::new().width(9).pin_columns([0, 1]).resize(&img).unwrap();
+    assert_eq!(column(&resized, 0), column(&img, 0));
+    assert_eq!(column(&resized, 1), column(&img, 1));
+    let unpinned = Carver::new().height(6).resize(&img).unwrap();
+    assert_ne!(row(&unpinned, 5), row(&img, 7));
+    let resized = Carver::new().height(6).pin_rows(vec![7]).resize(&img).unwrap();
+    assert_eq!(row(&resized, 5), row(&img, 7));
+    let blocked = Carver::new().width(10).pin_columns(1..12).resize(&img);
+    assert_eq!(blocked.err(), Some(SeamError::Blocked));
+}
+
+#[test]
+fn carves_equirectangular_images_near_the_poles() {
+    // The rows near the poles are busier than the others
+    let img = GrayImage::from_fn(16, 20, |x, y| {
+        let contrast = if (2..18).contains(&y) { 120 } else { 200 };
+        Luma([((x * 37 + y * 11) % contrast) as u8])
+    });
+    let row = |img: &GrayImage, y| -> Vec<u8> {
+        (0..img.width()).map(|x| img.get_pixel(x, y).0[0]).collect()
+    };
+    let flat = Carver::new().height(18).resize(&img).unwrap();
+    assert_eq!(row(&flat, 0), row(&img, 0));
+    let resized = Carver::new().height(18).equirectangular(true).resize(&img).unwrap();
+    assert_eq!(resized.dimensions(), (16, 18));
+    for y in 0..18 {
+        assert_eq!(row(&resized, y), row(&img, y + 1));
+    }
+}
+
+#[test]
+fn carves_only_the_valid_region() {
+    let img = GrayImage::from_raw(5, 2, vec![0, 10, 10, 80, 200, 0, 10, 10, 80, 200]).unwrap();
+    let resized = resize(&img, 4, 2);
+    assert_eq!(resized.into_raw(), vec![10, 10, 80, 200, 10, 10, 80, 200]);
+    let valid = GrayImage::from_raw(5, 2, vec![0, 1, 1, 1, 1, 0, 1, 1, 1, 1]).unwrap();
+    let resized = resize_within_region(&img, 4, 2, &valid).unwrap();
+    assert_eq!(resized.into_raw(), vec![0, 10, 80, 200, 0, 10, 80, 200]);
+}
+
+#[test]
+fn spreads_removed_seams() {
+    // A smooth ramp on the left, a busy pattern on the right
+    let img = GrayImage::from_fn(12, 3, |x, y| {
+        Luma([if x < 6 { x as u8 } else { ((x * 97 + y * 31) % 256) as u8 }])
+    });
+    let resized = resize_with_max_distortion(&img, 9, 3, 1, 1).unwrap();
+    for (y, row) in resized.rows().enumerate() {
+        let kept: Vec<u8> = row.map(|p| p.0[0]).collect();
+        let removed: Vec<u32> = (0..12)
+            .filter(|&x| !kept.contains(&img.get_pixel(x, y as u32).0[0]))
+            .collect();
+        assert!(removed.windows(2).all(|w| w[1] - w[0] > 1), "{:?}", removed);
+    }
+    assert_eq!(
+        resize_with_max_distortion(&img, 4, 3, 1, 1),
+        Err(SeamError::Blocked)
+    );
+}
+
+#[test]
+fn retargets_to_aspect_ratio() {
+    let img = GrayImage::from_fn(16, 9, |x, y| Luma([(x * 13 + y * 7) as u8]));
+    assert_eq!(retarget_aspect(&img, (1, 1)).dimensions(), (9, 9));
+    assert_eq!(retarget_aspect(&img, (4, 3)).dimensions(), (12, 9));
+    assert_eq!(retarget_aspect(&img, (2, 1)).dimensions(), (16, 8));
+    assert_eq!(retarget_aspect(&img, (16, 9)).dimensions(), (16, 9));
+}
+
+#[test]
+fn resizes_by_percentage() {
+    let img = GrayImage::from_fn(20, 10, |x, y| Luma([(x * 13 + y * 7) as u8]));
+    let carver = Carver::new().width(Dimension::Percent(80)).shrink_height_by(0.3);
+    assert_eq!(carver.target_dimensions(&img), (16, 7));
+    assert_eq!(carver.resize(&img).unwrap().dimensions(), (16, 7));
+    let carver = Carver::new().width(15);
+    assert_eq!(carver.resize(&img).unwrap(), resize(&img, 15, 10));
+}
+
+#[test]
+fn resizes_to_several_widths_at_once() {
+    let img = GrayImage::from_fn(20, 6, |x, y| Luma([(x * x * 3 + y * 19) as u8]));
+    let versions = Carver::new().resize_widths(&img, &[15, 20, 8]).unwrap();
+    assert_eq!(versions.len(), 3);
+    assert_eq!(versions[0], resize(&img, 15, 6));
+    assert_eq!(versions[1], img);
+    assert_eq!(versions[2], resize(&img, 8, 6));
+}
+
+#[test]
+fn renders_the_seam_index_map_at_any_width() {
+    let img = GrayImage::from_fn(20, 6, |x, y| Luma([(x * x * 3 + y * 19) as u8]));
+    let map = Carver::new().seam_index_map(&img, 8).unwrap();
+    let map = SeamIndexMap::from_bytes(&map.to_bytes()).unwrap();
+    for width in [20, 15, 8] {
+        assert_eq!(map.render_image(&img, width).unwrap(), resize(&img, width, 6));
+    }
+    assert_eq!(map.render_image(&img, 3).unwrap().dimensions(), (8, 6));
+}
+
+#[test]
+fn reuses_cached_seams() {
+    let dir = std::env::temp_dir().join(format!("seamcarving-cache-{}", std::process::id()));
+    let cache = SeamCache::new(&dir);
+    let img = GrayImage::from_fn(20, 12, |x, y| Luma([(x * x * 3 + y * y * 5) as u8]));
+    let carver = Carver::new().width(14).height(9);
+    let expected = carver.resize(&img).unwrap();
+    assert_eq!(carver.resize_cached(&img, &cache).unwrap(), expected);
+    assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 1);
+    // Served from the cache
+    assert_eq!(carver.resize_cached(&img, &cache).unwrap(), expected);
+    // With the seams of the mirrored image in the entry, the result only changes if it is hit
+    let entry = std::fs::read_dir(&dir).unwrap().next().unwrap().unwrap().path();
+    let mirrored_dir = dir.with_extension("mirrored");
+    let mirrored = image::imageops::flip_horizontal(&img);
+    carver.resize_cached(&mirrored, &SeamCache::new(&mirrored_dir)).unwrap();
+    let mirrored_entry = std::fs::read_dir(&mirrored_dir).unwrap().next().unwrap().unwrap();
+    std::fs::copy(mirrored_entry.path(), &entry).unwrap();
+    std::fs::remove_dir_all(mirrored_dir).unwrap();
+    let replayed = carver.resize_cached(&img, &cache).unwrap();
+    assert_eq!(replayed.dimensions(), expected.dimensions());
+    assert_ne!(replayed, expected);
+    std::fs::remove_file(entry).unwrap();
+    assert_eq!(carver.resize_cached(&img, &cache).unwrap(), expected);
+    // Different settings do not reuse the same entry
+    let other = Carver::new().width(10);
+    assert_eq!(other.resize_cached(&img, &cache).unwrap(), other.resize(&img).unwrap());
+    assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 2);
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn carves_video_frames() {
+    let frame = |t: u32| {
+        GrayImage::from_fn(20, 8, move |x, y| {
+            // A static background with a small moving square
+            let square = (t..t + 3).contains(&x) && (2..5).contains(&y);
+            Luma([if square { 255 } else { (x * x + y * 7) as u8 }])
+        })
+    };
+    let mut carver = FrameCarver::new(15, 0);
+    assert_eq!(carver.carve_frame(&frame(0)), resize(&frame(0), 15, 8));
+    // Without any tolerance, reusing the previous frame gives the same result
+    assert_eq!(carver.carve_frame(&frame(0)), resize(&frame(0), 15, 8));
+    for t in 1..5 {
+        assert_eq!(carver.carve_frame(&frame(t)).dimensions(), (15, 8));
+    }
+}
+
+#[test]
+fn starts_again_on_scene_cuts() {
+    // A flat left half, and noise on the right
+    let first = GrayImage::from_fn(20, 8, |x, y| match x {
+        0..=9 => Luma([0]),
+        _ => Luma([((x * 37 + y * 91) % 256) as u8]),
+    });
+    // A gentle gradient on the left, whose energy is under the threshold, and a flat right half
+    let cut = GrayImage::from_fn(20, 8, |x, _y| Luma([if x < 10 { x as u8 * 10 } else { 128 }]));
+    let mut carver = FrameCarver::new(15, 2000);
+    carver.carve_frame(&first);
+    assert_ne!(carver.carve_frame(&cut), resize(&cut, 15, 8));
+    let mut carver = FrameCarver::new(15, 2000).scene_cut(0.4);
+    carver.carve_frame(&first);
+    assert_eq!(carver.carve_frame(&cut), resize(&cut, 15, 8));
+}
+
+#[test]
+fn preserves_moving_subjects() {
+    // A textured background, with a flat band moving to the right on the left of the frames
+    let frame = |t: u32| GrayImage::from_fn(32, 16, move |x, y| {
+        if (2 + t..10 + t).contains(&x) {
+            Luma([128])
+        } else {
+            Luma([((x * 37 + y * 91) % 4 * 20) as u8])
+        }
+    });
+    let band = |img: &GrayImage| img.pixels().filter(|pixel| pixel.0 == [128]).count();
+    let mut carver = FrameCarver::new(26, 0);
+    carver.carve_frame(&frame(0));
+    assert!(band(&carver.carve_frame(&frame(1))) < 128);
+    let mut carver = FrameCarver::new(26, 0).motion_strength(100_000.);
+    carver.carve_frame(&frame(0));
+    assert_eq!(band(&carver.carve_frame(&frame(1))), 128);
+    // The same motion, given instead of estimated
+    let motion = |i: u32| if (3..11).contains(&(i % 32)) { (1., 0.) } else { (0., 0.) };
+    let flow = FlowField::new(32, 16, (0..32 * 16).map(motion).collect()).unwrap();
+    let mut carver = FrameCarver::new(26, 0).motion_strength(100_000.);
+    assert_eq!(band(&carver.carve_frame_with_flow(&frame(1), &flow)), 128);
+    assert!(FlowField::new(32, 15, Vec::new()).is_none());
+}
+
+#[test]
+fn scales_when_seams_are_too_visible() {
+    // Noise everywhere: every seam is expensive
+    let img = GrayImage::from_fn(20, 10, |x, y| Luma([((x * 97 + y * 57) % 7 * 40) as u8]));
+    let carver = Carver::new().width(15).height(8);
+    let (resized, report) = carver.resize_with_report(&img).unwrap();
+    assert_eq!(resized.dimensions(), (15, 8));
+    assert_eq!((report.seams, report.scaled), ((5, 2), (0, 0)));
+    let (resized, report) = carver.max_seam_energy(0).resize_with_report(&img).unwrap();
+    assert_eq!(resized.dimensions(), (15, 8));
+    assert_eq!((report.seams, report.scaled), ((0, 0), (5, 2)));
+}
+
+#[test]
+fn alternates_axes_greedily() {
+    // Horizontal stripes: removing rows is free, removing columns is not
+    let img = GrayImage::from_fn(12, 12, |x, y| Luma([((y / 3) * 60 + x % 2 * 90) as u8]));
+    let carver = Carver::new().width(10).height(8).axis_order(AxisOrder::Greedy);
+    let (resized, report) = carver.resize_with_report(&img).unwrap();
+    assert_eq!(resized.dimensions(), (10, 8));
+    assert_eq!(report.seams, (2, 4));
+    let dir = std::env::temp_dir().join(format!("seamcarving-greedy-{}", std::process::id()));
+    let cache = SeamCache::new(&dir);
+    assert_eq!(carver.resize_cached(&img, &cache).unwrap(), resized);
+    assert_eq!(carver.resize_cached(&img, &cache).unwrap(), resized);
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn stops_when_out_of_time() {
+    let img = GrayImage::from_fn(30, 20, |x, y| Luma([(x * x + y * 3) as u8]));
+    let no_time = Duration::from_secs(0);
+    let carver = Carver::new().width(20).height(15);
+    let (resized, report) = carver
+        .clone()
+        .time_budget(no_time, OverBudget::Stop)
+        .resize_with_report(&img)
+        .unwrap();
+    assert_eq!(resized, img);
+    assert!(report.out_of_time);
+    let late = carver.clone().time_budget(no_time, OverBudget::Scale);
+    let (resized, report) = late.resize_with_report(&img).unwrap();
+    assert_eq!(resized.dimensions(), (20, 15));
+    assert_eq!((report.seams, report.scaled), ((0, 0), (10, 5)));
+    let plenty = carver.time_budget(Duration::from_secs(3600), OverBudget::Stop);
+    let (resized, report) = plenty.resize_with_report(&img).unwrap();
+    assert_eq!(resized, resize(&img, 20, 15));
+    assert!(!report.out_of_time);
+}
+
+#[test]
+fn stops_when_out_of_energy() {
+    // Flat on the left, noisy on the right
+    let img = GrayImage::from_fn(30, 10, |x, y| match x {
+        0..=9 => Luma([50]),
+        _ => Luma([((x * 97 + y * 57) % 7 * 40) as u8]),
+    });
+    let carver = Carver::new().width(15);
+    let (resized, report) = carver.resize_with_report(&img).unwrap();
+    assert_eq!(resized, resize(&img, 15, 10));
+    assert!(!report.out_of_energy && report.removed_energy > 0);
+    assert_eq!((report.size, report.requested), ((15, 10), (15, 10)));
+
+    // Only the flat seams fit in the budget
+    for order in [AxisOrder::WidthFirst, AxisOrder::Greedy] {
+        let budgeted = carver.clone().axis_order(order).energy_budget(1000);
+        let (resized, report) = budgeted.resize_with_report(&img).unwrap();
+        assert!(report.out_of_energy && report.removed_energy <= 1000);
+        assert_eq!(report.scaled, (0, 0));
+        assert_eq!(report.size, resized.dimensions());
+        assert_eq!(report.requested, (15, 10));
+        assert!(resized.width() > 15 && resized.width() <= 22);
+    }
+}
+
+#[test]
+fn calls_back_after_each_seam() {
+    let img = GrayImage::from_fn(20, 12, |x, y| Luma([((x * 13 + y * 29) % 64 * 4) as u8]));
+    for order in [AxisOrder::WidthFirst, AxisOrder::Greedy] {
+        let carver = Carver::new().width(15).height(9).axis_order(order);
+        let mut calls = Vec::new();
+        let (resized, report) = carver
+            .resize_with_callback(&img, |seam, stats| {
+                calls.push((seam.to_vec(), stats));
+                ControlFlow::Continue(())
+            })
+            .unwrap();
+        assert_eq!(resized, carver.resize(&img).unwrap());
+        assert_eq!(calls.len(), 8);
+        assert!(!report.stopped);
+        let (mut width, mut height) = (20, 12);
+        for (seam, stats) in &calls {
+            match stats.axis {
+                // One position per row, or per column
+                Axis::Vertical => {
+                    let mut rows: Vec<u32> = seam.iter().map(|&Pos(_x, y)| y).collect();
+                    rows.sort_unstable();
+                    assert_eq!(rows, (0..height).collect::<Vec<_>>());
+                    assert!(seam.iter().all(|&Pos(x, _y)| x < width));
+                    width -= 1;
+                }
+                Axis::Horizontal => {
+                    let mut columns: Vec<u32> = seam.iter().map(|&Pos(x, _y)| x).collect();
+                    columns.sort_unstable();
+                    assert_eq!(columns, (0..width).collect::<Vec<_>>());
+                    assert!(seam.iter().all(|&Pos(_x, y)| y < height));
+                    height -= 1;
+                }
+            }
+            assert_eq!(stats.size, (width, height));
+            assert_eq!(stats.removed, (20 - width, 12 - height));
+        }
+        let total: u64 = calls.iter().map(|(_, stats)| u64::from(stats.energy)).sum();
+        assert_eq!(total, report.removed_energy);
+
+        // Stopping after three seams
+        let (resized, report) = carver
+            .resize_with_callback(&img, |_seam, stats| {
+                if stats.removed.0 + stats.removed.1 < 3 {
+                    ControlFlow::Continue(())
+                } else {
+                    ControlFlow::Break(())
+                }
+            })
+            .unwrap();
+        assert!(report.stopped);
+        assert_eq!(report.seams.0 + report.seams.1, 3);
+        assert_eq!(resized.width() + resized.height(), 32 - 3);
+    }
+}
+
+#[test]
+fn plans_without_carving() {
+    let img = GrayImage::from_fn(40, 30, |x, y| Luma([(x * y) as u8]));
+    let carver = Carver::new().width(30).height(Dimension::Percent(50));
+    let plan = carver.plan(&img);
+    assert_eq!(plan.seams, (10, 15));
+    assert!(!plan.may_scale);
+    assert!(plan.peak_memory >= 40 * 30);
+    assert_eq!(plan.size, (30, 15));
+    assert!(plan.estimated_time() > Duration::ZERO);
+    let json = r#"{"width":30,"height":15,"vertical_seams":10,"horizontal_seams":15,"#;
+    assert!(plan.to_json().starts_with(json));
+    let (_, report) = carver.resize_with_report(&img).unwrap();
+    assert_eq!(report.seams, plan.seams);
+    let greedy = carver.clone().axis_order(AxisOrder::Greedy).plan(&img);
+    assert!(greedy.cost > plan.cost);
+    assert!(carver.max_seam_energy(100).plan(&img).may_scale);
+}
+
+#[test]
+fn blends_seam_edges() {
+    // A smooth gradient, like a sky, between detailed borders
+    let img = GrayImage::from_fn(40, 10, |x, y| match x {
+        5..=34 => Luma([(x * 6) as u8]),
+        _ => Luma([((x + y) % 2 * 255) as u8]),
+    });
+    let max_step = |img: &GrayImage| {
+        let (width, height) = img.dimensions();
+        (0..height)
+            .flat_map(|y| (6..width - 6).map(move |x| (x, y)))
+            .map(|(x, y)| (img[(x, y)].0[0] as i32 - img[(x - 1, y)].0[0] as i32).abs())
+            .max()
+            .unwrap()
+    };
+    // More seams than half of the gradient, so that some of them are next to each other
+    // whatever seams the energies pick: with the energies updated exactly after each seam,
+    // the 10 seams of a width of 30 are all apart, and there is no seam edge to blend
+    let carver = Carver::new().width(15);
+    let sharp = carver.resize(&img).unwrap();
+    let blended = carver.clone().blend_seams(true).resize(&img).unwrap();
+    assert_eq!(blended.dimensions(), (15, 10));
+    assert!(max_step(&blended) < max_step(&sharp));
+
+    let cache = SeamCache::new(std::env::temp_dir().join("seamcarving-blend-test"));
+    let blending = carver.blend_seams(true);
+    blending.resize_cached(&img, &cache).unwrap();
+    assert_eq!(blending.resize_cached(&img, &cache).unwrap(), blended);
+}
+
+#[test]
+fn blends_the_seams_of_empty_images() {
+    let img = GrayImage::from_fn(8, 5, |x, y| Luma([(x * 30 + y) as u8]));
+    let narrow = Carver::new().width(0).blend_seams(true).resize(&img).unwrap();
+    assert_eq!(narrow.dimensions(), (0, 5));
+    let flat = Carver::new().height(0).blend_seams(true).resize(&img).unwrap();
+    assert_eq!(flat.dimensions(), (8, 0));
+}
+
+#[test]
+fn compacting_does_not_change_the_result() {
+    let img = GrayImage::from_fn(30, 20, |x, y| Luma([((x * x + 3 * y) % 256) as u8]));
+    let importance = ImportanceMap::from_mask(&GrayImage::from_fn(30, 20, |x, _y| {
+        Luma([if x < 5 { 255 } else { 0 }])
+    }));
+    let carver = Carver::new().width(18).height(12).importance(&importance);
+    let expected = carver.resize(&img).unwrap();
+    assert_eq!(carver.clone().compact_every(4).resize(&img).unwrap(), expected);
+    let greedy = carver.axis_order(AxisOrder::Greedy);
+    assert_eq!(
+        greedy.clone().compact_every(3).resize(&img).unwrap(),
+        greedy.resize(&img).unwrap()
+    );
+}
+
+#[test]
+fn quantizing_energies_saves_memory() {
+    // Multiples of 4 have energies that are multiples of 16, which are not rounded
+    let img = GrayImage::from_fn(30, 20, |x, y| Luma([((x * x + 3 * y) % 64 * 4) as u8]));
+    for order in [AxisOrder::WidthFirst, AxisOrder::Greedy] {
+        let carver = Carver::new().width(18).height(12).axis_order(order);
+        let quantized = carver.clone().quantize_energy(true);
+        let (expected, report) = carver.resize_with_report(&img).unwrap();
+        let (resized, quantized_report) = quantized.resize_with_report(&img).unwrap();
+        assert_eq!(resized, expected);
+        assert_eq!(quantized_report.removed_energy, report.removed_energy);
+        assert!(quantized.plan(&img).peak_memory < carver.plan(&img).peak_memory);
+    }
+}
+
+#[test]
+fn carves_in_stripes_within_the_memory_limit() {
+    let img = GrayImage::from_fn(40, 30, |x, y| Luma([((x * 7 + y * y) % 97) as u8]));
+    let carver = Carver::new().width(30).height(24);
+    let peak_memory = carver.plan(&img).peak_memory;
+    let limited = |bytes| carver.clone().max_memory(bytes).resize_with_report(&img);
+    let (resized, report) = limited(peak_memory).unwrap();
+    assert_eq!(resized, carver.resize(&img).unwrap());
+    assert!(!report.striped);
+    // Quantized energies are enough
+    let quantized = carver.clone().quantize_energy(true);
+    let (resized, report) = limited(quantized.plan(&img).peak_memory).unwrap();
+    assert_eq!(resized, quantized.resize(&img).unwrap());
+    assert!(!report.striped);
+    let (resized, report) = limited(peak_memory / 4).unwrap();
+    assert_eq!(resized.dimensions(), (30, 24));
+    assert!(report.striped);
+    assert_eq!((report.seams, report.size), ((10, 6), (30, 24)));
+    assert_eq!(limited(100).unwrap_err(), SeamError::MemoryExceeded);
+}
+
+#[test]
+fn peeks_at_next_seams() {
+    let img = GrayImage::from_fn(12, 6, |x, y| Luma([((x * 37 + y * 11) % 200) as u8]));
+    let mut carvable = Carvable::new(&img);
+    carvable.remove_seam();
+    let peeked = carvable.peek_seams(3).unwrap();
+    assert_eq!(peeked.len(), 3);
+    // Disjoint seams
+    for y in 0..6 {
+        let mut xs: Vec<u32> = peeked.iter().map(|seam| seam.x_at(y).unwrap()).collect();
+        xs.sort();
+        xs.dedup();
+        assert_eq!(xs.len(), 3);
+    }
+    // Peeking does not change what is removed next
+    assert_eq!(carvable.try_remove_seam_returning().unwrap(), peeked[0]);
+    assert_eq!(carvable.result().width(), 10);
+    assert!(carvable.peek_seams(11).is_err());
+}
+
+#[test]
+fn profiles_the_removed_seams() {
+    let img = GrayImage::from_fn(12, 6, |x, y| Luma([((x * 37 + y * 11) % 200) as u8]));
+    let mut carvable = Carvable::new(&img);
+    let mut profiled = Carvable::new(&img);
+    for _ in 0..4 {
+        let seam = carvable.try_remove_seam_returning().unwrap();
+        let (same, profile) = profiled.try_remove_seam_with_profile().unwrap();
+        assert_eq!(same, seam);
+        assert_eq!(profile.len(), 6);
+    }
+    assert_eq!(profiled.result().width(), 8);
+}
+
+#[test]
+fn recarves_only_the_edited_region() {
+    let img = GrayImage::from_fn(30, 20, |x, y| Luma([((x * 37 + y * 11) % 200) as u8]));
+    let edited = Rect { x: 12, y: 4, width: 6, height: 5 };
+    let mut stamped = img.clone();
+    for (x, y, pixel) in stamped.enumerate_pixels_mut() {
+        if edited.contains(x, y) {
+            *pixel = Luma([0]);
+        }
+    }
+    for wrap in [false, true] {
+        let carve = |carvable: &mut Carvable<_>| {
+            let seams: Vec<_> = (0..8)
+                .map(|_| carvable.try_remove_seam_returning().unwrap())
+                .collect();
+            let carved: GrayImage = carvable.result().into();
+            (seams, carved)
+        };
+        let mut recarved = Carvable::new(&img);
+        recarved.set_wrapping(wrap);
+        recarved.seam_table();
+        recarved.replace_image(&stamped, edited);
+        let mut fresh = Carvable::new(&stamped);
+        fresh.set_wrapping(wrap);
+        // The same seams as when everything is computed from the edited image
+        assert_eq!(carve(&mut recarved), carve(&mut fresh));
+    }
+}
+
+#[test]
+fn marks_carved_out_images_dirty() {
+    let img = GrayImage::from_fn(3, 2, |x, y| Luma([(x * 50 + y * 20) as u8]));
+    for wrap in [false, true] {
+        let mut carvable = Carvable::new(&img);
+        carvable.set_wrapping(wrap);
+        for _ in 0..3 {
+            carvable.remove_seam();
+        }
+        // Without any column, the compacted positions have no row left either
+        carvable.shrink_to_fit();
+        carvable.mark_dirty(Rect { x: 0, y: 0, width: 3, height: 2 });
+        carvable.replace_image(&img, Rect { x: 1, y: 0, width: 1, height: 1 });
+        assert_eq!(carvable.result().dimensions(), (0, 2));
+    }
+    let empty = GrayImage::new(0, 0);
+    Carvable::new(&empty).mark_dirty(Rect { x: 0, y: 0, width: 1, height: 1 });
+}
+
+#[test]
+fn applies_the_seams_of_a_carvable() {
+    let img = ImageBuffer::from_fn(20, 8, |x, y| {
+        Rgb([(x * 13) as u8, (y * 29) as u8, ((x * y) % 256) as u8])
+    });
+    let mut carvable = Carvable::new(&img);
+    let seams: Vec<_> = (0..5)
+        .map(|_| carvable.try_remove_seam_returning().unwrap())
+        .collect();
+    let carved: ImageBuffer<Rgb<u8>, Vec<u8>> = carvable.result().into();
+    let mut applied = img.clone();
+    for seam in &seams {
+        apply_seam(&mut applied, seam.positions()).unwrap();
+    }
+    assert_eq!(applied, carved);
+}
+
+#[test]
+fn replays_seams_on_other_rasters() {
+    let img = ImageBuffer::from_fn(24, 16, |x, y| {
+        Rgb([x as u8, y as u8, ((x * 7 + y * 13) % 256) as u8])
+    });
+    let (resized, seams) = Carver::new()
+        .width(17)
+        .height(11)
+        .resize_with_seams(&img)
+        .unwrap();
+    let seams = seams.unwrap();
+    assert_eq!((seams.dimensions(), seams.len()), ((24, 16), 12));
+    // The pixels of this raster are their own coordinates
+    let coordinates = ImageBuffer::from_fn(24, 16, |x, y| LumaA([x as u16, y as u16]));
+    let replayed = seams.replay(&coordinates).unwrap();
+    assert_eq!(replayed.dimensions(), resized.dimensions());
+    for (x, y, pixel) in resized.enumerate_pixels() {
+        let LumaA([ox, oy]) = *replayed.get_pixel(x, y);
+        assert_eq!(pixel, img.get_pixel(ox.into(), oy.into()));
+    }
+    let mismatch = seams.replay(&GrayImage::new(24, 15));
+    assert_eq!(mismatch.unwrap_err(), SeamError::SizeMismatch);
+}
+
+#[test]
+fn maps_resized_pixels_to_their_origin() {
+    let img = ImageBuffer::from_fn(24, 16, |x, y| {
+        Rgb([x as u8, y as u8, ((x * 7 + y * 13) % 256) as u8])
+    });
+    let (resized, origins) = Carver::new()
+        .width(17)
+        .height(11)
+        .resize_with_provenance(&img)
+        .unwrap();
+    let origins = origins.unwrap();
+    assert_eq!((origins.width(), origins.height()), (17, 11));
+    for (x, y, pixel) in resized.enumerate_pixels() {
+        let Pos(ox, oy) = origins[Pos(x, y)];
+        assert_eq!(pixel, img.get_pixel(ox, oy));
+    }
+    // Without any seam cheap enough, the image is only scaled down
+    let carver = Carver::new().width(12).height(8).max_seam_energy(0);
+    let (scaled, origins) = carver.resize_with_provenance(&img).unwrap();
+    let origins = origins.unwrap();
+    assert_eq!(scaled.dimensions(), (12, 8));
+    assert_eq!(origins[Pos(0, 0)], Pos(1, 1));
+    assert_eq!(origins[Pos(11, 7)], Pos(23, 15));
+}
+
+#[test]
+fn carries_annotations_along() {
+    // A uniform column, the only one without any energy
+    let img = GrayImage::from_fn(9, 6, |x, y| match x {
+        3..=5 => Luma([100]),
+        _ => Luma([((x * 73 + y * 31) % 256) as u8]),
+    });
+    let rect = |x, y, width, height| Annotation::Rect(Rect { x, y, width, height });
+    let annotations = [
+        Annotation::Point(6, 2),
+        Annotation::Point(4, 2),
+        rect(3, 1, 3, 2),
+        rect(4, 0, 1, 6),
+    ];
+    let carver = Carver::new().width(8);
+    let (resized, carried) = carver.resize_with_annotations(&img, &annotations).unwrap();
+    assert_eq!(resized, carver.resize(&img).unwrap());
+    let expected = [Some(Annotation::Point(5, 2)), None, Some(rect(3, 1, 2, 2)), None];
+    assert_eq!(carried, expected);
+    // Annotations of images carved in stripes are scaled
+    let img = GrayImage::from_fn(40, 30, |x, y| Luma([((x * 7 + y * y) % 97) as u8]));
+    let carver = Carver::new().width(20).height(15);
+    let striped = carver.clone().max_memory(carver.plan(&img).peak_memory / 4);
+    let (_resized, carried) = striped.resize_with_annotations(&img, &annotations).unwrap();
+    assert_eq!(carried[0], Some(Annotation::Point(3, 1)));
+}
+
+#[test]
+fn measures_timings() {
+    let img = GrayImage::from_fn(30, 20, |x, y| Luma([((x * 13 + y * 7) % 256) as u8]));
+    let carver = Carver::new().width(20).height(15);
+    let (expected, report) = carver.resize_with_report(&img).unwrap();
+    assert_eq!(report.timings, None);
+    for order in [AxisOrder::WidthFirst, AxisOrder::Greedy] {
+        let timed = carver.clone().axis_order(order).measure_timings(true);
+        let (resized, report) = timed.resize_with_report(&img).unwrap();
+        assert_eq!(resized.dimensions(), expected.dimensions());
+        let timings = report.timings.unwrap();
+        assert!(timings.energy > Duration::ZERO);
+        assert!(timings.total() >= timings.energy + timings.removal);
+    }
+}
+
+#[test]
+fn borrows_images_that_already_fit() {
+    use std::borrow::Cow;
+    let img = pi_img_8_3();
+    let carver = Carver::new().width(Dimension::Percent(100));
+    assert!(matches!(carver.resize_cow(&img), Ok(Cow::Borrowed(_))));
+    let resized = carver.width(7).resize_cow(&img).unwrap();
+    assert!(matches!(resized, Cow::Owned(_)));
+    assert_eq!(resized.into_owned(), resize(&img, 7, 3));
+}
+
+#[test]
+fn carves_any_pixel_type_like_8_bit_images() {
+    let img = ImageBuffer::from_fn(20, 12, |x, y| {
+        Rgb([(x * x % 256) as u8, (y * 19) as u8, ((x + y) * 11) as u8])
+    });
+    let expected = resize(&img, 14, 9);
+    let deep = ImageBuffer::from_fn(20, 12, |x, y| {
+        Rgb(img.get_pixel(x, y).0.map(|c| u16::from(c) * 257))
+    });
+    let resized = resize(&deep, 14, 9);
+    assert!(resized
+        .pixels()
+        .zip(expected.pixels())
+        .all(|(a, b)| a.0 == b.0.map(|c| u16::from(c) * 257)));
+    let float = ImageBuffer::from_fn(20, 12, |x, y| {
+        let [r, g, b] = img.get_pixel(x, y).0.map(|c| f32::from(c) / 255.);
+        Rgba([r, g, b, 1.])
+    });
+    let resized = resize(&float, 14, 9);
+    assert!(resized.pixels().zip(expected.pixels()).all(|(a, b)| {
+        a.0[..3]
+            .iter()
+            .zip(b.0)
+            .all(|(&a, b)| (a * 255.).round() as u8 == b)
+    }));
+    let gray = ImageBuffer::from_fn(20, 12, |x, y| LumaA([(x * y % 256) as u8, 255]));
+    assert_eq!(resize(&gray, 14, 9).dimensions(), (14, 9));
+}
+
+#[test]
+fn keeps_borders_of_tiles() {
+    // Flat borders, that seams would go through first
+    let img = GrayImage::from_fn(20, 16, |x, y| match (x, y) {
+        (2..=17, 2..=13) => Luma([((x * 37 + y * 71) % 256) as u8]),
+        _ => Luma([(x + y) as u8]),
+    });
+    let column =
+        |img: &GrayImage, x: u32| (0..img.height()).map(|y| img[(x, y)]).collect::<Vec<_>>();
+    let row = |img: &GrayImage, y: u32| (0..img.width()).map(|x| img[(x, y)]).collect::<Vec<_>>();
+
+    let narrower = Carver::new()
+        .width(12)
+        .keep_borders(true)
+        .resize(&img)
+        .unwrap();
+    assert_eq!(narrower.dimensions(), (12, 16));
+    assert_eq!(column(&narrower, 0), column(&img, 0));
+    assert_eq!(column(&narrower, 11), column(&img, 19));
+    let unkept = Carver::new().width(12).resize(&img).unwrap();
+    assert_ne!(column(&unkept, 11), column(&img, 19));
+
+    let lower = Carver::new()
+        .height(10)
+        .keep_borders(true)
+        .resize(&img)
+        .unwrap();
+    assert_eq!(row(&lower, 0), row(&img, 0));
+    assert_eq!(row(&lower, 9), row(&img, 15));
+    let greedy = Carver::new()
+        .width(12)
+        .height(10)
+        .axis_order(AxisOrder::Greedy);
+    let both = greedy.keep_borders(true).resize(&img).unwrap();
+    assert_eq!(both.dimensions(), (12, 10));
+    assert!(Carver::new()
+        .width(1)
+        .keep_borders(true)
+        .resize(&img)
+        .is_err());
+}
+
+#[test]
+fn selects_algorithms() {
+    // Noise everywhere: every seam is expensive
+    let noise = GrayImage::from_fn(20, 10, |x, y| Luma([((x * 97 + y * 57) % 7 * 40) as u8]));
+    let carver = Carver::new().width(15).height(8);
+    for algorithm in [
+        Algorithm::Backward,
+        Algorithm::Forward,
+        Algorithm::Greedy,
+        Algorithm::Hybrid,
+    ] {
+        let resized = carver.clone().algorithm(algorithm).resize(&noise).unwrap();
+        assert_eq!(resized.dimensions(), (15, 8));
+    }
+    assert_eq!(
+        carver
+            .clone()
+            .algorithm(Algorithm::Backward)
+            .resize(&noise)
+            .unwrap(),
+        carver.resize(&noise).unwrap()
+    );
+    let greedy = carver.clone().axis_order(AxisOrder::Greedy);
+    assert_eq!(
+        carver
+            .clone()
+            .algorithm(Algorithm::Greedy)
+            .resize(&noise)
+            .unwrap(),
+        greedy.resize(&noise).unwrap()
+    );
+    let hybrid = carver.clone().algorithm(Algorithm::Hybrid);
+    assert!(hybrid.plan(&noise).may_scale && !carver.plan(&noise).may_scale);
+    let (_, report) = hybrid.resize_with_report(&noise).unwrap();
+    assert_eq!((report.seams, report.scaled), ((0, 0), (5, 2)));
+
+    // Removing a pixel from a horizontal ramp joins pixels of different values,
+    // removing one from a vertical ramp does not, although its gradient is steeper
+    let ramps = GrayImage::from_fn(12, 5, |x, y| match x {
+        0..=5 => Luma([(100 + y * 24) as u8]),
+        _ => Luma([((x - 6) * 30) as u8]),
+    });
+    let narrower = |algorithm| {
+        let carver = Carver::new().width(11).algorithm(algorithm);
+        carver.resize(&ramps).unwrap()
+    };
+    assert_ne!(narrower(Algorithm::Forward), narrower(Algorithm::Backward));
+}
+
+#[test]
+fn retargets_both_axes_at_once() {
+    let img = GrayImage::from_fn(20, 12, |x, y| Luma([((x * 13 + y * 29) % 64 * 4) as u8]));
+    assert_eq!(retarget(&img, 14, 9).dimensions(), (14, 9));
+    let greedy = Carver::new()
+        .width(14)
+        .height(9)
+        .axis_order(AxisOrder::Greedy);
+    assert_eq!(retarget(&img, 14, 9), greedy.resize(&img).unwrap());
+    // Narrower and taller
+    let tall = retarget(&img, 15, 16);
+    assert_eq!(tall, enlarge(&resize(&img, 15, 12), 15, 16));
+    assert_eq!(retarget(&img, 25, 14).dimensions(), (25, 14));
+    assert_eq!(retarget(&img, 20, 12), img);
+}
+
+#[test]
+fn resumes_interrupted_resizes() {
+    let img = GrayImage::from_fn(24, 16, |x, y| Luma([((x * 37 + y * 11) % 97 * 2) as u8]));
+    let path = std::env::temp_dir().join(format!("seamcarving-{}.state", std::process::id()));
+    let checkpoint = Checkpoint::new(&path).every(1);
+    let carver = Carver::new().width(16).height(12);
+    let (expected, _report) = carver.resize_with_report(&img).unwrap();
+    let (resized, report) = carver.resize_checkpointed(&img, &checkpoint, false).unwrap();
+    assert_eq!(resized, expected);
+    // Interrupted in the middle of the horizontal seams, while writing a seam
+    let bytes = std::fs::read(&path).unwrap();
+    std::fs::write(&path, &bytes[..bytes.len() * 3 / 4]).unwrap();
+    let (resumed, resumed_report) = carver.resize_checkpointed(&img, &checkpoint, true).unwrap();
+    assert_eq!(resumed, expected);
+    assert_eq!(resumed_report.seams, report.seams);
+    assert!(std::fs::read(&path).unwrap() == bytes);
+    // Other settings start again
+    let other = Carver::new().width(20);
+    let (narrower, _report) = other.resize_checkpointed(&img, &checkpoint, true).unwrap();
+    assert_eq!(narrower, other.resize(&img).unwrap());
+    let pinned = Carver::new().width(20).pin_columns([3]);
+    let unsupported = pinned.resize_checkpointed(&img, &checkpoint, true);
+    assert!(matches!(unsupported, Err(CheckpointError::Unsupported { .. })));
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn snapshots_carvings_on_other_threads() {
+    let img = ImageBuffer::from_fn(20, 12, |x, y| Rgb([(x * 12) as u8, (y * y) as u8, 40]));
+    let carver = Carver::new().width(16).height(9);
+    let (sender, receiver) = std::sync::mpsc::channel();
+    let (resized, frames) = std::thread::scope(|scope| {
+        let frames = scope.spawn(move || receiver.iter().collect::<Vec<_>>());
+        let resized = carver.resize_with_snapshots(&img, 3, |frame| sender.send(frame).unwrap());
+        drop(sender);
+        (resized.unwrap().0, frames.join().unwrap())
+    });
+    let seams: Vec<u32> = frames.iter().map(|frame| frame.seams).collect();
+    assert_eq!(seams, vec![3, 6, 7]);
+    assert_eq!(frames[0].image.dimensions(), (17, 12));
+    assert_eq!(frames[1].image.dimensions(), (16, 10));
+    let last = carver.clone().height(10).resize(&img).unwrap();
+    assert_eq!(frames[1].image, last);
+    assert_eq!(frames[2].image, resized);
+}
+
+#[test]
+fn carves_decoded_grayscale_images_on_one_channel() {
+    use image::DynamicImage;
+    let depth = ImageBuffer::from_fn(24, 16, |x, y| Luma([(x * x * 90 + y * 300) as u16]));
+    let carver = Carver::new().width(18).height(13);
+    let (resized, report) = carver.resize_dynamic(&DynamicImage::ImageLuma16(depth.clone()))
+        .unwrap();
+    assert_eq!(report.seams, (6, 3));
+    assert_eq!(resized.as_luma16(), Some(&carver.resize(&depth).unwrap()));
+    // Color images are carved in RGBA
+    let photo = ImageBuffer::from_fn(24, 16, |x, y| Rgb([(x * 10) as u8, (y * y) as u8, 60]));
+    let photo = DynamicImage::ImageRgb8(photo);
+    let (resized, _report) = carver.resize_dynamic(&photo).unwrap();
+    assert_eq!(resized.as_rgba8(), Some(&carver.resize(&photo).unwrap()));
+}
+
+#[test]
+fn carves_until_the_image_is_too_dissimilar() {
+    // Smooth waves, which scaling back the carved image restores better the fewer seams it lost
+    let img = GrayImage::from_fn(48, 16, |x, y| {
+        Luma([(128. + 100. * (x as f32 / 5.).sin() * (y as f32 / 4.).cos()) as u8])
+    });
+    let widths: Vec<u32> = [0.3, 0.6, 0.9]
+        .iter()
+        .map(|&ssim| {
+            let carver = Carver::new().width(8).min_similarity(ssim, 4);
+            let (resized, report) = carver.resize_with_report(&img).unwrap();
+            assert!(!report.stopped);
+            if report.too_dissimilar {
+                // The seams are the ones of a resize without the threshold
+                let expected = Carver::new().width(resized.width()).resize(&img).unwrap();
+                assert_eq!(resized, expected);
+            }
+            resized.width()
+        })
+        .collect();
+    // Higher thresholds stop earlier, after a multiple of 4 seams
+    assert!(widths.windows(2).all(|pair| pair[0] < pair[1]));
+    assert!(widths.iter().all(|width| width % 4 == 0));
+    // The callback still stops carving first
+    let carver = Carver::new().width(8).min_similarity(0., 4);
+    let (resized, report) = carver
+        .resize_with_callback(&img, |_seam, stats| {
+            if stats.removed.0 < 3 {
+                ControlFlow::Continue(())
+            } else {
+                ControlFlow::Break(())
+            }
+        })
+        .unwrap();
+    assert_eq!(resized.width(), 45);
+    assert!(report.stopped && !report.too_dissimilar);
+}
+
+#[test]
+fn measures_the_similarity_of_empty_images() {
+    let img = GrayImage::from_fn(12, 6, |x, y| Luma([(x * 20 + y) as u8]));
+    let carver = Carver::new().width(0).min_similarity(-1., 1);
+    let (resized, report) = carver.resize_with_report(&img).unwrap();
+    assert_eq!(resized.dimensions(), (0, 6));
+    assert!(!report.too_dissimilar);
+    // Nothing left is less similar than any threshold
+    let (_, report) = carver.min_similarity(-0.99, 12).resize_with_report(&img).unwrap();
+    assert!(report.too_dissimilar);
+}
@@ -0,0 +1,120 @@
+// <seam-resize src="photo.png" map="photo.seams"></seam-resize>
+//
+// Shows an image that can be made narrower by dragging its bottom right corner,
+// rendered at each width from its seam index map, written by `seamcarve --seam-map`.
+// The module defaults to seamcarving_web.wasm, next to the page, and can be changed
+// with the `wasm` attribute.
+
+const modules = new Map();
+
+/** Instantiates each WebAssembly module once, whatever the number of elements using it */
+function instantiate(url) {
+  if (!modules.has(url)) {
+    const instance = WebAssembly.instantiateStreaming(fetch(url), {})
+      .then((result) => result.instance.exports);
+    modules.set(url, instance);
+  }
+  return modules.get(url);
+}
+
+/** The RGBA pixels of the image at the given url, row by row */
+async function loadPixels(url) {
+  const image = new Image();
+  image.crossOrigin = "anonymous";
+  image.src = url;
+  await image.decode();
+  const canvas = document.createElement("canvas");
+  canvas.width = image.naturalWidth;
+  canvas.height = image.naturalHeight;
+  const context = canvas.getContext("2d");
+  context.drawImage(image, 0, 0);
+  return context.getImageData(0, 0, canvas.width, canvas.height);
+}
+
+async function loadBytes(url) {
+  const response = await fetch(url);
+  if (!response.ok) {
+    throw new Error(`cannot load ${url}: ${response.status}`);
+  }
+  return new Uint8Array(await response.arrayBuffer());
+}
+
+class SeamResize extends HTMLElement {
+  async connectedCallback() {
+    const wasm = this.getAttribute("wasm") || "seamcarving_web.wasm";
+    const [exports, image, map] = await Promise.all([
+      instantiate(wasm),
+      loadPixels(this.getAttribute("src")),
+      loadBytes(this.getAttribute("map")),
+    ]);
+    this.exports = exports;
+    this.widget = this.createWidget(image.data, map);
+    if (!this.widget) {
+      throw new Error("the seam index map does not match the image");
+    }
+    this.height = image.height;
+
+    const shadow = this.shadowRoot || this.attachShadow({ mode: "open" });
+    const minWidth = exports.widget_min_width(this.widget);
+    const style = document.createElement("style");
+    style.textContent = `
+      :host {
+        display: inline-block;
+        overflow: hidden;
+        resize: horizontal;
+        width: ${image.width}px;
+        min-width: ${minWidth}px;
+        max-width: ${image.width}px;
+      }
+      canvas { display: block; }
+    `;
+    this.canvas = document.createElement("canvas");
+    shadow.replaceChildren(style, this.canvas);
+    this.observer = new ResizeObserver(([entry]) => this.render(entry.contentRect.width));
+    this.observer.observe(this);
+  }
+
+  disconnectedCallback() {
+    if (this.observer) {
+      this.observer.disconnect();
+    }
+    if (this.widget) {
+      this.exports.widget_free(this.widget);
+      this.widget = 0;
+    }
+  }
+
+  /** Copies the pixels and the map into the memory of the module, and returns the widget */
+  createWidget(pixels, map) {
+    const exports = this.exports;
+    const copy = (bytes) => {
+      const pointer = exports.buffer_new(bytes.length);
+      new Uint8Array(exports.memory.buffer, pointer, bytes.length).set(bytes);
+      return pointer;
+    };
+    const pixelsPointer = copy(pixels);
+    const mapPointer = copy(map);
+    const widget = exports.widget_new(pixelsPointer, pixels.length, mapPointer, map.length);
+    exports.buffer_free(pixelsPointer, pixels.length);
+    exports.buffer_free(mapPointer, map.length);
+    return widget;
+  }
+
+  render(width) {
+    if (!this.widget) {
+      return;
+    }
+    const exports = this.exports;
+    const rendered = exports.widget_render(this.widget, Math.round(width));
+    const length = rendered * this.height * 4;
+    // The memory may have grown, and moved, since the last render
+    const memory = new Uint8ClampedArray(exports.memory.buffer);
+    const pointer = exports.widget_pixels(this.widget);
+    const pixels = memory.subarray(pointer, pointer + length);
+    this.canvas.width = rendered;
+    this.canvas.height = this.height;
+    this.canvas.getContext("2d").putImageData(new ImageData(pixels, rendered, this.height), 0, 0);
+  }
+}
+
+customElements.define("seam-resize", SeamResize);
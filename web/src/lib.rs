@@ -0,0 +1,153 @@
+//! A WebAssembly module rendering an image at any width from its [SeamIndexMap],
+//! used by the `<seam-resize>` element of `seam-resize.js`, see README.md.
+//!
+//! The module has no imports, and its exports only take and return numbers:
+//! JavaScript copies the pixels and the map into buffers allocated with [buffer_new],
+//! creates a widget from them with [widget_new], then calls [widget_render]
+//! and reads the rendered pixels from the memory of the module at [widget_pixels].
+use seamcarving::SeamIndexMap;
+
+/// An image and its seam index map
+pub struct Widget {
+    pixels: Vec<[u8; 4]>,
+    map: SeamIndexMap,
+    rendered: Vec<[u8; 4]>,
+}
+
+impl Widget {
+    /// Fails if the map is invalid, or does not have one index per RGBA pixel
+    fn new(rgba: &[u8], map: &[u8]) -> Option<Widget> {
+        let map = SeamIndexMap::from_bytes(map)?;
+        let pixels: Vec<[u8; 4]> = rgba
+            .chunks_exact(4)
+            .map(|p| [p[0], p[1], p[2], p[3]])
+            .collect();
+        let (width, height) = map.dimensions();
+        if !rgba.len().is_multiple_of(4)
+            || pixels.len() as u64 != u64::from(width) * u64::from(height)
+        {
+            return None;
+        }
+        let mut widget = Widget {
+            pixels,
+            map,
+            rendered: Vec::new(),
+        };
+        widget.render(width);
+        Some(widget)
+    }
+
+    /// Renders the image at the given width, clamped to the widths the map allows,
+    /// and returns the rendered width
+    fn render(&mut self, width: u32) -> u32 {
+        self.map
+            .render_into(&self.pixels, width, &mut self.rendered)
+            .expect("the number of pixels was checked")
+    }
+}
+
+/// Allocates a buffer of `len` bytes, for JavaScript to write into
+#[no_mangle]
+pub extern "C" fn buffer_new(len: usize) -> *mut u8 {
+    Box::into_raw(vec![0u8; len].into_boxed_slice()).cast()
+}
+
+/// Frees a buffer allocated by [buffer_new]
+///
+/// # Safety
+///
+/// `ptr` and `len` must be the address and the length of a buffer returned by [buffer_new],
+/// which must not be used anymore.
+#[no_mangle]
+pub unsafe extern "C" fn buffer_free(ptr: *mut u8, len: usize) {
+    drop(Box::from_raw(std::ptr::slice_from_raw_parts_mut(ptr, len)));
+}
+
+/// Creates a widget from RGBA pixels, row by row, and from the bytes of their seam index map,
+/// which are copied. Returns null if they do not match.
+///
+/// # Safety
+///
+/// Both buffers must be readable for their given length, such as buffers of [buffer_new].
+#[no_mangle]
+pub unsafe extern "C" fn widget_new(
+    rgba: *const u8,
+    rgba_len: usize,
+    map: *const u8,
+    map_len: usize,
+) -> *mut Widget {
+    let rgba = std::slice::from_raw_parts(rgba, rgba_len);
+    let map = std::slice::from_raw_parts(map, map_len);
+    match Widget::new(rgba, map) {
+        Some(widget) => Box::into_raw(Box::new(widget)),
+        None => std::ptr::null_mut(),
+    }
+}
+
+/// Renders the image of the widget at the given width, clamped between
+/// [widget_min_width] and the width of the image, and returns the rendered width.
+/// The rendered pixels are at [widget_pixels], until the next render.
+///
+/// # Safety
+///
+/// `widget` must have been returned by [widget_new], and not freed.
+#[no_mangle]
+pub unsafe extern "C" fn widget_render(widget: *mut Widget, width: u32) -> u32 {
+    (*widget).render(width)
+}
+
+/// The address of the RGBA pixels rendered last, row by row
+///
+/// # Safety
+///
+/// `widget` must have been returned by [widget_new], and not freed.
+#[no_mangle]
+pub unsafe extern "C" fn widget_pixels(widget: *const Widget) -> *const u8 {
+    (*widget).rendered.as_ptr().cast()
+}
+
+/// The narrowest width the image of the widget can be rendered at
+///
+/// # Safety
+///
+/// `widget` must have been returned by [widget_new], and not freed.
+#[no_mangle]
+pub unsafe extern "C" fn widget_min_width(widget: *const Widget) -> u32 {
+    (*widget).map.min_width()
+}
+
+/// Frees a widget and the pixels it rendered
+///
+/// # Safety
+///
+/// `widget` must have been returned by [widget_new], and must not be used anymore.
+#[no_mangle]
+pub unsafe extern "C" fn widget_free(widget: *mut Widget) {
+    drop(Box::from_raw(widget));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn renders_through_the_exports() {
+        // A 3x1 image from which the middle pixel is removed first
+        let mut map = b"SIM1".to_vec();
+        for value in [3u32, 1, 1, u32::MAX, 0, u32::MAX] {
+            map.extend_from_slice(&value.to_le_bytes());
+        }
+        let rgba: Vec<u8> = (0..12).collect();
+        unsafe {
+            let widget = widget_new(rgba.as_ptr(), rgba.len(), map.as_ptr(), map.len());
+            assert!(!widget.is_null());
+            assert_eq!(widget_min_width(widget), 2);
+            assert_eq!(widget_render(widget, 0), 2);
+            let rendered = std::slice::from_raw_parts(widget_pixels(widget), 8);
+            assert_eq!(rendered, &[0, 1, 2, 3, 8, 9, 10, 11]);
+            widget_free(widget);
+            let invalid = widget_new(rgba.as_ptr(), 8, map.as_ptr(), map.len());
+            assert!(invalid.is_null());
+        }
+    }
+}